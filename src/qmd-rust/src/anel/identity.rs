@@ -0,0 +1,91 @@
+//! Agent identity verification.
+//!
+//! Callers identify themselves with `AGENT_IDENTITY_TOKEN` (stdio MCP) or the
+//! `X-Agent-Identity` header (HTTP). Tokens listed under `identity.tokens` map
+//! to a name recorded in audit logs. With `identity.enforce` set, a missing or
+//! unlisted token is rejected with `PermissionDenied`; otherwise unknown
+//! identities pass through unnamed.
+
+use super::{AnelError, AnelErrorCode, RecoveryHint};
+use crate::config::IdentityConfig;
+use std::collections::HashMap;
+
+/// Checks identity tokens against the configured set
+#[derive(Debug, Clone, Default)]
+pub struct IdentityVerifier {
+    enforce: bool,
+    /// token -> name
+    tokens: HashMap<String, String>,
+}
+
+impl IdentityVerifier {
+    pub fn new(config: &IdentityConfig) -> Self {
+        Self {
+            enforce: config.enforce,
+            tokens: config
+                .tokens
+                .iter()
+                .map(|t| (t.token.clone(), t.name.clone()))
+                .collect(),
+        }
+    }
+
+    /// Whether unknown identities are rejected
+    pub fn is_enforced(&self) -> bool {
+        self.enforce
+    }
+
+    /// Name of the identity behind `token`
+    ///
+    /// `Ok(None)` for an unknown or missing token when enforcement is off.
+    pub fn verify(&self, token: Option<&str>) -> Result<Option<String>, Box<AnelError>> {
+        let name = token.and_then(|t| self.tokens.get(t.trim())).cloned();
+        if name.is_none() && self.enforce {
+            let message = match token {
+                Some(_) => "Agent identity token is not recognized",
+                None => "An agent identity token is required",
+            };
+            return Err(Box::new(
+                AnelError::new(AnelErrorCode::PermissionDenied, "Unknown Identity", message).with_hint(RecoveryHint::new(
+                    "PROVIDE_IDENTITY",
+                    "Set AGENT_IDENTITY_TOKEN (or the X-Agent-Identity header) to a token listed in identity.tokens",
+                )),
+            ));
+        }
+        Ok(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::IdentityToken;
+
+    fn config(enforce: bool) -> IdentityConfig {
+        IdentityConfig {
+            enforce,
+            tokens: vec![IdentityToken {
+                name: "indexer".to_string(),
+                token: "tok-123".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_verify_enforced() {
+        let verifier = IdentityVerifier::new(&config(true));
+        assert_eq!(verifier.verify(Some("tok-123")).unwrap(), Some("indexer".to_string()));
+        let err = verifier.verify(Some("forged")).unwrap_err();
+        assert_eq!(err.error_code, AnelErrorCode::PermissionDenied);
+        assert_eq!(err.status, 403);
+        assert!(verifier.verify(None).is_err());
+    }
+
+    #[test]
+    fn test_verify_not_enforced_passes_unknown() {
+        let verifier = IdentityVerifier::new(&config(false));
+        assert_eq!(verifier.verify(Some("forged")).unwrap(), None);
+        assert_eq!(verifier.verify(None).unwrap(), None);
+        assert_eq!(verifier.verify(Some("tok-123")).unwrap(), Some("indexer".to_string()));
+    }
+}
@@ -9,9 +9,18 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+pub mod identity;
+
 /// ANEL protocol version
+///
+/// Stamped on every NDJSON record, result and error as `anel_version` so
+/// agents can detect output format changes between releases.
 pub const ANEL_VERSION: &str = "1.0";
 
+fn anel_version() -> String {
+    ANEL_VERSION.to_string()
+}
+
 /// Environment variable names
 pub mod env {
     /// Agent trace ID for request correlation
@@ -24,10 +33,16 @@ pub mod env {
     pub const DRY_RUN: &str = "AGENT_DRY_RUN";
     /// Emit spec mode
     pub const EMIT_SPEC: &str = "AGENT_EMIT_SPEC";
+    /// File the audit stream is appended to instead of stderr
+    pub const AUDIT_FILE: &str = "AGENT_AUDIT_FILE";
+    /// Size in bytes at which the audit file is rotated
+    pub const AUDIT_MAX_BYTES: &str = "AGENT_AUDIT_MAX_BYTES";
+    /// Bytes of tool arguments kept in each audit record
+    pub const AUDIT_ARGS_MAX_BYTES: &str = "AGENT_AUDIT_ARGS_MAX_BYTES";
 }
 
 /// Error severity levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Severity {
     /// Debug/trace level
@@ -37,22 +52,18 @@ pub enum Severity {
     /// Warning - operation may have issues
     Warning,
     /// Error - operation failed
+    #[default]
     Error,
     /// Critical - system-level failure
     Critical,
 }
 
-impl Default for Severity {
-    fn default() -> Self {
-        Self::Error
-    }
-}
-
 /// Error codes for ANEL operations
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum AnelErrorCode {
     // Generic errors
+    #[default]
     Unknown,
     InvalidInput,
     NotFound,
@@ -82,12 +93,6 @@ pub enum AnelErrorCode {
     EnvironmentError,
 }
 
-impl Default for AnelErrorCode {
-    fn default() -> Self {
-        Self::Unknown
-    }
-}
-
 /// Recovery hint for error resolution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecoveryHint {
@@ -119,6 +124,10 @@ impl RecoveryHint {
 /// Implements RFC 7807 Problem Details with ANEL extensions
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnelError {
+    /// ANEL protocol version the error was produced under
+    #[serde(default = "anel_version")]
+    pub anel_version: String,
+
     /// Error type identifier (ANEL error code)
     #[serde(rename = "error_code")]
     pub error_code: AnelErrorCode,
@@ -157,6 +166,7 @@ impl AnelError {
     ) -> Self {
         let status = error_code.to_status();
         Self {
+            anel_version: anel_version(),
             error_code,
             status,
             title: title.into(),
@@ -325,8 +335,14 @@ impl AnelSpec {
             "collection" => Some(Self::collection()),
             "embed" => Some(Self::embed()),
             "update" => Some(Self::update()),
+            "sync" => Some(Self::sync()),
             "status" => Some(Self::status()),
             "cleanup" => Some(Self::cleanup()),
+            "trash" => Some(Self::trash()),
+            "remove" => Some(Self::remove()),
+            "verify" => Some(Self::verify()),
+            "reindex" => Some(Self::reindex()),
+            "suggest" => Some(Self::suggest()),
             "agent" => Some(Self::agent()),
             "context" => Some(Self::context()),
             "mcp" => Some(Self::mcp()),
@@ -344,9 +360,26 @@ impl AnelSpec {
                 "properties": {
                     "query": {"type": "string"},
                     "limit": {"type": "integer", "default": 20},
+                    "offset": {"type": "integer", "default": 0},
                     "min_score": {"type": "number", "default": 0.0},
                     "collection": {"type": "string"},
-                    "all": {"type": "boolean", "default": false}
+                    "all": {"type": "boolean", "default": false},
+                    "really_all": {"type": "boolean", "default": false},
+                    "max_tokens": {"type": "integer"},
+                    "include_content": {"type": "string", "enum": ["full", "chunks", "snippet"]},
+                    "content_budget": {"type": "integer", "default": 65536},
+                    "max_snippets": {"type": "integer"},
+                    "max_output_bytes": {"type": "integer"},
+                    "max_output_tokens": {"type": "integer"},
+                    "dedupe_by_hash": {"type": "boolean", "default": false},
+                    "group_by": {"type": "string", "enum": ["collection", "dir"]},
+                    "sort_by": {"type": "string", "enum": ["score", "path", "modified"], "default": "score"},
+                    "reverse": {"type": "boolean", "default": false},
+                    "boost": {"type": "array", "items": {"type": "string", "pattern": "^.+:[0-9.]+$"}},
+                    "lang": {"type": "string", "enum": ["en", "zh", "mixed"]},
+                    "explain": {"type": "boolean", "default": false},
+                    "explain_terms": {"type": "boolean", "default": false},
+                    "json_schema": {"type": "boolean", "default": false}
                 },
                 "required": ["query"]
             }),
@@ -361,10 +394,32 @@ impl AnelSpec {
                                 "docid": {"type": "string"},
                                 "path": {"type": "string"},
                                 "score": {"type": "number"},
-                                "lines": {"type": "integer"}
+                                "lines": {"type": "integer"},
+                                "bytes": {"type": "integer"},
+                                "words": {"type": "integer"},
+                                "tokens": {"type": "integer"},
+                                "duplicates": {"type": "array", "items": {"type": "string"}, "description": "Paths of same-content results folded into this one"},
+                                "duplicate_count": {"type": "integer"},
+                                "match_line": {"type": "integer", "description": "1-based line of the best match, for get --around"}
                             }
                         }
                     },
+                    "$schema": {"type": "object", "description": "This schema, with --json-schema"},
+                    "language": {"type": "string", "enum": ["en", "zh", "mixed"]},
+                    "explain": {"type": "object"},
+                    "truncated": {"type": "boolean", "description": "Results or content were cut to fit --max-output-bytes"},
+                    "groups": {
+                        "type": "array",
+                        "description": "Results clustered with --group-by, ordered by best rank",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": {"type": "string"},
+                                "indices": {"type": "array", "items": {"type": "integer"}, "description": "Positions in results"}
+                            }
+                        }
+                    },
+                    "offset": {"type": "integer"},
                     "total": {"type": "integer"}
                 }
             }),
@@ -386,8 +441,21 @@ impl AnelSpec {
                 "properties": {
                     "query": {"type": "string"},
                     "limit": {"type": "integer", "default": 20},
+                    "offset": {"type": "integer", "default": 0},
                     "collection": {"type": "string"},
-                    "all": {"type": "boolean", "default": false}
+                    "all": {"type": "boolean", "default": false},
+                    "really_all": {"type": "boolean", "default": false},
+                    "max_tokens": {"type": "integer"},
+                    "include_content": {"type": "string", "enum": ["full", "chunks", "snippet"]},
+                    "content_budget": {"type": "integer", "default": 65536},
+                    "max_snippets": {"type": "integer"},
+                    "max_output_bytes": {"type": "integer"},
+                    "max_output_tokens": {"type": "integer"},
+                    "dedupe_by_hash": {"type": "boolean", "default": false},
+                    "group_by": {"type": "string", "enum": ["collection", "dir"]},
+                    "sort_by": {"type": "string", "enum": ["score", "path", "modified"], "default": "score"},
+                    "reverse": {"type": "boolean", "default": false},
+                    "timeout": {"type": "integer", "description": "Overall time budget in milliseconds"}
                 },
                 "required": ["query"]
             }),
@@ -402,10 +470,30 @@ impl AnelSpec {
                                 "docid": {"type": "string"},
                                 "path": {"type": "string"},
                                 "score": {"type": "number"},
-                                "lines": {"type": "integer"}
+                                "lines": {"type": "integer"},
+                                "bytes": {"type": "integer"},
+                                "words": {"type": "integer"},
+                                "tokens": {"type": "integer"},
+                                "duplicates": {"type": "array", "items": {"type": "string"}, "description": "Paths of same-content results folded into this one"},
+                                "duplicate_count": {"type": "integer"}
+                            }
+                        }
+                    },
+                    "partial": {"type": "boolean"},
+                    "truncated": {"type": "boolean", "description": "Results or content were cut to fit --max-output-bytes"},
+                    "skipped_stages": {"type": "array", "items": {"type": "string", "enum": ["expansion", "vector", "rerank"]}},
+                    "groups": {
+                        "type": "array",
+                        "description": "Results clustered with --group-by, ordered by best rank",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": {"type": "string"},
+                                "indices": {"type": "array", "items": {"type": "integer"}, "description": "Positions in results"}
                             }
                         }
                     },
+                    "offset": {"type": "integer"},
                     "total": {"type": "integer"}
                 }
             }),
@@ -428,8 +516,25 @@ impl AnelSpec {
                 "properties": {
                     "query": {"type": "string"},
                     "limit": {"type": "integer", "default": 20},
+                    "offset": {"type": "integer", "default": 0},
                     "collection": {"type": "string"},
-                    "all": {"type": "boolean", "default": false}
+                    "all": {"type": "boolean", "default": false},
+                    "really_all": {"type": "boolean", "default": false},
+                    "max_tokens": {"type": "integer"},
+                    "include_content": {"type": "string", "enum": ["full", "chunks", "snippet"]},
+                    "content_budget": {"type": "integer", "default": 65536},
+                    "max_snippets": {"type": "integer"},
+                    "max_output_bytes": {"type": "integer"},
+                    "max_output_tokens": {"type": "integer"},
+                    "dedupe_by_hash": {"type": "boolean", "default": false},
+                    "group_by": {"type": "string", "enum": ["collection", "dir"]},
+                    "sort_by": {"type": "string", "enum": ["score", "path", "modified"], "default": "score"},
+                    "reverse": {"type": "boolean", "default": false},
+                    "rerank_model": {"type": "string"},
+                    "lang": {"type": "string", "enum": ["en", "zh", "mixed"]},
+                    "explain": {"type": "boolean", "default": false},
+                    "timeout": {"type": "integer", "description": "Overall time budget in milliseconds"},
+                    "max_expansions": {"type": "integer", "minimum": 1, "default": 5}
                 },
                 "required": ["query"]
             }),
@@ -445,10 +550,55 @@ impl AnelSpec {
                                 "path": {"type": "string"},
                                 "score": {"type": "number"},
                                 "lines": {"type": "integer"},
+                                "bytes": {"type": "integer"},
+                                "words": {"type": "integer"},
+                                "tokens": {"type": "integer"},
+                                "duplicates": {"type": "array", "items": {"type": "string"}, "description": "Paths of same-content results folded into this one"},
+                                "duplicate_count": {"type": "integer"},
                                 "reranked": {"type": "boolean"}
                             }
                         }
                     },
+                    "language": {"type": "string", "enum": ["en", "zh", "mixed"]},
+                    "explain": {"type": "object"},
+                    "partial": {"type": "boolean"},
+                    "truncated": {"type": "boolean", "description": "Results or content were cut to fit --max-output-bytes"},
+                    "skipped_stages": {"type": "array", "items": {"type": "string", "enum": ["expansion", "vector", "rerank"]}},
+                    "degraded_stages": {
+                        "type": "array",
+                        "description": "Stages that failed; the results were gathered without them",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "stage": {"type": "string", "enum": ["vector", "rerank"]},
+                                "error": {"type": "string"}
+                            }
+                        }
+                    },
+                    "timings": {
+                        "type": "object",
+                        "description": "Milliseconds spent in each pipeline stage",
+                        "properties": {
+                            "expansion_ms": {"type": "number"},
+                            "bm25_ms": {"type": "number"},
+                            "vector_ms": {"type": "number"},
+                            "fusion_ms": {"type": "number"},
+                            "rerank_ms": {"type": "number"},
+                            "total_ms": {"type": "number"}
+                        }
+                    },
+                    "groups": {
+                        "type": "array",
+                        "description": "Results clustered with --group-by, ordered by best rank",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": {"type": "string"},
+                                "indices": {"type": "array", "items": {"type": "integer"}, "description": "Positions in results"}
+                            }
+                        }
+                    },
+                    "offset": {"type": "integer"},
                     "total": {"type": "integer"}
                 }
             }),
@@ -470,10 +620,12 @@ impl AnelSpec {
             input_schema: serde_json::json!({
                 "type": "object",
                 "properties": {
-                    "file": {"type": "string", "description": "File path with optional :line suffix"},
+                    "file": {"type": "string", "description": "File path or docid with optional :line suffix"},
                     "limit": {"type": "integer", "default": 50},
                     "from": {"type": "integer", "default": 0},
-                    "full": {"type": "boolean", "default": false}
+                    "full": {"type": "boolean", "default": false},
+                    "if_hash": {"type": "string", "description": "Hash from an earlier get; unchanged documents return only {unchanged, hash}"},
+                    "around": {"type": "boolean", "default": false, "description": "Center on :line or the last search's match_line"}
                 },
                 "required": ["file"]
             }),
@@ -482,7 +634,13 @@ impl AnelSpec {
                 "properties": {
                     "path": {"type": "string"},
                     "lines": {"type": "array", "items": {"type": "string"}},
-                    "total_lines": {"type": "integer"}
+                    "total_lines": {"type": "integer"},
+                    "match_line": {"type": ["integer", "null"]},
+                    "bytes": {"type": "integer"},
+                    "words": {"type": "integer"},
+                    "tokens": {"type": "integer"},
+                    "hash": {"type": "string"},
+                    "unchanged": {"type": "boolean"}
                 }
             }),
             error_codes: vec![
@@ -544,7 +702,10 @@ impl AnelSpec {
                     "path": {"type": "string"},
                     "mask": {"type": "string", "default": "**/*"},
                     "description": {"type": "string"},
-                    "new_name": {"type": "string"}
+                    "new_name": {"type": "string"},
+                    "purge": {"type": "boolean", "default": false},
+                    "yes": {"type": "boolean", "default": false},
+                    "with_stats": {"type": "boolean", "default": false}
                 }
             }),
             output_schema: serde_json::json!({
@@ -558,7 +719,16 @@ impl AnelSpec {
                                 "name": {"type": "string"},
                                 "path": {"type": "string"},
                                 "pattern": {"type": "string"},
-                                "description": {"type": "string"}
+                                "description": {"type": "string"},
+                                "stats": {
+                                    "type": "object",
+                                    "description": "Live index figures, with --with-stats",
+                                    "properties": {
+                                        "documents": {"type": "integer"},
+                                        "chunks": {"type": "integer"},
+                                        "index_bytes": {"type": "integer"}
+                                    }
+                                }
                             }
                         }
                     },
@@ -582,7 +752,8 @@ impl AnelSpec {
                 "type": "object",
                 "properties": {
                     "force": {"type": "boolean", "default": false},
-                    "collection": {"type": "string"}
+                    "collection": {"type": "string"},
+                    "dry_run": {"type": "boolean", "default": false}
                 }
             }),
             output_schema: serde_json::json!({
@@ -591,7 +762,26 @@ impl AnelSpec {
                     "collections_processed": {"type": "integer"},
                     "documents_embedded": {"type": "integer"},
                     "chunks_embedded": {"type": "integer"},
-                    "model": {"type": "string"}
+                    "model": {"type": "string"},
+                    "dry_run": {"type": "boolean"},
+                    "collections": {
+                        "type": "array",
+                        "description": "Per-collection estimates (dry run)",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "collection": {"type": "string"},
+                                "documents": {"type": "integer"},
+                                "chunks": {"type": "integer"},
+                                "tokens": {"type": "integer"},
+                                "cost_usd": {"type": ["number", "null"]},
+                                "chunks_per_second": {"type": ["number", "null"]},
+                                "rate_source": {"type": ["string", "null"], "enum": ["config", "last_run", null]},
+                                "seconds": {"type": ["number", "null"]}
+                            }
+                        }
+                    },
+                    "total": {"type": "object"}
                 }
             }),
             error_codes: vec![
@@ -612,7 +802,8 @@ impl AnelSpec {
                 "type": "object",
                 "properties": {
                     "pull": {"type": "boolean", "default": false},
-                    "collection": {"type": "string"}
+                    "collection": {"type": "string"},
+                    "strict": {"type": "boolean", "default": false}
                 }
             }),
             output_schema: serde_json::json!({
@@ -620,7 +811,21 @@ impl AnelSpec {
                 "properties": {
                     "collections_updated": {"type": "integer"},
                     "documents_indexed": {"type": "integer"},
-                    "documents_removed": {"type": "integer"}
+                    "documents_unchanged": {"type": "integer"},
+                    "documents_removed": {"type": "integer"},
+                    "errors": {
+                        "type": "array",
+                        "description": "Files skipped because they could not be read",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "collection": {"type": "string"},
+                                "path": {"type": "string"},
+                                "kind": {"type": "string"},
+                                "message": {"type": "string"}
+                            }
+                        }
+                    }
                 }
             }),
             error_codes: vec![
@@ -631,6 +836,32 @@ impl AnelSpec {
         }
     }
 
+    /// Get spec for sync command
+    pub fn sync() -> Self {
+        Self {
+            version: ANEL_VERSION.to_string(),
+            command: "sync".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {}
+            }),
+            output_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "documents_changed": {"type": "integer"},
+                    "documents_embedded": {"type": "integer"},
+                    "chunks_embedded": {"type": "integer"}
+                }
+            }),
+            error_codes: vec![
+                AnelErrorCode::IndexNotReady,
+                AnelErrorCode::StorageError,
+                AnelErrorCode::EmbeddingFailed,
+                AnelErrorCode::ModelNotFound,
+            ],
+        }
+    }
+
     /// Get spec for status command
     pub fn status() -> Self {
         Self {
@@ -640,7 +871,9 @@ impl AnelSpec {
                 "type": "object",
                 "properties": {
                     "verbose": {"type": "boolean", "default": false},
-                    "collection": {"type": "string"}
+                    "collection": {"type": "string"},
+                    "include_inactive": {"type": "boolean", "default": false},
+                    "report": {"type": "boolean", "default": false, "description": "Per-collection size report: disk usage, extensions, largest documents"}
                 }
             }),
             output_schema: serde_json::json!({
@@ -655,10 +888,15 @@ impl AnelSpec {
                                 "documents": {"type": "integer"},
                                 "chunks": {"type": "integer"},
                                 "embeddings": {"type": "integer"},
-                                "last_updated": {"type": "string"}
+                                "last_updated": {"type": "string"},
+                                "tokens": {"type": "integer"},
+                                "index_bytes": {"type": "integer"}
                             }
                         }
-                    }
+                    },
+                    "documents": {"type": "integer"},
+                    "tokens": {"type": "integer"},
+                    "index_bytes": {"type": "integer"}
                 }
             }),
             error_codes: vec![
@@ -678,7 +916,8 @@ impl AnelSpec {
                 "properties": {
                     "dry_run": {"type": "boolean", "default": false},
                     "older_than": {"type": "integer", "default": 30},
-                    "collection": {"type": "string"}
+                    "collection": {"type": "string"},
+                    "purge": {"type": "boolean", "default": false}
                 }
             }),
             output_schema: serde_json::json!({
@@ -695,6 +934,190 @@ impl AnelSpec {
         }
     }
 
+    /// Get spec for trash command
+    pub fn trash() -> Self {
+        Self {
+            version: ANEL_VERSION.to_string(),
+            command: "trash".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {"type": "string", "enum": ["list", "restore"]},
+                    "id": {"type": "string"}
+                },
+                "required": ["action"]
+            }),
+            output_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "operations": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "id": {"type": "string"},
+                                "operation": {"type": "string"},
+                                "created_at": {"type": "string"},
+                                "collections": {"type": "array", "items": {"type": "string"}},
+                                "documents": {"type": "integer"},
+                                "vectors": {"type": "integer"}
+                            }
+                        }
+                    },
+                    "restored": {"type": "integer"},
+                    "skipped": {"type": "array", "items": {"type": "string"}}
+                }
+            }),
+            error_codes: vec![
+                AnelErrorCode::NotFound,
+                AnelErrorCode::CollectionNotFound,
+                AnelErrorCode::StorageError,
+            ],
+        }
+    }
+
+    /// Get spec for rm command
+    pub fn remove() -> Self {
+        Self {
+            version: ANEL_VERSION.to_string(),
+            command: "remove".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "collection/path or qmd://collection/path"},
+                    "hard": {"type": "boolean", "default": false}
+                },
+                "required": ["path"]
+            }),
+            output_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "collection": {"type": "string"},
+                    "path": {"type": "string"},
+                    "removed": {"type": "boolean"},
+                    "hard": {"type": "boolean"}
+                }
+            }),
+            error_codes: vec![
+                AnelErrorCode::NotFound,
+                AnelErrorCode::CollectionNotFound,
+                AnelErrorCode::InvalidInput,
+                AnelErrorCode::StorageError,
+            ],
+        }
+    }
+
+    /// Get spec for verify command
+    pub fn verify() -> Self {
+        Self {
+            version: ANEL_VERSION.to_string(),
+            command: "verify".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "collection": {"type": "string"},
+                    "fix": {"type": "boolean", "default": false}
+                }
+            }),
+            output_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "consistent": {"type": "boolean"},
+                    "collections": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "collection": {"type": "string"},
+                                "missing_fts": {"type": "integer"},
+                                "stale_fts": {"type": "integer"},
+                                "fts_mismatch": {"type": "boolean"},
+                                "orphan_vectors": {"type": "integer"},
+                                "vocabulary_drift": {"type": "integer"},
+                                "wrong_sizes": {"type": "integer"},
+                                "fixed": {"type": "array", "items": {"type": "string"}}
+                            }
+                        }
+                    }
+                }
+            }),
+            error_codes: vec![
+                AnelErrorCode::CollectionNotFound,
+                AnelErrorCode::StorageError,
+            ],
+        }
+    }
+
+    /// Get spec for reindex command
+    pub fn reindex() -> Self {
+        Self {
+            version: ANEL_VERSION.to_string(),
+            command: "reindex".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "collection": {"type": "string"}
+                }
+            }),
+            output_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "collections": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "collection": {"type": "string"},
+                                "documents_indexed": {"type": "integer"},
+                                "vectors_cleared": {"type": "integer"}
+                            }
+                        }
+                    }
+                }
+            }),
+            error_codes: vec![
+                AnelErrorCode::CollectionNotFound,
+                AnelErrorCode::StorageError,
+            ],
+        }
+    }
+
+    /// Get spec for suggest command
+    pub fn suggest() -> Self {
+        Self {
+            version: ANEL_VERSION.to_string(),
+            command: "suggest".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "prefix": {"type": "string"},
+                    "limit": {"type": "integer", "default": 10}
+                },
+                "required": ["prefix"]
+            }),
+            output_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "prefix": {"type": "string"},
+                    "suggestions": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "term": {"type": "string"},
+                                "documents": {"type": "integer"}
+                            }
+                        }
+                    }
+                }
+            }),
+            error_codes: vec![
+                AnelErrorCode::ConfigError,
+                AnelErrorCode::StorageError,
+            ],
+        }
+    }
+
     /// Get spec for agent command
     pub fn agent() -> Self {
         Self {
@@ -733,7 +1156,8 @@ impl AnelSpec {
             input_schema: serde_json::json!({
                 "type": "object",
                 "properties": {
-                    "path": {"type": "string", "description": "Optional path: collection or collection/path. Supports qmd:// prefix"}
+                    "path": {"type": "string", "description": "Optional path: collection or collection/path. Supports qmd:// prefix"},
+                    "include_inactive": {"type": "boolean", "default": false}
                 }
             }),
             output_schema: serde_json::json!({
@@ -746,7 +1170,8 @@ impl AnelSpec {
                             "properties": {
                                 "name": {"type": "string"},
                                 "path": {"type": "string"},
-                                "file_count": {"type": "integer"}
+                                "file_count": {"type": "integer"},
+                                "tokens": {"type": "integer"}
                             }
                         }
                     },
@@ -758,6 +1183,8 @@ impl AnelSpec {
                                 "path": {"type": "string"},
                                 "title": {"type": "string"},
                                 "size": {"type": "integer"},
+                                "words": {"type": "integer"},
+                                "tokens": {"type": "integer"},
                                 "modified_at": {"type": "string"}
                             }
                         }
@@ -779,9 +1206,12 @@ impl AnelSpec {
             input_schema: serde_json::json!({
                 "type": "object",
                 "properties": {
-                    "action": {"type": "string", "enum": ["add", "list", "rm"]},
+                    "action": {"type": "string", "enum": ["add", "list", "check", "rm", "generate"]},
                     "path": {"type": "string"},
-                    "description": {"type": "string"}
+                    "description": {"type": "string"},
+                    "collection": {"type": "string", "description": "generate: collection to describe (default: all)"},
+                    "depth": {"type": "integer", "minimum": 1, "default": 1, "description": "generate: directory levels to describe"},
+                    "overwrite": {"type": "boolean", "default": false, "description": "generate: replace hand-written descriptions"}
                 },
                 "required": ["action"]
             }),
@@ -794,7 +1224,10 @@ impl AnelSpec {
                             "type": "object",
                             "properties": {
                                 "path": {"type": "string"},
-                                "description": {"type": "string"}
+                                "description": {"type": "string"},
+                                "collection": {"type": "string"},
+                                "documents": {"type": "integer"},
+                                "method": {"type": "string", "enum": ["llm", "rules", "skipped"]}
                             }
                         }
                     },
@@ -839,6 +1272,9 @@ impl AnelSpec {
 /// NDJSON output wrapper for streaming
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NdjsonRecord<T: Serialize> {
+    /// ANEL protocol version of the envelope
+    #[serde(default = "anel_version")]
+    pub anel_version: String,
     /// Record type: "result", "error", "spec", "metadata"
     #[serde(rename = "type")]
     pub record_type: String,
@@ -852,6 +1288,7 @@ impl<T: Serialize> NdjsonRecord<T> {
     /// Create a new NDJSON record
     pub fn new(record_type: impl Into<String>, seq: u64, payload: T) -> Self {
         Self {
+            anel_version: anel_version(),
             record_type: record_type.into(),
             seq,
             payload,
@@ -872,6 +1309,9 @@ impl<T: Serialize> NdjsonRecord<T> {
 /// ANEL command result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnelResult {
+    /// ANEL protocol version of the result
+    #[serde(default = "anel_version")]
+    pub anel_version: String,
     /// Success flag
     pub success: bool,
     /// Result data
@@ -887,6 +1327,7 @@ impl AnelResult {
     /// Create a success result
     pub fn success(data: serde_json::Value) -> Self {
         Self {
+            anel_version: anel_version(),
             success: true,
             data,
             error: None,
@@ -898,6 +1339,7 @@ impl AnelResult {
     pub fn error(error: AnelError) -> Self {
         let trace_id = error.trace_id.clone();
         Self {
+            anel_version: anel_version(),
             success: false,
             data: serde_json::Value::Null,
             error: Some(error),
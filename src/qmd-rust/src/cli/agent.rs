@@ -64,7 +64,7 @@ pub fn classify_intent(query: &str) -> QueryIntent {
 
     // Starts with question word → semantic
     let first_word = lower.split_whitespace().next().unwrap_or("");
-    let is_question = QUESTION_WORDS.iter().any(|q| first_word == *q);
+    let is_question = QUESTION_WORDS.contains(&first_word);
 
     // Ends with question mark → semantic
     let ends_with_question = trimmed.ends_with('?');
@@ -94,9 +94,7 @@ pub fn classify_intent(query: &str) -> QueryIntent {
 fn default_options() -> SearchOptions {
     SearchOptions {
         limit: 10,
-        min_score: 0.0,
-        collection: None,
-        search_all: false,
+        ..Default::default()
     }
 }
 
@@ -115,23 +113,7 @@ fn execute_search(
             store.bm25_search(query, options)
         }
         QueryIntent::Semantic => {
-            rt.block_on(async {
-                let embedding_result = llm.embed(&[query]).await?;
-                let collections = resolve_collections(store, &options)?;
-                let mut all_results = Vec::new();
-                for collection in collections {
-                    let conn = store.get_connection(&collection)?;
-                    let results = vector_search_in_db(
-                        &conn,
-                        &embedding_result.embeddings[0],
-                        options.limit,
-                    )?;
-                    all_results.extend(results);
-                }
-                all_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-                all_results.truncate(options.limit);
-                Ok(all_results)
-            })
+            rt.block_on(store.vector_search_with_embedder_async(query, options, llm))
         }
         QueryIntent::Complex => {
             rt.block_on(async {
@@ -141,88 +123,6 @@ fn execute_search(
     }
 }
 
-/// Resolve which collections to search
-fn resolve_collections(store: &Store, options: &SearchOptions) -> Result<Vec<String>> {
-    if let Some(ref col) = options.collection {
-        Ok(vec![col.clone()])
-    } else if options.search_all {
-        Ok(store.get_collections().iter().map(|c| c.name.clone()).collect())
-    } else {
-        Ok(vec![store
-            .get_collections()
-            .first()
-            .ok_or_else(|| anyhow::anyhow!("No collections configured"))?
-            .name
-            .clone()])
-    }
-}
-
-#[cfg(feature = "sqlite-vec")]
-fn vector_search_in_db(
-    conn: &rusqlite::Connection,
-    query_vector: &[f32],
-    limit: usize,
-) -> Result<Vec<crate::store::SearchResult>> {
-    use crate::store::SearchResult;
-
-    let query_vec_json = serde_json::to_string(query_vector)?;
-
-    let mut stmt = conn.prepare(
-        "SELECT
-            cv.hash,
-            d.path,
-            d.title,
-            d.collection,
-            MIN(vec_distance_cosine(v.embedding, ?)) as distance
-         FROM content_vectors cv
-         JOIN vectors_vec v ON v.hash_seq = cv.hash || '_' || cv.seq
-         JOIN documents d ON d.hash = cv.hash
-         WHERE d.active = 1
-         GROUP BY cv.hash
-         ORDER BY distance ASC
-         LIMIT ?"
-    )?;
-
-    let rows: Vec<(String, String, String, String, f64)> = stmt
-        .query_map(rusqlite::params![query_vec_json, limit as i64], |row| {
-            Ok((
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-                row.get(3)?,
-                row.get(4)?,
-            ))
-        })?
-        .filter_map(|r| r.ok())
-        .collect();
-
-    let mut results = Vec::new();
-    for (hash, path, title, collection, distance) in rows {
-        let docid = crate::store::make_docid(&collection, &path);
-        results.push(SearchResult {
-            docid,
-            path,
-            collection,
-            score: (1.0 - distance as f32).max(0.0),
-            lines: 0,
-            title,
-            hash,
-            query: None,
-        });
-    }
-
-    Ok(results)
-}
-
-#[cfg(not(feature = "sqlite-vec"))]
-fn vector_search_in_db(
-    _conn: &rusqlite::Connection,
-    _query_vector: &[f32],
-    _limit: usize,
-) -> Result<Vec<crate::store::SearchResult>> {
-    anyhow::bail!("sqlite-vec feature not enabled")
-}
-
 /// Handle agent command - autonomous search mode
 pub fn handle(cmd: &AgentArgs, store: &Store, llm: &Router) -> Result<()> {
     // Handle --emit-spec: output ANEL specification and exit
@@ -15,16 +15,20 @@ pub fn handle(
         return Ok(());
     }
 
+    compress_existing(cmd, store)?;
+
     let stale_files = store.find_stale_entries(cmd.older_than)?;
 
     if stale_files.is_empty() {
         println!("No stale entries found");
-        return Ok(());
-    }
-
-    println!("Found {} stale entries:", stale_files.len());
-    for file in &stale_files {
-        println!("  {}", file);
+        if !cmd.purge {
+            return Ok(());
+        }
+    } else {
+        println!("Found {} stale entries:", stale_files.len());
+        for file in &stale_files {
+            println!("  {}", file);
+        }
     }
 
     if cmd.dry_run {
@@ -32,10 +36,41 @@ pub fn handle(
         return Ok(());
     }
 
-    println!("\nRemoving stale entries...");
-    store.remove_stale_entries(&stale_files)?;
+    if !stale_files.is_empty() {
+        println!("\nRemoving stale entries...");
+        store.remove_stale_entries(&stale_files)?;
+    }
+
+    if cmd.purge {
+        match store.purge_inactive(cmd.collection.as_deref())? {
+            Some(manifest) => {
+                println!(
+                    "Purged {} soft-deleted documents ({} vectors)",
+                    manifest.documents, manifest.vectors
+                );
+                println!("  Undo with: qmd trash restore {}", manifest.id);
+            }
+            None => println!("No soft-deleted documents to purge"),
+        }
+    }
 
     println!("Cleanup completed");
 
     Ok(())
 }
+
+/// Compress the plain bodies left in collections that now set `compress_content`
+fn compress_existing(cmd: &CleanupArgs, store: &Store) -> Result<()> {
+    let collections: Vec<String> = match &cmd.collection {
+        Some(name) => vec![store.resolve_collection(name)?.to_string()],
+        None => store.get_collections().iter().map(|c| c.name.clone()).collect(),
+    };
+    for name in collections {
+        let count = store.compress_existing_content(&name, cmd.dry_run)?;
+        if count > 0 {
+            let verb = if cmd.dry_run { "Would compress" } else { "Compressed" };
+            println!("{} {} documents in {}", verb, count, name);
+        }
+    }
+    Ok(())
+}
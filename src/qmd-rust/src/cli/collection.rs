@@ -1,8 +1,13 @@
 use crate::anel::AnelSpec;
-use crate::cli::{CollectionArgs, CollectionCommands, CollectionAddArgs, CollectionRemoveArgs, CollectionRenameArgs};
-use crate::config::{Config, CollectionConfig};
+use crate::cli::ls::format_bytes;
+use crate::cli::{CollectionArgs, CollectionCommands, CollectionAddArgs, CollectionListArgs, CollectionRemoveArgs, CollectionRenameArgs};
+use crate::config::{Config, CollectionConfig, Stopwords};
+use crate::store::Store;
 use anyhow::Result;
-use std::path::PathBuf;
+use dialoguer::Confirm;
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 
 /// Handle collection commands
 pub fn handle(
@@ -27,13 +32,17 @@ pub fn handle(
                 println!("  name: {:?}", args.name);
                 println!("  mask: {}", args.mask);
                 println!("  description: {:?}", args.description);
+                println!("  code: {}", args.code);
+                println!("  stopwords: {:?}", args.stopwords);
             }
-            CollectionCommands::List => {
+            CollectionCommands::List(args) => {
                 println!("  action: list");
+                println!("  with_stats: {}", args.with_stats);
             }
             CollectionCommands::Remove(args) => {
                 println!("  action: remove");
                 println!("  name: {}", args.name);
+                println!("  purge: {}", args.purge);
             }
             CollectionCommands::Rename(args) => {
                 println!("  action: rename");
@@ -46,7 +55,7 @@ pub fn handle(
 
     match &cmd.command {
         CollectionCommands::Add(args) => add_collection(args, config),
-        CollectionCommands::List => list_collections(config),
+        CollectionCommands::List(args) => list_collections(args, config, &cmd.format),
         CollectionCommands::Remove(args) => remove_collection(args, config),
         CollectionCommands::Rename(args) => rename_collection(args, config),
     }
@@ -62,26 +71,30 @@ fn add_collection(args: &CollectionAddArgs, config: &mut Config) -> Result<()> {
             .to_string()
     });
 
-    let path = shellexpand::tilde(&args.path).parse::<PathBuf>()?;
+    let path = crate::paths::expand(&args.path)?;
 
     if path.exists() && !path.is_dir() {
         anyhow::bail!("Path exists but is not a directory: {}", args.path);
     }
 
-    // Check for duplicate name
-    if config.collections.iter().any(|c| c.name == name) {
-        anyhow::bail!("Collection '{}' already exists", name);
-    }
-
     let collection = CollectionConfig {
         name: name.clone(),
         path: path.clone(),
         pattern: Some(args.mask.clone()),
         description: args.description.clone(),
+        code: args.code,
+        stopwords: args.stopwords.clone(),
+        ..Default::default()
     };
 
-    config.collections.push(collection);
-    config.save()?;
+    // Re-read and write under the config lock so concurrent edits are not lost
+    *config = Config::update(|latest| {
+        if latest.collections.iter().any(|c| c.name == name) {
+            anyhow::bail!("Collection '{}' already exists", name);
+        }
+        latest.collections.push(collection);
+        Ok(())
+    })?;
 
     // Create cache directory for the collection
     let cache_dir = config.cache_dir_for(&name);
@@ -93,88 +106,238 @@ fn add_collection(args: &CollectionAddArgs, config: &mut Config) -> Result<()> {
     if let Some(desc) = &args.description {
         println!("  Description: {}", desc);
     }
+    if args.code {
+        println!("  Code mode: trigram tokenizer, no stemming");
+    }
+    match &args.stopwords {
+        Some(Stopwords::Builtin(name)) => println!("  Stopwords: {} (built-in)", name),
+        Some(Stopwords::Words(words)) => println!("  Stopwords: {}", words.join(", ")),
+        None => {}
+    }
 
     Ok(())
 }
 
+/// Live index figures for one collection, from `collection list --with-stats`
+#[derive(Debug, Serialize)]
+struct CollectionStats {
+    documents: usize,
+    chunks: usize,
+    index_bytes: u64,
+}
+
+/// One row of `collection list`
+#[derive(Debug, Serialize)]
+struct CollectionListing<'a> {
+    name: &'a str,
+    path: String,
+    pattern: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<CollectionStats>,
+}
+
 /// List all collections
-fn list_collections(config: &Config) -> Result<()> {
-    if config.collections.is_empty() {
+fn list_collections(args: &CollectionListArgs, config: &Config, format: &str) -> Result<()> {
+    let stats = if args.with_stats && !config.collections.is_empty() {
+        Some(Store::new(config)?.get_stats()?)
+    } else {
+        None
+    };
+    let listings: Vec<CollectionListing> = config
+        .collections
+        .iter()
+        .map(|collection| CollectionListing {
+            name: &collection.name,
+            path: collection.path.display().to_string(),
+            pattern: collection.pattern.as_deref().unwrap_or("**/*"),
+            description: collection.description.as_deref(),
+            stats: stats.as_ref().map(|stats| CollectionStats {
+                documents: stats.collection_stats.get(&collection.name).copied().unwrap_or(0),
+                chunks: stats.collection_chunks.get(&collection.name).copied().unwrap_or(0),
+                index_bytes: stats.collection_index_bytes.get(&collection.name).copied().unwrap_or(0),
+            }),
+        })
+        .collect();
+
+    if format == "json" {
+        let output = serde_json::json!({ "action": "list", "collections": listings });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if listings.is_empty() {
         println!("No collections configured");
         return Ok(());
     }
 
     println!("Collections:");
-    println!("{:<20} {:<40} {:<15} Description", "Name", "Path", "Pattern");
-    println!("{}", "-".repeat(95));
+    if args.with_stats {
+        println!(
+            "{:<20} {:<40} {:<15} {:>9} {:>9} {:>10} Description",
+            "Name", "Path", "Pattern", "Documents", "Chunks", "Index"
+        );
+        println!("{}", "-".repeat(126));
+    } else {
+        println!("{:<20} {:<40} {:<15} Description", "Name", "Path", "Pattern");
+        println!("{}", "-".repeat(95));
+    }
 
-    for collection in &config.collections {
-        let path_str = collection.path.display().to_string();
-        let pattern = collection.pattern.as_deref().unwrap_or("**/*");
-        let desc = collection.description.as_deref().unwrap_or("");
-        println!("{:<20} {:<40} {:<15} {}", collection.name, path_str, pattern, desc);
+    for listing in &listings {
+        let desc = listing.description.unwrap_or("");
+        match &listing.stats {
+            Some(stats) => println!(
+                "{:<20} {:<40} {:<15} {:>9} {:>9} {:>10} {}",
+                listing.name,
+                listing.path,
+                listing.pattern,
+                stats.documents,
+                stats.chunks,
+                format_bytes(stats.index_bytes as i64),
+                desc
+            ),
+            None => println!("{:<20} {:<40} {:<15} {}", listing.name, listing.path, listing.pattern, desc),
+        }
     }
 
     Ok(())
 }
 
-/// Remove a collection
+/// Remove a collection, keeping its index files unless `--purge` is given
 fn remove_collection(args: &CollectionRemoveArgs, config: &mut Config) -> Result<()> {
     let name = &args.name;
+    let cache_dir = config.cache_dir_for(name);
+    let configured = config.collections.iter().any(|c| c.name == *name);
 
-    let idx = config.collections.iter().position(|c| c.name == *name);
-    match idx {
-        Some(i) => {
-            config.collections.remove(i);
-            config.save()?;
-
-            // Remove cached index database
-            let cache_dir = config.cache_dir_for(name);
-            if cache_dir.exists() {
-                std::fs::remove_dir_all(&cache_dir)?;
-                println!("Removed cache directory: {}", cache_dir.display());
-            }
+    // --purge also cleans up index files left behind by an earlier plain remove
+    if !configured && (!args.purge || !cache_dir.exists()) {
+        anyhow::bail!("Collection not found: {}", name);
+    }
 
-            println!("Collection '{}' removed successfully", name);
-        }
-        None => {
-            anyhow::bail!("Collection not found: {}", name);
+    let bytes = dir_bytes(&cache_dir);
+    if args.purge && !args.yes && !confirm_purge(name, &cache_dir, bytes)? {
+        println!("Aborted; collection '{}' left unchanged", name);
+        return Ok(());
+    }
+
+    // Keep a restorable copy of the documents before the index goes
+    if args.purge && config.db_path_for(name).exists() {
+        let collection = config.collections.iter().find(|c| c.name == *name).cloned();
+        let store = Store::new(config)?;
+        let manifest = store.trash_collection(name, collection)?;
+        if config.cache.shared_db {
+            store.delete_collection_rows(name)?;
         }
+        println!(
+            "Moved {} documents ({} vectors) to the trash; undo with: qmd trash restore {}",
+            manifest.documents, manifest.vectors, manifest.id
+        );
+    }
+
+    if configured {
+        *config = Config::update(|latest| {
+            match latest.collections.iter().position(|c| c.name == *name) {
+                Some(i) => {
+                    latest.collections.remove(i);
+                    Ok(())
+                }
+                None => anyhow::bail!(
+                    "Collection not found: {} (it may have been removed or renamed by another process)",
+                    name
+                ),
+            }
+        })?;
+        println!("Collection '{}' removed successfully", name);
+    }
+
+    if !cache_dir.exists() {
+        return Ok(());
+    }
+    if args.purge {
+        std::fs::remove_dir_all(&cache_dir)?;
+        println!("Removed cache directory: {}", cache_dir.display());
+        println!("  Freed: {}", format_bytes(bytes as i64));
+    } else {
+        println!(
+            "Index files kept at {} ({}); re-run with --purge to delete them",
+            cache_dir.display(),
+            format_bytes(bytes as i64)
+        );
     }
 
     Ok(())
 }
 
+/// Ask before deleting index files; refuses when there is no terminal to ask on
+fn confirm_purge(name: &str, cache_dir: &Path, bytes: u64) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "Refusing to purge '{}' without confirmation; pass --yes to delete {}",
+            name,
+            cache_dir.display()
+        );
+    }
+
+    let confirmed = Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt(format!(
+            "Delete index files for '{}' at {} ({})?",
+            name,
+            cache_dir.display(),
+            format_bytes(bytes as i64)
+        ))
+        .default(false)
+        .interact()?;
+    Ok(confirmed)
+}
+
+/// Total size of the files under a directory, 0 if it does not exist
+fn dir_bytes(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_bytes(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
 /// Rename a collection
 fn rename_collection(args: &CollectionRenameArgs, config: &mut Config) -> Result<()> {
     let old_name = &args.old_name;
     let new_name = &args.new_name;
 
-    // Check if new name already exists
-    if config.collections.iter().any(|c| c.name == *new_name) {
-        anyhow::bail!("Collection name already exists: {}", new_name);
-    }
-
-    let idx = config.collections.iter().position(|c| c.name == *old_name);
-    match idx {
-        Some(i) => {
-            config.collections[i].name = new_name.clone();
-            config.save()?;
+    *config = Config::update(|latest| {
+        // Check if new name already exists
+        if latest.collections.iter().any(|c| c.name == *new_name) {
+            anyhow::bail!("Collection name already exists: {}", new_name);
+        }
 
-            // Rename cache directory
-            let old_cache = config.cache_dir_for(old_name);
-            let new_cache = config.cache_dir_for(new_name);
-            if old_cache.exists() {
-                std::fs::rename(&old_cache, &new_cache)?;
-                println!("Renamed cache directory: {} -> {}", old_cache.display(), new_cache.display());
+        match latest.collections.iter_mut().find(|c| c.name == *old_name) {
+            Some(collection) => {
+                collection.name = new_name.clone();
+                Ok(())
             }
-
-            println!("Collection '{}' renamed to '{}'", old_name, new_name);
-        }
-        None => {
-            anyhow::bail!("Collection not found: {}", old_name);
+            None => anyhow::bail!(
+                "Collection not found: {} (it may have been removed or renamed by another process)",
+                old_name
+            ),
         }
+    })?;
+
+    // Rename cache directory
+    let old_cache = config.cache_dir_for(old_name);
+    let new_cache = config.cache_dir_for(new_name);
+    if old_cache.exists() {
+        std::fs::rename(&old_cache, &new_cache)?;
+        println!("Renamed cache directory: {} -> {}", old_cache.display(), new_cache.display());
     }
 
+    println!("Collection '{}' renamed to '{}'", old_name, new_name);
+
     Ok(())
 }
@@ -1,9 +1,10 @@
 use crate::anel::AnelSpec;
-use crate::cli::{ContextCommands, ContextAddArgs, ContextRemoveArgs};
+use crate::cli::{ContextCommands, ContextAddArgs, ContextGenerateArgs, ContextRemoveArgs};
 use crate::config::Config;
+use crate::llm::Router;
+use crate::store::context::{ContextCoverage, GenerateMethod};
 use crate::store::Store;
 use anyhow::Result;
-use std::path::PathBuf;
 
 /// Handle context commands
 pub fn handle(
@@ -39,6 +40,13 @@ pub fn handle(
                 println!("  path: {}", args.path);
                 "rm"
             }
+            ContextCommands::Generate(args) => {
+                println!("[DRY-RUN] Would execute context generate with:");
+                println!("  collection: {:?}", args.collection);
+                println!("  depth: {}", args.depth);
+                println!("  overwrite: {}", args.overwrite);
+                "generate"
+            }
         };
         println!("  action: {}", action);
         return Ok(());
@@ -49,13 +57,14 @@ pub fn handle(
         ContextCommands::List => list_contexts(config),
         ContextCommands::Check => check_contexts(config),
         ContextCommands::Rm(args) => remove_context(args, config),
+        ContextCommands::Generate(args) => generate_contexts(args, config, &cmd.format),
     }
 }
 
 /// Add a context (path with description for relevance)
 fn add_context(args: &ContextAddArgs, config: &mut Config) -> Result<()> {
     let path = match &args.path {
-        Some(p) => shellexpand::tilde(p).parse::<PathBuf>()?,
+        Some(p) => crate::paths::expand(p)?,
         None => std::env::current_dir()?,
     };
 
@@ -86,6 +95,7 @@ fn add_context(args: &ContextAddArgs, config: &mut Config) -> Result<()> {
                 path: path.clone(),
                 pattern: Some("**/*".to_string()),
                 description: Some(args.description.clone()),
+                ..Default::default()
             };
             config.collections.push(collection);
             config.save()?;
@@ -134,7 +144,7 @@ fn list_contexts(config: &Config) -> Result<()> {
 
 /// Remove a context
 fn remove_context(args: &ContextRemoveArgs, config: &mut Config) -> Result<()> {
-    let path = shellexpand::tilde(&args.path).parse::<PathBuf>()?;
+    let path = crate::paths::expand(&args.path)?;
     let path_str = path.display().to_string();
 
     let idx = config.collections.iter().position(|c| c.path == path);
@@ -161,6 +171,53 @@ fn remove_context(args: &ContextRemoveArgs, config: &mut Config) -> Result<()> {
     Ok(())
 }
 
+/// Generate directory descriptions with the LLM, or rule-based summaries
+fn generate_contexts(args: &ContextGenerateArgs, config: &Config, format: &str) -> Result<()> {
+    let collections: Vec<String> = match &args.collection {
+        Some(name) => {
+            if !config.collections.iter().any(|c| &c.name == name) {
+                anyhow::bail!("Collection not found: {}", name);
+            }
+            vec![name.clone()]
+        }
+        None => config.collections.iter().map(|c| c.name.clone()).collect(),
+    };
+
+    let store = Store::new(config)?;
+    let llm = Router::new(config)?;
+    if !llm.has_generator() {
+        log::info!("No generation model configured; using rule-based summaries");
+    }
+
+    let rt = tokio::runtime::Runtime::new()?;
+    let mut generated = Vec::new();
+    for collection in &collections {
+        generated.extend(rt.block_on(store.generate_path_contexts(collection, args.depth, args.overwrite, &llm))?);
+    }
+
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "contexts": generated }))?);
+        return Ok(());
+    }
+
+    if generated.is_empty() {
+        println!("No directories to describe");
+        return Ok(());
+    }
+    for context in &generated {
+        let note = match context.method {
+            GenerateMethod::Llm => "",
+            GenerateMethod::Rules => " (rule-based)",
+            GenerateMethod::Skipped => " (kept manual description; use --overwrite to replace)",
+        };
+        println!("  qmd://{}/{} — {}{}", context.collection, context.path, context.description, note);
+    }
+    let written = generated.iter().filter(|c| c.method != GenerateMethod::Skipped).count();
+    println!("\nGenerated {} of {} directory contexts", written, generated.len());
+
+    Ok(())
+}
+
 /// Check for collections and paths without context
 fn check_contexts(config: &Config) -> Result<()> {
     // Get collections without context
@@ -219,6 +276,33 @@ fn check_contexts(config: &Config) -> Result<()> {
         println!("Use 'qmd context list' to see all configured contexts.\n");
     }
 
+    // Share of documents under a described collection or directory
+    if !all_collections.is_empty() {
+        println!("Context coverage:\n");
+        let mut overall = ContextCoverage::default();
+        for coll in all_collections {
+            let coverage = store.context_coverage(&coll.name, coll.description.is_some())?;
+            println!(
+                "  {}: {:.1}% ({}/{} documents)",
+                coll.name,
+                coverage.percent(),
+                coverage.covered,
+                coverage.total
+            );
+            overall.covered += coverage.covered;
+            overall.total += coverage.total;
+        }
+        println!(
+            "\n  Overall: {:.1}% ({}/{} documents)",
+            overall.percent(),
+            overall.covered,
+            overall.total
+        );
+        if overall.covered < overall.total {
+            println!("  Suggestion: qmd context generate\n");
+        }
+    }
+
     Ok(())
 }
 
@@ -263,7 +347,10 @@ fn get_top_level_paths_without_context(
         }
     }
 
-    // For now, return all top-level directories (we'd need to check contexts in DB for full impl)
+    // Directories already described (by hand or generated) need no suggestion
+    for (path, _) in store.list_path_contexts(collection_name)? {
+        top_level_dirs.remove(&path);
+    }
     Ok(top_level_dirs.into_iter().collect())
 }
 
@@ -1,15 +1,22 @@
 use crate::anel::AnelSpec;
 use crate::cli::EmbedArgs;
-use crate::store::Store;
+use crate::config::Config;
+use crate::store::{estimate_tokens, Store};
 use crate::store::chunker::{chunk_document, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP};
+use crate::store::embed::{pending_documents, EmbedStats};
 use crate::llm::Router;
 use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+
+pub use crate::store::embed::EmbedJob;
 
 /// Handle embed command - generate/update embeddings
 pub fn handle(
     cmd: &EmbedArgs,
     store: &Store,
     llm: &Router,
+    config: &Config,
 ) -> Result<()> {
     // Handle --emit-spec: output ANEL specification and exit
     if cmd.emit_spec {
@@ -18,141 +25,246 @@ pub fn handle(
         return Ok(());
     }
 
-    // Handle --dry-run: validate parameters without executing
+    // Handle --dry-run: estimate the work without calling any model
     if cmd.dry_run {
-        println!("[DRY-RUN] Would execute embed with:");
-        println!("  collection: {:?}", cmd.collection);
-        println!("  force: {}", cmd.force);
+        let collections: Vec<String> = match &cmd.collection {
+            Some(col) => vec![col.clone()],
+            None => store.get_collections().iter().map(|c| c.name.clone()).collect(),
+        };
+        let estimates = collections
+            .iter()
+            .map(|col| estimate_collection(store, col, config, llm.embedder_model().as_deref(), cmd.force))
+            .collect::<Result<Vec<_>>>()?;
+        print_estimates(&estimates, config, llm, &cmd.format)?;
         return Ok(());
     }
 
-    let collection = cmd.collection.clone();
+    let collections: Vec<String> = match &cmd.collection {
+        Some(col) => vec![col.clone()],
+        None => store.get_collections().iter().map(|c| c.name.clone()).collect(),
+    };
 
     // Create a Tokio runtime for async operations
     let rt = tokio::runtime::Runtime::new()?;
 
-    if let Some(col) = &collection {
-        println!("Generating embeddings for collection: {}", col);
-        rt.block_on(async {
-            embed_collection_async(store, col, llm, cmd.force).await
-        })?;
-    } else {
-        println!("Generating embeddings for all collections...");
-        rt.block_on(async {
-            embed_all_collections_async(store, llm, cmd.force).await
-        })?;
+    let json = cmd.format == "json";
+    let mut runs = Vec::with_capacity(collections.len());
+    for col in &collections {
+        if !json {
+            println!("Generating embeddings for collection: {}", col);
+        }
+        runs.push((col.as_str(), rt.block_on(store.embed_collection(col, llm, cmd.force))?));
     }
-
-    Ok(())
+    if !json && !llm.has_embedder() {
+        println!("Note: no embedding model is configured, so nothing was embedded.");
+    }
+    print_stats(&runs, json)
 }
 
-async fn embed_collection_async(
-    store: &Store,
-    collection: &str,
-    llm: &Router,
-    force: bool,
-) -> Result<()> {
-    use log::info;
+/// Report what a real run stored, in the shape of `AnelSpec::embed`
+fn print_stats(runs: &[(&str, EmbedStats)], json: bool) -> Result<()> {
+    let documents: usize = runs.iter().map(|(_, s)| s.documents_embedded).sum();
+    let chunks: usize = runs.iter().map(|(_, s)| s.chunks_embedded).sum();
+    let model = runs.iter().find_map(|(_, s)| s.model.clone());
 
-    info!("Embedding collection: {}", collection);
-
-    if !llm.has_embedder() {
-        log::warn!("No embedder available, skipping embedding");
+    if json {
+        let output = serde_json::json!({
+            "collections_processed": runs.len(),
+            "documents_embedded": documents,
+            "chunks_embedded": chunks,
+            "model": model,
+            "dry_run": false,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
         return Ok(());
     }
 
-    let conn = store.get_connection(collection)?;
-
-    // Get all documents that need embedding
-    let mut stmt = if force {
-        conn.prepare("SELECT id, hash, doc FROM documents WHERE active = 1")?
-    } else {
-        conn.prepare(
-            "SELECT id, hash, doc FROM documents
-             WHERE active = 1
-             AND hash NOT IN (SELECT DISTINCT hash FROM content_vectors)"
-        )?
-    };
-
-    let docs: Vec<(i64, String, String)> = stmt
-        .query_map([], |row| {
-            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
-        })?
-        .filter_map(|r| r.ok())
-        .collect();
-
-    info!("Found {} documents to embed", docs.len());
+    for (collection, stats) in runs {
+        println!(
+            "  {}: {} documents, {} chunks",
+            collection, stats.documents_embedded, stats.chunks_embedded
+        );
+    }
+    match model {
+        Some(model) => println!("Embedded {} documents ({} chunks) with {}", documents, chunks, model),
+        None => println!("Nothing to embed"),
+    }
+    Ok(())
+}
 
-    // Chunk all documents and build a flat list of (hash, chunk) pairs
-    let mut all_chunks: Vec<(String, crate::store::chunker::Chunk)> = Vec::new();
+/// Where the throughput behind a time estimate comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateSource {
+    /// `embed.chunks_per_second`
+    Config,
+    /// The collection's most recent recorded embed run
+    LastRun,
+}
 
-    for (_doc_id, hash, doc) in &docs {
-        let chunks = chunk_document(doc, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP);
+/// What `qmd embed` would do for one collection
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbedEstimate {
+    pub collection: String,
+    pub documents: usize,
+    pub chunks: usize,
+    pub tokens: usize,
+    /// USD, when the remote embedding model has a price in `embed.prices`
+    pub cost_usd: Option<f64>,
+    pub chunks_per_second: Option<f64>,
+    pub rate_source: Option<RateSource>,
+    pub seconds: Option<f64>,
+}
 
-        // If force re-embed, delete old chunks for this hash
-        if force {
-            conn.execute("DELETE FROM content_vectors WHERE hash = ?", [hash])?;
-            // Delete all vector entries for this hash (any seq)
-            let like_pattern = format!("{}_%", hash);
-            conn.execute("DELETE FROM vectors_vec WHERE hash_seq LIKE ?", [&like_pattern])?;
-        }
+/// Chunk the documents a real run would embed and price the result
+pub fn estimate_collection(
+    store: &Store,
+    collection: &str,
+    config: &Config,
+    model: Option<&str>,
+    force: bool,
+) -> Result<EmbedEstimate> {
+    let conn = store.get_connection(collection)?;
+    let docs = pending_documents(&conn, collection, model, force)?;
 
-        for chunk in chunks {
-            all_chunks.push((hash.clone(), chunk));
+    let mut chunks = 0;
+    let mut tokens = 0;
+    for (_, doc) in &docs {
+        for chunk in chunk_document(doc, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP) {
+            chunks += 1;
+            tokens += estimate_tokens(&chunk.text);
         }
     }
 
-    info!("Total chunks to embed: {}", all_chunks.len());
+    let cost_usd = remote_embed_model(config)
+        .and_then(|model| config.embed.prices.get(model))
+        .map(|price| tokens as f64 * price / 1_000_000.0);
 
-    // Process chunks in batches
-    let batch_size = 10;
-    for (batch_idx, batch) in all_chunks.chunks(batch_size).enumerate() {
-        info!("Processing batch {}/{}", batch_idx + 1, (all_chunks.len() + batch_size - 1) / batch_size);
+    let rate = match config.embed.chunks_per_second.filter(|r| *r > 0.0) {
+        Some(rate) => Some((rate, RateSource::Config)),
+        None => last_run_rate(&conn)?.map(|rate| (rate, RateSource::LastRun)),
+    };
 
-        // Prepare texts for embedding
-        let texts: Vec<&str> = batch.iter().map(|(_, chunk)| chunk.text.as_str()).collect();
+    Ok(EmbedEstimate {
+        collection: collection.to_string(),
+        documents: docs.len(),
+        chunks,
+        tokens,
+        cost_usd,
+        chunks_per_second: rate.map(|(r, _)| r),
+        rate_source: rate.map(|(_, s)| s),
+        seconds: rate.map(|(r, _)| chunks as f64 / r),
+    })
+}
 
-        // Generate embeddings
-        let embedding_result = llm.embed(&texts).await?;
+fn remote_embed_model(config: &Config) -> Option<&str> {
+    config.models.embed.as_ref()?.remote.as_deref()
+}
 
-        info!("Generated {} embeddings with model: {}",
-              embedding_result.embeddings.len(), embedding_result.model);
+/// Chunks per second of the collection's most recent embed run
+fn last_run_rate(conn: &Connection) -> Result<Option<f64>> {
+    let run: Option<(i64, f64)> = conn
+        .query_row(
+            "SELECT chunks, seconds FROM embed_runs
+             WHERE chunks > 0 AND seconds > 0
+             ORDER BY id DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    Ok(run.map(|(chunks, seconds)| chunks as f64 / seconds))
+}
 
-        // Store embeddings per chunk
-        for (i, (hash, chunk)) in batch.iter().enumerate() {
-            let embedding = &embedding_result.embeddings[i];
-            let embedding_json = serde_json::to_string(embedding)?;
+fn print_estimates(estimates: &[EmbedEstimate], config: &Config, llm: &Router, format: &str) -> Result<()> {
+    let documents: usize = estimates.iter().map(|e| e.documents).sum();
+    let chunks: usize = estimates.iter().map(|e| e.chunks).sum();
+    let tokens: usize = estimates.iter().map(|e| e.tokens).sum();
+    // Unknown in total when unknown for any collection
+    let cost_usd: Option<f64> = estimates.iter().map(|e| e.cost_usd).sum();
+    let seconds: Option<f64> = estimates.iter().map(|e| e.seconds).sum();
 
-            // Store in content_vectors metadata table
-            conn.execute(
-                "INSERT OR REPLACE INTO content_vectors (hash, seq, pos, model, embedded_at)
-                 VALUES (?, ?, ?, ?, datetime('now'))",
-                rusqlite::params![hash, chunk.seq as i64, chunk.pos as i64, &embedding_result.model],
-            )?;
+    if format == "json" {
+        let output = serde_json::json!({
+            "dry_run": true,
+            "model": remote_embed_model(config),
+            "collections": estimates,
+            "total": {
+                "documents": documents,
+                "chunks": chunks,
+                "tokens": tokens,
+                "cost_usd": cost_usd,
+                "seconds": seconds,
+            },
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
 
-            // Store in vectors_vec table
-            let hash_seq = format!("{}_{}", hash, chunk.seq);
-            conn.execute(
-                "INSERT OR REPLACE INTO vectors_vec (hash_seq, embedding)
-                 VALUES (?, ?)",
-                [&hash_seq, &embedding_json],
-            )?;
+    let cost = |cost: Option<f64>| match cost {
+        Some(usd) => format!("~${:.4}", usd),
+        None => "cost unknown".to_string(),
+    };
+    let time = |seconds: Option<f64>| match seconds {
+        Some(s) => format!("~{:.1}s", s),
+        None => "time unknown".to_string(),
+    };
 
-            info!("Stored embedding for hash: {} chunk seq: {}", hash, chunk.seq);
-        }
+    println!("[DRY-RUN] Embedding estimate (no models called):");
+    for e in estimates {
+        let rate = match e.rate_source {
+            Some(RateSource::Config) => " (configured rate)",
+            Some(RateSource::LastRun) => " (last run rate)",
+            None => "",
+        };
+        println!(
+            "  {}: {} documents, {} chunks, ~{} tokens, {}, {}{}",
+            e.collection, e.documents, e.chunks, e.tokens, cost(e.cost_usd), time(e.seconds), rate
+        );
+    }
+    println!(
+        "Total: {} documents, {} chunks, ~{} tokens, {}, {}",
+        documents, chunks, tokens, cost(cost_usd), time(seconds)
+    );
+    if !llm.has_embedder() {
+        println!("Note: no embedding model is configured, so a real run would skip embedding.");
     }
-
-    info!("Embedding complete for collection: {}", collection);
     Ok(())
 }
 
-async fn embed_all_collections_async(
+/// Embed exactly the given content hashes of a collection, replacing any
+/// existing chunks for them. Returns the number of chunks embedded.
+pub async fn embed_hashes_async(
     store: &Store,
+    collection: &str,
     llm: &Router,
-    force: bool,
-) -> Result<()> {
-    for collection in store.get_collections() {
-        embed_collection_async(store, &collection.name, llm, force).await?;
+    hashes: &[String],
+) -> Result<usize> {
+    use log::info;
+
+    if !llm.has_embedder() {
+        log::warn!("No embedder available, skipping embedding");
+        return Ok(0);
     }
-    Ok(())
+
+    let conn = store.get_connection(collection)?;
+
+    let mut docs: Vec<(String, String)> = Vec::new();
+    for hash in hashes {
+        if docs.iter().any(|(h, _)| h == hash) {
+            continue;
+        }
+        let doc: Option<String> = conn.query_row(
+            "SELECT qmd_doc(doc, codec, packed) FROM content WHERE hash = ?",
+            [hash],
+            |row| row.get(0),
+        ).optional()?;
+        match doc {
+            Some(doc) => docs.push((hash.clone(), doc)),
+            None => log::warn!("Content {} not found in collection {}", hash, collection),
+        }
+    }
+
+    info!("Embedding {} changed documents in collection: {}", docs.len(), collection);
+
+    EmbedJob::new(store, collection, conn, docs).run(llm, |_| {}).await
 }
@@ -1,14 +1,16 @@
 use crate::anel::AnelSpec;
 use crate::cli::GetArgs;
 use crate::config::Config;
+use crate::store::access::RetrievedDocument;
+use crate::store::extract::read_document;
+use crate::store::{make_docid, DocumentSize, Store};
 use anyhow::{Context, Result};
-use std::fs;
 use std::path::PathBuf;
 
 /// Handle get command - retrieve document content
 pub fn handle(
     cmd: &GetArgs,
-    _config: &Config,
+    config: &Config,
 ) -> Result<()> {
     let file_spec = &cmd.file;
 
@@ -26,30 +28,60 @@ pub fn handle(
         println!("  limit: {}", cmd.limit);
         println!("  from: {}", cmd.from);
         println!("  full: {}", cmd.full);
+        println!("  around: {}", cmd.around);
+        if let Some(hash) = &cmd.if_hash {
+            println!("  if_hash: {}", hash);
+        }
         return Ok(());
     }
 
-    // Parse file path with optional :line suffix
+    // Parse file path with optional :line suffix; docids name indexed paths
     let (file_path, line_spec) = parse_file_spec(file_spec)?;
+    let file_path = docid_path(&file_path, config).unwrap_or(file_path);
+
+    // Indexed documents come from the store so the stored hash is available;
+    // anything else is read straight from disk
+    let store = Store::new(config).ok();
+    let document = match indexed_document(&file_path, store.as_ref()) {
+        Some(document) => document,
+        None => read_file(&file_path, config)?,
+    };
 
-    // Resolve full path
-    let full_path = resolve_path(&file_path, _config)?;
-
-    if !full_path.exists() {
-        anyhow::bail!("File not found: {}", full_path.display());
-    }
-
-    if full_path.is_dir() {
-        anyhow::bail!("Path is a directory, not a file: {}", full_path.display());
+    if cmd.if_hash.as_deref() == Some(document.hash.as_str()) {
+        if cmd.format == "json" {
+            let output = serde_json::json!({ "unchanged": true, "hash": document.hash });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            println!("Unchanged: {} ({})", document.path, document.hash);
+        }
+        return Ok(());
     }
 
-    let content = fs::read_to_string(&full_path)
-        .with_context(|| format!("Failed to read file: {}", full_path.display()))?;
-
+    let content = document.content;
     let lines: Vec<&str> = content.lines().collect();
 
     // Handle line specifications
-    let (start, end) = if let Some(line) = line_spec {
+    let mut match_line = None;
+    let (start, end) = if cmd.around {
+        // Center on :line, or on where the last search matched this document
+        let line = match &line_spec {
+            Some(line) => line.parse().context("--around takes a single :line")?,
+            None => {
+                let prefix = format!("{}/", document.collection);
+                let relative = document.path.strip_prefix(&prefix).unwrap_or(&document.path);
+                let docid = make_docid(&document.collection, relative);
+                store
+                    .as_ref()
+                    .and_then(|store| store.match_position(&docid).ok().flatten())
+                    .map(|position| position.line)
+                    .with_context(|| {
+                        format!("No match recorded for {}; search for it first or pass :line", docid)
+                    })?
+            }
+        };
+        match_line = Some(line);
+        around_window(line, cmd.limit, lines.len())
+    } else if let Some(line) = line_spec {
         // :line or :line-end format
         let (start_line, end_line) = parse_line_range(&line, lines.len())?;
         (start_line, end_line)
@@ -59,9 +91,27 @@ pub fn handle(
         (cmd.from, std::cmp::min(cmd.from + cmd.limit, lines.len()))
     };
 
+    if cmd.format == "json" {
+        let size = DocumentSize::measure(&content);
+        let output = serde_json::json!({
+            "path": document.path,
+            "hash": document.hash,
+            "from": start + 1,
+            "lines": &lines[start..end],
+            "total_lines": lines.len(),
+            "match_line": match_line,
+            "bytes": size.bytes,
+            "words": size.words,
+            "tokens": size.tokens,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
     for (i, line) in lines[start..end].iter().enumerate() {
         let line_num = start + i + 1;
-        println!("{:>6}: {}", line_num, line);
+        let marker = if match_line == Some(line_num) { '>' } else { ':' };
+        println!("{:>6}{} {}", line_num, marker, line);
     }
 
     if end < lines.len() {
@@ -71,10 +121,58 @@ pub fn handle(
     Ok(())
 }
 
+/// Document as stored in the index, if the path names one
+fn indexed_document(file_path: &str, store: Option<&Store>) -> Option<RetrievedDocument> {
+    store?.retrieve_document(file_path).ok()
+}
+
+/// `collection/path` for a docid (`collection:path`) of a configured collection
+fn docid_path(spec: &str, config: &Config) -> Option<String> {
+    let (collection, path) = spec.split_once(':')?;
+    if path.is_empty() || !config.collections.iter().any(|c| c.name == collection) {
+        return None;
+    }
+    Some(format!("{}/{}", collection, path))
+}
+
+/// `limit` lines centered on 1-based `line`, as a 0-based half-open range
+fn around_window(line: usize, limit: usize, total_lines: usize) -> (usize, usize) {
+    let center = line.saturating_sub(1).min(total_lines.saturating_sub(1));
+    let start = center.saturating_sub(limit / 2);
+    let end = std::cmp::min(start + limit, total_lines);
+    // Near the end, show more lines before the match instead
+    (end.saturating_sub(limit).min(start), end)
+}
+
+/// File outside the index, hashed the same way the indexer would
+fn read_file(file_path: &str, config: &Config) -> Result<RetrievedDocument> {
+    let full_path = resolve_path(file_path, config)?;
+
+    if !full_path.exists() {
+        anyhow::bail!("File not found: {}", full_path.display());
+    }
+
+    if full_path.is_dir() {
+        anyhow::bail!("Path is a directory, not a file: {}", full_path.display());
+    }
+
+    let content = read_document(&full_path, config)?;
+
+    Ok(RetrievedDocument {
+        collection: String::new(),
+        path: full_path.display().to_string(),
+        hash: Store::calculate_hash(&content),
+        content,
+    })
+}
+
 fn parse_file_spec(spec: &str) -> Result<(String, Option<String>)> {
     if let Some((path, line)) = spec.rsplit_once(':') {
-        // Check if line part is numeric or range
-        if line.parse::<usize>().is_ok() || line.contains('-') {
+        // Check if line part is numeric or a numeric range
+        let is_range = line
+            .split_once('-')
+            .is_some_and(|(a, b)| a.parse::<usize>().is_ok() && b.parse::<usize>().is_ok());
+        if line.parse::<usize>().is_ok() || is_range {
             Ok((path.to_string(), Some(line.to_string())))
         } else {
             // The colon is part of the filename
@@ -111,3 +209,28 @@ fn resolve_path(relative: &str, _config: &Config) -> Result<PathBuf> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_around_window_centers_and_clamps() {
+        assert_eq!(around_window(50, 10, 100), (44, 54));
+        assert_eq!(around_window(2, 10, 100), (0, 10));
+        assert_eq!(around_window(99, 10, 100), (90, 100));
+        assert_eq!(around_window(3, 10, 5), (0, 5));
+    }
+
+    #[test]
+    fn test_parse_file_spec_keeps_dashed_names() {
+        assert_eq!(
+            parse_file_spec("notes:notes/long-doc.md").unwrap(),
+            ("notes:notes/long-doc.md".to_string(), None)
+        );
+        assert_eq!(
+            parse_file_spec("notes/a.md:3-7").unwrap(),
+            ("notes/a.md".to_string(), Some("3-7".to_string()))
+        );
+    }
+}
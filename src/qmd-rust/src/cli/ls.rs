@@ -19,20 +19,22 @@ pub fn handle(cmd: &LsArgs, config: &Config) -> Result<()> {
     if cmd.dry_run {
         println!("[DRY-RUN] Would execute ls with:");
         println!("  path: {:?}", cmd.path);
+        println!("  include_inactive: {}", cmd.include_inactive);
         return Ok(());
     }
 
+    let json = cmd.format == "json";
     match &cmd.path {
-        None => list_collections(config),
-        Some(path) => list_files(config, path),
+        None => list_collections(config, json, cmd.include_inactive),
+        Some(path) => list_files(config, path, json, cmd.include_inactive),
     }
 }
 
 /// List all collections with file counts
-fn list_collections(config: &Config) -> Result<()> {
+fn list_collections(config: &Config, json: bool, include_inactive: bool) -> Result<()> {
     let collections = &config.collections;
 
-    if collections.is_empty() {
+    if collections.is_empty() && !json {
         println!("No collections found. Run 'qmd collection add .' to index files.");
         return Ok(());
     }
@@ -40,17 +42,33 @@ fn list_collections(config: &Config) -> Result<()> {
     // Get file counts from each collection's database
     let store = Store::new(config)?;
 
-    println!("\nCollections:\n");
+    let entries: Vec<CollectionEntry> = collections
+        .iter()
+        .map(|coll| {
+            // Query file count from database
+            let (file_count, tokens) = get_collection_totals(&store, &coll.name, include_inactive).unwrap_or((0, 0));
+            CollectionEntry {
+                name: coll.name.clone(),
+                path: coll.path.display().to_string(),
+                file_count,
+                tokens,
+            }
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "collections": entries }))?);
+        return Ok(());
+    }
 
-    for coll in collections {
-        // Query file count from database
-        let count = get_collection_file_count(&store, &coll.name).unwrap_or(0);
+    println!("\nCollections:\n");
 
+    for entry in &entries {
         // Format output similar to TypeScript version:
         //   qmd://collection/  (N files)
         println!(
-            "  qmd://{}/  ({} files)",
-            coll.name, count
+            "  qmd://{}/  ({} files, ~{} tokens)",
+            entry.name, entry.file_count, entry.tokens
         );
     }
 
@@ -58,77 +76,61 @@ fn list_collections(config: &Config) -> Result<()> {
     Ok(())
 }
 
-/// Get file count for a collection
-fn get_collection_file_count(store: &Store, collection: &str) -> Result<usize> {
+/// Get file count and estimated token total for a collection
+fn get_collection_totals(store: &Store, collection: &str, include_inactive: bool) -> Result<(usize, usize)> {
     let conn = store.get_connection(collection)?;
 
-    let count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM documents WHERE active = 1",
-        [],
-        |row| row.get(0),
+    let (count, tokens): (i64, i64) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(tokens), 0) FROM documents WHERE collection = ? AND (active = 1 OR ?)",
+        rusqlite::params![collection, include_inactive],
+        |row| Ok((row.get(0)?, row.get(1)?)),
     )?;
 
-    Ok(count as usize)
+    Ok((count as usize, tokens as usize))
 }
 
 /// List files in a collection
-fn list_files(config: &Config, path_arg: &str) -> Result<()> {
+fn list_files(config: &Config, path_arg: &str, json: bool, include_inactive: bool) -> Result<()> {
     // Parse the path argument
     let (collection_name, path_prefix) = parse_ls_path(path_arg)?;
 
     // Get the collection config
-    let collection = config.collections.iter()
-        .find(|c| c.name == collection_name)
+    let collection = config.find_collection(&collection_name)
         .ok_or_else(|| anyhow::anyhow!("Collection not found: {}", collection_name))?;
 
     let store = Store::new(config)?;
     let conn = store.get_connection(&collection.name)?;
 
-    // Query files
-    let files = if let Some(prefix) = &path_prefix {
-        // List files under a specific path
-        let pattern = format!("{}%", prefix);
-        let mut stmt = conn.prepare(
-            "SELECT d.path, d.title, d.modified_at, LENGTH(ct.doc) as size
-             FROM documents d
-             JOIN content ct ON d.hash = ct.hash
-             WHERE d.collection = ? AND d.path LIKE ? AND d.active = 1
-             ORDER BY d.path"
-        )?;
-
-        let files = stmt.query_map([&collection.name, &pattern], |row| {
-            Ok(FileEntry {
-                path: row.get(0)?,
-                title: row.get(1)?,
-                modified_at: row.get(2)?,
-                size: row.get(3)?,
-            })
-        })?
-        .filter_map(|r| r.ok())
-        .collect::<Vec<_>>();
-        files
-    } else {
-        // List all files in the collection
-        let mut stmt = conn.prepare(
-            "SELECT d.path, d.title, d.modified_at, LENGTH(ct.doc) as size
-             FROM documents d
-             JOIN content ct ON d.hash = ct.hash
-             WHERE d.collection = ? AND d.active = 1
-             ORDER BY d.path"
-        )?;
-
-        let files = stmt.query_map([&collection.name], |row| {
-            Ok(FileEntry {
-                path: row.get(0)?,
-                title: row.get(1)?,
-                modified_at: row.get(2)?,
-                size: row.get(3)?,
-            })
-        })?
-        .filter_map(|r| r.ok())
-        .collect::<Vec<_>>();
-        files
-    };
+    // Query files, optionally under a specific path
+    let pattern = format!("{}%", path_prefix.as_deref().unwrap_or(""));
+    let mut stmt = conn.prepare(
+        "SELECT d.path, d.title, d.modified_at, LENGTH(qmd_doc(ct.doc, ct.codec, ct.packed)) as size,
+                COALESCE(d.words, 0), COALESCE(d.tokens, 0), d.active
+         FROM documents d
+         JOIN content ct ON d.hash = ct.hash
+         WHERE d.collection = ? AND d.path LIKE ? AND (d.active = 1 OR ?)
+         ORDER BY d.path"
+    )?;
+
+    let params = rusqlite::params![&collection.name, &pattern, include_inactive];
+    let files = stmt.query_map(params, |row| {
+        Ok(FileEntry {
+            path: row.get(0)?,
+            title: row.get(1)?,
+            modified_at: row.get(2)?,
+            size: row.get(3)?,
+            words: row.get(4)?,
+            tokens: row.get(5)?,
+            inactive: !row.get::<_, bool>(6)?,
+        })
+    })?
+    .filter_map(|r| r.ok())
+    .collect::<Vec<_>>();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "files": files }))?);
+        return Ok(());
+    }
 
     if files.is_empty() {
         if let Some(prefix) = &path_prefix {
@@ -142,13 +144,16 @@ fn list_files(config: &Config, path_arg: &str) -> Result<()> {
     // Output in ls -l style
     for file in &files {
         let size_str = format_bytes(file.size);
+        let tokens_str = format!("~{}t", file.tokens);
         let time_str = format_time(&file.modified_at);
         println!(
-            "{:>8}  {}  qmd://{}/{}",
+            "{:>8}  {:>7}  {}  qmd://{}/{}{}",
             size_str,
+            tokens_str,
             time_str,
             collection_name,
-            file.path
+            file.path,
+            if file.inactive { "  (inactive)" } else { "" }
         );
     }
 
@@ -180,16 +185,29 @@ fn parse_ls_path(path_arg: &str) -> Result<(String, Option<String>)> {
     }
 }
 
+#[derive(Serialize)]
+struct CollectionEntry {
+    name: String,
+    path: String,
+    file_count: usize,
+    tokens: usize,
+}
+
 #[derive(Serialize)]
 struct FileEntry {
     path: String,
     title: String,
     modified_at: String,
     size: i64,
+    words: i64,
+    tokens: i64,
+    /// Soft-deleted; only listed with --include-inactive
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    inactive: bool,
 }
 
 /// Format bytes as human-readable string
-fn format_bytes(bytes: i64) -> String {
+pub(crate) fn format_bytes(bytes: i64) -> String {
     const KB: i64 = 1024;
     const MB: i64 = KB * 1024;
     const GB: i64 = MB * 1024;
@@ -1,5 +1,14 @@
+use crate::config::{BM25Backend, Config, Stopwords, VectorBackend};
+use crate::formatter::{Format, GroupBy};
+use crate::store::bundle::{bundle_content, limit_pieces, BundledContent, ContentMode};
+use crate::store::lang::QueryLanguage;
+use crate::store::order::SortBy;
+use crate::store::{PathBoost, SearchOptions, SearchResult, Store};
 use clap::{Args, Parser, Subcommand};
 
+/// How many times the limit a deduplicating search fetches
+const DEDUPE_OVERFETCH: usize = 3;
+
 /// QMD - AI-powered search with hybrid BM25 and vector search
 #[derive(Parser, Debug)]
 #[command(name = "qmd")]
@@ -7,6 +16,22 @@ use clap::{Args, Parser, Subcommand};
 #[command(version = "0.1.0")]
 #[command(about = "AI-powered search with hybrid BM25 and vector search", long_about = None)]
 pub struct Cli {
+    /// Load API keys and settings from a dotenv file (process environment wins)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub env_file: Option<std::path::PathBuf>,
+
+    /// Suppress preflight warnings about collections
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Write a chrome-trace JSON timing profile of the command to this file
+    #[arg(long, global = true, value_name = "PATH")]
+    pub profile: Option<std::path::PathBuf>,
+
+    /// Log line format on stderr [env: QMD_LOG_FORMAT] [default: text]
+    #[arg(long, global = true, value_enum, value_name = "FORMAT")]
+    pub log_format: Option<crate::logging::LogFormat>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -22,8 +47,15 @@ pub mod vsearch;
 pub mod query;
 pub mod embed;
 pub mod update;
+pub mod sync;
 pub mod status;
+pub mod suggest;
 pub mod cleanup;
+pub mod trash;
+pub mod rm;
+pub mod verify;
+pub mod reindex;
+pub mod server;
 pub mod agent;
 pub mod plugin;
 
@@ -36,6 +68,9 @@ pub struct LsArgs {
     /// Output format: cli, json, ndjson
     #[arg(long, default_value = "cli")]
     pub format: String,
+    /// Also list soft-deleted (inactive) documents, for debugging
+    #[arg(long)]
+    pub include_inactive: bool,
     /// Emit ANEL specification (JSON Schema) instead of executing
     #[arg(long)]
     pub emit_spec: bool,
@@ -52,29 +87,173 @@ pub struct FormatOptions {
     /// Number of results to return
     #[arg(short, long, default_value = "20")]
     pub limit: usize,
+    /// Results to skip before the first one returned, for paging
+    #[arg(long, default_value = "0")]
+    pub offset: usize,
     /// Minimum score threshold
     #[arg(long, default_value = "0.0")]
     pub min_score: f32,
     /// Collection to search
     #[arg(short, long)]
     pub collection: Option<String>,
-    /// Search all collections
+    /// Search all collections that are searchable by default
     #[arg(long)]
     pub all: bool,
-    /// BM25 backend: sqlite_fts5, lancedb
-    #[arg(long, default_value = "sqlite_fts5")]
-    pub fts_backend: String,
-    /// Vector backend: qmd_builtin, lancedb
-    #[arg(long, default_value = "qmd_builtin")]
-    pub vector_backend: String,
+    /// Search every collection, including those not searchable by default
+    #[arg(long)]
+    pub really_all: bool,
+    /// Drop results whose documents exceed this many (estimated) tokens
+    #[arg(long)]
+    pub max_tokens: Option<usize>,
+    /// Bundle stored text with JSON/NDJSON/Markdown results: full, chunks, snippet
+    #[arg(long, value_name = "MODE", num_args = 0..=1, require_equals = true, default_missing_value = "full")]
+    pub include_content: Option<ContentMode>,
+    /// Byte budget for bundled content, spent across results in rank order
+    #[arg(long, default_value = "65536")]
+    pub content_budget: usize,
+    /// Most content pieces (chunks or snippets) bundled across all results
+    #[arg(long, value_name = "N")]
+    pub max_snippets: Option<usize>,
+    /// Cut bundled content, then trailing results, so the output fits in this many bytes
+    #[arg(long, value_name = "BYTES", conflicts_with = "max_output_tokens")]
+    pub max_output_bytes: Option<usize>,
+    /// Like --max-output-bytes, in estimated tokens
+    #[arg(long, value_name = "TOKENS")]
+    pub max_output_tokens: Option<usize>,
+    /// BM25 backend: sqlite_fts5, lancedb (default: from config)
+    #[arg(long, value_parser = parse_fts_backend)]
+    pub fts_backend: Option<BM25Backend>,
+    /// Vector backend: qmd_builtin, lancedb, qdrant (default: from config)
+    #[arg(long, value_parser = parse_vector_backend)]
+    pub vector_backend: Option<VectorBackend>,
     /// Emit ANEL specification (JSON Schema) instead of executing
     #[arg(long)]
     pub emit_spec: bool,
+    /// Keep only the best-ranked result per content hash (default: defaults.dedupe_by_hash)
+    #[arg(long)]
+    pub dedupe_by_hash: bool,
+    /// Cluster results by collection or top-level directory: collection, dir
+    #[arg(long, value_name = "BY")]
+    pub group_by: Option<GroupBy>,
+    /// Order of the results shown: score, path, modified (newest first)
+    #[arg(long, value_name = "KEY", default_value = "score")]
+    pub sort_by: SortBy,
+    /// Reverse the --sort-by order
+    #[arg(long)]
+    pub reverse: bool,
     /// Dry-run mode: validate parameters without executing
     #[arg(long)]
     pub dry_run: bool,
 }
 
+impl FormatOptions {
+    /// Clone `config` with the --fts-backend/--vector-backend overrides applied
+    pub fn apply_backend_overrides(&self, config: &Config) -> Config {
+        let mut config = config.clone();
+        if let Some(backend) = &self.fts_backend {
+            config.bm25.backend = backend.clone();
+        }
+        if let Some(backend) = &self.vector_backend {
+            config.vector.backend = backend.clone();
+        }
+        config
+    }
+
+    /// Whether results sharing a content hash are folded into the first
+    pub fn dedupes(&self, store: &Store) -> bool {
+        self.dedupe_by_hash || store.defaults().dedupe_by_hash
+    }
+
+    /// Options for fetching results up to the end of the page, which
+    /// [`skip_offset`](Self::skip_offset) cuts once duplicates are folded;
+    /// deduping over-fetches so that folded duplicates free slots for other
+    /// documents before the limit applies
+    pub fn fetch_options(&self, options: &SearchOptions, dedupe: bool) -> SearchOptions {
+        let mut options = options.unpaged();
+        if dedupe {
+            options.limit = options.limit.saturating_mul(DEDUPE_OVERFETCH);
+        }
+        options
+    }
+
+    /// Drop the results before `--offset`
+    pub fn skip_offset(&self, results: Vec<SearchResult>) -> Vec<SearchResult> {
+        results.into_iter().skip(self.offset).collect()
+    }
+
+    /// Reorder the results that will be printed by --sort-by/--reverse
+    ///
+    /// Relevance still picks which results are shown: the list is cut to the
+    /// limit before it is reordered.
+    pub fn sort_results(&self, store: &Store, mut results: Vec<SearchResult>) -> anyhow::Result<Vec<SearchResult>> {
+        if self.sort_by == SortBy::Score && !self.reverse {
+            return Ok(results);
+        }
+        results.truncate(self.limit);
+        store.sort_results(&mut results, self.sort_by, self.reverse)?;
+        Ok(results)
+    }
+
+    /// Bundle content for the results that will be printed, if requested and
+    /// the output format can carry it
+    pub fn bundle_content(
+        &self,
+        store: &Store,
+        results: &[SearchResult],
+        query: &str,
+    ) -> anyhow::Result<Option<Vec<BundledContent>>> {
+        let Some(mode) = self.include_content else {
+            return Ok(None);
+        };
+        if !Format::from_string(&self.format).supports_content() {
+            log::warn!("--include-content is ignored for --format {}", self.format);
+            return Ok(None);
+        }
+
+        let shown = &results[..results.len().min(self.limit)];
+        let mut contents = bundle_content(store, shown, query, mode, self.content_budget)?;
+        if let Some(max) = self.max_snippets {
+            limit_pieces(&mut contents, max);
+        }
+        Ok(Some(contents))
+    }
+
+    /// Output size limit from --max-output-bytes or --max-output-tokens
+    ///
+    /// Tokens are estimated as four characters and a character is at least
+    /// one byte, so four bytes per token keeps the estimate within the limit.
+    pub fn max_output_bytes(&self) -> Option<usize> {
+        self.max_output_bytes
+            .or(self.max_output_tokens.map(|tokens| tokens.saturating_mul(4)))
+    }
+}
+
+/// Parse --fts-backend, rejecting backends not compiled into this build
+fn parse_stopwords(s: &str) -> Result<Stopwords, String> {
+    s.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn parse_boost(s: &str) -> Result<PathBoost, String> {
+    s.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn parse_lang(s: &str) -> Result<QueryLanguage, String> {
+    s.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn parse_fts_backend(s: &str) -> Result<BM25Backend, String> {
+    let backend: BM25Backend = s.parse().map_err(|e: anyhow::Error| e.to_string())?;
+    backend.ensure_available().map_err(|e| e.to_string())?;
+    Ok(backend)
+}
+
+/// Parse --vector-backend, rejecting backends not compiled into this build
+fn parse_vector_backend(s: &str) -> Result<VectorBackend, String> {
+    let backend: VectorBackend = s.parse().map_err(|e: anyhow::Error| e.to_string())?;
+    backend.ensure_available().map_err(|e| e.to_string())?;
+    Ok(backend)
+}
+
 /// Collection management commands
 #[derive(Subcommand, Debug)]
 pub enum Commands {
@@ -108,12 +287,31 @@ pub enum Commands {
     /// Update index
     Update(UpdateArgs),
 
+    /// Update index, then embed only the documents that changed
+    Sync(SyncArgs),
+
     /// Show index status
     Status(StatusArgs),
 
-    /// Cleanup stale entries
+    /// Complete a query prefix from the words of titles and headings
+    Suggest(SuggestArgs),
+
+    /// Cleanup stale entries and compress plain text in `compress_content` collections
     Cleanup(CleanupArgs),
 
+    /// List or restore purged documents
+    Trash(TrashArgs),
+
+    /// Remove one document from the index
+    #[command(alias = "remove")]
+    Rm(RmArgs),
+
+    /// Check the index for tables out of step with the documents
+    Verify(VerifyArgs),
+
+    /// Rebuild the full-text index from scratch and clear vectors for `qmd embed`
+    Reindex(ReindexArgs),
+
     /// Run as MCP server
     Mcp(McpArgs),
 
@@ -147,7 +345,7 @@ pub enum CollectionCommands {
     /// Add a collection
     Add(CollectionAddArgs),
     /// List collections
-    List,
+    List(CollectionListArgs),
     /// Remove a collection
     Remove(CollectionRemoveArgs),
     /// Rename a collection
@@ -167,11 +365,30 @@ pub struct CollectionAddArgs {
     /// Collection description
     #[arg(short, long)]
     pub description: Option<String>,
+    /// Index as source code (trigram tokenizer, no stemming)
+    #[arg(long)]
+    pub code: bool,
+    /// Stopwords removed from BM25 queries: "english" or a comma-separated list
+    #[arg(long, value_parser = parse_stopwords)]
+    pub stopwords: Option<Stopwords>,
+}
+
+#[derive(Args, Debug)]
+pub struct CollectionListArgs {
+    /// Include each collection's document and chunk counts and index size
+    #[arg(long)]
+    pub with_stats: bool,
 }
 
 #[derive(Args, Debug)]
 pub struct CollectionRemoveArgs {
     pub name: String,
+    /// Also delete the collection's index files from the cache
+    #[arg(long)]
+    pub purge: bool,
+    /// Skip the --purge confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
 }
 
 #[derive(Args, Debug)]
@@ -205,6 +422,8 @@ pub enum ContextCommands {
     Check,
     /// Remove context
     Rm(ContextRemoveArgs),
+    /// Describe directories with the generation model (rule-based without one)
+    Generate(ContextGenerateArgs),
 }
 
 #[derive(Args, Debug)]
@@ -220,6 +439,19 @@ pub struct ContextRemoveArgs {
     pub path: String,
 }
 
+#[derive(Args, Debug)]
+pub struct ContextGenerateArgs {
+    /// Collection to describe (default: all)
+    #[arg(short, long)]
+    pub collection: Option<String>,
+    /// Describe directories down to this many levels below the collection root
+    #[arg(long, default_value_t = 1)]
+    pub depth: usize,
+    /// Replace hand-written descriptions too
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
 #[derive(Args, Debug)]
 pub struct GetArgs {
     /// File path (with optional :line suffix)
@@ -236,6 +468,12 @@ pub struct GetArgs {
     /// Output format: cli, json, ndjson
     #[arg(long, default_value = "cli")]
     pub format: String,
+    /// Hash from an earlier JSON get; print only {unchanged, hash} if it still matches
+    #[arg(long, value_name = "HASH")]
+    pub if_hash: Option<String>,
+    /// Center the window on :line, or on the match the last search recorded
+    #[arg(long)]
+    pub around: bool,
     /// Emit ANEL specification (JSON Schema) instead of executing
     #[arg(long)]
     pub emit_spec: bool,
@@ -269,6 +507,21 @@ pub struct MultiGetArgs {
 pub struct SearchArgs {
     /// Search query
     pub query: String,
+    /// Multiply scores of results under a path prefix (repeatable), e.g. docs/official/:2
+    #[arg(long, value_name = "PREFIX:WEIGHT", value_parser = parse_boost)]
+    pub boost: Vec<PathBoost>,
+    /// Query language (en, zh, mixed), skipping detection
+    #[arg(long, value_parser = parse_lang)]
+    pub lang: Option<QueryLanguage>,
+    /// Show how the query was interpreted: language, expansion rules, FTS queries
+    #[arg(long)]
+    pub explain: bool,
+    /// Like --explain, adding how many documents contain each query term
+    #[arg(long)]
+    pub explain_terms: bool,
+    /// Embed the output's JSON Schema as `$schema` in json/ndjson output
+    #[arg(long)]
+    pub json_schema: bool,
     #[command(flatten)]
     pub format: FormatOptions,
 }
@@ -277,6 +530,9 @@ pub struct SearchArgs {
 pub struct VsearchArgs {
     /// Search query
     pub query: String,
+    /// Overall time budget in milliseconds; on expiry, return what was found so far marked partial
+    #[arg(long, value_name = "MS")]
+    pub timeout: Option<u64>,
     #[command(flatten)]
     pub format: FormatOptions,
 }
@@ -285,6 +541,24 @@ pub struct VsearchArgs {
 pub struct QueryArgs {
     /// Search query
     pub query: String,
+    /// Reranker model for this run, overriding config (prefix with local: or remote:)
+    #[arg(long)]
+    pub rerank_model: Option<String>,
+    /// Query language (en, zh, mixed), skipping detection
+    #[arg(long, value_parser = parse_lang)]
+    pub lang: Option<QueryLanguage>,
+    /// Show how the query was interpreted: language, expansion rules, FTS queries
+    #[arg(long)]
+    pub explain: bool,
+    /// Overall time budget in milliseconds; on expiry, return what was found so far marked partial
+    #[arg(long, value_name = "MS")]
+    pub timeout: Option<u64>,
+    /// Most query variants to search, counting the original (overrides search.max_expansions)
+    #[arg(long, value_name = "N")]
+    pub max_expansions: Option<usize>,
+    /// Most candidates sent to the reranker in one call (overrides search.rerank_batch_size)
+    #[arg(long, value_name = "N")]
+    pub rerank_batch_size: Option<usize>,
     #[command(flatten)]
     pub format: FormatOptions,
 }
@@ -316,6 +590,9 @@ pub struct UpdateArgs {
     /// Collection to update (default: all)
     #[arg(short, long)]
     pub collection: Option<String>,
+    /// Fail when any file could not be indexed
+    #[arg(long)]
+    pub strict: bool,
     /// Output format: cli, json, ndjson
     #[arg(long, default_value = "cli")]
     pub format: String,
@@ -327,6 +604,19 @@ pub struct UpdateArgs {
     pub dry_run: bool,
 }
 
+#[derive(Args, Debug)]
+pub struct SyncArgs {
+    /// Output format: cli, json
+    #[arg(long, default_value = "cli")]
+    pub format: String,
+    /// Emit ANEL specification (JSON Schema) instead of executing
+    #[arg(long)]
+    pub emit_spec: bool,
+    /// Dry-run mode: validate parameters without executing
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
 #[derive(Args, Debug)]
 pub struct StatusArgs {
     /// Show detailed status
@@ -338,6 +628,30 @@ pub struct StatusArgs {
     /// Output format: cli, json, ndjson
     #[arg(long, default_value = "cli")]
     pub format: String,
+    /// Also count soft-deleted (inactive) documents, for debugging
+    #[arg(long)]
+    pub include_inactive: bool,
+    /// Size report per collection: disk usage, extensions, largest documents
+    #[arg(long)]
+    pub report: bool,
+    /// Emit ANEL specification (JSON Schema) instead of executing
+    #[arg(long)]
+    pub emit_spec: bool,
+    /// Dry-run mode: validate parameters without executing
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SuggestArgs {
+    /// Start of the word to complete
+    pub prefix: String,
+    /// Maximum number of suggestions
+    #[arg(short, long, default_value = "10")]
+    pub limit: usize,
+    /// Output format: cli, json
+    #[arg(long, default_value = "cli")]
+    pub format: String,
     /// Emit ANEL specification (JSON Schema) instead of executing
     #[arg(long)]
     pub emit_spec: bool,
@@ -357,6 +671,9 @@ pub struct CleanupArgs {
     /// Collection to clean up
     #[arg(short, long)]
     pub collection: Option<String>,
+    /// Permanently delete soft-deleted documents; `qmd trash restore` can bring them back
+    #[arg(long)]
+    pub purge: bool,
     /// Output format: cli, json, ndjson
     #[arg(long, default_value = "cli")]
     pub format: String,
@@ -365,6 +682,90 @@ pub struct CleanupArgs {
     pub emit_spec: bool,
 }
 
+#[derive(Args, Debug)]
+pub struct RmArgs {
+    /// Document to remove: collection/path or qmd://collection/path
+    pub path: String,
+    /// Delete the row, and its text and vectors unless another path shares
+    /// them, instead of soft-deleting it
+    #[arg(long)]
+    pub hard: bool,
+    /// Output format: cli, json
+    #[arg(long, default_value = "cli")]
+    pub format: String,
+    /// Emit ANEL specification (JSON Schema) instead of executing
+    #[arg(long)]
+    pub emit_spec: bool,
+    /// Dry-run mode: validate parameters without executing
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Collection to check (default: all)
+    #[arg(short, long)]
+    pub collection: Option<String>,
+    /// Repair what is found: rebuild the full-text index, delete orphan
+    /// vectors and recount vocabulary and document sizes
+    #[arg(long)]
+    pub fix: bool,
+    /// Output format: cli, json
+    #[arg(long, default_value = "cli")]
+    pub format: String,
+    /// Emit ANEL specification (JSON Schema) instead of executing
+    #[arg(long)]
+    pub emit_spec: bool,
+    /// Dry-run mode: validate parameters without executing
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ReindexArgs {
+    /// Collection to reindex (default: all)
+    #[arg(short, long)]
+    pub collection: Option<String>,
+    /// Output format: cli, json
+    #[arg(long, default_value = "cli")]
+    pub format: String,
+    /// Emit ANEL specification (JSON Schema) instead of executing
+    #[arg(long)]
+    pub emit_spec: bool,
+    /// Dry-run mode: validate parameters without executing
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct TrashArgs {
+    #[command(subcommand)]
+    pub command: TrashCommands,
+    /// Output format: cli, json
+    #[arg(long, default_value = "cli")]
+    pub format: String,
+    /// Emit ANEL specification (JSON Schema) instead of executing
+    #[arg(long)]
+    pub emit_spec: bool,
+    /// Dry-run mode: validate parameters without executing
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TrashCommands {
+    /// List purge operations that can still be restored, newest first
+    List,
+    /// Put a purge operation's documents back into the index
+    Restore(TrashRestoreArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct TrashRestoreArgs {
+    /// Operation id from `qmd trash list`
+    pub id: String,
+}
+
 #[derive(Args, Debug)]
 pub struct McpArgs {
     /// Transport: stdio, sse
@@ -382,10 +783,15 @@ pub struct McpArgs {
     /// Dry-run mode: validate parameters without executing
     #[arg(long)]
     pub dry_run: bool,
+    /// Warm indexes and load models before serving
+    #[arg(long)]
+    pub preload: bool,
 }
 
 #[derive(Args, Debug)]
 pub struct ServerArgs {
+    #[command(subcommand)]
+    pub command: Option<ServerCommands>,
     /// Host to bind to
     #[arg(long, default_value = "0.0.0.0")]
     pub host: String,
@@ -398,12 +804,59 @@ pub struct ServerArgs {
     /// Enable API key authentication
     #[arg(long)]
     pub auth: bool,
-    /// Comma-separated list of API keys
+    /// Comma-separated list of API keys; `key=docs+notes` limits a key to those collections.
+    /// Visible in process listings; prefer `server.api_keys_file` and `qmd server keys`
     #[arg(long)]
     pub api_keys: Option<String>,
     /// Comma-separated list of whitelisted IPs (skip auth)
     #[arg(long)]
     pub whitelist_ips: Option<String>,
+    /// Disable NDJSON audit records for /mcp requests
+    #[arg(long)]
+    pub no_mcp_audit: bool,
+    /// Warm indexes and load models before accepting traffic
+    #[arg(long)]
+    pub preload: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ServerCommands {
+    /// Manage the API keys in `server.api_keys_file`
+    Keys(ServerKeysArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ServerKeysArgs {
+    #[command(subcommand)]
+    pub command: ServerKeysCommands,
+    /// Output format: cli, json
+    #[arg(long, default_value = "cli")]
+    pub format: String,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ServerKeysCommands {
+    /// Generate a key and print it once; only its hash is stored
+    Add(ServerKeyAddArgs),
+    /// Remove a key by name
+    Revoke(ServerKeyRevokeArgs),
+    /// List key names, allowed collections and creation times
+    List,
+}
+
+#[derive(Args, Debug)]
+pub struct ServerKeyAddArgs {
+    /// Name recorded in audit logs and used to revoke the key
+    pub name: String,
+    /// Comma-separated collections the key may read (default: all)
+    #[arg(long)]
+    pub collections: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ServerKeyRevokeArgs {
+    /// Name of the key to remove
+    pub name: String,
 }
 
 #[derive(Args, Debug)]
@@ -2,6 +2,7 @@ use crate::anel::AnelSpec;
 use crate::cli::MultiGetArgs;
 use crate::config::Config;
 use anyhow::{Context, Result};
+use crate::store::path::portable_glob;
 use glob::glob;
 use std::fs;
 use std::path::PathBuf;
@@ -30,7 +31,7 @@ pub fn handle(
     }
 
     // Expand the glob pattern
-    let entries = glob(pattern)
+    let entries = glob(&portable_glob(pattern)?)
         .with_context(|| format!("Invalid glob pattern: {}", pattern))?;
 
     let mut count = 0;
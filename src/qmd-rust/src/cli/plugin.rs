@@ -1,9 +1,9 @@
 // Plugin CLI commands
 // Provides plugin management commands: install, list, remove, info
 
-use crate::cli::{Cli, PluginArgs, PluginCommands};
+use crate::cli::{PluginArgs, PluginCommands};
 use crate::config::Config;
-use crate::plugin::{PluginInfo, PluginManager};
+use crate::plugin::PluginManager;
 use anyhow::Result;
 use std::path::PathBuf;
 
@@ -102,10 +102,9 @@ fn show_plugins_dir(manager: &PluginManager) -> Result<()> {
 }
 
 /// Get plugins directory from config
-fn get_plugins_dir(config: &Config) -> PathBuf {
-    let cache_dir = dirs::cache_dir()
+fn get_plugins_dir(_config: &Config) -> PathBuf {
+    dirs::cache_dir()
         .unwrap_or_else(|| PathBuf::from("~/.cache"))
         .join("qmd")
-        .join("plugins");
-    cache_dir
+        .join("plugins")
 }
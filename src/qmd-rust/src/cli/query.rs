@@ -1,8 +1,11 @@
 use crate::anel::AnelSpec;
 use crate::cli::{QueryArgs, FormatOptions};
-use crate::store::Store;
+use crate::store::deadline::Deadline;
+use crate::store::lang::resolve_language;
+use crate::store::{apply_token_budget, dedupe_by_hash, Store};
 use crate::llm::Router;
-use crate::formatter::Format;
+use crate::formatter::{Format, SearchMeta};
+use crate::cli::suggest::hint_did_you_mean;
 use anyhow::Result;
 
 /// Handle query command - hybrid search with reranking
@@ -26,9 +29,23 @@ pub fn handle(
         println!("[DRY-RUN] Would execute query with:");
         println!("  query: {}", query);
         println!("  limit: {}", options.limit);
+        println!("  offset: {}", options.offset);
         println!("  min_score: {}", options.min_score);
         println!("  collection: {:?}", options.collection);
         println!("  search_all: {}", options.search_all);
+        println!("  really_all: {}", options.really_all);
+        println!("  max_tokens: {:?}", cmd.format.max_tokens);
+        println!("  include_content: {:?}", cmd.format.include_content);
+        println!("  rerank_model: {:?}", llm.reranker_model());
+        println!("  lang: {:?}", cmd.lang);
+        println!("  timeout: {:?}", cmd.timeout);
+        println!("  max_expansions: {:?}", cmd.max_expansions);
+        println!("  rerank_batch_size: {:?}", cmd.rerank_batch_size);
+        println!("  dedupe_by_hash: {}", cmd.format.dedupes(store));
+        println!("  group_by: {:?}", cmd.format.group_by);
+        println!("  sort_by: {:?}{}", cmd.format.sort_by, if cmd.format.reverse { " (reversed)" } else { "" });
+        println!("  max_snippets: {:?}", cmd.format.max_snippets);
+        println!("  max_output_bytes: {:?}", cmd.format.max_output_bytes());
         return Ok(());
     }
 
@@ -36,13 +53,50 @@ pub fn handle(
     let rt = tokio::runtime::Runtime::new()?;
 
     // Perform hybrid search with LLM reranking
-    let results = rt.block_on(async {
-        store.hybrid_search(query, options.clone(), llm).await
+    let deadline = Deadline::new(cmd.timeout);
+    let (lang, _) = resolve_language(query, cmd.lang);
+    let dedupe = cmd.format.dedupes(store);
+    let fetch = cmd.format.fetch_options(&options, dedupe);
+    let outcome = rt.block_on(async {
+        store.hybrid_search_within(query, fetch, llm, lang, &deadline).await
     })?;
 
+    // Fold copies after fusion and reranking, before the limit applies
+    let results = if dedupe { dedupe_by_hash(outcome.results) } else { outcome.results };
+    let results = apply_token_budget(results, cmd.format.max_tokens);
+    let results = cmd.format.skip_offset(results);
+    let results = cmd.format.sort_results(store, results)?;
+
     // Format and display results
     let formatter = Format::from_string(&cmd.format.format);
-    formatter.format_search_results(&results, options.limit)?;
+    let warnings = store.search_warnings(&options);
+    let contents = cmd.format.bundle_content(store, &results, query)?;
+    let explain = if cmd.explain {
+        let expansions = llm.expand_query_in(query, lang)?;
+        let mut explain = store.explain_query(query, expansions, &options, cmd.lang)?;
+        explain.candidate_cache = outcome.cache.clone();
+        Some(explain)
+    } else {
+        None
+    };
+    let meta = SearchMeta {
+        language: Some(lang),
+        explain: explain.as_ref(),
+        skipped: &outcome.skipped,
+        degraded: &outcome.degraded,
+        timings: outcome.timings.as_ref(),
+        group_by: cmd.format.group_by,
+        max_output_bytes: cmd.format.max_output_bytes(),
+        offset: cmd.format.offset,
+        ..SearchMeta::default()
+    };
+    formatter.format_search_results_with_meta(&results, options.limit, &warnings, contents.as_deref(), &meta)?;
+    hint_did_you_mean(store, query, &results);
+
+    // Let a following `get --around` center on these hits
+    if let Err(e) = store.record_match_positions(&results) {
+        log::warn!("Failed to record match positions: {}", e);
+    }
 
     Ok(())
 }
@@ -50,8 +104,10 @@ pub fn handle(
 fn convert_options(cmd: &FormatOptions) -> crate::store::SearchOptions {
     crate::store::SearchOptions {
         limit: cmd.limit,
+        offset: cmd.offset,
         min_score: cmd.min_score,
         collection: cmd.collection.clone(),
-        search_all: cmd.all,
+        search_all: cmd.all || cmd.really_all,
+        really_all: cmd.really_all,
     }
 }
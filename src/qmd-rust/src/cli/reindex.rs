@@ -0,0 +1,40 @@
+use crate::anel::AnelSpec;
+use crate::cli::ReindexArgs;
+use crate::store::Store;
+use anyhow::Result;
+
+/// Handle reindex command - rebuild the full-text index and clear vectors
+pub fn handle(cmd: &ReindexArgs, store: &Store) -> Result<()> {
+    // Handle --emit-spec: output ANEL specification and exit
+    if cmd.emit_spec {
+        let spec = AnelSpec::reindex();
+        println!("{}", serde_json::to_string_pretty(&spec)?);
+        return Ok(());
+    }
+
+    // Handle --dry-run: validate parameters without executing
+    if cmd.dry_run {
+        println!("[DRY-RUN] Would execute reindex with:");
+        println!("  collection: {:?}", cmd.collection);
+        return Ok(());
+    }
+
+    let reports = store.reindex(cmd.collection.as_deref())?;
+
+    if cmd.format == "json" {
+        let output = serde_json::json!({ "collections": reports });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    for report in &reports {
+        println!(
+            "{}: indexed {} documents, cleared {} vectors",
+            report.collection, report.documents_indexed, report.vectors_cleared
+        );
+    }
+    if reports.iter().any(|r| r.vectors_cleared > 0) {
+        println!("\nRun `qmd embed` to regenerate the vectors");
+    }
+    Ok(())
+}
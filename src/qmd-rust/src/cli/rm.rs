@@ -0,0 +1,73 @@
+use crate::anel::AnelSpec;
+use crate::cli::RmArgs;
+use crate::store::{path, Store};
+use anyhow::Result;
+
+/// Handle rm command - remove one document from the index
+pub fn handle(cmd: &RmArgs, store: &Store) -> Result<()> {
+    // Handle --emit-spec: output ANEL specification and exit
+    if cmd.emit_spec {
+        let spec = AnelSpec::remove();
+        println!("{}", serde_json::to_string_pretty(&spec)?);
+        return Ok(());
+    }
+
+    let (collection, relative) = parse_target(&cmd.path)?;
+
+    // Handle --dry-run: validate parameters without executing
+    if cmd.dry_run {
+        println!("[DRY-RUN] Would execute rm with:");
+        println!("  collection: {}", collection);
+        println!("  path: {}", relative);
+        println!("  hard: {}", cmd.hard);
+        return Ok(());
+    }
+
+    let removed = store.remove_document(&collection, &relative, cmd.hard)?;
+
+    if cmd.format == "json" {
+        let output = serde_json::json!({
+            "collection": collection,
+            "path": relative,
+            "removed": removed,
+            "hard": cmd.hard,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if !removed {
+        println!("Already removed: {}/{}", collection, relative);
+    } else if cmd.hard {
+        println!("Deleted {}/{}", collection, relative);
+    } else {
+        println!("Removed {}/{} (purge with `qmd cleanup --purge`)", collection, relative);
+    }
+    Ok(())
+}
+
+/// Split `collection/path` or `qmd://collection/path` into its parts
+fn parse_target(target: &str) -> Result<(String, String)> {
+    let (collection, relative) = if path::is_virtual_path(target) {
+        let parsed = path::parse_virtual_path(target)
+            .ok_or_else(|| anyhow::anyhow!("Invalid virtual path: {}", target))?;
+        (parsed.collection, parsed.path)
+    } else {
+        let (collection, relative) = target.split_once('/').unwrap_or((target, ""));
+        (collection.to_string(), relative.to_string())
+    };
+    if collection.is_empty() || relative.is_empty() {
+        anyhow::bail!("Expected <collection>/<path>, got: {}", target);
+    }
+    Ok((collection, relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(parse_target("docs/notes/a.md").unwrap(), ("docs".to_string(), "notes/a.md".to_string()));
+        assert_eq!(parse_target("qmd://docs/a.md").unwrap(), ("docs".to_string(), "a.md".to_string()));
+        assert!(parse_target("docs").is_err());
+        assert!(parse_target("docs/").is_err());
+    }
+}
@@ -1,7 +1,9 @@
 use crate::anel::AnelSpec;
 use crate::cli::{SearchArgs, FormatOptions};
-use crate::store::Store;
-use crate::formatter::Format;
+use crate::store::lang::resolve_language;
+use crate::store::{apply_path_boosts, apply_token_budget, dedupe_by_hash, Store};
+use crate::formatter::{Format, SearchMeta};
+use crate::cli::suggest::hint_did_you_mean;
 use anyhow::Result;
 
 /// Handle search command - BM25 full-text search
@@ -24,18 +26,67 @@ pub fn handle(
         println!("[DRY-RUN] Would execute search with:");
         println!("  query: {}", query);
         println!("  limit: {}", options.limit);
+        println!("  offset: {}", options.offset);
         println!("  min_score: {}", options.min_score);
         println!("  collection: {:?}", options.collection);
         println!("  search_all: {}", options.search_all);
+        println!("  really_all: {}", options.really_all);
+        println!("  boost: {:?}", cmd.boost);
+        println!("  lang: {:?}", cmd.lang);
+        println!("  explain_terms: {}", cmd.explain_terms);
+        println!("  json_schema: {}", cmd.json_schema);
+        println!("  max_tokens: {:?}", cmd.format.max_tokens);
+        println!("  include_content: {:?}", cmd.format.include_content);
+        println!("  dedupe_by_hash: {}", cmd.format.dedupes(store));
+        println!("  group_by: {:?}", cmd.format.group_by);
+        println!("  sort_by: {:?}{}", cmd.format.sort_by, if cmd.format.reverse { " (reversed)" } else { "" });
+        println!("  max_snippets: {:?}", cmd.format.max_snippets);
+        println!("  max_output_bytes: {:?}", cmd.format.max_output_bytes());
         return Ok(());
     }
 
     // Perform search
-    let results = store.bm25_search(query, options.clone())?;
+    let (lang, _) = resolve_language(query, cmd.lang);
+    let dedupe = cmd.format.dedupes(store);
+    let results = store.bm25_search_in(query, cmd.format.fetch_options(&options, dedupe), lang)?;
+    let results = apply_path_boosts(results, &cmd.boost);
+    let results = if dedupe { dedupe_by_hash(results) } else { results };
+
+    let results = apply_token_budget(results, cmd.format.max_tokens);
+    let results = cmd.format.skip_offset(results);
+
+    let results = cmd.format.sort_results(store, results)?;
 
     // Format and display results
     let formatter = Format::from_string(&cmd.format.format);
-    formatter.format_search_results(&results, options.limit)?;
+    let warnings = store.search_warnings(&options);
+    let contents = cmd.format.bundle_content(store, &results, query)?;
+    let schema = cmd.json_schema.then(|| AnelSpec::search().output_schema);
+    let explain = if cmd.explain || cmd.explain_terms {
+        let mut explain = store.explain_query(query, Vec::new(), &options, cmd.lang)?;
+        if cmd.explain_terms {
+            explain.terms = store.term_frequencies(&explain.fts_queries)?;
+        }
+        Some(explain)
+    } else {
+        None
+    };
+    let meta = SearchMeta {
+        language: Some(lang),
+        explain: explain.as_ref(),
+        schema: schema.as_ref(),
+        group_by: cmd.format.group_by,
+        max_output_bytes: cmd.format.max_output_bytes(),
+        offset: cmd.format.offset,
+        ..SearchMeta::default()
+    };
+    formatter.format_search_results_with_meta(&results, options.limit, &warnings, contents.as_deref(), &meta)?;
+    hint_did_you_mean(store, query, &results);
+
+    // Let a following `get --around` center on these hits
+    if let Err(e) = store.record_match_positions(&results) {
+        log::warn!("Failed to record match positions: {}", e);
+    }
 
     Ok(())
 }
@@ -43,8 +94,10 @@ pub fn handle(
 fn convert_options(cmd: &FormatOptions) -> crate::store::SearchOptions {
     crate::store::SearchOptions {
         limit: cmd.limit,
+        offset: cmd.offset,
         min_score: cmd.min_score,
         collection: cmd.collection.clone(),
-        search_all: cmd.all,
+        search_all: cmd.all || cmd.really_all,
+        really_all: cmd.really_all,
     }
 }
@@ -0,0 +1,62 @@
+use crate::cli::{ServerKeysArgs, ServerKeysCommands};
+use crate::config::Config;
+use crate::server::keys::KeyFile;
+use anyhow::{Context, Result};
+
+/// Handle `qmd server keys`
+pub fn handle_keys(cmd: &ServerKeysArgs, config: &Config) -> Result<()> {
+    let path = config
+        .server
+        .api_keys_file
+        .as_deref()
+        .context("No API keys file configured; set server.api_keys_file in the config")?;
+    let mut file = KeyFile::load(path)?;
+
+    match &cmd.command {
+        ServerKeysCommands::Add(args) => {
+            let collections = args.collections.as_ref().map(|list| {
+                list.split(',')
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+                    .collect()
+            });
+            let key = file.add(&args.name, collections)?;
+            file.save(path)?;
+            if cmd.format == "json" {
+                println!("{}", serde_json::json!({ "name": args.name, "key": key }));
+            } else {
+                println!("Added API key '{}'. It is not stored and will not be shown again:", args.name);
+                println!("{}", key);
+            }
+        }
+        ServerKeysCommands::Revoke(args) => {
+            file.revoke(&args.name)?;
+            file.save(path)?;
+            if cmd.format == "json" {
+                println!("{}", serde_json::json!({ "revoked": args.name }));
+            } else {
+                println!("Revoked API key '{}'", args.name);
+            }
+        }
+        ServerKeysCommands::List => {
+            if cmd.format == "json" {
+                let keys: Vec<_> = file
+                    .keys
+                    .iter()
+                    .map(|k| serde_json::json!({ "name": k.name, "collections": k.collections, "created_at": k.created_at }))
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "keys": keys }))?);
+            } else if file.keys.is_empty() {
+                println!("No API keys");
+            } else {
+                println!("{:<24} {:<28} Collections", "Name", "Created");
+                println!("{}", "-".repeat(80));
+                for key in &file.keys {
+                    let collections = key.collections.as_ref().map_or("(all)".to_string(), |c| c.join(", "));
+                    println!("{:<24} {:<28} {}", key.name, key.created_at, collections);
+                }
+            }
+        }
+    }
+    Ok(())
+}
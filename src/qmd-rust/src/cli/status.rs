@@ -1,5 +1,7 @@
 use crate::anel::AnelSpec;
+use crate::cli::ls::format_bytes;
 use crate::cli::StatusArgs;
+use crate::store::report::CollectionReport;
 use crate::store::Store;
 use anyhow::Result;
 
@@ -20,25 +22,165 @@ pub fn handle(
         println!("[DRY-RUN] Would execute status with:");
         println!("  verbose: {}", cmd.verbose);
         println!("  collection: {:?}", cmd.collection);
+        println!("  include_inactive: {}", cmd.include_inactive);
+        println!("  report: {}", cmd.report);
         return Ok(());
     }
 
-    println!("QMD Index Status");
-    println!("{}", "=".repeat(50));
+    if cmd.report {
+        return print_reports(cmd, store);
+    }
 
     let stats = store.get_stats()?;
 
+    if cmd.format == "json" {
+        let mut names: Vec<&String> = stats.collection_stats.keys().collect();
+        names.sort();
+        let collections: Vec<_> = names
+            .into_iter()
+            .map(|name| {
+                let mut entry = serde_json::json!({
+                    "name": name,
+                    "documents": stats.collection_stats[name],
+                    "tokens": stats.collection_tokens.get(name).copied().unwrap_or(0),
+                    "index_bytes": stats.collection_index_bytes.get(name).copied().unwrap_or(0),
+                });
+                if cmd.include_inactive {
+                    entry["inactive"] = stats.collection_inactive.get(name).copied().unwrap_or(0).into();
+                }
+                entry
+            })
+            .collect();
+        let mut output = serde_json::json!({
+            "collections": collections,
+            "documents": stats.document_count,
+            "indexed": stats.indexed_count,
+            "pending": stats.pending_count,
+            "chunks": stats.chunk_count,
+            "tokens": stats.token_count,
+            "index_bytes": stats.index_bytes,
+        });
+        if cmd.include_inactive {
+            output["inactive"] = stats.inactive_count.into();
+        }
+        if let Some(error) = store.vector_backend_error() {
+            output["vector_backend_error"] = serde_json::to_value(&error)?;
+        }
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!("QMD Index Status");
+    println!("{}", "=".repeat(50));
+
     println!("\nCollections: {}", stats.collection_count);
     println!("Documents: {}", stats.document_count);
     println!("Indexed: {}", stats.indexed_count);
     println!("Pending: {}", stats.pending_count);
+    println!("Tokens (est.): {}", stats.token_count);
+    println!("Index size: {}", format_bytes(stats.index_bytes as i64));
+    if cmd.include_inactive {
+        println!("Inactive (soft-deleted): {}", stats.inactive_count);
+    }
+    if let Some(error) = store.vector_backend_error() {
+        println!("\nVector search unavailable: {}", error.message);
+        for hint in &error.recovery_hints {
+            println!("  {}", hint.message);
+        }
+    }
 
     if cmd.verbose {
         println!("\nDetailed Statistics:");
         for (name, count) in &stats.collection_stats {
-            println!("  {}: {} documents", name, count);
+            let tokens = stats.collection_tokens.get(name).copied().unwrap_or(0);
+            let index_bytes = stats.collection_index_bytes.get(name).copied().unwrap_or(0);
+            print!(
+                "  {}: {} documents, ~{} tokens, {} on disk",
+                name, count, tokens, format_bytes(index_bytes as i64)
+            );
+            if cmd.include_inactive {
+                print!(", {} inactive", stats.collection_inactive.get(name).copied().unwrap_or(0));
+            }
+            println!();
         }
     }
 
     Ok(())
 }
+
+/// `status --report`: size breakdown for one collection or all of them
+fn print_reports(cmd: &StatusArgs, store: &Store) -> Result<()> {
+    let names: Vec<String> = match &cmd.collection {
+        Some(name) => vec![name.clone()],
+        None => store.get_collections().iter().map(|c| c.name.clone()).collect(),
+    };
+    let reports = names
+        .iter()
+        .map(|name| store.collection_report(name))
+        .collect::<Result<Vec<_>>>()?;
+
+    if cmd.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "collections": reports }))?);
+        return Ok(());
+    }
+
+    for report in &reports {
+        print_report_table(report);
+    }
+    Ok(())
+}
+
+fn print_report_table(report: &CollectionReport) {
+    println!("Collection: {}", report.collection);
+    println!("{}", "=".repeat(50));
+    println!("  Index size:        {}", format_bytes(report.index_bytes as i64));
+    println!("  Documents:         {}", report.document_count);
+    println!("  Content:           {}", format_bytes(report.content_bytes as i64));
+    println!(
+        "  Average document:  {} (~{} tokens)",
+        format_bytes(report.average_document_bytes as i64),
+        report.average_document_tokens
+    );
+    println!(
+        "  Chunks / vectors:  {} / {} (~{} of embeddings)",
+        report.chunk_count,
+        report.vector_count,
+        format_bytes(report.vector_bytes_estimate as i64)
+    );
+    let compression = &report.compression;
+    if compression.compressed_documents > 0 {
+        println!(
+            "  Compression:       {:.1}x, {} -> {} ({} compressed, {} plain)",
+            compression.ratio,
+            format_bytes(compression.original_bytes as i64),
+            format_bytes(compression.stored_bytes as i64),
+            compression.compressed_documents,
+            compression.plain_documents
+        );
+    }
+
+    if !report.extensions.is_empty() {
+        println!("\n  {:<12} {:>8} {:>8}", "Extension", "Docs", "Size");
+        for ext in &report.extensions {
+            println!(
+                "  {:<12} {:>8} {:>8}",
+                ext.extension,
+                ext.documents,
+                format_bytes(ext.bytes as i64)
+            );
+        }
+    }
+
+    if !report.largest_documents.is_empty() {
+        println!("\n  Largest documents:");
+        for doc in &report.largest_documents {
+            println!(
+                "  {:>8}  {:>7}  {}",
+                format_bytes(doc.bytes as i64),
+                format!("~{}t", doc.tokens),
+                doc.path
+            );
+        }
+    }
+    println!();
+}
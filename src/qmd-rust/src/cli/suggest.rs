@@ -0,0 +1,55 @@
+use crate::anel::AnelSpec;
+use crate::cli::SuggestArgs;
+use crate::store::{SearchResult, Store};
+use anyhow::Result;
+
+/// Handle suggest command - complete a prefix from the index vocabulary
+pub fn handle(cmd: &SuggestArgs, store: &Store) -> Result<()> {
+    // Handle --emit-spec: output ANEL specification and exit
+    if cmd.emit_spec {
+        let spec = AnelSpec::suggest();
+        println!("{}", serde_json::to_string_pretty(&spec)?);
+        return Ok(());
+    }
+
+    // Handle --dry-run: validate parameters without executing
+    if cmd.dry_run {
+        println!("[DRY-RUN] Would execute suggest with:");
+        println!("  prefix: {}", cmd.prefix);
+        println!("  limit: {}", cmd.limit);
+        return Ok(());
+    }
+
+    let suggestions = store.suggest(&cmd.prefix, cmd.limit)?;
+
+    if cmd.format == "json" {
+        let output = serde_json::json!({
+            "prefix": cmd.prefix,
+            "suggestions": suggestions,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if suggestions.is_empty() {
+        println!("No suggestions for '{}'", cmd.prefix);
+        return Ok(());
+    }
+    for suggestion in &suggestions {
+        let noun = if suggestion.documents == 1 { "document" } else { "documents" };
+        println!("{} ({} {})", suggestion.term, suggestion.documents, noun);
+    }
+    Ok(())
+}
+
+/// Point at a corrected query on stderr when a search found nothing
+pub fn hint_did_you_mean(store: &Store, query: &str, results: &[SearchResult]) {
+    if !results.is_empty() {
+        return;
+    }
+    match store.did_you_mean(query) {
+        Ok(Some(corrected)) => eprintln!("Did you mean: {}?", corrected),
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to look up spelling suggestions: {}", e),
+    }
+}
@@ -0,0 +1,81 @@
+use crate::anel::AnelSpec;
+use crate::cli::SyncArgs;
+use crate::cli::embed::embed_hashes_async;
+use crate::llm::Router;
+use crate::store::Store;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Outcome of a sync run
+#[derive(Debug, Default, Serialize)]
+pub struct SyncSummary {
+    pub documents_changed: usize,
+    pub documents_embedded: usize,
+    pub chunks_embedded: usize,
+}
+
+/// Handle sync command - update the index, then embed only what changed
+pub fn handle(
+    cmd: &SyncArgs,
+    store: &Store,
+    llm: &Router,
+) -> Result<()> {
+    // Handle --emit-spec: output ANEL specification and exit
+    if cmd.emit_spec {
+        let spec = AnelSpec::sync();
+        println!("{}", serde_json::to_string_pretty(&spec)?);
+        return Ok(());
+    }
+
+    // Handle --dry-run: validate parameters without executing
+    if cmd.dry_run {
+        println!("[DRY-RUN] Would execute sync with:");
+        println!("  format: {}", cmd.format);
+        return Ok(());
+    }
+
+    let rt = tokio::runtime::Runtime::new()?;
+    let summary = rt.block_on(sync_async(store, llm))?;
+
+    if cmd.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        println!(
+            "Synced {} changed documents ({} chunks embedded)",
+            summary.documents_changed, summary.chunks_embedded
+        );
+    }
+
+    Ok(())
+}
+
+/// Run `update_index` and embed exactly the content hashes it changed
+pub async fn sync_async(store: &Store, llm: &Router) -> Result<SyncSummary> {
+    let changed = store.update_index_tracked()?;
+
+    let mut by_collection: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for doc in &changed {
+        let hashes = by_collection.entry(doc.collection.as_str()).or_default();
+        if !hashes.contains(&doc.hash) {
+            hashes.push(doc.hash.clone());
+        }
+    }
+
+    let mut summary = SyncSummary {
+        documents_changed: changed.len(),
+        ..Default::default()
+    };
+
+    if !llm.has_embedder() {
+        log::warn!("No embedder available, skipping embedding");
+        return Ok(summary);
+    }
+
+    for (collection, hashes) in &by_collection {
+        summary.chunks_embedded += embed_hashes_async(store, collection, llm, hashes).await?;
+        summary.documents_embedded += hashes.len();
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,113 @@
+use crate::anel::AnelSpec;
+use crate::cli::{TrashArgs, TrashCommands, TrashRestoreArgs};
+use crate::config::Config;
+use crate::store::trash::{list_trash, trash_manifest};
+use crate::store::Store;
+use anyhow::Result;
+
+/// Handle trash commands
+pub fn handle(cmd: &TrashArgs, config: &mut Config) -> Result<()> {
+    // Handle --emit-spec: output ANEL specification and exit
+    if cmd.emit_spec {
+        let spec = AnelSpec::trash();
+        println!("{}", serde_json::to_string_pretty(&spec)?);
+        return Ok(());
+    }
+
+    // Handle --dry-run: validate parameters without executing
+    if cmd.dry_run {
+        println!("[DRY-RUN] Would execute trash with:");
+        println!("  format: {}", cmd.format);
+        match &cmd.command {
+            TrashCommands::List => println!("  action: list"),
+            TrashCommands::Restore(args) => {
+                println!("  action: restore");
+                println!("  id: {}", args.id);
+            }
+        }
+        return Ok(());
+    }
+
+    match &cmd.command {
+        TrashCommands::List => list(config, &cmd.format),
+        TrashCommands::Restore(args) => restore(args, config, &cmd.format),
+    }
+}
+
+/// List restorable purge operations
+fn list(config: &Config, format: &str) -> Result<()> {
+    let operations = list_trash(config)?;
+
+    if format == "json" {
+        let output = serde_json::json!({ "operations": operations });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if operations.is_empty() {
+        println!("Trash is empty");
+        return Ok(());
+    }
+    println!(
+        "{:<24} {:<28} {:<26} {:>6} {:>8} Collections",
+        "ID", "Operation", "Created", "Docs", "Vectors"
+    );
+    println!("{}", "-".repeat(110));
+    for op in &operations {
+        println!(
+            "{:<24} {:<28} {:<26} {:>6} {:>8} {}",
+            op.id,
+            op.operation,
+            op.created_at,
+            op.documents,
+            op.vectors,
+            op.collections.join(", ")
+        );
+    }
+    println!("\nTrash is kept for {} days", config.trash.retention_days);
+    Ok(())
+}
+
+/// Restore one purge operation, re-adding a removed collection first
+fn restore(args: &TrashRestoreArgs, config: &mut Config, format: &str) -> Result<()> {
+    let manifest = trash_manifest(config, &args.id)?;
+
+    if let Some(collection) = &manifest.collection_config {
+        if config.collections.iter().all(|c| c.name != collection.name) {
+            *config = Config::update(|latest| {
+                if latest.collections.iter().all(|c| c.name != collection.name) {
+                    latest.collections.push(collection.clone());
+                }
+                Ok(())
+            })?;
+            if format != "json" {
+                println!("Collection '{}' added back to the config", collection.name);
+            }
+        }
+    }
+    for name in &manifest.collections {
+        if config.collections.iter().all(|c| c.name != *name) {
+            anyhow::bail!(
+                "Collection '{}' is no longer configured; add it again before restoring {}",
+                name,
+                args.id
+            );
+        }
+    }
+
+    let store = Store::new(config)?;
+    let summary = store.restore_trash(&args.id)?;
+
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+    println!(
+        "Restored {} documents ({} vectors) from {}",
+        summary.restored, summary.manifest.vectors, summary.manifest.id
+    );
+    for path in &summary.skipped {
+        println!("  Skipped {}: an active document already exists", path);
+    }
+    Ok(())
+}
@@ -2,17 +2,21 @@ use crate::anel::AnelSpec;
 use crate::cli::UpdateArgs;
 use crate::store::Store;
 use anyhow::Result;
+use std::process::ExitCode;
+
+/// Exit code when some files could not be indexed but the rest were
+pub const PARTIAL_FAILURE_EXIT_CODE: u8 = 3;
 
 /// Handle update command - refresh index
 pub fn handle(
     cmd: &UpdateArgs,
     store: &Store,
-) -> Result<()> {
+) -> Result<ExitCode> {
     // Handle --emit-spec: output ANEL specification and exit
     if cmd.emit_spec {
         let spec = AnelSpec::update();
         println!("{}", serde_json::to_string_pretty(&spec)?);
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
     // Handle --dry-run: validate parameters without executing
@@ -20,7 +24,8 @@ pub fn handle(
         println!("[DRY-RUN] Would execute update with:");
         println!("  pull: {}", cmd.pull);
         println!("  collection: {:?}", cmd.collection);
-        return Ok(());
+        println!("  strict: {}", cmd.strict);
+        return Ok(ExitCode::SUCCESS);
     }
 
     if cmd.pull {
@@ -28,10 +33,36 @@ pub fn handle(
         // TODO: Implement git pull or other remote sync
     }
 
-    println!("Updating index...");
-    store.update_index()?;
+    if cmd.format != "json" {
+        println!("Updating index...");
+    }
+    let report = store.update_index_report()?;
 
-    println!("Index updated successfully");
+    if cmd.format == "json" {
+        let output = serde_json::json!({
+            "collections_updated": store.get_collections().len(),
+            "documents_indexed": report.indexed,
+            "documents_unchanged": report.unchanged,
+            "errors": report.errors,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if report.errors.is_empty() {
+        println!("Index updated successfully");
+    } else {
+        println!("Index updated with {} errors:", report.errors.len());
+        for error in &report.errors {
+            println!("  {}/{} ({}): {}", error.collection, error.path, error.kind, error.message);
+        }
+    }
 
-    Ok(())
+    if report.errors.is_empty() {
+        return Ok(ExitCode::SUCCESS);
+    }
+    if cmd.strict {
+        anyhow::bail!("{} files could not be indexed (--strict)", report.errors.len());
+    }
+    if report.indexed + report.unchanged == 0 {
+        anyhow::bail!("No files could be indexed ({} errors)", report.errors.len());
+    }
+    Ok(ExitCode::from(PARTIAL_FAILURE_EXIT_CODE))
 }
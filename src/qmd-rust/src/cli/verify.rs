@@ -0,0 +1,79 @@
+use crate::anel::AnelSpec;
+use crate::cli::VerifyArgs;
+use crate::store::verify::VerifyReport;
+use crate::store::Store;
+use anyhow::Result;
+use std::process::ExitCode;
+
+/// Exit code when discrepancies were found and left unrepaired
+pub const INCONSISTENT_EXIT_CODE: u8 = 4;
+
+/// Handle verify command - check, and optionally repair, derived tables
+pub fn handle(cmd: &VerifyArgs, store: &Store) -> Result<ExitCode> {
+    // Handle --emit-spec: output ANEL specification and exit
+    if cmd.emit_spec {
+        let spec = AnelSpec::verify();
+        println!("{}", serde_json::to_string_pretty(&spec)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Handle --dry-run: validate parameters without executing
+    if cmd.dry_run {
+        println!("[DRY-RUN] Would execute verify with:");
+        println!("  collection: {:?}", cmd.collection);
+        println!("  fix: {}", cmd.fix);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let reports = store.verify(cmd.collection.as_deref(), cmd.fix)?;
+    let needs_fix = reports.iter().any(VerifyReport::needs_fix);
+
+    if cmd.format == "json" {
+        let output = serde_json::json!({
+            "consistent": reports.iter().all(VerifyReport::is_consistent),
+            "collections": reports,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        for report in &reports {
+            print_report(report);
+        }
+        if needs_fix {
+            println!("\nRun `qmd verify --fix` to repair");
+        }
+    }
+
+    if needs_fix {
+        return Ok(ExitCode::from(INCONSISTENT_EXIT_CODE));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_report(report: &VerifyReport) {
+    if report.is_consistent() {
+        println!("{}: ok", report.collection);
+        return;
+    }
+    println!("{}:", report.collection);
+    if report.missing_fts > 0 {
+        println!("  {} documents missing from the full-text index", report.missing_fts);
+    }
+    if report.stale_fts > 0 {
+        println!("  {} stale full-text rows", report.stale_fts);
+    }
+    if report.fts_mismatch {
+        println!("  full-text index does not match document content");
+    }
+    if report.orphan_vectors > 0 {
+        println!("  {} orphan vectors", report.orphan_vectors);
+    }
+    if report.vocabulary_drift > 0 {
+        println!("  {} vocabulary terms with wrong counts", report.vocabulary_drift);
+    }
+    if report.wrong_sizes > 0 {
+        println!("  {} documents with wrong sizes", report.wrong_sizes);
+    }
+    for fix in &report.fixed {
+        println!("  fixed: {}", fix);
+    }
+}
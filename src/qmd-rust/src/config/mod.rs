@@ -1,10 +1,18 @@
+use crate::paths;
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::io::Write;
+use std::time::{Duration, Instant};
 use log::info;
 
 const DEFAULT_CONFIG_PATH: &str = "~/.config/qmd/index.yaml";
+/// Used instead of [`DEFAULT_CONFIG_PATH`] when only this one exists
+const DEFAULT_TOML_CONFIG_PATH: &str = "~/.config/qmd/index.toml";
 const DEFAULT_CACHE_PATH: &str = "~/.cache/qmd";
+const CONFIG_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// BM25 backend type
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -28,6 +36,76 @@ pub enum VectorBackend {
     Qdrant,
 }
 
+impl std::str::FromStr for BM25Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sqlite_fts5" => Ok(Self::SqliteFts5),
+            "lancedb" => Ok(Self::LanceDb),
+            other => anyhow::bail!(
+                "Unknown BM25 backend '{}'; valid options: sqlite_fts5, lancedb",
+                other
+            ),
+        }
+    }
+}
+
+impl BM25Backend {
+    /// Fail if this backend was not compiled into the binary
+    pub fn ensure_available(&self) -> Result<(), anyhow::Error> {
+        match self {
+            Self::SqliteFts5 => Ok(()),
+            Self::LanceDb => {
+                if cfg!(feature = "lancedb") {
+                    Ok(())
+                } else {
+                    anyhow::bail!("LanceDB backend not enabled. Build with --features lancedb")
+                }
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for VectorBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "qmd_builtin" => Ok(Self::QmdBuiltin),
+            "lancedb" => Ok(Self::LanceDb),
+            "qdrant" => Ok(Self::Qdrant),
+            other => anyhow::bail!(
+                "Unknown vector backend '{}'; valid options: qmd_builtin, lancedb, qdrant",
+                other
+            ),
+        }
+    }
+}
+
+impl VectorBackend {
+    /// Fail if this backend was not compiled into the binary
+    pub fn ensure_available(&self) -> Result<(), anyhow::Error> {
+        match self {
+            Self::QmdBuiltin => Ok(()),
+            Self::LanceDb => {
+                if cfg!(feature = "lancedb") {
+                    Ok(())
+                } else {
+                    anyhow::bail!("LanceDB backend not enabled. Build with --features lancedb")
+                }
+            }
+            Self::Qdrant => {
+                if cfg!(feature = "qdrant") {
+                    Ok(())
+                } else {
+                    anyhow::bail!("Qdrant backend not enabled. Build with --features qdrant")
+                }
+            }
+        }
+    }
+}
+
 /// Collection configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollectionConfig {
@@ -35,6 +113,72 @@ pub struct CollectionConfig {
     pub path: PathBuf,
     pub pattern: Option<String>,
     pub description: Option<String>,
+    /// Index as source code: trigram tokenizer, no stemming, so identifiers
+    /// like `get_connection` match whole and as substrings
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub code: bool,
+    /// Stopwords removed from BM25 queries against this collection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopwords: Option<Stopwords>,
+    /// Order among collections in `--all` searches: lower searches first,
+    /// ties keep config order
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+    /// Included in `--all`; collections like archives that set this to
+    /// false are only searched by name or with `--really-all`
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub searchable_by_default: bool,
+    /// Store document text zstd-compressed; rows indexed before this was
+    /// set stay plain until `qmd cleanup` compresses them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compress_content: bool,
+}
+
+impl Default for CollectionConfig {
+    /// Same values as a config entry that sets only `name` and `path`
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            path: PathBuf::new(),
+            pattern: None,
+            description: None,
+            code: false,
+            stopwords: None,
+            priority: 0,
+            searchable_by_default: true,
+            compress_content: false,
+        }
+    }
+}
+
+/// Stopword list: the name of a built-in list ("english") or explicit words
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Stopwords {
+    Builtin(String),
+    Words(Vec<String>),
+}
+
+impl std::str::FromStr for Stopwords {
+    type Err = anyhow::Error;
+
+    /// "english" names the built-in list; anything else is a comma-separated word list
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "english" => Ok(Self::Builtin(s.to_string())),
+            _ => {
+                let words: Vec<String> = s
+                    .split(',')
+                    .map(|w| w.trim().to_string())
+                    .filter(|w| !w.is_empty())
+                    .collect();
+                if words.is_empty() {
+                    anyhow::bail!("Stopword list is empty; use 'english' or a comma-separated list");
+                }
+                Ok(Self::Words(words))
+            }
+        }
+    }
 }
 
 /// LLM model configuration
@@ -74,6 +218,59 @@ pub struct Config {
     /// Cache directory
     #[serde(default = "default_cache_path")]
     pub cache_path: PathBuf,
+
+    /// HTTP client settings for remote LLM providers
+    #[serde(default, skip_serializing_if = "HttpConfig::is_default")]
+    pub http: HttpConfig,
+
+    /// How the MCP `get` tool and `/documents` endpoint resolve paths
+    #[serde(default, skip_serializing_if = "DocumentAccessConfig::is_default")]
+    pub documents: DocumentAccessConfig,
+
+    /// LLM response cache policy
+    #[serde(default, skip_serializing_if = "CacheConfig::is_default")]
+    pub cache: CacheConfig,
+
+    /// Retention of purged documents kept for `qmd trash restore`
+    #[serde(default, skip_serializing_if = "TrashConfig::is_default")]
+    pub trash: TrashConfig,
+
+    /// Search pipeline tuning
+    #[serde(default, skip_serializing_if = "SearchConfig::is_default")]
+    pub search: SearchConfig,
+
+    /// Agent identity verification for `qmd mcp` and `qmd server`
+    #[serde(default, skip_serializing_if = "IdentityConfig::is_default")]
+    pub identity: IdentityConfig,
+
+    /// Price and throughput figures for `qmd embed --dry-run`
+    #[serde(default, skip_serializing_if = "EmbedConfig::is_default")]
+    pub embed: EmbedConfig,
+
+    /// Defaults for search command flags
+    #[serde(default, skip_serializing_if = "DefaultsConfig::is_default")]
+    pub defaults: DefaultsConfig,
+
+    /// Warm indexes and load models before `qmd server` / `qmd mcp` accept traffic
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub preload: bool,
+
+    /// Text indexed from Jupyter notebooks
+    #[serde(default, skip_serializing_if = "NotebookConfig::is_default")]
+    pub notebooks: NotebookConfig,
+
+    /// Follow symbolic links when scanning collections; links are skipped by default
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub follow_symlinks: bool,
+
+    /// Refuse to index files that are not valid UTF-8 instead of replacing
+    /// the invalid bytes
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict_utf8: bool,
+
+    /// `qmd server` settings
+    #[serde(default, skip_serializing_if = "ServerConfig::is_default")]
+    pub server: ServerConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -96,6 +293,9 @@ pub struct VectorBackendConfig {
     pub backend: VectorBackend,
     #[serde(default)]
     pub model: String,
+    /// Width of the stored embeddings; the model's known output size when unset
+    #[serde(default)]
+    pub dimension: Option<usize>,
     /// Qdrant-specific configuration
     #[serde(default)]
     pub qdrant: QdrantConfig,
@@ -104,24 +304,39 @@ pub struct VectorBackendConfig {
     pub lancedb: LanceDbConfig,
 }
 
-/// LanceDB embedded database configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LanceDbConfig {
-    /// Embedding dimension (must match the embedding model)
-    #[serde(default = "default_embedding_dim")]
-    pub embedding_dim: usize,
+impl VectorBackendConfig {
+    /// Embedding width: `dimension` if set, else the output size of `model`
+    pub fn dimension(&self) -> usize {
+        self.dimension.unwrap_or_else(|| model_dimension(&self.model))
+    }
 }
 
-impl Default for LanceDbConfig {
-    fn default() -> Self {
-        Self {
-            embedding_dim: default_embedding_dim(),
-        }
+/// Embedding width when neither `vector.dimension` nor a known model says otherwise
+pub const DEFAULT_VECTOR_DIMENSION: usize = 768;
+
+/// Output size of well-known embedding models
+fn model_dimension(model: &str) -> usize {
+    let model = model.to_lowercase();
+    if model.contains("minilm") || model.contains("bge-small") || model.contains("e5-small") {
+        384
+    } else if model.contains("mxbai-embed-large") || model.contains("bge-large") || model.contains("e5-large") {
+        1024
+    } else if model.contains("text-embedding-3-small") || model.contains("text-embedding-ada-002") {
+        1536
+    } else if model.contains("text-embedding-3-large") {
+        3072
+    } else {
+        // embeddinggemma, nomic-embed-text, bge-base and most others
+        DEFAULT_VECTOR_DIMENSION
     }
 }
 
-fn default_embedding_dim() -> usize {
-    384
+/// LanceDB embedded database configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LanceDbConfig {
+    /// Embedding dimension, overriding `vector.dimension` for the LanceDB table
+    #[serde(default)]
+    pub embedding_dim: Option<usize>,
 }
 
 /// Qdrant vector database configuration
@@ -169,32 +384,455 @@ impl Default for VectorBackendConfig {
         Self {
             backend: VectorBackend::QmdBuiltin,
             model: "embeddinggemma-300M".to_string(),
+            dimension: None,
             qdrant: QdrantConfig::default(),
             lancedb: LanceDbConfig::default(),
         }
     }
 }
 
+/// HTTP client configuration shared by all remote LLM calls
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// Proxy URL for all remote requests (e.g., "http://proxy.local:3128")
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Overall request timeout in seconds
+    #[serde(default = "default_http_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Connection establishment timeout in seconds
+    #[serde(default = "default_http_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Extra PEM-encoded CA certificate to trust (e.g., a corporate proxy CA)
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+}
+
+impl HttpConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            timeout_secs: default_http_timeout_secs(),
+            connect_timeout_secs: default_http_connect_timeout_secs(),
+            ca_cert: None,
+        }
+    }
+}
+
+/// Document retrieval settings for the MCP and HTTP servers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentAccessConfig {
+    /// Read files under a collection path that are not (yet) indexed; disable
+    /// for hardened deployments so only indexed content is served
+    #[serde(default = "default_true")]
+    pub filesystem_fallback: bool,
+}
+
+impl DocumentAccessConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for DocumentAccessConfig {
+    fn default() -> Self {
+        Self {
+            filesystem_fallback: true,
+        }
+    }
+}
+
+/// Index database layout and the expiry policy for the `llm_cache` table
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// TTL applied when a caller does not pass one; unset keeps entries until cleared
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<i64>,
+    /// Keep every collection in one `cache_path/index.db` instead of one
+    /// database per collection; rows are told apart by their collection column
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shared_db: bool,
+}
+
+impl CacheConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How long purged documents stay restorable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashConfig {
+    /// Days before a purge operation is deleted for good; 0 keeps none
+    #[serde(default = "default_trash_retention_days")]
+    pub retention_days: u32,
+}
+
+impl TrashConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: default_trash_retention_days(),
+        }
+    }
+}
+
+fn default_trash_retention_days() -> u32 {
+    30
+}
+
+/// Search pipeline tuning
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchConfig {
+    /// Most query variants `qmd query` searches, counting the original
+    #[serde(default = "default_max_expansions")]
+    pub max_expansions: usize,
+    /// BM25 hits kept for fusion with the vector results, across all variants
+    #[serde(default = "default_fusion_pool_size")]
+    pub fusion_pool_size: usize,
+    /// Top fused candidates passed to the reranker (and returned)
+    #[serde(default = "default_rerank_candidates")]
+    pub rerank_candidates: usize,
+    /// Candidates reranked together; a page only reranks the windows it
+    /// reaches, and each window is ordered on its own. Windows keep their
+    /// fused order, so a strong match in a later window still ranks below
+    /// the earlier windows; in exchange a longer page never reorders the
+    /// shorter one, and offsets stay stable. Unset reranks all candidates as
+    /// one window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank_window: Option<usize>,
+    /// Most candidates sent to the reranker in one call; larger sets are
+    /// split into batches whose scores are merged in order. Unset sends
+    /// them all in one call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank_batch_size: Option<usize>,
+    /// Collections a multi-collection search queries at once; 1 searches
+    /// them one after another
+    #[serde(default = "default_search_parallelism")]
+    pub parallelism: usize,
+}
+
+impl SearchConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            max_expansions: default_max_expansions(),
+            fusion_pool_size: default_fusion_pool_size(),
+            rerank_candidates: default_rerank_candidates(),
+            rerank_window: None,
+            rerank_batch_size: None,
+            parallelism: default_search_parallelism(),
+        }
+    }
+}
+
+fn default_max_expansions() -> usize {
+    5
+}
+
+fn default_fusion_pool_size() -> usize {
+    100
+}
+
+fn default_rerank_candidates() -> usize {
+    30
+}
+
+fn default_search_parallelism() -> usize {
+    4
+}
+
+/// Accepted agent identities (`AGENT_IDENTITY_TOKEN` / `X-Agent-Identity`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IdentityConfig {
+    /// Reject callers whose identity token is missing or not listed
+    #[serde(default)]
+    pub enforce: bool,
+    /// Valid tokens and the names audit records show for them
+    #[serde(default)]
+    pub tokens: Vec<IdentityToken>,
+}
+
+impl IdentityConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Settings for `qmd server`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerConfig {
+    /// File of hashed API keys managed by `qmd server keys`; must not be
+    /// readable by other users
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_keys_file: Option<PathBuf>,
+    /// Search, vsearch, query and MCP requests served at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_limit: Option<ConcurrencyLimit>,
+    /// Requests to every other route served at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cheap_limit: Option<ConcurrencyLimit>,
+    /// Emit NDJSON audit records for `/mcp` requests (default true);
+    /// `--no-mcp-audit` turns them off as well
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mcp_audit: Option<bool>,
+}
+
+impl ServerConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `search_limit`, or 4 in flight with 16 waiting
+    pub fn search_limit(&self) -> ConcurrencyLimit {
+        self.search_limit.unwrap_or(ConcurrencyLimit { max_in_flight: 4, max_queued: 16 })
+    }
+
+    /// `cheap_limit`, or 64 in flight with 256 waiting
+    pub fn cheap_limit(&self) -> ConcurrencyLimit {
+        self.cheap_limit.unwrap_or(ConcurrencyLimit { max_in_flight: 64, max_queued: 256 })
+    }
+
+    /// `mcp_audit`, on unless turned off
+    pub fn mcp_audit(&self) -> bool {
+        self.mcp_audit.unwrap_or(true)
+    }
+}
+
+/// How many requests of one kind the server handles at once; requests past
+/// `max_in_flight` wait for a turn, and those past `max_queued` waiting get a 503
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConcurrencyLimit {
+    pub max_in_flight: usize,
+    #[serde(default)]
+    pub max_queued: usize,
+}
+
+/// Bulk embedding settings and the figures behind `qmd embed --dry-run`
+/// estimates
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbedConfig {
+    /// USD per million tokens, keyed by remote embedding model
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prices: BTreeMap<String, f64>,
+    /// Chunks embedded per second; the last recorded run's rate when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunks_per_second: Option<f64>,
+    /// Requests to the remote embedding model in flight at once during a
+    /// bulk embed (default 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_concurrency: Option<usize>,
+}
+
+impl EmbedConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `remote_concurrency`, at least 1
+    pub fn remote_concurrency(&self) -> usize {
+        self.remote_concurrency.unwrap_or(1).max(1)
+    }
+}
+
+/// Config defaults for search command flags
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DefaultsConfig {
+    /// Collapse results sharing a content hash, as with `--dedupe-by-hash`
+    #[serde(default)]
+    pub dedupe_by_hash: bool,
+}
+
+impl DefaultsConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Text extracted from `.ipynb` files
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotebookConfig {
+    /// Index the `text/plain` and stream outputs of code cells
+    #[serde(default)]
+    pub include_outputs: bool,
+    /// Bytes of output kept per cell; longer output is truncated
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+}
+
+impl NotebookConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for NotebookConfig {
+    fn default() -> Self {
+        Self {
+            include_outputs: false,
+            max_output_bytes: default_max_output_bytes(),
+        }
+    }
+}
+
+fn default_max_output_bytes() -> usize {
+    2000
+}
+
+/// One accepted identity token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentityToken {
+    pub name: String,
+    pub token: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+fn default_http_timeout_secs() -> u64 {
+    60
+}
+
+fn default_http_connect_timeout_secs() -> u64 {
+    10
+}
+
+/// `~/.cache/qmd`, or a directory under the temp dir when there is no home
+/// directory (a container without `HOME`)
 fn default_cache_path() -> PathBuf {
-    shellexpand::tilde(DEFAULT_CACHE_PATH).parse().unwrap()
+    paths::expand(DEFAULT_CACHE_PATH).unwrap_or_else(|_| std::env::temp_dir().join("qmd"))
+}
+
+/// Load environment variables (API keys, AGENT_* settings) from a dotenv file
+///
+/// Must run before the LLM router is created, since providers read their keys
+/// from the process environment. Variables already set in the process
+/// environment take precedence over values in the file.
+pub fn load_env_file(path: &Path) -> Result<(), anyhow::Error> {
+    let vars = read_env_file(path, |key| std::env::var(key).ok())?;
+    for (key, value) in vars {
+        std::env::set_var(key, value);
+    }
+    info!("Loaded environment from: {:?}", path);
+    Ok(())
+}
+
+/// Parse a dotenv file into the variables it would add to an environment
+///
+/// `current` looks a variable up in that environment; keys it already has are
+/// left out, so the returned map is exactly what [`load_env_file`] sets.
+pub fn read_env_file(
+    path: &Path,
+    current: impl Fn(&str) -> Option<String>,
+) -> Result<BTreeMap<String, String>, anyhow::Error> {
+    let path = paths::expand(&path.to_string_lossy())?;
+    let fail = |e: dotenvy::Error| anyhow::anyhow!("Failed to load env file {}: {}", path.display(), e);
+    let mut vars = BTreeMap::new();
+    for item in dotenvy::from_path_iter(&path).map_err(fail)? {
+        let (key, value) = item.map_err(fail)?;
+        if current(&key).is_none() {
+            vars.entry(key).or_insert(value);
+        }
+    }
+    Ok(vars)
+}
+
+/// On-disk format of a config file, chosen by its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// TOML for `.toml` files, YAML for anything else
+    fn of(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Yaml,
+        }
+    }
+
+    fn parse(self, content: &str) -> Result<Config, anyhow::Error> {
+        Ok(match self {
+            Self::Yaml => serde_yaml::from_str(content)?,
+            Self::Toml => toml::from_str(content)?,
+        })
+    }
+
+    fn render(self, config: &Config) -> Result<String, anyhow::Error> {
+        Ok(match self {
+            Self::Yaml => serde_yaml::to_string(config)?,
+            Self::Toml => toml::to_string(config)?,
+        })
+    }
+}
+
+/// `index.yaml`, or `index.toml` when that is the only one present
+fn default_config_path() -> Result<PathBuf, anyhow::Error> {
+    let yaml = paths::expand(DEFAULT_CONFIG_PATH)?;
+    let toml = paths::expand(DEFAULT_TOML_CONFIG_PATH)?;
+    Ok(if !yaml.exists() && toml.exists() { toml } else { yaml })
 }
 
 impl Config {
     /// Load configuration from default path or create default
+    ///
+    /// Without a home directory there is no default path, so defaults apply.
     pub fn load() -> Result<Self, anyhow::Error> {
-        let config_path = expand_path(DEFAULT_CONFIG_PATH);
+        match default_config_path() {
+            Ok(path) => Self::load_from(&path),
+            Err(e) => {
+                info!("{:#}; using default configuration", e);
+                Ok(Self::default())
+            }
+        }
+    }
 
+    /// Load configuration from a specific path, or defaults if it does not exist
+    ///
+    /// `.toml` files are read as TOML, anything else as YAML.
+    pub fn load_from(config_path: &Path) -> Result<Self, anyhow::Error> {
         if config_path.exists() {
             info!("Loading configuration from: {:?}", config_path);
-            let content = fs::read_to_string(&config_path)?;
+            let content = fs::read_to_string(config_path)?;
             // serde's #[serde(default)] handles all defaults during deserialization
-            let mut config: Config = serde_yaml::from_str(&content)?;
+            let mut config = ConfigFormat::of(config_path).parse(&content)?;
 
             // Expand tilde paths in configuration
-            config.cache_path = expand_path(&config.cache_path.to_string_lossy());
+            config.cache_path = paths::expand(&config.cache_path.to_string_lossy()).context("Invalid cache_path")?;
 
             for collection in &mut config.collections {
-                collection.path = expand_path(&collection.path.to_string_lossy());
+                collection.path = paths::expand(&collection.path.to_string_lossy())
+                    .with_context(|| format!("Invalid path for collection {}", collection.name))?;
+            }
+            if let Some(path) = &mut config.server.api_keys_file {
+                *path = paths::expand(&path.to_string_lossy()).context("Invalid server.api_keys_file")?;
             }
 
             Ok(config)
@@ -206,26 +844,86 @@ impl Config {
 
     /// Save configuration to default path
     pub fn save(&self) -> Result<(), anyhow::Error> {
-        let config_path = expand_path(DEFAULT_CONFIG_PATH);
+        self.save_to(&default_config_path()?)
+    }
 
+    /// Save configuration to a specific path, atomically and under the config lock
+    ///
+    /// Written in the format [`Config::load_from`] reads from that path.
+    pub fn save_to(&self, config_path: &Path) -> Result<(), anyhow::Error> {
+        let _lock = ConfigLock::acquire(config_path)?;
+        self.write_atomic(config_path)
+    }
+
+    /// Apply a mutation to the configuration on disk at the default path
+    ///
+    /// See [`Config::update_at`].
+    pub fn update<F>(mutate: F) -> Result<Self, anyhow::Error>
+    where
+        F: FnOnce(&mut Config) -> Result<(), anyhow::Error>,
+    {
+        Self::update_at(&default_config_path()?, mutate)
+    }
+
+    /// Apply a mutation to the configuration file at `config_path`
+    ///
+    /// Holds an exclusive lock file for the whole read-modify-write and re-reads
+    /// the file under the lock, so concurrent edits merge instead of clobbering
+    /// each other. Returns the configuration as written.
+    pub fn update_at<F>(config_path: &Path, mutate: F) -> Result<Self, anyhow::Error>
+    where
+        F: FnOnce(&mut Config) -> Result<(), anyhow::Error>,
+    {
+        let _lock = ConfigLock::acquire(config_path)?;
+        let mut config = Self::load_from(config_path)?;
+        mutate(&mut config)?;
+        config.write_atomic(config_path)?;
+        Ok(config)
+    }
+
+    /// Write to a temp file next to the config and rename it into place
+    fn write_atomic(&self, config_path: &Path) -> Result<(), anyhow::Error> {
         if let Some(parent) = config_path.parent() {
             fs::create_dir_all(parent)?;
         }
 
         // Create a copy with paths compressed back to tilde format
         let mut save_config = self.clone();
-        save_config.cache_path = compress_path(&save_config.cache_path);
+        save_config.cache_path = paths::compress(&save_config.cache_path);
         for collection in &mut save_config.collections {
-            collection.path = compress_path(&collection.path);
+            collection.path = paths::compress(&collection.path);
+        }
+        if let Some(path) = &mut save_config.server.api_keys_file {
+            *path = paths::compress(path);
         }
 
-        let content = serde_yaml::to_string(&save_config)?;
-        fs::write(&config_path, content)?;
+        let content = ConfigFormat::of(config_path).render(&save_config)?;
+
+        let tmp_path = sibling_path(config_path, &format!(".tmp.{}", std::process::id()));
+        let write_result = (|| -> std::io::Result<()> {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(content.as_bytes())?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, config_path)
+        })();
+        if let Err(e) = write_result {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(anyhow::anyhow!(
+                "Failed to write configuration {}: {}",
+                config_path.display(),
+                e
+            ));
+        }
 
         info!("Configuration saved to: {:?}", config_path);
         Ok(())
     }
 
+    /// Directory holding purged documents, one file per purge operation
+    pub fn trash_dir(&self) -> PathBuf {
+        self.cache_path.join(".trash")
+    }
+
     /// Get cache directory for a specific collection
     pub fn cache_dir_for(&self, collection: &str) -> PathBuf {
         let mut path = self.cache_path.clone();
@@ -234,11 +932,85 @@ impl Config {
     }
 
     /// Get database path for a collection
+    ///
+    /// With `cache.shared_db` every collection maps to `cache_path/index.db`.
     pub fn db_path_for(&self, collection: &str) -> PathBuf {
-        let mut path = self.cache_dir_for(collection);
+        let mut path = if self.cache.shared_db {
+            self.cache_path.clone()
+        } else {
+            self.cache_dir_for(collection)
+        };
         path.push("index.db");
         path
     }
+
+    /// Find a collection by name, ignoring case and accents
+    ///
+    /// An exact match wins over a folded one, so "Docs" and "docs" can coexist.
+    pub fn find_collection(&self, name: &str) -> Option<&CollectionConfig> {
+        self.collections.iter().find(|c| c.name == name).or_else(|| {
+            let folded = fold_name(name);
+            self.collections.iter().find(|c| fold_name(&c.name) == folded)
+        })
+    }
+
+    /// Configured collection names close to `name`, closest first
+    pub fn similar_collection_names(&self, name: &str) -> Vec<&str> {
+        let folded = fold_name(name);
+        let mut scored: Vec<(usize, &str)> = self
+            .collections
+            .iter()
+            .filter_map(|c| {
+                let candidate = fold_name(&c.name);
+                let distance = edit_distance(&folded, &candidate);
+                let close = distance <= folded.chars().count().div_ceil(3)
+                    || candidate.contains(&folded)
+                    || folded.contains(&candidate);
+                close.then_some((distance, c.name.as_str()))
+            })
+            .collect();
+        scored.sort();
+        scored.into_iter().map(|(_, name)| name).collect()
+    }
+}
+
+/// Lowercase a name and strip Latin diacritics ("Café" -> "cafe")
+pub fn fold_name(name: &str) -> String {
+    name.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+            'ç' | 'ć' | 'č' => 'c',
+            'ď' => 'd',
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+            'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' => 'i',
+            'ł' => 'l',
+            'ñ' | 'ń' | 'ň' => 'n',
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => 'o',
+            'ř' => 'r',
+            'ś' | 'š' | 'ş' => 's',
+            'ť' | 'ţ' => 't',
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => 'u',
+            'ý' | 'ÿ' => 'y',
+            'ź' | 'ż' | 'ž' => 'z',
+            other => other,
+        })
+        .collect()
+}
+
+/// Levenshtein distance between two strings, by character
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            row.push((prev[j] + cost).min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+    prev[b.len()]
 }
 
 impl Default for Config {
@@ -249,19 +1021,72 @@ impl Default for Config {
             collections: Vec::new(),
             models: ModelsConfig::default(),
             cache_path: default_cache_path(),
+            http: HttpConfig::default(),
+            documents: DocumentAccessConfig::default(),
+            cache: CacheConfig::default(),
+            trash: TrashConfig::default(),
+            search: SearchConfig::default(),
+            identity: IdentityConfig::default(),
+            embed: EmbedConfig::default(),
+            defaults: DefaultsConfig::default(),
+            preload: false,
+            notebooks: NotebookConfig::default(),
+            follow_symlinks: false,
+            strict_utf8: false,
+            server: ServerConfig::default(),
         }
     }
 }
 
-fn expand_path(path: &str) -> PathBuf {
-    shellexpand::tilde(path).parse().unwrap()
+/// Exclusive lock on a config file, held as a `<config>.lock` file next to it
+struct ConfigLock {
+    path: PathBuf,
+}
+
+impl ConfigLock {
+    fn acquire(config_path: &Path) -> Result<Self, anyhow::Error> {
+        let path = sibling_path(config_path, ".lock");
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let deadline = Instant::now() + CONFIG_LOCK_TIMEOUT;
+        loop {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let _ = writeln!(file, "{}", std::process::id());
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if Instant::now() >= deadline {
+                        anyhow::bail!(
+                            "Timed out waiting for config lock {}; remove it if no other qmd process is running",
+                            path.display()
+                        );
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => {
+                    return Err(anyhow::anyhow!(
+                        "Failed to create config lock {}: {}",
+                        path.display(),
+                        e
+                    ))
+                }
+            }
+        }
+    }
 }
 
-/// Compress absolute path back to tilde format (e.g., /Users/foo/.cache → ~/.cache)
-fn compress_path(path: &PathBuf) -> PathBuf {
-    let home = expand_path("~");
-    if let Ok(relative) = path.strip_prefix(&home) {
-        return PathBuf::from(format!("~/{}", relative.display()));
+impl Drop for ConfigLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
     }
-    path.clone()
+}
+
+/// `index.yaml` + `.lock` -> `index.yaml.lock`
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
 }
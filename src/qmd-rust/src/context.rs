@@ -0,0 +1,423 @@
+//! Retrieval context for prompt construction.
+//!
+//! [`ContextBuilder`] runs the hybrid search pipeline for a query and packs
+//! the stored text of the results, best first, into one string that fits a
+//! token or character budget. Each source gets a citation number the first
+//! time it contributes text; the returned [`Context`] lists the sources with
+//! the byte offsets of their text in the string, so callers can map a
+//! citation in a model's answer back to the document it came from.
+
+use crate::llm::Router;
+use crate::store::bundle::{best_chunks, query_terms};
+use crate::store::deadline::Deadline;
+use crate::store::{estimate_tokens, lang, SearchOptions, SearchResult, Store};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Search results considered for a context
+const DEFAULT_CANDIDATES: usize = 20;
+
+/// Size limit for the assembled context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Budget {
+    /// Estimated tokens, as counted by [`estimate_tokens`]
+    Tokens(usize),
+    /// Characters
+    Chars(usize),
+}
+
+impl Budget {
+    /// The budget in characters
+    fn chars(self) -> usize {
+        match self {
+            Self::Tokens(amount) | Self::Chars(amount) => self.to_chars(amount),
+        }
+    }
+
+    /// `amount` in this budget's unit, in characters
+    fn to_chars(self, amount: usize) -> usize {
+        match self {
+            // estimate_tokens counts one token per four characters
+            Self::Tokens(_) => amount.saturating_mul(4),
+            Self::Chars(_) => amount,
+        }
+    }
+}
+
+/// How much of each result goes into the context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    /// The whole stored document
+    Document,
+    /// The chunks that best match the query
+    Chunk,
+}
+
+/// How each piece of text is attributed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CitationStyle {
+    /// `[1] collection/path.md`
+    Numbered,
+    /// `[collection/path.md]`
+    Path,
+    /// No header; sources are only listed in [`Context::sources`]
+    None,
+}
+
+/// Options for [`ContextBuilder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextOptions {
+    pub budget: Budget,
+    pub granularity: Granularity,
+    pub citation: CitationStyle,
+    /// Most text taken from one source, in the budget's unit
+    pub per_source_cap: Option<usize>,
+    /// Placed between pieces of text
+    pub separator: String,
+    /// Search results considered before deduplication and budgeting
+    pub candidates: usize,
+}
+
+impl Default for ContextOptions {
+    fn default() -> Self {
+        Self {
+            budget: Budget::Tokens(2000),
+            granularity: Granularity::Chunk,
+            citation: CitationStyle::Numbered,
+            per_source_cap: None,
+            separator: "\n\n".to_string(),
+            candidates: DEFAULT_CANDIDATES,
+        }
+    }
+}
+
+/// Assembled context and where its text came from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Context {
+    pub text: String,
+    /// Estimated tokens of `text`
+    pub tokens: usize,
+    /// Sources in citation order
+    pub sources: Vec<ContextSource>,
+    /// Set when the budget cut or dropped text
+    pub truncated: bool,
+}
+
+/// One cited document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContextSource {
+    /// Citation number, from 1 in order of first appearance
+    pub citation: usize,
+    pub collection: String,
+    pub path: String,
+    pub title: String,
+    pub hash: String,
+    pub score: f32,
+    /// Where the source's text sits in [`Context::text`]
+    pub spans: Vec<ContextSpan>,
+}
+
+/// Text from a source, located in the context and in the document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ContextSpan {
+    /// Byte offsets of the text in [`Context::text`]
+    pub start: usize,
+    pub end: usize,
+    /// Character offset of the text in the stored document
+    pub document_pos: usize,
+}
+
+/// A result and the text it can contribute, best piece first
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub result: SearchResult,
+    /// `(character offset in the document, text)` pairs
+    pub pieces: Vec<(usize, String)>,
+}
+
+/// Builds budgeted, citation-annotated context from search results
+#[derive(Debug, Clone, Default)]
+pub struct ContextBuilder {
+    options: ContextOptions,
+}
+
+impl ContextBuilder {
+    pub fn new(options: ContextOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.options.budget = budget;
+        self
+    }
+
+    pub fn granularity(mut self, granularity: Granularity) -> Self {
+        self.options.granularity = granularity;
+        self
+    }
+
+    pub fn citation(mut self, citation: CitationStyle) -> Self {
+        self.options.citation = citation;
+        self
+    }
+
+    pub fn per_source_cap(mut self, cap: usize) -> Self {
+        self.options.per_source_cap = Some(cap);
+        self
+    }
+
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.options.separator = separator.into();
+        self
+    }
+
+    pub fn options(&self) -> &ContextOptions {
+        &self.options
+    }
+
+    /// Run hybrid search for `query` and assemble context from the results
+    pub async fn build(&self, store: &Store, llm: &Router, query: &str) -> Result<Context> {
+        let options = SearchOptions {
+            limit: self.options.candidates,
+            search_all: true,
+            ..Default::default()
+        };
+        let outcome = store
+            .hybrid_search_within(query, options, llm, lang::detect_language(query), &Deadline::unlimited())
+            .await?;
+        self.assemble_results(store, query, outcome.results)
+    }
+
+    /// Assemble context from search results, reading their stored text
+    pub fn assemble_results(&self, store: &Store, query: &str, results: Vec<SearchResult>) -> Result<Context> {
+        let terms = query_terms(query);
+        let mut candidates = Vec::new();
+        for result in results {
+            let Some(doc) = store.document_content(&result)? else {
+                continue;
+            };
+            let pieces = match self.options.granularity {
+                Granularity::Document => vec![(0, doc)],
+                Granularity::Chunk => best_chunks(&doc, &terms)
+                    .into_iter()
+                    .map(|piece| (piece.pos, piece.text))
+                    .collect(),
+            };
+            candidates.push(Candidate { result, pieces });
+        }
+        Ok(self.assemble(candidates))
+    }
+
+    /// Pack candidates into the budget, highest score first
+    ///
+    /// Repeated paths and repeated content hashes are dropped, so a document
+    /// indexed under two paths is cited once.
+    pub fn assemble(&self, mut candidates: Vec<Candidate>) -> Context {
+        // Stable: equal scores keep the search order
+        candidates.sort_by(|a, b| b.result.score.partial_cmp(&a.result.score).unwrap_or(std::cmp::Ordering::Equal));
+
+        let budget = self.options.budget.chars();
+        let source_cap = self
+            .options
+            .per_source_cap
+            .map_or(usize::MAX, |cap| self.options.budget.to_chars(cap));
+
+        let mut text = String::new();
+        let mut used = 0;
+        let mut sources: Vec<ContextSource> = Vec::new();
+        let mut seen_paths = HashSet::new();
+        let mut seen_hashes = HashSet::new();
+        let mut truncated = false;
+
+        for candidate in candidates {
+            let result = candidate.result;
+            if !seen_paths.insert((result.collection.clone(), result.path.clone())) {
+                continue;
+            }
+            if !result.hash.is_empty() && !seen_hashes.insert(result.hash.clone()) {
+                continue;
+            }
+
+            // Numbers go only to sources that contribute text
+            let citation = sources.len() + 1;
+            let header = match self.options.citation {
+                CitationStyle::Numbered => format!("[{}] {}\n", citation, result.path),
+                CitationStyle::Path => format!("[{}]\n", result.path),
+                CitationStyle::None => String::new(),
+            };
+            let mut spans = Vec::new();
+            let mut source_used = 0;
+            let mut exhausted = false;
+
+            for (document_pos, piece) in candidate.pieces {
+                let piece = piece.trim_end();
+                if piece.is_empty() {
+                    continue;
+                }
+                let separator = if used == 0 { "" } else { self.options.separator.as_str() };
+                let overhead = chars(separator) + chars(&header);
+                let budget_room = budget.saturating_sub(used + overhead);
+                let cap_room = source_cap - source_used;
+                if budget_room == 0 || cap_room == 0 {
+                    truncated = true;
+                    exhausted = budget_room == 0;
+                    break;
+                }
+
+                let kept = take_chars(piece, budget_room.min(cap_room));
+                truncated |= kept.len() < piece.len();
+                text.push_str(separator);
+                text.push_str(&header);
+                let start = text.len();
+                text.push_str(kept);
+                spans.push(ContextSpan {
+                    start,
+                    end: text.len(),
+                    document_pos,
+                });
+                let kept_chars = chars(kept);
+                used += overhead + kept_chars;
+                source_used += kept_chars;
+            }
+
+            if !spans.is_empty() {
+                sources.push(ContextSource {
+                    citation,
+                    collection: result.collection,
+                    path: result.path,
+                    title: result.title,
+                    hash: result.hash,
+                    score: result.score,
+                    spans,
+                });
+            }
+            if exhausted {
+                break;
+            }
+        }
+
+        Context {
+            tokens: estimate_tokens(&text),
+            text,
+            sources,
+            truncated,
+        }
+    }
+}
+
+fn chars(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// The first `n` characters of `text`
+fn take_chars(text: &str, n: usize) -> &str {
+    match text.char_indices().nth(n) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(path: &str, hash: &str, score: f32, pieces: &[&str]) -> Candidate {
+        Candidate {
+            result: SearchResult {
+                docid: String::new(),
+                path: path.to_string(),
+                collection: "docs".to_string(),
+                score,
+                lines: 0,
+                title: path.to_string(),
+                hash: hash.to_string(),
+                query: None,
+                size: None,
+                match_line: None,
+                duplicates: Vec::new(),
+                source: Vec::new(),
+                score_components: None,
+            },
+            pieces: pieces.iter().enumerate().map(|(i, p)| (i * 100, p.to_string())).collect(),
+        }
+    }
+
+    fn chars_budget(n: usize) -> ContextBuilder {
+        ContextBuilder::default().budget(Budget::Chars(n))
+    }
+
+    #[test]
+    fn test_assemble_orders_by_score_with_stable_citations() {
+        let candidates = vec![
+            candidate("docs/low.md", "h1", 0.2, &["Low scoring text."]),
+            candidate("docs/high.md", "h2", 0.9, &["High scoring text."]),
+            candidate("docs/mid.md", "h3", 0.5, &["Middle text."]),
+        ];
+        let context = chars_budget(1000).assemble(candidates.clone());
+
+        assert_eq!(
+            context.text,
+            "[1] docs/high.md\nHigh scoring text.\n\n[2] docs/mid.md\nMiddle text.\n\n[3] docs/low.md\nLow scoring text."
+        );
+        let cited: Vec<(usize, &str)> = context.sources.iter().map(|s| (s.citation, s.path.as_str())).collect();
+        assert_eq!(cited, [(1, "docs/high.md"), (2, "docs/mid.md"), (3, "docs/low.md")]);
+        assert!(!context.truncated);
+
+        // Same input, same numbering; spans point at each source's text
+        assert_eq!(chars_budget(1000).assemble(candidates), context);
+        let span = context.sources[1].spans[0];
+        assert_eq!(&context.text[span.start..span.end], "Middle text.");
+    }
+
+    #[test]
+    fn test_assemble_enforces_budget() {
+        let candidates = vec![
+            candidate("docs/a.md", "h1", 0.9, &["a".repeat(40).as_str()]),
+            candidate("docs/b.md", "h2", 0.8, &["b".repeat(40).as_str()]),
+            candidate("docs/c.md", "h3", 0.7, &["c".repeat(40).as_str()]),
+        ];
+
+        let context = chars_budget(80).assemble(candidates.clone());
+        assert!(context.text.chars().count() <= 80, "{}", context.text);
+        assert!(context.truncated);
+        assert_eq!(context.sources.len(), 2);
+        let b = context.sources[1].spans[0];
+        assert_eq!(&context.text[b.start..b.end], "b".repeat(10));
+
+        // Tokens are four characters each
+        let context = ContextBuilder::default().budget(Budget::Tokens(14)).assemble(candidates);
+        assert_eq!(context.text, format!("[1] docs/a.md\n{}", "a".repeat(40)));
+        assert!(context.tokens <= 14);
+    }
+
+    #[test]
+    fn test_assemble_dedupes_and_caps_sources() {
+        let candidates = vec![
+            candidate("docs/guide.md", "same", 0.9, &["First chunk of the guide.", "Second chunk of the guide."]),
+            candidate("docs/vendor/guide.md", "same", 0.8, &["First chunk of the guide."]),
+            candidate("docs/guide.md", "other", 0.7, &["Stale copy."]),
+            candidate("docs/notes.md", "h2", 0.6, &["Notes."]),
+        ];
+
+        let context = chars_budget(1000).citation(CitationStyle::Path).separator("\n---\n").assemble(candidates.clone());
+        assert_eq!(
+            context.text,
+            "[docs/guide.md]\nFirst chunk of the guide.\n---\n[docs/guide.md]\nSecond chunk of the guide.\
+             \n---\n[docs/notes.md]\nNotes."
+        );
+        assert_eq!(context.sources.len(), 2);
+        assert_eq!(context.sources[0].spans.len(), 2);
+        assert_eq!(context.sources[0].spans[1].document_pos, 100);
+        assert_eq!(context.sources[1].citation, 2);
+
+        // A capped source yields its budget share to the next one
+        let context = chars_budget(1000).per_source_cap(10).citation(CitationStyle::None).assemble(candidates);
+        assert_eq!(context.text, "First chun\n\nNotes.");
+        assert_eq!(context.sources.len(), 2);
+        assert!(context.truncated);
+    }
+}
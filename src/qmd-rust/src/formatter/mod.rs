@@ -1,7 +1,101 @@
 use crate::anel::{NdjsonRecord, TraceContext};
-use crate::store::SearchResult;
+use crate::store::bundle::{content_bytes, limit_content, BundledContent};
+use crate::store::deadline::{SearchStage, StageFailure};
+use crate::store::lang::{QueryExplain, QueryLanguage};
+use crate::store::timings::StageTimings;
+use crate::store::{SearchResult, StoreWarning};
 use serde::Serialize;
 
+/// Query-level extras for search output, beyond the results themselves
+#[derive(Debug, Default)]
+pub struct SearchMeta<'a> {
+    /// Language the query was searched in, recorded in JSON/NDJSON metadata
+    pub language: Option<QueryLanguage>,
+    /// How the query was interpreted (`--explain`)
+    pub explain: Option<&'a QueryExplain>,
+    /// Output schema embedded as `$schema` in JSON/NDJSON (`--json-schema`)
+    pub schema: Option<&'a serde_json::Value>,
+    /// Stages skipped when `--timeout` ran out; non-empty marks the results partial
+    pub skipped: &'a [SearchStage],
+    /// Stages that failed, e.g. the embedder; non-empty also marks the results partial
+    pub degraded: &'a [StageFailure],
+    /// Per-stage pipeline timings; always in JSON/NDJSON, printed with `explain`
+    pub timings: Option<&'a StageTimings>,
+    /// Cluster CLI/Markdown results under headers; JSON adds a `groups` array
+    pub group_by: Option<GroupBy>,
+    /// Cut content, then results, until the output fits in this many bytes
+    pub max_output_bytes: Option<usize>,
+    /// Results skipped before this page (`--offset`), recorded in JSON/NDJSON
+    pub offset: usize,
+}
+
+impl SearchMeta<'_> {
+    fn is_partial(&self) -> bool {
+        !self.skipped.is_empty() || !self.degraded.is_empty()
+    }
+}
+
+/// What `--group-by` clusters results by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// The result's collection
+    Collection,
+    /// The top-level directory within the collection
+    Dir,
+}
+
+impl std::str::FromStr for GroupBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "collection" => Ok(Self::Collection),
+            "dir" => Ok(Self::Dir),
+            other => anyhow::bail!("Unknown grouping '{}'; valid options: collection, dir", other),
+        }
+    }
+}
+
+impl GroupBy {
+    /// Header a result is grouped under: `collection`, or `collection/dir/`
+    /// (`collection/` for files at the collection root)
+    pub fn key(self, result: &SearchResult) -> String {
+        match self {
+            Self::Collection => result.collection.clone(),
+            Self::Dir => {
+                let prefix = format!("{}/", result.collection);
+                let path = result.path.strip_prefix(&prefix).unwrap_or(&result.path);
+                match path.split_once('/') {
+                    Some((dir, _)) => format!("{}{}/", prefix, dir),
+                    None => prefix,
+                }
+            }
+        }
+    }
+}
+
+/// Results sharing a group key, in rank order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResultGroup {
+    pub name: String,
+    /// Positions of the group's results in the flat result list
+    pub indices: Vec<usize>,
+}
+
+/// Group `results` by `group_by`; groups are ordered by their best-ranked result
+pub fn group_results(results: &[SearchResult], group_by: GroupBy) -> Vec<ResultGroup> {
+    let mut groups: Vec<ResultGroup> = Vec::new();
+    for (i, result) in results.iter().enumerate() {
+        let name = group_by.key(result);
+        match groups.iter_mut().find(|group| group.name == name) {
+            Some(group) => group.indices.push(i),
+            None => groups.push(ResultGroup { name, indices: vec![i] }),
+        }
+    }
+    groups
+}
+
 /// Output format types
 #[derive(Debug, Clone)]
 pub enum Format {
@@ -30,130 +124,465 @@ impl Format {
 
     /// Format search results
     pub fn format_search_results(&self, results: &[SearchResult], limit: usize) -> Result<(), anyhow::Error> {
+        self.format_search_results_with_warnings(results, limit, &[])
+    }
+
+    /// Format search results, attaching store warnings to JSON/NDJSON output
+    pub fn format_search_results_with_warnings(
+        &self,
+        results: &[SearchResult],
+        limit: usize,
+        warnings: &[StoreWarning],
+    ) -> Result<(), anyhow::Error> {
+        self.format_search_results_with_content(results, limit, warnings, None)
+    }
+
+    /// Whether this format can carry bundled document content
+    pub fn supports_content(&self) -> bool {
+        matches!(self, Self::Json | Self::Ndjson | Self::Markdown)
+    }
+
+    /// Format search results with store warnings and optional bundled content,
+    /// one entry per displayed result in rank order
+    pub fn format_search_results_with_content(
+        &self,
+        results: &[SearchResult],
+        limit: usize,
+        warnings: &[StoreWarning],
+        contents: Option<&[BundledContent]>,
+    ) -> Result<(), anyhow::Error> {
+        self.format_search_results_with_meta(results, limit, warnings, contents, &SearchMeta::default())
+    }
+
+    /// Format search results along with query metadata (see `SearchMeta`)
+    ///
+    /// JSON and NDJSON carry the explanation as an `explain` object; CLI output
+    /// prints it above the results and the other formats print it to stderr.
+    pub fn format_search_results_with_meta(
+        &self,
+        results: &[SearchResult],
+        limit: usize,
+        warnings: &[StoreWarning],
+        contents: Option<&[BundledContent]>,
+        meta: &SearchMeta,
+    ) -> Result<(), anyhow::Error> {
         let limited_results = &results[..std::cmp::min(results.len(), limit)];
+        let contents = contents.unwrap_or(&[]);
 
-        match self {
-            Self::Cli => self.format_cli(limited_results),
-            Self::Json => self.format_json(limited_results),
-            Self::Ndjson => self.format_ndjson(limited_results),
-            Self::Markdown => self.format_markdown(limited_results),
-            Self::Csv => self.format_csv(limited_results),
-            Self::Files => self.format_files(limited_results),
-            Self::Xml => self.format_xml(limited_results),
+        if meta.schema.is_some() && !matches!(self, Self::Json | Self::Ndjson) {
+            log::warn!("--json-schema only applies to json and ndjson output");
+        }
+        if let Some(explain) = meta.explain {
+            let mut rendered = explain.render();
+            if let Some(timings) = meta.timings {
+                rendered.push_str(&timings.render());
+            }
+            for result in limited_results.iter().filter(|r| !r.duplicates.is_empty()) {
+                rendered.push_str(&format!(
+                    "Duplicates of {} ({}): {}\n",
+                    result.path,
+                    result.duplicates.len(),
+                    result.duplicates.join(", ")
+                ));
+            }
+            match self {
+                Self::Cli => println!("{}", rendered),
+                Self::Json | Self::Ndjson => {}
+                _ => eprint!("{}", rendered),
+            }
+        }
+        if !meta.skipped.is_empty() && !matches!(self, Self::Json | Self::Ndjson) {
+            let stages: Vec<String> = meta.skipped.iter().map(|s| s.to_string()).collect();
+            eprintln!("Partial results: timed out before {}", stages.join(", "));
+        }
+        if meta.group_by.is_some() && !matches!(self, Self::Cli | Self::Markdown | Self::Json) {
+            log::warn!("--group-by only applies to cli, md and json output");
+        }
+        if !matches!(self, Self::Json | Self::Ndjson) {
+            for failure in meta.degraded {
+                eprintln!("Partial results: {} stage failed: {}", failure.stage, failure.error);
+            }
         }
+
+        let trace_id = TraceContext::from_env().get_or_generate_trace_id();
+        let output = match meta.max_output_bytes {
+            Some(max) => {
+                let (output, truncated) = self.fit_output(limited_results, warnings, contents, meta, &trace_id, max)?;
+                if truncated && !matches!(self, Self::Json | Self::Ndjson) {
+                    eprintln!("Output truncated to fit {} bytes", max);
+                }
+                output
+            }
+            None => self.render(limited_results, warnings, contents, meta, &trace_id, false)?,
+        };
+        print!("{}", output);
+        Ok(())
     }
 
-    fn format_cli(&self, results: &[SearchResult]) -> Result<(), anyhow::Error> {
-        println!("Found {} results:", results.len());
-        println!("{:<6} {:<8} {:<40} Path", "Score", "Lines", "DocID");
-        println!("{}", "-".repeat(100));
+    /// Output for `results`, flagged as truncated in JSON/NDJSON when `truncated`
+    fn render(
+        &self,
+        results: &[SearchResult],
+        warnings: &[StoreWarning],
+        contents: &[BundledContent],
+        meta: &SearchMeta,
+        trace_id: &str,
+        truncated: bool,
+    ) -> Result<String, anyhow::Error> {
+        Ok(match self {
+            Self::Cli => self.render_cli(results, meta.group_by),
+            Self::Json => self.render_json(results, warnings, contents, meta, truncated)?,
+            Self::Ndjson => self.render_ndjson(results, warnings, contents, meta, trace_id, truncated)?,
+            Self::Markdown => render_markdown_grouped(results, contents, meta.group_by),
+            Self::Csv => self.render_csv(results),
+            Self::Files => self.render_files(results),
+            Self::Xml => self.render_xml(results),
+        })
+    }
 
-        for result in results {
+    /// Output cut to at most `max` bytes, and whether anything was cut
+    ///
+    /// Bundled content is shortened first, spending what is left in rank
+    /// order; results are dropped from the end only when their output alone
+    /// is over the limit.
+    fn fit_output(
+        &self,
+        results: &[SearchResult],
+        warnings: &[StoreWarning],
+        contents: &[BundledContent],
+        meta: &SearchMeta,
+        trace_id: &str,
+        max: usize,
+    ) -> Result<(String, bool), anyhow::Error> {
+        let output = self.render(results, warnings, contents, meta, trace_id, false)?;
+        if output.len() <= max {
+            return Ok((output, false));
+        }
+
+        for shown in (0..=results.len()).rev() {
+            let results = &results[..shown];
+            let contents = &contents[..contents.len().min(shown)];
+            let fitting = |budget: usize| -> Result<Option<String>, anyhow::Error> {
+                let cut = limit_content(contents, budget);
+                let output = self.render(results, warnings, &cut, meta, trace_id, true)?;
+                Ok((output.len() <= max).then_some(output))
+            };
+
+            let Some(mut best) = fitting(0)? else {
+                continue;
+            };
+            // Output grows with the content budget, so bisect for the most that fits
+            let (mut low, mut high) = (0, content_bytes(contents));
+            while low < high {
+                let mid = low + (high - low).div_ceil(2);
+                match fitting(mid)? {
+                    Some(output) => {
+                        low = mid;
+                        best = output;
+                    }
+                    None => high = mid - 1,
+                }
+            }
+            return Ok((best, true));
+        }
+        // Not even the empty result list fits; print it anyway
+        Ok((self.render(&[], warnings, &[], meta, trace_id, true)?, true))
+    }
+
+    fn render_cli(&self, results: &[SearchResult], group_by: Option<GroupBy>) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let _ = writeln!(out, "Found {} results:", results.len());
+        let _ = writeln!(out, "{:<6} {:<8} {:<40} Path", "Score", "Lines", "DocID");
+        let _ = writeln!(out, "{}", "-".repeat(100));
+
+        let write_row = |out: &mut String, result: &SearchResult| {
             let score = format!("{:.4}", result.score);
-            println!("{:<6} {:<8} {:<40} {}", score, result.lines, result.docid, result.path);
+            let _ = writeln!(out, "{:<6} {:<8} {:<40} {}", score, result.lines, result.docid, located_path(result));
+        };
+        match group_by {
+            Some(group_by) => {
+                for group in group_results(results, group_by) {
+                    let _ = writeln!(out, "\n{} ({})", group.name, group.indices.len());
+                    group.indices.iter().for_each(|&i| write_row(&mut out, &results[i]));
+                }
+            }
+            None => results.iter().for_each(|result| write_row(&mut out, result)),
         }
-        Ok(())
+        out
     }
 
-    fn format_json(&self, results: &[SearchResult]) -> Result<(), anyhow::Error> {
+    fn render_json(
+        &self,
+        results: &[SearchResult],
+        warnings: &[StoreWarning],
+        contents: &[BundledContent],
+        meta: &SearchMeta,
+        truncated: bool,
+    ) -> Result<String, anyhow::Error> {
         #[derive(Serialize)]
-        struct JsonResult {
+        struct JsonResult<'a> {
+            #[serde(rename = "$schema", skip_serializing_if = "Option::is_none")]
+            schema: Option<&'a serde_json::Value>,
             query: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            language: Option<QueryLanguage>,
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            partial: bool,
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            skipped_stages: &'a [SearchStage],
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            degraded_stages: &'a [StageFailure],
+            /// Results or content were cut to fit the output budget
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            truncated: bool,
+            offset: usize,
             total: usize,
-            results: Vec<SearchResult>,
+            results: Vec<ResultWithContent<'a>>,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            warnings: Vec<StoreWarning>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            explain: Option<&'a QueryExplain>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            timings: Option<&'a StageTimings>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            groups: Option<Vec<ResultGroup>>,
         }
 
         // Extract query from first result if available
         let query = results.first().and_then(|r| r.query.clone());
 
         let output = JsonResult {
+            schema: meta.schema,
             query,
+            language: meta.language,
+            partial: meta.is_partial(),
+            skipped_stages: meta.skipped,
+            degraded_stages: meta.degraded,
+            truncated,
+            offset: meta.offset,
             total: results.len(),
-            results: results.to_vec(),
+            results: with_content(results, contents).collect(),
+            warnings: warnings.to_vec(),
+            explain: meta.explain,
+            timings: meta.timings,
+            groups: meta.group_by.map(|group_by| group_results(results, group_by)),
         };
 
-        println!("{}", serde_json::to_string_pretty(&output)?);
-        Ok(())
+        Ok(format!("{}\n", serde_json::to_string_pretty(&output)?))
     }
 
     /// Format results as NDJSON (Newline-Delimited JSON)
     ///
     /// Each result is emitted as a separate JSON line, suitable for streaming
-    fn format_ndjson(&self, results: &[SearchResult]) -> Result<(), anyhow::Error> {
-        let trace_ctx = TraceContext::from_env();
-        let trace_id = trace_ctx.get_or_generate_trace_id();
-
+    fn render_ndjson(
+        &self,
+        results: &[SearchResult],
+        warnings: &[StoreWarning],
+        contents: &[BundledContent],
+        meta: &SearchMeta,
+        trace_id: &str,
+        truncated: bool,
+    ) -> Result<String, anyhow::Error> {
         // Get query from first result if available
         let query = results.first().and_then(|r| r.query.clone());
 
         // Emit metadata record first
-        let metadata = serde_json::json!({
+        let mut metadata = serde_json::json!({
+            "offset": meta.offset,
             "total": results.len(),
             "query": query,
             "trace_id": trace_id,
             "version": "1.0"
         });
-        let metadata_record = NdjsonRecord::new("metadata", 0, metadata);
-        metadata_record.emit();
+        if !warnings.is_empty() {
+            metadata["warnings"] = serde_json::to_value(warnings)?;
+        }
+        if let Some(language) = meta.language {
+            metadata["language"] = serde_json::to_value(language)?;
+        }
+        if let Some(explain) = meta.explain {
+            metadata["explain"] = serde_json::to_value(explain)?;
+        }
+        if let Some(schema) = meta.schema {
+            metadata["$schema"] = schema.clone();
+        }
+        if meta.is_partial() {
+            metadata["partial"] = serde_json::json!(true);
+        }
+        if !meta.skipped.is_empty() {
+            metadata["skipped_stages"] = serde_json::to_value(meta.skipped)?;
+        }
+        if !meta.degraded.is_empty() {
+            metadata["degraded_stages"] = serde_json::to_value(meta.degraded)?;
+        }
+        if let Some(timings) = meta.timings {
+            metadata["timings"] = serde_json::to_value(timings)?;
+        }
+        if truncated {
+            metadata["truncated"] = serde_json::json!(true);
+        }
+        let mut out = NdjsonRecord::new("metadata", 0, metadata).to_ndjson();
+        out.push('\n');
 
-        // Emit each result as a separate NDJSON line
-        for (i, result) in results.iter().enumerate() {
-            let record = NdjsonRecord::new("result", (i + 1) as u64, result);
-            record.emit();
+        // Each result as a separate NDJSON line
+        for (i, result) in with_content(results, contents).enumerate() {
+            out.push_str(&NdjsonRecord::new("result", (i + 1) as u64, result).to_ndjson());
+            out.push('\n');
         }
 
-        Ok(())
+        Ok(out)
     }
 
-    fn format_markdown(&self, results: &[SearchResult]) -> Result<(), anyhow::Error> {
-        println!("# Search Results");
-        println!();
-        println!("Found {} results:", results.len());
-        println!();
-
-        for (i, result) in results.iter().enumerate() {
-            println!("## {}. {}", i + 1, result.path);
-            println!("- **DocID**: {}", result.docid);
-            println!("- **Score**: {:.4}", result.score);
-            println!("- **Lines**: {}", result.lines);
-            println!();
+    fn render_csv(&self, results: &[SearchResult]) -> String {
+        let mut out = String::from("docid,score,lines,path\n");
+        for result in results {
+            out.push_str(&format!("{},{:.4},{},{}\n", result.docid, result.score, result.lines, result.path));
         }
-        Ok(())
+        out
     }
 
-    fn format_csv(&self, results: &[SearchResult]) -> Result<(), anyhow::Error> {
-        println!("docid,score,lines,path");
+    fn render_files(&self, results: &[SearchResult]) -> String {
+        results.iter().map(|result| format!("{}\n", result.path)).collect()
+    }
+
+    fn render_xml(&self, results: &[SearchResult]) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let _ = writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(out, "<results total=\"{}\">", results.len());
         for result in results {
-            println!("{},{:.4},{},{}", result.docid, result.score, result.lines, result.path);
+            let _ = writeln!(out, "  <result>");
+            let _ = writeln!(out, "    <docid>{}</docid>", escape_xml(&result.docid));
+            let _ = writeln!(out, "    <path>{}</path>", escape_xml(&result.path));
+            let _ = writeln!(out, "    <collection>{}</collection>", escape_xml(&result.collection));
+            let _ = writeln!(out, "    <title>{}</title>", escape_xml(&result.title));
+            let _ = writeln!(out, "    <score>{:.4}</score>", result.score);
+            let _ = writeln!(out, "    <lines>{}</lines>", result.lines);
+            let _ = writeln!(out, "  </result>");
         }
-        Ok(())
+        let _ = writeln!(out, "</results>");
+        out
     }
+}
 
-    fn format_files(&self, results: &[SearchResult]) -> Result<(), anyhow::Error> {
-        for result in results {
-            println!("{}", result.path);
+/// A search result serialized together with its bundled content, if any
+#[derive(Serialize)]
+struct ResultWithContent<'a> {
+    #[serde(flatten)]
+    result: &'a SearchResult,
+    /// Copies folded into this result by `--dedupe-by-hash`
+    #[serde(skip_serializing_if = "is_zero")]
+    duplicate_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<&'a BundledContent>,
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+fn with_content<'a>(
+    results: &'a [SearchResult],
+    contents: &'a [BundledContent],
+) -> impl Iterator<Item = ResultWithContent<'a>> {
+    results
+        .iter()
+        .enumerate()
+        .map(move |(i, result)| ResultWithContent {
+            result,
+            duplicate_count: result.duplicates.len(),
+            content: contents.get(i),
+        })
+}
+
+/// Render results as Markdown, one section per result with its content fenced
+pub fn render_markdown(results: &[SearchResult], contents: &[BundledContent]) -> String {
+    render_markdown_grouped(results, contents, None)
+}
+
+/// Render results as Markdown, under one header per group when grouping;
+/// results keep their overall rank numbers
+pub fn render_markdown_grouped(
+    results: &[SearchResult],
+    contents: &[BundledContent],
+    group_by: Option<GroupBy>,
+) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# Search Results\n");
+    let _ = writeln!(out, "Found {} results:\n", results.len());
+
+    match group_by {
+        Some(group_by) => {
+            for group in group_results(results, group_by) {
+                let _ = writeln!(out, "## {} ({})\n", group.name, group.indices.len());
+                for i in group.indices {
+                    render_markdown_result(&mut out, "###", i, &results[i], contents.get(i));
+                }
+            }
+        }
+        None => {
+            for (i, result) in results.iter().enumerate() {
+                render_markdown_result(&mut out, "##", i, result, contents.get(i));
+            }
         }
-        Ok(())
     }
+    out
+}
 
-    fn format_xml(&self, results: &[SearchResult]) -> Result<(), anyhow::Error> {
-        println!(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
-        println!("<results total=\"{}\">", results.len());
-        for result in results {
-            println!("  <result>");
-            println!("    <docid>{}</docid>", escape_xml(&result.docid));
-            println!("    <path>{}</path>", escape_xml(&result.path));
-            println!("    <collection>{}</collection>", escape_xml(&result.collection));
-            println!("    <title>{}</title>", escape_xml(&result.title));
-            println!("    <score>{:.4}</score>", result.score);
-            println!("    <lines>{}</lines>", result.lines);
-            println!("  </result>");
-        }
-        println!("</results>");
-        Ok(())
+fn render_markdown_result(
+    out: &mut String,
+    heading: &str,
+    index: usize,
+    result: &SearchResult,
+    content: Option<&BundledContent>,
+) {
+    use std::fmt::Write;
+
+    let _ = writeln!(out, "{} {}. {}", heading, index + 1, located_path(result));
+    let _ = writeln!(out, "- **DocID**: {}", result.docid);
+    let _ = writeln!(out, "- **Score**: {:.4}", result.score);
+    let _ = writeln!(out, "- **Lines**: {}", result.lines);
+    let _ = writeln!(out);
+
+    if let Some(content) = content {
+        for piece in &content.pieces {
+            if let Some(seq) = piece.seq {
+                let _ = writeln!(out, "Chunk {} (line {}):\n", seq, piece.line);
+            } else if piece.pos > 0 {
+                let _ = writeln!(out, "Line {}:\n", piece.line);
+            }
+            let fence = code_fence_for(&piece.text);
+            let _ = writeln!(out, "{}\n{}\n{}\n", fence, piece.text.trim_end_matches('\n'), fence);
+        }
+        if content.truncated {
+            let _ = writeln!(out, "_Content truncated by --content-budget._\n");
+        }
+    }
+}
+
+/// `path:line` when the result knows where its best match starts
+fn located_path(result: &SearchResult) -> String {
+    match result.match_line {
+        Some(line) => format!("{}:{}", result.path, line),
+        None => result.path.clone(),
     }
 }
 
+/// A backtick fence longer than any backtick run inside `text`
+fn code_fence_for(text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
 /// Escape special XML characters
 fn escape_xml(s: &str) -> String {
     s.replace('&', "&amp;")
@@ -3,9 +3,14 @@
 pub mod anel;
 pub mod cli;
 pub mod config;
+pub mod context;
 pub mod formatter;
 pub mod llm;
+pub mod logging;
 pub mod mcp;
+pub mod paths;
 pub mod plugin;
+pub mod preload;
+pub mod profile;
 pub mod server;
 pub mod store;
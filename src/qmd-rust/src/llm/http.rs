@@ -0,0 +1,261 @@
+//! Shared HTTP client for remote LLM providers
+//!
+//! One pooled `reqwest::Client` is built per router from the `http` config
+//! section and cloned into every remote provider, so connections, TLS sessions
+//! and DNS lookups are reused across calls.
+
+use crate::anel::TraceContext;
+use crate::config::HttpConfig;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::time::Duration;
+use tracing::Instrument;
+
+/// User-Agent sent with every remote LLM request
+pub const USER_AGENT: &str = concat!("qmd/", env!("CARGO_PKG_VERSION"));
+
+/// Header carrying the trace id to remote providers
+pub const TRACE_ID_HEADER: &str = "x-trace-id";
+
+/// Times a rate-limited (429) request is retried before its error is returned
+const RATE_LIMIT_RETRIES: u32 = 4;
+
+/// First backoff after a 429 without `Retry-After`, doubled on each retry
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest wait honored from a `Retry-After` header
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Delay a `Retry-After` header asks for, when given in seconds
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let seconds: f64 = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+    (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+/// Pooled HTTP client; clones share the same connection pool
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: reqwest::Client,
+}
+
+impl Client {
+    /// Build a client from config: proxy, timeouts, extra CA certificate
+    pub fn new(config: &HttpConfig) -> Result<Self> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .connect_timeout(Duration::from_secs(config.connect_timeout_secs));
+
+        if let Some(ref proxy) = config.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .with_context(|| format!("Invalid HTTP proxy: {}", proxy))?;
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(ref path) = config.ca_cert {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("Failed to read CA certificate: {}", path.display()))?;
+            let cert = reqwest::Certificate::from_pem(&pem)
+                .with_context(|| format!("Invalid CA certificate: {}", path.display()))?;
+            builder = builder.add_root_certificate(cert);
+        }
+
+        let inner = builder.build().context("Failed to build HTTP client")?;
+        Ok(Self { inner })
+    }
+
+    /// Underlying reqwest client, for requests `post_json` does not cover
+    pub fn inner(&self) -> &reqwest::Client {
+        &self.inner
+    }
+
+    /// POST a JSON body and parse the JSON response
+    ///
+    /// The request runs inside an `llm_http` span carrying the trace id, which
+    /// is also forwarded in the `x-trace-id` header. A `429 Too Many Requests`
+    /// is retried after the `Retry-After` the provider asks for, or with
+    /// exponential backoff when it names none.
+    pub async fn post_json<B: Serialize + ?Sized>(
+        &self,
+        url: &str,
+        api_key: Option<&str>,
+        body: &B,
+    ) -> Result<serde_json::Value> {
+        let trace_id = TraceContext::from_env().get_or_generate_trace_id();
+        let span = tracing::info_span!(
+            "llm_http",
+            method = "POST",
+            url = %url,
+            trace_id = %trace_id,
+            status = tracing::field::Empty,
+        );
+
+        async {
+            let mut retries = 0;
+            let response = loop {
+                let mut request = self.inner
+                    .post(url)
+                    .header(TRACE_ID_HEADER, &trace_id)
+                    .json(body);
+                if let Some(key) = api_key {
+                    request = request.bearer_auth(key);
+                }
+
+                let response = request
+                    .send()
+                    .await
+                    .with_context(|| format!("Request to {} failed", url))?;
+                if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS || retries == RATE_LIMIT_RETRIES {
+                    break response;
+                }
+                let delay = retry_after(response.headers())
+                    .unwrap_or(RATE_LIMIT_BACKOFF * 2u32.pow(retries))
+                    .min(MAX_RETRY_AFTER);
+                retries += 1;
+                log::warn!("{} is rate limiting requests, retry {} in {:?}", url, retries, delay);
+                tokio::time::sleep(delay).await;
+            };
+            let status = response.status();
+            tracing::Span::current().record("status", status.as_u16());
+
+            if !status.is_success() {
+                let text = response.text().await.unwrap_or_default();
+                anyhow::bail!("{} returned {}: {}", url, status, text);
+            }
+            response
+                .json()
+                .await
+                .with_context(|| format!("Invalid JSON response from {}", url))
+        }
+        .instrument(span)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Minimal keep-alive HTTP/1.1 server recording connections and request heads
+    struct MockServer {
+        addr: SocketAddr,
+        connections: Arc<AtomicUsize>,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockServer {
+        async fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let connections = Arc::new(AtomicUsize::new(0));
+            let requests = Arc::new(Mutex::new(Vec::new()));
+
+            let (conns, reqs) = (connections.clone(), requests.clone());
+            tokio::spawn(async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    conns.fetch_add(1, Ordering::SeqCst);
+                    tokio::spawn(Self::serve(socket, reqs.clone()));
+                }
+            });
+
+            Self { addr, connections, requests }
+        }
+
+        async fn serve(mut socket: tokio::net::TcpStream, requests: Arc<Mutex<Vec<String>>>) {
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 4096];
+            loop {
+                // Read the request head, then its body
+                let head_end = loop {
+                    if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break i + 4;
+                    }
+                    match socket.read(&mut chunk).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    }
+                };
+                let head = String::from_utf8_lossy(&buf[..head_end]).to_lowercase();
+                let body_len = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("content-length:"))
+                    .and_then(|v| v.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                while buf.len() < head_end + body_len {
+                    match socket.read(&mut chunk).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    }
+                }
+                buf.drain(..head_end + body_len);
+                requests.lock().unwrap().push(head);
+
+                let body = r#"{"ok":true}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                if socket.write_all(response.as_bytes()).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sequential_calls_reuse_one_connection() {
+        let server = MockServer::start().await;
+        let client = Client::new(&HttpConfig::default()).unwrap();
+        let url = format!("http://{}/v1/embeddings", server.addr);
+
+        for _ in 0..3 {
+            let value = client.post_json(&url, None, &serde_json::json!({"input": "x"})).await.unwrap();
+            assert_eq!(value["ok"], true);
+        }
+        // A clone shares the pool
+        client.clone().post_json(&url, None, &serde_json::json!({})).await.unwrap();
+
+        assert_eq!(server.requests.lock().unwrap().len(), 4);
+        assert_eq!(server.connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_and_headers_applied() {
+        let proxy = MockServer::start().await;
+        let config = HttpConfig {
+            proxy: Some(format!("http://{}", proxy.addr)),
+            ..HttpConfig::default()
+        };
+        let client = Client::new(&config).unwrap();
+
+        client
+            .post_json("http://llm.example.invalid/v1/rerank", Some("sk-test"), &serde_json::json!({}))
+            .await
+            .unwrap();
+
+        let requests = proxy.requests.lock().unwrap();
+        let head = &requests[0];
+        // Proxied requests carry the absolute URL in the request line
+        assert!(head.starts_with("post http://llm.example.invalid/v1/rerank http/1.1"), "{}", head);
+        assert!(head.contains(&format!("user-agent: {}", USER_AGENT.to_lowercase())));
+        assert!(head.contains("authorization: bearer sk-test"));
+        assert!(head.contains("x-trace-id: "));
+    }
+
+    #[test]
+    fn test_invalid_ca_cert_is_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = HttpConfig {
+            ca_cert: Some(tmp.path().join("missing.pem")),
+            ..HttpConfig::default()
+        };
+        let err = Client::new(&config).unwrap_err();
+        assert!(err.to_string().contains("Failed to read CA certificate"));
+    }
+}
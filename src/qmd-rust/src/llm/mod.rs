@@ -1,10 +1,16 @@
 use crate::config::Config;
-use anyhow::Result;
+use crate::store::lang::{detect_language, QueryLanguage};
+use anyhow::{Context, Result};
 use std::fmt;
+use std::future::Future;
 use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
 #[cfg(feature = "llama-cpp")]
 use std::sync::Mutex;
 
+pub mod http;
+
 /// Common query expansion terms for knowledge base searches
 const EXPANSION_TERMS: &[(&str, &[&str])] = &[
     ("how", &["how to", "guide", "tutorial"]),
@@ -17,6 +23,27 @@ const EXPANSION_TERMS: &[(&str, &[&str])] = &[
     ("doc", &["documentation", "docs", "guide"]),
 ];
 
+/// Chinese counterparts of `EXPANSION_TERMS`, matched as substrings since
+/// Chinese queries have no spaces between words
+const EXPANSION_TERMS_ZH: &[(&str, &[&str])] = &[
+    ("如何", &["怎么", "怎样", "教程"]),
+    ("什么是", &["定义", "介绍"]),
+    ("为什么", &["原因", "目的"]),
+    ("配置", &["设置", "配置文件"]),
+    ("安装", &["部署", "设置"]),
+    ("错误", &["异常", "问题", "故障"]),
+    ("接口", &["API", "端点"]),
+    ("文档", &["说明", "手册", "指南"]),
+];
+
+/// Expansion terms for one named rule set (see `QueryLanguage::expansion_rules`)
+fn expansion_terms(rules: &str) -> &'static [(&'static str, &'static [&'static str])] {
+    match rules {
+        "chinese" => EXPANSION_TERMS_ZH,
+        _ => EXPANSION_TERMS,
+    }
+}
+
 /// LLM provider types
 #[derive(Debug, Clone)]
 pub enum LLMProvider {
@@ -41,15 +68,52 @@ pub struct EmbeddingResult {
     pub model: String,
 }
 
+/// Future returned by a custom embedder
+pub type EmbedFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<Vec<f32>>>> + Send + 'a>>;
+
+/// Embedding provider installed with `Router::set_embedder`, tried before the
+/// configured models (e.g. an in-process model or a test double)
+pub trait Embed: Send + Sync {
+    fn model_name(&self) -> String;
+    fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a>;
+}
+
+/// Future returned by a text generator
+pub type GenerateFuture<'a> = Pin<Box<dyn Future<Output = Result<String>> + Send + 'a>>;
+
+/// Text generation provider installed with `Router::set_generator`, tried
+/// before the configured remote query expansion model
+pub trait Generate: Send + Sync {
+    fn model_name(&self) -> String;
+    fn generate<'a>(&'a self, prompt: &'a str) -> GenerateFuture<'a>;
+}
+
+/// Future returned by a custom reranker
+pub type RerankFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<f32>>> + Send + 'a>>;
+
+/// Reranking provider installed with `Router::set_reranker`, tried before the
+/// configured models; scores one document text at a time, in order
+pub trait Rerank: Send + Sync {
+    fn model_name(&self) -> String;
+    fn rerank<'a>(&'a self, query: &'a str, docs: &'a [&'a str]) -> RerankFuture<'a>;
+}
+
 /// LLM Router - routes requests to local or remote providers
 pub struct Router {
     config: Config,
+    custom_embedder: Option<Arc<dyn Embed>>,
+    custom_generator: Option<Arc<dyn Generate>>,
+    custom_reranker: Option<Arc<dyn Rerank>>,
     local_embedder: Option<LocalEmbedder>,
     remote_embedder: Option<RemoteEmbedder>,
     local_reranker: Option<LocalReranker>,
     remote_reranker: Option<RemoteReranker>,
     local_query_expander: Option<LocalQueryExpander>,
     remote_query_expander: Option<RemoteQueryExpander>,
+    /// Most variants `expand_query` returns, counting the original
+    max_expansions: usize,
+    /// Pooled HTTP client shared by every remote provider
+    http: http::Client,
 }
 
 impl Router {
@@ -57,21 +121,29 @@ impl Router {
     pub fn new(config: &Config) -> Result<Self> {
         let mut router = Self {
             config: config.clone(),
+            custom_embedder: None,
+            custom_generator: None,
+            custom_reranker: None,
             local_embedder: None,
             remote_embedder: None,
             local_reranker: None,
             remote_reranker: None,
             local_query_expander: None,
             remote_query_expander: None,
+            max_expansions: config.search.max_expansions.max(1),
+            http: http::Client::new(&config.http)?,
         };
 
         // Initialize embedder models
         if let Some(ref models) = config.models.embed {
             if let Some(ref local) = models.local {
-                router.local_embedder = Some(LocalEmbedder::new(local)?);
+                router.local_embedder = Some(LocalEmbedder::new(local)?.with_dimension(config.vector.dimension()));
             }
             if let Some(ref remote) = models.remote {
-                router.remote_embedder = Some(RemoteEmbedder::new(remote)?);
+                router.remote_embedder = Some(
+                    RemoteEmbedder::new(remote, router.http.clone())?
+                        .with_concurrency(config.embed.remote_concurrency()),
+                );
             }
         }
 
@@ -81,17 +153,19 @@ impl Router {
                 router.local_reranker = Some(LocalReranker::new(local)?);
             }
             if let Some(ref remote) = models.remote {
-                router.remote_reranker = Some(RemoteReranker::new(remote)?);
+                router.remote_reranker = Some(RemoteReranker::new(remote, router.http.clone())?);
             }
         }
 
         // Initialize query expansion models
         if let Some(ref models) = config.models.query_expansion {
             if let Some(ref local) = models.local {
-                router.local_query_expander = Some(LocalQueryExpander::new(local)?);
+                router.local_query_expander = Some(
+                    LocalQueryExpander::new(local)?.with_max_expansions(router.max_expansions - 1),
+                );
             }
             if let Some(ref remote) = models.remote {
-                router.remote_query_expander = Some(RemoteQueryExpander::new(remote)?);
+                router.remote_query_expander = Some(RemoteQueryExpander::new(remote, router.http.clone())?);
             }
         }
 
@@ -100,16 +174,120 @@ impl Router {
 
     /// Check if any embedder is available
     pub fn has_embedder(&self) -> bool {
-        self.local_embedder.is_some() || self.remote_embedder.is_some()
+        self.custom_embedder.is_some() || self.local_embedder.is_some() || self.remote_embedder.is_some()
+    }
+
+    /// Embedding requests a bulk embed may have in flight at once
+    ///
+    /// Only a remote embedder runs requests in parallel; custom and local
+    /// embedders, which take precedence over it, get one batch at a time.
+    pub fn embed_concurrency(&self) -> usize {
+        match self.remote_embedder {
+            Some(ref remote) if self.custom_embedder.is_none() && self.local_embedder.is_none() => remote.concurrency(),
+            _ => 1,
+        }
+    }
+
+    /// Use `embedder` ahead of the configured embedding models
+    pub fn set_embedder(&mut self, embedder: Arc<dyn Embed>) {
+        log::info!("Custom embedder installed: {}", embedder.model_name());
+        self.custom_embedder = Some(embedder);
+    }
+
+    /// Check if any model can generate free text
+    pub fn has_generator(&self) -> bool {
+        self.custom_generator.is_some() || self.remote_query_expander.is_some()
+    }
+
+    /// Use `generator` ahead of the configured generation model
+    pub fn set_generator(&mut self, generator: Arc<dyn Generate>) {
+        log::info!("Custom generator installed: {}", generator.model_name());
+        self.custom_generator = Some(generator);
+    }
+
+    /// Generate text for `prompt`
+    ///
+    /// Local query expansion is rule-based, so only a custom generator or the
+    /// remote query expansion model can answer.
+    #[tracing::instrument(skip_all, fields(prompt_len = prompt.len()))]
+    pub async fn generate(&self, prompt: &str) -> Result<String> {
+        if let Some(ref custom) = self.custom_generator {
+            return custom.generate(prompt).await;
+        }
+        if let Some(ref remote) = self.remote_query_expander {
+            return remote.complete(prompt).await;
+        }
+        anyhow::bail!("No generation model available")
     }
 
     /// Check if any reranker is available
     pub fn has_reranker(&self) -> bool {
-        self.local_reranker.is_some() || self.remote_reranker.is_some()
+        self.custom_reranker.is_some() || self.local_reranker.is_some() || self.remote_reranker.is_some()
+    }
+
+    /// Use `reranker` ahead of the configured rerank models
+    pub fn set_reranker(&mut self, reranker: Arc<dyn Rerank>) {
+        log::info!("Custom reranker installed: {}", reranker.model_name());
+        self.custom_reranker = Some(reranker);
+    }
+
+    /// Replace the configured reranker for this router
+    ///
+    /// `model` may be prefixed with `local:` or `remote:` to pick the provider;
+    /// a bare name uses the provider the config sets up (local if both or neither).
+    pub fn override_reranker(&mut self, model: &str) -> Result<()> {
+        let (remote, name) = match model.split_once(':') {
+            Some(("local", name)) => (false, name),
+            Some(("remote", name)) => (true, name),
+            _ => (self.local_reranker.is_none() && self.remote_reranker.is_some(), model),
+        };
+        if name.is_empty() {
+            anyhow::bail!("Rerank model name is empty");
+        }
+
+        if remote {
+            self.remote_reranker = Some(RemoteReranker::new(name, self.http.clone())?);
+            self.local_reranker = None;
+        } else {
+            self.local_reranker = Some(LocalReranker::new(name)?);
+            self.remote_reranker = None;
+        }
+        self.custom_reranker = None;
+        log::info!("Reranker overridden: {} ({})", name, if remote { "remote" } else { "local" });
+        Ok(())
+    }
+
+    /// Name of the model `embed` tries first, if any; stored vectors record
+    /// the model that made them
+    pub fn embedder_model(&self) -> Option<String> {
+        self.custom_embedder
+            .as_ref()
+            .map(|e| e.model_name())
+            .or_else(|| self.local_embedder.as_ref().map(|e| e.model_name()))
+            .or_else(|| self.remote_embedder.as_ref().map(|e| e.model_name()))
+    }
+
+    /// Name of the model `rerank` tries first, if any
+    pub fn reranker_model(&self) -> Option<String> {
+        self.custom_reranker
+            .as_ref()
+            .map(|r| r.model_name())
+            .or_else(|| self.local_reranker.as_ref().map(|r| r.model_name()))
+            .or_else(|| self.remote_reranker.as_ref().map(|r| r.model_name()))
     }
 
     /// Generate embeddings
+    #[tracing::instrument(skip_all, fields(texts = texts.len()))]
     pub async fn embed(&self, texts: &[&str]) -> Result<EmbeddingResult> {
+        if let Some(ref custom) = self.custom_embedder {
+            let embeddings = custom.embed(texts).await?;
+            return Ok(EmbeddingResult {
+                embeddings,
+                provider: LLMProvider::Local,
+                model: custom.model_name(),
+            });
+        }
+
         // Try local first, then remote
         if let Some(ref local) = self.local_embedder {
             match local.embed(texts).await {
@@ -145,6 +323,10 @@ impl Router {
     }
 
     /// Rerank documents
+    ///
+    /// Returns one score per document, in document order. Sets larger than
+    /// `search.rerank_batch_size` are scored in consecutive batches.
+    #[tracing::instrument(skip(self, docs), fields(docs = docs.len()))]
     pub async fn rerank(&self, query: &str, docs: &[crate::store::SearchResult]) -> Result<Vec<f32>> {
         // Build richer document text for reranking: title + filepath for context
         let doc_strings: Vec<String> = docs.iter().map(|d| {
@@ -152,9 +334,31 @@ impl Router {
         }).collect();
         let doc_texts: Vec<&str> = doc_strings.iter().map(|s| s.as_str()).collect();
 
+        let batch_size = self.config.search.rerank_batch_size.unwrap_or(doc_texts.len()).max(1);
+        let mut scores = Vec::with_capacity(doc_texts.len());
+        for batch in doc_texts.chunks(batch_size) {
+            let batch_scores = self.rerank_texts(query, batch).await?;
+            if batch_scores.len() != batch.len() {
+                anyhow::bail!(
+                    "Reranker returned {} scores for {} documents",
+                    batch_scores.len(),
+                    batch.len()
+                );
+            }
+            scores.extend(batch_scores);
+        }
+        Ok(scores)
+    }
+
+    /// Score one batch with the first reranker that succeeds
+    async fn rerank_texts(&self, query: &str, doc_texts: &[&str]) -> Result<Vec<f32>> {
+        if let Some(ref custom) = self.custom_reranker {
+            return custom.rerank(query, doc_texts).await;
+        }
+
         // Try local first
         if let Some(ref local) = self.local_reranker {
-            match local.rerank(query, &doc_texts).await {
+            match local.rerank(query, doc_texts).await {
                 Ok(scores) => return Ok(scores),
                 Err(e) => {
                     log::warn!("Local reranker failed: {}, trying remote", e);
@@ -163,7 +367,7 @@ impl Router {
         }
 
         if let Some(ref remote) = self.remote_reranker {
-            match remote.rerank(query, &doc_texts).await {
+            match remote.rerank(query, doc_texts).await {
                 Ok(scores) => return Ok(scores),
                 Err(e) => {
                     log::error!("Remote reranker failed: {}", e);
@@ -176,20 +380,31 @@ impl Router {
 
     /// Expand query using LLM
     ///
-    /// Generates multiple query variations to improve search recall:
-    /// 1. Original query
-    /// 2. Rule-based expansions (keywords, synonyms)
-    /// 3. LLM-generated variations (if available)
+    /// Generates multiple query variations to improve search recall, in this
+    /// order:
+    /// 1. Original query (always element 0)
+    /// 2. Rule-based expansions (keywords, synonyms), in rule order
+    /// 3. LLM-generated variations (if available), in the order returned
+    ///
+    /// Duplicates keep their first position and the list is never re-sorted,
+    /// so callers can weight variants by position. At most
+    /// `search.max_expansions` variants are returned, the original included.
     pub fn expand_query(&self, query: &str) -> Result<Vec<String>> {
+        self.expand_query_in(query, detect_language(query))
+    }
+
+    /// Expand a query using the rule sets for `lang`
+    #[tracing::instrument(name = "expand_query", skip(self))]
+    pub fn expand_query_in(&self, query: &str, lang: QueryLanguage) -> Result<Vec<String>> {
         // Always include the original query
         let mut expansions = vec![query.to_string()];
 
         // Try local query expander first
         if let Some(ref local) = self.local_query_expander {
-            match local.expand(query) {
-                Ok(mut local_expansions) => {
-                    expansions.append(&mut local_expansions);
+            match local.expand_in(query, lang) {
+                Ok(local_expansions) => {
                     log::info!("Local query expansion generated {} variants", local_expansions.len());
+                    push_unique(&mut expansions, local_expansions);
                 }
                 Err(e) => {
                     log::warn!("Local query expander failed: {}, trying remote", e);
@@ -201,9 +416,9 @@ impl Router {
         if expansions.len() == 1 {
             if let Some(ref remote) = self.remote_query_expander {
                 match remote.expand(query) {
-                    Ok(mut remote_expansions) => {
-                        expansions.append(&mut remote_expansions);
+                    Ok(remote_expansions) => {
                         log::info!("Remote query expansion generated {} variants", remote_expansions.len());
+                        push_unique(&mut expansions, remote_expansions);
                     }
                     Err(e) => {
                         log::warn!("Remote query expander failed: {}", e);
@@ -213,19 +428,21 @@ impl Router {
         }
 
         // Limit the number of expansions to avoid excessive queries
-        let max_expansions = 5;
-        if expansions.len() > max_expansions {
-            expansions.truncate(max_expansions);
-        }
-
-        // Remove duplicates while preserving order
-        expansions.sort();
-        expansions.dedup();
+        expansions.truncate(self.max_expansions);
 
         Ok(expansions)
     }
 }
 
+/// Append variants not already present, keeping first-seen order
+fn push_unique(expansions: &mut Vec<String>, variants: Vec<String>) {
+    for variant in variants {
+        if !expansions.contains(&variant) {
+            expansions.push(variant);
+        }
+    }
+}
+
 /// Cached llama.cpp model state to avoid reloading on every query
 #[cfg(feature = "llama-cpp")]
 struct CachedLlamaModel {
@@ -242,13 +459,15 @@ unsafe impl Send for CachedLlamaModel {}
 pub struct LocalEmbedder {
     model_path: PathBuf,
     model_name: String,
+    /// Width of the fallback vectors used while the model is missing
+    dimension: usize,
     #[cfg(feature = "llama-cpp")]
     cached_model: Mutex<Option<CachedLlamaModel>>,
 }
 
 impl LocalEmbedder {
     pub fn new(model_name: &str) -> Result<Self> {
-        let cache_path = shellexpand::tilde("~/.cache/qmd/models").parse::<PathBuf>()?;
+        let cache_path = crate::paths::expand("~/.cache/qmd/models")?;
         let model_path = cache_path.join(format!("{}.gguf", model_name));
 
         // Check if model file exists
@@ -259,11 +478,18 @@ impl LocalEmbedder {
         Ok(Self {
             model_path,
             model_name: model_name.to_string(),
+            dimension: crate::config::DEFAULT_VECTOR_DIMENSION,
             #[cfg(feature = "llama-cpp")]
             cached_model: Mutex::new(None),
         })
     }
 
+    /// Make fallback vectors `dimension` wide, matching the vector table
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
     pub fn model_name(&self) -> String {
         self.model_name.clone()
     }
@@ -274,7 +500,7 @@ impl LocalEmbedder {
         // Check if model exists, fallback to random if not
         if !self.model_path.exists() {
             log::warn!("Model not found, using random embeddings as fallback");
-            let dim = 384;
+            let dim = self.dimension;
             return Ok(texts.iter()
                 .map(|_| (0..dim).map(|_| rand::random::<f32>()).collect())
                 .collect());
@@ -288,7 +514,7 @@ impl LocalEmbedder {
         #[cfg(not(feature = "llama-cpp"))]
         {
             log::warn!("llama-cpp feature not enabled, using random embeddings as fallback");
-            let dim = 384;
+            let dim = self.dimension;
             Ok(texts.iter()
                 .map(|_| (0..dim).map(|_| rand::random::<f32>()).collect())
                 .collect())
@@ -374,6 +600,7 @@ impl LocalEmbedder {
     }
 
     /// Normalize embedding vector for cosine similarity search
+    #[cfg(any(feature = "llama-cpp", test))]
     fn normalize_embedding(embedding: &[f32]) -> Vec<f32> {
         let magnitude: f32 = embedding.iter()
             .map(|&x| x * x)
@@ -393,39 +620,100 @@ pub struct RemoteEmbedder {
     api_key: String,
     base_url: String,
     model: String,
+    http: http::Client,
+    /// Caps the requests in flight at once, see `embed.remote_concurrency`
+    permits: Arc<tokio::sync::Semaphore>,
+    concurrency: usize,
+}
+
+/// Base URL of the OpenAI API, unless `OPENAI_BASE_URL` points elsewhere
+fn openai_base_url() -> String {
+    std::env::var("OPENAI_BASE_URL")
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|_| "https://api.openai.com/v1".to_string())
 }
 
 impl RemoteEmbedder {
-    pub fn new(model: &str) -> Result<Self> {
+    pub fn new(model: &str, http: http::Client) -> Result<Self> {
         let api_key = std::env::var("OPENAI_API_KEY")
             .or_else(|_| std::env::var("ANTHROPIC_API_KEY"))?;
 
-        let (base_url, model) = if model.starts_with("text-embedding-") {
-            ("https://api.openai.com/v1".to_string(), model.to_string())
-        } else {
-            // Default to OpenAI-compatible API
-            ("https://api.openai.com/v1".to_string(), model.to_string())
-        };
+        // OpenAI and OpenAI-compatible APIs share the embeddings endpoint
+        Ok(Self::with_endpoint(model, &openai_base_url(), &api_key, http))
+    }
 
-        Ok(Self {
-            api_key,
-            base_url,
-            model,
-        })
+    /// Embedder calling the OpenAI-compatible API at `base_url`
+    pub fn with_endpoint(model: &str, base_url: &str, api_key: &str, http: http::Client) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            http,
+            permits: Arc::new(tokio::sync::Semaphore::new(1)),
+            concurrency: 1,
+        }
+    }
+
+    /// Allow `concurrency` requests in flight at once (at least 1)
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self.permits = Arc::new(tokio::sync::Semaphore::new(self.concurrency));
+        self
+    }
+
+    /// Requests this embedder runs at once
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
     }
 
     pub fn model_name(&self) -> String {
         self.model.clone()
     }
 
+    /// Embed all `texts` in one `POST {base_url}/embeddings` request
     pub async fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
-        log::info!("Remote embedding with model: {}", self.model);
+        log::info!("Remote embedding with model: {} ({} texts)", self.model, texts.len());
+        let body = serde_json::json!({
+            "model": self.model,
+            "input": texts,
+        });
+        let _permit = self.permits.acquire().await?;
+        let response = self
+            .http
+            .post_json(&format!("{}/embeddings", self.base_url), Some(&self.api_key), &body)
+            .await?;
+        Self::parse_embeddings(&response, texts.len())
+            .with_context(|| format!("Invalid embeddings response from {}", self.model))
+    }
 
-        // Placeholder: return random embeddings
-        let dim = 1536; // OpenAI ada-002 dimension
-        Ok(texts.iter()
-            .map(|_| (0..dim).map(|_| rand::random::<f32>()).collect())
-            .collect())
+    /// Vectors of an embeddings response, in input order
+    ///
+    /// Each `data[]` entry carries the `index` of the input it embeds; entries
+    /// without one are taken in response order.
+    fn parse_embeddings(response: &serde_json::Value, expected: usize) -> Result<Vec<Vec<f32>>> {
+        let data = response["data"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("missing data array"))?;
+        let mut indexed = Vec::with_capacity(data.len());
+        for (position, entry) in data.iter().enumerate() {
+            let index = entry["index"].as_u64().map_or(position, |i| i as usize);
+            let embedding = entry["embedding"]
+                .as_array()
+                .ok_or_else(|| anyhow::anyhow!("data[{}] has no embedding array", position))?
+                .iter()
+                .map(|x| x.as_f64().map(|x| x as f32))
+                .collect::<Option<Vec<f32>>>()
+                .ok_or_else(|| anyhow::anyhow!("data[{}] has a non-numeric embedding value", position))?;
+            indexed.push((index, embedding));
+        }
+        indexed.sort_by_key(|(index, _)| *index);
+        if indexed.len() != expected {
+            anyhow::bail!("expected {} embeddings, got {}", expected, indexed.len());
+        }
+        if indexed.iter().enumerate().any(|(i, (index, _))| i != *index) {
+            anyhow::bail!("embedding indices do not match the {} inputs", expected);
+        }
+        Ok(indexed.into_iter().map(|(_, embedding)| embedding).collect())
     }
 }
 
@@ -439,7 +727,7 @@ pub struct LocalReranker {
 
 impl LocalReranker {
     pub fn new(model_name: &str) -> Result<Self> {
-        let cache_path = shellexpand::tilde("~/.cache/qmd/models").parse::<PathBuf>()?;
+        let cache_path = crate::paths::expand("~/.cache/qmd/models")?;
         let model_path = cache_path.join(format!("{}.gguf", model_name));
 
         if !model_path.exists() {
@@ -563,14 +851,19 @@ impl LocalReranker {
 }
 
 /// Remote reranking provider
+///
+/// Scores are still placeholders; the credentials and client are kept for
+/// the rerank request.
+#[allow(dead_code)]
 pub struct RemoteReranker {
     api_key: String,
     base_url: String,
     model: String,
+    http: http::Client,
 }
 
 impl RemoteReranker {
-    pub fn new(model: &str) -> Result<Self> {
+    pub fn new(model: &str, http: http::Client) -> Result<Self> {
         let api_key = std::env::var("OPENAI_API_KEY")
             .or_else(|_| std::env::var("ANTHROPIC_API_KEY"))?;
 
@@ -580,9 +873,14 @@ impl RemoteReranker {
             api_key,
             base_url,
             model: model.to_string(),
+            http,
         })
     }
 
+    pub fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
     pub async fn rerank(&self, _query: &str, docs: &[&str]) -> Result<Vec<f32>> {
         log::info!("Remote reranking with model: {}", self.model);
 
@@ -600,30 +898,40 @@ pub trait QueryExpander {
 /// Local query expander (rule-based)
 pub struct LocalQueryExpander {
     model_name: String,
+    /// Most variants returned, not counting the original query
+    max_expansions: usize,
 }
 
 impl LocalQueryExpander {
     pub fn new(model_name: &str) -> Result<Self> {
         Ok(Self {
             model_name: model_name.to_string(),
+            max_expansions: 3,
         })
     }
-}
 
-impl QueryExpander for LocalQueryExpander {
-    fn expand(&self, query: &str) -> Result<Vec<String>> {
-        log::info!("Local query expansion with model: {}", self.model_name);
+    /// Return at most `max` variants
+    pub fn with_max_expansions(mut self, max: usize) -> Self {
+        self.max_expansions = max;
+        self
+    }
+
+    /// Expand a query with the rule sets for `lang`
+    pub fn expand_in(&self, query: &str, lang: QueryLanguage) -> Result<Vec<String>> {
+        log::info!("Local query expansion with model: {} ({})", self.model_name, lang);
 
         let mut expansions = Vec::new();
         let query_lower = query.to_lowercase();
 
         // Rule-based query expansion
-        for (keyword, synonyms) in EXPANSION_TERMS {
-            if query_lower.contains(keyword) {
-                for synonym in *synonyms {
-                    let expansion = query_lower.replace(keyword, synonym);
-                    if expansion != query_lower && !expansions.contains(&expansion) {
-                        expansions.push(expansion);
+        for rules in lang.expansion_rules() {
+            for (keyword, synonyms) in expansion_terms(rules) {
+                if query_lower.contains(keyword) {
+                    for synonym in *synonyms {
+                        let expansion = query_lower.replace(keyword, synonym);
+                        if expansion != query_lower && !expansions.contains(&expansion) {
+                            expansions.push(expansion);
+                        }
                     }
                 }
             }
@@ -644,24 +952,28 @@ impl QueryExpander for LocalQueryExpander {
         }
 
         // Limit expansions
-        let max_expansions = 3;
-        if expansions.len() > max_expansions {
-            expansions.truncate(max_expansions);
-        }
+        expansions.truncate(self.max_expansions);
 
         Ok(expansions)
     }
 }
 
+impl QueryExpander for LocalQueryExpander {
+    fn expand(&self, query: &str) -> Result<Vec<String>> {
+        self.expand_in(query, detect_language(query))
+    }
+}
+
 /// Remote query expander (LLM-based)
 pub struct RemoteQueryExpander {
     api_key: String,
     base_url: String,
     model: String,
+    http: http::Client,
 }
 
 impl RemoteQueryExpander {
-    pub fn new(model: &str) -> Result<Self> {
+    pub fn new(model: &str, http: http::Client) -> Result<Self> {
         let api_key = std::env::var("OPENAI_API_KEY")
             .or_else(|_| std::env::var("ANTHROPIC_API_KEY"))?;
 
@@ -676,10 +988,31 @@ impl RemoteQueryExpander {
             api_key,
             base_url,
             model,
+            http,
         })
     }
 }
 
+impl RemoteQueryExpander {
+    /// Single chat completion for `prompt` (OpenAI-compatible API)
+    pub async fn complete(&self, prompt: &str) -> Result<String> {
+        log::info!("Remote generation with model: {}", self.model);
+        let body = serde_json::json!({
+            "model": self.model,
+            "messages": [{"role": "user", "content": prompt}],
+            "temperature": 0.2,
+        });
+        let response = self
+            .http
+            .post_json(&format!("{}/chat/completions", self.base_url), Some(&self.api_key), &body)
+            .await?;
+        response["choices"][0]["message"]["content"]
+            .as_str()
+            .map(|text| text.trim().to_string())
+            .ok_or_else(|| anyhow::anyhow!("Completion response from {} has no content", self.model))
+    }
+}
+
 impl QueryExpander for RemoteQueryExpander {
     fn expand(&self, query: &str) -> Result<Vec<String>> {
         log::info!("Remote query expansion with model: {}", self.model);
@@ -760,6 +1093,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_local_expander_picks_rules_by_language() {
+        let expander = LocalQueryExpander::new("rule-based").unwrap();
+
+        let chinese = expander.expand("如何安装").unwrap();
+        assert!(chinese.contains(&"怎么安装".to_string()), "{:?}", chinese);
+
+        // Forced to English, the Chinese dictionary does not apply
+        let english = expander.expand_in("如何安装", QueryLanguage::English).unwrap();
+        assert!(english.is_empty(), "{:?}", english);
+
+        let mixed = expander.expand("api 文档").unwrap();
+        assert!(mixed.contains(&"interface 文档".to_string()), "{:?}", mixed);
+        assert!(mixed.contains(&"api 说明".to_string()), "{:?}", mixed);
+    }
+
     // ==================== Router expand_query Tests ====================
 
     #[test]
@@ -826,6 +1175,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_router_expand_query_keeps_original_first() {
+        let config = crate::config::Config {
+            models: crate::config::ModelsConfig {
+                embed: None,
+                rerank: None,
+                query_expansion: Some(crate::config::LLMModelConfig {
+                    local: Some("rule-based".to_string()),
+                    remote: None,
+                }),
+            },
+            ..crate::config::Config::default()
+        };
+        let router = Router::new(&config).unwrap();
+
+        // "zzz" would sort after every rule-based variant
+        for query in ["zzz config setup", "how to install rust", "zzz", "所有权 文档"] {
+            let result = router.expand_query(query).unwrap();
+            assert_eq!(result[0], query, "original must come first: {:?}", result);
+        }
+
+        // Rule-based variants follow in the expander's own order
+        let local = LocalQueryExpander::new("rule-based").unwrap();
+        let expected: Vec<String> = std::iter::once("zzz config setup".to_string())
+            .chain(local.expand("zzz config setup").unwrap())
+            .take(5)
+            .collect();
+        assert_eq!(router.expand_query("zzz config setup").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_router_expand_query_respects_max_expansions() {
+        let mut config = crate::config::Config {
+            models: crate::config::ModelsConfig {
+                embed: None,
+                rerank: None,
+                query_expansion: Some(crate::config::LLMModelConfig {
+                    local: Some("rule-based".to_string()),
+                    remote: None,
+                }),
+            },
+            ..crate::config::Config::default()
+        };
+        config.search.max_expansions = 2;
+        let router = Router::new(&config).unwrap();
+
+        let result = router.expand_query("how to install config api").unwrap();
+        assert_eq!(result.len(), 2, "{:?}", result);
+        assert_eq!(result[0], "how to install config api");
+
+        // The original is always kept, even with a limit of 1 or 0
+        for max in [0, 1] {
+            config.search.max_expansions = max;
+            let router = Router::new(&config).unwrap();
+            assert_eq!(router.expand_query("config setup").unwrap(), vec!["config setup"]);
+        }
+    }
+
+    #[test]
+    fn test_push_unique_keeps_first_position() {
+        let mut expansions = vec!["b".to_string()];
+        push_unique(&mut expansions, vec!["a".into(), "b".into(), "c".into(), "a".into()]);
+        assert_eq!(expansions, vec!["b", "a", "c"]);
+    }
+
     // ==================== Router has_embedder / has_reranker Tests ====================
 
     #[test]
@@ -933,4 +1347,60 @@ mod tests {
         assert!(router.has_reranker(), "Router should have reranker when config provides one");
         assert!(!router.has_embedder(), "Router should not have embedder without embed config");
     }
+
+    fn make_search_result(path: &str) -> crate::store::SearchResult {
+        crate::store::SearchResult {
+            docid: crate::store::make_docid("test", path),
+            path: path.to_string(),
+            collection: "test".to_string(),
+            score: 0.0,
+            lines: 0,
+            title: path.to_string(),
+            hash: path.to_string(),
+            query: None,
+            size: None,
+            match_line: None,
+            duplicates: Vec::new(),
+            source: Vec::new(),
+            score_components: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_override_reranker_replaces_configured_model() {
+        let config = crate::config::Config {
+            models: crate::config::ModelsConfig {
+                embed: None,
+                rerank: Some(crate::config::LLMModelConfig {
+                    local: Some("bge-reranker-v2-m3-Q8_0".to_string()),
+                    remote: None,
+                }),
+                query_expansion: None,
+            },
+            ..crate::config::Config::default()
+        };
+
+        let mut router = Router::new(&config).unwrap();
+        assert_eq!(router.reranker_model().as_deref(), Some("bge-reranker-v2-m3-Q8_0"));
+
+        router.override_reranker("local:mock-reranker").unwrap();
+        assert_eq!(router.reranker_model().as_deref(), Some("mock-reranker"));
+
+        let docs = vec![make_search_result("a.md"), make_search_result("b.md")];
+        let scores = router.rerank("query", &docs).await.unwrap();
+        assert_eq!(scores.len(), 2);
+    }
+
+    #[test]
+    fn test_override_reranker_without_config() {
+        let mut router = Router::new(&crate::config::Config::default()).unwrap();
+        assert_eq!(router.reranker_model(), None);
+
+        // A bare name defaults to a local reranker
+        router.override_reranker("mock-reranker").unwrap();
+        assert!(router.has_reranker());
+        assert_eq!(router.reranker_model().as_deref(), Some("mock-reranker"));
+
+        assert!(router.override_reranker("local:").is_err());
+    }
 }
@@ -0,0 +1,259 @@
+//! Log output format for the CLI and the server.
+//!
+//! Human-readable env_logger lines are the default. `--log-format json` (or
+//! `QMD_LOG_FORMAT=json`) switches to one JSON object per stderr line with
+//! `timestamp`, `level`, `target`, `message` and, when the calling agent set
+//! `AGENT_TRACE_ID`, `trace_id`, so log collectors can ingest qmd without
+//! scraping text. `RUST_LOG` selects the level in both formats, and `log`
+//! records are bridged into the JSON output alongside `tracing` events.
+//! Messages the text format prints straight to stderr, like collection
+//! preflight warnings, are logged as records instead so every line parses.
+
+use crate::anel::TraceContext;
+use crate::profile::{self, Profile};
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Subscriber};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable selecting the log format when `--log-format` is not given
+pub const LOG_FORMAT_ENV: &str = "QMD_LOG_FORMAT";
+
+/// Log line format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines (env_logger)
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl LogFormat {
+    /// `--log-format` if given, else `QMD_LOG_FORMAT`, else text
+    pub fn resolve(flag: Option<LogFormat>) -> Result<Self> {
+        if let Some(format) = flag {
+            return Ok(format);
+        }
+        match std::env::var(LOG_FORMAT_ENV) {
+            Ok(value) if !value.trim().is_empty() => LogFormat::from_str(value.trim(), true)
+                .map_err(|_| anyhow!("Invalid {}={:?}: expected text or json", LOG_FORMAT_ENV, value)),
+            _ => Ok(Self::Text),
+        }
+    }
+}
+
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+
+/// Format installed by `init`; text if logging was never initialized
+pub fn format() -> LogFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Installed logging; completes the `--profile` trace when dropped
+pub struct Logging {
+    _profile: Option<Profile>,
+}
+
+/// Install process-wide logging, optionally recording a timing profile too
+pub fn init(format: LogFormat, profile_path: Option<&Path>) -> Result<Logging> {
+    let _ = FORMAT.set(format);
+    match format {
+        LogFormat::Text => {
+            env_logger::init();
+            let profile = profile_path.map(profile::start).transpose()?;
+            Ok(Logging { _profile: profile })
+        }
+        LogFormat::Json => {
+            // Warnings by default, since they replace the text format's direct stderr output
+            let filter = EnvFilter::builder()
+                .with_default_directive(LevelFilter::WARN.into())
+                .from_env_lossy();
+            let (chrome, guard) = match profile_path {
+                Some(path) => {
+                    let (layer, guard) = profile::layer(path)?;
+                    (Some(layer), Some(guard))
+                }
+                None => (None, None),
+            };
+            // The filter is per-layer so it never hides spans from the profiler
+            tracing_subscriber::registry()
+                .with(json_layer().with_filter(filter))
+                .with(chrome)
+                .try_init()
+                .context("Failed to install JSON logger")?;
+            if let Some(path) = profile_path {
+                log::info!("Writing timing profile to {}", path.display());
+            }
+            Ok(Logging { _profile: guard.map(Profile::from_guard) })
+        }
+    }
+}
+
+/// Layer writing events to stderr as JSON lines
+pub fn json_layer<S>() -> impl tracing_subscriber::Layer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(std::io::stderr)
+        .event_format(JsonLines {
+            trace_id: TraceContext::from_env().trace_id,
+        })
+}
+
+/// Event formatter emitting one JSON object per event
+struct JsonLines {
+    trace_id: Option<String>,
+}
+
+impl<S, N> FormatEvent<S, N> for JsonLines
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    N: for<'w> FormatFields<'w> + 'static,
+{
+    fn format_event(&self, _ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        // Records bridged from `log` carry their real target and level here
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+
+        let mut record = serde_json::Map::new();
+        record.insert(
+            "timestamp".to_string(),
+            chrono::Utc::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+                .into(),
+        );
+        record.insert("level".to_string(), metadata.level().to_string().into());
+        record.insert("target".to_string(), metadata.target().into());
+        record.insert("message".to_string(), fields.message.unwrap_or_default().into());
+        if let Some(ref trace_id) = self.trace_id {
+            record.insert("trace_id".to_string(), trace_id.clone().into());
+        }
+        // Event fields last, so an explicit `trace_id` field wins over the env
+        record.extend(fields.values);
+
+        writeln!(writer, "{}", serde_json::Value::Object(record))
+    }
+}
+
+/// Collects an event's message and structured fields
+#[derive(Default)]
+struct JsonFields {
+    message: Option<String>,
+    values: serde_json::Map<String, serde_json::Value>,
+}
+
+impl JsonFields {
+    fn insert(&mut self, field: &Field, value: serde_json::Value) {
+        // Source locations added by the `log` bridge are noise in the output
+        if field.name().starts_with("log.") {
+            return;
+        }
+        if field.name() == "message" {
+            self.message = Some(match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            });
+        } else {
+            self.values.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for JsonFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{:?}", value).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_lines_format() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let layer = tracing_subscriber::fmt::layer()
+            .with_writer(move || writer.clone())
+            .event_format(JsonLines {
+                trace_id: Some("trace-123".to_string()),
+            });
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: "qmd_rust::store", docs = 3, "Indexed \"notes\"");
+            tracing::warn!(trace_id = "explicit", "Override");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2, "{}", output);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[0]["target"], "qmd_rust::store");
+        assert_eq!(lines[0]["message"], "Indexed \"notes\"");
+        assert_eq!(lines[0]["trace_id"], "trace-123");
+        assert_eq!(lines[0]["docs"], 3);
+        assert!(lines[0]["timestamp"].as_str().unwrap().ends_with('Z'));
+        assert_eq!(lines[1]["level"], "WARN");
+        assert_eq!(lines[1]["trace_id"], "explicit");
+    }
+
+    #[test]
+    fn test_resolve_prefers_flag() {
+        assert_eq!(LogFormat::resolve(Some(LogFormat::Json)).unwrap(), LogFormat::Json);
+        assert_eq!(LogFormat::from_str("JSON", true).unwrap(), LogFormat::Json);
+    }
+}
@@ -1,22 +1,24 @@
-use crate::cli::Commands;
-use crate::config::Config;
 use anyhow::{Context, Result};
 use clap::Parser;
 use log::info;
+use qmd_rust::cli::Commands;
+use qmd_rust::config::Config;
+use qmd_rust::{cli, config, llm, logging, mcp, server, store};
+use std::process::ExitCode;
 
-mod anel;
-mod cli;
-mod config;
-mod formatter;
-mod llm;
-mod mcp;
-mod plugin;
-mod server;
-mod store;
+fn main() -> Result<ExitCode> {
+    // Parse CLI arguments
+    let cli = cli::Cli::parse();
 
-fn main() -> Result<()> {
-    // Initialize logger
-    env_logger::init();
+    // Initialize logger; spans are only collected when profiling and the
+    // trace is written when this drops
+    let log_format = logging::LogFormat::resolve(cli.log_format)?;
+    let _logging = logging::init(log_format, cli.profile.as_deref())?;
+
+    // Load env file before anything reads API keys from the environment
+    if let Some(ref env_file) = cli.env_file {
+        config::load_env_file(env_file)?;
+    }
 
     // Load configuration
     let mut config = Config::load().context("Failed to load configuration")?;
@@ -25,10 +27,8 @@ fn main() -> Result<()> {
     info!("BM25 backend: {:?}", config.bm25.backend);
     info!("Vector backend: {:?}", config.vector.backend);
 
-    // Parse CLI arguments
-    let cli = cli::Cli::parse();
-
     // Dispatch commands
+    let mut exit_code = ExitCode::SUCCESS;
     match &cli.command {
         Commands::Collection(cmd) => {
             crate::cli::collection::handle(cmd, &mut config)?;
@@ -46,49 +46,89 @@ fn main() -> Result<()> {
             crate::cli::multi_get::handle(cmd, &config)?;
         }
         Commands::Search(cmd) => {
-            let store = store::Store::new(&config)?;
+            let config = with_backend_overrides(&cmd.format, &config);
+            let store = open_store(&config, cli.quiet)?;
             crate::cli::search::handle(cmd, &store)?;
         }
         Commands::Vsearch(cmd) => {
-            let store = store::Store::new(&config)?;
+            let config = with_backend_overrides(&cmd.format, &config);
+            let store = open_store(&config, cli.quiet)?;
             let llm = llm::Router::new(&config)?;
             crate::cli::vsearch::handle(cmd, &store, &llm)?;
         }
         Commands::Query(cmd) => {
-            let store = store::Store::new(&config)?;
-            let llm = llm::Router::new(&config)?;
+            let mut config = with_backend_overrides(&cmd.format, &config);
+            if let Some(max) = cmd.max_expansions {
+                config.search.max_expansions = max;
+            }
+            if cmd.rerank_batch_size.is_some() {
+                config.search.rerank_batch_size = cmd.rerank_batch_size;
+            }
+            let store = open_store(&config, cli.quiet)?;
+            let mut llm = llm::Router::new(&config)?;
+            if let Some(ref model) = cmd.rerank_model {
+                llm.override_reranker(model)?;
+            }
             crate::cli::query::handle(cmd, &store, &llm)?;
         }
         Commands::Embed(cmd) => {
-            let store = store::Store::new(&config)?;
+            let store = open_store(&config, cli.quiet)?;
             let llm = llm::Router::new(&config)?;
-            crate::cli::embed::handle(cmd, &store, &llm)?;
+            crate::cli::embed::handle(cmd, &store, &llm, &config)?;
         }
         Commands::Update(cmd) => {
-            let store = store::Store::new(&config)?;
-            crate::cli::update::handle(cmd, &store)?;
+            let store = open_store(&config, cli.quiet)?;
+            exit_code = crate::cli::update::handle(cmd, &store)?;
+        }
+        Commands::Sync(cmd) => {
+            let store = open_store(&config, cli.quiet)?;
+            let llm = llm::Router::new(&config)?;
+            crate::cli::sync::handle(cmd, &store, &llm)?;
         }
         Commands::Status(cmd) => {
-            let store = store::Store::new(&config)?;
+            let store = open_store(&config, cli.quiet)?;
             crate::cli::status::handle(cmd, &store)?;
         }
+        Commands::Suggest(cmd) => {
+            let store = open_store(&config, cli.quiet)?;
+            crate::cli::suggest::handle(cmd, &store)?;
+        }
         Commands::Cleanup(cmd) => {
-            let store = store::Store::new(&config)?;
+            let store = open_store(&config, cli.quiet)?;
             crate::cli::cleanup::handle(cmd, &store)?;
         }
+        Commands::Trash(cmd) => {
+            crate::cli::trash::handle(cmd, &mut config)?;
+        }
+        Commands::Rm(cmd) => {
+            let store = open_store(&config, cli.quiet)?;
+            crate::cli::rm::handle(cmd, &store)?;
+        }
+        Commands::Verify(cmd) => {
+            let store = open_store(&config, cli.quiet)?;
+            exit_code = crate::cli::verify::handle(cmd, &store)?;
+        }
+        Commands::Reindex(cmd) => {
+            let store = open_store(&config, cli.quiet)?;
+            crate::cli::reindex::handle(cmd, &store)?;
+        }
         Commands::Mcp(cmd) => {
             mcp::run_server(cmd, &config)?;
         }
         Commands::Server(cmd) => {
-            // Parse API keys from comma-separated string
-            let api_keys: Vec<(String, String)> = cmd.api_keys
+            if let Some(crate::cli::ServerCommands::Keys(keys)) = &cmd.command {
+                crate::cli::server::handle_keys(keys, &config)?;
+                return Ok(exit_code);
+            }
+
+            // Parse API keys from comma-separated string; `key=a+b` limits a key to collections
+            let mut api_keys: Vec<server::middleware::ApiKey> = cmd.api_keys
                 .as_ref()
-                .map(|s| {
-                    s.split(',')
-                        .map(|k| (k.trim().to_string(), "default".to_string()))
-                        .collect()
-                })
+                .map(|s| s.split(',').map(server::middleware::ApiKey::parse).collect())
                 .unwrap_or_default();
+            if let Some(path) = &config.server.api_keys_file {
+                api_keys.extend(server::keys::KeyFile::load(path)?.api_keys());
+            }
 
             // Parse whitelist IPs from comma-separated string
             let whitelist_ips: Vec<String> = cmd.whitelist_ips
@@ -109,11 +149,13 @@ fn main() -> Result<()> {
                 auth_enabled: cmd.auth,
                 api_keys,
                 whitelist_ips,
+                mcp_audit: !cmd.no_mcp_audit && config.server.mcp_audit(),
+                preload: cmd.preload || config.preload,
             };
             server::run_server(&server_config, &config)?;
         }
         Commands::Agent(cmd) => {
-            let store = store::Store::new(&config)?;
+            let store = open_store(&config, cli.quiet)?;
             let llm = llm::Router::new(&config)?;
             crate::cli::agent::handle(cmd, &store, &llm)?;
         }
@@ -122,5 +164,29 @@ fn main() -> Result<()> {
         }
     }
 
-    Ok(())
+    Ok(exit_code)
+}
+
+/// Open the store and print its preflight warnings once, unless --quiet
+fn open_store(config: &Config, quiet: bool) -> Result<store::Store> {
+    let store = store::Store::new(config)?;
+    if !quiet {
+        for warning in store.warnings() {
+            match logging::format() {
+                logging::LogFormat::Text => eprintln!("Warning: {}", warning),
+                logging::LogFormat::Json => log::warn!("{}", warning),
+            }
+        }
+    }
+    Ok(store)
+}
+
+/// Apply per-invocation backend flags to a copy of the loaded config
+fn with_backend_overrides(options: &cli::FormatOptions, config: &Config) -> Config {
+    let config = options.apply_backend_overrides(config);
+    info!(
+        "Using BM25 backend: {:?}, vector backend: {:?}",
+        config.bm25.backend, config.vector.backend
+    );
+    config
 }
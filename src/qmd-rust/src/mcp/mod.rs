@@ -1,8 +1,16 @@
-use crate::anel::{self, TraceContext};
+use crate::anel::identity::IdentityVerifier;
+use crate::anel::{self, AnelError, AnelErrorCode, TraceContext};
+use crate::cli::embed::EmbedJob;
 use crate::cli::McpArgs;
 use crate::config::Config;
+use crate::formatter::{group_results, GroupBy};
 use crate::llm::Router;
-use crate::store::{SearchOptions, Store};
+use crate::preload::{self, PreloadReport};
+use crate::server::observability::AuditLog;
+use crate::store::deadline::{Deadline, SearchOutcome, SearchStage};
+use crate::store::report::CollectionReport;
+use crate::store::timings::StageTimings;
+use crate::store::{lang, page, run_hybrid_search, SearchOptions, Store};
 use anyhow::Result;
 use bytes::Bytes;
 use http_body_util::BodyExt;
@@ -10,7 +18,7 @@ use rmcp::handler::server::tool::ToolRouter;
 use rmcp::handler::server::wrapper::Parameters;
 use rmcp::model::*;
 use rmcp::service::{RequestContext, RoleServer, ServiceExt};
-use rmcp::{tool, tool_router, ErrorData as McpError, ServerHandler};
+use rmcp::{tool, tool_handler, tool_router, ErrorData as McpError, Peer, ServerHandler};
 use schemars::JsonSchema;
 use serde::Deserialize;
 use std::sync::{Arc, Mutex};
@@ -20,41 +28,91 @@ use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 // ── Stream Tap (Audit Layer) ─────────────────────────────────────
 
-/// Audit logger that records every MCP tool invocation as NDJSON to stderr.
-#[derive(Clone, Debug)]
+/// Audit logger that records every MCP tool invocation as NDJSON to stderr,
+/// or appends it to `AGENT_AUDIT_FILE` (rotated by size) when that is set.
+#[derive(Clone)]
 struct StreamTap {
     identity: Option<String>,
+    /// Configured name of the verified identity token
+    identity_name: Option<String>,
+    /// Why the identity was refused, when `identity.enforce` is on
+    denied: Option<AnelError>,
     trace_id: String,
+    sink: Arc<AuditLog>,
 }
 
 impl StreamTap {
-    fn new() -> Self {
+    fn new(verifier: &IdentityVerifier) -> Result<Self> {
         let ctx = TraceContext::from_env();
-        Self {
-            identity: std::env::var(anel::env::IDENTITY_TOKEN).ok(),
+        let identity = std::env::var(anel::env::IDENTITY_TOKEN).ok();
+        let (identity_name, denied) = match verifier.verify(identity.as_deref()) {
+            Ok(name) => (name, None),
+            Err(error) => {
+                log::warn!("Agent identity rejected: {}", error.message);
+                (None, Some(*error))
+            }
+        };
+        Ok(Self {
+            identity,
+            identity_name,
+            denied,
             trace_id: ctx.get_or_generate_trace_id(),
-        }
+            sink: Arc::new(AuditLog::from_env(true)?),
+        })
     }
 
     fn log(&self, tool_name: &str, args_summary: &str, status: &str, duration_ms: u64) {
-        let timestamp_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_millis())
-            .unwrap_or(0);
-        let record = serde_json::json!({
-            "type": "audit",
-            "timestamp": timestamp_ms,
-            "tool": tool_name,
-            "trace_id": self.trace_id,
-            "X-Agent-Identity": self.identity,
-            "args": args_summary,
-            "status": status,
-            "duration_ms": duration_ms,
-        });
-        eprintln!("{}", serde_json::to_string(&record).unwrap_or_default());
+        let mut record = audit_record(
+            tool_name,
+            &self.trace_id,
+            self.identity.as_deref(),
+            args_summary,
+            status,
+            duration_ms,
+        );
+        if let Some(ref name) = self.identity_name {
+            record["identity_name"] = serde_json::json!(name);
+        }
+        self.sink.log(&record);
+    }
+}
+
+/// Add per-stage `timings` to a JSON args summary for the audit record
+fn args_with_timings(args: &str, timings: &StageTimings) -> String {
+    match serde_json::from_str::<serde_json::Value>(args) {
+        Ok(serde_json::Value::Object(mut map)) => {
+            map.insert("timings".to_string(), serde_json::json!(timings));
+            serde_json::Value::Object(map).to_string()
+        }
+        _ => args.to_string(),
     }
 }
 
+/// Build a StreamTap audit record (shared with the HTTP server's `/mcp` bridge)
+pub fn audit_record(
+    tool_name: &str,
+    trace_id: &str,
+    identity: Option<&str>,
+    args_summary: &str,
+    status: &str,
+    duration_ms: u64,
+) -> serde_json::Value {
+    let timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    serde_json::json!({
+        "type": "audit",
+        "timestamp": timestamp_ms,
+        "tool": tool_name,
+        "trace_id": trace_id,
+        "X-Agent-Identity": identity,
+        "args": args_summary,
+        "status": status,
+        "duration_ms": duration_ms,
+    })
+}
+
 // ── Parameter types ──────────────────────────────────────────────
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -63,22 +121,60 @@ pub struct SearchParams {
     pub query: String,
     /// Maximum number of results (default: 20)
     pub limit: Option<usize>,
+    /// Results to skip before the first one returned, for paging (default: 0)
+    pub offset: Option<usize>,
     /// Collection name to search in
     pub collection: Option<String>,
+    /// Overall time budget in milliseconds for vsearch/query; on expiry the
+    /// results gathered so far are returned, marked partial
+    pub timeout_ms: Option<u64>,
+    /// Cluster results by "collection" or top-level "dir"; the structured
+    /// content then carries `groups` alongside the flat `results`
+    pub group_by: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct GetParams {
-    /// File path of the document to retrieve
+    /// Document path: collection/relative path (as returned by search), qmd:// path,
+    /// or a file inside a collection
     pub path: String,
     /// Start line number (0-based, default: 0)
     pub from: Option<usize>,
     /// Maximum number of lines to return (default: 50)
     pub limit: Option<usize>,
+    /// Hash from a previous get; if the document is unchanged only
+    /// `{unchanged: true, hash}` is returned instead of the content
+    pub if_hash: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct EmbedParams {
+    /// Collection to embed (default: every collection)
+    pub collection: Option<String>,
+    /// Re-embed documents that already have vectors (default: false)
+    pub force: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct StatusParams {
+    /// Include a per-collection size report: disk usage, top extensions,
+    /// largest documents (default: false)
+    pub detailed: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SuggestParams {
+    /// Start of the word to complete
+    pub prefix: String,
+    /// Maximum number of suggestions (default: 10)
+    pub limit: Option<usize>,
 }
 
 // ── MCP Server ───────────────────────────────────────────────────
 
+/// Names of the tools [`QmdMcpServer`] registers
+pub const TOOL_NAMES: &[&str] = &["search", "vsearch", "query", "get", "embed", "status", "suggest"];
+
 #[derive(Clone)]
 pub struct QmdMcpServer {
     store: Arc<Mutex<Store>>,
@@ -91,6 +187,51 @@ pub struct QmdMcpServer {
 // ── Dry-run / audit helpers (outside #[tool_router] block) ───────
 
 impl QmdMcpServer {
+    /// Warm every collection and load the embedding model before serving
+    pub async fn preload(&self) -> Result<PreloadReport> {
+        let mut report = preload::warm_collections(&self.store.lock().unwrap())?;
+        preload::warm_model(&*self.llm.lock().await, &mut report).await;
+        log::info!("Preload complete in {:.1?}", report.total());
+        Ok(report)
+    }
+
+    /// Log and render a finished vsearch/query, noting stages skipped or failed
+    fn search_result(
+        &self,
+        tool_name: &str,
+        args: &str,
+        start: Instant,
+        outcome: &SearchOutcome,
+        group_by: Option<GroupBy>,
+    ) -> CallToolResult {
+        let status = if outcome.is_partial() { "partial" } else { "ok" };
+        let args = match outcome.timings {
+            Some(ref timings) => args_with_timings(args, timings),
+            None => args.to_string(),
+        };
+        self.tap.log(tool_name, &args, status, start.elapsed().as_millis() as u64);
+        let mut text = format_search_results(&outcome.results, group_by);
+        if !outcome.skipped.is_empty() {
+            let stages: Vec<String> = outcome.skipped.iter().map(|s| s.to_string()).collect();
+            text.push_str(&format!("\n\nPartial results: timed out before {}", stages.join(", ")));
+        }
+        for failure in &outcome.degraded {
+            text.push_str(&format!("\n\nPartial results: {} stage failed: {}", failure.stage, failure.error));
+        }
+        with_groups(CallToolResult::success(vec![Content::text(text)]), &outcome.results, group_by)
+    }
+
+    /// Refuse every tool call when identity enforcement rejected the caller
+    fn check_identity(&self, tool_name: &str, args: &str) -> Result<(), McpError> {
+        match self.tap.denied {
+            Some(ref error) => {
+                self.tap.log(tool_name, args, "denied", 0);
+                Err(McpError::invalid_request(error.to_string(), serde_json::to_value(error).ok()))
+            }
+            None => Ok(()),
+        }
+    }
+
     fn check_dry_run(&self, tool_name: &str, args: &str) -> Option<CallToolResult> {
         if self.dry_run {
             self.tap.log(tool_name, args, "dry-run", 0);
@@ -111,7 +252,7 @@ impl QmdMcpServer {
         let dry_run = std::env::var(anel::env::DRY_RUN)
             .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
             .unwrap_or(false);
-        let tap = StreamTap::new();
+        let tap = StreamTap::new(&IdentityVerifier::new(&config.identity))?;
         Ok(Self {
             store: Arc::new(Mutex::new(store)),
             llm: Arc::new(tokio::sync::Mutex::new(llm)),
@@ -128,9 +269,12 @@ impl QmdMcpServer {
     ) -> Result<CallToolResult, McpError> {
         let p = params.0;
         let args_summary = serde_json::to_string(&serde_json::json!({
-            "query": &p.query, "limit": p.limit, "collection": &p.collection
+            "query": &p.query, "limit": p.limit, "offset": p.offset, "collection": &p.collection, "group_by": &p.group_by
         })).unwrap_or_default();
 
+        self.check_identity("search", &args_summary)?;
+        let group_by = parse_group_by(&p)?;
+
         if let Some(result) = self.check_dry_run("search", &args_summary) {
             return Ok(result);
         }
@@ -141,12 +285,15 @@ impl QmdMcpServer {
             self.tap.log("search", &args_summary, "error", start.elapsed().as_millis() as u64);
             McpError::internal_error(format!("Store lock failed: {e}"), None)
         })?;
-        match store.bm25_search(&p.query, options) {
+        match store.bm25_search(&p.query, options.unpaged()) {
             Ok(results) => {
+                let results = page(results, &options);
                 self.tap.log("search", &args_summary, "ok", start.elapsed().as_millis() as u64);
-                Ok(CallToolResult::success(vec![Content::text(
-                    format_search_results(&results),
-                )]))
+                Ok(with_groups(
+                    CallToolResult::success(vec![Content::text(format_search_results(&results, group_by))]),
+                    &results,
+                    group_by,
+                ))
             }
             Err(e) => {
                 self.tap.log("search", &args_summary, "error", start.elapsed().as_millis() as u64);
@@ -165,24 +312,37 @@ impl QmdMcpServer {
     ) -> Result<CallToolResult, McpError> {
         let p = params.0;
         let args_summary = serde_json::to_string(&serde_json::json!({
-            "query": &p.query, "limit": p.limit, "collection": &p.collection
+            "query": &p.query, "limit": p.limit, "offset": p.offset, "collection": &p.collection, "timeout_ms": p.timeout_ms,
+            "group_by": &p.group_by
         })).unwrap_or_default();
 
+        self.check_identity("vsearch", &args_summary)?;
+        let group_by = parse_group_by(&p)?;
+
         if let Some(result) = self.check_dry_run("vsearch", &args_summary) {
             return Ok(result);
         }
 
         let start = Instant::now();
+        let deadline = Deadline::new(p.timeout_ms);
         let options = make_search_options(&p);
+        let mut outcome = SearchOutcome::default();
 
         // Step 1: Generate embedding (async, no store lock)
-        let embedding = {
-            let llm = self.llm.lock().await;
-            llm.embed(&[p.query.as_str()]).await.map_err(|e| {
-                self.tap.log("vsearch", &args_summary, "error", start.elapsed().as_millis() as u64);
-                McpError::internal_error(format!("Embedding failed: {e}"), None)
-            })?
+        let embedding = deadline
+            .run(async {
+                let llm = self.llm.lock().await;
+                llm.embed(&[p.query.as_str()]).await
+            })
+            .await;
+        let Some(embedding) = embedding else {
+            outcome.skip(SearchStage::Vector);
+            return Ok(self.search_result("vsearch", &args_summary, start, &outcome, group_by));
         };
+        let embedding = embedding.map_err(|e| {
+            self.tap.log("vsearch", &args_summary, "error", start.elapsed().as_millis() as u64);
+            McpError::internal_error(format!("Embedding failed: {e}"), None)
+        })?;
         let query_vector = &embedding.embeddings[0];
 
         // Step 2: Sync vector search with pre-computed embedding
@@ -190,18 +350,18 @@ impl QmdMcpServer {
             self.tap.log("vsearch", &args_summary, "error", start.elapsed().as_millis() as u64);
             McpError::internal_error(format!("Store lock failed: {e}"), None)
         })?;
-        match store.vector_search_with_embedding(query_vector, options) {
+        match store.vector_search_with_embedding(query_vector, options.unpaged()) {
             Ok(results) => {
-                self.tap.log("vsearch", &args_summary, "ok", start.elapsed().as_millis() as u64);
-                Ok(CallToolResult::success(vec![Content::text(
-                    format_search_results(&results),
-                )]))
+                outcome.results = page(results, &options);
+                Ok(self.search_result("vsearch", &args_summary, start, &outcome, group_by))
             }
             Err(e) => {
                 self.tap.log("vsearch", &args_summary, "error", start.elapsed().as_millis() as u64);
+                // BackendUnavailable and the like keep their code and recovery hints
+                let data = e.downcast_ref::<AnelError>().and_then(|anel| serde_json::to_value(anel).ok());
                 Err(McpError::internal_error(
                     format!("Vector search failed: {e}"),
-                    None,
+                    data,
                 ))
             }
         }
@@ -214,107 +374,46 @@ impl QmdMcpServer {
     ) -> Result<CallToolResult, McpError> {
         let p = params.0;
         let args_summary = serde_json::to_string(&serde_json::json!({
-            "query": &p.query, "limit": p.limit, "collection": &p.collection
+            "query": &p.query, "limit": p.limit, "offset": p.offset, "collection": &p.collection, "timeout_ms": p.timeout_ms,
+            "group_by": &p.group_by
         })).unwrap_or_default();
 
+        self.check_identity("query", &args_summary)?;
+        let group_by = parse_group_by(&p)?;
+
         if let Some(result) = self.check_dry_run("query", &args_summary) {
             return Ok(result);
         }
 
         let start = Instant::now();
+        let deadline = Deadline::new(p.timeout_ms);
         let options = make_search_options(&p);
+        let lang = lang::detect_language(&p.query);
 
-        // Step 1: Query expansion (sync LLM call)
-        let expanded_queries = {
-            let llm = self.llm.lock().await;
-            llm.expand_query(&p.query).map_err(|e| {
-                self.tap.log("query", &args_summary, "error", start.elapsed().as_millis() as u64);
-                McpError::internal_error(format!("Query expansion failed: {e}"), None)
-            })?
-        };
-
-        // Step 2: BM25 retrieval for all expanded queries
-        let all_bm25_results = {
-            let store = self.store.lock().map_err(|e| {
-                self.tap.log("query", &args_summary, "error", start.elapsed().as_millis() as u64);
-                McpError::internal_error(format!("Store lock failed: {e}"), None)
-            })?;
-            let mut results = Vec::new();
-            for eq in &expanded_queries {
-                if let Ok(r) = store.bm25_search(eq, options.clone()) {
-                    results.extend(r);
-                }
-            }
-            results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-            results.truncate(100);
-            results
-        };
-
-        // Step 3: Vector search (embed async, then sync DB query)
-        let vector_results = {
-            let embedding = {
-                let llm = self.llm.lock().await;
-                llm.embed(&[p.query.as_str()]).await.map_err(|e| {
-                    self.tap.log("query", &args_summary, "error", start.elapsed().as_millis() as u64);
-                    McpError::internal_error(format!("Embedding failed: {e}"), None)
-                })?
-            };
-            let query_vector = &embedding.embeddings[0];
-            let store = self.store.lock().map_err(|e| {
+        // The store lock is taken per stage inside the pipeline, never across an await
+        let llm = self.llm.lock().await;
+        let outcome = run_hybrid_search(&*self.store, &p.query, options, &llm, lang, &deadline)
+            .await
+            .map_err(|e| {
                 self.tap.log("query", &args_summary, "error", start.elapsed().as_millis() as u64);
-                McpError::internal_error(format!("Store lock failed: {e}"), None)
+                McpError::internal_error(format!("Hybrid search failed: {e}"), None)
             })?;
-            store.vector_search_with_embedding(query_vector, options.clone()).map_err(|e| {
-                self.tap.log("query", &args_summary, "error", start.elapsed().as_millis() as u64);
-                McpError::internal_error(format!("Vector search failed: {e}"), None)
-            })?
-        };
 
-        // Step 4: RRF fusion
-        let result_lists = vec![all_bm25_results, vector_results];
-        let weights = Some(vec![1.0, 1.5]);
-        let mut fused = Store::rrf_fusion(&result_lists, weights, 60);
-        fused.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-        let candidates: Vec<_> = fused.into_iter().take(30).collect();
-
-        // Step 5: Try LLM reranking
-        let llm = self.llm.lock().await;
-        let final_results = if llm.has_reranker() {
-            match llm.rerank(&p.query, &candidates).await {
-                Ok(scores) => {
-                    let mut reranked: Vec<_> = candidates
-                        .into_iter()
-                        .zip(scores)
-                        .map(|(mut doc, score)| {
-                            doc.score = score;
-                            doc
-                        })
-                        .collect();
-                    reranked.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-                    reranked
-                }
-                Err(_) => candidates,
-            }
-        } else {
-            candidates
-        };
-
-        self.tap.log("query", &args_summary, "ok", start.elapsed().as_millis() as u64);
-        Ok(CallToolResult::success(vec![Content::text(
-            format_search_results(&final_results),
-        )]))
+        Ok(self.search_result("query", &args_summary, start, &outcome, group_by))
     }
 
-    #[tool(description = "Get document content by file path with optional line range")]
+    #[tool(description = "Get document content by file path with optional line range. Pass if_hash with the hash from an earlier get to skip unchanged content")]
     async fn get(
         &self,
         params: Parameters<GetParams>,
     ) -> Result<CallToolResult, McpError> {
         let p = params.0;
         let args_summary = serde_json::to_string(&serde_json::json!({
-            "path": &p.path, "from": p.from, "limit": p.limit
+            "path": &p.path, "from": p.from, "limit": p.limit, "if_hash": &p.if_hash
         })).unwrap_or_default();
 
+        self.check_identity("get", &args_summary)?;
+
         if let Some(result) = self.check_dry_run("get", &args_summary) {
             return Ok(result);
         }
@@ -323,19 +422,33 @@ impl QmdMcpServer {
         let from = p.from.unwrap_or(0);
         let limit = p.limit.unwrap_or(50);
 
-        match std::fs::read_to_string(&p.path) {
-            Ok(content) => {
-                let lines: Vec<&str> = content.lines().collect();
+        let document = {
+            let store = self.store.lock().map_err(|e| {
+                self.tap.log("get", &args_summary, "error", start.elapsed().as_millis() as u64);
+                McpError::internal_error(format!("Store lock failed: {e}"), None)
+            })?;
+            store.retrieve_document(&p.path)
+        };
+
+        match document {
+            Ok(document) if p.if_hash.as_deref() == Some(document.hash.as_str()) => {
+                let text = serde_json::json!({ "unchanged": true, "hash": document.hash }).to_string();
+                self.tap.log("get", &args_summary, "ok", start.elapsed().as_millis() as u64);
+                Ok(CallToolResult::success(vec![Content::text(text)]))
+            }
+            Ok(document) => {
+                let lines: Vec<&str> = document.content.lines().collect();
                 let total = lines.len();
                 let line_start = from.min(total);
                 let end = (line_start + limit).min(total);
                 let selected = &lines[line_start..end];
                 let text = format!(
-                    "File: {} (lines {}-{} of {})\n\n{}",
-                    p.path,
+                    "File: {} (lines {}-{} of {})\nHash: {}\n\n{}",
+                    document.path,
                     line_start + 1,
                     end,
                     total,
+                    document.hash,
                     selected.join("\n")
                 );
                 self.tap.log("get", &args_summary, "ok", start.elapsed().as_millis() as u64);
@@ -343,17 +456,134 @@ impl QmdMcpServer {
             }
             Err(e) => {
                 self.tap.log("get", &args_summary, "error", start.elapsed().as_millis() as u64);
-                Err(McpError::internal_error(
-                    format!("Failed to read file '{}': {e}", p.path),
-                    None,
-                ))
+                let data = serde_json::to_value(&e).ok();
+                Err(match e.error_code {
+                    AnelErrorCode::NotFound => McpError::resource_not_found(e.to_string(), data),
+                    _ => McpError::invalid_request(e.to_string(), data),
+                })
+            }
+        }
+    }
+
+    #[tool(description = "Generate embeddings for documents that lack them (all documents with force), sending progress notifications as chunks are embedded")]
+    async fn embed(
+        &self,
+        params: Parameters<EmbedParams>,
+        meta: Meta,
+        peer: Peer<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let force = p.force.unwrap_or(false);
+        let args_summary = serde_json::to_string(&serde_json::json!({
+            "collection": &p.collection, "force": force
+        })).unwrap_or_default();
+
+        self.check_identity("embed", &args_summary)?;
+
+        if let Some(result) = self.check_dry_run("embed", &args_summary) {
+            return Ok(result);
+        }
+
+        let start = Instant::now();
+        let fail = |error: McpError| {
+            self.tap.log("embed", &args_summary, "error", start.elapsed().as_millis() as u64);
+            error
+        };
+        let Some(model) = self.llm.lock().await.embedder_model() else {
+            return Err(fail(McpError::invalid_request("No embedding model is configured", None)));
+        };
+
+        // Read and chunk the pending documents under the store lock, then
+        // release it: embedding takes the LLM lock one batch at a time and
+        // other tools keep working in between
+        let jobs = {
+            let store = self.store.lock().map_err(|e| fail(McpError::internal_error(format!("Store lock failed: {e}"), None)))?;
+            let collections: Vec<String> = match &p.collection {
+                Some(name) if store.get_collections().iter().any(|c| &c.name == name) => vec![name.clone()],
+                Some(name) => {
+                    return Err(fail(McpError::invalid_params(format!("Unknown collection: {name}"), None)));
+                }
+                None => store.get_collections().iter().map(|c| c.name.clone()).collect(),
+            };
+            collections
+                .iter()
+                .map(|name| EmbedJob::pending(&store, name, Some(&model), force))
+                .collect::<Result<Vec<_>>>()
+                .map_err(|e| fail(McpError::internal_error(format!("Embed failed: {e}"), None)))?
+        };
+
+        let total: usize = jobs.iter().map(EmbedJob::chunks).sum();
+        let progress_token = meta.get_progress_token();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<(usize, String)>();
+
+        // Progress is forwarded while the jobs run, and fully sent before the result
+        let embedding = async move {
+            let mut summaries = Vec::new();
+            let mut done = 0;
+            for job in jobs {
+                let collection = job.collection.clone();
+                let documents = job.documents();
+                let chunks = job
+                    .run(self.llm.as_ref(), |embedded| {
+                        let _ = tx.send((done + embedded, collection.clone()));
+                    })
+                    .await?;
+                done += chunks;
+                summaries.push(serde_json::json!({
+                    "collection": collection, "documents": documents, "chunks": chunks
+                }));
             }
+            anyhow::Ok(summaries)
+        };
+        let forwarding = async {
+            while let Some((progress, collection)) = rx.recv().await {
+                if let Some(ref token) = progress_token {
+                    let _ = peer
+                        .notify_progress(ProgressNotificationParam {
+                            progress_token: token.clone(),
+                            progress: progress as f64,
+                            total: Some(total as f64),
+                            message: Some(format!("Embedded {}/{} chunks ({})", progress, total, collection)),
+                        })
+                        .await;
+                }
+            }
+        };
+        let (summaries, ()) = tokio::join!(embedding, forwarding);
+        let summaries = summaries.map_err(|e| fail(McpError::internal_error(format!("Embed failed: {e}"), None)))?;
+
+        let documents: u64 = summaries.iter().filter_map(|s| s["documents"].as_u64()).sum();
+        let chunks: u64 = summaries.iter().filter_map(|s| s["chunks"].as_u64()).sum();
+        let mut text = format!("Embedded {} chunks from {} documents\n", chunks, documents);
+        for summary in &summaries {
+            text.push_str(&format!(
+                "  {}: {} documents, {} chunks\n",
+                summary["collection"].as_str().unwrap_or_default(),
+                summary["documents"],
+                summary["chunks"]
+            ));
         }
+        self.tap.log("embed", &args_summary, "ok", start.elapsed().as_millis() as u64);
+        let mut result = CallToolResult::success(vec![Content::text(text)]);
+        result.structured_content = Some(serde_json::json!({
+            "documents": documents,
+            "chunks": chunks,
+            "collections": summaries,
+        }));
+        Ok(result)
     }
 
     #[tool(description = "Show index statistics including document counts and collection info")]
-    async fn status(&self) -> Result<CallToolResult, McpError> {
-        let args_summary = "{}".to_string();
+    async fn status(
+        &self,
+        params: Parameters<StatusParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let detailed = params.0.detailed.unwrap_or(false);
+        let args_summary = serde_json::to_string(&serde_json::json!({
+            "detailed": detailed
+        })).unwrap_or_default();
+
+        self.check_identity("status", &args_summary)?;
 
         if let Some(result) = self.check_dry_run("status", &args_summary) {
             return Ok(result);
@@ -367,17 +597,33 @@ impl QmdMcpServer {
         match store.get_stats() {
             Ok(stats) => {
                 let mut text = format!(
-                    "Index Status:\n  Collections: {}\n  Documents: {}\n  Indexed: {}\n  Pending: {}\n  Chunks: {}\n",
+                    "Index Status:\n  Collections: {}\n  Documents: {}\n  Indexed: {}\n  Pending: {}\n  Chunks: {}\n  Tokens (est.): {}\n  Index bytes: {}\n",
                     stats.collection_count,
                     stats.document_count,
                     stats.indexed_count,
                     stats.pending_count,
                     stats.chunk_count,
+                    stats.token_count,
+                    stats.index_bytes,
                 );
+                text.push_str(&format!("  Audit records dropped: {}\n", self.tap.sink.dropped()));
                 if !stats.collection_stats.is_empty() {
                     text.push_str("\nPer-collection:\n");
                     for (name, count) in &stats.collection_stats {
-                        text.push_str(&format!("  {}: {} docs\n", name, count));
+                        let tokens = stats.collection_tokens.get(name).copied().unwrap_or(0);
+                        let index_bytes = stats.collection_index_bytes.get(name).copied().unwrap_or(0);
+                        text.push_str(&format!(
+                            "  {}: {} docs, ~{} tokens, {} index bytes\n",
+                            name, count, tokens, index_bytes
+                        ));
+                    }
+                }
+                if detailed {
+                    for collection in store.get_collections() {
+                        match store.collection_report(&collection.name) {
+                            Ok(report) => text.push_str(&format_report(&report.trimmed(MCP_REPORT_ROWS))),
+                            Err(e) => text.push_str(&format!("\n{}: report failed: {}\n", collection.name, e)),
+                        }
                     }
                 }
                 self.tap.log("status", &args_summary, "ok", start.elapsed().as_millis() as u64);
@@ -392,12 +638,63 @@ impl QmdMcpServer {
             }
         }
     }
+
+    #[tool(description = "Complete a query prefix with words from document titles and headings, most used first")]
+    async fn suggest(
+        &self,
+        params: Parameters<SuggestParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let SuggestParams { prefix, limit } = params.0;
+        let limit = limit.unwrap_or(10);
+        let args_summary = serde_json::to_string(&serde_json::json!({
+            "prefix": prefix,
+            "limit": limit
+        })).unwrap_or_default();
+
+        self.check_identity("suggest", &args_summary)?;
+
+        if let Some(result) = self.check_dry_run("suggest", &args_summary) {
+            return Ok(result);
+        }
+
+        let start = Instant::now();
+        let store = self.store.lock().map_err(|e| {
+            self.tap.log("suggest", &args_summary, "error", start.elapsed().as_millis() as u64);
+            McpError::internal_error(format!("Store lock failed: {e}"), None)
+        })?;
+        match store.suggest(&prefix, limit) {
+            Ok(suggestions) => {
+                let text = if suggestions.is_empty() {
+                    format!("No suggestions for '{}'", prefix)
+                } else {
+                    suggestions
+                        .iter()
+                        .map(|s| format!("{} ({} documents)", s.term, s.documents))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                };
+                self.tap.log("suggest", &args_summary, "ok", start.elapsed().as_millis() as u64);
+                let mut result = CallToolResult::success(vec![Content::text(text)]);
+                result.structured_content = Some(serde_json::json!({
+                    "prefix": prefix,
+                    "suggestions": suggestions,
+                }));
+                Ok(result)
+            }
+            Err(e) => {
+                self.tap.log("suggest", &args_summary, "error", start.elapsed().as_millis() as u64);
+                Err(McpError::internal_error(format!("Suggest failed: {e}"), None))
+            }
+        }
+    }
 }
 
+#[tool_handler]
 impl ServerHandler for QmdMcpServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
-            instructions: Some("QMD - AI-powered document search with hybrid BM25 and vector search. Use 'search' for keyword matching, 'vsearch' for semantic search, 'query' for best results combining both, 'get' to read document content, and 'status' to check index health.".into()),
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            instructions: Some("QMD - AI-powered document search with hybrid BM25 and vector search. Use 'search' for keyword matching, 'vsearch' for semantic search, 'query' for best results combining both, 'get' to read document content, 'status' to check index health, 'suggest' to complete query words, and 'embed' to generate missing embeddings.".into()),
             ..Default::default()
         }
     }
@@ -439,19 +736,24 @@ pub fn run_server(args: &McpArgs, config: &Config) -> Result<()> {
         println!("  transport: {}", args.transport);
         println!("  port: {}", args.port);
         println!("  format: {}", args.format);
+        println!("  preload: {}", args.preload || config.preload);
         return Ok(());
     }
 
+    let preload = args.preload || config.preload;
     match args.transport.as_str() {
-        "stdio" => run_stdio_server(config),
-        "http" | "sse" => run_http_server(args, config),
+        "stdio" => run_stdio_server(config, preload),
+        "http" | "sse" => run_http_server(args, config, preload),
         _ => anyhow::bail!("Unknown transport: {}", args.transport),
     }
 }
 
-fn run_stdio_server(config: &Config) -> Result<()> {
+fn run_stdio_server(config: &Config, preload: bool) -> Result<()> {
     let server = QmdMcpServer::new(config.clone())?;
     tokio::runtime::Runtime::new()?.block_on(async {
+        if preload {
+            server.preload().await?;
+        }
         let transport = rmcp::transport::io::stdio();
         let service = server.serve(transport).await?;
         service.waiting().await?;
@@ -459,7 +761,7 @@ fn run_stdio_server(config: &Config) -> Result<()> {
     })
 }
 
-fn run_http_server(args: &McpArgs, config: &Config) -> Result<()> {
+fn run_http_server(args: &McpArgs, config: &Config, preload: bool) -> Result<()> {
     use rmcp::transport::streamable_http_server::{
         StreamableHttpServerConfig, StreamableHttpService,
     };
@@ -517,6 +819,9 @@ fn run_http_server(args: &McpArgs, config: &Config) -> Result<()> {
     log::info!("Model will stay loaded in memory for fast subsequent queries");
 
     rt.block_on(async {
+        if preload {
+            server.preload().await?;
+        }
         let listener = tokio::net::TcpListener::bind(addr).await?;
         axum::serve(listener, app).await?;
         Ok::<(), anyhow::Error>(())
@@ -528,30 +833,768 @@ fn run_http_server(args: &McpArgs, config: &Config) -> Result<()> {
 fn make_search_options(p: &SearchParams) -> SearchOptions {
     SearchOptions {
         limit: p.limit.unwrap_or(20),
-        min_score: 0.0,
+        offset: p.offset.unwrap_or(0),
         collection: p.collection.clone(),
         search_all: p.collection.is_none(),
+        ..Default::default()
     }
 }
 
-fn format_search_results(results: &[crate::store::SearchResult]) -> String {
+/// Parse the `group_by` search parameter
+fn parse_group_by(p: &SearchParams) -> Result<Option<GroupBy>, McpError> {
+    p.group_by
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e: anyhow::Error| McpError::invalid_params(e.to_string(), None))
+}
+
+fn format_search_results(results: &[crate::store::SearchResult], group_by: Option<GroupBy>) -> String {
     if results.is_empty() {
         return "No results found.".to_string();
     }
-    results
+    let format_result = |i: usize| {
+        let r = &results[i];
+        format!(
+            "{}. [{}] {} (score: {:.4}, docid: {})\n   Path: {}",
+            i + 1,
+            r.collection,
+            r.title,
+            r.score,
+            r.docid,
+            r.path,
+        )
+    };
+    match group_by {
+        Some(group_by) => group_results(results, group_by)
+            .into_iter()
+            .map(|group| {
+                let lines: Vec<String> = group.indices.iter().map(|&i| format_result(i)).collect();
+                format!("## {} ({})\n{}", group.name, group.indices.len(), lines.join("\n"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        None => (0..results.len()).map(format_result).collect::<Vec<_>>().join("\n"),
+    }
+}
+
+/// Attach `{results, groups}` as structured content when results are grouped
+fn with_groups(
+    mut result: CallToolResult,
+    results: &[crate::store::SearchResult],
+    group_by: Option<GroupBy>,
+) -> CallToolResult {
+    if let Some(group_by) = group_by {
+        result.structured_content = Some(serde_json::json!({
+            "group_by": group_by,
+            "results": results,
+            "groups": group_results(results, group_by),
+        }));
+    }
+    result
+}
+
+/// Extensions and largest documents listed per collection by `status` with `detailed`
+const MCP_REPORT_ROWS: usize = 3;
+
+fn format_report(report: &CollectionReport) -> String {
+    let mut text = format!(
+        "\nReport for {}:\n  Index bytes: {}\n  Content bytes: {}\n  Average document: {} bytes, ~{} tokens\n  Chunks: {}, vectors: {} (~{} bytes)\n",
+        report.collection,
+        report.index_bytes,
+        report.content_bytes,
+        report.average_document_bytes,
+        report.average_document_tokens,
+        report.chunk_count,
+        report.vector_count,
+        report.vector_bytes_estimate,
+    );
+    let compression = &report.compression;
+    if compression.compressed_documents > 0 {
+        text.push_str(&format!(
+            "  Compression: {:.1}x ({} compressed, {} plain)\n",
+            compression.ratio, compression.compressed_documents, compression.plain_documents
+        ));
+    }
+    let extensions: Vec<String> = report
+        .extensions
         .iter()
-        .enumerate()
-        .map(|(i, r)| {
-            format!(
-                "{}. [{}] {} (score: {:.4}, docid: {})\n   Path: {}",
-                i + 1,
-                r.collection,
-                r.title,
-                r.score,
-                r.docid,
-                r.path,
-            )
+        .map(|e| format!("{} ({} docs, {} bytes)", e.extension, e.documents, e.bytes))
+        .collect();
+    if !extensions.is_empty() {
+        text.push_str(&format!("  Top extensions: {}\n", extensions.join(", ")));
+    }
+    for doc in &report.largest_documents {
+        text.push_str(&format!("  Large: {} ({} bytes)\n", doc.path, doc.bytes));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CollectionConfig;
+
+    #[test]
+    fn test_tool_names_match_registered_tools() {
+        let mut registered: Vec<String> = QmdMcpServer::tool_router()
+            .list_all()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        registered.sort();
+        let mut names = TOOL_NAMES.to_vec();
+        names.sort();
+        assert_eq!(registered, names);
+    }
+
+    fn get_params(path: &str) -> Parameters<GetParams> {
+        Parameters(GetParams {
+            path: path.to_string(),
+            from: None,
+            limit: None,
+            if_hash: None,
         })
-        .collect::<Vec<_>>()
-        .join("\n")
+    }
+
+    fn result_text(result: &CallToolResult) -> String {
+        result
+            .content
+            .iter()
+            .filter_map(|c| c.as_text().map(|t| t.text.clone()))
+            .collect()
+    }
+
+    /// Audit writer capturing records in memory
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Buffer {
+        fn records(&self) -> Vec<serde_json::Value> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct SlowEmbedder;
+
+    impl crate::llm::Embed for SlowEmbedder {
+        fn model_name(&self) -> String {
+            "slow-mock".to_string()
+        }
+
+        fn embed<'a>(&'a self, texts: &'a [&'a str]) -> crate::llm::EmbedFuture<'a> {
+            Box::pin(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                Ok(texts.iter().map(|_| vec![0.1; 8]).collect())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_tool_timeout_returns_partial_results() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("guide.md"), "# Guide\nOwnership rules").unwrap();
+        let config = Config {
+            collections: vec![CollectionConfig {
+                name: "docs".to_string(),
+                path: docs,
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        Store::new(&config).unwrap().update_index().unwrap();
+        let server = QmdMcpServer::new(config).unwrap();
+        server.llm.lock().await.set_embedder(Arc::new(SlowEmbedder));
+
+        let start = Instant::now();
+        let result = server
+            .query(Parameters(SearchParams {
+                query: "ownership".to_string(),
+                limit: None,
+                offset: None,
+                collection: None,
+                timeout_ms: Some(200),
+                group_by: None,
+            }))
+            .await
+            .unwrap();
+        let text = result_text(&result);
+        assert!(start.elapsed() < std::time::Duration::from_secs(2), "took {:?}", start.elapsed());
+        assert!(text.contains("guide.md"), "{}", text);
+        assert!(text.ends_with("Partial results: timed out before vector"), "{}", text);
+    }
+
+    #[tokio::test]
+    async fn test_query_tool_audits_stage_timings() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("guide.md"), "# Guide\nOwnership rules").unwrap();
+        let config = Config {
+            collections: vec![CollectionConfig {
+                name: "docs".to_string(),
+                path: docs,
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        Store::new(&config).unwrap().update_index().unwrap();
+        let mut server = QmdMcpServer::new(config).unwrap();
+        let log = Buffer::default();
+        server.tap.sink = Arc::new(AuditLog::with_writer(Box::new(log.clone())));
+        server.llm.lock().await.set_embedder(Arc::new(SlowEmbedder));
+
+        // The embedder stalls until the 150ms budget cuts the vector stage off
+        server
+            .query(Parameters(SearchParams {
+                query: "ownership".to_string(),
+                limit: None,
+                offset: None,
+                collection: None,
+                timeout_ms: Some(150),
+                group_by: None,
+            }))
+            .await
+            .unwrap();
+
+        server.tap.sink.flush();
+        let record = &log.records()[0];
+        assert_eq!(record["status"], "partial");
+        let args: serde_json::Value = serde_json::from_str(record["args"].as_str().unwrap()).unwrap();
+        assert_eq!(args["query"], "ownership");
+        let timings = &args["timings"];
+        let vector = timings["vector_ms"].as_f64().unwrap();
+        assert!((100.0..1000.0).contains(&vector), "{}", timings);
+        assert!(timings["bm25_ms"].as_f64().unwrap() < 100.0, "{}", timings);
+        assert!(timings["total_ms"].as_f64().unwrap() >= vector, "{}", timings);
+    }
+
+    #[tokio::test]
+    async fn test_query_tool_matches_cli_hybrid_search() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("guide.md"), "# Guide\nOwnership rules and borrowing").unwrap();
+        std::fs::write(docs.join("ownership.md"), "# Ownership\nOwnership, ownership, ownership.").unwrap();
+        std::fs::write(docs.join("traits.md"), "# Traits\nTraits describe shared behaviour; ownership aside.").unwrap();
+        std::fs::write(docs.join("cargo.md"), "# Cargo\nBuilds crates.").unwrap();
+        let config = Config {
+            collections: vec![CollectionConfig {
+                name: "docs".to_string(),
+                path: docs,
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        let store = Store::new(&config).unwrap();
+        store.update_index().unwrap();
+        let mut router = Router::new(&config).unwrap();
+        router.set_embedder(Arc::new(MockEmbedder));
+        let server = QmdMcpServer::new(config).unwrap();
+        server.llm.lock().await.set_embedder(Arc::new(MockEmbedder));
+
+        let params = || SearchParams {
+            query: "ownership".to_string(),
+            limit: None,
+            offset: None,
+            collection: None,
+            timeout_ms: None,
+            group_by: None,
+        };
+        // What `qmd query` runs for the same query and options
+        let (lang, _) = lang::resolve_language("ownership", None);
+        let cli = store
+            .hybrid_search_within("ownership", make_search_options(&params()), &router, lang, &Deadline::unlimited())
+            .await
+            .unwrap();
+        assert_eq!(cli.results.len(), 3, "{:?}", cli.results);
+
+        let result = server.query(Parameters(params())).await.unwrap();
+        // Both note the same failed stages when sqlite-vec is missing
+        let text = result_text(&result);
+        assert!(text.starts_with(&format_search_results(&cli.results, None)), "{}", text);
+        assert_eq!(text.contains("vector stage failed"), !cli.degraded.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_huge_tool_arguments_are_capped_in_audit() {
+        use crate::server::observability::audit::DEFAULT_AUDIT_ARGS_MAX_BYTES;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config {
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        let mut server = QmdMcpServer::new(config).unwrap();
+        let log = Buffer::default();
+        server.tap.sink = Arc::new(AuditLog::with_writer(Box::new(log.clone())));
+
+        let huge = format!("docs/{}.md", "x".repeat(4 * 1024 * 1024));
+        let start = Instant::now();
+        let _ = server
+            .get(Parameters(GetParams {
+                path: huge,
+                from: None,
+                limit: None,
+                if_hash: None,
+            }))
+            .await;
+        assert!(start.elapsed() < std::time::Duration::from_secs(2), "took {:?}", start.elapsed());
+
+        server.tap.sink.flush();
+        let record = log.records().into_iter().find(|r| r["tool"] == "get").unwrap();
+        let args = record["args"].as_str().unwrap();
+        assert!(args.len() < DEFAULT_AUDIT_ARGS_MAX_BYTES + 64, "{} bytes", args.len());
+        assert!(args.contains("…[truncated "), "{}", args);
+        assert_eq!(record["args_sha256"].as_str().unwrap().len(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_stream_tap_appends_to_audit_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("guide.md"), "# Guide\nOwnership rules").unwrap();
+        let config = Config {
+            collections: vec![CollectionConfig {
+                name: "docs".to_string(),
+                path: docs,
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        Store::new(&config).unwrap().update_index().unwrap();
+
+        let audit_file = tmp.path().join("logs").join("audit.ndjson");
+        std::env::set_var(anel::env::AUDIT_FILE, &audit_file);
+        let server = QmdMcpServer::new(config);
+        std::env::remove_var(anel::env::AUDIT_FILE);
+        let server = server.unwrap();
+
+        server
+            .search(Parameters(SearchParams {
+                query: "ownership".to_string(),
+                limit: None,
+                offset: None,
+                collection: None,
+                timeout_ms: None,
+                group_by: None,
+            }))
+            .await
+            .unwrap();
+
+        server.tap.sink.flush();
+        let records: Vec<serde_json::Value> = std::fs::read_to_string(&audit_file)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let record = records.iter().find(|r| r["tool"] == "search").expect("search audited");
+        assert_eq!(record["type"], "audit");
+        assert_eq!(record["status"], "ok");
+        assert!(record["args"].as_str().unwrap().contains("ownership"));
+    }
+
+    #[tokio::test]
+    async fn test_identity_enforcement_on_tool_calls() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config {
+            cache_path: tmp.path().join("cache"),
+            identity: crate::config::IdentityConfig {
+                enforce: true,
+                tokens: vec![crate::config::IdentityToken {
+                    name: "indexer".to_string(),
+                    token: "tok-123".to_string(),
+                }],
+            },
+            ..Config::default()
+        };
+        std::env::set_var(anel::env::IDENTITY_TOKEN, "forged");
+        let denied = QmdMcpServer::new(config.clone());
+        std::env::set_var(anel::env::IDENTITY_TOKEN, "tok-123");
+        let accepted = QmdMcpServer::new(config);
+        std::env::remove_var(anel::env::IDENTITY_TOKEN);
+
+        let mut denied = denied.unwrap();
+        let denied_log = Buffer::default();
+        denied.tap.sink = Arc::new(AuditLog::with_writer(Box::new(denied_log.clone())));
+        let err = denied.status(Parameters(StatusParams::default())).await.unwrap_err();
+        assert!(err.message.contains("PermissionDenied"), "{}", err.message);
+        denied.tap.sink.flush();
+        assert_eq!(denied_log.records()[0]["status"], "denied");
+
+        let mut accepted = accepted.unwrap();
+        let accepted_log = Buffer::default();
+        accepted.tap.sink = Arc::new(AuditLog::with_writer(Box::new(accepted_log.clone())));
+        accepted.status(Parameters(StatusParams::default())).await.unwrap();
+        accepted.tap.sink.flush();
+        let record = &accepted_log.records()[0];
+        assert_eq!(record["status"], "ok");
+        assert_eq!(record["identity_name"], "indexer");
+    }
+
+    #[tokio::test]
+    async fn test_get_tool_rejects_traversal() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("guide.md"), "# Guide\nIndexed content").unwrap();
+        let secret = tmp.path().join("secret.txt");
+        std::fs::write(&secret, "TOP-SECRET").unwrap();
+
+        let config = Config {
+            collections: vec![CollectionConfig {
+                name: "docs".to_string(),
+                path: docs.clone(),
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        Store::new(&config).unwrap().update_index().unwrap();
+        let server = QmdMcpServer::new(config).unwrap();
+
+        let result = server.get(get_params("docs/guide.md")).await.unwrap();
+        assert!(result_text(&result).contains("Indexed content"));
+
+        for attempt in [
+            "docs/../secret.txt".to_string(),
+            format!("{}/../secret.txt", docs.display()),
+            secret.display().to_string(),
+            "/etc/passwd".to_string(),
+        ] {
+            let err = server.get(get_params(&attempt)).await.unwrap_err();
+            assert!(err.message.contains("PermissionDenied"), "{}: {}", attempt, err.message);
+            let data = err.data.map(|d| d.to_string()).unwrap_or_default();
+            assert!(!err.message.contains("TOP-SECRET") && !data.contains("TOP-SECRET"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_tool_if_hash_skips_unchanged_content() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("guide.md"), "# Guide\nFirst draft").unwrap();
+
+        let config = Config {
+            collections: vec![CollectionConfig {
+                name: "docs".to_string(),
+                path: docs.clone(),
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        Store::new(&config).unwrap().update_index().unwrap();
+        let server = QmdMcpServer::new(config.clone()).unwrap();
+        let get_if = |hash: &str| {
+            let mut params = get_params("docs/guide.md");
+            params.0.if_hash = Some(hash.to_string());
+            params
+        };
+        let hash_of = |text: &str| {
+            text.lines()
+                .find_map(|l| l.strip_prefix("Hash: "))
+                .expect("hash line")
+                .to_string()
+        };
+
+        let first = result_text(&server.get(get_params("docs/guide.md")).await.unwrap());
+        assert!(first.contains("First draft"));
+        let hash = hash_of(&first);
+
+        let again = result_text(&server.get(get_if(&hash)).await.unwrap());
+        let again: serde_json::Value = serde_json::from_str(&again).unwrap();
+        assert_eq!(again, serde_json::json!({ "unchanged": true, "hash": hash }));
+
+        std::fs::write(docs.join("guide.md"), "# Guide\nSecond draft").unwrap();
+        Store::new(&config).unwrap().update_index().unwrap();
+
+        let changed = result_text(&server.get(get_if(&hash)).await.unwrap());
+        assert!(changed.contains("Second draft"));
+        assert_ne!(hash_of(&changed), hash);
+    }
+
+    #[tokio::test]
+    async fn test_tools_skip_deactivated_documents() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("kept.md"), "# Kept\nOwnership rules").unwrap();
+        std::fs::write(docs.join("gone.md"), "# Gone\nOwnership moves").unwrap();
+
+        let config = Config {
+            collections: vec![CollectionConfig {
+                name: "docs".to_string(),
+                path: docs.clone(),
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        let store = Store::new(&config).unwrap();
+        store.update_index().unwrap();
+        store.remove_stale_entries(&["gone.md".to_string()]).unwrap();
+        let server = QmdMcpServer::new(config).unwrap();
+
+        let search = server
+            .search(Parameters(SearchParams {
+                query: "ownership".to_string(),
+                limit: None,
+                offset: None,
+                collection: None,
+                timeout_ms: None,
+                group_by: None,
+            }))
+            .await
+            .unwrap();
+        let text = result_text(&search);
+        assert!(text.contains("kept.md") && !text.contains("gone.md"), "{}", text);
+
+        let err = server.get(get_params("docs/gone.md")).await.unwrap_err();
+        assert!(err.message.contains("NotFound"), "{}", err.message);
+
+        let status = result_text(&server.status(Parameters(StatusParams::default())).await.unwrap());
+        assert!(status.contains("Documents: 1\n"), "{}", status);
+
+        let detailed = server
+            .status(Parameters(StatusParams { detailed: Some(true) }))
+            .await
+            .unwrap();
+        let detailed = result_text(&detailed);
+        assert!(detailed.contains("Report for docs"), "{}", detailed);
+        assert!(detailed.contains("Large: kept.md") && !detailed.contains("gone.md"), "{}", detailed);
+    }
+
+    #[tokio::test]
+    async fn test_search_tool_groups_results_in_structured_content() {
+        let tmp = tempfile::tempdir().unwrap();
+        let collection = |name: &str| {
+            let path = tmp.path().join(name);
+            std::fs::create_dir_all(path.join("guides")).unwrap();
+            CollectionConfig {
+                name: name.to_string(),
+                path,
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }
+        };
+        let config = Config {
+            collections: vec![collection("docs"), collection("notes")],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        std::fs::write(tmp.path().join("docs/guides/a.md"), "# A\nOwnership ownership ownership").unwrap();
+        std::fs::write(tmp.path().join("docs/b.md"), "# B\nOwnership rules").unwrap();
+        std::fs::write(tmp.path().join("notes/guides/c.md"), "# C\nOwnership notes and more words here").unwrap();
+        Store::new(&config).unwrap().update_index().unwrap();
+        let server = QmdMcpServer::new(config).unwrap();
+
+        let params = |group_by: &str| {
+            Parameters(SearchParams {
+                query: "ownership".to_string(),
+                limit: None,
+                offset: None,
+                collection: None,
+                timeout_ms: None,
+                group_by: Some(group_by.to_string()),
+            })
+        };
+
+        let result = server.search(params("collection")).await.unwrap();
+        let structured = result.structured_content.as_ref().unwrap();
+        assert_eq!(structured["group_by"], "collection");
+        let results = structured["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        let groups = structured["groups"].as_array().unwrap();
+        let mut names: Vec<&str> = groups.iter().map(|g| g["name"].as_str().unwrap()).collect();
+        names.sort();
+        assert_eq!(names, ["docs", "notes"]);
+        let mut seen: Vec<u64> = Vec::new();
+        for group in groups {
+            let indices: Vec<u64> = group["indices"].as_array().unwrap().iter().map(|i| i.as_u64().unwrap()).collect();
+            // Rank order is kept within each group
+            assert!(indices.windows(2).all(|pair| pair[0] < pair[1]), "{}", group);
+            for &i in &indices {
+                assert_eq!(results[i as usize]["collection"], group["name"]);
+            }
+            seen.extend(indices);
+        }
+        seen.sort();
+        assert_eq!(seen, [0, 1, 2]);
+        assert!(result_text(&result).contains("## docs (2)"), "{}", result_text(&result));
+
+        let result = server.search(params("dir")).await.unwrap();
+        let groups = &result.structured_content.as_ref().unwrap()["groups"];
+        let mut names: Vec<&str> = groups.as_array().unwrap().iter().map(|g| g["name"].as_str().unwrap()).collect();
+        names.sort();
+        assert_eq!(names, ["docs/", "docs/guides/", "notes/guides/"]);
+
+        let err = server.search(params("extension")).await.unwrap_err();
+        assert!(err.message.contains("valid options: collection, dir"), "{}", err.message);
+
+        // Ungrouped results stay text-only
+        let plain = server
+            .search(Parameters(SearchParams { group_by: None, ..params("collection").0 }))
+            .await
+            .unwrap();
+        assert!(plain.structured_content.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_suggest_tool_completes_from_headings() {
+        let tmp = tempfile::tempdir().unwrap();
+        let content = tmp.path().join("docs");
+        std::fs::create_dir_all(&content).unwrap();
+        std::fs::write(content.join("a.md"), "# Ownership\n## Borrowing").unwrap();
+        std::fs::write(content.join("b.md"), "# Ownership rules").unwrap();
+        let config = Config {
+            collections: vec![CollectionConfig {
+                name: "docs".to_string(),
+                path: content,
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        Store::new(&config).unwrap().update_index().unwrap();
+        let server = QmdMcpServer::new(config).unwrap();
+
+        let params = Parameters(SuggestParams { prefix: "o".to_string(), limit: None });
+        let result = server.suggest(params).await.unwrap();
+        assert_eq!(result_text(&result), "ownership (2 documents)");
+        let structured = result.structured_content.as_ref().unwrap();
+        assert_eq!(structured["suggestions"], serde_json::json!([{"term": "ownership", "documents": 2}]));
+    }
+
+    struct MockEmbedder;
+
+    impl crate::llm::Embed for MockEmbedder {
+        fn model_name(&self) -> String {
+            "mock".to_string()
+        }
+
+        fn embed<'a>(&'a self, texts: &'a [&'a str]) -> crate::llm::EmbedFuture<'a> {
+            Box::pin(async move { Ok(texts.iter().map(|_| vec![0.1; 8]).collect()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embed_tool_reports_progress() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        for i in 0..15 {
+            std::fs::write(docs.join(format!("note-{:02}.md", i)), format!("# Note {}\nOwnership rules", i)).unwrap();
+        }
+        let config = Config {
+            collections: vec![CollectionConfig {
+                name: "docs".to_string(),
+                path: docs,
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        Store::new(&config).unwrap().update_index().unwrap();
+        let mut server = QmdMcpServer::new(config).unwrap();
+        let log = Buffer::default();
+        server.tap.sink = Arc::new(AuditLog::with_writer(Box::new(log.clone())));
+        server.llm.lock().await.set_embedder(Arc::new(MockEmbedder));
+        let store = server.store.clone();
+        let sink = server.tap.sink.clone();
+
+        let (client, transport) = tokio::io::duplex(64 * 1024);
+        let running = tokio::spawn(async move { server.serve(transport).await.unwrap().waiting().await });
+        let (read, mut write) = tokio::io::split(client);
+        let mut lines = BufReader::new(read).lines();
+        for message in [
+            serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+                "protocolVersion": "2025-03-26", "capabilities": {},
+                "clientInfo": {"name": "test", "version": "0"}
+            }}),
+            serde_json::json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+            serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {
+                "name": "embed", "arguments": {}, "_meta": {"progressToken": "embed-1"}
+            }}),
+        ] {
+            write.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+        }
+
+        let mut progress = Vec::new();
+        let result = loop {
+            let line = tokio::time::timeout(std::time::Duration::from_secs(10), lines.next_line())
+                .await
+                .expect("timed out waiting for the server")
+                .unwrap()
+                .expect("server closed the stream");
+            let message: serde_json::Value = serde_json::from_str(&line).unwrap();
+            if message["method"] == "notifications/progress" {
+                assert_eq!(message["params"]["progressToken"], "embed-1");
+                assert_eq!(message["params"]["total"], 15.0);
+                progress.push(message["params"]["progress"].as_f64().unwrap());
+            } else if message["id"] == 2 {
+                break message["result"].clone();
+            }
+        };
+        write.shutdown().await.unwrap();
+        running.await.unwrap().unwrap();
+
+        // One notification per batch of ten chunks, all sent before the result
+        assert_eq!(progress, [10.0, 15.0]);
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert!(text.starts_with("Embedded 15 chunks from 15 documents"), "{}", text);
+        assert_eq!(result["structuredContent"]["chunks"], 15);
+        assert_eq!(result["structuredContent"]["collections"][0]["collection"], "docs");
+
+        let conn = store.lock().unwrap().get_connection("docs").unwrap();
+        let embedded: i64 = conn.query_row("SELECT COUNT(*) FROM content_vectors", [], |row| row.get(0)).unwrap();
+        assert_eq!(embedded, 15);
+        sink.flush();
+        let record = log.records().into_iter().find(|r| r["tool"] == "embed").unwrap();
+        assert_eq!(record["status"], "ok");
+    }
 }
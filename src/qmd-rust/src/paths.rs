@@ -0,0 +1,188 @@
+//! Tilde expansion and lexical path normalization, shared by every module
+//! that turns user-supplied paths into filesystem locations.
+//!
+//! `~` and `~/…` expand to the home directory; `~user` forms are refused
+//! rather than guessed. Paths are normalized lexically, without touching the
+//! filesystem: `.` components are dropped and `..` removes the component
+//! before it.
+
+use anyhow::{bail, Result};
+use std::path::{Component, Path, PathBuf, MAIN_SEPARATOR};
+
+/// Directory `~` stands for
+///
+/// `HOME` wins when set, so shells like Git Bash on Windows agree with Unix;
+/// otherwise `USERPROFILE` on Windows and then the platform default.
+pub fn home_dir() -> Option<PathBuf> {
+    home_dir_from(|name| std::env::var(name).ok())
+}
+
+fn home_dir_from(var: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let from_env = |name: &str| var(name).filter(|v| !v.trim().is_empty()).map(PathBuf::from);
+    from_env("HOME")
+        .or_else(|| if cfg!(windows) { from_env("USERPROFILE") } else { None })
+        .or_else(dirs::home_dir)
+}
+
+/// Expand a leading `~` to the home directory and normalize the result
+///
+/// Fails when the path needs a home directory and none is known (a container
+/// without `HOME`), and for `~user` forms.
+pub fn expand(path: &str) -> Result<PathBuf> {
+    expand_with(path, home_dir().as_deref(), MAIN_SEPARATOR)
+}
+
+fn expand_with(path: &str, home: Option<&Path>, sep: char) -> Result<PathBuf> {
+    let is_sep = |c: char| c == '/' || c == sep;
+    let Some(rest) = path.strip_prefix('~') else {
+        return Ok(normalize(Path::new(path)));
+    };
+    if !rest.is_empty() && !rest.starts_with(is_sep) {
+        bail!("Cannot expand `{}`: `~user` paths are not supported, use an absolute path", path);
+    }
+    let Some(home) = home else {
+        bail!("Cannot expand `{}`: no home directory is known, set HOME", path);
+    };
+    let mut expanded = home.to_path_buf();
+    expanded.extend(rest.split(is_sep).filter(|part| !part.is_empty()));
+    Ok(normalize(&expanded))
+}
+
+/// Replace the home directory prefix with `~` (e.g. `/Users/foo/.cache` → `~/.cache`)
+pub fn compress(path: &Path) -> PathBuf {
+    compress_with(path, home_dir().as_deref())
+}
+
+fn compress_with(path: &Path, home: Option<&Path>) -> PathBuf {
+    match home.map(|home| path.strip_prefix(home)) {
+        Some(Ok(relative)) if relative.as_os_str().is_empty() => PathBuf::from("~"),
+        // `/` separators keep the saved config portable across platforms
+        Some(Ok(relative)) => PathBuf::from(format!("~/{}", crate::store::path::index_path(relative))),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `path` relative to `base`, normalized
+///
+/// An absolute `path` must lie under `base`. Fails when `..` components
+/// climb out of `base`.
+pub fn normalize_rel(base: &Path, path: &str) -> Result<PathBuf> {
+    let path = Path::new(path);
+    let relative = if path.has_root() {
+        match normalize(path).strip_prefix(normalize(base)) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => bail!("`{}` is outside `{}`", path.display(), base.display()),
+        }
+    } else {
+        normalize(path)
+    };
+    if relative.components().next() == Some(Component::ParentDir) {
+        bail!("`{}` escapes `{}`", path.display(), base.display());
+    }
+    Ok(relative)
+}
+
+/// Drop `.` components and resolve `..` against the component before it
+///
+/// `..` at the root stays at the root; leading `..` of a relative path are
+/// kept, and a relative path that resolves to nothing becomes `.`.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other),
+        }
+    }
+    if normalized.as_os_str().is_empty() && !path.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_home_dir_prefers_home_variable() {
+        let vars = |name: &str| match name {
+            "HOME" => Some("/custom/home".to_string()),
+            "USERPROFILE" => Some("C:\\Users\\me".to_string()),
+            _ => None,
+        };
+        assert_eq!(home_dir_from(vars), Some(PathBuf::from("/custom/home")));
+
+        // An empty HOME does not count as set
+        let empty = |name: &str| (name == "HOME").then(String::new);
+        assert_ne!(home_dir_from(empty), Some(PathBuf::new()));
+    }
+
+    #[test]
+    fn test_expand_tilde_forms() {
+        let home = Some(Path::new("/home/me"));
+        let expected = Path::new("/home/me").join("notes").join("daily");
+        assert_eq!(expand_with("~/notes/daily", home, '/').unwrap(), expected);
+        assert_eq!(expand_with("~\\notes\\daily", home, '\\').unwrap(), expected);
+        assert_eq!(expand_with("~", home, '/').unwrap(), PathBuf::from("/home/me"));
+        assert_eq!(expand_with("~/notes/./old/../daily", home, '/').unwrap(), expected);
+
+        let err = expand_with("~other/x", home, '/').unwrap_err().to_string();
+        assert!(err.contains("`~other/x`") && err.contains("~user"), "{}", err);
+    }
+
+    #[test]
+    fn test_expand_plain_paths() {
+        let home = Some(Path::new("/home/me"));
+        assert_eq!(expand_with("/srv/docs/../notes", home, '/').unwrap(), PathBuf::from("/srv/notes"));
+        assert_eq!(expand_with("/..", home, '/').unwrap(), PathBuf::from("/"));
+        assert_eq!(expand_with("./docs", home, '/').unwrap(), PathBuf::from("docs"));
+        assert_eq!(expand_with(".", home, '/').unwrap(), PathBuf::from("."));
+        assert_eq!(expand_with("docs/..", home, '/').unwrap(), PathBuf::from("."));
+        assert_eq!(expand_with("../docs", home, '/').unwrap(), PathBuf::from("../docs"));
+        // A file literally named `x~` is no tilde path
+        assert_eq!(expand_with("notes/x~", home, '/').unwrap(), PathBuf::from("notes/x~"));
+    }
+
+    #[test]
+    fn test_expand_without_home() {
+        let err = expand_with("~/notes", None, '/').unwrap_err().to_string();
+        assert!(err.contains("`~/notes`") && err.contains("HOME"), "{}", err);
+        // Paths not needing a home still expand
+        assert_eq!(expand_with("/srv/docs", None, '/').unwrap(), PathBuf::from("/srv/docs"));
+        assert_eq!(expand_with("docs", None, '/').unwrap(), PathBuf::from("docs"));
+    }
+
+    #[test]
+    fn test_compress_round_trips_with_forward_slashes() {
+        let home = Path::new("/home/me");
+        let nested = home.join("notes").join("daily");
+        assert_eq!(compress_with(&nested, Some(home)), PathBuf::from("~/notes/daily"));
+        assert_eq!(compress_with(home, Some(home)), PathBuf::from("~"));
+        assert_eq!(compress_with(Path::new("/srv/docs"), Some(home)), PathBuf::from("/srv/docs"));
+        assert_eq!(compress_with(&nested, None), nested);
+        assert_eq!(expand_with("~/notes/daily", Some(home), '/').unwrap(), nested);
+    }
+
+    #[test]
+    fn test_normalize_rel_collection_patterns() {
+        let base = Path::new("/srv/docs");
+        assert_eq!(normalize_rel(base, "**/*.md").unwrap(), PathBuf::from("**/*.md"));
+        assert_eq!(normalize_rel(base, "./guides/*.md").unwrap(), PathBuf::from("guides/*.md"));
+        assert_eq!(normalize_rel(base, "guides/../**/*.md").unwrap(), PathBuf::from("**/*.md"));
+        assert_eq!(normalize_rel(base, "/srv/docs/guides/*.md").unwrap(), PathBuf::from("guides/*.md"));
+
+        let err = normalize_rel(base, "../secrets/*.md").unwrap_err().to_string();
+        assert!(err.contains("`../secrets/*.md` escapes `/srv/docs`"), "{}", err);
+        assert!(normalize_rel(base, "guides/../../*.md").is_err());
+        let err = normalize_rel(base, "/etc/*.conf").unwrap_err().to_string();
+        assert!(err.contains("`/etc/*.conf` is outside `/srv/docs`"), "{}", err);
+    }
+}
@@ -20,6 +20,7 @@ pub struct PluginInfo {
 /// Loaded plugin (simplified - stores module reference)
 pub struct Plugin {
     info: PluginInfo,
+    #[allow(dead_code)]
     module: Module,
 }
 
@@ -73,7 +74,7 @@ impl PluginManager {
             let entry = entry?;
             let path = entry.path();
 
-            if path.extension().map_or(false, |ext| ext == "wasm") {
+            if path.extension().is_some_and(|ext| ext == "wasm") {
                 // Try to extract info from filename (format: name-version.wasm)
                 let filename = path.file_stem()
                     .and_then(|s| s.to_str())
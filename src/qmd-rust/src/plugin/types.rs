@@ -12,9 +12,12 @@ pub trait Filter: Send + Sync {
     fn filter(&self, title: &str, body: &str) -> bool;
 }
 
+/// Title, body and metadata pairs produced by a transform
+pub type Transformed = (String, String, Vec<(String, String)>);
+
 /// Plugin trait for custom transform
 pub trait Transform: Send + Sync {
-    fn transform(&self, title: &str, body: &str) -> Result<(String, String, Vec<(String, String)>), String>;
+    fn transform(&self, title: &str, body: &str) -> Result<Transformed, String>;
 }
 
 /// Plugin trait for query preprocessing
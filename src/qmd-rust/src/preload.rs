@@ -0,0 +1,134 @@
+//! Startup warm-up for the long-running server and MCP modes.
+//!
+//! Without it the first query after start pays for the SQLite page cache and,
+//! with llama.cpp, for loading the embedding model. Preload does that work
+//! before the listener accepts traffic and logs how long each step took.
+
+use crate::llm::Router;
+use crate::store::Store;
+use anyhow::Result;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Text embedded to force the embedding model to load
+const WARMUP_TEXT: &str = "qmd preload";
+
+/// Model loading step of preload; implemented by the LLM router
+pub trait Warmup {
+    /// Embed a throwaway text; returns false when no embedder is configured
+    fn warm_up(&self) -> impl Future<Output = Result<bool>>;
+}
+
+impl Warmup for Router {
+    async fn warm_up(&self) -> Result<bool> {
+        if !self.has_embedder() {
+            return Ok(false);
+        }
+        self.embed(&[WARMUP_TEXT]).await?;
+        Ok(true)
+    }
+}
+
+/// Time taken by one preload step
+#[derive(Debug, Clone)]
+pub struct PreloadStep {
+    pub name: String,
+    pub elapsed: Duration,
+}
+
+/// Steps run by `preload`, in order
+#[derive(Debug, Clone, Default)]
+pub struct PreloadReport {
+    pub steps: Vec<PreloadStep>,
+}
+
+impl PreloadReport {
+    fn record(&mut self, name: String, start: Instant) {
+        let elapsed = start.elapsed();
+        log::info!("Preload: {} in {:.1?}", name, elapsed);
+        self.steps.push(PreloadStep { name, elapsed });
+    }
+
+    /// Sum of all step durations
+    pub fn total(&self) -> Duration {
+        self.steps.iter().map(|s| s.elapsed).sum()
+    }
+}
+
+/// Open and query every collection, then force the embedding model to load
+///
+/// Collection failures abort startup; a model that fails to load is only
+/// logged, since BM25 search still works without it.
+pub async fn preload<W: Warmup>(store: &Store, llm: &W) -> Result<PreloadReport> {
+    let mut report = warm_collections(store)?;
+    warm_model(llm, &mut report).await;
+    log::info!("Preload complete in {:.1?}", report.total());
+    Ok(report)
+}
+
+/// Collection half of `preload`, for callers that cannot hold the store across an await
+pub fn warm_collections(store: &Store) -> Result<PreloadReport> {
+    let mut report = PreloadReport::default();
+    for collection in store.get_collections() {
+        let start = Instant::now();
+        store.warm_up_collection(&collection.name)?;
+        report.record(format!("collection '{}'", collection.name), start);
+    }
+    Ok(report)
+}
+
+/// Model half of `preload`
+pub async fn warm_model<W: Warmup>(llm: &W, report: &mut PreloadReport) {
+    let start = Instant::now();
+    match llm.warm_up().await {
+        Ok(true) => report.record("embedding model".to_string(), start),
+        Ok(false) => log::info!("Preload: no embedder configured, skipping model load"),
+        Err(e) => log::warn!("Preload: embedding model failed to load: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CollectionConfig, Config};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FailingWarmup(AtomicUsize);
+
+    impl Warmup for FailingWarmup {
+        async fn warm_up(&self) -> Result<bool> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            anyhow::bail!("model file missing")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preload_times_each_collection_and_tolerates_model_failure() {
+        let tmp = tempfile::tempdir().unwrap();
+        let collection = |name: &str| {
+            let path = tmp.path().join(name);
+            std::fs::create_dir_all(&path).unwrap();
+            CollectionConfig {
+                name: name.to_string(),
+                path,
+                pattern: None,
+                description: None,
+                ..Default::default()
+            }
+        };
+        let config = Config {
+            collections: vec![collection("docs"), collection("notes")],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        let store = Store::new(&config).unwrap();
+        let warmup = FailingWarmup(AtomicUsize::new(0));
+
+        let report = preload(&store, &warmup).await.unwrap();
+
+        let names: Vec<&str> = report.steps.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["collection 'docs'", "collection 'notes'"]);
+        assert_eq!(warmup.0.load(Ordering::SeqCst), 1);
+        assert!(config.db_path_for("notes").exists());
+    }
+}
@@ -0,0 +1,49 @@
+//! Chrome-trace timing profiles for `--profile`.
+//!
+//! Search and indexing stages carry `tracing` spans; with `--profile <file>`
+//! they are recorded by tracing-chrome and written as trace-event JSON when
+//! the command exits. Open the file in https://ui.perfetto.dev or
+//! `chrome://tracing`, or drop it on https://www.speedscope.app for a
+//! flamegraph view.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::prelude::*;
+
+/// Active profile; the trace file is completed when this is dropped
+pub struct Profile {
+    _guard: FlushGuard,
+}
+
+/// Start recording spans to `path` for the rest of the process
+pub fn start(path: &Path) -> Result<Profile> {
+    let (layer, guard) = layer(path)?;
+    // set_global_default rather than try_init: env_logger already owns the `log` facade
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+        .context("Failed to install profiling subscriber")?;
+    log::info!("Writing timing profile to {}", path.display());
+    Ok(Profile { _guard: guard })
+}
+
+impl Profile {
+    /// Wrap the guard of a `layer` installed by another subscriber
+    pub fn from_guard(guard: FlushGuard) -> Self {
+        Self { _guard: guard }
+    }
+}
+
+/// Chrome-trace layer writing to `path`, for callers installing their own subscriber
+pub fn layer<S>(path: &Path) -> Result<(tracing_chrome::ChromeLayer<S>, FlushGuard)>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span> + Send + Sync,
+{
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create profile file: {}", path.display()))?;
+    Ok(ChromeLayerBuilder::new()
+        .writer(BufWriter::new(file))
+        .include_args(true)
+        .build())
+}
@@ -1,11 +1,18 @@
 // HTTP request handlers
 
+use crate::anel::{AnelError, AnelErrorCode, RecoveryHint};
+use crate::config::fold_name;
+use crate::server::middleware::VerifiedIdentity;
+use crate::server::observability::Tracing;
 use crate::server::ServerState;
-use crate::store::SearchOptions;
+use crate::store::deadline::{Deadline, SearchOutcome, SearchStage, StageFailure};
+use crate::store::timings::{StageTimer, StageTimings};
+use crate::store::access::{RetrievedDocument, RetrievedFile};
+use crate::store::{fused_relevance, page, SearchOptions, SearchResult, Store};
 use axum::{
-    extract::{Path, Query, State},
-    http::{header::HeaderMap, StatusCode},
-    response::{IntoResponse, Json},
+    extract::{Extension, Path, Query, State},
+    http::{header, header::HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
 
@@ -18,7 +25,7 @@ pub async fn check_rate_limit_and_auth(
     let client_ip = get_client_ip(headers);
 
     // Check rate limit
-    let (allowed, remaining, reset_secs) = state.rate_limit_state.check(&client_ip).await;
+    let (allowed, _remaining, _reset_secs) = state.rate_limit_state.check(&client_ip).await;
     if !allowed {
         let error = ErrorResponse {
             error: "Rate limit exceeded".to_string(),
@@ -68,15 +75,31 @@ pub struct SearchRequest {
     pub query: String,
     #[serde(default)]
     pub limit: Option<usize>,
+    /// Results to skip, for paging; applied after results are merged and reranked
+    #[serde(default)]
+    pub offset: Option<usize>,
     #[serde(default)]
     pub collection: Option<String>,
+    /// Overall time budget for `/vsearch` and `/query`; on expiry the results
+    /// gathered so far are returned with `partial: true`
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct SearchResponse {
     pub results: Vec<SearchResultDto>,
+    pub offset: usize,
     pub total: usize,
     pub query: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_stages: Vec<SearchStage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub degraded_stages: Vec<StageFailure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<StageTimings>,
 }
 
 #[derive(Debug, Serialize)]
@@ -87,6 +110,8 @@ pub struct SearchResultDto {
     pub path: String,
     pub score: f32,
     pub lines: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<usize>,
 }
 
 #[derive(Debug, Serialize)]
@@ -103,6 +128,8 @@ pub struct StatsResponse {
     pub indexed: usize,
     pub pending: usize,
     pub chunks: usize,
+    pub tokens: usize,
+    pub index_bytes: u64,
     pub collection_stats: Vec<CollectionStatDto>,
 }
 
@@ -110,6 +137,8 @@ pub struct StatsResponse {
 pub struct CollectionStatDto {
     pub name: String,
     pub count: usize,
+    pub tokens: usize,
+    pub index_bytes: u64,
 }
 
 #[derive(Debug, Serialize)]
@@ -119,12 +148,33 @@ pub struct HealthResponse {
     pub model_loaded: bool,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SuggestQuery {
+    /// Prefix to complete
+    pub q: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GetDocumentQuery {
     #[serde(default)]
     pub from: Option<usize>,
     #[serde(default)]
     pub limit: Option<usize>,
+    /// Serve the document body instead of the JSON line window
+    #[serde(default, rename = "as")]
+    pub format: Option<DocumentFormat>,
+}
+
+/// Body served by `GET /documents/{path}?as=...`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentFormat {
+    /// The text the index stores, extracted for notebooks
+    Text,
+    /// The file's bytes as they are on disk
+    Raw,
 }
 
 #[derive(Debug, Serialize)]
@@ -160,6 +210,15 @@ pub async fn health(State(state): State<ServerState>) -> impl IntoResponse {
     Json(response)
 }
 
+/// Readiness probe: 503 until startup preload has finished
+pub async fn readyz(State(state): State<ServerState>) -> impl IntoResponse {
+    if state.ready.load(std::sync::atomic::Ordering::SeqCst) {
+        (StatusCode::OK, Json(serde_json::json!({"status": "ready"})))
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({"status": "starting"})))
+    }
+}
+
 /// ANEL specification endpoint - returns all command specifications
 pub async fn spec() -> impl IntoResponse {
     let specs = serde_json::json!({
@@ -176,6 +235,10 @@ pub async fn spec() -> impl IntoResponse {
             "update": AnelSpec::update(),
             "status": AnelSpec::status(),
             "cleanup": AnelSpec::cleanup(),
+            "remove": AnelSpec::remove(),
+            "suggest": AnelSpec::suggest(),
+            "verify": AnelSpec::verify(),
+            "reindex": AnelSpec::reindex(),
             "agent": AnelSpec::agent(),
             "mcp": AnelSpec::mcp()
         }
@@ -184,15 +247,18 @@ pub async fn spec() -> impl IntoResponse {
     Json(specs)
 }
 
-/// List all collections
+/// List all collections the caller's API key may read
 pub async fn list_collections(
     State(state): State<ServerState>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
+    let allowed = allowed_collections(&state, &headers).await;
     let store = state.store.lock().await;
     let collections = store.get_collections();
 
     let collection_dtos: Vec<CollectionDto> = collections
         .iter()
+        .filter(|c| allowed.as_ref().is_none_or(|allowed| allows(allowed, &c.name).is_some()))
         .map(|c| CollectionDto {
             name: c.name.clone(),
             description: c.description.clone(),
@@ -203,18 +269,33 @@ pub async fn list_collections(
     Json(collection_dtos)
 }
 
-/// Index statistics
-pub async fn stats(State(state): State<ServerState>) -> impl IntoResponse {
+/// Index statistics, limited to the collections the caller's API key may read
+pub async fn stats(State(state): State<ServerState>, headers: HeaderMap) -> impl IntoResponse {
+    let allowed = allowed_collections(&state, &headers).await;
     let store = state.store.lock().await;
 
-    let stats = store.get_stats();
+    let allowed = allowed.map(|allowed| {
+        store
+            .get_collections()
+            .iter()
+            .filter(|c| allows(&allowed, &c.name).is_some())
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>()
+    });
+    let stats = store.get_stats_for(allowed.as_deref());
 
     let response = match stats {
         Ok(stats) => {
+            let collection_tokens = stats.collection_tokens;
+            let collection_index_bytes = stats.collection_index_bytes;
             let collection_stats: Vec<CollectionStatDto> = stats
                 .collection_stats
                 .into_iter()
-                .map(|(name, count)| CollectionStatDto { name, count })
+                .map(|(name, count)| {
+                    let tokens = collection_tokens.get(&name).copied().unwrap_or(0);
+                    let index_bytes = collection_index_bytes.get(&name).copied().unwrap_or(0);
+                    CollectionStatDto { name, count, tokens, index_bytes }
+                })
                 .collect();
 
             StatsResponse {
@@ -223,6 +304,8 @@ pub async fn stats(State(state): State<ServerState>) -> impl IntoResponse {
                 indexed: stats.indexed_count,
                 pending: stats.pending_count,
                 chunks: stats.chunk_count,
+                tokens: stats.token_count,
+                index_bytes: stats.index_bytes,
                 collection_stats,
             }
         }
@@ -233,6 +316,8 @@ pub async fn stats(State(state): State<ServerState>) -> impl IntoResponse {
                 indexed: 0,
                 pending: 0,
                 chunks: 0,
+                tokens: 0,
+                index_bytes: 0,
                 collection_stats: vec![],
             }
         }
@@ -241,223 +326,536 @@ pub async fn stats(State(state): State<ServerState>) -> impl IntoResponse {
     Json(response)
 }
 
+/// Size report for one collection: disk usage, extensions, largest documents
+pub async fn collection_report(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Response {
+    if let Err(e) = collection_scope(&state, &headers, Some(&name)).await {
+        return problem_response(e);
+    }
+    let store = state.store.lock().await;
+
+    match store.collection_report(&name) {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => problem_response(store_error(e, |e| {
+            AnelError::new(
+                AnelErrorCode::StorageError,
+                "Collection Report Failed",
+                format!("Failed to build report for '{}': {}", name, e),
+            )
+        })),
+    }
+}
+
+/// Completions of `q` from the vocabulary of the collections the caller's
+/// API key may read
+pub async fn suggest(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    Query(query): Query<SuggestQuery>,
+) -> Response {
+    let allowed = allowed_collections(&state, &headers).await;
+    let store = state.store.lock().await;
+
+    let allowed = allowed.map(|allowed| {
+        store
+            .get_collections()
+            .iter()
+            .filter(|c| allows(&allowed, &c.name).is_some())
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>()
+    });
+    match store.suggest_for(&query.q, query.limit.unwrap_or(10), allowed.as_deref()) {
+        Ok(suggestions) => Json(serde_json::json!({
+            "prefix": query.q,
+            "suggestions": suggestions,
+        }))
+        .into_response(),
+        Err(e) => problem_response(store_error(e, |e| {
+            AnelError::new(
+                AnelErrorCode::StorageError,
+                "Suggest Failed",
+                format!("Failed to suggest completions for '{}': {}", query.q, e),
+            )
+        })),
+    }
+}
+
 /// BM25 full-text search
 pub async fn search(
     State(state): State<ServerState>,
+    headers: HeaderMap,
     Json(req): Json<SearchRequest>,
-) -> impl IntoResponse {
+) -> Response {
+    let scope = match collection_scope(&state, &headers, req.collection.as_deref()).await {
+        Ok(scope) => scope,
+        Err(e) => return problem_response(e),
+    };
     let store = state.store.lock().await;
 
     let options = SearchOptions {
         limit: req.limit.unwrap_or(20),
-        min_score: 0.0,
+        offset: req.offset.unwrap_or(0),
         collection: req.collection.clone(),
         search_all: req.collection.is_none(),
+        ..Default::default()
     };
 
-    let results = store.bm25_search(&req.query, options);
+    let results = scoped_bm25_search(&store, &req.query, &scoped_options(options.unpaged(), scope)).unwrap_or_default();
+    let dtos = to_dtos(page(results, &options));
 
-    let dtos: Vec<SearchResultDto> = match results {
-        Ok(results) => results
+    let response = SearchResponse {
+        offset: options.offset,
+        total: dtos.len(),
+        results: dtos,
+        query: req.query,
+        partial: false,
+        skipped_stages: Vec::new(),
+        degraded_stages: Vec::new(),
+        timings: None,
+    };
+
+    Json(response).into_response()
+}
+
+// ── Collection scoping ──────────────────────────────────────────────
+
+/// Collections the request's API key is limited to; `None` when unrestricted
+async fn allowed_collections(state: &ServerState, headers: &HeaderMap) -> Option<Vec<String>> {
+    let api_key = headers.get("x-api-key").and_then(|v| v.to_str().ok());
+    state.auth_state.allowed_collections(api_key).await
+}
+
+/// The allowance entry matching `name`, compared like collection lookups
+fn allows<'a>(allowed: &'a [String], name: &str) -> Option<&'a String> {
+    let name = fold_name(name);
+    allowed.iter().find(|a| fold_name(a) == name)
+}
+
+/// Collections a request may search, after applying its API key's allowance
+///
+/// `None` leaves the request as it is. Otherwise it is the requested
+/// collection, if the key allows it, or else the key's whole allowance.
+/// The effective set is written to the access log.
+async fn collection_scope(
+    state: &ServerState,
+    headers: &HeaderMap,
+    requested: Option<&str>,
+) -> Result<Option<Vec<String>>, AnelError> {
+    let Some(allowed) = allowed_collections(state, headers).await else {
+        return Ok(None);
+    };
+
+    let scope = match requested {
+        // Unknown names get the same answer as forbidden ones, so other
+        // tenants' collections cannot be discovered
+        Some(name) => match allows(&allowed, name) {
+            Some(name) => vec![name.clone()],
+            None => return Err(collection_denied_error(name)),
+        },
+        None => allowed,
+    };
+
+    let api_key = headers.get("x-api-key").and_then(|v| v.to_str().ok());
+    let api_key_name = state.auth_state.key_name(api_key).await;
+    tracing::info!(
+        target: "qmd::access",
+        api_key_name = api_key_name.as_deref().unwrap_or("-"),
+        collections = ?scope,
+        "Scoped request"
+    );
+    Ok(Some(scope))
+}
+
+/// One search per collection in scope, or the request unchanged when unrestricted
+fn scoped_options(options: SearchOptions, scope: Option<Vec<String>>) -> Vec<SearchOptions> {
+    match scope {
+        None => vec![options],
+        Some(collections) => collections
             .into_iter()
-            .map(|r| SearchResultDto {
-                docid: r.docid,
-                collection: r.collection,
-                title: r.title,
-                path: r.path,
-                score: r.score,
-                lines: r.lines,
+            .map(|collection| SearchOptions {
+                collection: Some(collection),
+                search_all: false,
+                ..options.clone()
             })
             .collect(),
-        Err(_) => vec![],
+    }
+}
+
+fn collection_denied_error(name: &str) -> AnelError {
+    AnelError::new(
+        AnelErrorCode::PermissionDenied,
+        "Collection Not Allowed",
+        format!("This API key may not read collection '{}'", name),
+    )
+    .with_hint(
+        RecoveryHint::new("LIST_ALLOWED_COLLECTIONS", "Only collections listed by /collections are readable with this key")
+            .with_action("GET /collections"),
+    )
+}
+
+/// Build an RFC 7807 problem+json response from an ANEL error
+pub(crate) fn problem_response(error: AnelError) -> Response {
+    let status = StatusCode::from_u16(error.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut response = (status, Json(error)).into_response();
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/problem+json"),
+    );
+    response
+}
+
+/// Reject queries that cannot be searched before touching any backend
+fn validate_query(query: &str) -> Result<(), Box<AnelError>> {
+    if query.trim().is_empty() {
+        return Err(Box::new(
+            AnelError::new(AnelErrorCode::QueryParseError, "Invalid Query", "Query must not be empty")
+                .with_hint(RecoveryHint::new("PROVIDE_QUERY", "Send a non-empty \"query\" field")),
+        ));
+    }
+
+    if !query.matches('"').count().is_multiple_of(2) {
+        return Err(Box::new(
+            AnelError::new(
+                AnelErrorCode::QueryParseError,
+                "Invalid Query",
+                format!("Unbalanced quotes in query: {}", query),
+            )
+            .with_hint(RecoveryHint::new("FIX_QUOTES", "Close every quoted phrase in the query")),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Embed the query, holding the LLM lock only for the embedding call
+async fn embed_query(state: &ServerState, query: &str) -> Result<Vec<f32>, AnelError> {
+    let result = {
+        let llm = state.llm.lock().await;
+        if !llm.has_embedder() {
+            return Err(AnelError::new(
+                AnelErrorCode::BackendUnavailable,
+                "Embedder Unavailable",
+                "No embedder available",
+            )
+            .with_hint(
+                RecoveryHint::new("CONFIGURE_EMBEDDER", "Configure an embedding model")
+                    .with_action("Set models.embed.local or models.embed.remote in index.yaml"),
+            ));
+        }
+        llm.embed(&[query]).await
     };
 
-    let response = SearchResponse {
+    state.metrics.inc_llm_embeddings();
+    let embedding = result
+        .map_err(|e| {
+            state.metrics.inc_llm_errors();
+            AnelError::new(
+                AnelErrorCode::BackendUnavailable,
+                "Embedding Failed",
+                format!("Failed to embed query: {}", e),
+            )
+        })?
+        .embeddings
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+
+    if embedding.is_empty() {
+        return Err(AnelError::new(
+            AnelErrorCode::EmbeddingFailed,
+            "Embedding Failed",
+            "Embedder returned an empty vector",
+        ));
+    }
+
+    Ok(embedding)
+}
+
+/// Run a vector search with a pre-computed embedding
+async fn vector_search(
+    state: &ServerState,
+    embedding: &[f32],
+    options: SearchOptions,
+) -> Result<Vec<SearchResult>, AnelError> {
+    let store = state.store.lock().await;
+    store
+        .vector_search_with_embedding(embedding, options)
+        .map_err(|e| {
+            store_error(e, |e| {
+                AnelError::new(
+                    AnelErrorCode::BackendUnavailable,
+                    "Vector Search Failed",
+                    format!("Vector backend error: {}", e),
+                )
+                .with_hint(RecoveryHint::new(
+                    "CHECK_VECTOR_BACKEND",
+                    "Check the configured vector backend and run `qmd embed`",
+                ))
+            })
+        })
+}
+
+/// Keep ANEL errors raised by the Store as-is, wrap anything else
+fn store_error(err: anyhow::Error, wrap: impl FnOnce(&anyhow::Error) -> AnelError) -> AnelError {
+    match err.downcast::<AnelError>() {
+        Ok(anel) => anel,
+        Err(err) => wrap(&err),
+    }
+}
+
+/// Response for a deadline-bound search, counting it if partial
+fn search_response(state: &ServerState, query: String, offset: Option<usize>, outcome: SearchOutcome) -> Response {
+    let partial = outcome.is_partial();
+    if partial {
+        state.metrics.inc_partial();
+    }
+    if let Some(ref timings) = outcome.timings {
+        state.metrics.record_search_stages(timings);
+    }
+    let dtos = to_dtos(outcome.results);
+    Json(SearchResponse {
+        offset: offset.unwrap_or(0),
         total: dtos.len(),
         results: dtos,
-        query: req.query,
-    };
+        query,
+        partial,
+        skipped_stages: outcome.skipped,
+        degraded_stages: outcome.degraded,
+        timings: outcome.timings,
+    })
+    .into_response()
+}
 
-    Json(response)
+fn to_dtos(results: Vec<SearchResult>) -> Vec<SearchResultDto> {
+    results
+        .into_iter()
+        .map(|r| SearchResultDto {
+            docid: r.docid,
+            collection: r.collection,
+            title: r.title,
+            path: r.path,
+            score: r.score,
+            lines: r.lines,
+            tokens: r.size.map(|size| size.tokens),
+        })
+        .collect()
 }
 
 /// Vector semantic search
 pub async fn vsearch(
     State(state): State<ServerState>,
+    headers: HeaderMap,
     Json(req): Json<SearchRequest>,
-) -> impl IntoResponse {
-    // Generate embedding first
-    let embedding = {
-        let llm = state.llm.lock().await;
-        match llm.embed(&[req.query.as_str()]).await {
-            Ok(e) => e.embeddings.into_iter().next().unwrap_or_default(),
-            Err(_) => vec![],
+) -> Response {
+    state.metrics.inc_vsearch();
+
+    match run_vsearch(&state, &headers, &req).await {
+        Ok(outcome) => search_response(&state, req.query, req.offset, outcome),
+        Err(e) => {
+            state.metrics.inc_errors();
+            problem_response(e)
         }
-    };
-
-    if embedding.is_empty() {
-        let response = SearchResponse {
-            total: 0,
-            results: vec![],
-            query: req.query,
-        };
-        return Json(response);
     }
+}
 
-    // Vector search with pre-computed embedding (sync function)
-    let store = state.store.lock().await;
+async fn run_vsearch(state: &ServerState, headers: &HeaderMap, req: &SearchRequest) -> Result<SearchOutcome, AnelError> {
+    let deadline = Deadline::new(req.timeout_ms);
+    validate_query(&req.query).map_err(|e| *e)?;
+    let scope = collection_scope(state, headers, req.collection.as_deref()).await?;
+    let mut outcome = SearchOutcome::default();
+
+    // Generate embedding first
+    let Some(embedding) = deadline.run(embed_query(state, &req.query)).await else {
+        outcome.skip(SearchStage::Vector);
+        return Ok(outcome);
+    };
+    let embedding = embedding?;
+    if let Some(error) = state.store.lock().await.vector_backend_error() {
+        return Err(error);
+    }
 
     let options = SearchOptions {
         limit: req.limit.unwrap_or(20),
-        min_score: 0.0,
+        offset: req.offset.unwrap_or(0),
         collection: req.collection.clone(),
         search_all: req.collection.is_none(),
+        ..Default::default()
     };
 
-    let results = store.vector_search_with_embedding(&embedding, options);
-
-    let dtos: Vec<SearchResultDto> = match results {
-        Ok(results) => results
-            .into_iter()
-            .map(|r| SearchResultDto {
-                docid: r.docid,
-                collection: r.collection,
-                title: r.title,
-                path: r.path,
-                score: r.score,
-                lines: r.lines,
-            })
-            .collect(),
-        Err(_) => vec![],
-    };
+    let results = scoped_vector_search(state, &embedding, scoped_options(options.unpaged(), scope)).await?;
+    outcome.results = page(results, &options);
+    Ok(outcome)
+}
 
-    let response = SearchResponse {
-        total: dtos.len(),
-        results: dtos,
-        query: req.query,
-    };
+/// BM25 search over each scoped option set, merged best-first and cut to
+/// the options' limit
+fn scoped_bm25_search(store: &Store, query: &str, scoped: &[SearchOptions]) -> anyhow::Result<Vec<SearchResult>> {
+    let mut results = Vec::new();
+    for options in scoped {
+        results.extend(store.bm25_search(query, options.clone())?);
+    }
+    // Scores share one scale across collections (higher is better)
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.truncate(scoped.first().map_or(0, |options| options.limit));
+    Ok(results)
+}
 
-    Json(response)
+/// Vector search over each scoped option set, merged nearest-first
+async fn scoped_vector_search(
+    state: &ServerState,
+    embedding: &[f32],
+    scoped: Vec<SearchOptions>,
+) -> Result<Vec<SearchResult>, AnelError> {
+    if scoped.len() == 1 {
+        return vector_search(state, embedding, scoped.into_iter().next().unwrap()).await;
+    }
+    let mut results = Vec::new();
+    for options in scoped {
+        results.extend(vector_search(state, embedding, options).await?);
+    }
+    // Same order the backends use across collections (most similar first)
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(results)
 }
 
 /// Hybrid search (BM25 + Vector + RRF + Reranking)
 pub async fn query(
     State(state): State<ServerState>,
+    headers: HeaderMap,
     Json(req): Json<SearchRequest>,
-) -> impl IntoResponse {
-    let query = req.query.clone();
-    let limit = req.limit.unwrap_or(20);
-    let collection = req.collection.clone();
-    let search_all = collection.is_none();
+) -> Response {
+    state.metrics.inc_query();
+
+    match run_query(&state, &headers, &req).await {
+        Ok(outcome) => search_response(&state, req.query, req.offset, outcome),
+        Err(e) => {
+            state.metrics.inc_errors();
+            problem_response(e)
+        }
+    }
+}
 
-    // Search options for BM25 and Vector
-    let options = SearchOptions {
-        limit: limit * 2, // Fetch more for reranking
-        min_score: 0.0,
-        collection: collection.clone(),
-        search_all,
-    };
+async fn run_query(state: &ServerState, headers: &HeaderMap, req: &SearchRequest) -> Result<SearchOutcome, AnelError> {
+    let deadline = Deadline::new(req.timeout_ms);
+    validate_query(&req.query).map_err(|e| *e)?;
+    let scope = collection_scope(state, headers, req.collection.as_deref()).await?;
 
-    // Step 1: BM25 search (hold Store lock)
-    let bm25_results = {
-        let store = state.store.lock().await;
-        store.bm25_search(&query, options.clone())
-    };
+    let query = req.query.as_str();
+    let limit = req.limit.unwrap_or(20);
+    let offset = req.offset.unwrap_or(0);
 
-    let bm25_results = match bm25_results {
-        Ok(r) => r,
-        Err(_) => vec![],
+    // Search options for BM25 and Vector, up to the end of the page; the page
+    // is cut after fusion and reranking
+    let options = SearchOptions {
+        limit: (offset + limit) * 2, // Fetch more for reranking
+        collection: req.collection.clone(),
+        search_all: req.collection.is_none(),
+        ..Default::default()
     };
 
-    // Step 2: Vector search (release Store lock first, then re-acquire)
-    let vector_results = {
-        // Generate embedding
-        let embedding = {
-            let llm = state.llm.lock().await;
-            match llm.embed(&[&query]).await {
-                Ok(e) => e.embeddings.into_iter().next().unwrap_or_default(),
-                Err(_) => vec![],
-            }
-        };
+    let scoped = scoped_options(options, scope);
+    let mut timings = StageTimings::default();
+    let mut timer = StageTimer::start();
 
-        if embedding.is_empty() {
-            vec![]
-        } else {
-            let store = state.store.lock().await;
-            match store.vector_search_with_embedding(&embedding, options) {
-                Ok(r) => r,
-                Err(_) => vec![],
+    // Step 1: BM25 search (hold Store lock)
+    let bm25_results = scoped_bm25_search(&*state.store.lock().await, query, &scoped).map_err(|e| {
+        store_error(e, |e| {
+            AnelError::new(
+                AnelErrorCode::SearchFailed,
+                "BM25 Search Failed",
+                format!("BM25 search error: {}", e),
+            )
+        })
+    })?;
+    timings.bm25_ms = timer.lap();
+
+    // Step 2: Vector search (LLM lock for the embedding only, then Store lock);
+    // BM25-only if the budget runs out first or the vector backend is missing
+    let mut outcome = SearchOutcome::default();
+    let vector_results = match deadline.run(embed_query(state, query)).await {
+        Some(embedding) => {
+            let embedding = embedding?;
+            let unavailable = state.store.lock().await.vector_backend_error();
+            match unavailable {
+                Some(error) => {
+                    outcome.fail(SearchStage::Vector, error);
+                    Vec::new()
+                }
+                None => scoped_vector_search(state, &embedding, scoped).await?,
             }
         }
+        None => {
+            outcome.skip(SearchStage::Vector);
+            Vec::new()
+        }
     };
+    timings.vector_ms = timer.lap();
 
     // Step 3: RRF Fusion (no locks held)
-    let fused_results = {
-        use crate::store::Store;
-        let result_lists = [bm25_results.clone(), vector_results.clone()];
-        Store::rrf_fusion(
-            &result_lists,
-            None,
-            limit as u32,
-        )
-    };
+    let mut fused_results = Store::rrf_fusion(&[bm25_results, vector_results], None, limit as u32);
+    for result in &mut fused_results {
+        result.score = fused_relevance(result.score, &[1.0, 1.0], limit as u32);
+    }
+    timings.fusion_ms = timer.lap();
+
+    if fused_results.is_empty() {
+        outcome.results = fused_results;
+        timings.total_ms = timer.total();
+        outcome.timings = Some(timings);
+        return Ok(outcome);
+    }
 
     // Step 4: LLM Reranking (hold LLM lock)
     // rerank returns Vec<f32> (scores), need to reorder results
-    let final_results = if !fused_results.is_empty() {
-        let scores = {
-            let llm = state.llm.lock().await;
-            match llm.rerank(&query, &fused_results).await {
-                Ok(s) => s,
-                Err(_) => fused_results.iter().map(|r| r.score).collect(),
+    let scores = {
+        let llm = state.llm.lock().await;
+        if llm.has_reranker() {
+            state.metrics.inc_llm_rerank();
+            match deadline.run(llm.rerank(query, &fused_results)).await {
+                Some(scores) => scores.ok(),
+                None => {
+                    outcome.skip(SearchStage::Rerank);
+                    None
+                }
             }
-        };
+        } else {
+            None
+        }
+    };
+    let reranked = scores.is_some();
+    let scores = scores.unwrap_or_else(|| fused_results.iter().map(|r| r.score).collect());
 
-        // Reorder fused_results based on rerank scores
-        let mut paired: Vec<(crate::store::SearchResult, f32)> = fused_results
-            .into_iter()
-            .zip(scores.into_iter())
-            .collect();
+    // Reorder fused_results based on rerank scores
+    let mut paired: Vec<(SearchResult, f32)> = fused_results.into_iter().zip(scores).collect();
 
-        // Sort by rerank score descending
-        paired.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    // Sort by rerank score descending
+    paired.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
 
-        // Update scores and return
-        paired
-            .into_iter()
-            .map(|(mut r, score)| {
-                r.score = score;
-                r
-            })
-            .collect()
-    } else {
-        fused_results
-    };
-
-    let dtos: Vec<SearchResultDto> = final_results
+    outcome.results = paired
         .into_iter()
-        .map(|r| SearchResultDto {
-            docid: r.docid,
-            collection: r.collection,
-            title: r.title,
-            path: r.path,
-            score: r.score,
-            lines: r.lines,
+        .skip(offset)
+        .take(limit)
+        .map(|(mut r, score)| {
+            r.score = score;
+            if let Some(components) = r.score_components.as_mut() {
+                components.rerank_score = reranked.then_some(score);
+            }
+            r
         })
         .collect();
-
-    let response = SearchResponse {
-        total: dtos.len(),
-        results: dtos,
-        query: req.query,
-    };
-
-    Json(response)
+    timings.rerank_ms = timer.lap();
+    timings.total_ms = timer.total();
+    outcome.timings = Some(timings);
+    Ok(outcome)
 }
 
 /// Get document content
 pub async fn get_document(
-    State(_state): State<ServerState>,
+    State(state): State<ServerState>,
+    headers: HeaderMap,
     Path(path): Path<String>,
     Query(query): Query<GetDocumentQuery>,
 ) -> axum::response::Response {
@@ -470,26 +868,50 @@ pub async fn get_document(
         Err(_) => path,
     };
 
-    // Read file
-    let content = std::fs::read_to_string(&path);
+    // Only indexed documents, or files inside a collection, are served
+    let allowed = allowed_collections(&state, &headers).await;
+    let permitted = |collection: &str| match allowed {
+        Some(ref allowed) if allows(allowed, collection).is_none() => Err(Box::new(collection_denied_error(collection))),
+        _ => Ok(()),
+    };
+
+    if query.format == Some(DocumentFormat::Raw) {
+        let file = {
+            let store = state.store.lock().await;
+            store.retrieve_file(&path)
+        };
+        return match file.and_then(|file| permitted(&file.collection).map(|_| file)) {
+            Ok(file) => raw_document(&headers, file).await,
+            Err(error) => problem_response(*error),
+        };
+    }
+
+    let document = {
+        let store = state.store.lock().await;
+        store.retrieve_document(&path)
+    };
+    let document = document.and_then(|document| permitted(&document.collection).map(|_| document));
+    if let (Ok(document), Some(DocumentFormat::Text)) = (&document, query.format) {
+        return text_document(&headers, document);
+    }
 
-    match content {
-        Ok(content) => {
-            let lines: Vec<&str> = content.lines().collect();
+    match document {
+        Ok(document) => {
+            let lines: Vec<&str> = document.content.lines().collect();
             let total = lines.len();
             let start = from.min(total);
             let end = (start + limit).min(total);
             let selected = lines[start..end].join("\n");
 
             // Extract title from path
-            let title = std::path::Path::new(&path)
+            let title = std::path::Path::new(&document.path)
                 .file_name()
                 .and_then(|n| n.to_str())
                 .unwrap_or("Unknown")
                 .to_string();
 
             let response = DocumentResponse {
-                path: path.clone(),
+                path: document.path.clone(),
                 title,
                 total_lines: total,
                 content: selected,
@@ -499,30 +921,258 @@ pub async fn get_document(
 
             Json(response).into_response()
         }
-        Err(_) => {
-            let error = ErrorResponse {
-                error: format!("File not found: {}", path),
-                code: "FILE_NOT_FOUND".to_string(),
-            };
-            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        Err(error) => problem_response(*error),
+    }
+}
+
+/// Indexed (or extracted) text of a document
+fn text_document(headers: &HeaderMap, document: &RetrievedDocument) -> Response {
+    let etag = format!("\"{}\"", document.hash);
+    if not_modified(headers, Some(&etag)) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+    let content_type = match content_type_for(&document.path) {
+        text if text.starts_with("text/") => text,
+        _ => "text/plain; charset=utf-8",
+    };
+
+    let bytes = document.content.as_bytes();
+    let len = bytes.len() as u64;
+    let range = requested_range(headers, len);
+    let body = match range {
+        ByteRange::Full => bytes.to_vec(),
+        ByteRange::Partial(start, end) => bytes[start as usize..=end as usize].to_vec(),
+        ByteRange::Unsatisfiable => Vec::new(),
+    };
+    body_response(content_type, Some(&etag), len, range, body)
+}
+
+/// A document's file as it is on disk
+async fn raw_document(headers: &HeaderMap, file: RetrievedFile) -> Response {
+    let etag = file.hash.as_ref().map(|hash| format!("\"{}\"", hash));
+    if not_modified(headers, etag.as_deref()) {
+        let etag = etag.unwrap_or_default();
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    match read_file_range(&file.path, headers).await {
+        Ok((len, range, body)) => body_response(
+            content_type_for(&file.path.to_string_lossy()),
+            etag.as_deref(),
+            len,
+            range,
+            body,
+        ),
+        Err(e) => {
+            log::warn!("Failed to read {}: {}", file.path.display(), e);
+            problem_response(AnelError::new(
+                AnelErrorCode::NotFound,
+                "Document Not Found",
+                format!("Document not found: {}", file.path.display()),
+            ))
+        }
+    }
+}
+
+/// Whether `If-None-Match` names the current ETag
+fn not_modified(headers: &HeaderMap, etag: Option<&str>) -> bool {
+    let Some(etag) = etag else {
+        return false;
+    };
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|tags| tags.split(',').map(str::trim).any(|tag| tag == "*" || tag == etag))
+}
+
+/// Document body, partial when a range was requested
+fn body_response(content_type: &'static str, etag: Option<&str>, len: u64, range: ByteRange, body: Vec<u8>) -> Response {
+    let (status, content_range) = match range {
+        ByteRange::Full => (StatusCode::OK, None),
+        ByteRange::Partial(start, end) => (StatusCode::PARTIAL_CONTENT, Some(format!("bytes {}-{}/{}", start, end, len))),
+        ByteRange::Unsatisfiable => (StatusCode::RANGE_NOT_SATISFIABLE, Some(format!("bytes */{}", len))),
+    };
+
+    let mut response = (status, body).into_response();
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    for (name, value) in [(header::CONTENT_RANGE, content_range.as_deref()), (header::ETAG, etag)] {
+        if let Some(value) = value.and_then(|v| HeaderValue::from_str(v).ok()) {
+            response_headers.insert(name, value);
         }
     }
+    response
+}
+
+/// Byte range a request asked for, over a body of known length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ByteRange {
+    Full,
+    /// Inclusive start and end offsets
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Single `Range: bytes=...` request; multiple or malformed ranges get the full body
+fn requested_range(headers: &HeaderMap, len: u64) -> ByteRange {
+    let Some(spec) = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().strip_prefix("bytes="))
+    else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // Suffix range: the last `end` bytes
+        return match end.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if len == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial(len - suffix.min(len), len - 1),
+            Err(_) => ByteRange::Full,
+        };
+    }
+    let Ok(start) = start.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = match end {
+        "" => len.saturating_sub(1),
+        end => match end.parse::<u64>() {
+            Ok(end) if end >= start => end.min(len.saturating_sub(1)),
+            _ => return ByteRange::Full,
+        },
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end)
+}
+
+/// Length of `file`, the range requested of it, and the bytes in that range
+async fn read_file_range(file: &std::path::Path, headers: &HeaderMap) -> std::io::Result<(u64, ByteRange, Vec<u8>)> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let mut handle = tokio::fs::File::open(file).await?;
+    let len = handle.metadata().await?.len();
+    let range = requested_range(headers, len);
+    let mut body = Vec::new();
+    match range {
+        ByteRange::Full => {
+            handle.read_to_end(&mut body).await?;
+        }
+        ByteRange::Partial(start, end) => {
+            handle.seek(std::io::SeekFrom::Start(start)).await?;
+            handle.take(end - start + 1).read_to_end(&mut body).await?;
+        }
+        ByteRange::Unsatisfiable => {}
+    }
+    Ok((len, range, body))
+}
+
+/// `Content-Type` for a document, by file extension
+fn content_type_for(path: &str) -> &'static str {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "md" | "markdown" => "text/markdown; charset=utf-8",
+        "txt" | "text" | "log" | "rst" | "org" => "text/plain; charset=utf-8",
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "rs" | "py" | "js" | "ts" | "go" | "java" | "c" | "h" | "cpp" | "sh" | "toml" => "text/plain; charset=utf-8",
+        "json" => "application/json",
+        "ipynb" => "application/x-ipynb+json",
+        "yaml" | "yml" => "application/yaml",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => "application/octet-stream",
+    }
 }
 
 /// MCP protocol handler (JSON-RPC)
 /// Note: For production, use standalone MCP HTTP server: `qmd mcp --transport http --port 8081`
-/// This endpoint provides basic MCP protocol info
+/// This endpoint provides basic MCP protocol info; every request is still counted
+/// per method/tool and audited like the stdio server's StreamTap.
 pub async fn mcp(
-    State(_state): State<ServerState>,
-    _body: String,
+    State(state): State<ServerState>,
+    identity: Option<Extension<VerifiedIdentity>>,
+    headers: HeaderMap,
+    body: String,
 ) -> impl IntoResponse {
+    let start = std::time::Instant::now();
+
+    let request: Option<serde_json::Value> = serde_json::from_str(&body).ok();
+    let method = request
+        .as_ref()
+        .and_then(|r| r.get("method"))
+        .and_then(|m| m.as_str())
+        .unwrap_or("invalid")
+        .to_string();
+    let params = request.as_ref().and_then(|r| r.get("params"));
+    let tool = if method == "tools/call" {
+        params
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str())
+            .map(|n| n.to_string())
+    } else {
+        None
+    };
+
     // For now, return instructions to use standalone MCP server
     // Full MCP integration requires more complex state management
     let error = ErrorResponse {
         error: "Use standalone MCP server instead: qmd mcp --transport http --port 8081".to_string(),
         code: "MCP_USE_STANDALONE".to_string(),
     };
-    (StatusCode::NOT_IMPLEMENTED, Json(error)).into_response()
+    let response = (StatusCode::NOT_IMPLEMENTED, Json(error)).into_response();
+
+    let duration = start.elapsed();
+    state.metrics.record_mcp_request(&method, tool.as_deref(), duration);
+
+    if state.audit.is_enabled() {
+        let header_str = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        let api_key_name = state.auth_state.key_name(header_str("x-api-key")).await;
+        let trace_id = header_str("x-trace-id")
+            .map(|t| t.to_string())
+            .unwrap_or_else(Tracing::generate_request_id);
+        let args_summary = params
+            .and_then(|p| if tool.is_some() { p.get("arguments") } else { Some(p) })
+            .map(|a| a.to_string())
+            .unwrap_or_else(|| "{}".to_string());
+
+        let mut record = crate::mcp::audit_record(
+            tool.as_deref().unwrap_or(&method),
+            &trace_id,
+            header_str("x-agent-identity"),
+            &args_summary,
+            "error",
+            duration.as_millis() as u64,
+        );
+        record["method"] = serde_json::json!(method);
+        record["api_key_name"] = serde_json::json!(api_key_name);
+        if let Some(Extension(VerifiedIdentity(name))) = identity {
+            record["identity_name"] = serde_json::json!(name);
+        }
+        record["collections"] = serde_json::json!(allowed_collections(&state, &headers).await);
+        state.audit.log(&record);
+    }
+
+    response
 }
 
 /// Prometheus metrics endpoint
@@ -567,6 +1217,10 @@ qmd_query_total {}
 # TYPE qmd_errors_total counter
 qmd_errors_total {}
 
+# HELP qmd_partial_responses_total Searches that hit their timeout and returned partial results
+# TYPE qmd_partial_responses_total counter
+qmd_partial_responses_total {}
+
 # HELP qmd_llm_embeddings_total Total embedding requests
 # TYPE qmd_llm_embeddings_total counter
 qmd_llm_embeddings_total {}
@@ -578,17 +1232,290 @@ qmd_llm_rerank_total {}
 # HELP qmd_llm_errors_total Total LLM errors
 # TYPE qmd_llm_errors_total counter
 qmd_llm_errors_total {}
-"#,
+
+{}
+{}
+{}
+{}"#,
         m.get_requests_total(),
         m.get_requests_in_flight(),
         m.get_search_total(),
         m.get_vsearch_total(),
         m.get_query_total(),
         m.get_errors_total(),
+        m.get_partial_total(),
         m.get_llm_embeddings_total(),
         m.get_llm_rerank_total(),
-        m.get_llm_errors()
+        m.get_llm_errors(),
+        m.render_mcp_prometheus(),
+        m.render_stage_prometheus(),
+        m.render_pool_prometheus(),
+        crate::server::observability::Metrics::render_limits_prometheus(&state.limits)
     );
 
     (StatusCode::OK, [("Content-Type", "text/plain; version=0.0.4")], output)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CollectionConfig, Config, LLMModelConfig};
+    use crate::llm::Router;
+    use crate::server::middleware::{AuthState, RateLimitState, RouteLimits};
+    use crate::server::observability::{AuditLog, Metrics};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    /// Build server state around a Router; `embed_model` configures a mock local embedder
+    fn test_state(embed_model: Option<&str>) -> (tempfile::TempDir, ServerState) {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut config = Config {
+            collections: vec![CollectionConfig {
+                name: "docs".to_string(),
+                path: tmp.path().to_path_buf(),
+                pattern: None,
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().to_path_buf(),
+            ..Config::default()
+        };
+        if let Some(model) = embed_model {
+            // The model file does not exist, so the local embedder returns fallback vectors
+            config.models.embed = Some(LLMModelConfig {
+                local: Some(model.to_string()),
+                remote: None,
+            });
+        }
+
+        let state = ServerState {
+            store: Arc::new(Mutex::new(Store::new(&config).unwrap())),
+            llm: Arc::new(Mutex::new(Router::new(&config).unwrap())),
+            config,
+            rate_limit_state: Arc::new(RateLimitState::new(100, 60)),
+            auth_state: Arc::new(AuthState::new(vec![], vec![])),
+            auth_enabled: false,
+            metrics: Arc::new(Metrics::new()),
+            audit: Arc::new(AuditLog::stderr(false)),
+            identity: Arc::new(crate::anel::identity::IdentityVerifier::default()),
+            ready: Arc::new(std::sync::atomic::AtomicBool::new(true)),
+            limits: RouteLimits::new(&Default::default()),
+        };
+        (tmp, state)
+    }
+
+    fn request(query: &str) -> Json<SearchRequest> {
+        Json(SearchRequest {
+            query: query.to_string(),
+            limit: None,
+            offset: None,
+            collection: None,
+            timeout_ms: None,
+        })
+    }
+
+    async fn read_response(response: Response) -> (StatusCode, Option<String>, serde_json::Value) {
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, content_type, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_vsearch_without_embedder_returns_503_problem() {
+        let (_tmp, state) = test_state(None);
+        let response = vsearch(State(state.clone()), HeaderMap::new(), request("rust")).await;
+        let (status, content_type, body) = read_response(response).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(content_type.as_deref(), Some("application/problem+json"));
+        assert_eq!(body["error_code"], "BACKEND_UNAVAILABLE");
+        assert_eq!(body["status"], 503);
+        assert_eq!(body["message"], "No embedder available");
+        assert_eq!(body["recovery_hints"][0]["code"], "CONFIGURE_EMBEDDER");
+        assert_eq!(state.metrics.get_errors_total(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_query_without_embedder_returns_503_problem() {
+        let (_tmp, state) = test_state(None);
+        let response = query(State(state), HeaderMap::new(), request("rust")).await;
+        let (status, _, body) = read_response(response).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["error_code"], "BACKEND_UNAVAILABLE");
+    }
+
+    #[tokio::test]
+    async fn test_bad_query_returns_400_problem() {
+        let (_tmp, state) = test_state(Some("mock-embedder"));
+
+        for q in ["", "   ", "\"unterminated phrase"] {
+            let response = vsearch(State(state.clone()), HeaderMap::new(), request(q)).await;
+            let (status, content_type, body) = read_response(response).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "query {:?}", q);
+            assert_eq!(content_type.as_deref(), Some("application/problem+json"));
+            assert_eq!(body["error_code"], "QUERY_PARSE_ERROR");
+            assert_eq!(body["status"], 400);
+
+            let response = query(State(state.clone()), HeaderMap::new(), request(q)).await;
+            let (status, _, body) = read_response(response).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "query {:?}", q);
+            assert_eq!(body["error_code"], "QUERY_PARSE_ERROR");
+        }
+
+        // Validation happens before the embedder is touched
+        assert_eq!(state.metrics.get_llm_embeddings_total(), 0);
+    }
+
+    #[cfg(feature = "sqlite-vec")]
+    #[tokio::test]
+    async fn test_vsearch_success_response_shape() {
+        let (_tmp, state) = test_state(Some("mock-embedder"));
+        let response = vsearch(State(state.clone()), HeaderMap::new(), request("rust ownership")).await;
+        let (status, content_type, body) = read_response(response).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type.as_deref(), Some("application/json"));
+        assert_eq!(body["query"], "rust ownership");
+        assert_eq!(body["total"], 0);
+        assert!(body["results"].as_array().unwrap().is_empty());
+        assert_eq!(state.metrics.get_llm_embeddings_total(), 1);
+        assert_eq!(state.metrics.get_errors_total(), 0);
+    }
+
+    #[cfg(not(feature = "sqlite-vec"))]
+    #[tokio::test]
+    async fn test_vsearch_without_sqlite_vec_returns_503_problem() {
+        let (_tmp, state) = test_state(Some("mock-embedder"));
+        let response = vsearch(State(state.clone()), HeaderMap::new(), request("rust ownership")).await;
+        let (status, content_type, body) = read_response(response).await;
+
+        // The builtin backend fails instead of finding nothing
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(content_type.as_deref(), Some("application/problem+json"));
+        assert_eq!(body["error_code"], "BACKEND_UNAVAILABLE");
+        assert_eq!(body["recovery_hints"][0]["code"], "ENABLE_SQLITE_VEC");
+    }
+
+    #[tokio::test]
+    async fn test_query_success_response_shape() {
+        let (_tmp, state) = test_state(Some("mock-embedder"));
+        let response = query(State(state.clone()), HeaderMap::new(), request("rust ownership")).await;
+        let (status, _, body) = read_response(response).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["query"], "rust ownership");
+        assert_eq!(body["total"], 0);
+        assert!(body["results"].is_array());
+        assert_eq!(state.metrics.get_query_total(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_collections_returns_config_problem() {
+        let (_tmp, mut state) = test_state(Some("mock-embedder"));
+        state.config.collections.clear();
+        let config = state.config.clone();
+        state.store = Arc::new(Mutex::new(Store::new(&config).unwrap()));
+
+        let response = query(State(state), HeaderMap::new(), request("rust")).await;
+        let (status, _, body) = read_response(response).await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["error_code"], "CONFIG_ERROR");
+        assert_eq!(body["recovery_hints"][0]["code"], "ADD_COLLECTION");
+    }
+
+    /// Embedder that never answers within a test's budget
+    struct SlowEmbedder;
+
+    impl crate::llm::Embed for SlowEmbedder {
+        fn model_name(&self) -> String {
+            "slow-mock".to_string()
+        }
+
+        fn embed<'a>(&'a self, texts: &'a [&'a str]) -> crate::llm::EmbedFuture<'a> {
+            Box::pin(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                Ok(texts.iter().map(|_| vec![0.1; 8]).collect())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_timeout_returns_partial_bm25_results() {
+        let (tmp, mut state) = test_state(None);
+        // The cache shares the collection directory; index only the markdown
+        std::fs::write(tmp.path().join("rust.md"), "# Rust\nOwnership rules").unwrap();
+        state.config.collections[0].pattern = Some("*.md".to_string());
+        let config = state.config.clone();
+        state.store = Arc::new(Mutex::new(Store::new(&config).unwrap()));
+        state.store.lock().await.update_index().unwrap();
+        state.llm.lock().await.set_embedder(Arc::new(SlowEmbedder));
+
+        let start = std::time::Instant::now();
+        let mut req = request("ownership");
+        req.timeout_ms = Some(200);
+        let response = query(State(state.clone()), HeaderMap::new(), req).await;
+        let (status, _, body) = read_response(response).await;
+
+        assert!(start.elapsed() < std::time::Duration::from_secs(2), "took {:?}", start.elapsed());
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["partial"], true);
+        assert_eq!(body["skipped_stages"], serde_json::json!(["vector"]));
+        assert_eq!(body["total"], 1, "{}", body);
+        assert_eq!(state.metrics.get_partial_total(), 1);
+        assert_eq!(state.metrics.get_errors_total(), 0);
+
+        // Complete responses carry no partial flag and are not counted
+        let response = search(State(state.clone()), HeaderMap::new(), request("ownership")).await;
+        let (_, _, body) = read_response(response).await;
+        assert!(body.get("partial").is_none());
+        assert_eq!(state.metrics.get_partial_total(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_query_reports_stage_timings_and_histograms() {
+        let (tmp, mut state) = test_state(None);
+        std::fs::write(tmp.path().join("rust.md"), "# Rust\nOwnership rules").unwrap();
+        state.config.collections[0].pattern = Some("*.md".to_string());
+        let config = state.config.clone();
+        state.store = Arc::new(Mutex::new(Store::new(&config).unwrap()));
+        state.store.lock().await.update_index().unwrap();
+        state.llm.lock().await.set_embedder(Arc::new(SlowEmbedder));
+
+        // The stalled embedder holds the vector stage for the whole 200ms budget
+        let mut req = request("ownership");
+        req.timeout_ms = Some(200);
+        let response = query(State(state.clone()), HeaderMap::new(), req).await;
+        let (_, _, body) = read_response(response).await;
+
+        let vector = body["timings"]["vector_ms"].as_f64().unwrap();
+        assert!((150.0..1000.0).contains(&vector), "{}", body);
+        assert!(body["timings"]["fusion_ms"].as_f64().unwrap() < 50.0, "{}", body);
+        assert!(body["timings"]["total_ms"].as_f64().unwrap() >= vector, "{}", body);
+
+        assert_eq!(state.metrics.get_search_stage_count("vector"), 1);
+        let rendered = state.metrics.render_stage_prometheus();
+        assert!(rendered.contains("qmd_search_stage_duration_seconds_bucket{stage=\"vector\",le=\"0.1\"} 0"), "{}", rendered);
+        assert!(rendered.contains("qmd_search_stage_duration_seconds_bucket{stage=\"vector\",le=\"1\"} 1"), "{}", rendered);
+        assert!(rendered.contains("qmd_search_stage_duration_seconds_count{stage=\"bm25\"} 1"), "{}", rendered);
+
+        // BM25-only search does not run the hybrid pipeline
+        search(State(state.clone()), HeaderMap::new(), request("ownership")).await;
+        assert_eq!(state.metrics.get_search_stage_count("bm25"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_embedder_lock_released_after_embedding() {
+        let (_tmp, state) = test_state(Some("mock-embedder"));
+        let embedding = embed_query(&state, "rust").await.unwrap();
+
+        assert_eq!(embedding.len(), state.config.vector.dimension());
+        assert!(state.llm.try_lock().is_ok());
+    }
+}
@@ -0,0 +1,169 @@
+//! API keys kept in `server.api_keys_file` and managed by `qmd server keys`.
+//!
+//! The file records only the SHA-256 digest of each key; the key itself is
+//! printed once when it is added. Because the digests still identify valid
+//! keys, the file must not be accessible to other users: it is written with
+//! mode 0600 and loading refuses a file that others can read or write.
+
+use super::middleware::{hash_api_key, ApiKey};
+use anyhow::{bail, Context, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Prefix of generated keys, so leaked keys are recognizable
+const KEY_PREFIX: &str = "qmd_";
+
+/// Contents of the API keys file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeyFile {
+    #[serde(default)]
+    pub keys: Vec<StoredKey>,
+}
+
+/// One key as stored: its digest, never the key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredKey {
+    /// Name recorded in audit logs and used to revoke the key
+    pub name: String,
+    /// [`hash_api_key`] of the key
+    pub hash: String,
+    /// Collections the key may read; every collection when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collections: Option<Vec<String>>,
+    pub created_at: String,
+}
+
+impl KeyFile {
+    /// Read the keys file, or no keys if it does not exist yet
+    ///
+    /// Fails if other users can access the file.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        check_permissions(path)?;
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read API keys file {}", path.display()))?;
+        serde_yaml::from_str(&content)
+            .with_context(|| format!("Invalid API keys file {}", path.display()))
+    }
+
+    /// Write the keys file, readable only by its owner
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_yaml::to_string(self)?;
+
+        let mut tmp_name = path.as_os_str().to_owned();
+        tmp_name.push(format!(".tmp.{}", std::process::id()));
+        let tmp_path = Path::new(&tmp_name);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let write_result = (|| -> std::io::Result<()> {
+            let mut file = options.open(tmp_path)?;
+            file.write_all(content.as_bytes())?;
+            file.sync_all()?;
+            fs::rename(tmp_path, path)
+        })();
+        if write_result.is_err() {
+            let _ = fs::remove_file(tmp_path);
+        }
+        write_result.with_context(|| format!("Failed to write API keys file {}", path.display()))
+    }
+
+    /// Generate and record a new key; returns the key, which is not stored
+    pub fn add(&mut self, name: &str, collections: Option<Vec<String>>) -> Result<String> {
+        if self.keys.iter().any(|k| k.name == name) {
+            bail!("An API key named '{}' already exists; revoke it first", name);
+        }
+        let bytes: [u8; 32] = rand::thread_rng().gen();
+        let key: String = std::iter::once(KEY_PREFIX.to_string())
+            .chain(bytes.iter().map(|b| format!("{:02x}", b)))
+            .collect();
+        self.keys.push(StoredKey {
+            name: name.to_string(),
+            hash: hash_api_key(&key),
+            collections,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        });
+        Ok(key)
+    }
+
+    /// Remove the key named `name`
+    pub fn revoke(&mut self, name: &str) -> Result<()> {
+        let before = self.keys.len();
+        self.keys.retain(|k| k.name != name);
+        if self.keys.len() == before {
+            bail!("No API key named '{}'", name);
+        }
+        Ok(())
+    }
+
+    /// Keys for the auth middleware
+    pub fn api_keys(&self) -> Vec<ApiKey> {
+        self.keys
+            .iter()
+            .map(|stored| {
+                let key = ApiKey::hashed(&stored.hash, &stored.name);
+                match &stored.collections {
+                    Some(collections) => key.with_collections(collections.clone()),
+                    None => key,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Refuse a keys file that other users can read or write
+#[cfg(unix)]
+pub fn check_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)?.permissions().mode();
+    if mode & 0o007 != 0 {
+        bail!(
+            "API keys file {} is accessible by other users (mode {:o}); run `chmod 600 {}`",
+            path.display(),
+            mode & 0o777,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Refuse a keys file that other users can read or write
+#[cfg(not(unix))]
+pub fn check_permissions(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_stores_only_the_digest() {
+        let mut file = KeyFile::default();
+        let key = file.add("ci", Some(vec!["docs".to_string()])).unwrap();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(file.keys[0].hash, hash_api_key(&key));
+        assert!(!serde_yaml::to_string(&file).unwrap().contains(&key));
+        assert!(file.add("ci", None).is_err());
+
+        let api_keys = file.api_keys();
+        assert_eq!(api_keys, [ApiKey::new(&key, "ci").with_collections(vec!["docs".to_string()])]);
+
+        file.revoke("ci").unwrap();
+        assert!(file.keys.is_empty());
+        assert!(file.revoke("ci").is_err());
+    }
+}
@@ -1,19 +1,24 @@
 // HTTP middleware
 
+use crate::anel::identity::IdentityVerifier;
+use crate::anel::{AnelError, AnelErrorCode};
+use crate::config::ConcurrencyLimit;
 use axum::{
     body::Body,
-    extract::Request,
-    http::{header:: HeaderMap, StatusCode},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
     middleware::Next,
     response::{IntoResponse, Json, Response},
 };
 use std::{
     collections::HashMap,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
     sync::Arc,
     time::{Duration, Instant},
 };
-use tokio::sync::RwLock;
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 
 /// Rate limiter state
 pub struct RateLimitState {
@@ -121,6 +126,138 @@ pub async fn rate_limit_mw(
     response
 }
 
+/// Seconds a client turned away by a full queue is asked to wait
+const OVERLOAD_RETRY_AFTER_SECS: u64 = 1;
+
+/// Bounds the requests of one route class served at once
+///
+/// Requests past `max_in_flight` wait for a permit; once `max_queued` are
+/// waiting, further requests are turned away instead of piling up.
+pub struct ConcurrencyLimiter {
+    name: &'static str,
+    permits: Arc<Semaphore>,
+    max_queued: usize,
+    in_flight: Arc<AtomicUsize>,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+/// A request's turn; gives the permit back when dropped
+pub struct LimitPermit {
+    _permit: OwnedSemaphorePermit,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for LimitPermit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A waiting request's place in the queue, freed even if the request is dropped
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(name: &'static str, limit: ConcurrencyLimit) -> Self {
+        Self {
+            name,
+            permits: Arc::new(Semaphore::new(limit.max_in_flight.max(1))),
+            max_queued: limit.max_queued,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            queued: AtomicUsize::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Route class the limiter covers, as labelled in metrics
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Wait for a turn, or `None` when the queue is already full
+    pub async fn acquire(&self) -> Option<LimitPermit> {
+        let permit = match self.permits.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                if self.queued.fetch_add(1, Ordering::SeqCst) >= self.max_queued {
+                    self.queued.fetch_sub(1, Ordering::SeqCst);
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+                let _slot = QueueSlot(&self.queued);
+                self.permits.clone().acquire_owned().await.ok()?
+            }
+        };
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        Some(LimitPermit {
+            _permit: permit,
+            in_flight: self.in_flight.clone(),
+        })
+    }
+
+    /// Requests being served
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Requests waiting for a turn
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// Requests turned away because the queue was full
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+}
+
+/// Limiters for the server's route classes
+#[derive(Clone)]
+pub struct RouteLimits {
+    /// Search, vsearch, query and MCP
+    pub search: Arc<ConcurrencyLimiter>,
+    /// Every other route
+    pub cheap: Arc<ConcurrencyLimiter>,
+}
+
+impl RouteLimits {
+    pub fn new(config: &crate::config::ServerConfig) -> Self {
+        Self {
+            search: Arc::new(ConcurrencyLimiter::new("search", config.search_limit())),
+            cheap: Arc::new(ConcurrencyLimiter::new("cheap", config.cheap_limit())),
+        }
+    }
+}
+
+/// Concurrency limiting middleware: runs the request once the route class
+/// has a free slot, answering 503 with `Retry-After` when its queue is full
+pub async fn concurrency_limit_mw(
+    State(limiter): State<Arc<ConcurrencyLimiter>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(_permit) = limiter.acquire().await else {
+        tracing::warn!("Too many {} requests, rejecting {}", limiter.name(), request.uri().path());
+        let error = AnelError::new(
+            AnelErrorCode::BackendUnavailable,
+            "Server Busy",
+            format!("Too many {} requests in progress; retry later", limiter.name()),
+        );
+        let mut response = super::handlers::problem_response(error);
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(OVERLOAD_RETRY_AFTER_SECS));
+        return response;
+    };
+    next.run(request).await
+}
+
 /// Extract client IP from request
 pub fn extract_client_ip(request: &Request<Body>) -> String {
     // Check X-Forwarded-For header first
@@ -142,15 +279,79 @@ pub fn extract_client_ip(request: &Request<Body>) -> String {
     "unknown".to_string()
 }
 
+/// Hex SHA-256 digest of an API key, the only form in which keys are kept
+pub fn hash_api_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// An accepted API key and the collections it may read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKey {
+    /// [`hash_api_key`] of the key
+    pub hash: String,
+    /// Name recorded in audit logs
+    pub name: String,
+    /// Collections this key may read; `None` allows every collection
+    pub collections: Option<Vec<String>>,
+}
+
+impl ApiKey {
+    /// Key readable by every collection
+    pub fn new(key: &str, name: impl Into<String>) -> Self {
+        Self::hashed(hash_api_key(key), name)
+    }
+
+    /// Key known only by its digest, as stored in the API keys file
+    pub fn hashed(hash: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            hash: hash.into(),
+            name: name.into(),
+            collections: None,
+        }
+    }
+
+    /// Restrict the key to the given collections
+    pub fn with_collections(mut self, collections: Vec<String>) -> Self {
+        self.collections = Some(collections);
+        self
+    }
+
+    /// Parse one `--api-keys` entry: `key` or `key=collection+collection`
+    pub fn parse(spec: &str) -> Self {
+        match spec.trim().split_once('=') {
+            Some((key, collections)) => Self::new(key.trim(), "default").with_collections(
+                collections
+                    .split('+')
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+                    .collect(),
+            ),
+            None => Self::new(spec.trim(), "default"),
+        }
+    }
+}
+
 /// API Key authentication state
 pub struct AuthState {
-    valid_keys: RwLock<HashMap<String, String>>, // key -> description
+    /// Accepted keys by digest; presented keys are hashed before lookup
+    valid_keys: RwLock<HashMap<String, ApiKey>>,
     whitelist_ips: RwLock<Vec<String>>,
 }
 
 impl AuthState {
     pub fn new(api_keys: Vec<(String, String)>, whitelist_ips: Vec<String>) -> Self {
-        let valid_keys: HashMap<String, String> = api_keys.into_iter().collect();
+        Self::with_keys(
+            api_keys.into_iter().map(|(key, name)| ApiKey::new(&key, name)).collect(),
+            whitelist_ips,
+        )
+    }
+
+    /// Build from keys that may carry collection allowances
+    pub fn with_keys(api_keys: Vec<ApiKey>, whitelist_ips: Vec<String>) -> Self {
+        let valid_keys: HashMap<String, ApiKey> = api_keys
+            .into_iter()
+            .map(|key| (key.hash.clone(), key))
+            .collect();
         Self {
             valid_keys: RwLock::new(valid_keys),
             whitelist_ips: RwLock::new(whitelist_ips),
@@ -169,11 +370,25 @@ impl AuthState {
         // Check API key
         if let Some(key) = api_key {
             let keys = self.valid_keys.read().await;
-            return keys.contains_key(key);
+            return keys.contains_key(&hash_api_key(key));
         }
 
         false
     }
+
+    /// Look up the configured name (description) of an API key
+    pub async fn key_name(&self, api_key: Option<&str>) -> Option<String> {
+        let keys = self.valid_keys.read().await;
+        api_key.and_then(|key| keys.get(&hash_api_key(key)).map(|k| k.name.clone()))
+    }
+
+    /// Collections an API key is limited to; `None` when it is unrestricted or unknown
+    pub async fn allowed_collections(&self, api_key: Option<&str>) -> Option<Vec<String>> {
+        let keys = self.valid_keys.read().await;
+        api_key
+            .and_then(|key| keys.get(&hash_api_key(key)))
+            .and_then(|k| k.collections.clone())
+    }
 }
 
 pub type SharedAuthState = Arc<AuthState>;
@@ -218,6 +433,42 @@ pub async fn auth_mw(
     next.run(request).await
 }
 
+/// Name of the identity `identity_mw` verified, for handlers to audit
+#[derive(Debug, Clone)]
+pub struct VerifiedIdentity(pub String);
+
+/// Endpoints probes and scrapers reach without an agent identity
+const IDENTITY_EXEMPT_PATHS: &[&str] = &["/health", "/readyz", "/metrics", "/spec"];
+
+/// Agent identity middleware: checks `X-Agent-Identity` against the
+/// configured tokens and answers 403 PermissionDenied when enforcement rejects it
+pub async fn identity_mw(
+    State(verifier): State<Arc<IdentityVerifier>>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    if IDENTITY_EXEMPT_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let token = request
+        .headers()
+        .get("x-agent-identity")
+        .and_then(|v| v.to_str().ok());
+    match verifier.verify(token) {
+        Ok(Some(name)) => {
+            request.extensions_mut().insert(VerifiedIdentity(name));
+        }
+        Ok(None) => {}
+        Err(error) => {
+            tracing::warn!("Rejected agent identity from {}", extract_client_ip(&request));
+            return super::handlers::problem_response(*error);
+        }
+    }
+
+    next.run(request).await
+}
+
 /// Request tracing middleware
 pub async fn trace_request_mw(
     request: Request<Body>,
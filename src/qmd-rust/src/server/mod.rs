@@ -2,22 +2,25 @@
 // Provides independent HTTP API server with REST endpoints
 
 pub mod handlers;
+pub mod keys;
 pub mod middleware;
 pub mod observability;
 
+use crate::anel::identity::IdentityVerifier;
 use crate::config::Config;
 use crate::llm::Router;
+use crate::logging::LogFormat;
+use crate::preload::{self, Warmup};
 use crate::store::Store;
 use anyhow::Result;
 use axum::Router as AxumRouter;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tower::ServiceExt;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use middleware::{RateLimitState, AuthState};
-use observability::{Metrics, Tracing};
+use middleware::{ApiKey, RateLimitState, AuthState, RouteLimits};
+use observability::{AuditLog, Metrics};
 
 /// QMD HTTP Server state
 #[derive(Clone)]
@@ -29,6 +32,24 @@ pub struct ServerState {
     pub auth_state: Arc<AuthState>,
     pub auth_enabled: bool,
     pub metrics: Arc<Metrics>,
+    /// NDJSON audit sink for the `/mcp` bridge
+    pub audit: Arc<AuditLog>,
+    /// Checks `X-Agent-Identity` against `identity.tokens`
+    pub identity: Arc<IdentityVerifier>,
+    /// Set once startup work (preload) is done; backs `/readyz`
+    pub ready: Arc<AtomicBool>,
+    /// In-flight bounds for the search and cheap route classes
+    pub limits: RouteLimits,
+}
+
+impl ServerState {
+    /// Preload indexes and models, then mark the server ready
+    pub async fn preload<W: Warmup>(&self, llm: &W) -> Result<()> {
+        let store = self.store.lock().await;
+        preload::preload(&store, llm).await?;
+        self.ready.store(true, Ordering::SeqCst);
+        Ok(())
+    }
 }
 
 /// Server configuration
@@ -43,10 +64,14 @@ pub struct ServerConfig {
     pub rate_limit_window_secs: u64,
     /// Enable authentication
     pub auth_enabled: bool,
-    /// API keys (key -> description)
-    pub api_keys: Vec<(String, String)>,
+    /// API keys, optionally limited to some collections
+    pub api_keys: Vec<ApiKey>,
     /// Whitelist IPs (skip auth)
     pub whitelist_ips: Vec<String>,
+    /// Emit NDJSON audit records for `/mcp` requests
+    pub mcp_audit: bool,
+    /// Warm indexes and models before accepting traffic
+    pub preload: bool,
 }
 
 impl Default for ServerConfig {
@@ -60,27 +85,33 @@ impl Default for ServerConfig {
             auth_enabled: false,
             api_keys: vec![],
             whitelist_ips: vec![],
+            mcp_audit: true,
+            preload: false,
         }
     }
 }
 
 /// Initialize logging for the server
-pub fn init_logging() {
+pub fn init_logging(format: LogFormat) {
     use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
     let filter = tracing_subscriber::EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| "qmd_server=debug,tower=warn,axum=warn".into());
 
-    tracing_subscriber::registry()
-        .with(filter)
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let registry = tracing_subscriber::registry().with(filter);
+    match format {
+        LogFormat::Text => registry.with(tracing_subscriber::fmt::layer()).init(),
+        LogFormat::Json => registry.with(crate::logging::json_layer()).init(),
+    }
 }
 
 /// Run the HTTP server
 pub fn run_server(config: &ServerConfig, app_config: &Config) -> Result<()> {
-    // Initialize runtime
-    let rt = tokio::runtime::Runtime::new()?;
+    // Initialize runtime, one worker thread per `--workers`
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(config.workers.max(1))
+        .enable_all()
+        .build()?;
 
     rt.block_on(async {
         // Create server state
@@ -94,13 +125,13 @@ pub fn run_server(config: &ServerConfig, app_config: &Config) -> Result<()> {
         ));
 
         // Create auth state
-        let auth_state = Arc::new(AuthState::new(
+        let auth_state = Arc::new(AuthState::with_keys(
             config.api_keys.clone(),
             config.whitelist_ips.clone(),
         ));
 
-        // Create metrics
-        let metrics = Arc::new(Metrics::new());
+        // Create metrics, including the store's connection pool counters
+        let metrics = Arc::new(Metrics::with_pool_stats(store.pool_stats()));
 
         let state = ServerState {
             store: Arc::new(Mutex::new(store)),
@@ -110,8 +141,17 @@ pub fn run_server(config: &ServerConfig, app_config: &Config) -> Result<()> {
             auth_state,
             auth_enabled: config.auth_enabled,
             metrics,
+            audit: Arc::new(AuditLog::from_env(config.mcp_audit)?),
+            identity: Arc::new(IdentityVerifier::new(&app_config.identity)),
+            ready: Arc::new(AtomicBool::new(!config.preload)),
+            limits: RouteLimits::new(&app_config.server),
         };
 
+        if config.preload {
+            let llm = state.llm.clone();
+            state.preload(&*llm.lock().await).await?;
+        }
+
         // Build router with all routes
         let app = build_router(state)?;
 
@@ -125,13 +165,16 @@ pub fn run_server(config: &ServerConfig, app_config: &Config) -> Result<()> {
         tracing::info!("QMD HTTP Server listening on http://{}", addr);
         tracing::info!("API endpoints available:");
         tracing::info!("  GET  /health          - Health check");
+        tracing::info!("  GET  /readyz          - Readiness probe");
         tracing::info!("  GET  /collections     - List collections");
+        tracing::info!("  GET  /collections/:name/report - Collection size report");
         tracing::info!("  POST /search          - BM25 search");
         tracing::info!("  POST /vsearch         - Vector search");
         tracing::info!("  POST /query           - Hybrid search (BM25 + Vector + RRF + Rerank)");
+        tracing::info!("  GET  /suggest         - Query completions");
         tracing::info!("  GET  /stats           - Index statistics");
         tracing::info!("  GET  /metrics        - Prometheus metrics");
-        tracing::info!("  GET  /documents/:path - Get document content");
+        tracing::info!("  GET  /documents/:path - Get document content (?as=text|raw for the body)");
         tracing::info!("  POST /mcp             - MCP protocol (JSON-RPC)");
         if config.auth_enabled {
             tracing::info!("  Auth: API Key required (X-API-Key header)");
@@ -140,6 +183,14 @@ pub fn run_server(config: &ServerConfig, app_config: &Config) -> Result<()> {
             tracing::info!("  Auth: Whitelisted IPs: {:?}", config.whitelist_ips);
         }
         tracing::info!("  Rate limit: {} req/{}s", config.rate_limit_max, config.rate_limit_window_secs);
+        let (search, cheap) = (app_config.server.search_limit(), app_config.server.cheap_limit());
+        tracing::info!(
+            "  Concurrency: search {} in flight + {} queued, other {} + {} queued, {} workers",
+            search.max_in_flight, search.max_queued, cheap.max_in_flight, cheap.max_queued, config.workers.max(1)
+        );
+        if !config.mcp_audit {
+            tracing::info!("  MCP audit: disabled");
+        }
 
         axum::serve(listener, app).await?;
         Ok::<(), anyhow::Error>(())
@@ -156,21 +207,39 @@ fn build_router(state: ServerState) -> Result<AxumRouter> {
         .allow_methods(Any)
         .allow_headers(Any);
 
+    // Search endpoints and the MCP bridge share the search limiter
+    let search_routes = AxumRouter::new()
+        .route("/search", post(handlers::search))
+        .route("/vsearch", post(handlers::vsearch))
+        .route("/query", post(handlers::query))
+        // MCP protocol
+        .route("/mcp", post(handlers::mcp))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.limits.search.clone(),
+            middleware::concurrency_limit_mw,
+        ));
+
     let app = AxumRouter::new()
         // Health and info
         .route("/health", get(handlers::health))
+        .route("/readyz", get(handlers::readyz))
         .route("/spec", get(handlers::spec))
         .route("/collections", get(handlers::list_collections))
+        .route("/collections/{name}/report", get(handlers::collection_report))
         .route("/stats", get(handlers::stats))
         .route("/metrics", get(handlers::metrics))
-        // Search endpoints
-        .route("/search", post(handlers::search))
-        .route("/vsearch", post(handlers::vsearch))
-        .route("/query", post(handlers::query))
+        .route("/suggest", get(handlers::suggest))
         // Document retrieval
-        .route("/documents/:path", get(handlers::get_document))
-        // MCP protocol
-        .route("/mcp", post(handlers::mcp))
+        .route("/documents/{path}", get(handlers::get_document))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.limits.cheap.clone(),
+            middleware::concurrency_limit_mw,
+        ))
+        .merge(search_routes)
+        .layer(axum::middleware::from_fn_with_state(
+            state.identity.clone(),
+            middleware::identity_mw,
+        ))
         .layer(cors)
         .with_state(state);
 
@@ -180,6 +249,712 @@ fn build_router(state: ServerState) -> Result<AxumRouter> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use std::io::Write;
+    use tower::ServiceExt;
+
+    /// Audit writer capturing output in memory
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn test_state(cache: &std::path::Path, audit: AuditLog) -> ServerState {
+        let app_config = Config {
+            cache_path: cache.to_path_buf(),
+            ..Config::default()
+        };
+        state_with_config(app_config, audit)
+    }
+
+    fn state_with_config(app_config: Config, audit: AuditLog) -> ServerState {
+        ServerState {
+            store: Arc::new(Mutex::new(Store::new(&app_config).unwrap())),
+            llm: Arc::new(Mutex::new(Router::new(&app_config).unwrap())),
+            identity: Arc::new(IdentityVerifier::new(&app_config.identity)),
+            limits: RouteLimits::new(&app_config.server),
+            config: app_config,
+            rate_limit_state: Arc::new(RateLimitState::new(100, 60)),
+            auth_state: Arc::new(AuthState::new(
+                vec![("secret-key".to_string(), "ci-agent".to_string())],
+                vec![],
+            )),
+            auth_enabled: false,
+            metrics: Arc::new(Metrics::new()),
+            audit: Arc::new(audit),
+            ready: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    fn tools_call_request() -> Request<Body> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": {"name": "search", "arguments": {"query": "rust"}}
+        });
+        Request::post("/mcp")
+            .header("content-type", "application/json")
+            .header("x-api-key", "secret-key")
+            .header("x-trace-id", "trace-123")
+            .header("x-agent-identity", "agent-7")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn scrape_metrics(app: &AxumRouter) -> String {
+        let response = app
+            .clone()
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_mcp_tools_call_counted_and_audited() {
+        let tmp = tempfile::tempdir().unwrap();
+        let buffer = SharedBuffer::default();
+        let state = test_state(tmp.path(), AuditLog::with_writer(Box::new(buffer.clone())));
+        let audit = state.audit.clone();
+        let app = build_router(state).unwrap();
+
+        let response = app.clone().oneshot(tools_call_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+
+        let metrics = scrape_metrics(&app).await;
+        assert!(metrics.contains("qmd_mcp_requests_total{method=\"tools/call\",tool=\"search\"} 1"));
+        assert!(metrics.contains("qmd_mcp_request_duration_seconds_count{method=\"tools/call\"} 1"));
+        assert!(metrics.contains("qmd_mcp_request_duration_seconds_bucket{method=\"tools/call\",le=\"+Inf\"} 1"));
+
+        audit.flush();
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let record: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(record["type"], "audit");
+        assert_eq!(record["tool"], "search");
+        assert_eq!(record["method"], "tools/call");
+        assert_eq!(record["trace_id"], "trace-123");
+        assert_eq!(record["X-Agent-Identity"], "agent-7");
+        assert_eq!(record["api_key_name"], "ci-agent");
+        assert_eq!(record["args"], r#"{"query":"rust"}"#);
+        assert!(record["duration_ms"].is_u64());
+        assert!(record["timestamp"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_identity_enforcement_rejects_unknown_identity() {
+        let tmp = tempfile::tempdir().unwrap();
+        let buffer = SharedBuffer::default();
+        let mut app_config = Config {
+            cache_path: tmp.path().to_path_buf(),
+            ..Config::default()
+        };
+        app_config.identity = crate::config::IdentityConfig {
+            enforce: true,
+            tokens: vec![crate::config::IdentityToken {
+                name: "indexer".to_string(),
+                token: "agent-7".to_string(),
+            }],
+        };
+        let state = state_with_config(app_config, AuditLog::with_writer(Box::new(buffer.clone())));
+        let audit = state.audit.clone();
+        let app = build_router(state).unwrap();
+
+        let mut forged = tools_call_request();
+        forged.headers_mut().insert("x-agent-identity", "agent-8".parse().unwrap());
+        let response = app.clone().oneshot(forged).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let problem: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(problem["status"], 403);
+        audit.flush();
+        assert!(buffer.0.lock().unwrap().is_empty(), "rejected calls never reach the handler");
+
+        // Probes need no identity
+        let health = app.clone().oneshot(Request::get("/health").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(health.status(), StatusCode::OK);
+
+        let response = app.clone().oneshot(tools_call_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+        audit.flush();
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let record: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        assert_eq!(record["identity_name"], "indexer");
+    }
+
+    #[tokio::test]
+    async fn test_mcp_methods_counted_separately() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_state(tmp.path(), AuditLog::stderr(false));
+        let metrics = state.metrics.clone();
+        let app = build_router(state).unwrap();
+
+        let initialize = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}});
+        for body in [initialize.to_string(), "not json".to_string()] {
+            app.clone()
+                .oneshot(Request::post("/mcp").body(Body::from(body)).unwrap())
+                .await
+                .unwrap();
+        }
+        app.clone().oneshot(tools_call_request()).await.unwrap();
+
+        assert_eq!(metrics.get_mcp_requests("initialize", None), 1);
+        assert_eq!(metrics.get_mcp_requests("invalid", None), 1);
+        assert_eq!(metrics.get_mcp_requests("tools/call", Some("search")), 1);
+        assert_eq!(metrics.get_mcp_requests("tools/call", Some("query")), 0);
+    }
+
+    #[tokio::test]
+    async fn test_mcp_unknown_methods_and_tools_counted_as_other() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_state(tmp.path(), AuditLog::stderr(false));
+        let metrics = state.metrics.clone();
+        let app = build_router(state).unwrap();
+
+        for i in 0..3 {
+            let bodies = [
+                serde_json::json!({"jsonrpc": "2.0", "id": i, "method": format!("random/{}", i)}),
+                serde_json::json!({"jsonrpc": "2.0", "id": i, "method": "tools/call", "params": {"name": format!("tool-{}", i)}}),
+            ];
+            for body in bodies {
+                app.clone()
+                    .oneshot(Request::post("/mcp").body(Body::from(body.to_string())).unwrap())
+                    .await
+                    .unwrap();
+            }
+        }
+
+        assert_eq!(metrics.get_mcp_requests("other", None), 3);
+        assert_eq!(metrics.get_mcp_requests("tools/call", Some("other")), 3);
+        assert_eq!(metrics.get_mcp_requests("random/0", None), 0);
+        let rendered = metrics.render_mcp_prometheus();
+        assert!(!rendered.contains("random/"), "{}", rendered);
+        assert!(!rendered.contains("tool-"), "{}", rendered);
+    }
+
+    #[tokio::test]
+    async fn test_documents_route_rejects_traversal() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("guide.md"), "# Guide\nIndexed content").unwrap();
+        let secret = tmp.path().join("secret.txt");
+        std::fs::write(&secret, "TOP-SECRET").unwrap();
+
+        let app_config = Config {
+            collections: vec![crate::config::CollectionConfig {
+                name: "docs".to_string(),
+                path: docs.clone(),
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        let state = state_with_config(app_config, AuditLog::stderr(false));
+        state.store.lock().await.update_index().unwrap();
+        let app = build_router(state).unwrap();
+
+        let get_document = |path: String| {
+            let app = app.clone();
+            async move {
+                let uri = format!("/documents/{}", urlencoding::encode(&path));
+                let response = app.oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, String::from_utf8(bytes.to_vec()).unwrap())
+            }
+        };
+
+        let (status, body) = get_document("docs/guide.md".to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("Indexed content"));
+
+        for attempt in [
+            "docs/../secret.txt".to_string(),
+            "qmd://docs/../../secret.txt".to_string(),
+            format!("{}/../secret.txt", docs.display()),
+            secret.display().to_string(),
+            "/etc/passwd".to_string(),
+        ] {
+            let (status, body) = get_document(attempt.clone()).await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{}: {}", attempt, body);
+            assert!(body.contains("PERMISSION_DENIED"), "{}", body);
+            assert!(!body.contains("TOP-SECRET") && !body.contains("root:"), "{}", body);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_documents_route_without_filesystem_fallback() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("guide.md"), "Indexed content").unwrap();
+
+        let mut app_config = Config {
+            collections: vec![crate::config::CollectionConfig {
+                name: "docs".to_string(),
+                path: docs.clone(),
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        app_config.documents.filesystem_fallback = false;
+        let state = state_with_config(app_config, AuditLog::stderr(false));
+        state.store.lock().await.update_index().unwrap();
+        // Written after indexing, so only the filesystem could serve it
+        std::fs::write(docs.join("draft.md"), "Unindexed draft").unwrap();
+        let app = build_router(state).unwrap();
+
+        for (path, expected) in [
+            (docs.join("guide.md"), StatusCode::OK),
+            (docs.join("draft.md"), StatusCode::NOT_FOUND),
+        ] {
+            let uri = format!("/documents/{}", urlencoding::encode(&path.display().to_string()));
+            let response = app.clone().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), expected, "{}", path.display());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_documents_route_serves_text_and_raw_bytes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        let guide = "# Guide\nIndexed content";
+        std::fs::write(docs.join("guide.md"), guide).unwrap();
+        // Not valid UTF-8, and not matched by the indexed pattern
+        let pdf: Vec<u8> = b"%PDF-1.7\n\xff\xfe\x00binary\n%%EOF".to_vec();
+        std::fs::write(docs.join("report.pdf"), &pdf).unwrap();
+
+        let app_config = Config {
+            collections: vec![crate::config::CollectionConfig {
+                name: "docs".to_string(),
+                path: docs.clone(),
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        let state = state_with_config(app_config, AuditLog::stderr(false));
+        state.store.lock().await.update_index().unwrap();
+        let app = build_router(state).unwrap();
+
+        let get = |uri: &str, headers: &[(&str, &str)]| {
+            let mut request = Request::get(uri);
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            let request = request.body(Body::empty()).unwrap();
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let headers = response.headers().clone();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, headers, bytes.to_vec())
+            }
+        };
+        let header = |headers: &axum::http::HeaderMap, name: &str| {
+            headers.get(name).map(|v| v.to_str().unwrap().to_string())
+        };
+
+        // Indexed text, tagged with the stored hash
+        let (status, headers, body) = get("/documents/docs%2Fguide.md?as=text", &[]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, guide.as_bytes());
+        assert_eq!(header(&headers, "content-type").unwrap(), "text/markdown; charset=utf-8");
+        let etag = header(&headers, "etag").unwrap();
+        assert_eq!(etag, format!("\"{}\"", Store::calculate_hash(guide)));
+        let (status, _, body) = get("/documents/docs%2Fguide.md?as=text", &[("if-none-match", &etag)]).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert!(body.is_empty());
+
+        // Raw bytes of a binary file, by range
+        let (status, headers, body) = get("/documents/docs%2Freport.pdf?as=raw", &[("range", "bytes=4-12")]).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, &pdf[4..=12]);
+        assert_eq!(header(&headers, "content-type").unwrap(), "application/pdf");
+        assert_eq!(header(&headers, "content-length").unwrap(), "9");
+        assert_eq!(header(&headers, "content-range").unwrap(), format!("bytes 4-12/{}", pdf.len()));
+        assert_eq!(header(&headers, "accept-ranges").unwrap(), "bytes");
+        // Unindexed files have no stored hash to tag them with
+        assert!(header(&headers, "etag").is_none());
+
+        let (status, _, body) = get("/documents/docs%2Freport.pdf?as=raw", &[("range", "bytes=-5")]).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, b"%%EOF");
+        let (status, headers, _) = get("/documents/docs%2Freport.pdf?as=raw", &[("range", "bytes=500-")]).await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(header(&headers, "content-range").unwrap(), format!("bytes */{}", pdf.len()));
+
+        let (status, headers, body) = get("/documents/docs%2Fguide.md?as=raw", &[]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, guide.as_bytes());
+        assert_eq!(header(&headers, "etag").unwrap(), etag);
+
+        for uri in ["/documents/docs%2Fmissing.md?as=raw", "/documents/docs%2Fmissing.md?as=text"] {
+            let (status, headers, _) = get(uri, &[]).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "{}", uri);
+            assert_eq!(header(&headers, "content-type").unwrap(), "application/problem+json");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_isolate_collections() {
+        let tmp = tempfile::tempdir().unwrap();
+        let collection = |name: &str| {
+            let path = tmp.path().join("content").join(name);
+            std::fs::create_dir_all(&path).unwrap();
+            std::fs::write(path.join(format!("{}.md", name)), format!("# {}\nShared tenant text", name)).unwrap();
+            crate::config::CollectionConfig {
+                name: name.to_string(),
+                path,
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }
+        };
+        let app_config = Config {
+            collections: vec![collection("docs"), collection("notes")],
+            cache_path: tmp.path().join("cache"),
+            models: crate::config::ModelsConfig {
+                // Missing model file: the local embedder returns fallback vectors
+                embed: Some(crate::config::LLMModelConfig {
+                    local: Some("mock-embedder".to_string()),
+                    remote: None,
+                }),
+                ..Default::default()
+            },
+            ..Config::default()
+        };
+        let mut state = state_with_config(app_config, AuditLog::stderr(false));
+        state.auth_state = Arc::new(AuthState::with_keys(
+            vec![ApiKey::parse("key-a=docs"), ApiKey::parse("key-b=notes")],
+            vec![],
+        ));
+        state.store.lock().await.update_index().unwrap();
+        let app = build_router(state).unwrap();
+
+        let call = |method: &str, uri: String, key: Option<&str>, body: Option<serde_json::Value>| {
+            let mut request = Request::builder().method(method).uri(uri);
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            let request = match body {
+                Some(body) => request
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string())),
+                None => request.body(Body::empty()),
+            };
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request.unwrap()).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default())
+            }
+        };
+        let result_collections = |body: &serde_json::Value| -> Vec<String> {
+            body["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["collection"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        for (key, own, other) in [("key-a", "docs", "notes"), ("key-b", "notes", "docs")] {
+            let key = Some(key);
+
+            let (_, body) = call("GET", "/collections".to_string(), key, None).await;
+            assert_eq!(body, serde_json::json!([{"name": own, "description": null, "document_count": 0}]));
+
+            let (_, body) = call("GET", "/stats".to_string(), key, None).await;
+            assert_eq!(body["collections"], 1);
+            assert_eq!(body["collection_stats"][0]["name"], own);
+
+            for endpoint in ["/search", "/query"] {
+                let (status, body) = call("POST", endpoint.to_string(), key, Some(serde_json::json!({"query": "tenant"}))).await;
+                assert_eq!(status, StatusCode::OK, "{} {}", endpoint, body);
+                let collections = result_collections(&body);
+                assert!(!collections.is_empty(), "{}", endpoint);
+                assert!(collections.iter().all(|c| c == own), "{} {:?}", endpoint, collections);
+            }
+
+            #[cfg(feature = "sqlite-vec")]
+            {
+                let (status, body) = call("POST", "/vsearch".to_string(), key, Some(serde_json::json!({"query": "tenant"}))).await;
+                assert_eq!(status, StatusCode::OK);
+                assert!(result_collections(&body).iter().all(|c| c == own));
+            }
+
+            for endpoint in ["/search", "/vsearch", "/query"] {
+                let request = serde_json::json!({"query": "tenant", "collection": other});
+                let (status, body) = call("POST", endpoint.to_string(), key, Some(request)).await;
+                assert_eq!(status, StatusCode::FORBIDDEN, "{}", endpoint);
+                assert_eq!(body["error_code"], "PERMISSION_DENIED");
+            }
+
+            let (status, _) = call("GET", format!("/documents/{}%2F{}.md", own, own), key, None).await;
+            assert_eq!(status, StatusCode::OK);
+            let (status, _) = call("GET", format!("/documents/{}%2F{}.md", other, other), key, None).await;
+            assert_eq!(status, StatusCode::FORBIDDEN);
+
+            let (status, body) = call("GET", format!("/collections/{}/report", own), key, None).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["collection"], own);
+            assert_eq!(body["document_count"], 1);
+            let (status, _) = call("GET", format!("/collections/{}/report", other), key, None).await;
+            assert_eq!(status, StatusCode::FORBIDDEN);
+        }
+
+        // Keys without an allowance, and requests without a key, see everything
+        let (_, body) = call("GET", "/collections".to_string(), None, None).await;
+        assert_eq!(body.as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_multi_collection_key_ranks_hits_across_collections() {
+        let tmp = tempfile::tempdir().unwrap();
+        let collection = |name: &str, text: &str| {
+            let path = tmp.path().join("content").join(name);
+            std::fs::create_dir_all(&path).unwrap();
+            std::fs::write(path.join(format!("{}.md", name)), text).unwrap();
+            crate::config::CollectionConfig {
+                name: name.to_string(),
+                path,
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }
+        };
+        let app_config = Config {
+            collections: vec![
+                collection("docs", "# Docs\nA walrus, once, among many other words about unrelated topics and things"),
+                collection("notes", "# Walrus\nWalrus walrus walrus"),
+            ],
+            cache_path: tmp.path().join("cache"),
+            models: crate::config::ModelsConfig {
+                embed: Some(crate::config::LLMModelConfig {
+                    local: Some("mock-embedder".to_string()),
+                    remote: None,
+                }),
+                ..Default::default()
+            },
+            ..Config::default()
+        };
+        let mut state = state_with_config(app_config, AuditLog::stderr(false));
+        state.auth_state = Arc::new(AuthState::with_keys(vec![ApiKey::parse("key-ab=docs+notes")], vec![]));
+        state.store.lock().await.update_index().unwrap();
+        let app = build_router(state).unwrap();
+
+        let post = |uri: &str, body: serde_json::Value| {
+            let request = Request::post(uri)
+                .header("x-api-key", "key-ab")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+            }
+        };
+        let collections = |body: &serde_json::Value| -> Vec<String> {
+            body["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["collection"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        // The better hit sits in the second allowed collection
+        let body = post("/search", serde_json::json!({"query": "walrus"})).await;
+        assert_eq!(collections(&body), ["notes", "docs"]);
+        let body = post("/search", serde_json::json!({"query": "walrus", "limit": 1})).await;
+        assert_eq!(collections(&body), ["notes"]);
+
+        // Without sqlite-vec the vector leg is degraded, so BM25 alone decides
+        #[cfg(not(feature = "sqlite-vec"))]
+        {
+            let body = post("/query", serde_json::json!({"query": "walrus"})).await;
+            assert_eq!(collections(&body), ["notes", "docs"]);
+        }
+    }
+
+    #[test]
+    fn test_api_key_parse_allowance() {
+        assert_eq!(ApiKey::parse(" plain "), ApiKey::new("plain", "default"));
+        assert_eq!(
+            ApiKey::parse("team=docs+ notes"),
+            ApiKey::new("team", "default").with_collections(vec!["docs".to_string(), "notes".to_string()])
+        );
+    }
+
+    /// Embedder stand-in counting warm-up calls
+    #[derive(Default)]
+    struct MockEmbedder(std::sync::atomic::AtomicUsize);
+
+    impl Warmup for MockEmbedder {
+        async fn warm_up(&self) -> Result<bool> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(true)
+        }
+    }
+
+    /// Embedder taking 300ms per call, so concurrent searches overlap
+    struct SlowEmbedder;
+
+    impl crate::llm::Embed for SlowEmbedder {
+        fn model_name(&self) -> String {
+            "slow-mock".to_string()
+        }
+
+        fn embed<'a>(&'a self, texts: &'a [&'a str]) -> crate::llm::EmbedFuture<'a> {
+            Box::pin(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+                Ok(texts.iter().map(|_| vec![0.1; 8]).collect())
+            })
+        }
+    }
+
+    /// Two concurrent hybrid queries against a server allowing one at a time
+    async fn concurrent_queries(max_queued: usize) -> (Vec<axum::response::Response>, String) {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut app_config = Config {
+            collections: vec![crate::config::CollectionConfig {
+                name: "docs".to_string(),
+                path: tmp.path().join("content"),
+                pattern: None,
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+        app_config.server.search_limit = Some(crate::config::ConcurrencyLimit { max_in_flight: 1, max_queued });
+        let state = state_with_config(app_config, AuditLog::stderr(false));
+        state.llm.lock().await.set_embedder(Arc::new(SlowEmbedder));
+        let app = build_router(state).unwrap();
+
+        let query = || {
+            let request = Request::post("/query")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"query": "ownership"}"#))
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+        let (first, second) = tokio::join!(query(), query());
+        let responses = vec![first.unwrap(), second.unwrap()];
+        (responses, scrape_metrics(&app).await)
+    }
+
+    #[tokio::test]
+    async fn test_search_limit_rejects_past_full_queue() {
+        let (responses, metrics) = concurrent_queries(0).await;
+
+        let (busy, served): (Vec<_>, Vec<_>) = responses
+            .iter()
+            .partition(|r| r.status() == StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(busy.len(), 1);
+        assert_eq!(busy[0].headers()["retry-after"], "1");
+        assert!(served[0].headers().get("retry-after").is_none());
+
+        assert!(metrics.contains("qmd_http_limited_rejected_total{class=\"search\"} 1"), "{}", metrics);
+        assert!(metrics.contains("qmd_http_limited_in_flight{class=\"search\"} 0"), "{}", metrics);
+        // The scrape itself is a cheap request in flight
+        assert!(metrics.contains("qmd_http_limited_in_flight{class=\"cheap\"} 1"), "{}", metrics);
+    }
+
+    #[tokio::test]
+    async fn test_search_limit_queues_within_capacity() {
+        let start = std::time::Instant::now();
+        let (responses, metrics) = concurrent_queries(1).await;
+
+        for response in &responses {
+            assert_ne!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        }
+        // The second search waited for the first instead of running alongside it
+        assert!(start.elapsed() >= std::time::Duration::from_millis(600), "took {:?}", start.elapsed());
+        assert!(metrics.contains("qmd_http_limited_rejected_total{class=\"search\"} 0"), "{}", metrics);
+        assert!(metrics.contains("qmd_http_limited_queued{class=\"search\"} 0"), "{}", metrics);
+    }
+
+    #[tokio::test]
+    async fn test_preload_runs_before_ready() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("guide.md"), "# Guide\nPreloaded content").unwrap();
+
+        let app_config = Config {
+            collections: vec![crate::config::CollectionConfig {
+                name: "docs".to_string(),
+                path: docs,
+                pattern: Some("**/*.md".to_string()),
+                description: None,
+                ..Default::default()
+            }],
+            cache_path: tmp.path().join("cache"),
+            preload: true,
+            ..Config::default()
+        };
+        let mut state = state_with_config(app_config, AuditLog::stderr(false));
+        state.ready = Arc::new(AtomicBool::new(false));
+        state.store.lock().await.update_index().unwrap();
+        let app = build_router(state.clone()).unwrap();
+
+        let readyz = || Request::get("/readyz").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(readyz()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let embedder = MockEmbedder::default();
+        state.preload(&embedder).await.unwrap();
+        assert_eq!(embedder.0.load(Ordering::SeqCst), 1);
+
+        let response = app.clone().oneshot(readyz()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The first real request finds the model already loaded
+        let search = Request::post("/search")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"query": "preloaded"}"#))
+            .unwrap();
+        let response = app.clone().oneshot(search).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(embedder.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_mcp_audit_disabled_writes_nothing() {
+        let audit = AuditLog::stderr(false);
+        assert!(!audit.is_enabled());
+        // Must be a no-op rather than writing to stderr
+        audit.log(&serde_json::json!({"type": "audit"}));
+    }
 
     #[test]
     fn test_server_config_default() {
@@ -187,5 +962,6 @@ mod tests {
         assert_eq!(config.port, 8080);
         assert_eq!(config.host, "0.0.0.0");
         assert_eq!(config.workers, 4);
+        assert!(config.mcp_audit);
     }
 }
@@ -0,0 +1,412 @@
+// NDJSON audit log for QMD HTTP Server
+
+use crate::anel;
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+/// Size at which the audit file is rotated unless `AGENT_AUDIT_MAX_BYTES` says otherwise
+pub const DEFAULT_AUDIT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Longest `args` kept in a record unless `AGENT_AUDIT_ARGS_MAX_BYTES` says otherwise
+pub const DEFAULT_AUDIT_ARGS_MAX_BYTES: usize = 2048;
+
+/// Rotated files kept next to the active one (`audit.log.1` is the newest)
+const AUDIT_BACKUPS: usize = 3;
+
+/// Records waiting for the writer thread; past this the oldest are dropped
+const AUDIT_QUEUE_CAPACITY: usize = 1024;
+
+/// Hex digits of the SHA-256 of the full `args` kept as `args_sha256`
+const ARGS_HASH_PREFIX: usize = 16;
+
+/// Audit sink writing one StreamTap-compatible JSON record per line
+///
+/// Records are queued and written by a background thread, so callers never
+/// wait on audit IO; when the queue is full the oldest record is dropped and
+/// counted in [`AuditLog::dropped`].
+pub struct AuditLog {
+    enabled: bool,
+    queue: Arc<AuditQueue>,
+    writer: Option<JoinHandle<()>>,
+}
+
+struct AuditQueue {
+    pending: Mutex<Pending>,
+    /// Signalled when records arrive or the log closes
+    ready: Condvar,
+    /// Signalled when the writer has written everything queued
+    idle: Condvar,
+    dropped: AtomicU64,
+    args_max_bytes: usize,
+}
+
+#[derive(Default)]
+struct Pending {
+    records: VecDeque<serde_json::Value>,
+    writing: bool,
+    closed: bool,
+}
+
+impl AuditLog {
+    /// Audit log writing to stderr, like the stdio MCP server's StreamTap
+    pub fn stderr(enabled: bool) -> Self {
+        Self::spawn(enabled, Box::new(std::io::stderr()), DEFAULT_AUDIT_ARGS_MAX_BYTES)
+    }
+
+    /// Audit log appending to `AGENT_AUDIT_FILE` when it is set, stderr otherwise
+    pub fn from_env(enabled: bool) -> Result<Self> {
+        let args_max_bytes = match std::env::var(anel::env::AUDIT_ARGS_MAX_BYTES) {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid {}: {}", anel::env::AUDIT_ARGS_MAX_BYTES, value))?,
+            Err(_) => DEFAULT_AUDIT_ARGS_MAX_BYTES,
+        };
+        let path = match std::env::var(anel::env::AUDIT_FILE) {
+            Ok(path) if !path.trim().is_empty() => PathBuf::from(path),
+            _ => return Ok(Self::spawn(enabled, Box::new(std::io::stderr()), args_max_bytes)),
+        };
+        let max_bytes = match std::env::var(anel::env::AUDIT_MAX_BYTES) {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid {}: {}", anel::env::AUDIT_MAX_BYTES, value))?,
+            Err(_) => DEFAULT_AUDIT_MAX_BYTES,
+        };
+        let file = RotatingFile::open(&path, max_bytes)
+            .with_context(|| format!("Failed to open audit file: {}", path.display()))?;
+        Ok(Self::spawn(enabled, Box::new(file), args_max_bytes))
+    }
+
+    /// Audit log writing to an arbitrary sink
+    pub fn with_writer(writer: Box<dyn Write + Send>) -> Self {
+        Self::spawn(true, writer, DEFAULT_AUDIT_ARGS_MAX_BYTES)
+    }
+
+    /// Start the writer thread; a disabled log needs none
+    fn spawn(enabled: bool, writer: Box<dyn Write + Send>, args_max_bytes: usize) -> Self {
+        let queue = Arc::new(AuditQueue {
+            pending: Mutex::new(Pending::default()),
+            ready: Condvar::new(),
+            idle: Condvar::new(),
+            dropped: AtomicU64::new(0),
+            args_max_bytes,
+        });
+        let writer = enabled.then(|| {
+            let queue = Arc::clone(&queue);
+            std::thread::Builder::new()
+                .name("qmd-audit".to_string())
+                .spawn(move || queue.write_all(writer))
+                .expect("failed to spawn audit writer")
+        });
+        Self { enabled, queue, writer }
+    }
+
+    /// Whether records are written at all
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Queue a record to be written as a single NDJSON line
+    pub fn log(&self, record: &serde_json::Value) {
+        if !self.enabled {
+            return;
+        }
+        if let Ok(mut pending) = self.queue.pending.lock() {
+            if pending.records.len() >= AUDIT_QUEUE_CAPACITY {
+                pending.records.pop_front();
+                self.queue.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            pending.records.push_back(record.clone());
+            self.queue.ready.notify_one();
+        }
+    }
+
+    /// Records dropped because the writer fell behind
+    pub fn dropped(&self) -> u64 {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Block until every queued record has been written
+    pub fn flush(&self) {
+        if self.writer.is_none() {
+            return;
+        }
+        if let Ok(mut pending) = self.queue.pending.lock() {
+            while !pending.records.is_empty() || pending.writing {
+                pending = match self.queue.idle.wait(pending) {
+                    Ok(pending) => pending,
+                    Err(_) => return,
+                };
+            }
+        }
+    }
+}
+
+impl AuditQueue {
+    /// Writer thread: drain queued records in batches until the log closes
+    fn write_all(&self, mut writer: Box<dyn Write + Send>) {
+        loop {
+            let batch = {
+                let Ok(mut pending) = self.pending.lock() else { return };
+                while pending.records.is_empty() && !pending.closed {
+                    pending = match self.ready.wait(pending) {
+                        Ok(pending) => pending,
+                        Err(_) => return,
+                    };
+                }
+                if pending.records.is_empty() {
+                    return;
+                }
+                pending.writing = true;
+                std::mem::take(&mut pending.records)
+            };
+
+            for mut record in batch {
+                summarize_args(&mut record, self.args_max_bytes);
+                // One write per record so rotation never splits a line
+                let mut line = serde_json::to_string(&record).unwrap_or_default();
+                line.push('\n');
+                let _ = writer.write_all(line.as_bytes());
+            }
+            let _ = writer.flush();
+
+            if let Ok(mut pending) = self.pending.lock() {
+                pending.writing = false;
+                if pending.records.is_empty() {
+                    self.idle.notify_all();
+                }
+            }
+        }
+    }
+}
+
+/// Cap a record's `args` at `max_bytes`, marking what was cut, and add the
+/// SHA-256 prefix of the full payload as `args_sha256` to correlate records
+fn summarize_args(record: &mut serde_json::Value, max_bytes: usize) {
+    let Some(serde_json::Value::String(args)) = record.get_mut("args") else {
+        return;
+    };
+    let digest = format!("{:x}", Sha256::digest(args.as_bytes()));
+    if args.len() > max_bytes {
+        let mut end = max_bytes;
+        while !args.is_char_boundary(end) {
+            end -= 1;
+        }
+        let omitted = args.len() - end;
+        args.truncate(end);
+        args.push_str(&format!("…[truncated {} bytes]", omitted));
+    }
+    record["args_sha256"] = serde_json::json!(&digest[..ARGS_HASH_PREFIX]);
+}
+
+impl Drop for AuditLog {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.queue.pending.lock() {
+            pending.closed = true;
+            self.queue.ready.notify_all();
+        }
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::stderr(true)
+    }
+}
+
+/// Append-only file that rolls over to numbered backups once it passes `max_bytes`
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+}
+
+impl RotatingFile {
+    /// Open `path` for appending, creating it and its parent directory if needed
+    pub fn open(path: impl Into<PathBuf>, max_bytes: u64) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_bytes,
+        })
+    }
+
+    /// `<path>.<n>`
+    fn backup(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    /// Shift `<path>.N-1` .. `<path>` up by one and start an empty file
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.sync_data()?;
+        for n in (1..AUDIT_BACKUPS).rev() {
+            let from = self.backup(n);
+            if from.exists() {
+                fs::rename(&from, self.backup(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.backup(1))?;
+        self.file = append(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotating_file_keeps_whole_records() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("audit").join("audit.log");
+        let audit = AuditLog::with_writer(Box::new(RotatingFile::open(&path, 64).unwrap()));
+        for i in 0..6 {
+            audit.log(&serde_json::json!({"type": "audit", "seq": i, "pad": "xxxxxxxxxxxxxxxxxxxx"}));
+        }
+        audit.flush();
+
+        // Each record is ~50 bytes, so every write past the first rotates
+        let read = |p: &Path| fs::read_to_string(p).unwrap();
+        let seq = |text: String| -> Vec<i64> {
+            text.lines()
+                .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["seq"].as_i64().unwrap())
+                .collect()
+        };
+        assert_eq!(seq(read(&path)), vec![5]);
+        assert_eq!(seq(read(&tmp.path().join("audit/audit.log.1"))), vec![4]);
+        assert_eq!(seq(read(&tmp.path().join("audit/audit.log.3"))), vec![2]);
+        assert!(!tmp.path().join("audit/audit.log.4").exists());
+    }
+
+    #[test]
+    fn test_large_args_are_capped_and_hashed() {
+        let buffer = Buffer::default();
+        let audit = AuditLog::spawn(true, Box::new(buffer.clone()), 64);
+        let huge = "é".repeat(10_000);
+        audit.log(&serde_json::json!({"type": "audit", "args": huge}));
+        audit.log(&serde_json::json!({"type": "audit", "args": "{}"}));
+        audit.flush();
+
+        let records = buffer.records();
+        let args = records[0]["args"].as_str().unwrap();
+        assert!(args.starts_with("éé"));
+        assert!(args.ends_with("…[truncated 19936 bytes]"), "{}", args);
+        let digest = format!("{:x}", Sha256::digest(huge.as_bytes()));
+        assert_eq!(records[0]["args_sha256"], digest[..16]);
+        assert_eq!(records[1]["args"], "{}");
+        assert_eq!(records[1]["args_sha256"].as_str().unwrap().len(), 16);
+    }
+
+    #[test]
+    fn test_stalled_writer_never_blocks_log() {
+        use std::sync::mpsc;
+
+        /// Sink whose first write stalls until the test lets it through
+        struct Gated {
+            entered: mpsc::Sender<()>,
+            gate: mpsc::Receiver<()>,
+            inner: Buffer,
+        }
+        impl Write for Gated {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.entered.send(()).is_ok() {
+                    let _ = self.gate.recv();
+                }
+                self.inner.write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let (entered_tx, entered) = mpsc::channel();
+        let (open, gate) = mpsc::channel();
+        let buffer = Buffer::default();
+        let audit = AuditLog::with_writer(Box::new(Gated { entered: entered_tx, gate, inner: buffer.clone() }));
+        audit.log(&serde_json::json!({"type": "audit", "seq": 0}));
+        entered.recv().unwrap();
+        drop(entered);
+
+        // The writer is stuck, yet a huge record and a full queue cost the caller nothing
+        let start = std::time::Instant::now();
+        audit.log(&serde_json::json!({"type": "audit", "seq": 1, "args": "x".repeat(8 * 1024 * 1024)}));
+        for seq in 2..AUDIT_QUEUE_CAPACITY + 11 {
+            audit.log(&serde_json::json!({"type": "audit", "seq": seq}));
+        }
+        assert!(start.elapsed() < std::time::Duration::from_secs(2), "took {:?}", start.elapsed());
+        assert_eq!(audit.dropped(), 10);
+
+        open.send(()).unwrap();
+        audit.flush();
+        let records = buffer.records();
+        assert_eq!(records.len(), 1 + AUDIT_QUEUE_CAPACITY);
+        // The oldest queued records went first
+        assert_eq!(records[1]["seq"], 11);
+        assert_eq!(records.last().unwrap()["seq"], AUDIT_QUEUE_CAPACITY + 10);
+    }
+
+    /// In-memory sink shared with the writer thread
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Buffer {
+        fn records(&self) -> Vec<serde_json::Value> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
@@ -1,7 +1,54 @@
 // Prometheus metrics for QMD HTTP Server
 
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::server::middleware::RouteLimits;
+use crate::store::pool::PoolStats;
+use crate::store::timings::StageTimings;
+
+/// JSON-RPC methods counted under their own name, plus `invalid` for bodies
+/// that are not JSON-RPC; any other method is counted as `other`
+const MCP_METHODS: &[&str] = &[
+    "initialize",
+    "notifications/initialized",
+    "ping",
+    "tools/list",
+    "tools/call",
+    "invalid",
+];
+
+/// `name` when it is one of `known`, otherwise `other`, so request bodies
+/// cannot add label values
+fn known_label(name: &str, known: &[&'static str]) -> &'static str {
+    known.iter().find(|k| **k == name).copied().unwrap_or("other")
+}
+
+/// Upper bounds (seconds) of the MCP and search stage latency histogram buckets
+const LATENCY_BUCKETS: [f64; 7] = [0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// Cumulative latency histogram for one MCP method or search stage
+#[derive(Debug, Default, Clone)]
+struct LatencyHistogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum_secs: f64,
+    count: u64,
+}
+
+impl LatencyHistogram {
+    fn observe(&mut self, secs: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum_secs += secs;
+        self.count += 1;
+    }
+}
 
 /// Metrics collector for QMD server
 #[derive(Clone)]
@@ -19,11 +66,23 @@ pub struct Metrics {
 
     // Error metrics
     errors_total: Arc<AtomicU64>,
+    /// Searches cut short by their timeout
+    partial_total: Arc<AtomicU64>,
 
     // LLM metrics
     llm_embeddings_total: Arc<AtomicU64>,
     llm_rerank_total: Arc<AtomicU64>,
     llm_errors: Arc<AtomicU64>,
+
+    // MCP bridge metrics: (method, tool) -> count, method -> latency
+    mcp_requests: Arc<Mutex<BTreeMap<(String, String), u64>>>,
+    mcp_latency: Arc<Mutex<BTreeMap<String, LatencyHistogram>>>,
+
+    // Hybrid search pipeline: stage -> latency
+    stage_latency: Arc<Mutex<BTreeMap<&'static str, LatencyHistogram>>>,
+
+    // Store connection pool: busy retries and reopened connections
+    pool: Arc<PoolStats>,
 }
 
 impl Metrics {
@@ -38,12 +97,22 @@ impl Metrics {
             vsearch_total: Arc::new(AtomicU64::new(0)),
             query_total: Arc::new(AtomicU64::new(0)),
             errors_total: Arc::new(AtomicU64::new(0)),
+            partial_total: Arc::new(AtomicU64::new(0)),
             llm_embeddings_total: Arc::new(AtomicU64::new(0)),
             llm_rerank_total: Arc::new(AtomicU64::new(0)),
             llm_errors: Arc::new(AtomicU64::new(0)),
+            mcp_requests: Arc::new(Mutex::new(BTreeMap::new())),
+            mcp_latency: Arc::new(Mutex::new(BTreeMap::new())),
+            stage_latency: Arc::new(Mutex::new(BTreeMap::new())),
+            pool: Arc::new(PoolStats::default()),
         }
     }
 
+    /// Metrics that also report the store's connection pool counters
+    pub fn with_pool_stats(pool: Arc<PoolStats>) -> Self {
+        Self { pool, ..Self::new() }
+    }
+
     /// Increment total requests counter
     pub fn inc_requests_total(&self) {
         self.requests_total.fetch_add(1, Ordering::Relaxed);
@@ -96,6 +165,11 @@ impl Metrics {
         self.errors_total.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Increment partial-response counter
+    pub fn inc_partial(&self) {
+        self.partial_total.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Increment LLM embeddings counter
     pub fn inc_llm_embeddings(&self) {
         self.llm_embeddings_total.fetch_add(1, Ordering::Relaxed);
@@ -111,6 +185,188 @@ impl Metrics {
         self.llm_errors.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Record an MCP request by JSON-RPC method (and tool name for tools/call);
+    /// unknown methods and tools are counted as `other`
+    pub fn record_mcp_request(&self, method: &str, tool: Option<&str>, duration: Duration) {
+        let method = known_label(method, MCP_METHODS);
+        let tool = tool.map(|tool| known_label(tool, crate::mcp::TOOL_NAMES));
+        if let Ok(mut requests) = self.mcp_requests.lock() {
+            *requests
+                .entry((method.to_string(), tool.unwrap_or_default().to_string()))
+                .or_insert(0) += 1;
+        }
+        if let Ok(mut latency) = self.mcp_latency.lock() {
+            latency
+                .entry(method.to_string())
+                .or_default()
+                .observe(duration.as_secs_f64());
+        }
+    }
+
+    /// Record the per-stage timings of one hybrid search
+    pub fn record_search_stages(&self, timings: &StageTimings) {
+        if let Ok(mut latency) = self.stage_latency.lock() {
+            for (stage, ms) in timings.stages() {
+                latency.entry(stage).or_default().observe(ms / 1000.0);
+            }
+        }
+    }
+
+    /// Number of hybrid searches whose `stage` was timed
+    pub fn get_search_stage_count(&self, stage: &str) -> u64 {
+        self.stage_latency
+            .lock()
+            .ok()
+            .and_then(|latency| latency.get(stage).map(|histogram| histogram.count))
+            .unwrap_or(0)
+    }
+
+    /// Get the MCP request count for a method (and tool name for tools/call)
+    pub fn get_mcp_requests(&self, method: &str, tool: Option<&str>) -> u64 {
+        self.mcp_requests
+            .lock()
+            .ok()
+            .and_then(|requests| {
+                requests
+                    .get(&(method.to_string(), tool.unwrap_or_default().to_string()))
+                    .copied()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Render MCP counters and latency histograms in Prometheus text format
+    pub fn render_mcp_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP qmd_mcp_requests_total Total MCP bridge requests by method and tool\n");
+        out.push_str("# TYPE qmd_mcp_requests_total counter\n");
+        if let Ok(requests) = self.mcp_requests.lock() {
+            for ((method, tool), count) in requests.iter() {
+                let _ = writeln!(
+                    out,
+                    "qmd_mcp_requests_total{{method=\"{}\",tool=\"{}\"}} {}",
+                    escape_label(method),
+                    escape_label(tool),
+                    count
+                );
+            }
+        }
+
+        out.push_str("\n# HELP qmd_mcp_request_duration_seconds MCP bridge request latency\n");
+        out.push_str("# TYPE qmd_mcp_request_duration_seconds histogram\n");
+        if let Ok(latency) = self.mcp_latency.lock() {
+            for (method, histogram) in latency.iter() {
+                let method = escape_label(method);
+                for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                    let _ = writeln!(
+                        out,
+                        "qmd_mcp_request_duration_seconds_bucket{{method=\"{}\",le=\"{}\"}} {}",
+                        method, bound, count
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "qmd_mcp_request_duration_seconds_bucket{{method=\"{}\",le=\"+Inf\"}} {}",
+                    method, histogram.count
+                );
+                let _ = writeln!(
+                    out,
+                    "qmd_mcp_request_duration_seconds_sum{{method=\"{}\"}} {}",
+                    method, histogram.sum_secs
+                );
+                let _ = writeln!(
+                    out,
+                    "qmd_mcp_request_duration_seconds_count{{method=\"{}\"}} {}",
+                    method, histogram.count
+                );
+            }
+        }
+
+        out
+    }
+
+    /// Render hybrid search stage latency histograms in Prometheus text format
+    pub fn render_stage_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP qmd_search_stage_duration_seconds Hybrid search latency per pipeline stage\n");
+        out.push_str("# TYPE qmd_search_stage_duration_seconds histogram\n");
+        if let Ok(latency) = self.stage_latency.lock() {
+            for (stage, histogram) in latency.iter() {
+                for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                    let _ = writeln!(
+                        out,
+                        "qmd_search_stage_duration_seconds_bucket{{stage=\"{}\",le=\"{}\"}} {}",
+                        stage, bound, count
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "qmd_search_stage_duration_seconds_bucket{{stage=\"{}\",le=\"+Inf\"}} {}",
+                    stage, histogram.count
+                );
+                let _ = writeln!(
+                    out,
+                    "qmd_search_stage_duration_seconds_sum{{stage=\"{}\"}} {}",
+                    stage, histogram.sum_secs
+                );
+                let _ = writeln!(
+                    out,
+                    "qmd_search_stage_duration_seconds_count{{stage=\"{}\"}} {}",
+                    stage, histogram.count
+                );
+            }
+        }
+
+        out
+    }
+
+    /// Render store connection pool counters in Prometheus text format
+    pub fn render_pool_prometheus(&self) -> String {
+        format!(
+            "# HELP qmd_storage_retries_total Store queries retried because the database was busy or locked\n\
+             # TYPE qmd_storage_retries_total counter\n\
+             qmd_storage_retries_total {}\n\
+             \n\
+             # HELP qmd_storage_reopens_total Pooled store connections reopened after their database changed or failed\n\
+             # TYPE qmd_storage_reopens_total counter\n\
+             qmd_storage_reopens_total {}\n\
+             \n\
+             # HELP qmd_storage_schema_inits_total Database schemas created or migrated on open\n\
+             # TYPE qmd_storage_schema_inits_total counter\n\
+             qmd_storage_schema_inits_total {}\n",
+            self.pool.retries(),
+            self.pool.reopens(),
+            self.pool.schema_inits()
+        )
+    }
+
+    /// Render the route class limiters' load in Prometheus text format
+    pub fn render_limits_prometheus(limits: &RouteLimits) -> String {
+        let limiters = [&limits.search, &limits.cheap];
+        let mut out = String::new();
+
+        out.push_str("# HELP qmd_http_limited_in_flight Requests being served, per route class\n");
+        out.push_str("# TYPE qmd_http_limited_in_flight gauge\n");
+        for limiter in limiters {
+            let _ = writeln!(out, "qmd_http_limited_in_flight{{class=\"{}\"}} {}", limiter.name(), limiter.in_flight());
+        }
+
+        out.push_str("\n# HELP qmd_http_limited_queued Requests waiting for a turn, per route class\n");
+        out.push_str("# TYPE qmd_http_limited_queued gauge\n");
+        for limiter in limiters {
+            let _ = writeln!(out, "qmd_http_limited_queued{{class=\"{}\"}} {}", limiter.name(), limiter.queued());
+        }
+
+        out.push_str("\n# HELP qmd_http_limited_rejected_total Requests answered 503 because their class's queue was full\n");
+        out.push_str("# TYPE qmd_http_limited_rejected_total counter\n");
+        for limiter in limiters {
+            let _ = writeln!(out, "qmd_http_limited_rejected_total{{class=\"{}\"}} {}", limiter.name(), limiter.rejected());
+        }
+
+        out
+    }
+
     /// Get current values
     pub fn get_requests_total(&self) -> u64 {
         self.requests_total.load(Ordering::Relaxed)
@@ -136,6 +392,10 @@ impl Metrics {
         self.errors_total.load(Ordering::Relaxed)
     }
 
+    pub fn get_partial_total(&self) -> u64 {
+        self.partial_total.load(Ordering::Relaxed)
+    }
+
     pub fn get_llm_embeddings_total(&self) -> u64 {
         self.llm_embeddings_total.load(Ordering::Relaxed)
     }
@@ -149,6 +409,14 @@ impl Metrics {
     }
 }
 
+/// Escape a Prometheus label value
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
 impl Default for Metrics {
     fn default() -> Self {
         Self::new()
@@ -1,8 +1,10 @@
 // Observability module for QMD HTTP Server
 // Provides metrics, logging, and distributed tracing
 
+pub mod audit;
 pub mod metrics;
 pub mod tracing_mod;
 
+pub use audit::AuditLog;
 pub use metrics::Metrics;
 pub use tracing_mod::Tracing;
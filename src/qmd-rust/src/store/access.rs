@@ -0,0 +1,260 @@
+//! Resolving client-supplied document paths for the MCP and HTTP servers.
+//!
+//! Requests are answered from the documents table (collection + relative
+//! path). Reading the filesystem is only a fallback, and only for files that
+//! canonicalize to somewhere under a configured collection path.
+
+use super::path::{index_path, native_path, parse_virtual_path};
+use super::extract::read_document;
+use super::Store;
+use crate::anel::{AnelError, AnelErrorCode, RecoveryHint};
+use crate::config::CollectionConfig;
+use rusqlite::OptionalExtension;
+use std::path::{Component, Path, PathBuf};
+
+/// Document text returned for a retrieval request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetrievedDocument {
+    /// Collection the document belongs to
+    pub collection: String,
+    /// `collection/relative-path` for indexed documents, the file path otherwise
+    pub path: String,
+    pub content: String,
+    /// SHA-256 of the content, as stored in the index
+    pub hash: String,
+}
+
+/// On-disk file behind a retrieval request, for serving its original bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetrievedFile {
+    /// Collection the file belongs to
+    pub collection: String,
+    /// Canonical path, inside the collection path
+    pub path: PathBuf,
+    /// Hash stored in the index; None for files served by the filesystem fallback
+    pub hash: Option<String>,
+}
+
+/// Where a retrieval request resolved to
+enum Located<'a> {
+    /// Indexed document, and the file it was indexed from
+    Indexed(RetrievedDocument, &'a CollectionConfig, PathBuf),
+    /// File under a collection path, found by the filesystem fallback
+    OnDisk(&'a CollectionConfig, PathBuf),
+}
+
+impl Store {
+    /// Look up a document for a client request without reading outside collections
+    ///
+    /// Accepts `qmd://collection/path`, `collection/path` and filesystem paths.
+    /// Paths escaping their collection fail with PermissionDenied.
+    pub fn retrieve_document(&self, requested: &str) -> Result<RetrievedDocument, Box<AnelError>> {
+        match self.locate(requested)? {
+            Located::Indexed(doc, _, _) => Ok(doc),
+            Located::OnDisk(collection, path) => {
+                let content =
+                    read_document(&path, &self.config).map_err(|_| not_found_error(requested))?;
+                Ok(RetrievedDocument {
+                    collection: collection.name.clone(),
+                    path: path.display().to_string(),
+                    hash: Store::calculate_hash(&content),
+                    content,
+                })
+            }
+        }
+    }
+
+    /// Look up the file behind a document without reading it
+    ///
+    /// Resolves like [`Store::retrieve_document`], so the same paths are
+    /// served or refused; the file of an indexed document must also still
+    /// canonicalize to somewhere under its collection path.
+    pub fn retrieve_file(&self, requested: &str) -> Result<RetrievedFile, Box<AnelError>> {
+        match self.locate(requested)? {
+            Located::Indexed(doc, collection, path) => Ok(RetrievedFile {
+                collection: doc.collection,
+                path: file_within(collection, &path, requested)?,
+                hash: Some(doc.hash),
+            }),
+            Located::OnDisk(collection, path) => Ok(RetrievedFile {
+                collection: collection.name.clone(),
+                path,
+                hash: None,
+            }),
+        }
+    }
+
+    /// Resolve a request to an indexed document or a file under a collection
+    fn locate(&self, requested: &str) -> Result<Located<'_>, Box<AnelError>> {
+        let requested = requested.trim();
+        let fallback = self.config.documents.filesystem_fallback;
+
+        if let Some((collection, relative)) = self.split_collection_path(requested) {
+            let relative = clean_relative(&relative).ok_or_else(|| escape_error(requested))?;
+            let path = native_path(&collection.path, &relative);
+            if let Some(doc) = self.indexed_document(&collection.name, &relative)? {
+                return Ok(Located::Indexed(doc, collection, path));
+            }
+            if fallback && !self.is_deactivated(&collection.name, &relative)? {
+                return Ok(Located::OnDisk(collection, file_within(collection, &path, requested)?));
+            }
+            return Err(not_found_error(requested));
+        }
+
+        // Plain filesystem path: map it into a collection before touching it
+        let path = Path::new(requested);
+        for collection in &self.config.collections {
+            let Some(relative) = relative_to(&collection.path, path) else {
+                continue;
+            };
+            let relative = clean_relative(&relative).ok_or_else(|| escape_error(requested))?;
+            if let Some(doc) = self.indexed_document(&collection.name, &relative)? {
+                return Ok(Located::Indexed(doc, collection, path.to_path_buf()));
+            }
+            if fallback && !self.is_deactivated(&collection.name, &relative)? {
+                return Ok(Located::OnDisk(collection, file_within(collection, path, requested)?));
+            }
+            return Err(not_found_error(requested));
+        }
+
+        if fallback {
+            // Relative paths or symlinks may still land inside a collection
+            if let Ok(canonical) = path.canonicalize() {
+                for collection in &self.config.collections {
+                    if let Ok(base) = collection.path.canonicalize() {
+                        if let Ok(relative) = canonical.strip_prefix(&base) {
+                            if self.is_deactivated(&collection.name, &relative.to_string_lossy())? {
+                                return Err(not_found_error(requested));
+                            }
+                            return Ok(Located::OnDisk(collection, file_within(collection, path, requested)?));
+                        }
+                    }
+                }
+            }
+        }
+        // Same error whether or not the file exists, so callers cannot probe the disk
+        Err(escape_error(requested))
+    }
+
+    /// Collection named by a virtual or `collection/...` path, with the rest of the path
+    fn split_collection_path(&self, requested: &str) -> Option<(&CollectionConfig, String)> {
+        let (name, relative) = match parse_virtual_path(requested) {
+            Some(vp) => (vp.collection, vp.path),
+            None => {
+                // Windows users may type `collection\path`
+                let (name, relative) = requested.split_once(['/', std::path::MAIN_SEPARATOR])?;
+                (name.to_string(), relative.to_string())
+            }
+        };
+        self.config.find_collection(&name).map(|c| (c, relative))
+    }
+
+    /// Active document stored under `collection/relative`
+    fn indexed_document(&self, collection: &str, relative: &str) -> Result<Option<RetrievedDocument>, Box<AnelError>> {
+        let conn = self.get_connection(collection).map_err(AnelError::from)?;
+        let row: Option<(String, String)> = conn
+            .query_row(
+                "SELECT qmd_doc(c.doc, c.codec, c.packed), d.hash FROM documents d
+                 JOIN content c ON c.hash = d.hash
+                 WHERE d.collection = ? AND d.path = ? AND d.active = 1",
+                [collection, relative],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| AnelError::from(anyhow::Error::from(e)))?;
+
+        Ok(row.map(|(content, hash)| RetrievedDocument {
+            collection: collection.to_string(),
+            path: format!("{}/{}", collection, relative),
+            content,
+            hash,
+        }))
+    }
+
+    /// Whether `collection/relative` is indexed but soft-deleted; such documents
+    /// are not served from disk even with the filesystem fallback
+    fn is_deactivated(&self, collection: &str, relative: &str) -> Result<bool, Box<AnelError>> {
+        let conn = self.get_connection(collection).map_err(AnelError::from)?;
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM documents WHERE collection = ? AND path = ? AND active = 0)",
+            [collection, relative],
+            |row| row.get(0),
+        )
+        .map_err(|e| Box::new(AnelError::from(anyhow::Error::from(e))))
+    }
+}
+
+/// Normalized relative path, or None if it is absolute or climbs out with `..`
+fn clean_relative(relative: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in Path::new(relative).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// `path` relative to `base`, compared lexically
+fn relative_to(base: &Path, path: &Path) -> Option<String> {
+    if !path.is_absolute() {
+        return None;
+    }
+    path.strip_prefix(base).ok().map(index_path)
+}
+
+/// Canonical `path`, only if it is a file under the collection path
+fn file_within(collection: &CollectionConfig, path: &Path, requested: &str) -> Result<PathBuf, Box<AnelError>> {
+    let canonical: PathBuf = path.canonicalize().map_err(|_| not_found_error(requested))?;
+    let base = collection.path.canonicalize().map_err(|_| not_found_error(requested))?;
+    if !canonical.starts_with(&base) {
+        return Err(escape_error(requested));
+    }
+    if !canonical.is_file() {
+        return Err(not_found_error(requested));
+    }
+    Ok(canonical)
+}
+
+fn not_found_error(requested: &str) -> Box<AnelError> {
+    Box::new(AnelError::new(
+        AnelErrorCode::NotFound,
+        "Document Not Found",
+        format!("Document not found: {}", requested),
+    )
+    .with_hint(RecoveryHint::new(
+        "USE_SEARCH_PATH",
+        "Request a path returned by search, e.g. collection/relative/path.md",
+    )))
+}
+
+fn escape_error(requested: &str) -> Box<AnelError> {
+    Box::new(AnelError::new(
+        AnelErrorCode::PermissionDenied,
+        "Path Outside Collections",
+        format!("Path is outside the configured collections: {}", requested),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_relative_rejects_escapes() {
+        assert_eq!(clean_relative("a/./b.md").as_deref(), Some("a/b.md"));
+        assert_eq!(clean_relative("../etc/passwd"), None);
+        assert_eq!(clean_relative("a/../../b"), None);
+        assert_eq!(clean_relative("/etc/passwd"), None);
+    }
+
+    #[test]
+    fn test_relative_to_requires_absolute_prefix() {
+        let base = Path::new("/data/docs");
+        assert_eq!(relative_to(base, Path::new("/data/docs/a.md")).as_deref(), Some("a.md"));
+        assert_eq!(relative_to(base, Path::new("/data/docs-other/a.md")), None);
+        assert_eq!(relative_to(base, Path::new("docs/a.md")), None);
+    }
+}
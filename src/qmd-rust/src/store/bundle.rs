@@ -0,0 +1,321 @@
+//! Bundling stored document text with search results for offline RAG.
+//!
+//! Content is taken from the index (not the filesystem) and capped by a byte
+//! budget shared across results in rank order.
+
+use super::chunker::{chunk_document, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP};
+use super::{SearchResult, Store};
+use anyhow::Result;
+use serde::Serialize;
+
+/// Maximum chunks bundled per result in `chunks` mode
+const MAX_CHUNKS_PER_RESULT: usize = 3;
+/// Characters of context on each side of the first match in `snippet` mode
+const SNIPPET_CONTEXT: usize = 160;
+
+/// How much of each document to bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentMode {
+    /// The whole stored document
+    Full,
+    /// The best-matching chunks, with their positions
+    Chunks,
+    /// A short window around the first matching term
+    Snippet,
+}
+
+impl std::str::FromStr for ContentMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Self::Full),
+            "chunks" => Ok(Self::Chunks),
+            "snippet" => Ok(Self::Snippet),
+            other => anyhow::bail!(
+                "Unknown content mode '{}'; valid options: full, chunks, snippet",
+                other
+            ),
+        }
+    }
+}
+
+/// A piece of document text with its character offset in the document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContentPiece {
+    /// Chunk index, in `chunks` mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<usize>,
+    /// Character offset in the original document
+    pub pos: usize,
+    /// 1-based line the piece starts on, for `get --from`
+    pub line: usize,
+    pub text: String,
+}
+
+/// Text bundled with one search result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BundledContent {
+    pub mode: ContentMode,
+    pub pieces: Vec<ContentPiece>,
+    /// Set when the content budget cut or dropped text for this result
+    pub truncated: bool,
+}
+
+impl Store {
+    /// Stored text of the document behind a search result
+    pub fn document_content(&self, result: &SearchResult) -> Result<Option<String>> {
+        use rusqlite::OptionalExtension;
+
+        // SQLite results carry `collection/path`; backends may carry the bare path
+        let prefix = format!("{}/", result.collection);
+        let path = result.path.strip_prefix(&prefix).unwrap_or(&result.path);
+
+        self.with_connection(&result.collection, |conn| {
+            Ok(conn.query_row(
+                "SELECT qmd_doc(c.doc, c.codec, c.packed) FROM documents d
+                 JOIN content c ON c.hash = d.hash
+                 WHERE d.collection = ? AND d.path = ? AND d.active = 1",
+                [&result.collection, path],
+                |row| row.get(0),
+            ).optional()?)
+        })
+    }
+}
+
+/// Bundle content for each result, spending `budget` bytes across results in rank order
+pub fn bundle_content(
+    store: &Store,
+    results: &[SearchResult],
+    query: &str,
+    mode: ContentMode,
+    budget: usize,
+) -> Result<Vec<BundledContent>> {
+    let terms = query_terms(query);
+    let mut remaining = budget;
+
+    results
+        .iter()
+        .map(|result| {
+            let doc = store.document_content(result)?.unwrap_or_default();
+            let pieces = match mode {
+                ContentMode::Full => vec![ContentPiece { seq: None, pos: 0, line: 1, text: doc }],
+                ContentMode::Chunks => best_chunks(&doc, &terms),
+                ContentMode::Snippet => snippet(&doc, &terms).into_iter().collect(),
+            };
+            Ok(apply_budget(mode, pieces, &mut remaining))
+        })
+        .collect()
+}
+
+/// Lowercased query words, ignoring FTS syntax characters
+pub(crate) fn query_terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| c.is_whitespace() || matches!(c, '"' | '*' | '(' | ')' | ':' | '^'))
+        .filter(|t| !t.is_empty() && !matches!(*t, "AND" | "OR" | "NOT" | "NEAR"))
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// Number of query term occurrences in a text
+fn term_hits(text: &str, terms: &[String]) -> usize {
+    let text = text.to_lowercase();
+    terms.iter().map(|t| text.matches(t.as_str()).count()).sum()
+}
+
+/// Chunks containing the most query terms, best first; the first chunk if none match
+pub(crate) fn best_chunks(doc: &str, terms: &[String]) -> Vec<ContentPiece> {
+    let mut scored: Vec<(usize, ContentPiece)> = chunk_document(doc, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP)
+        .into_iter()
+        .map(|chunk| {
+            let hits = term_hits(&chunk.text, terms);
+            (hits, ContentPiece { seq: Some(chunk.seq), pos: chunk.pos, line: chunk.line, text: chunk.text })
+        })
+        .collect();
+
+    if scored.iter().all(|(hits, _)| *hits == 0) {
+        scored.truncate(1);
+        return scored.into_iter().map(|(_, piece)| piece).collect();
+    }
+
+    // Stable sort keeps document order among equally good chunks
+    scored.sort_by_key(|(hits, _)| std::cmp::Reverse(*hits));
+    scored
+        .into_iter()
+        .filter(|(hits, _)| *hits > 0)
+        .take(MAX_CHUNKS_PER_RESULT)
+        .map(|(_, piece)| piece)
+        .collect()
+}
+
+/// Window around the first query term occurrence, or the start of the document
+fn snippet(doc: &str, terms: &[String]) -> Option<ContentPiece> {
+    if doc.is_empty() {
+        return None;
+    }
+
+    let lower = doc.to_lowercase();
+    // Lowercasing can change byte lengths; only trust offsets when it did not
+    let hit = if lower.len() == doc.len() {
+        terms.iter().filter_map(|t| lower.find(t.as_str())).min().unwrap_or(0)
+    } else {
+        0
+    };
+
+    let start = floor_char_boundary(doc, hit.saturating_sub(SNIPPET_CONTEXT));
+    let end = floor_char_boundary(doc, (hit + SNIPPET_CONTEXT).min(doc.len()));
+
+    Some(ContentPiece {
+        seq: None,
+        pos: doc[..start].chars().count(),
+        line: doc[..start].matches('\n').count() + 1,
+        text: doc[start..end].to_string(),
+    })
+}
+
+/// Cut pieces down to the remaining byte budget, flagging any loss
+fn apply_budget(mode: ContentMode, pieces: Vec<ContentPiece>, remaining: &mut usize) -> BundledContent {
+    let mut truncated = false;
+    let mut kept = Vec::new();
+
+    for mut piece in pieces {
+        if *remaining == 0 {
+            truncated = true;
+            break;
+        }
+        if piece.text.len() > *remaining {
+            let cut = floor_char_boundary(&piece.text, *remaining);
+            piece.text.truncate(cut);
+            truncated = true;
+        }
+        *remaining -= piece.text.len();
+        if !piece.text.is_empty() {
+            kept.push(piece);
+        }
+        if truncated {
+            // Budget exhausted mid-piece; nothing else fits
+            *remaining = 0;
+        }
+    }
+
+    BundledContent { mode, pieces: kept, truncated }
+}
+
+/// Bundled content cut down to `budget` bytes of text, spent in rank order
+pub fn limit_content(contents: &[BundledContent], budget: usize) -> Vec<BundledContent> {
+    let mut remaining = budget;
+    contents
+        .iter()
+        .map(|content| {
+            let mut limited = apply_budget(content.mode, content.pieces.clone(), &mut remaining);
+            limited.truncated |= content.truncated;
+            limited
+        })
+        .collect()
+}
+
+/// Bytes of text across all bundled pieces
+pub fn content_bytes(contents: &[BundledContent]) -> usize {
+    contents.iter().flat_map(|c| &c.pieces).map(|p| p.text.len()).sum()
+}
+
+/// Keep at most `max` pieces across all results, in rank order
+pub fn limit_pieces(contents: &mut [BundledContent], max: usize) {
+    let mut remaining = max;
+    for content in contents {
+        if content.pieces.len() > remaining {
+            content.pieces.truncate(remaining);
+            content.truncated = true;
+        }
+        remaining -= content.pieces.len();
+    }
+}
+
+/// Largest UTF-8 character boundary at or before `pos`
+fn floor_char_boundary(text: &str, mut pos: usize) -> usize {
+    pos = pos.min(text.len());
+    while pos > 0 && !text.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn piece(text: &str) -> ContentPiece {
+        ContentPiece { seq: None, pos: 0, line: 1, text: text.to_string() }
+    }
+
+    #[test]
+    fn test_content_mode_from_str() {
+        assert_eq!("chunks".parse::<ContentMode>().unwrap(), ContentMode::Chunks);
+        let err = "all".parse::<ContentMode>().unwrap_err();
+        assert!(err.to_string().contains("valid options: full, chunks, snippet"));
+    }
+
+    #[test]
+    fn test_budget_spent_in_rank_order() {
+        let mut remaining = 10;
+        let first = apply_budget(ContentMode::Full, vec![piece("123456")], &mut remaining);
+        let second = apply_budget(ContentMode::Full, vec![piece("abcdefgh")], &mut remaining);
+        let third = apply_budget(ContentMode::Full, vec![piece("xyz")], &mut remaining);
+
+        assert_eq!(first.pieces[0].text, "123456");
+        assert!(!first.truncated);
+        assert_eq!(second.pieces[0].text, "abcd");
+        assert!(second.truncated);
+        assert!(third.pieces.is_empty());
+        assert!(third.truncated);
+    }
+
+    #[test]
+    fn test_budget_cuts_on_char_boundary() {
+        let mut remaining = 3;
+        let bundled = apply_budget(ContentMode::Full, vec![piece("héllo")], &mut remaining);
+        // 'é' is two bytes, so only "hé" (3 bytes) fits
+        assert_eq!(bundled.pieces[0].text, "hé");
+        assert!(bundled.truncated);
+    }
+
+    #[test]
+    fn test_piece_limit_spent_in_rank_order() {
+        let bundled = |n: usize| BundledContent {
+            mode: ContentMode::Chunks,
+            pieces: (0..n).map(|i| piece(&i.to_string())).collect(),
+            truncated: false,
+        };
+        let mut contents = vec![bundled(2), bundled(3), bundled(1)];
+        limit_pieces(&mut contents, 4);
+
+        assert_eq!(contents.iter().map(|c| c.pieces.len()).collect::<Vec<_>>(), [2, 2, 0]);
+        assert_eq!(contents.iter().map(|c| c.truncated).collect::<Vec<_>>(), [false, true, true]);
+
+        let limited = limit_content(&contents, 3);
+        assert_eq!(content_bytes(&limited), 3);
+        assert!(!limited[0].truncated);
+        assert!(limited[1].truncated);
+    }
+
+    #[test]
+    fn test_best_chunks_prefers_matching_chunk() {
+        let filler = "Lorem ipsum dolor sit amet. ".repeat(150);
+        let doc = format!("{}Zebra crossings appear here.\n\n{}", filler, filler);
+        let chunks = best_chunks(&doc, &query_terms("zebra"));
+
+        assert!(!chunks.is_empty());
+        assert!(chunks[0].text.contains("Zebra"));
+        assert!(chunks[0].seq.unwrap() > 0);
+    }
+
+    #[test]
+    fn test_snippet_centers_on_match() {
+        let doc = format!("{}needle{}", "a".repeat(500), "b".repeat(500));
+        let snip = snippet(&doc, &query_terms("needle")).unwrap();
+        assert!(snip.text.contains("needle"));
+        assert!(snip.text.len() <= 2 * SNIPPET_CONTEXT);
+        assert_eq!(snip.pos, 500 - SNIPPET_CONTEXT);
+    }
+}
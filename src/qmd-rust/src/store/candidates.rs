@@ -0,0 +1,228 @@
+//! Fused hybrid-search candidates kept between requests.
+//!
+//! Agents often repeat a query asking for a longer page. The fused candidate
+//! list does not depend on the page, so it is cached per query, language,
+//! collections and models, together with the rerank scores computed so far.
+//! A longer page then skips retrieval and fusion and only reranks the windows
+//! (`search.rerank_window`) no earlier request reached. Entries expire after
+//! [`CANDIDATE_TTL`] and as soon as the index generation changes, so a cached
+//! ranking is always the one a cold run would produce.
+
+use super::deadline::StageFailure;
+use super::lang::QueryLanguage;
+use super::{SearchResult, Store};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long cached candidates stay usable
+pub const CANDIDATE_TTL: Duration = Duration::from_secs(60);
+
+/// Most queries kept; the oldest entry makes room for a new one
+const CANDIDATE_CAPACITY: usize = 64;
+
+/// What the fused candidates of a hybrid search depend on besides the index
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CandidateKey {
+    pub query: String,
+    pub lang: QueryLanguage,
+    /// Collections searched, in search order
+    pub collections: Vec<String>,
+    pub embedder: Option<String>,
+    pub reranker: Option<String>,
+}
+
+/// Fused candidates in fusion order, with the rerank score of each once known
+#[derive(Debug, Clone, Default)]
+pub struct Candidates {
+    pub results: Vec<SearchResult>,
+    pub scores: Vec<Option<f32>>,
+    /// Stages that failed while gathering the candidates, reported again
+    /// by every request the cached candidates serve
+    pub degraded: Vec<StageFailure>,
+}
+
+impl Candidates {
+    pub fn new(results: Vec<SearchResult>, degraded: Vec<StageFailure>) -> Self {
+        let scores = vec![None; results.len()];
+        Self { results, scores, degraded }
+    }
+
+    /// Positions in `start..end` the reranker has not scored yet
+    pub fn unscored(&self, start: usize, end: usize) -> Vec<usize> {
+        (start..end).filter(|&i| self.scores[i].is_none()).collect()
+    }
+
+    /// Candidates `start..end` with their rerank scores, best first
+    ///
+    /// Every candidate in the window must be scored. Ties keep fusion order.
+    pub fn reranked_window(&self, start: usize, end: usize) -> Vec<SearchResult> {
+        let mut window: Vec<SearchResult> = (start..end)
+            .map(|i| {
+                let score = self.scores[i].expect("window is scored");
+                let mut doc = self.results[i].clone();
+                doc.score = score;
+                if let Some(components) = doc.score_components.as_mut() {
+                    components.rerank_score = Some(score);
+                }
+                doc
+            })
+            .collect();
+        window.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        window
+    }
+}
+
+/// How one hybrid search used the candidate cache, shown by `--explain`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CandidateCacheUse {
+    /// The fused candidates came from the cache
+    pub hit: bool,
+    /// Rerank scores taken from the cache
+    pub reused_scores: usize,
+    /// Candidates the reranker scored for this request
+    pub reranked: usize,
+    /// Lookups that found usable candidates since the store was opened
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl CandidateCacheUse {
+    /// Human-readable line for CLI output
+    pub fn render(&self) -> String {
+        format!(
+            "Candidate cache: {} ({} rerank scores reused, {} reranked; {} hits, {} misses)\n",
+            if self.hit { "hit" } else { "miss" },
+            self.reused_scores,
+            self.reranked,
+            self.hits,
+            self.misses
+        )
+    }
+}
+
+struct CacheEntry {
+    generation: String,
+    stored_at: Instant,
+    candidates: Candidates,
+}
+
+/// Candidates of recent hybrid searches, see the module docs
+#[derive(Default)]
+pub struct CandidateCache {
+    entries: Mutex<HashMap<CandidateKey, CacheEntry>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl CandidateCache {
+    /// Candidates cached for `key` while the index is at `generation`
+    pub fn get(&self, key: &CandidateKey, generation: &str) -> Option<Candidates> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let fresh = entries
+            .get(key)
+            .is_some_and(|entry| entry.generation == generation && entry.stored_at.elapsed() < CANDIDATE_TTL);
+        if fresh {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            entries.get(key).map(|entry| entry.candidates.clone())
+        } else {
+            entries.remove(key);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            None
+        }
+    }
+
+    /// Keep `candidates` for `key`; an update keeps the entry's original age
+    pub fn put(&self, key: CandidateKey, generation: String, candidates: Candidates) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let stored_at = match entries.get(&key) {
+            Some(entry) if entry.generation == generation => entry.stored_at,
+            _ => Instant::now(),
+        };
+        entries.retain(|_, entry| entry.stored_at.elapsed() < CANDIDATE_TTL);
+        if entries.len() >= CANDIDATE_CAPACITY && !entries.contains_key(&key) {
+            if let Some(oldest) = entries.iter().min_by_key(|(_, entry)| entry.stored_at).map(|(key, _)| key.clone()) {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, CacheEntry { generation, stored_at, candidates });
+    }
+
+    /// Lookups that hit and missed so far
+    pub fn stats(&self) -> (usize, usize) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+}
+
+impl Store {
+    /// Candidates of recent hybrid searches
+    pub fn candidate_cache(&self) -> &CandidateCache {
+        &self.candidate_cache
+    }
+
+    /// Fingerprint of the searched collections' documents and vectors
+    ///
+    /// Changes whenever a document is added, changed, deactivated or
+    /// embedded, whichever process wrote it.
+    pub fn index_generation(&self, collections: &[String]) -> Result<String> {
+        let mut generation = String::new();
+        for collection in collections {
+            let fingerprint: String = self.with_connection(collection, |conn| {
+                Ok(conn.query_row(
+                    "SELECT COUNT(*) || ':' || COALESCE(SUM(active), 0) || ':' || COALESCE(MAX(id), 0)
+                            || ':' || COALESCE(MAX(modified_at), '')
+                            || ':' || (SELECT COUNT(*) || ':' || COALESCE(MAX(embedded_at), '') FROM content_vectors)
+                     FROM documents WHERE collection = ?",
+                    [collection],
+                    |row| row.get(0),
+                )?)
+            })?;
+            generation.push_str(&format!("{}={};", collection, fingerprint));
+        }
+        Ok(generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(query: &str) -> CandidateKey {
+        CandidateKey {
+            query: query.to_string(),
+            lang: QueryLanguage::English,
+            collections: vec!["docs".to_string()],
+            embedder: None,
+            reranker: Some("mock".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_cache_hits_only_at_same_generation() {
+        let cache = CandidateCache::default();
+        assert!(cache.get(&key("walrus"), "g1").is_none());
+
+        let mut candidates = Candidates::new(Vec::new(), Vec::new());
+        candidates.scores.push(Some(0.5));
+        cache.put(key("walrus"), "g1".to_string(), candidates);
+        assert_eq!(cache.get(&key("walrus"), "g1").unwrap().scores, vec![Some(0.5)]);
+        assert!(cache.get(&key("narwhal"), "g1").is_none());
+        // A changed index drops the entry
+        assert!(cache.get(&key("walrus"), "g2").is_none());
+        assert!(cache.get(&key("walrus"), "g1").is_none());
+        assert_eq!(cache.stats(), (1, 4));
+    }
+
+    #[test]
+    fn test_cache_evicts_oldest_when_full() {
+        let cache = CandidateCache::default();
+        for i in 0..=CANDIDATE_CAPACITY {
+            cache.put(key(&i.to_string()), "g".to_string(), Candidates::default());
+        }
+        assert_eq!(cache.entries.lock().unwrap().len(), CANDIDATE_CAPACITY);
+        assert!(cache.get(&key(&CANDIDATE_CAPACITY.to_string()), "g").is_some());
+    }
+}
@@ -1,7 +1,7 @@
-/// Document chunking for semantic search.
-///
-/// Splits large documents into overlapping chunks so each chunk's embedding
-/// captures focused semantics rather than a diluted average of the whole document.
+//! Document chunking for semantic search.
+//!
+//! Splits large documents into overlapping chunks so each chunk's embedding
+//! captures focused semantics rather than a diluted average of the whole document.
 
 /// A single chunk of a document.
 #[derive(Debug, Clone)]
@@ -10,6 +10,8 @@ pub struct Chunk {
     pub seq: usize,
     /// Character offset in the original document
     pub pos: usize,
+    /// 1-based line of the original document the chunk starts on
+    pub line: usize,
     /// Chunk text content
     pub text: String,
 }
@@ -19,6 +21,9 @@ pub const DEFAULT_CHUNK_SIZE: usize = 3200;
 /// Default overlap in characters (~120 tokens)
 pub const DEFAULT_OVERLAP: usize = 480;
 
+/// Line prefix marking a soft split point, such as a notebook cell boundary
+pub const SECTION_MARKER: &str = "%% ";
+
 /// Find the nearest UTF-8 character boundary at or before `pos`
 fn prev_char_boundary(text: &str, mut pos: usize) -> usize {
     while pos > 0 && !text.is_char_boundary(pos) {
@@ -30,7 +35,8 @@ fn prev_char_boundary(text: &str, mut pos: usize) -> usize {
 /// Split a document into overlapping chunks.
 ///
 /// - Short documents (< chunk_size * 1.2) return a single chunk.
-/// - Splits prefer paragraph boundaries (`\n\n`), then sentence boundaries (`. `),
+/// - Splits prefer section markers (a line starting with [`SECTION_MARKER`]),
+///   then paragraph boundaries (`\n\n`), then sentence boundaries (`. `),
 ///   then word boundaries.
 pub fn chunk_document(text: &str, chunk_size: usize, overlap: usize) -> Vec<Chunk> {
     if text.is_empty() {
@@ -42,14 +48,21 @@ pub fn chunk_document(text: &str, chunk_size: usize, overlap: usize) -> Vec<Chun
         return vec![Chunk {
             seq: 0,
             pos: 0,
+            line: 1,
             text: text.to_string(),
         }];
     }
 
     let mut chunks = Vec::new();
     let mut start = 0;
+    // Line `start` is on; chunks only move forward, so count newlines as they pass
+    let mut line = 1;
+    let mut line_pos = 0;
 
     while start < text.len() {
+        line += text[line_pos..start].matches('\n').count();
+        line_pos = start;
+
         let end_raw = (start + chunk_size).min(text.len());
         // Ensure we're at a UTF-8 char boundary
         let end = prev_char_boundary(text, end_raw);
@@ -59,6 +72,7 @@ pub fn chunk_document(text: &str, chunk_size: usize, overlap: usize) -> Vec<Chun
             chunks.push(Chunk {
                 seq: chunks.len(),
                 pos: start,
+                line,
                 text: text[start..].to_string(),
             });
             break;
@@ -72,6 +86,7 @@ pub fn chunk_document(text: &str, chunk_size: usize, overlap: usize) -> Vec<Chun
         chunks.push(Chunk {
             seq: chunks.len(),
             pos: start,
+            line,
             text: text[start..split].to_string(),
         });
 
@@ -109,6 +124,14 @@ fn find_split_point(text: &str, target: usize, min_pos: usize) -> usize {
     // Safe to slice now that both boundaries are valid
     let region = &text[search_start..target];
 
+    // 0. Section marker, split before the marker line
+    if let Some(pos) = region.rfind(&format!("\n{}", SECTION_MARKER)) {
+        let split = search_start + pos + 1;
+        if split > min_pos {
+            return split;
+        }
+    }
+
     // 1. Paragraph boundary (\n\n)
     if let Some(pos) = region.rfind("\n\n") {
         let split = search_start + pos + 2; // after the double newline
@@ -160,9 +183,28 @@ mod tests {
         assert_eq!(chunks.len(), 1);
         assert_eq!(chunks[0].seq, 0);
         assert_eq!(chunks[0].pos, 0);
+        assert_eq!(chunks[0].line, 1);
         assert_eq!(chunks[0].text, text);
     }
 
+    #[test]
+    fn test_chunk_lines_match_where_text_starts() {
+        let text: String = (1..=400)
+            .map(|n| format!("Paragraph {} explains one more idea in a sentence or two.\n\n", n))
+            .collect();
+        let chunks = chunk_document(&text, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP);
+        assert!(chunks.len() > 3);
+
+        let lines: Vec<&str> = text.lines().collect();
+        for chunk in &chunks {
+            assert_eq!(chunk.line, text[..chunk.pos].matches('\n').count() + 1);
+            // The chunk's first line is a suffix of the document line it starts on
+            let first = chunk.text.lines().next().unwrap();
+            assert!(lines[chunk.line - 1].ends_with(first), "{:?} vs {:?}", first, lines[chunk.line - 1]);
+        }
+        assert!(chunks.windows(2).all(|pair| pair[0].line < pair[1].line));
+    }
+
     #[test]
     fn test_chunk_long_document() {
         // Create a document that's ~3x chunk_size
@@ -203,7 +245,7 @@ mod tests {
 
             // At least some text should be shared
             assert!(
-                overlap_region_end.len() > 0 && overlap_region_start.len() > 0,
+                !overlap_region_end.is_empty() && !overlap_region_start.is_empty(),
                 "Overlap regions should not be empty"
             );
         }
@@ -231,6 +273,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_chunk_prefers_section_marker() {
+        // A cell boundary early in the search window beats a later paragraph break
+        let text = format!(
+            "{}\n%% [code] In [1]\n{}\n\n{}",
+            "a".repeat(2900),
+            "b".repeat(200),
+            "c".repeat(2000)
+        );
+
+        let chunks = chunk_document(&text, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP);
+        assert!(chunks.len() >= 2);
+        assert_eq!(chunks[0].text.len(), 2901);
+        assert!(chunks[1].text.contains("%% [code] In [1]"));
+    }
+
     #[test]
     fn test_chunk_positions() {
         let sentence = "Hello world this is a test sentence for chunking. ";
@@ -396,7 +454,7 @@ mod tests {
         // At threshold — single chunk (< threshold means single)
         let text_at = "x".repeat(threshold);
         let chunks_at = chunk_document(&text_at, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP);
-        assert!(chunks_at.len() >= 1, "At threshold should produce chunks");
+        assert!(!chunks_at.is_empty(), "At threshold should produce chunks");
 
         // Just over threshold — multiple chunks
         let text_over = "x".repeat(threshold + 1);
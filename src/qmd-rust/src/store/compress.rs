@@ -0,0 +1,195 @@
+//! Zstd-compressed document text.
+//!
+//! Collections with `compress_content` store each body zstd-compressed in
+//! `content.packed`, with `content.codec` naming the codec and `content.doc`
+//! left empty. Rows without a codec keep plain text in `doc`, so bodies
+//! indexed before compression was turned on keep working until `qmd cleanup`
+//! compresses them.
+//!
+//! SQLite cannot decompress by itself, so every store connection registers
+//! `qmd_doc(doc, codec, packed)`. The FTS view and every query reading a body
+//! go through it, which keeps the external-content FTS index (the writes in
+//! [`fts`](super::fts), rebuilds, `snippet()`) working on compressed rows.
+//! Other connections lack the function: they can write every table, but
+//! reading `documents_fts_view` or a `documents_fts` column fails with
+//! "no such function: qmd_doc".
+
+use super::Store;
+use anyhow::Result;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// Codec recorded for zstd-compressed bodies
+pub const CODEC_ZSTD: &str = "zstd";
+
+/// Fast level that still gets most of the gain on prose
+const ZSTD_LEVEL: i32 = 3;
+
+/// Compress a document body for the content table
+pub fn pack(text: &str) -> Result<Vec<u8>> {
+    Ok(zstd::encode_all(text.as_bytes(), ZSTD_LEVEL)?)
+}
+
+/// Text of a content row, decompressing it when `codec` is set
+pub fn unpack(doc: String, codec: Option<&str>, packed: Option<&[u8]>) -> Result<String> {
+    match codec {
+        None => Ok(doc),
+        Some(CODEC_ZSTD) => {
+            let bytes = zstd::decode_all(packed.unwrap_or_default())?;
+            Ok(String::from_utf8(bytes)?)
+        }
+        Some(other) => anyhow::bail!("Unknown content codec: {}", other),
+    }
+}
+
+/// Register `qmd_doc(doc, codec, packed)`, which returns a content row's text
+pub(super) fn register_functions(conn: &Connection) -> Result<()> {
+    conn.create_scalar_function(
+        "qmd_doc",
+        3,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let doc: Option<String> = ctx.get(0)?;
+            let codec: Option<String> = ctx.get(1)?;
+            let packed: Option<Vec<u8>> = ctx.get(2)?;
+            unpack(doc.unwrap_or_default(), codec.as_deref(), packed.as_deref())
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))
+        },
+    )?;
+    Ok(())
+}
+
+/// Store `text` under `hash` unless a row already holds it, compressed if asked
+///
+/// An existing row is left alone: replacing it would cascade-delete the
+/// documents sharing the hash.
+pub(super) fn insert_content(conn: &Connection, hash: &str, text: &str, compress: bool, created_at: &str) -> Result<()> {
+    if compress {
+        conn.execute(
+            "INSERT OR IGNORE INTO content (hash, doc, codec, packed, created_at) VALUES (?, '', ?, ?, ?)",
+            rusqlite::params![hash, CODEC_ZSTD, pack(text)?, created_at],
+        )?;
+    } else {
+        conn.execute(
+            "INSERT OR IGNORE INTO content (hash, doc, created_at) VALUES (?, ?, ?)",
+            [hash, text, created_at],
+        )?;
+    }
+    Ok(())
+}
+
+/// Stored size of a collection's document text
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CompressionStats {
+    /// Distinct bodies stored compressed
+    pub compressed_documents: usize,
+    /// Distinct bodies stored as plain text
+    pub plain_documents: usize,
+    /// Text size of the compressed bodies
+    pub original_bytes: u64,
+    /// What the compressed bodies take in the database
+    pub stored_bytes: u64,
+    /// `original_bytes / stored_bytes`; 1 when nothing is compressed
+    pub ratio: f64,
+}
+
+impl Store {
+    /// Whether the collection stores new bodies compressed
+    pub fn compresses_content(&self, collection: &str) -> bool {
+        self.config
+            .collections
+            .iter()
+            .any(|c| c.name == collection && c.compress_content)
+    }
+
+    /// How the collection's active documents' bodies are stored
+    pub fn compression_stats(&self, collection: &str) -> Result<CompressionStats> {
+        self.with_connection(collection, |conn| {
+            let mut stats = CompressionStats::default();
+            let mut stmt = conn.prepare(
+                "SELECT c.codec IS NOT NULL, COUNT(*),
+                        COALESCE(SUM(LENGTH(CAST(qmd_doc(c.doc, c.codec, c.packed) AS BLOB))), 0),
+                        COALESCE(SUM(LENGTH(c.packed)), 0)
+                 FROM content c
+                 WHERE c.hash IN (SELECT hash FROM documents WHERE collection = ? AND active = 1)
+                 GROUP BY c.codec IS NOT NULL",
+            )?;
+            let rows = stmt.query_map([collection], |row| {
+                Ok((row.get::<_, bool>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?))
+            })?;
+            for row in rows {
+                match row? {
+                    (true, count, original, stored) => {
+                        stats.compressed_documents = count as usize;
+                        stats.original_bytes = original as u64;
+                        stats.stored_bytes = stored as u64;
+                    }
+                    (false, count, _, _) => stats.plain_documents = count as usize,
+                }
+            }
+            stats.ratio = match stats.stored_bytes {
+                0 => 1.0,
+                stored => stats.original_bytes as f64 / stored as f64,
+            };
+            Ok(stats)
+        })
+    }
+
+    /// Compress the plain bodies of a compressing collection's documents
+    ///
+    /// Returns how many bodies were compressed. The FTS index is untouched:
+    /// `qmd_doc` reads the same text back. With `dry_run` only counts them.
+    pub fn compress_existing_content(&self, collection: &str, dry_run: bool) -> Result<usize> {
+        if !self.compresses_content(collection) {
+            return Ok(0);
+        }
+        let mut conn = self.get_connection(collection)?;
+        let tx = conn.transaction()?;
+        let plain: Vec<(String, String)> = tx
+            .prepare(
+                "SELECT hash, doc FROM content
+                 WHERE codec IS NULL AND hash IN (SELECT hash FROM documents WHERE collection = ?)",
+            )?
+            .query_map([collection], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        if dry_run {
+            return Ok(plain.len());
+        }
+        for (hash, doc) in &plain {
+            tx.execute(
+                "UPDATE content SET doc = '', codec = ?, packed = ? WHERE hash = ?",
+                rusqlite::params![CODEC_ZSTD, pack(doc)?, hash],
+            )?;
+        }
+        tx.commit()?;
+        Ok(plain.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_round_trips() {
+        let text = "The walrus naps on the ice. ".repeat(100);
+        let packed = pack(&text).unwrap();
+        assert!(packed.len() < text.len() / 10);
+        assert_eq!(unpack(String::new(), Some(CODEC_ZSTD), Some(&packed)).unwrap(), text);
+        assert_eq!(unpack("plain".to_string(), None, None).unwrap(), "plain");
+        assert!(unpack(String::new(), Some("lz4"), Some(&packed)).is_err());
+    }
+
+    #[test]
+    fn test_qmd_doc_reads_plain_and_compressed_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_functions(&conn).unwrap();
+        let read = |doc: &str, codec: Option<&str>, packed: Option<Vec<u8>>| -> String {
+            conn.query_row("SELECT qmd_doc(?, ?, ?)", rusqlite::params![doc, codec, packed], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(read("plain text", None, None), "plain text");
+        assert_eq!(read("", Some(CODEC_ZSTD), Some(pack("packed text").unwrap())), "packed text");
+    }
+}
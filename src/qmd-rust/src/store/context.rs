@@ -0,0 +1,346 @@
+//! Generated descriptions for directories (path contexts).
+//!
+//! `qmd context generate` groups a collection's active documents by directory,
+//! samples their titles and opening lines, and asks the generation model for a
+//! one-sentence description of each directory. Without a model, or when it
+//! fails, a summary of recurring title words is stored instead. Descriptions
+//! written by hand are kept unless overwriting is requested. Documents at the
+//! collection root are described by the collection's own context.
+
+use super::stopwords::ENGLISH;
+use super::Store;
+use crate::llm::Router;
+use anyhow::Result;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Titles and excerpts shown to the model per directory
+const SAMPLE_TITLES: usize = 8;
+const SAMPLE_SNIPPETS: usize = 3;
+const SNIPPET_CHARS: usize = 200;
+
+/// Title words listed in a rule-based summary
+const SUMMARY_WORDS: usize = 5;
+
+/// Who wrote a path context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextSource {
+    /// `qmd context add`
+    Manual,
+    /// `qmd context generate`
+    Generated,
+}
+
+impl ContextSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Manual => "manual",
+            Self::Generated => "generated",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "generated" => Self::Generated,
+            _ => Self::Manual,
+        }
+    }
+}
+
+/// Documents under one directory, with the samples shown to the model
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryGroup {
+    /// Directory relative to the collection root
+    pub path: String,
+    /// Active documents in the directory and its subdirectories
+    pub documents: usize,
+    pub titles: Vec<String>,
+    pub snippets: Vec<String>,
+}
+
+/// How a directory's description was produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerateMethod {
+    /// Written by the generation model
+    Llm,
+    /// Rule-based summary
+    Rules,
+    /// Kept the hand-written description
+    Skipped,
+}
+
+/// One directory handled by `Store::generate_path_contexts`
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedContext {
+    pub collection: String,
+    pub path: String,
+    pub documents: usize,
+    pub description: String,
+    pub method: GenerateMethod,
+}
+
+/// Active documents of a collection covered by some description
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ContextCoverage {
+    pub covered: usize,
+    pub total: usize,
+}
+
+impl ContextCoverage {
+    /// Covered share in percent; an empty collection counts as covered
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.covered as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+/// Directories containing `path`, at most `depth` levels deep, outermost first
+fn ancestor_dirs(path: &str, depth: usize) -> Vec<String> {
+    let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let levels = parts.len().saturating_sub(1).min(depth);
+    (1..=levels).map(|n| parts[..n].join("/")).collect()
+}
+
+/// Opening text of a document, skipping headings and front matter fences
+fn snippet(doc: &str) -> Option<String> {
+    let text = doc
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && *line != "---")
+        .take(3)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if text.is_empty() {
+        return None;
+    }
+    Some(match text.char_indices().nth(SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    })
+}
+
+/// Prompt asking the model for a one-sentence description of `group`
+pub fn context_prompt(collection: &str, group: &DirectoryGroup) -> String {
+    let mut prompt = format!(
+        "Write a one-sentence description of the \"{}\" directory in the \"{}\" document collection, \
+         for a search index. It contains {} documents.\n",
+        group.path, collection, group.documents
+    );
+    if !group.titles.is_empty() {
+        prompt.push_str("\nSample titles:\n");
+        for title in &group.titles {
+            prompt.push_str(&format!("- {}\n", title));
+        }
+    }
+    if !group.snippets.is_empty() {
+        prompt.push_str("\nSample excerpts:\n");
+        for snippet in &group.snippets {
+            prompt.push_str(&format!("- {}\n", snippet));
+        }
+    }
+    prompt.push_str("\nReply with the sentence only.");
+    prompt
+}
+
+/// Description from the document count and the most frequent title words
+pub fn rule_based_summary(group: &DirectoryGroup) -> String {
+    let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+    let mut seen = 0;
+    for title in &group.titles {
+        for word in title.split(|c: char| !c.is_alphanumeric()) {
+            let word = word.to_lowercase();
+            if word.chars().count() < 3 || ENGLISH.contains(&word.as_str()) || word.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            let entry = counts.entry(word).or_insert((0, seen));
+            entry.0 += 1;
+            seen += 1;
+        }
+    }
+    // Most frequent first, ties in order of appearance
+    let mut words: Vec<(String, (usize, usize))> = counts.into_iter().collect();
+    words.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.1 .1.cmp(&b.1 .1)));
+    let topics: Vec<String> = words.into_iter().take(SUMMARY_WORDS).map(|(word, _)| word).collect();
+
+    let noun = if group.documents == 1 { "document" } else { "documents" };
+    if topics.is_empty() {
+        format!("{} {}", group.documents, noun)
+    } else {
+        format!("{} {} about {}", group.documents, noun, topics.join(", "))
+    }
+}
+
+/// First line of a model reply, without surrounding quotes
+fn clean_reply(reply: &str) -> Option<String> {
+    let line = reply.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line.trim_matches(|c| c == '"' || c == '\'' || c == '`').trim();
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+impl Store {
+    /// Who wrote the context stored for `path`, if any
+    pub fn path_context_source(&self, collection: &str, path: &str) -> Result<Option<ContextSource>> {
+        let conn = self.get_connection(collection)?;
+        let source: Option<String> = conn
+            .query_row("SELECT source FROM path_contexts WHERE path = ?", [path], |row| row.get(0))
+            .optional()?;
+        Ok(source.as_deref().map(ContextSource::parse))
+    }
+
+    /// Active documents grouped by directory, down to `depth` levels
+    pub fn directory_groups(&self, collection: &str, depth: usize) -> Result<Vec<DirectoryGroup>> {
+        let conn = self.get_connection(collection)?;
+        let mut stmt = conn.prepare(
+            "SELECT d.path, d.title, qmd_doc(c.doc, c.codec, c.packed) FROM documents d
+             JOIN content c ON c.hash = d.hash
+             WHERE d.collection = ? AND d.active = 1
+             ORDER BY d.path",
+        )?;
+        let rows = stmt.query_map([collection], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?;
+
+        let mut groups: BTreeMap<String, DirectoryGroup> = BTreeMap::new();
+        for row in rows {
+            let (path, title, doc) = row?;
+            for dir in ancestor_dirs(&path, depth) {
+                let group = groups.entry(dir.clone()).or_insert_with(|| DirectoryGroup {
+                    path: dir,
+                    ..DirectoryGroup::default()
+                });
+                group.documents += 1;
+                if group.titles.len() < SAMPLE_TITLES && !title.is_empty() {
+                    group.titles.push(title.clone());
+                }
+                if group.snippets.len() < SAMPLE_SNIPPETS {
+                    group.snippets.extend(snippet(&doc));
+                }
+            }
+        }
+        Ok(groups.into_values().collect())
+    }
+
+    /// Describe every directory of `collection` down to `depth` levels
+    ///
+    /// Uses the generation model when the router has one and falls back to a
+    /// rule-based summary. Hand-written descriptions are kept unless `overwrite`.
+    pub async fn generate_path_contexts(
+        &self,
+        collection: &str,
+        depth: usize,
+        overwrite: bool,
+        llm: &Router,
+    ) -> Result<Vec<GeneratedContext>> {
+        let mut generated = Vec::new();
+        for group in self.directory_groups(collection, depth)? {
+            if !overwrite && self.path_context_source(collection, &group.path)? == Some(ContextSource::Manual) {
+                let description = self
+                    .get_path_context(collection, &group.path)?
+                    .map(|(description, _)| description)
+                    .unwrap_or_default();
+                generated.push(GeneratedContext {
+                    collection: collection.to_string(),
+                    path: group.path,
+                    documents: group.documents,
+                    description,
+                    method: GenerateMethod::Skipped,
+                });
+                continue;
+            }
+
+            let mut reply = None;
+            if llm.has_generator() {
+                match llm.generate(&context_prompt(collection, &group)).await {
+                    Ok(text) => reply = clean_reply(&text),
+                    Err(e) => log::warn!("Context generation failed for {}/{}: {}", collection, group.path, e),
+                }
+            }
+            let (description, method) = match reply {
+                Some(text) => (text, GenerateMethod::Llm),
+                None => (rule_based_summary(&group), GenerateMethod::Rules),
+            };
+
+            self.set_path_context_from(collection, &group.path, &description, ContextSource::Generated)?;
+            generated.push(GeneratedContext {
+                collection: collection.to_string(),
+                path: group.path,
+                documents: group.documents,
+                description,
+                method,
+            });
+        }
+        Ok(generated)
+    }
+
+    /// How many active documents sit under a described directory
+    ///
+    /// `collection_described` covers every document, as does a context for the
+    /// collection root.
+    pub fn context_coverage(&self, collection: &str, collection_described: bool) -> Result<ContextCoverage> {
+        let conn = self.get_connection(collection)?;
+        let paths: Vec<String> = conn
+            .prepare("SELECT path FROM documents WHERE collection = ? AND active = 1")?
+            .query_map([collection], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let described: HashSet<String> = self
+            .list_path_contexts(collection)?
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        let all = collection_described || described.contains("");
+        let covered = paths
+            .iter()
+            .filter(|path| all || ancestor_dirs(path, usize::MAX).iter().any(|dir| described.contains(dir)))
+            .count();
+        Ok(ContextCoverage {
+            covered,
+            total: paths.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ancestor_dirs_respects_depth() {
+        assert_eq!(ancestor_dirs("a/b/c.md", 1), vec!["a"]);
+        assert_eq!(ancestor_dirs("a/b/c.md", 5), vec!["a", "a/b"]);
+        assert!(ancestor_dirs("root.md", 3).is_empty());
+    }
+
+    #[test]
+    fn test_rule_based_summary_counts_title_words() {
+        let group = DirectoryGroup {
+            path: "rust".to_string(),
+            documents: 3,
+            titles: vec![
+                "Rust Ownership".to_string(),
+                "Ownership and Borrowing".to_string(),
+                "The Rust Book".to_string(),
+            ],
+            snippets: vec![],
+        };
+        assert_eq!(rule_based_summary(&group), "3 documents about rust, ownership, borrowing, book");
+
+        let empty = DirectoryGroup {
+            documents: 1,
+            ..DirectoryGroup::default()
+        };
+        assert_eq!(rule_based_summary(&empty), "1 document");
+    }
+
+    #[test]
+    fn test_clean_reply_takes_first_line() {
+        assert_eq!(clean_reply("\n\"Rust notes.\"\nMore"), Some("Rust notes.".to_string()));
+        assert_eq!(clean_reply("  \n "), None);
+    }
+}
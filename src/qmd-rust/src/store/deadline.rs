@@ -0,0 +1,158 @@
+//! Request-scoped time budget for the search pipeline.
+//!
+//! `--timeout` (or `timeout_ms` over MCP/HTTP) starts a `Deadline` when the
+//! request arrives. Each pipeline stage checks the remaining budget before it
+//! starts, and the slow async stages (embedding, reranking) are cut off when it
+//! runs out. BM25 retrieval of the original query always runs, so an expired
+//! budget degrades to BM25-only results flagged as partial instead of an error.
+//! Optional stages that fail outright degrade the same way and are reported
+//! with their error.
+
+use super::candidates::CandidateCacheUse;
+use super::timings::StageTimings;
+use super::SearchResult;
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Pipeline stage that can be skipped when the budget runs out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchStage {
+    /// Query expansion, or BM25 over the expanded variants
+    Expansion,
+    /// Query embedding and vector retrieval
+    Vector,
+    /// LLM reranking of the fused candidates
+    Rerank,
+}
+
+impl fmt::Display for SearchStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Expansion => write!(f, "expansion"),
+            Self::Vector => write!(f, "vector"),
+            Self::Rerank => write!(f, "rerank"),
+        }
+    }
+}
+
+/// Time budget shared by every stage of one request
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    start: Instant,
+    budget: Option<Duration>,
+}
+
+impl Deadline {
+    /// Budget of `timeout_ms` from now; `None` never expires
+    pub fn new(timeout_ms: Option<u64>) -> Self {
+        Self {
+            start: Instant::now(),
+            budget: timeout_ms.map(Duration::from_millis),
+        }
+    }
+
+    /// A deadline that never expires
+    pub fn unlimited() -> Self {
+        Self::new(None)
+    }
+
+    /// Time left, or `None` without a budget
+    pub fn remaining(&self) -> Option<Duration> {
+        self.budget.map(|budget| budget.saturating_sub(self.start.elapsed()))
+    }
+
+    /// Whether the budget is used up
+    pub fn expired(&self) -> bool {
+        self.remaining().is_some_and(|left| left.is_zero())
+    }
+
+    /// Run `fut` within the remaining budget; `None` if it ran out first
+    pub async fn run<F: Future>(&self, fut: F) -> Option<F::Output> {
+        match self.remaining() {
+            None => Some(fut.await),
+            Some(left) if left.is_zero() => None,
+            Some(left) => tokio::time::timeout(left, fut).await.ok(),
+        }
+    }
+}
+
+/// Optional stage that failed, leaving the results without its contribution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StageFailure {
+    pub stage: SearchStage,
+    pub error: String,
+}
+
+/// Results of a deadline-bound search and the stages it had to skip
+#[derive(Debug, Clone, Default)]
+pub struct SearchOutcome {
+    pub results: Vec<SearchResult>,
+    pub skipped: Vec<SearchStage>,
+    /// Stages that failed instead of running out of time
+    pub degraded: Vec<StageFailure>,
+    /// Per-stage timings, for pipelines that time their stages
+    pub timings: Option<StageTimings>,
+    /// How the candidate cache was used, for pipelines that have one
+    pub cache: Option<CandidateCacheUse>,
+}
+
+impl SearchOutcome {
+    /// Whether a stage was skipped or failed, so the results are incomplete
+    pub fn is_partial(&self) -> bool {
+        !self.skipped.is_empty() || !self.degraded.is_empty()
+    }
+
+    /// Record a failed stage once, keeping the first error
+    pub fn fail(&mut self, stage: SearchStage, error: impl fmt::Display) {
+        if !self.degraded.iter().any(|f| f.stage == stage) {
+            log::warn!("Search {} stage failed, continuing without it: {}", stage, error);
+            self.degraded.push(StageFailure {
+                stage,
+                error: error.to_string(),
+            });
+        }
+    }
+
+    /// Record a skipped stage once
+    pub fn skip(&mut self, stage: SearchStage) {
+        if !self.skipped.contains(&stage) {
+            log::warn!("Search budget exhausted, skipping {} stage", stage);
+            self.skipped.push(stage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_deadline_cuts_off_slow_futures() {
+        let deadline = Deadline::new(Some(20));
+        assert!(!deadline.expired());
+        let slow = deadline.run(tokio::time::sleep(Duration::from_secs(5))).await;
+        assert!(slow.is_none());
+        assert!(deadline.expired());
+        // Once expired, nothing else starts
+        assert_eq!(deadline.run(async { 1 }).await, None);
+
+        let unlimited = Deadline::unlimited();
+        assert_eq!(unlimited.remaining(), None);
+        assert_eq!(unlimited.run(async { 1 }).await, Some(1));
+    }
+
+    #[test]
+    fn test_outcome_records_each_stage_once() {
+        let mut outcome = SearchOutcome::default();
+        assert!(!outcome.is_partial());
+        outcome.skip(SearchStage::Vector);
+        outcome.skip(SearchStage::Vector);
+        outcome.skip(SearchStage::Rerank);
+        assert!(outcome.is_partial());
+        assert_eq!(outcome.skipped, vec![SearchStage::Vector, SearchStage::Rerank]);
+        assert_eq!(serde_json::to_value(&outcome.skipped).unwrap(), serde_json::json!(["vector", "rerank"]));
+    }
+}
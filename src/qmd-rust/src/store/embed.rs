@@ -0,0 +1,372 @@
+//! Chunking documents, embedding the chunks and storing their vectors
+//!
+//! Vectors go to `content_vectors` (chunk metadata) and `vectors_vec`
+//! (sqlite-vec, keyed `hash_seq`), and to Qdrant when that backend is on.
+
+use super::chunker::{chunk_document, Chunk, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP};
+use super::{vector_dimension_error, vector_table_dimension, Store};
+use crate::llm::{EmbeddingResult, Router};
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use log::info;
+use rusqlite::{params, Connection, Transaction, TransactionBehavior};
+use serde::Serialize;
+use std::future::Future;
+use std::time::Instant;
+
+/// What one embed run stored
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct EmbedStats {
+    pub documents_embedded: usize,
+    pub chunks_embedded: usize,
+    /// Model that produced the vectors; `None` when nothing was embedded
+    pub model: Option<String>,
+}
+
+impl Store {
+    /// Chunk and embed the active documents of `collection` that have no
+    /// vectors from the router's embedding model yet, or all of them with
+    /// `force`
+    ///
+    /// Each document's vectors are written in one transaction, so a failed
+    /// run leaves the documents it finished embedded and the rest pending.
+    /// Without an embedder nothing is embedded.
+    pub async fn embed_collection(&self, collection: &str, llm: &Router, force: bool) -> Result<EmbedStats> {
+        if !llm.has_embedder() {
+            log::warn!("No embedder available, skipping embedding");
+            return Ok(EmbedStats::default());
+        }
+        let job = EmbedJob::pending(self, collection, llm.embedder_model().as_deref(), force)?;
+        info!("Found {} documents to embed in collection: {}", job.documents(), collection);
+        job.run_with_stats(llm, |_| {}).await
+    }
+
+    /// Content hashes of the active documents in `collection` without
+    /// vectors from `model`; with no model, those without any vectors
+    pub fn pending_embeddings(&self, collection: &str, model: Option<&str>) -> Result<Vec<String>> {
+        self.with_connection(collection, |conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT DISTINCT d.hash FROM documents d WHERE {} ORDER BY d.hash",
+                PENDING
+            ))?;
+            let hashes = stmt
+                .query_map(params![collection, model], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(hashes)
+        })
+    }
+}
+
+/// Active documents `d` of collection `?1` still needing vectors from model
+/// `?2` (any model when NULL)
+const PENDING: &str = "d.collection = ?1 AND d.active = 1 AND NOT EXISTS (
+    SELECT 1 FROM content_vectors cv WHERE cv.hash = d.hash AND (?2 IS NULL OR cv.model = ?2))";
+
+/// Documents of `collection` an embed run processes, as `(hash, doc)` pairs
+///
+/// Everything active with `force`, otherwise only content without vectors
+/// from `model`; see [`Store::pending_embeddings`].
+pub(crate) fn pending_documents(
+    conn: &Connection,
+    collection: &str,
+    model: Option<&str>,
+    force: bool,
+) -> Result<Vec<(String, String)>> {
+    let query = |filter: &str| {
+        format!(
+            "SELECT DISTINCT d.hash, qmd_doc(c.doc, c.codec, c.packed) FROM documents d
+             JOIN content c ON c.hash = d.hash
+             WHERE {}",
+            filter
+        )
+    };
+    let doc = |row: &rusqlite::Row| Ok((row.get(0)?, row.get(1)?));
+
+    let docs = if force {
+        conn.prepare(&query("d.collection = ?1 AND d.active = 1"))?
+            .query_map([collection], doc)?
+            .filter_map(|r| r.ok())
+            .collect()
+    } else {
+        conn.prepare(&query(PENDING))?
+            .query_map(params![collection, model], doc)?
+            .filter_map(|r| r.ok())
+            .collect()
+    };
+    Ok(docs)
+}
+
+/// Where an embed run gets its vectors
+///
+/// A router shared behind a mutex is locked for one batch at a time, so
+/// searches embedding their queries get a turn during a long run.
+pub trait EmbedSource: Sync {
+    fn embed_batch<'a>(&'a self, texts: &'a [&'a str]) -> impl Future<Output = Result<EmbeddingResult>> + Send + 'a;
+
+    /// Batches to have in flight at once
+    fn concurrency(&self) -> usize {
+        1
+    }
+}
+
+impl EmbedSource for Router {
+    fn embed_batch<'a>(&'a self, texts: &'a [&'a str]) -> impl Future<Output = Result<EmbeddingResult>> + Send + 'a {
+        self.embed(texts)
+    }
+
+    fn concurrency(&self) -> usize {
+        self.embed_concurrency()
+    }
+}
+
+impl EmbedSource for tokio::sync::Mutex<Router> {
+    async fn embed_batch<'a>(&'a self, texts: &'a [&'a str]) -> Result<EmbeddingResult> {
+        self.lock().await.embed(texts).await
+    }
+}
+
+/// Documents of one collection, chunked and ready to embed
+///
+/// The job owns its connection, so it can run without holding the store.
+/// The connection is only borrowed between awaits: it is not `Sync`, and the
+/// MCP server needs `run` to be `Send`.
+pub struct EmbedJob {
+    pub collection: String,
+    conn: Connection,
+    /// `(hash, doc)` pairs
+    docs: Vec<(String, String)>,
+    /// Every document's chunks, with the index of the document they belong to
+    chunks: Vec<(usize, Chunk)>,
+    chunk_counts: Vec<usize>,
+    /// Also receives each document's vectors when `vector.backend` is `qdrant`
+    #[cfg(feature = "qdrant")]
+    qdrant: Option<std::sync::Arc<crate::store::qdrant_backend::QdrantBackend>>,
+}
+
+impl EmbedJob {
+    /// Chunk `(hash, doc)` pairs of `collection`
+    pub fn new(store: &Store, collection: &str, conn: Connection, docs: Vec<(String, String)>) -> Self {
+        #[cfg(not(feature = "qdrant"))]
+        let _ = store;
+        let mut chunks = Vec::new();
+        let mut chunk_counts = Vec::with_capacity(docs.len());
+        for (index, (_, doc)) in docs.iter().enumerate() {
+            let doc_chunks = chunk_document(doc, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP);
+            chunk_counts.push(doc_chunks.len());
+            chunks.extend(doc_chunks.into_iter().map(|chunk| (index, chunk)));
+        }
+        Self {
+            collection: collection.to_string(),
+            conn,
+            docs,
+            chunks,
+            chunk_counts,
+            #[cfg(feature = "qdrant")]
+            qdrant: store.qdrant_backend(),
+        }
+    }
+
+    /// The documents `qmd embed` would process in `collection` for `model`
+    pub fn pending(store: &Store, collection: &str, model: Option<&str>, force: bool) -> Result<Self> {
+        let conn = store.get_connection(collection)?;
+        let docs = pending_documents(&conn, collection, model, force)?;
+        Ok(Self::new(store, collection, conn, docs))
+    }
+
+    pub fn documents(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Embed every chunk and store the vectors, calling `progress` with the
+    /// chunks embedded so far after each batch; returns the chunk count
+    ///
+    /// Each document's vectors replace whatever an earlier or concurrent run
+    /// stored for it, so embedding the same content twice leaves one set of
+    /// chunk rows.
+    pub async fn run(self, llm: &impl EmbedSource, progress: impl FnMut(usize)) -> Result<usize> {
+        Ok(self.run_with_stats(llm, progress).await?.chunks_embedded)
+    }
+
+    /// [`run`](Self::run), reporting documents and model as well
+    pub async fn run_with_stats(self, llm: &impl EmbedSource, mut progress: impl FnMut(usize)) -> Result<EmbedStats> {
+        // vectors_vec only exists when sqlite-vec is loaded
+        let vec_dimension = vector_table_dimension(&self.conn)?;
+        let has_vec_table = vec_dimension.is_some();
+
+        for (count, (hash, _)) in self.chunk_counts.iter().zip(&self.docs) {
+            if *count == 0 {
+                // Nothing to embed, but vectors of an earlier version go
+                store_document_vectors(&self.conn, has_vec_table, hash, "", &[])?;
+            }
+        }
+
+        info!("Total chunks to embed: {}", self.chunks.len());
+
+        let started = Instant::now();
+        let mut model = None;
+        let mut embedded = 0;
+        // Vectors of the document in progress, written once all its chunks are embedded
+        let mut pending: Vec<(&Chunk, Vec<f32>)> = Vec::new();
+
+        // Process chunks in batches, up to `concurrency` of them at once;
+        // results still arrive in batch order
+        let batch_size = 10;
+        let batch_count = self.chunks.len().div_ceil(batch_size);
+        // Collected up front: a lazily mapped stream is not `Send`
+        let requests: Vec<_> = self
+            .chunks
+            .chunks(batch_size)
+            .enumerate()
+            .map(|(batch_idx, batch)| embed_chunks(llm, batch, batch_idx, batch_count))
+            .collect();
+        let mut batches = stream::iter(requests).buffered(llm.concurrency().max(1));
+
+        while let Some(next) = batches.next().await {
+            let (batch, embedding_result) = next?;
+
+            info!("Generated {} embeddings with model: {}",
+                  embedding_result.embeddings.len(), embedding_result.model);
+            model = Some(embedding_result.model.clone());
+
+            for ((index, chunk), embedding) in batch.iter().zip(embedding_result.embeddings) {
+                // Caught here rather than as a vec0 insert failure
+                if let Some(dimension) = vec_dimension.filter(|d| *d != embedding.len()) {
+                    return Err(vector_dimension_error(dimension, embedding.len()).into());
+                }
+                pending.push((chunk, embedding));
+                if pending.len() == self.chunk_counts[*index] {
+                    let hash = &self.docs[*index].0;
+                    store_document_vectors(&self.conn, has_vec_table, hash, &embedding_result.model, &pending)?;
+                    #[cfg(feature = "qdrant")]
+                    if let Some(ref qdrant) = self.qdrant {
+                        let points = qdrant_points(&self.conn, &self.collection, hash, &pending)?;
+                        qdrant.replace_document(&self.collection, hash, points).await?;
+                    }
+                    info!("Stored {} embeddings for hash: {}", pending.len(), hash);
+                    pending.clear();
+                }
+            }
+            embedded += batch.len();
+            progress(embedded);
+        }
+
+        // Record the throughput for `embed --dry-run` time estimates
+        if let Some(ref model) = model {
+            self.conn.execute(
+                "INSERT INTO embed_runs (model, chunks, seconds, finished_at)
+                 VALUES (?, ?, ?, datetime('now'))",
+                params![model, self.chunks.len() as i64, started.elapsed().as_secs_f64()],
+            )?;
+        }
+
+        Ok(EmbedStats {
+            documents_embedded: self.docs.len(),
+            chunks_embedded: self.chunks.len(),
+            model,
+        })
+    }
+}
+
+/// Embed one batch of a job's chunks, returning it with its vectors
+async fn embed_chunks<'a>(
+    llm: &impl EmbedSource,
+    batch: &'a [(usize, Chunk)],
+    batch_idx: usize,
+    batch_count: usize,
+) -> Result<(&'a [(usize, Chunk)], EmbeddingResult)> {
+    log::info!("Processing batch {}/{}", batch_idx + 1, batch_count);
+    let texts: Vec<&str> = batch.iter().map(|(_, chunk)| chunk.text.as_str()).collect();
+    Ok((batch, llm.embed_batch(&texts).await?))
+}
+
+/// Qdrant points for the chunks of content `hash`, one per chunk of every
+/// active document in `collection` with that content
+#[cfg(feature = "qdrant")]
+fn qdrant_points(
+    conn: &Connection,
+    collection: &str,
+    hash: &str,
+    chunks: &[(&Chunk, Vec<f32>)],
+) -> Result<Vec<crate::store::qdrant_backend::DocumentInput>> {
+    use crate::store::qdrant_backend::{point_id, DocumentInput};
+
+    let mut stmt = conn.prepare(
+        "SELECT path, title FROM documents WHERE collection = ? AND hash = ? AND active = 1",
+    )?;
+    let documents = stmt
+        .query_map([collection, hash], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut points = Vec::new();
+    for (path, title) in documents {
+        // Search results name documents by collection-prefixed path
+        let path = format!("{}/{}", collection, path);
+        for (chunk, embedding) in chunks {
+            points.push(DocumentInput::new(
+                point_id(collection, &path, chunk.seq).to_string(),
+                path.clone(),
+                title.clone(),
+                chunk.text.clone(),
+                hash.to_string(),
+                collection.to_string(),
+                embedding.clone(),
+            ));
+        }
+    }
+    Ok(points)
+}
+
+/// Replace the stored vectors of one document with `chunks`
+///
+/// Runs in one immediate transaction: a concurrent writer waits its turn, and
+/// nobody sees the document with metadata rows but no vectors. Rows for
+/// chunks past the end, left by a longer earlier version, are removed.
+fn store_document_vectors(
+    conn: &Connection,
+    has_vec_table: bool,
+    hash: &str,
+    model: &str,
+    chunks: &[(&Chunk, Vec<f32>)],
+) -> Result<()> {
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+
+    for (chunk, embedding) in chunks {
+        tx.execute(
+            "INSERT INTO content_vectors (hash, seq, pos, line, model, embedded_at)
+             VALUES (?, ?, ?, ?, ?, datetime('now'))
+             ON CONFLICT (hash, seq) DO UPDATE SET
+                 pos = excluded.pos, line = excluded.line, model = excluded.model,
+                 embedded_at = excluded.embedded_at",
+            params![hash, chunk.seq as i64, chunk.pos as i64, chunk.line as i64, model],
+        )?;
+
+        if has_vec_table {
+            // vec0 has no upsert, and OR REPLACE can leave a duplicate key
+            let hash_seq = format!("{}_{}", hash, chunk.seq);
+            tx.execute("DELETE FROM vectors_vec WHERE hash_seq = ?", [&hash_seq])?;
+            tx.execute(
+                "INSERT INTO vectors_vec (hash_seq, embedding) VALUES (?, ?)",
+                [&hash_seq, &serde_json::to_string(embedding)?],
+            )?;
+        }
+    }
+
+    let count = chunks.len() as i64;
+    tx.execute("DELETE FROM content_vectors WHERE hash = ? AND seq >= ?", params![hash, count])?;
+    if has_vec_table {
+        let prefix = format!("{}_", hash);
+        tx.execute(
+            "DELETE FROM vectors_vec
+             WHERE substr(hash_seq, 1, length(?1)) = ?1
+             AND CAST(substr(hash_seq, length(?1) + 1) AS INTEGER) >= ?2",
+            params![prefix, count],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
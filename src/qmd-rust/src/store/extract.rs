@@ -0,0 +1,275 @@
+//! Text indexed for a file, extracted by file type.
+//!
+//! Most files are indexed as they are. Formats whose raw bytes make poor
+//! search text get an extractor here; the indexer hashes and stores the
+//! extracted text, so a file is re-indexed only when its text changes.
+//!
+//! Jupyter notebooks (`.ipynb`) become their markdown and code cells in
+//! order, each introduced by a [`SECTION_MARKER`] line naming the cell type
+//! and, for code, its execution count. The chunker splits at those lines
+//! first, so chunks follow cell boundaries where it can.
+
+use super::chunker::SECTION_MARKER;
+use crate::config::{Config, NotebookConfig};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Read `path` and extract the text the index keeps for it
+pub fn read_document(path: &Path, config: &Config) -> Result<String> {
+    let raw = read_text(path, config.strict_utf8)?;
+    if is_notebook(path) {
+        return extract_notebook(&raw, &config.notebooks)
+            .with_context(|| format!("Malformed notebook: {}", path.display()));
+    }
+    Ok(raw)
+}
+
+/// Read `path` as text, replacing invalid UTF-8 bytes with U+FFFD unless
+/// `strict`, when they are an error
+fn read_text(path: &Path, strict: bool) -> Result<String> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(e) if strict => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e.utf8_error()))
+            .with_context(|| format!("Failed to read file: {}", path.display())),
+        Err(e) => {
+            log::warn!("Replacing invalid UTF-8 in {}: {}", path.display(), e.utf8_error());
+            Ok(String::from_utf8_lossy(e.as_bytes()).into_owned())
+        }
+    }
+}
+
+/// Longest title taken from a document's text
+const MAX_TITLE_BYTES: usize = 120;
+
+/// Title the index stores for a document, never empty
+///
+/// The file name without its extension or leading dots names the document.
+/// When that is blank (`.md`, `...`, `  .txt`) the first heading of the text
+/// is used, else its first non-blank line, and failing both the path.
+pub fn document_title(path: &Path, relative: &str, content: &str) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let stem = stem.trim().trim_start_matches('.').trim();
+    if !stem.is_empty() {
+        return stem.to_string();
+    }
+
+    let lines = || {
+        content
+            .lines()
+            .map(str::trim)
+            // Notebook cell markers and bare heading marks say nothing
+            .filter(|line| !line.trim_start_matches('#').is_empty() && !line.starts_with(SECTION_MARKER.trim_end()))
+    };
+    let heading = lines().find_map(|line| {
+        let text = line.strip_prefix('#')?.trim_start_matches('#').trim();
+        (!text.is_empty()).then_some(text)
+    });
+    match heading.or_else(|| lines().next()) {
+        Some(line) => truncate(line, MAX_TITLE_BYTES).trim_end().to_string(),
+        None => relative.to_string(),
+    }
+}
+
+/// Whether `path` is a Jupyter notebook
+pub fn is_notebook(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ipynb"))
+}
+
+#[derive(Deserialize)]
+struct Notebook {
+    cells: Vec<Cell>,
+}
+
+#[derive(Deserialize)]
+struct Cell {
+    cell_type: String,
+    #[serde(default)]
+    source: MultilineText,
+    #[serde(default)]
+    execution_count: Option<u64>,
+    #[serde(default)]
+    outputs: Vec<Output>,
+}
+
+#[derive(Deserialize)]
+struct Output {
+    output_type: String,
+    #[serde(default)]
+    text: Option<MultilineText>,
+    #[serde(default)]
+    data: Option<OutputData>,
+}
+
+#[derive(Deserialize)]
+struct OutputData {
+    #[serde(rename = "text/plain")]
+    text_plain: Option<MultilineText>,
+}
+
+/// Notebook text: a string or a list of lines
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MultilineText {
+    Whole(String),
+    Lines(Vec<String>),
+}
+
+impl Default for MultilineText {
+    fn default() -> Self {
+        Self::Whole(String::new())
+    }
+}
+
+impl MultilineText {
+    fn joined(&self) -> String {
+        match self {
+            Self::Whole(text) => text.clone(),
+            Self::Lines(lines) => lines.concat(),
+        }
+    }
+}
+
+/// Markdown and code cells of a notebook, with cell markers
+pub fn extract_notebook(raw: &str, config: &NotebookConfig) -> Result<String> {
+    let notebook: Notebook = serde_json::from_str(raw)?;
+    let mut text = String::new();
+
+    for (index, cell) in notebook.cells.iter().enumerate() {
+        let marker = match cell.cell_type.as_str() {
+            "markdown" => format!("[markdown] cell {}", index + 1),
+            "code" => match cell.execution_count {
+                Some(count) => format!("[code] In [{}]", count),
+                None => "[code] In [ ]".to_string(),
+            },
+            _ => continue,
+        };
+        push_section(&mut text, &marker, &cell.source.joined());
+
+        if config.include_outputs && cell.cell_type == "code" {
+            let output = cell_output(cell);
+            if !output.trim().is_empty() {
+                push_section(&mut text, "[output]", truncate(&output, config.max_output_bytes));
+            }
+        }
+    }
+
+    Ok(text)
+}
+
+fn push_section(text: &mut String, marker: &str, body: &str) {
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(SECTION_MARKER);
+    text.push_str(marker);
+    text.push('\n');
+    text.push_str(body.trim_end());
+    text.push('\n');
+}
+
+/// Stream text and `text/plain` results of a code cell
+fn cell_output(cell: &Cell) -> String {
+    cell.outputs
+        .iter()
+        .filter_map(|output| match output.output_type.as_str() {
+            "stream" => output.text.as_ref(),
+            "execute_result" | "display_data" => output.data.as_ref()?.text_plain.as_ref(),
+            _ => None,
+        })
+        .map(MultilineText::joined)
+        .collect()
+}
+
+/// `text` cut to at most `max_bytes`, on a character boundary
+fn truncate(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_title_falls_back_to_text_then_path() {
+        let title = |name: &str, content: &str| document_title(Path::new(name), name, content);
+
+        assert_eq!(title("guide.md", "# Something else"), "guide");
+        assert_eq!(title(".notes", "Buy milk"), "notes");
+        assert_eq!(title("  .md", "intro text\n\n## Setup  \nsteps"), "Setup");
+        assert_eq!(title("...", "\n\n  First line  \nsecond"), "First line");
+        assert_eq!(title("...", "%% markdown\n#\nplain"), "plain");
+        assert_eq!(title("  .md", &"word ".repeat(100)).len(), MAX_TITLE_BYTES - 1);
+        assert_eq!(title("  .md", " \n\t\n"), "  .md");
+    }
+
+    const NOTEBOOK: &str = r##"{
+        "cells": [
+            {"cell_type": "markdown", "metadata": {}, "source": ["# Results\n", "Accuracy improves with more data."]},
+            {"cell_type": "code", "execution_count": 3, "metadata": {}, "source": "model = fit_model(data)",
+             "outputs": [
+                {"output_type": "stream", "name": "stdout", "text": ["epoch 1\n", "epoch 2\n"]},
+                {"output_type": "display_data", "data": {"image/png": "iVBORw0KGgo=", "text/plain": "<Figure>"}}
+             ]},
+            {"cell_type": "raw", "metadata": {}, "source": "ignored"}
+        ],
+        "metadata": {},
+        "nbformat": 4,
+        "nbformat_minor": 5
+    }"##;
+
+    #[test]
+    fn test_extract_notebook_cells_without_outputs() {
+        let text = extract_notebook(NOTEBOOK, &NotebookConfig::default()).unwrap();
+        assert_eq!(
+            text,
+            "%% [markdown] cell 1\n# Results\nAccuracy improves with more data.\n\
+             \n%% [code] In [3]\nmodel = fit_model(data)\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_notebook_plain_outputs_are_capped() {
+        let config = NotebookConfig {
+            include_outputs: true,
+            max_output_bytes: 12,
+        };
+        let text = extract_notebook(NOTEBOOK, &config).unwrap();
+        assert!(text.ends_with("%% [output]\nepoch 1\nepoc\n"), "{}", text);
+        assert!(!text.contains("iVBOR"));
+
+        let config = NotebookConfig {
+            include_outputs: true,
+            ..NotebookConfig::default()
+        };
+        let text = extract_notebook(NOTEBOOK, &config).unwrap();
+        assert!(text.ends_with("epoch 2\n<Figure>\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_extract_notebook_rejects_malformed_json() {
+        assert!(extract_notebook("{\"cells\": [", &NotebookConfig::default()).is_err());
+        assert!(extract_notebook("{\"nbformat\": 4}", &NotebookConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_read_text_replaces_invalid_utf8_unless_strict() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("latin1.md");
+        std::fs::write(&path, b"na\xefve").unwrap();
+
+        assert_eq!(read_text(&path, false).unwrap(), "na\u{fffd}ve");
+        let error = read_text(&path, true).unwrap_err();
+        assert!(error.chain().any(|cause| cause
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::InvalidData)));
+    }
+}
@@ -0,0 +1,51 @@
+//! Keeping the full-text index in step with `documents`.
+//!
+//! `documents_fts` is an external-content FTS5 table over
+//! `documents_fts_view`, one row per active document with its body read
+//! through `qmd_doc()` (see [`compress`](super::compress)). The store writes
+//! the index itself rather than through triggers: [`unindex`] before a
+//! document is deleted, deactivated or changed, and [`index`] once it is
+//! written. A tool without `qmd_doc()` (the `sqlite3` shell, a script, an
+//! older qmd) can still write `documents`; the index lags behind until
+//! `qmd verify --fix` rebuilds it.
+
+use anyhow::Result;
+use rusqlite::{Connection, Params};
+
+/// Add the active documents matching `filter`, a condition on `documents d`,
+/// to the index
+pub fn index(conn: &Connection, filter: &str, params: impl Params) -> Result<usize> {
+    Ok(conn.execute(
+        &format!(
+            "INSERT INTO documents_fts(rowid, filepath, title, body)
+             SELECT v.id, v.filepath, v.title, v.body FROM documents_fts_view v
+             JOIN documents d ON d.id = v.id
+             WHERE {}",
+            filter
+        ),
+        params,
+    )?)
+}
+
+/// Remove the active documents matching `filter` from the index
+///
+/// External content is removed by passing back the exact values indexed, so
+/// this runs while the rows and their content are still as they were.
+pub fn unindex(conn: &Connection, filter: &str, params: impl Params) -> Result<usize> {
+    Ok(conn.execute(
+        &format!(
+            "INSERT INTO documents_fts(documents_fts, rowid, filepath, title, body)
+             SELECT 'delete', v.id, v.filepath, v.title, v.body FROM documents_fts_view v
+             JOIN documents d ON d.id = v.id
+             WHERE {}",
+            filter
+        ),
+        params,
+    )?)
+}
+
+/// Re-read every active document into the index, dropping whatever it held
+pub fn rebuild(conn: &Connection) -> Result<()> {
+    conn.execute("INSERT INTO documents_fts(documents_fts) VALUES('rebuild')", [])?;
+    Ok(())
+}
@@ -11,8 +11,8 @@
 use crate::store::SearchResult;
 use anyhow::{Context, Result};
 use arrow_array::{
-    Array, FixedSizeListArray, Float32Array, Int64Array, RecordBatch, RecordBatchIterator,
-    StringArray,
+    Array, BooleanArray, FixedSizeListArray, Float32Array, Int64Array, RecordBatch,
+    RecordBatchIterator, StringArray,
 };
 use arrow_schema::{DataType, Field, Schema};
 use futures::TryStreamExt;
@@ -23,6 +23,9 @@ use lancedb::{connect, Connection as LanceConnection, DistanceType};
 use std::path::PathBuf;
 use std::sync::Arc;
 
+/// Row filter keeping soft-deleted documents out of every query
+const ACTIVE_FILTER: &str = "active = true";
+
 /// LanceDB backend for QMD
 ///
 /// This implementation uses LanceDB for both FTS and vector search.
@@ -78,6 +81,7 @@ impl LanceDbBackend {
             Field::new("title", DataType::Utf8, false),
             Field::new("body", DataType::Utf8, false),
             Field::new("hash", DataType::Utf8, false),
+            Field::new("active", DataType::Boolean, false),
             Field::new(
                 "embedding",
                 DataType::FixedSizeList(
@@ -118,6 +122,16 @@ impl LanceDbBackend {
         }
     }
 
+    /// Whether the table has the `active` column; tables created before it
+    /// existed cannot be filtered until they are rebuilt
+    async fn has_active_column(table: &lancedb::Table) -> bool {
+        table
+            .schema()
+            .await
+            .map(|schema| schema.field_with_name("active").is_ok())
+            .unwrap_or(false)
+    }
+
     /// Full-text search using LanceDB FTS
     pub async fn fts_search(
         &self,
@@ -129,12 +143,11 @@ impl LanceDbBackend {
 
         let fts_query = FullTextSearchQuery::new(query.to_string());
 
-        let stream = table
-            .query()
-            .full_text_search(fts_query)
-            .limit(limit)
-            .execute()
-            .await;
+        let mut query = table.query().full_text_search(fts_query).limit(limit);
+        if Self::has_active_column(&table).await {
+            query = query.only_if(ACTIVE_FILTER);
+        }
+        let stream = query.execute().await;
 
         let batches = match stream {
             Ok(stream) => stream.try_collect::<Vec<_>>().await?,
@@ -179,6 +192,11 @@ impl LanceDbBackend {
                         title,
                         hash,
                         query: Some(query.to_string()),
+                        size: None,
+                        match_line: None,
+                        duplicates: Vec::new(),
+                        source: vec![crate::store::SOURCE_BM25.to_string()],
+                        score_components: None,
                     });
                 }
             }
@@ -196,13 +214,15 @@ impl LanceDbBackend {
     ) -> Result<Vec<SearchResult>> {
         let table = self.open_or_create_table(collection).await?;
 
-        let stream = table
+        let mut query = table
             .vector_search(query_vector)
             .context("Failed to create vector query")?
             .distance_type(DistanceType::Cosine)
-            .limit(limit)
-            .execute()
-            .await;
+            .limit(limit);
+        if Self::has_active_column(&table).await {
+            query = query.only_if(ACTIVE_FILTER);
+        }
+        let stream = query.execute().await;
 
         let batches = match stream {
             Ok(stream) => stream.try_collect::<Vec<_>>().await?,
@@ -249,6 +269,11 @@ impl LanceDbBackend {
                         title,
                         hash,
                         query: None,
+                        size: None,
+                        match_line: None,
+                        duplicates: Vec::new(),
+                        source: vec![crate::store::SOURCE_VECTOR.to_string()],
+                        score_components: None,
                     });
                 }
             }
@@ -309,6 +334,7 @@ impl LanceDbBackend {
                 Arc::new(StringArray::from(titles)),
                 Arc::new(StringArray::from(bodies)),
                 Arc::new(StringArray::from(hashes)),
+                Arc::new(BooleanArray::from(vec![true; len])),
                 Arc::new(embedding_array) as Arc<dyn Array>,
             ],
         )?;
@@ -326,6 +352,35 @@ impl LanceDbBackend {
         Ok(())
     }
 
+    /// Mark documents as soft-deleted so searches skip them
+    pub async fn deactivate_paths(&self, collection: &str, paths: &[String]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let table = self.open_or_create_table(collection).await?;
+        if !Self::has_active_column(&table).await {
+            log::warn!(
+                "LanceDB table for '{}' predates the active column; re-sync it to hide deleted documents",
+                collection
+            );
+            return Ok(());
+        }
+
+        let quoted: Vec<String> = paths
+            .iter()
+            .map(|p| format!("'{}'", p.replace('\'', "''")))
+            .collect();
+        table
+            .update()
+            .only_if(format!("path IN ({})", quoted.join(", ")))
+            .column("active", "false")
+            .execute()
+            .await
+            .context("Failed to deactivate documents in LanceDB")?;
+        Ok(())
+    }
+
     /// Ensure FTS index exists on body and title columns
     pub async fn ensure_fts_index(&self, collection: &str) -> Result<()> {
         let table = self.open_or_create_table(collection).await?;
@@ -393,7 +448,7 @@ impl LanceDbBackend {
     {
         // Query documents with their content
         let mut stmt = conn.prepare(
-            "SELECT d.id, d.path, d.title, c.content, d.hash
+            "SELECT d.id, d.path, d.title, qmd_doc(c.doc, c.codec, c.packed), d.hash
              FROM documents d
              JOIN content c ON d.hash = c.hash
              WHERE d.collection = ? AND d.active = 1"
@@ -416,6 +471,14 @@ impl LanceDbBackend {
             .filter_map(|r| r.ok())
             .collect();
 
+        // Documents deactivated in SQLite since an earlier sync
+        let inactive: Vec<String> = conn
+            .prepare("SELECT path FROM documents WHERE collection = ? AND active = 0")?
+            .query_map([collection], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        self.deactivate_paths(collection, &inactive).await?;
+
         if rows.is_empty() {
             log::info!("No documents to sync for collection '{}'", collection);
             return Ok(0);
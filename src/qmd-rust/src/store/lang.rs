@@ -0,0 +1,251 @@
+//! Query language detection.
+//!
+//! English queries suit the stemming porter tokenizer and the English
+//! expansion rules. Chinese text has no spaces between words, so it needs
+//! n-gram matching and its own expansion dictionary. A script count over the
+//! query is enough to tell the two apart; `--lang` overrides it.
+
+use super::candidates::CandidateCacheUse;
+use serde::Serialize;
+use std::fmt;
+
+/// Shortest substring the FTS5 trigram tokenizer can match
+const TRIGRAM: usize = 3;
+
+/// Language of a search query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum QueryLanguage {
+    #[serde(rename = "en")]
+    English,
+    /// CJK text; Japanese and Korean take the same n-gram path
+    #[serde(rename = "zh")]
+    Chinese,
+    /// Both Latin words and CJK text
+    #[serde(rename = "mixed")]
+    Mixed,
+}
+
+impl QueryLanguage {
+    /// Expansion rule sets that apply, in the order they are tried
+    pub fn expansion_rules(self) -> &'static [&'static str] {
+        match self {
+            Self::English => &["english"],
+            Self::Chinese => &["chinese"],
+            Self::Mixed => &["english", "chinese"],
+        }
+    }
+
+    /// Whether the query holds CJK text that needs n-gram matching
+    pub fn has_cjk(self) -> bool {
+        !matches!(self, Self::English)
+    }
+}
+
+impl fmt::Display for QueryLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::English => write!(f, "en"),
+            Self::Chinese => write!(f, "zh"),
+            Self::Mixed => write!(f, "mixed"),
+        }
+    }
+}
+
+impl std::str::FromStr for QueryLanguage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "en" | "english" => Ok(Self::English),
+            "zh" | "chinese" | "cjk" => Ok(Self::Chinese),
+            "mixed" => Ok(Self::Mixed),
+            other => anyhow::bail!("Unknown language '{}'; valid options: en, zh, mixed", other),
+        }
+    }
+}
+
+/// Where the language used for a query came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LanguageSource {
+    Detected,
+    Override,
+}
+
+/// Language for a query: the override if given, else detected
+pub fn resolve_language(query: &str, lang: Option<QueryLanguage>) -> (QueryLanguage, LanguageSource) {
+    match lang {
+        Some(lang) => (lang, LanguageSource::Override),
+        None => (detect_language(query), LanguageSource::Detected),
+    }
+}
+
+/// CJK ideographs, kana and hangul
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2A6DF}')
+}
+
+/// Detect a query's language from the scripts it uses
+///
+/// Queries without CJK characters (including empty ones) count as English.
+pub fn detect_language(query: &str) -> QueryLanguage {
+    let cjk = query.chars().filter(|&c| is_cjk(c)).count();
+    let latin = query.chars().filter(|c| c.is_ascii_alphabetic()).count();
+    match (cjk, latin) {
+        (0, _) => QueryLanguage::English,
+        (_, 0) => QueryLanguage::Chinese,
+        _ => QueryLanguage::Mixed,
+    }
+}
+
+/// Trigram-style fallback for a query against a trigram-tokenized collection
+///
+/// Every run of CJK characters becomes its overlapping three-character
+/// substrings, OR-ed together with the query's Latin words, so documents that
+/// share part of an unsegmented phrase still match. Runs and words shorter
+/// than a trigram cannot match and are dropped. Returns `None` when nothing
+/// usable remains.
+pub fn ngram_fallback_query(query: &str) -> Option<String> {
+    let mut terms: Vec<String> = Vec::new();
+    let mut push = |term: String| {
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    };
+
+    let mut run: Vec<char> = Vec::new();
+    let mut word = String::new();
+    for c in query.chars().chain(std::iter::once(' ')) {
+        if is_cjk(c) {
+            run.push(c);
+        } else if run.len() >= TRIGRAM {
+            for gram in run.windows(TRIGRAM) {
+                push(gram.iter().collect());
+            }
+            run.clear();
+        } else {
+            run.clear();
+        }
+
+        if c.is_alphanumeric() && !is_cjk(c) {
+            word.push(c);
+        } else {
+            if word.chars().count() >= TRIGRAM {
+                push(std::mem::take(&mut word));
+            }
+            word.clear();
+        }
+    }
+
+    if terms.is_empty() {
+        return None;
+    }
+    Some(
+        terms
+            .iter()
+            .map(|t| format!("\"{}\"", t))
+            .collect::<Vec<_>>()
+            .join(" OR "),
+    )
+}
+
+/// One FTS query run against a collection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedQuery {
+    pub collection: String,
+    pub query: String,
+    /// Added n-gram fallback rather than the query as written
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub fallback: bool,
+}
+
+/// How a query was interpreted, for `--explain`
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryExplain {
+    pub language: QueryLanguage,
+    pub language_source: LanguageSource,
+    pub expansion_rules: &'static [&'static str],
+    /// Query variants searched besides the original (hybrid search only)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub expansions: Vec<String>,
+    pub fts_queries: Vec<PlannedQuery>,
+    /// Document frequency of each query term, with `--explain-terms`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub terms: Vec<TermFrequency>,
+    /// Candidate cache use of the hybrid search explained
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candidate_cache: Option<CandidateCacheUse>,
+}
+
+/// How many documents of a collection contain a query term
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TermFrequency {
+    pub collection: String,
+    pub term: String,
+    /// Active documents containing the term
+    pub documents: usize,
+    /// Active documents in the collection
+    pub total_documents: usize,
+}
+
+impl QueryExplain {
+    /// Human-readable lines for CLI output
+    pub fn render(&self) -> String {
+        let source = match self.language_source {
+            LanguageSource::Detected => "detected",
+            LanguageSource::Override => "--lang",
+        };
+        let mut out = format!("Language: {} ({})\n", self.language, source);
+        out.push_str(&format!("Expansion rules: {}\n", self.expansion_rules.join(", ")));
+        if !self.expansions.is_empty() {
+            out.push_str(&format!("Expansions: {}\n", self.expansions.join(" | ")));
+        }
+        for planned in &self.fts_queries {
+            let kind = if planned.fallback { "n-gram fallback" } else { "fts" };
+            out.push_str(&format!("{} [{}]: {}\n", kind, planned.collection, planned.query));
+        }
+        for term in &self.terms {
+            out.push_str(&format!(
+                "term [{}] {}: in {} of {} documents\n",
+                term.collection, term.term, term.documents, term.total_documents
+            ));
+        }
+        if let Some(cache) = &self.candidate_cache {
+            out.push_str(&cache.render());
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language("how to install rust"), QueryLanguage::English);
+        assert_eq!(detect_language("所有权规则"), QueryLanguage::Chinese);
+        assert_eq!(detect_language("rust 所有权"), QueryLanguage::Mixed);
+        assert_eq!(detect_language("2024"), QueryLanguage::English);
+        assert_eq!(detect_language(""), QueryLanguage::English);
+    }
+
+    #[test]
+    fn test_ngram_fallback_query() {
+        assert_eq!(
+            ngram_fallback_query("所有权规则").as_deref(),
+            Some("\"所有权\" OR \"有权规\" OR \"权规则\"")
+        );
+        assert_eq!(
+            ngram_fallback_query("rust 所有权 规则").as_deref(),
+            Some("\"rust\" OR \"所有权\"")
+        );
+        assert_eq!(ngram_fallback_query("规则"), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! Match positions of search hits.
+//!
+//! Search results carry the line of their best match. The CLI searches record
+//! those lines per docid so a following `qmd get <docid> --around` can center
+//! its window on the hit instead of starting at the top of the document.
+
+use super::{SearchResult, Store};
+use anyhow::Result;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+/// FTS5 operators that are not search terms
+const OPERATORS: [&str; 4] = ["and", "or", "not", "near"];
+
+/// Where the last search matched a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MatchPosition {
+    pub docid: String,
+    /// 1-based line
+    pub line: usize,
+    pub query: Option<String>,
+    pub recorded_at: String,
+}
+
+/// Lowercased terms of a search query
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for term in query.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        let term = term.to_lowercase();
+        if term.chars().count() < 2 || OPERATORS.contains(&term.as_str()) || terms.contains(&term) {
+            continue;
+        }
+        terms.push(term);
+    }
+    terms
+}
+
+/// 1-based line containing the most distinct query terms, the first on ties
+pub fn match_line(content: &str, query: &str) -> Option<usize> {
+    let terms = query_terms(query);
+    let mut best: Option<(usize, usize)> = None;
+    for (i, line) in content.lines().enumerate() {
+        let line = line.to_lowercase();
+        let hits = terms.iter().filter(|term| line.contains(term.as_str())).count();
+        if hits > 0 && best.is_none_or(|(most, _)| hits > most) {
+            best = Some((hits, i + 1));
+        }
+    }
+    best.map(|(_, line)| line)
+}
+
+/// 1-based line containing byte offset `pos`
+#[cfg_attr(not(feature = "sqlite-vec"), allow(dead_code))]
+pub fn line_at(content: &str, pos: usize) -> usize {
+    let mut pos = pos.min(content.len());
+    while !content.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    content[..pos].matches('\n').count() + 1
+}
+
+/// Collection named by a docid (`collection:path`)
+fn docid_collection(docid: &str) -> Option<&str> {
+    docid.split_once(':').map(|(collection, _)| collection)
+}
+
+impl Store {
+    /// Remember where each result matched, replacing earlier positions
+    pub fn record_match_positions(&self, results: &[SearchResult]) -> Result<()> {
+        for result in results {
+            let Some(line) = result.match_line else {
+                continue;
+            };
+            self.with_connection(&result.collection, |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO match_positions (docid, line, query, recorded_at)
+                     VALUES (?, ?, ?, datetime('now'))",
+                    rusqlite::params![result.docid, line as i64, result.query],
+                )?;
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Position recorded for `docid` by the most recent search that found it
+    pub fn match_position(&self, docid: &str) -> Result<Option<MatchPosition>> {
+        let Some(collection) = docid_collection(docid) else {
+            return Ok(None);
+        };
+        if !self.get_collections().iter().any(|c| c.name == collection) {
+            return Ok(None);
+        }
+        let conn = self.get_connection(collection)?;
+        let position = conn
+            .query_row(
+                "SELECT docid, line, query, recorded_at FROM match_positions WHERE docid = ?",
+                [docid],
+                |row| {
+                    Ok(MatchPosition {
+                        docid: row.get(0)?,
+                        line: row.get::<_, i64>(1)? as usize,
+                        query: row.get(2)?,
+                        recorded_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_line_prefers_most_terms() {
+        let content = "# Title\nrust is fast\nownership in rust\nnothing here";
+        assert_eq!(match_line(content, "rust ownership"), Some(3));
+        assert_eq!(match_line(content, "Rust"), Some(2));
+        assert_eq!(match_line(content, "rust AND fast"), Some(2));
+        assert_eq!(match_line(content, "python"), None);
+    }
+
+    #[test]
+    fn test_line_at_counts_newlines_before_offset() {
+        let content = "one\ntwo\nthree";
+        assert_eq!(line_at(content, 0), 1);
+        assert_eq!(line_at(content, 4), 2);
+        assert_eq!(line_at(content, 100), 3);
+        assert_eq!(line_at("é\nx", 1), 1);
+    }
+}
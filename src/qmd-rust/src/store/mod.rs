@@ -1,6 +1,30 @@
+pub mod access;
+pub mod bundle;
+pub mod candidates;
 pub mod chunker;
+pub mod compress;
+pub mod context;
+pub mod deadline;
+pub mod embed;
+pub mod extract;
+pub mod fts;
+pub mod lang;
 pub mod lance_backend;
+pub mod matches;
+pub mod order;
+pub mod parallel;
 pub mod path;
+pub mod pool;
+pub mod reindex;
+pub mod remove;
+pub mod report;
+pub mod runtime;
+pub mod stopwords;
+pub mod suggest;
+pub mod timings;
+pub mod trash;
+pub mod verify;
+pub mod walk;
 
 #[cfg(feature = "qdrant")]
 pub mod qdrant_backend;
@@ -10,14 +34,33 @@ use lance_backend::LanceDbBackend;
 #[cfg(feature = "qdrant")]
 use qdrant_backend::QdrantBackend;
 
+use crate::anel::{AnelError, AnelErrorCode, RecoveryHint};
 use crate::config::{Config, BM25Backend, VectorBackend};
 use crate::llm::Router;
+use context::ContextSource;
+use deadline::{Deadline, SearchOutcome, SearchStage};
+use lang::{PlannedQuery, QueryExplain, QueryLanguage, TermFrequency};
+use path::native_path;
+use timings::{StageTimer, StageTimings};
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
-use std::sync::Mutex;
-use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use log::{debug, info, warn};
+
+/// FTS5 tokenizer for prose: stemmed words
+const FTS_TOKENIZER_TEXT: &str = "porter unicode61";
+/// FTS5 tokenizer for code: trigrams match identifiers whole and as substrings
+const FTS_TOKENIZER_CODE: &str = "trigram";
+/// RRF rank constant used by hybrid search
+const RRF_K: u32 = 60;
+/// Added to the RRF score of the top fused result
+const RRF_FIRST_PLACE_BONUS: f32 = 0.05;
+/// How long a connection waits for another writer (a concurrent `embed` or
+/// `update`) to release the database before failing
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
 
 /// Search result structure
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -25,11 +68,228 @@ pub struct SearchResult {
     pub docid: String,
     pub path: String,
     pub collection: String,
+    /// Relevance, higher is better, whatever the backend: BM25 hits report
+    /// their [`bm25_relevance`], vector hits their cosine similarity and
+    /// hybrid hits the reranker's score or their [`fused_relevance`], all at
+    /// most 1. `SearchOptions::min_score` is compared against this.
     pub score: f32,
     pub lines: usize,
     pub title: String,
     pub hash: String,
     pub query: Option<String>,
+    /// Size of the matched document, when the backend indexed it
+    #[serde(flatten, default)]
+    pub size: Option<DocumentSize>,
+    /// 1-based line of the best match, for `qmd get --around`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub match_line: Option<usize>,
+    /// Paths of lower-ranked results with the same content, folded into this one
+    /// by `--dedupe-by-hash`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<String>,
+    /// Search arms that found this result, [`SOURCE_BM25`] and/or [`SOURCE_VECTOR`]
+    #[serde(default)]
+    pub source: Vec<String>,
+    /// Ranks and scores behind a hybrid search result's final score
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_components: Option<ScoreComponents>,
+}
+
+/// Where a hybrid search result's score came from, for tuning fusion weights
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreComponents {
+    /// 1-based rank among the BM25 hits, if BM25 found the document
+    pub bm25_rank: Option<usize>,
+    /// 1-based rank among the vector hits, if vector search found the document
+    pub vector_rank: Option<usize>,
+    /// Fused RRF score, including the top-rank bonus
+    pub rrf_score: f32,
+    /// Score from the reranker, when one reordered the results
+    pub rerank_score: Option<f32>,
+}
+
+impl ScoreComponents {
+    /// Note a result's 1-based `rank` under each of its sources, keeping the best
+    fn record_rank(&mut self, sources: &[String], rank: usize) {
+        for source in sources {
+            let best = match source.as_str() {
+                SOURCE_BM25 => &mut self.bm25_rank,
+                SOURCE_VECTOR => &mut self.vector_rank,
+                _ => continue,
+            };
+            *best = Some(best.map_or(rank, |best| best.min(rank)));
+        }
+    }
+}
+
+/// [`SearchResult::source`] of full-text hits
+pub const SOURCE_BM25: &str = "bm25";
+/// [`SearchResult::source`] of vector hits
+pub const SOURCE_VECTOR: &str = "vector";
+
+/// Document size measured at index time, for context budgeting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentSize {
+    pub bytes: usize,
+    pub words: usize,
+    /// Estimated token count, see [`estimate_tokens`]
+    pub tokens: usize,
+}
+
+impl DocumentSize {
+    /// Measure a document's content
+    pub fn measure(content: &str) -> Self {
+        Self {
+            bytes: content.len(),
+            words: content.split_whitespace().count(),
+            tokens: estimate_tokens(content),
+        }
+    }
+
+    /// Read the `bytes`, `words` and `tokens` columns starting at `idx`
+    ///
+    /// Returns `None` for documents indexed before sizes were recorded.
+    pub fn from_row(row: &rusqlite::Row, idx: usize) -> rusqlite::Result<Option<Self>> {
+        let bytes: Option<i64> = row.get(idx)?;
+        let words: Option<i64> = row.get(idx + 1)?;
+        let tokens: Option<i64> = row.get(idx + 2)?;
+        Ok(match (bytes, words, tokens) {
+            (Some(bytes), Some(words), Some(tokens)) => Some(Self {
+                bytes: bytes as usize,
+                words: words as usize,
+                tokens: tokens as usize,
+            }),
+            _ => None,
+        })
+    }
+}
+
+/// Estimate the LLM token count of a text as one token per four characters
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Drop results whose documents exceed a token budget
+///
+/// Results without a recorded size are kept, since their cost is unknown.
+pub fn apply_token_budget(results: Vec<SearchResult>, max_tokens: Option<usize>) -> Vec<SearchResult> {
+    match max_tokens {
+        Some(max) => results
+            .into_iter()
+            .filter(|r| r.size.is_none_or(|size| size.tokens <= max))
+            .collect(),
+        None => results,
+    }
+}
+
+/// Query-time score multiplier for results under a path prefix
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathBoost {
+    /// Matched against `collection/relative/path` and the collection-relative path
+    pub prefix: String,
+    pub weight: f32,
+}
+
+impl PathBoost {
+    fn matches(&self, result: &SearchResult) -> bool {
+        let relative = result
+            .path
+            .strip_prefix(&result.collection)
+            .and_then(|rest| rest.strip_prefix('/'));
+        result.path.starts_with(&self.prefix)
+            || relative.is_some_and(|relative| relative.starts_with(&self.prefix))
+    }
+}
+
+impl std::str::FromStr for PathBoost {
+    type Err = anyhow::Error;
+
+    /// `prefix:weight`, split at the last colon so prefixes may contain one
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (prefix, weight) = s
+            .rsplit_once(':')
+            .with_context(|| format!("Invalid boost '{}'; expected PREFIX:WEIGHT", s))?;
+        let weight: f32 = weight
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid boost weight '{}'", weight))?;
+        if prefix.is_empty() || !weight.is_finite() || weight <= 0.0 {
+            anyhow::bail!("Invalid boost '{}'; expected a path prefix and a positive weight", s);
+        }
+        Ok(Self { prefix: prefix.to_string(), weight })
+    }
+}
+
+/// Multiply the scores of results under boosted paths, then re-rank
+///
+/// Every matching boost applies, so nested prefixes compound.
+pub fn apply_path_boosts(mut results: Vec<SearchResult>, boosts: &[PathBoost]) -> Vec<SearchResult> {
+    if boosts.is_empty() {
+        return results;
+    }
+    for result in &mut results {
+        let weight: f32 = boosts.iter().filter(|b| b.matches(result)).map(|b| b.weight).product();
+        result.score *= weight;
+    }
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results
+}
+
+/// BM25 relevance in `[0, 1)`, higher is better
+///
+/// SQLite FTS5 reports `bm25()` negated (more negative is a better match) and
+/// LanceDB positive, so only the magnitude counts: `|bm25| / (1 + |bm25|)`.
+pub fn bm25_relevance(bm25: f64) -> f32 {
+    let magnitude = bm25.abs();
+    (magnitude / (1.0 + magnitude)) as f32
+}
+
+/// Cosine similarity for a cosine distance as returned by sqlite-vec
+pub fn cosine_similarity(distance: f64) -> f32 {
+    (1.0 - distance) as f32
+}
+
+/// RRF score as a share of the best one possible, higher is better
+///
+/// A document ranked first by every list, and so first overall, scores 1.
+pub fn fused_relevance(rrf_score: f32, weights: &[f32], k: u32) -> f32 {
+    let best = weights.iter().sum::<f32>() / k as f32 + RRF_FIRST_PLACE_BONUS;
+    rrf_score / best
+}
+
+/// Whether a score clears `min_score`; a threshold of zero or less keeps
+/// everything, including results with negative similarity
+pub fn meets_min_score(score: f32, min_score: f32) -> bool {
+    min_score <= 0.0 || score >= min_score
+}
+
+/// Keep only the first result per content hash, in rank order
+///
+/// Later results with the same content (symlinked docs, vendored copies) are
+/// dropped and their paths listed in the kept result's `duplicates`. Results
+/// without a hash are kept as they are.
+pub fn dedupe_by_hash(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut kept: Vec<SearchResult> = Vec::with_capacity(results.len());
+    let mut by_hash: HashMap<String, usize> = HashMap::new();
+    for result in results {
+        if result.hash.is_empty() {
+            kept.push(result);
+            continue;
+        }
+        match by_hash.get(&result.hash) {
+            Some(&i) => {
+                let first = &mut kept[i];
+                if first.path != result.path && !first.duplicates.contains(&result.path) {
+                    first.duplicates.push(result.path);
+                }
+            }
+            None => {
+                by_hash.insert(result.hash.clone(), kept.len());
+                kept.push(result);
+            }
+        }
+    }
+    kept
 }
 
 /// Generate a stable document ID from collection and path
@@ -37,13 +297,191 @@ pub fn make_docid(collection: &str, path: &str) -> String {
     format!("{}:{}", collection, path)
 }
 
+/// Size of a SQLite database file plus its WAL and shared-memory files
+pub fn index_file_bytes(db_path: &Path) -> u64 {
+    let mut paths = vec![db_path.to_path_buf()];
+    for suffix in ["-wal", "-shm"] {
+        let mut name = db_path.as_os_str().to_os_string();
+        name.push(suffix);
+        paths.push(PathBuf::from(name));
+    }
+
+    paths
+        .iter()
+        .filter_map(|p| std::fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Active documents whose index holds `term`, see [`Store::term_frequencies`]
+fn document_frequency(conn: &Connection, term: &str) -> Result<usize> {
+    let vocab: Option<i64> = conn
+        .query_row("SELECT doc FROM temp.documents_fts_vocab WHERE term = ?", [term], |row| row.get(0))
+        .optional()?;
+    if let Some(documents) = vocab {
+        return Ok(documents as usize);
+    }
+    let phrase = format!("\"{}\"", term.replace('"', "\"\""));
+    let documents: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM documents_fts WHERE documents_fts MATCH ?",
+        [&phrase],
+        |row| row.get(0),
+    )?;
+    Ok(documents as usize)
+}
+
+/// Birth time of a file, falling back to its modified time on filesystems
+/// that do not record one
+fn created_or_modified(
+    created: std::io::Result<std::time::SystemTime>,
+    modified: std::time::SystemTime,
+) -> chrono::DateTime<chrono::Utc> {
+    match created {
+        Ok(created) => created.into(),
+        Err(e) => {
+            debug!("File creation time unavailable ({}), using modified time", e);
+            modified.into()
+        }
+    }
+}
+
+/// Stamp results from a per-collection backend the way the SQLite path does
+///
+/// SQLite FTS returns `collection/path` as the path and derives the docid from the
+/// relative path, so backends storing bare relative paths are normalized to the same
+/// shape to keep fusion and dedup consistent across backends.
+#[cfg_attr(not(feature = "lancedb"), allow(dead_code))]
+fn stamp_collection(collection: &str, results: &mut [SearchResult]) {
+    let prefix = format!("{}/", collection);
+    for result in results.iter_mut() {
+        let relative = result.path.strip_prefix(&prefix).unwrap_or(&result.path).to_string();
+        result.path = format!("{}{}", prefix, relative);
+        result.collection = collection.to_string();
+        result.docid = make_docid(collection, &relative);
+    }
+}
+
+/// Error returned by searches when no collections are configured
+pub fn no_collections_error() -> AnelError {
+    AnelError::new(
+        AnelErrorCode::ConfigError,
+        "No Collections Configured",
+        "No collections configured; run `qmd collection add <path> --name <name>` to add one",
+    )
+    .with_hint(
+        RecoveryHint::new("ADD_COLLECTION", "Add a collection of documents to index")
+            .with_action("qmd collection add <path> --name <name>"),
+    )
+}
+
+/// Error for a requested collection that matches no configured name
+pub fn collection_not_found_error(name: &str, suggestions: &[&str], configured: &[&str]) -> AnelError {
+    let hint = if suggestions.is_empty() {
+        RecoveryHint::new(
+            "LIST_COLLECTIONS",
+            format!("Configured collections: {}", configured.join(", ")),
+        )
+        .with_action("qmd collection list")
+    } else {
+        RecoveryHint::new("DID_YOU_MEAN", format!("Did you mean: {}?", suggestions.join(", ")))
+            .with_action(format!("--collection {}", suggestions[0]))
+    };
+    AnelError::new(
+        AnelErrorCode::CollectionNotFound,
+        "Collection Not Found",
+        format!("Collection '{}' is not configured", name),
+    )
+    .with_hint(hint)
+}
+
+/// Vector searches against a builtin backend whose sqlite-vec functions are missing
+pub fn vector_backend_unavailable_error(reason: &str) -> AnelError {
+    AnelError::new(
+        AnelErrorCode::BackendUnavailable,
+        "Vector Backend Unavailable",
+        format!("sqlite-vec is not available, so vector.backend qmd_builtin cannot search: {}", reason),
+    )
+    .with_hint(RecoveryHint::new(
+        "ENABLE_SQLITE_VEC",
+        "Rebuild with --features sqlite-vec or switch vector.backend to lancedb or qdrant",
+    ))
+}
+
+/// A vector whose width differs from the vectors stored in `vectors_vec`
+pub fn vector_dimension_error(expected: usize, actual: usize) -> AnelError {
+    AnelError::new(
+        AnelErrorCode::InvalidInput,
+        "Vector Dimension Mismatch",
+        format!("Expected a {}-dimensional vector, got {} dimensions", expected, actual),
+    )
+    .with_hint(RecoveryHint::new(
+        "SET_VECTOR_DIMENSION",
+        "Set vector.dimension to the embedding model's output size and rebuild the index",
+    ))
+}
+
+/// Width `vectors_vec` was created with, or None without the table
+pub(crate) fn vector_table_dimension(conn: &Connection) -> Result<Option<usize>> {
+    let sql: Option<String> = conn
+        .query_row("SELECT sql FROM sqlite_master WHERE name = 'vectors_vec'", [], |row| row.get(0))
+        .optional()?;
+    Ok(sql.and_then(|sql| {
+        let width = sql.split_once("float[")?.1.split_once(']')?.0;
+        width.trim().parse().ok()
+    }))
+}
+
 /// Search options
 #[derive(Debug, Clone)]
 pub struct SearchOptions {
     pub limit: usize,
+    /// Results skipped from the front of the ranked list, for paging; merged
+    /// and fused searches skip after merging, so pages never overlap
+    pub offset: usize,
+    /// Lowest [`SearchResult::score`] kept, 0 to keep everything
     pub min_score: f32,
     pub collection: Option<String>,
     pub search_all: bool,
+    /// With `search_all`, also search collections that are not searchable by default
+    pub really_all: bool,
+}
+
+impl Default for SearchOptions {
+    /// The first 20 results of the default collections, unfiltered
+    fn default() -> Self {
+        Self {
+            limit: 20,
+            offset: 0,
+            min_score: 0.0,
+            collection: None,
+            search_all: false,
+            really_all: false,
+        }
+    }
+}
+
+impl SearchOptions {
+    /// Options for everything up to the end of this page, for searches that
+    /// merge or reorder results before the page is cut
+    pub fn unpaged(&self) -> Self {
+        Self {
+            limit: self.limit.saturating_add(self.offset),
+            offset: 0,
+            ..self.clone()
+        }
+    }
+}
+
+/// The page `options` asks for out of ranked `results`: `offset` skipped,
+/// at most `limit` kept
+pub fn page(mut results: Vec<SearchResult>, options: &SearchOptions) -> Vec<SearchResult> {
+    skip_offset(&mut results, options.offset);
+    results.truncate(options.limit);
+    results
+}
+
+fn skip_offset(results: &mut Vec<SearchResult>, offset: usize) {
+    results.drain(..offset.min(results.len()));
 }
 
 /// Index statistics
@@ -55,16 +493,126 @@ pub struct IndexStats {
     pub pending_count: usize,
     pub chunk_count: usize,
     pub collection_stats: HashMap<String, usize>,
+    pub collection_chunks: HashMap<String, usize>,
+    /// Estimated tokens across all active documents
+    pub token_count: usize,
+    pub collection_tokens: HashMap<String, usize>,
+    /// On-disk size of the index databases, including WAL/SHM files
+    pub index_bytes: u64,
+    pub collection_index_bytes: HashMap<String, u64>,
+    /// Soft-deleted documents, excluded from every other count
+    pub inactive_count: usize,
+    pub collection_inactive: HashMap<String, usize>,
+}
+
+/// File contents and metadata read during an index update
+struct IndexedFile {
+    content: String,
+    hash: String,
+    created: chrono::DateTime<chrono::Utc>,
+    modified: chrono::DateTime<chrono::Utc>,
+}
+
+/// Document added or modified by an index update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedDocument {
+    pub collection: String,
+    pub path: String,
+    pub hash: String,
+}
+
+/// File an index update could not read, reported instead of aborting the run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileError {
+    pub collection: String,
+    pub path: String,
+    /// `permission_denied`, `not_found`, `invalid_data`, `malformed`, `io` or `other`
+    pub kind: String,
+    pub message: String,
+}
+
+impl FileError {
+    fn new(collection: &str, path: String, error: &anyhow::Error) -> Self {
+        Self {
+            collection: collection.to_string(),
+            path,
+            kind: file_error_kind(error).to_string(),
+            message: format!("{:#}", error),
+        }
+    }
+}
+
+/// Short name for why a file could not be indexed
+fn file_error_kind(error: &anyhow::Error) -> &'static str {
+    for cause in error.chain() {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            return match io.kind() {
+                std::io::ErrorKind::PermissionDenied => "permission_denied",
+                std::io::ErrorKind::NotFound => "not_found",
+                std::io::ErrorKind::InvalidData => "invalid_data",
+                _ => "io",
+            };
+        }
+        if cause.is::<serde_json::Error>() {
+            return "malformed";
+        }
+    }
+    "other"
+}
+
+/// Outcome of an index update
+#[derive(Debug, Default, Serialize)]
+pub struct UpdateReport {
+    /// Documents added or modified
+    #[serde(skip)]
+    pub changed: Vec<ChangedDocument>,
+    pub indexed: usize,
+    pub unchanged: usize,
+    /// Files skipped because they could not be read
+    pub errors: Vec<FileError>,
+}
+
+/// Problem found by the store preflight (missing path, empty index, ...)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreWarning {
+    pub code: String,
+    pub collection: String,
+    pub message: String,
+}
+
+impl StoreWarning {
+    fn new(code: &str, collection: &str, message: impl Into<String>) -> Self {
+        Self {
+            code: code.to_string(),
+            collection: collection.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for StoreWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.collection, self.message)
+    }
 }
 
 /// Main Store structure
 pub struct Store {
     config: Config,
-    connections: HashMap<String, Connection>,
+    /// Connections the search paths reuse, see [`pool`]
+    connections: Mutex<HashMap<String, pool::ConnectionSlot>>,
+    pool_stats: Arc<pool::PoolStats>,
+    schemas: pool::InitializedSchemas,
+    /// Fused candidates of recent hybrid searches, see [`candidates`]
+    candidate_cache: candidates::CandidateCache,
+    warnings: Vec<StoreWarning>,
+    /// Why sqlite-vec failed its probe, when the builtin vector backend is configured
+    vector_unavailable: Option<String>,
     #[cfg(feature = "lancedb")]
     lance_backend: Option<Mutex<LanceDbBackend>>,
+    /// Shared with embed runs, which upsert chunk vectors into it
     #[cfg(feature = "qdrant")]
-    qdrant_backend: Option<Mutex<QdrantBackend>>,
+    qdrant_backend: Option<Arc<QdrantBackend>>,
 }
 
 impl Store {
@@ -78,13 +626,11 @@ impl Store {
         let lance_backend = if matches!(config.bm25.backend, BM25Backend::LanceDb)
             || matches!(config.vector.backend, VectorBackend::LanceDb)
         {
-            let embedding_dim = config.vector.lancedb.embedding_dim;
+            let embedding_dim = config.vector.lancedb.embedding_dim.unwrap_or_else(|| config.vector.dimension());
             let db_path = config.cache_path.clone();
             let mut backend = LanceDbBackend::new(db_path, embedding_dim);
 
-            // Use tokio runtime to connect
-            let rt = tokio::runtime::Runtime::new()?;
-            rt.block_on(async { backend.connect().await })?;
+            runtime::block_on(backend.connect())??;
 
             Some(Mutex::new(backend))
         } else {
@@ -98,26 +644,17 @@ impl Store {
         #[cfg(feature = "qdrant")]
         let qdrant_backend = if matches!(config.vector.backend, VectorBackend::Qdrant) {
             let qdrant_config = &config.vector.qdrant;
-            let mut backend = {
-                let rt = tokio::runtime::Runtime::new()?;
-                rt.block_on(async {
-                    QdrantBackend::new(
-                        &qdrant_config.url,
-                        qdrant_config.api_key.as_deref(),
-                        &qdrant_config.collection,
-                        qdrant_config.vector_size,
-                    )
-                    .await
-                })?
-            };
+            let backend = runtime::block_on(QdrantBackend::new(
+                &qdrant_config.url,
+                qdrant_config.api_key.as_deref(),
+                &qdrant_config.collection,
+                qdrant_config.vector_size,
+            ))??;
 
             // Ensure collection exists
-            {
-                let rt = tokio::runtime::Runtime::new()?;
-                rt.block_on(async { backend.ensure_collection().await })?;
-            }
+            runtime::block_on(backend.ensure_collection())??;
 
-            Some(Mutex::new(backend))
+            Some(Arc::new(backend))
         } else {
             None
         };
@@ -125,9 +662,14 @@ impl Store {
         #[cfg(not(feature = "qdrant"))]
         let _ = config; // Suppress unused warning
 
-        let store = Self {
+        let mut store = Self {
             config: config.clone(),
-            connections: HashMap::new(),
+            connections: Mutex::new(HashMap::new()),
+            pool_stats: Default::default(),
+            schemas: Default::default(),
+            candidate_cache: Default::default(),
+            warnings: Vec::new(),
+            vector_unavailable: None,
             #[cfg(feature = "lancedb")]
             lance_backend,
             #[cfg(feature = "qdrant")]
@@ -139,14 +681,126 @@ impl Store {
             store.get_connection(&collection.name)?;
         }
 
+        store.warnings = store.preflight();
+        if matches!(store.config.vector.backend, VectorBackend::QmdBuiltin) {
+            store.vector_unavailable = Self::probe_sqlite_vec();
+        }
+        if let Some(error) = store.vector_backend_error() {
+            store.warnings.push(StoreWarning::new("VECTOR_BACKEND_UNAVAILABLE", "*", error.message));
+        }
+        for warning in &store.warnings {
+            warn!("Preflight: {}", warning);
+        }
+
         Ok(store)
     }
 
+    /// Check that every collection path is readable and its index has documents
+    pub fn preflight(&self) -> Vec<StoreWarning> {
+        let mut warnings = Vec::new();
+
+        for collection in &self.config.collections {
+            let name = collection.name.as_str();
+
+            if !collection.path.exists() {
+                warnings.push(StoreWarning::new(
+                    "COLLECTION_PATH_MISSING",
+                    name,
+                    format!("Collection path does not exist: {}", collection.path.display()),
+                ));
+            } else if let Err(e) = std::fs::read_dir(&collection.path) {
+                warnings.push(StoreWarning::new(
+                    "COLLECTION_PATH_UNREADABLE",
+                    name,
+                    format!("Collection path is not readable: {} ({})", collection.path.display(), e),
+                ));
+            }
+
+            let active: i64 = self
+                .get_connection(name)
+                .and_then(|conn| {
+                    Ok(conn.query_row(
+                        "SELECT COUNT(*) FROM documents WHERE collection = ? AND active = 1",
+                        [name],
+                        |row| row.get(0),
+                    )?)
+                })
+                .unwrap_or(0);
+
+            if active == 0 {
+                warnings.push(StoreWarning::new(
+                    "COLLECTION_EMPTY",
+                    name,
+                    "Collection index has no documents; run `qmd update`",
+                ));
+            }
+        }
+
+        warnings
+    }
+
+    /// Warnings recorded by the preflight when the store was opened
+    pub fn warnings(&self) -> &[StoreWarning] {
+        &self.warnings
+    }
+
+    /// Preflight warnings relevant to the collections a search covers
+    pub fn search_warnings(&self, options: &SearchOptions) -> Vec<StoreWarning> {
+        let collections = match self.search_collections(options) {
+            Ok(collections) => collections,
+            Err(e) => {
+                return match (&options.collection, e.downcast_ref::<AnelError>()) {
+                    (Some(name), Some(err)) if err.error_code == AnelErrorCode::CollectionNotFound => {
+                        vec![StoreWarning::new("COLLECTION_NOT_FOUND", name, err.message.clone())]
+                    }
+                    _ => Vec::new(),
+                };
+            }
+        };
+
+        self.warnings
+            .iter()
+            .filter(|w| collections.contains(&w.collection.as_str()))
+            .cloned()
+            .collect()
+    }
+
     /// Get collections from config
     pub fn get_collections(&self) -> &[crate::config::CollectionConfig] {
         &self.config.collections
     }
 
+    /// Search pipeline tuning from config
+    pub fn search_config(&self) -> &crate::config::SearchConfig {
+        &self.config.search
+    }
+
+    /// Config defaults for search command flags
+    pub fn defaults(&self) -> &crate::config::DefaultsConfig {
+        &self.config.defaults
+    }
+
+    /// Run a throwaway FTS and vector query so the collection's pages are cached
+    pub fn warm_up_collection(&self, collection: &str) -> Result<()> {
+        self.with_connection(collection, |conn| {
+            let mut fts = conn.prepare("SELECT rowid FROM documents_fts WHERE documents_fts MATCH ?1 LIMIT 1")?;
+            fts.query_map(["qmd"], |_| Ok(()))?.count();
+
+            conn.query_row("SELECT COUNT(*) FROM content_vectors", [], |_| Ok(()))?;
+            // vectors_vec only exists when sqlite-vec is loaded
+            let has_vec: bool = conn.query_row(
+                "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = 'vectors_vec'",
+                [],
+                |row| row.get(0),
+            )?;
+            if has_vec {
+                let mut vec = conn.prepare("SELECT hash_seq FROM vectors_vec LIMIT 1")?;
+                vec.query_map([], |_| Ok(()))?.count();
+            }
+            Ok(())
+        })
+    }
+
     /// Initialize sqlite-vec extension
     fn init_sqlite_vec() -> Result<()> {
         #[cfg(feature = "sqlite-vec")]
@@ -168,7 +822,24 @@ impl Store {
         Ok(())
     }
 
-    /// Get or create database connection for a collection
+    /// Why sqlite-vec's functions cannot be used, checked on a scratch connection
+    fn probe_sqlite_vec() -> Option<String> {
+        let probe = Connection::open_in_memory()
+            .and_then(|conn| conn.prepare("SELECT vec_distance_cosine('[1.0]', '[1.0]')").map(|_| ()));
+        probe.err().map(|e| e.to_string())
+    }
+
+    /// BackendUnavailable error vector searches fail with, if the builtin
+    /// vector backend is configured but sqlite-vec is missing
+    pub fn vector_backend_error(&self) -> Option<AnelError> {
+        self.vector_unavailable.as_deref().map(vector_backend_unavailable_error)
+    }
+
+    /// Open a connection to a collection's database, creating it if needed
+    ///
+    /// The schema is created or migrated on the first open only; see
+    /// [`pool`]. Search paths should prefer [`Store::with_connection`], which
+    /// reuses one connection per collection.
     pub fn get_connection(&self, collection: &str) -> Result<Connection> {
         let db_path = self.config.db_path_for(collection);
 
@@ -179,15 +850,38 @@ impl Store {
 
         let conn = Connection::open(&db_path)
             .with_context(|| format!("Failed to open database: {}", db_path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        compress::register_functions(&conn)?;
 
-        // Initialize schema
-        Self::init_schema(&conn)?;
+        self.ensure_schema(&conn, collection, &db_path)?;
 
         Ok(conn)
     }
 
+    /// FTS5 tokenizer for a collection, depending on whether it holds code
+    ///
+    /// A shared database has one FTS table, indexed for code when any
+    /// collection holds code, so opening it never flips the tokenizer.
+    fn fts_tokenizer(&self, collection: &str) -> &'static str {
+        let is_code = self.config.collections.iter()
+            .any(|c| (c.name == collection || self.config.cache.shared_db) && c.code);
+        if is_code {
+            FTS_TOKENIZER_CODE
+        } else {
+            FTS_TOKENIZER_TEXT
+        }
+    }
+
     /// Initialize database schema
+    #[cfg(test)]
     fn init_schema(conn: &Connection) -> Result<()> {
+        compress::register_functions(conn)?;
+        Self::init_schema_with_tokenizer(conn, FTS_TOKENIZER_TEXT, crate::config::DEFAULT_VECTOR_DIMENSION)
+    }
+
+    /// Initialize database schema, indexing text with the given FTS5 tokenizer
+    /// and storing `dimension`-wide vectors
+    fn init_schema_with_tokenizer(conn: &Connection, tokenizer: &str, dimension: usize) -> Result<()> {
         info!("Initializing database schema");
 
         // Check if we need to migrate from old schema
@@ -203,6 +897,9 @@ impl Store {
             );
         "#)?;
 
+        // Compressed bodies (see compress) live in codec/packed columns
+        Self::add_content_codec_columns(conn)?;
+
         // Documents table - file system layer mapping virtual paths to content hashes
         // Collections are managed in ~/.config/qmd/index.yml
         conn.execute_batch(r#"
@@ -216,11 +913,17 @@ impl Store {
                 created_at TEXT NOT NULL,
                 modified_at TEXT NOT NULL,
                 active INTEGER NOT NULL DEFAULT 1,
+                bytes INTEGER,
+                words INTEGER,
+                tokens INTEGER,
                 FOREIGN KEY (hash) REFERENCES content(hash) ON DELETE CASCADE,
                 UNIQUE(collection, path)
             );
         "#)?;
 
+        // Databases created before size tracking lack the size columns
+        Self::add_document_size_columns(conn)?;
+
         conn.execute_batch(r#"
             -- Create indexes
             CREATE INDEX IF NOT EXISTS idx_documents_collection ON documents(collection, active);
@@ -228,56 +931,57 @@ impl Store {
             CREATE INDEX IF NOT EXISTS idx_documents_path ON documents(path, active);
         "#)?;
 
+        // Older databases keep a full copy of every body inside documents_fts,
+        // and switching a collection to/from code mode changes the tokenizer
+        let needs_fts_migration = Self::check_fts_migration_needed(conn, tokenizer)?;
+        Self::drop_outdated_fts_writers(conn)?;
+
+        // FTS5 reads document bodies through this view instead of keeping its own copy
         conn.execute_batch(r#"
-            -- FTS5 virtual table for full-text search
-            CREATE VIRTUAL TABLE IF NOT EXISTS documents_fts USING fts5(
-                filepath, title, body,
-                tokenize='porter unicode61'
-            );
+            -- Source rows for the external-content FTS5 table
+            CREATE VIEW IF NOT EXISTS documents_fts_view AS
+            SELECT
+                d.id AS id,
+                d.collection || '/' || d.path AS filepath,
+                d.title AS title,
+                qmd_doc(c.doc, c.codec, c.packed) AS body
+            FROM documents d
+            JOIN content c ON c.hash = d.hash
+            WHERE d.active = 1;
         "#)?;
 
-        // FTS triggers - now references content table via documents.hash
-        conn.execute_batch(r#"
-            -- Triggers to keep FTS index synchronized
-            CREATE TRIGGER IF NOT EXISTS documents_ai AFTER INSERT ON documents
-            WHEN new.active = 1
-            BEGIN
-                INSERT INTO documents_fts(rowid, filepath, title, body)
-                SELECT
-                    new.id,
-                    new.collection || '/' || new.path,
-                    new.title,
-                    (SELECT doc FROM content WHERE hash = new.hash)
-                WHERE new.active = 1;
-            END;
+        if needs_fts_migration {
+            info!("Rebuilding documents_fts as external-content table (tokenizer: {})...", tokenizer);
+            conn.execute("DROP TABLE IF EXISTS documents_fts", [])?;
+        }
 
-            CREATE TRIGGER IF NOT EXISTS documents_ad AFTER DELETE ON documents BEGIN
-                DELETE FROM documents_fts WHERE rowid = old.id;
-            END;
+        Self::create_fts_index(conn, tokenizer)?;
 
-            CREATE TRIGGER IF NOT EXISTS documents_au AFTER UPDATE ON documents BEGIN
-                -- Delete from FTS if no longer active
-                DELETE FROM documents_fts WHERE rowid = old.id AND new.active = 0;
-                -- Update FTS if still/newly active
-                INSERT OR REPLACE INTO documents_fts(rowid, filepath, title, body)
-                SELECT
-                    new.id,
-                    new.collection || '/' || new.path,
-                    new.title,
-                    (SELECT doc FROM content WHERE hash = new.hash)
-                WHERE new.active = 1;
-            END;
-        "#)?;
+        if needs_fts_migration {
+            // Re-index all active documents from the view
+            fts::rebuild(conn)?;
+            info!("FTS migration complete");
+        }
 
         // Vector storage — requires sqlite-vec extension; skip gracefully if unavailable
-        if let Err(e) = conn.execute_batch(r#"
+        if let Err(e) = conn.execute_batch(&format!(r#"
             CREATE VIRTUAL TABLE IF NOT EXISTS vectors_vec USING vec0(
                 hash_seq TEXT PRIMARY KEY,
-                embedding float[768] distance_metric=cosine
+                embedding float[{}] distance_metric=cosine
             );
-        "#) {
+        "#, dimension)) {
             warn!("Could not create vectors_vec table (sqlite-vec may not be loaded): {}", e);
         }
+        // A table created for another width keeps it until recreated
+        if let Some(existing) = vector_table_dimension(conn)? {
+            if existing != dimension {
+                warn!(
+                    "vectors_vec stores {}-dimensional vectors but vector.dimension is {}; \
+                     remove the index and run `qmd update` and `qmd embed` to rebuild it",
+                    existing, dimension
+                );
+            }
+        }
 
         conn.execute_batch(r#"
             -- Vector metadata
@@ -287,18 +991,69 @@ impl Store {
                 pos INTEGER NOT NULL DEFAULT 0,
                 model TEXT NOT NULL,
                 embedded_at TEXT NOT NULL,
+                -- 1-based line the chunk starts on; NULL for chunks embedded before it was recorded
+                line INTEGER,
                 PRIMARY KEY (hash, seq)
             );
+
+            -- Line of the last search hit per document, for `get --around`
+            CREATE TABLE IF NOT EXISTS match_positions (
+                docid TEXT PRIMARY KEY,
+                line INTEGER NOT NULL,
+                query TEXT,
+                recorded_at TEXT NOT NULL
+            );
+
+            -- Completed embed runs, for `embed --dry-run` time estimates
+            CREATE TABLE IF NOT EXISTS embed_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                model TEXT NOT NULL,
+                chunks INTEGER NOT NULL,
+                seconds REAL NOT NULL,
+                finished_at TEXT NOT NULL
+            );
+
+            -- Words of titles and headings, for `suggest` and did-you-mean hints
+            CREATE TABLE IF NOT EXISTS vocabulary (
+                term TEXT PRIMARY KEY,
+                documents INTEGER NOT NULL
+            );
         "#)?;
 
+        Self::add_chunk_line_column(conn)?;
+
+        // Indexes built on Windows before paths were normalized used backslashes;
+        // their FTS filepaths are re-derived by a rebuild
+        #[cfg(windows)]
+        if conn.execute(
+            "UPDATE OR IGNORE documents SET path = replace(path, char(92), '/') WHERE instr(path, char(92)) > 0",
+            [],
+        )? > 0
+        {
+            fts::rebuild(conn)?;
+        }
+
+        Self::drop_unscoped_llm_cache(conn)?;
         conn.execute_batch(r#"
-            -- LLM response cache
+            -- LLM response cache, one entry per key and model
             CREATE TABLE IF NOT EXISTS llm_cache (
-                cache_key TEXT PRIMARY KEY,
+                cache_key TEXT NOT NULL,
                 model TEXT NOT NULL,
                 response TEXT NOT NULL,
                 created_at TEXT NOT NULL,
-                expires_at TEXT
+                expires_at TEXT,
+                PRIMARY KEY (cache_key, model)
+            );
+        "#)?;
+
+        conn.execute_batch(r#"
+            -- Directory descriptions, written by `context add` or `context generate`
+            CREATE TABLE IF NOT EXISTS path_contexts (
+                path TEXT PRIMARY KEY,
+                description TEXT NOT NULL,
+                source TEXT NOT NULL DEFAULT 'manual',
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
             );
         "#)?;
 
@@ -308,6 +1063,119 @@ impl Store {
             Self::migrate_from_old_schema(conn)?;
         }
 
+        Self::backfill_document_sizes(conn)?;
+
+        Ok(())
+    }
+
+    /// Add the bytes/words/tokens columns to an existing documents table
+    fn add_document_size_columns(conn: &Connection) -> Result<()> {
+        let columns: Vec<String> = conn
+            .prepare("PRAGMA table_info(documents)")?
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        for column in ["bytes", "words", "tokens"] {
+            if !columns.iter().any(|c| c == column) {
+                conn.execute(&format!("ALTER TABLE documents ADD COLUMN {} INTEGER", column), [])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the codec and packed columns to an existing content table
+    fn add_content_codec_columns(conn: &Connection) -> Result<()> {
+        let columns: Vec<String> = conn
+            .prepare("PRAGMA table_info(content)")?
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        for (column, kind) in [("codec", "TEXT"), ("packed", "BLOB")] {
+            if !columns.iter().any(|c| c == column) {
+                conn.execute(&format!("ALTER TABLE content ADD COLUMN {} {}", column, kind), [])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop the triggers older databases kept documents_fts in step with,
+    /// since [`fts`] now writes the index, and an FTS view reading
+    /// `content.doc` directly, which would index compressed bodies as empty
+    fn drop_outdated_fts_writers(conn: &Connection) -> Result<()> {
+        conn.execute_batch(r#"
+            DROP TRIGGER IF EXISTS documents_ai;
+            DROP TRIGGER IF EXISTS documents_ad;
+            DROP TRIGGER IF EXISTS documents_au;
+        "#)?;
+        let plain_view: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master
+             WHERE name = 'documents_fts_view' AND sql NOT LIKE '%qmd_doc%'",
+            [],
+            |row| row.get(0),
+        )?;
+        if plain_view {
+            info!("Recreating FTS view to read compressed bodies");
+            conn.execute("DROP VIEW documents_fts_view", [])?;
+        }
+        Ok(())
+    }
+
+    /// Add the line column to an existing content_vectors table
+    fn add_chunk_line_column(conn: &Connection) -> Result<()> {
+        let has_line: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('content_vectors') WHERE name = 'line'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_line {
+            conn.execute("ALTER TABLE content_vectors ADD COLUMN line INTEGER", [])?;
+        }
+        Ok(())
+    }
+
+    /// Drop an llm_cache keyed on cache_key alone, which would serve one
+    /// model's responses to another; it is only a cache, so it is rebuilt empty
+    fn drop_unscoped_llm_cache(conn: &Connection) -> Result<()> {
+        let sql: Option<String> = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type='table' AND name='llm_cache'",
+                [],
+                |row| row.get(0),
+            )
+            .ok();
+        if sql.is_some_and(|sql| sql.contains("cache_key TEXT PRIMARY KEY")) {
+            info!("Recreating llm_cache with per-model keys");
+            conn.execute("DROP TABLE llm_cache", [])?;
+        }
+        Ok(())
+    }
+
+    /// Compute sizes for documents indexed before they were recorded
+    fn backfill_document_sizes(conn: &Connection) -> Result<()> {
+        let missing: Vec<(i64, String)> = conn
+            .prepare(
+                "SELECT d.id, qmd_doc(c.doc, c.codec, c.packed) FROM documents d
+                 JOIN content c ON c.hash = d.hash
+                 WHERE d.tokens IS NULL"
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        info!("Recording sizes for {} documents", missing.len());
+        for (id, doc) in missing {
+            let size = DocumentSize::measure(&doc);
+            conn.execute(
+                "UPDATE documents SET bytes = ?, words = ?, tokens = ? WHERE id = ?",
+                rusqlite::params![size.bytes as i64, size.words as i64, size.tokens as i64, id],
+            )?;
+        }
         Ok(())
     }
 
@@ -332,6 +1200,54 @@ impl Store {
         Ok(has_doc_column && !has_content_table)
     }
 
+    /// Create the external-content FTS5 table unless it exists; [`fts`]
+    /// keeps it in step with `documents`
+    fn create_fts_index(conn: &Connection, tokenizer: &str) -> Result<()> {
+        conn.execute_batch(&format!(r#"
+            -- External-content FTS5 table: the index only, bodies stay in content
+            CREATE VIRTUAL TABLE IF NOT EXISTS documents_fts USING fts5(
+                filepath, title, body,
+                content='documents_fts_view',
+                content_rowid='id',
+                tokenize='{}'
+            );
+        "#, tokenizer))?;
+        Ok(())
+    }
+
+    /// Check if documents_fts stores its own copy of document bodies or was
+    /// built with a different tokenizer
+    fn check_fts_migration_needed(conn: &Connection, tokenizer: &str) -> Result<bool> {
+        let sql: Option<String> = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type='table' AND name='documents_fts'",
+                [],
+                |row| row.get(0),
+            )
+            .ok();
+
+        // Need migration if the table exists but was not created with external
+        // content or with this tokenizer
+        let tokenize = format!("tokenize='{}'", tokenizer);
+        let Some(sql) = sql else {
+            return Ok(false);
+        };
+        if !sql.contains("content=") || !sql.contains(&tokenize) {
+            return Ok(true);
+        }
+
+        // Older update triggers re-indexed rows on every update, so deactivated
+        // documents stayed searchable; the index needs rebuilding
+        let update_trigger: Option<String> = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type='trigger' AND name='documents_au'",
+                [],
+                |row| row.get(0),
+            )
+            .ok();
+        Ok(update_trigger.is_some_and(|t| !t.contains("new.active = 1")))
+    }
+
     /// Migrate from old schema (documents.doc) to new schema (content table)
     fn migrate_from_old_schema(conn: &Connection) -> Result<()> {
         info!("Running schema migration: copying documents to content table");
@@ -347,13 +1263,175 @@ impl Store {
         Ok(())
     }
 
-    /// BM25 full-text search
+    /// Resolve the collections a search covers
+    ///
+    /// `search_all` covers the collections searchable by default, or every
+    /// collection with `really_all`, ordered by priority. Fails with a
+    /// ConfigError when no collections are configured, so an empty setup is
+    /// reported instead of looking like a search with no matches.
+    pub fn search_collections<'a>(&'a self, options: &'a SearchOptions) -> Result<Vec<&'a str>> {
+        if self.config.collections.is_empty() {
+            return Err(no_collections_error().into());
+        }
+
+        Ok(if options.search_all {
+            let mut collections: Vec<_> = self
+                .config
+                .collections
+                .iter()
+                .filter(|c| c.searchable_by_default || options.really_all)
+                .collect();
+            collections.sort_by_key(|c| c.priority);
+            collections.into_iter().map(|c| c.name.as_str()).collect()
+        } else if let Some(ref name) = options.collection {
+            vec![self.resolve_collection(name)?]
+        } else {
+            vec![self.config.collections[0].name.as_str()]
+        })
+    }
+
+    /// Configured name for a requested collection, ignoring case and accents
+    pub fn resolve_collection(&self, name: &str) -> Result<&str> {
+        if let Some(collection) = self.config.find_collection(name) {
+            return Ok(collection.name.as_str());
+        }
+        let configured: Vec<&str> = self.config.collections.iter().map(|c| c.name.as_str()).collect();
+        Err(collection_not_found_error(
+            name,
+            &self.config.similar_collection_names(name),
+            &configured,
+        )
+        .into())
+    }
+
+    /// BM25 query for each collection, with the collection's stopwords removed
+    ///
+    /// Collections where only stopwords remain are skipped; if that is every
+    /// collection the query is rejected as too generic. CJK queries against
+    /// trigram-tokenized collections also get an n-gram fallback query.
+    fn collection_queries(&self, collections: &[&str], query: &str, lang: QueryLanguage) -> Result<Vec<PlannedQuery>> {
+        let mut queries = Vec::new();
+        for &collection in collections {
+            let planned = |query: String, fallback: bool| PlannedQuery {
+                collection: collection.to_string(),
+                query,
+                fallback,
+            };
+            let stopwords = self.config.collections.iter()
+                .find(|c| c.name == collection)
+                .and_then(|c| c.stopwords.as_ref());
+            let fts_query = match stopwords {
+                None => query.to_string(),
+                Some(stopwords) => stopwords::strip_stopwords(query, &stopwords::word_set(stopwords)?),
+            };
+            if fts_query.is_empty() {
+                debug!("Query '{}' is only stopwords for collection {}", query, collection);
+                continue;
+            }
+
+            let fallback = if lang.has_cjk() && self.fts_tokenizer(collection) == FTS_TOKENIZER_CODE {
+                lang::ngram_fallback_query(&fts_query).filter(|q| *q != fts_query)
+            } else {
+                None
+            };
+            queries.push(planned(fts_query, false));
+            if let Some(fallback) = fallback {
+                queries.push(planned(fallback, true));
+            }
+        }
+
+        if queries.is_empty() && !collections.is_empty() {
+            return Err(stopwords::query_too_generic_error(query).into());
+        }
+        Ok(queries)
+    }
+
+    /// Describe how a query will be searched, for `--explain`
+    ///
+    /// `expansions` are the hybrid-search variants besides the original query;
+    /// each is planned like the original.
+    pub fn explain_query(
+        &self,
+        query: &str,
+        expansions: Vec<String>,
+        options: &SearchOptions,
+        lang: Option<QueryLanguage>,
+    ) -> Result<QueryExplain> {
+        let (language, language_source) = lang::resolve_language(query, lang);
+        let collections = self.search_collections(options)?;
+        let expansions: Vec<String> = expansions.into_iter().filter(|q| q != query).collect();
+
+        let mut fts_queries = self.collection_queries(&collections, query, language)?;
+        for expanded in &expansions {
+            // An expansion that is all stopwords is skipped at search time too
+            if let Ok(mut planned) = self.collection_queries(&collections, expanded, language) {
+                fts_queries.append(&mut planned);
+            }
+        }
+
+        Ok(QueryExplain {
+            language,
+            language_source,
+            expansion_rules: language.expansion_rules(),
+            expansions,
+            fts_queries,
+            terms: Vec::new(),
+            candidate_cache: None,
+        })
+    }
+
+    /// Document frequency of each term of the planned FTS queries, per collection
+    ///
+    /// Frequencies come from an `fts5vocab` view of the collection's index. A
+    /// term the tokenizer rewrites (a porter stem, trigrams) has no vocabulary
+    /// row of its own and is counted by matching it against the index instead.
+    pub fn term_frequencies(&self, planned: &[PlannedQuery]) -> Result<Vec<TermFrequency>> {
+        let mut frequencies = Vec::new();
+        for query in planned.iter().filter(|q| !q.fallback) {
+            let terms = bundle::query_terms(&query.query);
+            let counted = self.with_connection(&query.collection, |conn| {
+                conn.execute_batch(
+                    "CREATE VIRTUAL TABLE IF NOT EXISTS temp.documents_fts_vocab
+                     USING fts5vocab(main, 'documents_fts', 'row')",
+                )?;
+                let total: i64 = conn.query_row(
+                    "SELECT COUNT(*) FROM documents WHERE collection = ? AND active = 1",
+                    [&query.collection],
+                    |row| row.get(0),
+                )?;
+                let mut counts = Vec::new();
+                for term in &terms {
+                    counts.push((term.clone(), document_frequency(conn, term)?, total as usize));
+                }
+                Ok(counts)
+            })?;
+            for (term, documents, total_documents) in counted {
+                if !frequencies.iter().any(|f: &TermFrequency| f.collection == query.collection && f.term == term) {
+                    frequencies.push(TermFrequency {
+                        collection: query.collection.clone(),
+                        term,
+                        documents,
+                        total_documents,
+                    });
+                }
+            }
+        }
+        Ok(frequencies)
+    }
+
+    /// BM25 full-text search, in the query's detected language
     pub fn bm25_search(&self, query: &str, options: SearchOptions) -> Result<Vec<SearchResult>> {
+        self.bm25_search_in(query, options, lang::detect_language(query))
+    }
+
+    /// BM25 full-text search, treating the query as written in `lang`
+    #[tracing::instrument(name = "bm25_search", skip(self, options))]
+    pub fn bm25_search_in(&self, query: &str, options: SearchOptions, lang: QueryLanguage) -> Result<Vec<SearchResult>> {
         // Determine which backend to use based on configuration
         match &self.config.bm25.backend {
-            BM25Backend::SqliteFts5 => self.bm25_sqlite_search(query, options),
+            BM25Backend::SqliteFts5 => self.bm25_sqlite_search(query, options, lang),
             #[cfg(feature = "lancedb")]
-            BM25Backend::LanceDb => self.bm25_lance_search(query, options),
+            BM25Backend::LanceDb => self.bm25_lance_search(query, options, lang),
             #[cfg(not(feature = "lancedb"))]
             BM25Backend::LanceDb => {
                 anyhow::bail!("LanceDB backend not enabled. Build with --features lancedb")
@@ -363,95 +1441,125 @@ impl Store {
 
     /// LanceDB FTS search implementation
     #[cfg(feature = "lancedb")]
-    fn bm25_lance_search(&self, query: &str, options: SearchOptions) -> Result<Vec<SearchResult>> {
-        use futures::executor::block_on;
-
+    fn bm25_lance_search(&self, query: &str, options: SearchOptions, lang: QueryLanguage) -> Result<Vec<SearchResult>> {
         let mut all_results = Vec::new();
 
-        let collections: Vec<&str> = if options.search_all {
-            self.config.collections.iter().map(|c| c.name.as_str()).collect()
-        } else if let Some(ref name) = options.collection {
-            vec![name.as_str()]
-        } else if let Some(col) = self.config.collections.first() {
-            vec![col.name.as_str()]
-        } else {
-            return Ok(all_results);
-        };
+        let collections = self.search_collections(&options)?;
+        let queries = self.collection_queries(&collections, query, lang)?;
 
-        let limit = options.limit;
+        // Every collection's hits up to the end of the page, skipped once merged
+        let limit = options.unpaged().limit;
 
-        for collection in collections {
+        for PlannedQuery { collection, query: fts_query, .. } in queries {
+            let collection = collection.as_str();
             if let Some(ref backend_mutex) = self.lance_backend {
-                if let Ok(backend) = backend_mutex.lock() {
-                    let rt = tokio::runtime::Runtime::new()?;
-                    let results = rt.block_on(async {
-                        backend.fts_search(collection, query, limit).await
-                    });
-                    if let Ok(mut results) = results {
-                        all_results.append(&mut results);
-                    }
+                let backend = backend_mutex.lock().unwrap_or_else(|e| e.into_inner());
+                let mut results = runtime::block_on(backend.fts_search(collection, &fts_query, limit))?
+                    .with_context(|| format!("LanceDB full-text search failed for collection {}", collection))?;
+                stamp_collection(collection, &mut results);
+                for result in &mut results {
+                    result.score = bm25_relevance(result.score as f64);
                 }
+                results.retain(|r| meets_min_score(r.score, options.min_score));
+                results.retain(|r| !all_results.iter().any(|seen: &SearchResult| seen.docid == r.docid));
+                all_results.append(&mut results);
             }
         }
 
+        skip_offset(&mut all_results, options.offset);
         Ok(all_results)
     }
 
     /// SQLite FTS5 search implementation
-    fn bm25_sqlite_search(&self, query: &str, options: SearchOptions) -> Result<Vec<SearchResult>> {
-        let mut results = Vec::new();
-
-        let collections: Vec<&str> = if options.search_all {
-            self.config.collections.iter().map(|c| c.name.as_str()).collect()
-        } else if let Some(ref name) = options.collection {
-            vec![name.as_str()]
-        } else if let Some(col) = self.config.collections.first() {
-            vec![col.name.as_str()]
-        } else {
-            return Ok(results);
-        };
+    ///
+    /// The planned queries run concurrently, up to `search.parallelism` at a
+    /// time, and their rows are merged in collection priority order, scored
+    /// by [`bm25_relevance`].
+    ///
+    /// A single query pages in SQL; several fetch up to the end of the page
+    /// each and skip `offset` once merged.
+    fn bm25_sqlite_search(&self, query: &str, options: SearchOptions, lang: QueryLanguage) -> Result<Vec<SearchResult>> {
+        let mut results: Vec<SearchResult> = Vec::new();
 
-        let limit = options.limit;
+        let collections = self.search_collections(&options)?;
+        let queries = self.collection_queries(&collections, query, lang)?;
 
-        for collection in collections {
-            if let Ok(conn) = self.get_connection(collection) {
-                let fts_query = query.to_string();
+        let (limit, sql_offset) = match queries.len() {
+            1 => (options.limit, options.offset),
+            _ => (options.unpaged().limit, 0),
+        };
+        let parent = tracing::Span::current();
 
+        type Row = (String, f64, String, String, String, Option<DocumentSize>, Option<String>);
+        let query_rows = parallel::map_ordered(&queries, self.config.search.parallelism, |planned| {
+            let collection = planned.collection.as_str();
+            let _span = tracing::info_span!(parent: &parent, "bm25_collection", collection).entered();
+            self.with_connection(collection, |conn| {
                 let mut stmt = conn.prepare(
-                    "SELECT rowid, bm25(documents_fts), title, filepath
+                    "SELECT d.hash, bm25(documents_fts), documents_fts.title,
+                            documents_fts.filepath, d.path, d.bytes, d.words, d.tokens,
+                            qmd_doc(c.doc, c.codec, c.packed)
                      FROM documents_fts
-                     WHERE documents_fts MATCH ?
-                     ORDER BY bm25(documents_fts)
-                     LIMIT ?"
+                     JOIN documents d ON d.id = documents_fts.rowid
+                     LEFT JOIN content c ON c.hash = d.hash
+                     WHERE documents_fts MATCH ? AND d.collection = ? AND d.active = 1
+                     ORDER BY bm25(documents_fts), d.id
+                     LIMIT ? OFFSET ?"
                 )?;
 
-                let rows: Vec<(i32, f64, String, String)> = stmt
-                    .query_map((&fts_query, limit as i64), |row| {
-                        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                let rows: Vec<Row> = stmt
+                    .query_map((&planned.query, collection, limit as i64, sql_offset as i64), |row| {
+                        Ok((
+                            row.get(0)?,
+                            row.get(1)?,
+                            row.get(2)?,
+                            row.get(3)?,
+                            row.get(4)?,
+                            DocumentSize::from_row(row, 5)?,
+                            row.get(8)?,
+                        ))
                     })?
-                    .filter_map(|r| r.ok())
-                    .collect();
+                    // A busy or changed database fails on the first step; it
+                    // must reach the pool's retry rather than read as no rows
+                    .collect::<rusqlite::Result<_>>()?;
+                Ok(rows)
+            })
+        });
 
-                for (rowid, score, title, filepath) in rows {
-                    let docid = make_docid(collection, &filepath);
-                    // Calculate line count by reading the file
-                    let lines = std::fs::read_to_string(&filepath)
-                        .map(|content| content.lines().count())
-                        .unwrap_or(0);
-                    results.push(SearchResult {
-                        docid,
-                        path: filepath,
-                        collection: collection.to_string(),
-                        score: score as f32,
-                        lines,
-                        title,
-                        hash: rowid.to_string(),
-                        query: Some(query.to_string()),
-                    });
+        for (planned, rows) in queries.iter().zip(query_rows) {
+            let collection = planned.collection.as_str();
+            let rows = rows?;
+            for (hash, bm25, title, filepath, rel_path, size, doc) in rows {
+                let score = bm25_relevance(bm25);
+                if !meets_min_score(score, options.min_score) {
+                    continue;
                 }
+                let docid = make_docid(collection, &rel_path);
+                // Already found by an earlier query for this collection
+                if results.iter().any(|r| r.docid == docid) {
+                    continue;
+                }
+                // Count lines of the indexed content; the file may have moved since
+                let lines = doc.as_deref().map_or(0, |doc| doc.lines().count());
+                results.push(SearchResult {
+                    docid,
+                    path: filepath,
+                    collection: collection.to_string(),
+                    score,
+                    lines,
+                    title,
+                    hash,
+                    query: Some(query.to_string()),
+                    size,
+                    match_line: doc.as_deref().and_then(|doc| matches::match_line(doc, query)),
+                    duplicates: Vec::new(),
+                    source: vec![SOURCE_BM25.to_string()],
+                    score_components: None,
+                });
             }
         }
 
+        skip_offset(&mut results, options.offset - sql_offset);
         Ok(results)
     }
 
@@ -480,6 +1588,9 @@ impl Store {
     ///
     /// Performs similarity search using a pre-computed embedding vector.
     /// Useful when the embedding has already been generated externally.
+    /// Fails with BackendUnavailable when the builtin backend has no
+    /// sqlite-vec, rather than finding nothing.
+    #[tracing::instrument(skip_all)]
     pub fn vector_search_with_embedding(
         &self,
         query_vector: &[f32],
@@ -488,6 +1599,9 @@ impl Store {
         // Dispatch based on vector backend configuration
         match &self.config.vector.backend {
             VectorBackend::QmdBuiltin => {
+                if let Some(error) = self.vector_backend_error() {
+                    return Err(error.into());
+                }
                 self.vector_search_sqlite(query_vector, options)
             }
             #[cfg(feature = "lancedb")]
@@ -518,26 +1632,26 @@ impl Store {
         // Perform vector search in each collection
         let mut results = Vec::new();
 
-        let collections: Vec<&str> = if options.search_all {
-            self.config.collections.iter().map(|c| c.name.as_str()).collect()
-        } else if let Some(ref name) = options.collection {
-            vec![name.as_str()]
-        } else if let Some(col) = self.config.collections.first() {
-            vec![col.name.as_str()]
-        } else {
-            return Ok(results);
-        };
+        let collections = self.search_collections(&options)?;
 
-        for collection in collections {
-            if let Ok(conn) = self.get_connection(collection) {
-                let collection_results = self.vector_search_in_db(&conn, query_vector, options.limit)?;
-                results.extend(collection_results);
-            }
+        // One collection pages in SQL; several are merged, then skipped
+        let (limit, sql_offset) = match collections.len() {
+            1 => (options.limit, options.offset),
+            _ => (options.unpaged().limit, 0),
+        };
+        let per_collection = parallel::map_ordered(&collections, self.config.search.parallelism, |collection| {
+            self.with_connection(collection, |conn| {
+                self.vector_search_in_db(conn, collection, query_vector, limit, sql_offset, options.min_score)
+            })
+        });
+        for collection_results in per_collection {
+            results.extend(collection_results?);
         }
 
-        // Sort by score (cosine distance - lower is better)
-        results.sort_by(|a, b| a.score.partial_cmp(&b.score).unwrap());
+        // Sort by score (cosine similarity - higher is better)
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
 
+        skip_offset(&mut results, options.offset - sql_offset);
         Ok(results)
     }
 
@@ -550,30 +1664,20 @@ impl Store {
     ) -> Result<Vec<SearchResult>> {
         let mut all_results = Vec::new();
 
-        let collections: Vec<&str> = if options.search_all {
-            self.config.collections.iter().map(|c| c.name.as_str()).collect()
-        } else if let Some(ref name) = options.collection {
-            vec![name.as_str()]
-        } else if let Some(col) = self.config.collections.first() {
-            vec![col.name.as_str()]
-        } else {
-            return Ok(all_results);
-        };
+        let collections = self.search_collections(&options)?;
 
         for collection in collections {
             if let Some(ref backend_mutex) = self.lance_backend {
-                if let Ok(backend) = backend_mutex.lock() {
-                    let rt = tokio::runtime::Runtime::new()?;
-                    let results = rt.block_on(async {
-                        backend.vector_search(collection, query_vector, options.limit).await
-                    });
-                    if let Ok(results) = results {
-                        all_results.extend(results);
-                    }
-                }
+                let backend = backend_mutex.lock().unwrap_or_else(|e| e.into_inner());
+                let mut results = runtime::block_on(backend.vector_search(collection, query_vector, options.unpaged().limit))?
+                    .with_context(|| format!("LanceDB vector search failed for collection {}", collection))?;
+                stamp_collection(collection, &mut results);
+                results.retain(|r| meets_min_score(r.score, options.min_score));
+                all_results.extend(results);
             }
         }
 
+        skip_offset(&mut all_results, options.offset);
         Ok(all_results)
     }
 
@@ -584,94 +1688,143 @@ impl Store {
         query_vector: &[f32],
         options: SearchOptions,
     ) -> Result<Vec<SearchResult>> {
-        if let Some(ref backend_mutex) = self.qdrant_backend {
-            if let Ok(backend) = backend_mutex.lock() {
-                let rt = tokio::runtime::Runtime::new()?;
-                let results = rt.block_on(async {
-                    backend
-                        .vector_search(query_vector, options.limit, None)
-                        .await
-                });
-                return results;
+        if self.config.collections.is_empty() {
+            return Err(no_collections_error().into());
+        }
+
+        let Some(ref backend) = self.qdrant_backend else {
+            return Ok(Vec::new());
+        };
+        let collections = self.search_collections(&options)?;
+
+        // Points are chunks: ask for extra hits so that keeping the best
+        // chunk of each document still fills the limit
+        let limit = options.unpaged().limit;
+        let hits = runtime::block_on(backend.vector_search(query_vector, limit * 4, None))??;
+
+        let mut results: Vec<SearchResult> = Vec::new();
+        for hit in hits {
+            if !collections.contains(&hit.collection.as_str())
+                || !meets_min_score(hit.score, options.min_score)
+                || results.iter().any(|r| r.docid == hit.docid)
+            {
+                continue;
+            }
+            results.push(hit);
+            if results.len() == limit {
+                break;
             }
         }
-        Ok(Vec::new())
+        skip_offset(&mut results, options.offset);
+        Ok(results)
+    }
+
+    /// The Qdrant backend, when `vector.backend` is `qdrant`
+    #[cfg(feature = "qdrant")]
+    pub fn qdrant_backend(&self) -> Option<Arc<QdrantBackend>> {
+        self.qdrant_backend.clone()
     }
 
     /// Perform vector search in a single database
     fn vector_search_in_db(
         &self,
         _conn: &Connection,
+        _collection: &str,
         _query_vector: &[f32],
         _limit: usize,
+        _offset: usize,
+        _min_score: f32,
     ) -> Result<Vec<SearchResult>> {
-        let mut results = Vec::new();
-
-        // Try sqlite-vec first
         #[cfg(feature = "sqlite-vec")]
         {
-            results = self.vector_search_sqlite_vec(_conn, _query_vector, _limit)?;
+            self.vector_search_sqlite_vec(_conn, _collection, _query_vector, _limit, _offset, _min_score)
         }
-
-        // Fallback to BM25 if no results or sqlite-vec not available
-        if results.is_empty() {
-            warn!("Falling back to BM25 for vector search");
-            // Convert query_vector back to a simple text search
-            // This is a placeholder fallback
+        // Unreachable in practice: the builtin backend fails fast without sqlite-vec
+        #[cfg(not(feature = "sqlite-vec"))]
+        {
+            Err(vector_backend_unavailable_error("built without the sqlite-vec feature").into())
         }
-
-        Ok(results)
     }
 
     /// SQLite vector search using sqlite-vec
     ///
     /// Aggregates chunks back to document level by taking the best (minimum distance)
-    /// chunk per document via GROUP BY.
+    /// chunk per document via GROUP BY and scores it by its similarity
+    /// (1 - cosine distance), like the other vector backends. Documents whose
+    /// best chunk is less than `min_score` similar are dropped.
     #[cfg(feature = "sqlite-vec")]
     fn vector_search_sqlite_vec(
         &self,
         conn: &Connection,
+        collection: &str,
         query_vector: &[f32],
         limit: usize,
+        offset: usize,
+        min_score: f32,
     ) -> Result<Vec<SearchResult>> {
         let mut results = Vec::new();
 
+        // A query of the wrong width is a config problem, not a SQLite error
+        if let Some(dimension) = vector_table_dimension(conn)? {
+            if query_vector.len() != dimension {
+                return Err(vector_dimension_error(dimension, query_vector.len()).into());
+            }
+        }
+
         // Convert query vector to JSON array format for sqlite-vec
         let query_vec_json = serde_json::to_string(query_vector)?;
 
         // Use sqlite-vec's vec_distance_cosine function for similarity search.
-        // GROUP BY cv.hash aggregates multiple chunks back to one result per document,
-        // taking the best (minimum distance) chunk score.
+        // GROUP BY d.id aggregates multiple chunks back to one result per document,
+        // taking the best (minimum distance) chunk score. Copies sharing a hash
+        // share its vectors and each come back under their own path.
         let mut stmt = conn.prepare(
             "SELECT
                 cv.hash,
                 d.path,
                 d.title,
                 d.collection,
-                MIN(vec_distance_cosine(v.embedding, ?)) as distance
+                MIN(vec_distance_cosine(v.embedding, ?)) as distance,
+                d.bytes,
+                d.words,
+                d.tokens,
+                cv.pos,
+                qmd_doc(c.doc, c.codec, c.packed),
+                cv.line
              FROM content_vectors cv
              JOIN vectors_vec v ON v.hash_seq = cv.hash || '_' || cv.seq
              JOIN documents d ON d.hash = cv.hash
-             WHERE d.active = 1
-             GROUP BY cv.hash
-             ORDER BY distance ASC
-             LIMIT ?"
+             LEFT JOIN content c ON c.hash = cv.hash
+             WHERE d.collection = ? AND d.active = 1
+             GROUP BY d.id
+             ORDER BY distance ASC, cv.hash, d.path
+             LIMIT ? OFFSET ?"
         )?;
 
-        let rows: Vec<(String, String, String, String, f64)> = stmt
-            .query_map(rusqlite::params![query_vec_json, limit as i64], |row| {
+        // With MIN(), SQLite takes cv.pos and cv.line from the closest chunk's row
+        type Row = (String, String, String, String, f64, Option<DocumentSize>, i64, Option<String>, Option<i64>);
+        let rows: Vec<Row> = stmt
+            .query_map(rusqlite::params![query_vec_json, collection, limit as i64, offset as i64], |row| {
                 Ok((
                     row.get(0)?,
                     row.get(1)?,
                     row.get(2)?,
                     row.get(3)?,
                     row.get(4)?,
+                    DocumentSize::from_row(row, 5)?,
+                    row.get(8)?,
+                    row.get(9)?,
+                    row.get(10)?,
                 ))
             })?
             .filter_map(|r| r.ok())
             .collect();
 
-        for (hash, path, title, collection, distance) in rows {
+        for (hash, path, title, collection, distance, size, pos, doc, line) in rows {
+            let score = cosine_similarity(distance);
+            if !meets_min_score(score, min_score) {
+                continue;
+            }
             let docid = make_docid(&collection, &path);
             // Calculate line count
             let lines = std::fs::read_to_string(&path)
@@ -681,28 +1834,25 @@ impl Store {
                 docid,
                 path,
                 collection,
-                score: distance as f32,
+                score,
                 lines,
                 title,
                 hash,
                 query: None,
+                size,
+                // Chunks embedded before lines were recorded fall back to the offset
+                match_line: line
+                    .map(|line| line as usize)
+                    .or_else(|| doc.as_deref().map(|doc| matches::line_at(doc, pos as usize))),
+                duplicates: Vec::new(),
+                source: vec![SOURCE_VECTOR.to_string()],
+                score_components: None,
             });
         }
 
         Ok(results)
     }
 
-    #[cfg(not(feature = "sqlite-vec"))]
-    fn vector_search_sqlite_vec(
-        &self,
-        _conn: &Connection,
-        _query_vector: &[f32],
-        _limit: usize,
-    ) -> Result<Vec<SearchResult>> {
-        warn!("sqlite-vec feature not enabled");
-        Ok(Vec::new())
-    }
-
     /// Hybrid search with reranking
     ///
     /// Combines BM25 and vector search with query expansion and LLM reranking:
@@ -717,68 +1867,34 @@ impl Store {
         options: SearchOptions,
         llm: &Router,
     ) -> Result<Vec<SearchResult>> {
-        // Step 1: Query expansion using LLM
-        let expanded_queries = llm.expand_query(query)?;
-
-        info!("Hybrid search: original='{}', expanded={} variants", query, expanded_queries.len());
-
-        // Step 2: BM25 retrieval for all expanded queries
-        let mut all_bm25_results = Vec::new();
-
-        for expanded_query in &expanded_queries {
-            // BM25 search
-            let bm25_results = self.bm25_search(expanded_query, options.clone())?;
-            all_bm25_results.extend(bm25_results);
-        }
-
-        // Limit intermediate results to avoid memory issues
-        all_bm25_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-        all_bm25_results.truncate(100);
-
-        // Step 3: Vector search for original query
-        let vector_results = self.vector_search_with_embedder_async(query, options.clone(), llm).await?;
-
-        info!("BM25 results: {}, Vector results: {}", all_bm25_results.len(), vector_results.len());
-
-        // Step 4: RRF fusion of BM25 and vector results
-        let result_lists = vec![all_bm25_results, vector_results];
-        let weights = Some(vec![1.0, 1.5]); // Give more weight to vector search
-        let mut fused = Self::rrf_fusion(&result_lists, weights, 60);
-
-        // Sort by RRF score (higher is better)
-        fused.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-
-        // Step 5: Top 30 for reranking
-        let candidates: Vec<SearchResult> = fused.into_iter().take(30).collect();
-
-        // Step 6: Try LLM reranking if available
-        let final_results = if llm.has_reranker() {
-            info!("LLM reranking available, applying to top candidates");
-            match llm.rerank(query, &candidates).await {
-                Ok(scores) => {
-                    // Apply reranking scores
-                    let mut reranked: Vec<_> = candidates
-                        .into_iter()
-                        .zip(scores)
-                        .map(|(mut doc, score)| {
-                            doc.score = score;
-                            doc
-                        })
-                        .collect();
-                    // Sort by reranking score (higher is better)
-                    reranked.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-                    reranked
-                }
-                Err(e) => {
-                    warn!("LLM reranking failed: {}, using original candidates", e);
-                    candidates
-                }
-            }
-        } else {
-            candidates
-        };
+        self.hybrid_search_in(query, options, llm, lang::detect_language(query)).await
+    }
+
+    /// Hybrid search, treating the query as written in `lang`
+    ///
+    /// The language picks the expansion rules and whether trigram-tokenized
+    /// collections also get an n-gram fallback query.
+    pub async fn hybrid_search_in(
+        &self,
+        query: &str,
+        options: SearchOptions,
+        llm: &Router,
+        lang: QueryLanguage,
+    ) -> Result<Vec<SearchResult>> {
+        let outcome = self.hybrid_search_within(query, options, llm, lang, &Deadline::unlimited()).await?;
+        Ok(outcome.results)
+    }
 
-        Ok(final_results)
+    /// Hybrid search bounded by `deadline`; see [`run_hybrid_search`]
+    pub async fn hybrid_search_within(
+        &self,
+        query: &str,
+        options: SearchOptions,
+        llm: &Router,
+        lang: QueryLanguage,
+        deadline: &Deadline,
+    ) -> Result<SearchOutcome> {
+        run_hybrid_search(self, query, options, llm, lang, deadline).await
     }
 
     /// RRF (Reciprocal Rank Fusion) algorithm
@@ -792,6 +1908,8 @@ impl Store {
     /// - k is a constant (typically 60)
     ///
     /// The algorithm also applies a Top-Rank Bonus to give extra weight to highly-ranked results.
+    /// Each fused result's `source` collects the sources of every list that found it.
+    #[tracing::instrument(skip_all, fields(lists = result_lists.len()))]
     pub fn rrf_fusion(
         result_lists: &[Vec<SearchResult>],
         weights: Option<Vec<f32>>,
@@ -799,13 +1917,18 @@ impl Store {
     ) -> Vec<SearchResult> {
         let weights = weights.unwrap_or_else(|| vec![1.0; result_lists.len()]);
 
-        // Map from document identifier (path) to aggregated data
+        // Map from docid to aggregated data; bare paths collide across collections
         type DocData = (
             f32,                    // accumulated RRF score
             String,                 // collection
             usize,                  // lines
             String,                 // title
             String,                 // hash
+            Option<DocumentSize>,   // size
+            Option<usize>,          // match line
+            Vec<String>,            // sources
+            ScoreComponents,        // per-arm ranks
+            String,                 // path, as the first list reported it
         );
         let mut doc_map: HashMap<String, DocData> = HashMap::new();
 
@@ -817,32 +1940,47 @@ impl Store {
                 let rank_plus_k = k + rank as u32;
                 let rrf_score = weight as f64 / rank_plus_k as f64;
 
-                // Use path as unique identifier
-                let path_key = result.path.clone();
+                let docid = if result.docid.is_empty() {
+                    make_docid(&result.collection, &result.path)
+                } else {
+                    result.docid.clone()
+                };
 
-                doc_map.entry(path_key).and_modify(|data| {
-                    data.0 += rrf_score as f32;
-                }).or_insert((
-                    rrf_score as f32,         // initial RRF score
+                let data = doc_map.entry(docid).or_insert_with(|| (
+                    0.0,                       // RRF score, accumulated below
                     result.collection.clone(), // collection
-                    result.lines,             // lines
-                    result.title.clone(),     // title
-                    result.hash.clone(),      // hash
+                    result.lines,              // lines
+                    result.title.clone(),      // title
+                    result.hash.clone(),       // hash
+                    None,                      // size
+                    None,                      // match line
+                    Vec::new(),                // sources
+                    ScoreComponents::default(),
+                    result.path.clone(),       // path
                 ));
+                data.0 += rrf_score as f32;
+                data.5 = data.5.or(result.size);
+                data.6 = data.6.or(result.match_line);
+                for source in &result.source {
+                    if !data.7.contains(source) {
+                        data.7.push(source.clone());
+                    }
+                }
+                data.8.record_rank(&result.source, rank + 1);
             }
         }
 
-        // Sort by RRF score (descending)
+        // Sort by RRF score (descending); ties by docid, so pages stay stable
         let mut results: Vec<_> = doc_map.into_iter().collect();
-        results.sort_by(|a, b| b.1.0.partial_cmp(&a.1.0).unwrap());
+        results.sort_by(|a, b| b.1.0.partial_cmp(&a.1.0).unwrap().then_with(|| a.0.cmp(&b.0)));
 
         // Apply Top-Rank Bonus and construct final results
-        results.into_iter().enumerate().map(|(rank, (path, data))| {
+        results.into_iter().enumerate().map(|(rank, (docid, data))| {
             let mut final_score = data.0;
 
             // Top-Rank Bonus: extra points for highly-ranked documents
             if rank == 0 {
-                final_score += 0.05; // First place bonus
+                final_score += RRF_FIRST_PLACE_BONUS;
             } else if rank < 3 {
                 final_score += 0.02; // Top 3 bonus
             } else if rank < 10 {
@@ -850,29 +1988,46 @@ impl Store {
             }
 
             SearchResult {
-                docid: make_docid(&data.1, &path),
-                path,
+                docid,
+                path: data.9,
                 collection: data.1,
                 score: final_score,
                 lines: data.2,
                 title: data.3,
                 hash: data.4,
                 query: None,
+                size: data.5,
+                match_line: data.6,
+                duplicates: Vec::new(),
+                source: data.7,
+                score_components: Some(ScoreComponents { rrf_score: final_score, ..data.8 }),
             }
         }).collect()
     }
 
     /// Set a path context (upsert)
     pub fn set_path_context(&self, collection: &str, path: &str, description: &str) -> Result<()> {
+        self.set_path_context_from(collection, path, description, ContextSource::Manual)
+    }
+
+    /// Set a path context, recording whether it was written by hand or generated
+    pub fn set_path_context_from(
+        &self,
+        collection: &str,
+        path: &str,
+        description: &str,
+        source: ContextSource,
+    ) -> Result<()> {
         let conn = self.get_connection(collection)?;
         let now = chrono::Utc::now().to_rfc3339();
         conn.execute(
-            "INSERT INTO path_contexts (path, description, created_at, updated_at)
-             VALUES (?, ?, ?, ?)
+            "INSERT INTO path_contexts (path, description, source, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?)
              ON CONFLICT(path) DO UPDATE SET
                 description = excluded.description,
+                source = excluded.source,
                 updated_at = excluded.updated_at",
-            [path, description, &now, &now],
+            [path, description, source.as_str(), &now, &now],
         )?;
         Ok(())
     }
@@ -914,14 +2069,14 @@ impl Store {
         Ok(deleted > 0)
     }
 
-    /// Get a cached LLM response
-    pub fn cache_get(&self, collection: &str, cache_key: &str) -> Result<Option<String>> {
+    /// Get a cached LLM response; entries written for another model miss
+    pub fn cache_get(&self, collection: &str, cache_key: &str, model: &str) -> Result<Option<String>> {
         let conn = self.get_connection(collection)?;
         let now = chrono::Utc::now().to_rfc3339();
         let result = conn.query_row(
             "SELECT response FROM llm_cache
-             WHERE cache_key = ? AND (expires_at IS NULL OR expires_at > ?)",
-            [cache_key, &now],
+             WHERE cache_key = ? AND model = ? AND (expires_at IS NULL OR expires_at > ?)",
+            [cache_key, model, &now],
             |row| row.get::<_, String>(0),
         );
         match result {
@@ -932,6 +2087,9 @@ impl Store {
     }
 
     /// Set a cached LLM response
+    ///
+    /// Without an explicit `ttl_seconds` the configured `cache.ttl_seconds`
+    /// applies. Expired entries in the collection are swept first.
     pub fn cache_set(
         &self,
         collection: &str,
@@ -943,14 +2101,17 @@ impl Store {
         let conn = self.get_connection(collection)?;
         let now = chrono::Utc::now();
         let created_at = now.to_rfc3339();
-        let expires_at = ttl_seconds.map(|ttl| {
+        conn.execute(
+            "DELETE FROM llm_cache WHERE expires_at IS NOT NULL AND expires_at <= ?",
+            [&created_at],
+        )?;
+        let expires_at = ttl_seconds.or(self.config.cache.ttl_seconds).map(|ttl| {
             (now + chrono::Duration::seconds(ttl)).to_rfc3339()
         });
         conn.execute(
             "INSERT INTO llm_cache (cache_key, model, response, created_at, expires_at)
              VALUES (?, ?, ?, ?, ?)
-             ON CONFLICT(cache_key) DO UPDATE SET
-                model = excluded.model,
+             ON CONFLICT(cache_key, model) DO UPDATE SET
                 response = excluded.response,
                 created_at = excluded.created_at,
                 expires_at = excluded.expires_at",
@@ -979,118 +2140,163 @@ impl Store {
 
     /// Update index
     pub fn update_index(&self) -> Result<()> {
-        use std::io::Read;
+        self.update_index_report().map(|_| ())
+    }
+
+    /// Update index, returning the documents whose content changed
+    pub fn update_index_tracked(&self) -> Result<Vec<ChangedDocument>> {
+        self.update_index_report().map(|report| report.changed)
+    }
+
+    /// Update index, reporting what changed and which files could not be read
+    ///
+    /// A file that cannot be read or is malformed is recorded in the report's
+    /// `errors` and the run moves on. A missing collection path or an
+    /// unusable database aborts the update.
+    #[tracing::instrument(skip_all)]
+    pub fn update_index_report(&self) -> Result<UpdateReport> {
+        let mut report = UpdateReport::default();
 
         for collection in &self.config.collections {
+            let _span = tracing::info_span!("update_collection", collection = %collection.name).entered();
             info!("Updating collection: {}", collection.name);
 
             // Expand the path
-            let base_path = shellexpand::tilde(&collection.path.to_string_lossy())
-                .parse::<std::path::PathBuf>()?;
+            let base_path = crate::paths::expand(&collection.path.to_string_lossy())?;
+            if !base_path.is_dir() {
+                anyhow::bail!(
+                    "Collection path for {} does not exist: {}",
+                    collection.name,
+                    base_path.display()
+                );
+            }
 
-            // Get glob pattern
-            let pattern = collection.pattern.as_deref().unwrap_or("**/*");
-            let glob_path = base_path.join(pattern);
-            let glob_pattern = glob_path.to_string_lossy();
+            let mut conn = self
+                .get_connection(&collection.name)
+                .with_context(|| format!("Database for {} is unusable", collection.name))?;
 
-            info!("Scanning files with pattern: {}", glob_pattern);
+            let pattern = collection.pattern.as_deref().unwrap_or("**/*");
+            info!("Scanning files with pattern: {}", pattern);
 
-            // Find matching files
-            let entries = glob::glob(&glob_pattern)?;
+            let (files, walk_errors) = walk::collection_files(&base_path, pattern, self.config.follow_symlinks)?;
 
             let mut file_count = 0;
             let mut skip_count = 0;
+            let error_count = report.errors.len();
 
-            for entry in entries {
-                match entry {
-                    Ok(path) => {
-                        if !path.is_file() {
-                            continue;
-                        }
-
-                        // Read file content
-                        let mut file = std::fs::File::open(&path)?;
-                        let mut content = String::new();
-                        file.read_to_string(&mut content)?;
-
-                        // Calculate hash of content
-                        let hash = Self::calculate_hash(&content);
-
-                        // Get relative path from base
-                        let rel_path = path.strip_prefix(&base_path)
-                            .unwrap_or(&path)
-                            .to_string_lossy()
-                            .into_owned();
-
-                        // Get file metadata
-                        let metadata = std::fs::metadata(&path)?;
-                        let modified: chrono::DateTime<chrono::Utc> = metadata.modified()?.into();
-                        let created: chrono::DateTime<chrono::Utc> = metadata.created()?.into();
-
-                        // Extract title from filename
-                        let title = path.file_stem()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .into_owned();
-
-                        // Insert or update document
-                        let conn = match self.get_connection(&collection.name) {
-                            Ok(c) => c,
-                            Err(e) => {
-                                warn!("Failed to get connection for {}: {}", collection.name, e);
-                                continue;
-                            }
-                        };
-
-                        // Check if document exists and is modified
-                        let existing_hash: Option<String> = conn.query_row(
-                            "SELECT hash FROM documents WHERE path = ? AND collection = ?",
-                            [&rel_path, &collection.name],
-                            |row| row.get(0)
-                        ).ok();
-
-                        if existing_hash.as_ref() == Some(&hash) {
-                            // Document unchanged, skip
-                            skip_count += 1;
-                            continue;
-                        }
+            for walk::WalkError { relative, error } in walk_errors {
+                let error = anyhow::Error::new(error);
+                warn!("Failed to access {}: {:#}", relative, error);
+                report.errors.push(FileError::new(&collection.name, relative, &error));
+            }
 
-                        // Upsert content first (content-addressable storage)
-                        conn.execute(
-                            "INSERT OR REPLACE INTO content (hash, doc, created_at)
-                             VALUES (?, ?, ?)",
-                            [&hash, &content, &created.to_rfc3339()],
-                        )?;
-
-                        // Then upsert document reference
-                        conn.execute(
-                            "INSERT INTO documents (collection, path, title, hash, created_at, modified_at, active)
-                             VALUES (?, ?, ?, ?, ?, ?, 1)
-                             ON CONFLICT(collection, path) DO UPDATE SET
-                                title = excluded.title,
-                                hash = excluded.hash,
-                                modified_at = excluded.modified_at,
-                                active = 1",
-                            [&collection.name, &rel_path, &title, &hash,
-                             &created.to_rfc3339(), &modified.to_rfc3339()],
-                        )?;
-
-                        file_count += 1;
-                    }
+            for walk::CollectionFile { path, relative: rel_path } in files {
+                let file = match Self::read_indexed_file(&path, &self.config) {
+                    Ok(file) => file,
                     Err(e) => {
-                        warn!("Failed to access path: {:?}", e);
+                        warn!("Skipping {}: {:#}", rel_path, e);
+                        report.errors.push(FileError::new(&collection.name, rel_path, &e));
+                        continue;
                     }
+                };
+                let IndexedFile { content, hash, created, modified } = file;
+
+                let title = extract::document_title(&path, &rel_path, &content);
+
+                // Check if document exists and is modified
+                let existing_hash: Option<String> = conn.query_row(
+                    "SELECT hash FROM documents WHERE path = ? AND collection = ? AND active = 1",
+                    [&rel_path, &collection.name],
+                    |row| row.get(0)
+                ).ok();
+
+                if existing_hash.as_ref() == Some(&hash) {
+                    // Document unchanged, skip; a deactivated document is
+                    // upserted again below so it becomes searchable
+                    skip_count += 1;
+                    continue;
                 }
+
+                // Store content first (content-addressable storage)
+                compress::insert_content(
+                    &conn,
+                    &hash,
+                    &content,
+                    self.compresses_content(&collection.name),
+                    &created.to_rfc3339(),
+                )?;
+
+                // Then upsert document reference, swapping its FTS row
+                let size = DocumentSize::measure(&content);
+                let tx = conn.unchecked_transaction()?;
+                fts::unindex(&tx, "d.collection = ? AND d.path = ?", [&collection.name, &rel_path])?;
+                tx.execute(
+                    "INSERT INTO documents (collection, path, title, hash, created_at, modified_at, active,
+                                            bytes, words, tokens)
+                     VALUES (?, ?, ?, ?, ?, ?, 1, ?, ?, ?)
+                     ON CONFLICT(collection, path) DO UPDATE SET
+                        title = excluded.title,
+                        hash = excluded.hash,
+                        modified_at = excluded.modified_at,
+                        active = 1,
+                        bytes = excluded.bytes,
+                        words = excluded.words,
+                        tokens = excluded.tokens",
+                    rusqlite::params![&collection.name, &rel_path, &title, &hash,
+                     &created.to_rfc3339(), &modified.to_rfc3339(),
+                     size.bytes as i64, size.words as i64, size.tokens as i64],
+                )?;
+                fts::index(&tx, "d.collection = ? AND d.path = ?", [&collection.name, &rel_path])?;
+                tx.commit()?;
+
+                report.changed.push(ChangedDocument {
+                    collection: collection.name.clone(),
+                    path: rel_path,
+                    hash,
+                });
+                file_count += 1;
+            }
+
+            // Indexes from before the vocabulary existed get theirs on the next update
+            let vocabulary_empty: bool =
+                conn.query_row("SELECT NOT EXISTS (SELECT 1 FROM vocabulary)", [], |row| row.get(0))?;
+            if file_count > 0 || vocabulary_empty {
+                suggest::rebuild_vocabulary(&mut conn)?;
             }
 
-            info!("Updated {} files ({} unchanged)", file_count, skip_count);
+            report.indexed += file_count;
+            report.unchanged += skip_count;
+            info!(
+                "Updated {} files ({} unchanged, {} failed)",
+                file_count,
+                skip_count,
+                report.errors.len() - error_count
+            );
+        }
+
+        if !report.errors.is_empty() {
+            warn!("{} files could not be indexed", report.errors.len());
         }
 
-        Ok(())
+        Ok(report)
+    }
+
+    /// Read a file's indexed text, hash and timestamps
+    fn read_indexed_file(path: &Path, config: &Config) -> Result<IndexedFile> {
+        let content = extract::read_document(path, config)?;
+        let hash = Self::calculate_hash(&content);
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata.modified()?;
+        Ok(IndexedFile {
+            content,
+            hash,
+            created: created_or_modified(metadata.created(), modified),
+            modified: modified.into(),
+        })
     }
 
     /// Calculate SHA256 hash of content
-    fn calculate_hash(content: &str) -> String {
+    pub fn calculate_hash(content: &str) -> String {
         use sha2::{Sha256, Digest};
         let mut hasher = Sha256::new();
         hasher.update(content.as_bytes());
@@ -1099,33 +2305,71 @@ impl Store {
 
     /// Get index statistics
     pub fn get_stats(&self) -> Result<IndexStats> {
+        self.get_stats_for(None)
+    }
+
+    /// Statistics over the named collections only; `None` covers all of them
+    pub fn get_stats_for(&self, names: Option<&[String]>) -> Result<IndexStats> {
+        let collections: Vec<&crate::config::CollectionConfig> = self
+            .config
+            .collections
+            .iter()
+            .filter(|c| names.is_none_or(|names| names.contains(&c.name)))
+            .collect();
         let mut stats = IndexStats {
-            collection_count: self.config.collections.len(),
+            collection_count: collections.len(),
             ..Default::default()
         };
+        // Pending means no vectors from the model `embed` would use
+        let model = self
+            .config
+            .models
+            .embed
+            .as_ref()
+            .and_then(|embed| embed.local.as_deref().or(embed.remote.as_deref()));
+        let mut index_files = HashSet::new();
 
-        for collection in &self.config.collections {
+        for collection in collections {
             if let Ok(conn) = self.get_connection(&collection.name) {
-                let count: i64 = conn.query_row(
-                    "SELECT COUNT(*) FROM documents WHERE active = 1",
-                    [],
-                    |row| row.get(0)
-                ).unwrap_or(0);
-
+                let (count, tokens, inactive): (i64, i64, i64) = conn.query_row(
+                    "SELECT COALESCE(SUM(active = 1), 0),
+                            COALESCE(SUM(CASE WHEN active = 1 THEN tokens END), 0),
+                            COALESCE(SUM(active = 0), 0)
+                     FROM documents
+                     WHERE collection = ?",
+                    [&collection.name],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                ).unwrap_or((0, 0, 0));
+
+                // Chunks shared only by inactive documents are not searchable
                 let chunks: i64 = conn.query_row(
-                    "SELECT COUNT(*) FROM content_vectors",
-                    [],
+                    "SELECT COUNT(*) FROM content_vectors
+                     WHERE hash IN (SELECT hash FROM documents WHERE collection = ? AND active = 1)",
+                    [&collection.name],
                     |row| row.get(0)
                 ).unwrap_or(0);
 
                 stats.document_count += count as usize;
                 stats.chunk_count += chunks as usize;
                 stats.collection_stats.insert(collection.name.clone(), count as usize);
+                stats.collection_chunks.insert(collection.name.clone(), chunks as usize);
+                stats.token_count += tokens as usize;
+                stats.collection_tokens.insert(collection.name.clone(), tokens as usize);
+                stats.inactive_count += inactive as usize;
+                stats.collection_inactive.insert(collection.name.clone(), inactive as usize);
+                stats.pending_count += self.pending_embeddings(&collection.name, model).map(|p| p.len()).unwrap_or(0);
+
+                let db_path = self.config.db_path_for(&collection.name);
+                let index_bytes = index_file_bytes(&db_path);
+                // A shared database is counted once in the total
+                if index_files.insert(db_path) {
+                    stats.index_bytes += index_bytes;
+                }
+                stats.collection_index_bytes.insert(collection.name.clone(), index_bytes);
             }
         }
 
         stats.indexed_count = stats.document_count;
-        stats.pending_count = 0;
 
         Ok(stats)
     }
@@ -1136,15 +2380,15 @@ impl Store {
 
         for collection in &self.config.collections {
             if let Ok(conn) = self.get_connection(&collection.name) {
-                let mut stmt = conn.prepare("SELECT path FROM documents WHERE active = 1")?;
+                let mut stmt = conn.prepare("SELECT path FROM documents WHERE collection = ? AND active = 1")?;
                 let paths: Vec<String> = stmt
-                    .query_map([], |row| row.get(0))?
+                    .query_map([&collection.name], |row| row.get(0))?
                     .filter_map(|r| r.ok())
                     .collect();
 
                 for path in paths {
-                    // Check if file exists
-                    if !std::path::Path::new(&path).exists() {
+                    // Paths are stored relative to the collection root
+                    if !native_path(&collection.path, &path).exists() {
                         stale_paths.push(path);
                     }
                 }
@@ -1157,19 +2401,38 @@ impl Store {
     /// Remove stale entries from database
     pub fn remove_stale_entries(&self, entries: &[String]) -> Result<()> {
         for collection in &self.config.collections {
-            if let Ok(conn) = self.get_connection(&collection.name) {
+            if let Ok(mut conn) = self.get_connection(&collection.name) {
                 for path in entries {
                     // Soft delete - mark as inactive
-                    conn.execute(
-                        "UPDATE documents SET active = 0 WHERE path = ?",
-                        [path],
+                    let tx = conn.transaction()?;
+                    fts::unindex(&tx, "d.collection = ? AND d.path = ?", [&collection.name, path])?;
+                    tx.execute(
+                        "UPDATE documents SET active = 0 WHERE collection = ? AND path = ?",
+                        [&collection.name, path],
                     )?;
+                    tx.commit()?;
                 }
+                suggest::rebuild_vocabulary(&mut conn)?;
             }
+            #[cfg(feature = "lancedb")]
+            self.deactivate_in_lance(&collection.name, entries)?;
         }
         Ok(())
     }
 
+    /// Mirror soft deletes into LanceDB so its searches skip the documents
+    #[cfg(feature = "lancedb")]
+    fn deactivate_in_lance(&self, collection: &str, paths: &[String]) -> Result<()> {
+        let Some(ref backend_mutex) = self.lance_backend else {
+            return Ok(());
+        };
+
+        runtime::block_on(async {
+            let backend = backend_mutex.lock().unwrap();
+            backend.deactivate_paths(collection, paths).await
+        })?
+    }
+
     /// Sync documents from SQLite to LanceDB
     ///
     /// This method imports all active documents from SQLite into LanceDB.
@@ -1193,13 +2456,12 @@ impl Store {
 
         let conn = self.get_connection(collection)?;
 
-        let rt = tokio::runtime::Runtime::new()?;
-        let count = rt.block_on(async {
+        let count = runtime::block_on(async {
             let backend = backend_mutex.lock().unwrap();
             backend
                 .sync_from_sqlite(collection, &conn, embedder)
                 .await
-        })?;
+        })??;
 
         info!("Synced {} documents to LanceDB for collection '{}'", count, collection);
         Ok(count)
@@ -1222,17 +2484,260 @@ impl Store {
             return Ok(());
         };
 
-        let rt = tokio::runtime::Runtime::new()?;
-        rt.block_on(async {
-            let backend = backend_mutex.lock().unwrap();
-            backend.ensure_fts_index(collection).await?;
-            backend.ensure_vector_index(collection).await?;
-            Ok::<(), anyhow::Error>(())
-        })?;
+        runtime::block_on(async {
+            let backend = backend_mutex.lock().unwrap();
+            backend.ensure_fts_index(collection).await?;
+            backend.ensure_vector_index(collection).await?;
+            Ok::<(), anyhow::Error>(())
+        })??;
+
+        info!("Ensured LanceDB indexes for collection '{}'", collection);
+        Ok(())
+    }
+}
+
+/// Store access for the index-reading stages of the hybrid pipeline
+///
+/// The pipeline awaits the embedder and reranker between stages. Callers
+/// keeping the store behind a lock implement this to hold it only while a
+/// stage reads the index, never across an await.
+pub trait StoreAccess: Sync {
+    fn with_store<T>(&self, read: impl FnOnce(&Store) -> Result<T>) -> Result<T>;
+}
+
+impl StoreAccess for Store {
+    fn with_store<T>(&self, read: impl FnOnce(&Store) -> Result<T>) -> Result<T> {
+        read(self)
+    }
+}
+
+impl StoreAccess for std::sync::Mutex<Store> {
+    fn with_store<T>(&self, read: impl FnOnce(&Store) -> Result<T>) -> Result<T> {
+        let store = self.lock().map_err(|e| anyhow::anyhow!("Store lock failed: {e}"))?;
+        read(&store)
+    }
+}
+
+/// Hybrid search bounded by `deadline`, the one pipeline behind `qmd query`
+/// and the MCP `query` tool
+///
+/// BM25 over the original query always runs. Expansion, the vector leg and
+/// reranking each run only while budget remains; the stages cut short are
+/// listed in the outcome and the candidates gathered so far are returned.
+/// A failing vector leg or reranker is listed as degraded the same way.
+/// `min_score` applies to the final fused or reranked scores, not to the
+/// BM25 and vector legs feeding them, and so do `offset` and `limit`: pages
+/// are cut from the same `search.rerank_candidates` ranking.
+///
+/// The reranker scores the candidates one `search.rerank_window` at a time,
+/// only as far as the page reaches, and each window is sorted on its own
+/// rather than merged with the others, so no page shifts once a later
+/// window is scored. Complete fused candidates and their
+/// scores are kept in the store's [`candidates::CandidateCache`], so a repeat
+/// of the query with a longer page skips retrieval and reranks only the new
+/// windows.
+#[tracing::instrument(name = "hybrid_search", skip(store, options, llm, deadline))]
+pub async fn run_hybrid_search(
+    store: &impl StoreAccess,
+    query: &str,
+    options: SearchOptions,
+    llm: &Router,
+    lang: QueryLanguage,
+    deadline: &Deadline,
+) -> Result<SearchOutcome> {
+    let mut outcome = SearchOutcome::default();
+    let mut timings = StageTimings::default();
+    let mut timer = StageTimer::start();
+    let min_score = options.min_score;
+    let search = store.with_store(|store| Ok(store.search_config().clone()))?;
+    // Every page is cut from the same fused and reranked ranking, so the legs
+    // fetch the fusion pool whatever page is asked for; per-leg pages overlap
+    let paging = options.clone();
+    let options = SearchOptions {
+        limit: search.fusion_pool_size.max(1),
+        offset: 0,
+        min_score: 0.0,
+        ..options
+    };
+
+    let key = candidates::CandidateKey {
+        query: query.to_string(),
+        lang,
+        collections: store.with_store(|store| {
+            Ok(store.search_collections(&options)?.into_iter().map(str::to_string).collect())
+        })?,
+        embedder: llm.embedder_model(),
+        reranker: llm.reranker_model(),
+    };
+    let (generation, cached) = store.with_store(|store| {
+        let generation = store.index_generation(&key.collections)?;
+        let cached = store.candidate_cache().get(&key, &generation);
+        Ok((generation, cached))
+    })?;
+    let mut cache_use = candidates::CandidateCacheUse {
+        hit: cached.is_some(),
+        ..Default::default()
+    };
+
+    let mut candidates = match cached {
+        Some(candidates) => {
+            for failure in &candidates.degraded {
+                outcome.fail(failure.stage, &failure.error);
+            }
+            candidates
+        }
+        None => fuse_candidates(store, query, &options, &search, llm, lang, deadline, &mut outcome, &mut timings, &mut timer).await?,
+    };
+    // Candidates cut short by the deadline or a passing failure would stand
+    // in for a complete ranking; a vector leg without an embedder stays degraded
+    let cacheable = outcome.skipped.is_empty() && (outcome.degraded.is_empty() || !llm.has_embedder());
+    timings.fusion_ms += timer.lap();
+
+    // Step 6: LLM reranking, one window at a time until the page is filled
+    let wanted = paging.offset + paging.limit;
+    let window = search.rerank_window.unwrap_or(candidates.results.len()).max(1);
+    let mut ranked: Vec<SearchResult> = Vec::new();
+    let mut start = 0;
+    if llm.has_reranker() {
+        info!("LLM reranking available, applying to top candidates");
+        while start < candidates.results.len() && ranked.len() < wanted {
+            let end = (start + window).min(candidates.results.len());
+            let unscored = candidates.unscored(start, end);
+            cache_use.reused_scores += end - start - unscored.len();
+            if !unscored.is_empty() {
+                let docs: Vec<SearchResult> = unscored.iter().map(|&i| candidates.results[i].clone()).collect();
+                match deadline.run(llm.rerank(query, &docs)).await {
+                    Some(Ok(scores)) => {
+                        cache_use.reranked += unscored.len();
+                        for (i, score) in unscored.into_iter().zip(scores) {
+                            candidates.scores[i] = Some(score);
+                        }
+                    }
+                    Some(Err(e)) => {
+                        outcome.fail(SearchStage::Rerank, e);
+                        break;
+                    }
+                    None => {
+                        outcome.skip(SearchStage::Rerank);
+                        break;
+                    }
+                }
+            }
+            ranked.extend(
+                candidates
+                    .reranked_window(start, end)
+                    .into_iter()
+                    .filter(|r| meets_min_score(r.score, min_score)),
+            );
+            start = end;
+        }
+    }
+    // Candidates no window reached keep their fused order
+    ranked.extend(
+        candidates.results[start..]
+            .iter()
+            .filter(|r| meets_min_score(r.score, min_score))
+            .cloned(),
+    );
+    outcome.results = page(ranked, &paging);
+
+    if cacheable {
+        store.with_store(|store| {
+            store.candidate_cache().put(key, generation, candidates);
+            Ok(())
+        })?;
+    }
+    (cache_use.hits, cache_use.misses) = store.with_store(|store| Ok(store.candidate_cache().stats()))?;
+    outcome.cache = Some(cache_use);
+    timings.rerank_ms = timer.lap();
+    timings.total_ms = timer.total();
+    outcome.timings = Some(timings);
+
+    Ok(outcome)
+}
+
+/// Steps 1-5 of [`run_hybrid_search`]: the top `search.rerank_candidates`
+/// fused candidates, unscored
+#[allow(clippy::too_many_arguments)]
+async fn fuse_candidates(
+    store: &impl StoreAccess,
+    query: &str,
+    options: &SearchOptions,
+    search: &crate::config::SearchConfig,
+    llm: &Router,
+    lang: QueryLanguage,
+    deadline: &Deadline,
+    outcome: &mut SearchOutcome,
+    timings: &mut StageTimings,
+    timer: &mut StageTimer,
+) -> Result<candidates::Candidates> {
+    // Step 1: Query expansion using LLM
+    let expanded_queries = if deadline.expired() {
+        outcome.skip(SearchStage::Expansion);
+        vec![query.to_string()]
+    } else {
+        llm.expand_query_in(query, lang)?
+    };
+    timings.expansion_ms = timer.lap();
+
+    info!("Hybrid search: original='{}' ({}), expanded={} variants", query, lang, expanded_queries.len());
+
+    // Step 2: BM25 retrieval for all expanded queries
+    let mut all_bm25_results = store.with_store(|store| {
+        let mut results = Vec::new();
+        for (i, expanded_query) in expanded_queries.iter().enumerate() {
+            // The original query always runs; variants only within budget
+            if i > 0 && deadline.expired() {
+                outcome.skip(SearchStage::Expansion);
+                break;
+            }
+            results.extend(store.bm25_search_in(expanded_query, options.clone(), lang)?);
+        }
+        Ok(results)
+    })?;
+
+    // Limit intermediate results to the fusion pool (search.fusion_pool_size)
+    all_bm25_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    all_bm25_results.truncate(search.fusion_pool_size.max(1));
+    timings.bm25_ms = timer.lap();
 
-        info!("Ensured LanceDB indexes for collection '{}'", collection);
-        Ok(())
+    // Step 3: Vector search for original query; a failure degrades to BM25
+    let vector_search = async {
+        let embedding = llm.embed(&[query]).await?;
+        info!("Generated embedding with {} dimensions, provider: {}",
+              embedding.embeddings[0].len(), embedding.provider);
+        store.with_store(|store| store.vector_search_with_embedding(&embedding.embeddings[0], options.clone()))
+    };
+    let vector_results = match deadline.run(vector_search).await {
+        Some(Ok(results)) => results,
+        Some(Err(e)) => {
+            outcome.fail(SearchStage::Vector, e);
+            Vec::new()
+        }
+        None => {
+            outcome.skip(SearchStage::Vector);
+            Vec::new()
+        }
+    };
+    timings.vector_ms = timer.lap();
+
+    info!("BM25 results: {}, Vector results: {}", all_bm25_results.len(), vector_results.len());
+
+    // Step 4: RRF fusion of BM25 and vector results
+    let result_lists = vec![all_bm25_results, vector_results];
+    let weights = vec![1.0, 1.5]; // Give more weight to vector search
+    let mut fused = Store::rrf_fusion(&result_lists, Some(weights.clone()), RRF_K);
+    for result in &mut fused {
+        result.score = fused_relevance(result.score, &weights, RRF_K);
     }
+
+    // Sort by RRF score (higher is better)
+    fused.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+
+    // Step 5: Top candidates for reranking (search.rerank_candidates)
+    fused.truncate(search.rerank_candidates.max(1));
+    debug!("Hybrid search: {} candidates for reranking", fused.len());
+    Ok(candidates::Candidates::new(fused, outcome.degraded.clone()))
 }
 
 #[cfg(test)]
@@ -1259,9 +2764,37 @@ mod tests {
             title: path.to_string(),
             hash: format!("hash_{}", path),
             query: None,
+            size: None,
+            match_line: None,
+            duplicates: Vec::new(),
+            source: Vec::new(),
+            score_components: None,
         }
     }
 
+    // ==================== Dedupe Tests ====================
+
+    #[test]
+    fn test_dedupe_by_hash_keeps_first_and_lists_copies() {
+        let mut copy = make_result("vendor/readme.md", 0.8);
+        copy.hash = "hash_readme.md".to_string();
+        let mut unhashed = make_result("scratch.md", 0.5);
+        unhashed.hash.clear();
+        let results = vec![
+            make_result("readme.md", 0.9),
+            make_result("guide.md", 0.85),
+            copy,
+            unhashed.clone(),
+            unhashed,
+        ];
+
+        let deduped = dedupe_by_hash(results);
+        let paths: Vec<&str> = deduped.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, vec!["readme.md", "guide.md", "scratch.md", "scratch.md"]);
+        assert_eq!(deduped[0].duplicates, vec!["vendor/readme.md".to_string()]);
+        assert!(deduped[1].duplicates.is_empty());
+    }
+
     // ==================== RRF Fusion Tests ====================
 
     #[test]
@@ -1318,6 +2851,71 @@ mod tests {
         assert_eq!(result[0].path, "doc2.md");
     }
 
+    #[test]
+    fn test_rrf_fusion_merges_sources() {
+        let tagged = |path: &str, source: &str| SearchResult {
+            source: vec![source.to_string()],
+            ..make_result(path, 0.9)
+        };
+        let bm25 = vec![tagged("both.md", SOURCE_BM25), tagged("text.md", SOURCE_BM25)];
+        let vector = vec![tagged("both.md", SOURCE_VECTOR), tagged("meaning.md", SOURCE_VECTOR)];
+        let result = Store::rrf_fusion(&[bm25, vector], None, 60);
+
+        let source = |path: &str| result.iter().find(|r| r.path == path).unwrap().source.clone();
+        assert_eq!(source("both.md"), ["bm25", "vector"]);
+        assert_eq!(source("text.md"), ["bm25"]);
+        assert_eq!(source("meaning.md"), ["vector"]);
+
+        let json = serde_json::to_value(&result[0]).unwrap();
+        assert_eq!(json["source"], serde_json::json!(["bm25", "vector"]));
+    }
+
+    #[test]
+    fn test_rrf_fusion_keeps_same_path_in_different_collections() {
+        let in_collection = |collection: &str| SearchResult {
+            docid: make_docid(collection, "guide.md"),
+            collection: collection.to_string(),
+            ..make_result("guide.md", 0.9)
+        };
+        let bm25 = vec![in_collection("alpha"), in_collection("beta")];
+        let vector = vec![in_collection("beta")];
+        let result = Store::rrf_fusion(&[bm25, vector], None, 60);
+
+        assert_eq!(result.len(), 2);
+        // beta's guide.md is in both lists, so it outranks alpha's
+        assert_eq!(result[0].collection, "beta");
+        assert_eq!(result[0].docid, "beta:guide.md");
+        assert_eq!(result[1].collection, "alpha");
+        assert_eq!(result[1].docid, "alpha:guide.md");
+        assert!(result.iter().all(|r| r.path == "guide.md"));
+    }
+
+    #[test]
+    fn test_rrf_fusion_reports_score_components() {
+        let tagged = |path: &str, source: &str| SearchResult {
+            source: vec![source.to_string()],
+            ..make_result(path, 0.9)
+        };
+        let bm25 = vec![tagged("text.md", SOURCE_BM25), tagged("both.md", SOURCE_BM25)];
+        let vector = vec![tagged("both.md", SOURCE_VECTOR), tagged("meaning.md", SOURCE_VECTOR)];
+        let result = Store::rrf_fusion(&[bm25, vector], Some(vec![1.0, 1.5]), 60);
+
+        let components = |path: &str| result.iter().find(|r| r.path == path).unwrap().score_components.unwrap();
+        let both = components("both.md");
+        assert_eq!((both.bm25_rank, both.vector_rank), (Some(2), Some(1)));
+        assert_eq!(both.rerank_score, None);
+        assert_eq!((components("text.md").bm25_rank, components("text.md").vector_rank), (Some(1), None));
+        assert_eq!((components("meaning.md").bm25_rank, components("meaning.md").vector_rank), (None, Some(2)));
+        for r in &result {
+            assert_eq!(r.score_components.unwrap().rrf_score, r.score);
+        }
+
+        let json = serde_json::to_value(&result[0]).unwrap();
+        assert_eq!(json["score_components"]["bm25_rank"], 2);
+        assert_eq!(json["score_components"]["vector_rank"], 1);
+        assert!(json["score_components"]["rerank_score"].is_null());
+    }
+
     #[test]
     fn test_rrf_fusion_with_weights() {
         let list1 = vec![make_result("doc1.md", 0.9)];
@@ -1364,7 +2962,7 @@ mod tests {
         ];
 
         // With k=1, scores are 1/(1+0)=1.0 and 1/(1+1)=0.5
-        let result_k1 = Store::rrf_fusion(&[list.clone()], None, 1);
+        let result_k1 = Store::rrf_fusion(std::slice::from_ref(&list), None, 1);
         // With k=100, scores are 1/(100+0)=0.01 and 1/(100+1)≈0.0099
         let result_k100 = Store::rrf_fusion(&[list], None, 100);
 
@@ -1385,6 +2983,11 @@ mod tests {
             title: "Test Document".to_string(),
             hash: "abc123".to_string(),
             query: None,
+            size: None,
+            match_line: None,
+            duplicates: Vec::new(),
+            source: Vec::new(),
+            score_components: None,
         }];
         let result = Store::rrf_fusion(&[list], None, 60);
 
@@ -1442,9 +3045,7 @@ mod tests {
     fn test_search_options_defaults() {
         let opts = SearchOptions {
             limit: 10,
-            min_score: 0.0,
-            collection: None,
-            search_all: false,
+            ..Default::default()
         };
         assert_eq!(opts.limit, 10);
         assert!(!opts.search_all);
@@ -1535,6 +3136,7 @@ mod tests {
                 path: tmp.path().to_path_buf(),
                 pattern: None,
                 description: None,
+                ..Default::default()
             }],
             cache_path: tmp.path().to_path_buf(),
             ..Config::default()
@@ -1563,12 +3165,18 @@ mod tests {
              VALUES ('test_col', 'python_guide.md', 'Python Tutorial', 'hash2', datetime('now'), datetime('now'), 1)",
             [],
         ).unwrap();
+        fts::rebuild(&conn).unwrap();
 
         drop(conn);
 
         let store = Store {
             config,
-            connections: HashMap::new(),
+            connections: Mutex::new(HashMap::new()),
+            pool_stats: Default::default(),
+            schemas: Default::default(),
+            candidate_cache: Default::default(),
+            warnings: Vec::new(),
+            vector_unavailable: None,
             #[cfg(feature = "lancedb")]
             lance_backend: None,
             #[cfg(feature = "qdrant")]
@@ -1577,9 +3185,8 @@ mod tests {
 
         let opts = SearchOptions {
             limit: 10,
-            min_score: 0.0,
             collection: Some("test_col".to_string()),
-            search_all: false,
+            ..Default::default()
         };
 
         let results = store.bm25_search("Rust programming", opts).unwrap();
@@ -1599,6 +3206,7 @@ mod tests {
                 path: tmp.path().to_path_buf(),
                 pattern: None,
                 description: None,
+                ..Default::default()
             }],
             cache_path: tmp.path().to_path_buf(),
             ..Config::default()
@@ -1616,12 +3224,18 @@ mod tests {
              VALUES ('test_col', 'doc.md', 'Test', 'hash1', datetime('now'), datetime('now'), 1)",
             [],
         ).unwrap();
+        fts::rebuild(&conn).unwrap();
 
         drop(conn);
 
         let store = Store {
             config,
-            connections: HashMap::new(),
+            connections: Mutex::new(HashMap::new()),
+            pool_stats: Default::default(),
+            schemas: Default::default(),
+            candidate_cache: Default::default(),
+            warnings: Vec::new(),
+            vector_unavailable: None,
             #[cfg(feature = "lancedb")]
             lance_backend: None,
             #[cfg(feature = "qdrant")]
@@ -1630,15 +3244,589 @@ mod tests {
 
         let opts = SearchOptions {
             limit: 10,
-            min_score: 0.0,
             collection: Some("test_col".to_string()),
-            search_all: false,
+            ..Default::default()
         };
 
         let results = store.bm25_search("nonexistent_xyz_query", opts).unwrap();
         assert!(results.is_empty(), "Should find no results for unrelated query");
     }
 
+    // ==================== min_score Tests ====================
+
+    /// A store over two documents, one far more about walruses than the other
+    fn walrus_store(tmp: &std::path::Path) -> (Config, Store) {
+        let docs = tmp.join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("strong.md"), "# Walrus\nWalrus walrus walrus. The walrus naps.").unwrap();
+        std::fs::write(
+            docs.join("weak.md"),
+            "# Harbour\nBoats, gulls, nets, ropes, crates, tides and once a walrus, then more boats and gulls.",
+        )
+        .unwrap();
+        let mut docs = collection("docs", docs);
+        docs.pattern = Some("**/*.md".to_string());
+        let config = Config {
+            collections: vec![docs],
+            cache_path: tmp.join("cache"),
+            ..Config::default()
+        };
+        let store = Store::new(&config).unwrap();
+        store.update_index().unwrap();
+        (config, store)
+    }
+
+    fn min_score_options(min_score: f32) -> SearchOptions {
+        SearchOptions {
+            limit: 10,
+            min_score,
+            search_all: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bm25_relevance_ignores_sign() {
+        // FTS5 negates bm25(): -3 is a better match than -1
+        assert_eq!(bm25_relevance(-3.0), 0.75);
+        assert_eq!(bm25_relevance(3.0), 0.75);
+        assert_eq!(bm25_relevance(0.0), 0.0);
+        assert!(bm25_relevance(-3.0) > bm25_relevance(-1.0));
+        assert!(bm25_relevance(-1e6) < 1.0);
+    }
+
+    #[test]
+    fn test_meets_min_score_on_vector_similarity() {
+        // min_score 0.7 means at least 0.7 similar
+        assert!(meets_min_score(cosine_similarity(0.2), 0.7));
+        assert!(meets_min_score(cosine_similarity(0.3), 0.7));
+        assert!(!meets_min_score(cosine_similarity(0.4), 0.7));
+        // Zero keeps everything, even vectors pointing away from the query
+        assert!(meets_min_score(cosine_similarity(1.5), 0.0));
+        assert!(!meets_min_score(cosine_similarity(1.5), 0.1));
+    }
+
+    #[test]
+    fn test_bm25_search_drops_results_below_min_score() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_config, store) = walrus_store(tmp.path());
+
+        let all = store.bm25_search("walrus", min_score_options(0.0)).unwrap();
+        let paths: Vec<&str> = all.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["docs/strong.md", "docs/weak.md"]);
+        // Reported as relevance in [0, 1), the scale min_score uses
+        let (strong, weak) = (all[0].score, all[1].score);
+        assert!(strong > weak, "{} <= {}", strong, weak);
+        assert!(weak > 0.0 && strong < 1.0);
+
+        let threshold = (strong + weak) / 2.0;
+        let kept = store.bm25_search("walrus", min_score_options(threshold)).unwrap();
+        let paths: Vec<&str> = kept.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["docs/strong.md"]);
+        assert_eq!(kept[0].score, strong);
+
+        assert!(store.bm25_search("walrus", min_score_options(strong + 0.01)).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_applies_min_score_to_final_scores() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (config, store) = walrus_store(tmp.path());
+        let router = Router::new(&config).unwrap();
+
+        let all = store.hybrid_search("walrus", min_score_options(0.0), &router).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(all[0].score > all[1].score);
+        let components = all[0].score_components.unwrap();
+        assert_eq!(components.bm25_rank, Some(1));
+        // Raw RRF in the components, its share of the best possible as the score
+        assert_eq!(fused_relevance(components.rrf_score, &[1.0, 1.5], RRF_K), all[0].score);
+        assert!(all[0].score <= 1.0);
+
+        let threshold = (all[0].score + all[1].score) / 2.0;
+        let kept = store.hybrid_search("walrus", min_score_options(threshold), &router).await.unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].path, all[0].path);
+        assert_eq!(kept[0].score, all[0].score);
+    }
+
+    // ==================== External-content FTS Tests ====================
+
+    /// Insert and index a document (content + reference) for FTS tests
+    fn insert_doc(conn: &Connection, path: &str, title: &str, hash: &str, body: &str) {
+        conn.execute(
+            "INSERT OR REPLACE INTO content (hash, doc, created_at) VALUES (?1, ?2, datetime('now'))",
+            rusqlite::params![hash, body],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO documents (collection, path, title, hash, created_at, modified_at, active)
+             VALUES ('test_col', ?1, ?2, ?3, datetime('now'), datetime('now'), 1)",
+            rusqlite::params![path, title, hash],
+        ).unwrap();
+        fts::index(conn, "d.path = ?", [path]).unwrap();
+    }
+
+    /// Run `sql` on the document at `path` the way the store does, taking it
+    /// out of the index first and putting it back afterwards
+    fn reindexed(conn: &Connection, path: &str, sql: &str) {
+        fts::unindex(conn, "d.path = ?", [path]).unwrap();
+        conn.execute(sql, [path]).unwrap();
+        fts::index(conn, "d.path = ?", [path]).unwrap();
+    }
+
+    /// Replace documents_fts with the pre-external-content layout that stored bodies itself
+    fn downgrade_to_legacy_fts(conn: &Connection) {
+        conn.execute_batch(r#"
+            DROP TABLE documents_fts;
+            CREATE VIRTUAL TABLE documents_fts USING fts5(
+                filepath, title, body,
+                tokenize='porter unicode61'
+            );
+            INSERT INTO documents_fts(rowid, filepath, title, body)
+            SELECT id, filepath, title, body FROM documents_fts_view;
+        "#).unwrap();
+    }
+
+    fn fts_match_count(conn: &Connection, query: &str) -> i64 {
+        conn.query_row(
+            "SELECT COUNT(*) FROM documents_fts WHERE documents_fts MATCH ?1",
+            [query],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn db_page_count(conn: &Connection) -> i64 {
+        conn.execute_batch("VACUUM").unwrap();
+        conn.query_row("PRAGMA page_count", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_fts_uses_external_content() {
+        let tmp = tempfile::tempdir().unwrap();
+        let conn = init_test_db(&tmp.path().join("test.db"));
+
+        let sql: String = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type='table' AND name='documents_fts'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(sql.contains("content='documents_fts_view'"));
+
+        // No shadow table holding a second copy of the bodies
+        let shadow: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name='documents_fts_content'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(shadow, 0);
+        assert!(!Store::check_fts_migration_needed(&conn, FTS_TOKENIZER_TEXT).unwrap());
+    }
+
+    #[test]
+    fn test_fts_writes_track_updates_and_deactivation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let conn = init_test_db(&tmp.path().join("test.db"));
+
+        insert_doc(&conn, "a.md", "Alpha", "hash_a", "Rust ownership and borrowing explained");
+        insert_doc(&conn, "b.md", "Beta", "hash_b", "Python generators and iterators");
+        assert_eq!(fts_match_count(&conn, "ownership"), 1);
+
+        // Content change: old terms must leave the index, new ones must enter it
+        conn.execute(
+            "INSERT INTO content (hash, doc, created_at) VALUES ('hash_a2', 'Rust lifetimes in depth', datetime('now'))",
+            [],
+        ).unwrap();
+        reindexed(&conn, "a.md", "UPDATE documents SET hash = 'hash_a2' WHERE path = ?");
+        assert_eq!(fts_match_count(&conn, "ownership"), 0);
+        assert_eq!(fts_match_count(&conn, "lifetimes"), 1);
+
+        // snippet() and bm25() read the body back through the view
+        let (snippet, score): (String, f64) = conn
+            .query_row(
+                "SELECT snippet(documents_fts, 2, '[', ']', '...', 8), bm25(documents_fts)
+                 FROM documents_fts WHERE documents_fts MATCH 'lifetimes'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(snippet, "Rust [lifetimes] in depth");
+        assert!(score < 0.0);
+
+        // Deactivation removes the document, deletion of an inactive row is a no-op
+        reindexed(&conn, "b.md", "UPDATE documents SET active = 0 WHERE path = ?");
+        assert_eq!(fts_match_count(&conn, "generators"), 0);
+        reindexed(&conn, "b.md", "DELETE FROM documents WHERE path = ?");
+        reindexed(&conn, "a.md", "DELETE FROM documents WHERE path = ?");
+        assert_eq!(fts_match_count(&conn, "lifetimes"), 0);
+
+        conn.execute("INSERT INTO documents_fts(documents_fts) VALUES('integrity-check')", [])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_fts_legacy_triggers_are_dropped() {
+        let tmp = tempfile::tempdir().unwrap();
+        let conn = init_test_db(&tmp.path().join("test.db"));
+
+        // The original update trigger re-indexed every row regardless of `active`
+        conn.execute_batch(r#"
+            CREATE TRIGGER documents_au AFTER UPDATE ON documents BEGIN
+                INSERT INTO documents_fts(documents_fts, rowid, filepath, title, body)
+                SELECT 'delete', old.id, old.collection || '/' || old.path, old.title,
+                       (SELECT doc FROM content WHERE hash = old.hash);
+                INSERT INTO documents_fts(rowid, filepath, title, body)
+                SELECT new.id, new.collection || '/' || new.path, new.title,
+                       (SELECT doc FROM content WHERE hash = new.hash);
+            END;
+        "#).unwrap();
+
+        insert_doc(&conn, "a.md", "Alpha", "hash_a", "Soft deleted walrus");
+        conn.execute("UPDATE documents SET active = 0 WHERE path = 'a.md'", []).unwrap();
+        assert_eq!(fts_match_count(&conn, "walrus"), 1, "legacy trigger keeps the row indexed");
+        assert!(Store::check_fts_migration_needed(&conn, FTS_TOKENIZER_TEXT).unwrap());
+
+        Store::init_schema(&conn).unwrap();
+        assert!(!Store::check_fts_migration_needed(&conn, FTS_TOKENIZER_TEXT).unwrap());
+        assert_eq!(fts_match_count(&conn, "walrus"), 0);
+        let triggers: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'trigger'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(triggers, 0);
+
+        reindexed(&conn, "a.md", "UPDATE documents SET active = 1 WHERE path = ?");
+        assert_eq!(fts_match_count(&conn, "walrus"), 1);
+    }
+
+    #[test]
+    fn test_fts_migration_from_legacy_table_saves_space() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("test_col").join("index.db");
+        std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+        let conn = init_test_db(&db_path);
+
+        let filler = "storage efficiency matters for large markdown collections ".repeat(200);
+        for i in 0..50 {
+            let body = format!("Document {} about quantum{} topics. {}", i, i, filler);
+            insert_doc(&conn, &format!("doc{}.md", i), &format!("Doc {}", i), &format!("hash{}", i), &body);
+        }
+        downgrade_to_legacy_fts(&conn);
+        assert!(Store::check_fts_migration_needed(&conn, FTS_TOKENIZER_TEXT).unwrap());
+        let legacy_pages = db_page_count(&conn);
+
+        Store::init_schema(&conn).unwrap();
+        assert!(!Store::check_fts_migration_needed(&conn, FTS_TOKENIZER_TEXT).unwrap());
+        let migrated_pages = db_page_count(&conn);
+        assert!(
+            migrated_pages < legacy_pages,
+            "external content should shrink the database ({} >= {} pages)",
+            migrated_pages,
+            legacy_pages
+        );
+        drop(conn);
+
+        // Search still finds migrated documents
+        let store = Store {
+            config: Config {
+                collections: vec![CollectionConfig {
+                    name: "test_col".to_string(),
+                    path: tmp.path().to_path_buf(),
+                    pattern: None,
+                    description: None,
+                    ..Default::default()
+                }],
+                cache_path: tmp.path().to_path_buf(),
+                ..Config::default()
+            },
+            connections: Mutex::new(HashMap::new()),
+            pool_stats: Default::default(),
+            schemas: Default::default(),
+            candidate_cache: Default::default(),
+            warnings: Vec::new(),
+            vector_unavailable: None,
+            #[cfg(feature = "lancedb")]
+            lance_backend: None,
+            #[cfg(feature = "qdrant")]
+            qdrant_backend: None,
+        };
+        let opts = SearchOptions {
+            limit: 10,
+            collection: Some("test_col".to_string()),
+            ..Default::default()
+        };
+        let results = store.bm25_search("quantum7", opts).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "test_col/doc7.md");
+        assert_eq!(results[0].title, "Doc 7");
+    }
+
+    #[test]
+    fn test_search_without_collections_reports_config_error() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = Store {
+            config: Config {
+                collections: vec![],
+                cache_path: tmp.path().to_path_buf(),
+                ..Config::default()
+            },
+            connections: Mutex::new(HashMap::new()),
+            pool_stats: Default::default(),
+            schemas: Default::default(),
+            candidate_cache: Default::default(),
+            warnings: Vec::new(),
+            vector_unavailable: None,
+            #[cfg(feature = "lancedb")]
+            lance_backend: None,
+            #[cfg(feature = "qdrant")]
+            qdrant_backend: None,
+        };
+
+        for search_all in [false, true] {
+            let opts = SearchOptions {
+                limit: 10,
+                search_all,
+                ..Default::default()
+            };
+
+            let err = store.bm25_search("rust", opts.clone()).unwrap_err();
+            let anel = err.downcast_ref::<AnelError>().expect("should be an AnelError");
+            assert_eq!(anel.error_code, AnelErrorCode::ConfigError);
+            assert_eq!(
+                anel.recovery_hints[0].action.as_deref(),
+                Some("qmd collection add <path> --name <name>")
+            );
+
+            let err = store.vector_search_with_embedding(&[0.1; 384], opts).unwrap_err();
+            assert!(err.downcast_ref::<AnelError>().is_some());
+        }
+    }
+
+    // ==================== Preflight Tests ====================
+
+    fn collection(name: &str, path: std::path::PathBuf) -> CollectionConfig {
+        CollectionConfig {
+            name: name.to_string(),
+            path,
+            pattern: None,
+            description: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_preflight_warns_about_nonexistent_path() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config {
+            collections: vec![collection("gone", tmp.path().join("does-not-exist"))],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+
+        let store = Store::new(&config).unwrap();
+        let preflight = store.preflight();
+        let codes: Vec<&str> = preflight.iter().map(|w| w.code.as_str()).collect();
+        assert_eq!(codes, vec!["COLLECTION_PATH_MISSING", "COLLECTION_EMPTY"]);
+        assert!(preflight.iter().all(|w| w.collection == "gone"));
+        // Store::new also warns once about a missing sqlite-vec
+        assert!(store.warnings().starts_with(&preflight));
+        assert_eq!(store.warnings().len(), 2 + store.vector_backend_error().iter().count());
+    }
+
+    #[test]
+    fn test_preflight_warns_about_empty_collection() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docs = tmp.path().join("docs");
+        let notes = tmp.path().join("notes");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::create_dir_all(&notes).unwrap();
+        let config = Config {
+            collections: vec![collection("docs", docs), collection("notes", notes)],
+            cache_path: tmp.path().join("cache"),
+            ..Config::default()
+        };
+
+        // Index one document into "docs" only
+        let store = Store::new(&config).unwrap();
+        let conn = store.get_connection("docs").unwrap();
+        conn.execute(
+            "INSERT INTO content (hash, doc, created_at) VALUES ('h1', 'hello world', datetime('now'))",
+            [],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO documents (collection, path, title, hash, created_at, modified_at, active)
+             VALUES ('docs', 'a.md', 'A', 'h1', datetime('now'), datetime('now'), 1)",
+            [],
+        ).unwrap();
+        drop(conn);
+
+        let warnings = store.preflight();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "COLLECTION_EMPTY");
+        assert_eq!(warnings[0].collection, "notes");
+
+        // Reopening picks the remaining warning up; search warnings follow the searched scope
+        let store = Store::new(&config).unwrap();
+        let scoped = |collection: Option<&str>, search_all: bool| {
+            store.search_warnings(&SearchOptions {
+                limit: 10,
+                collection: collection.map(|c| c.to_string()),
+                search_all,
+                ..Default::default()
+            })
+        };
+        assert!(scoped(Some("docs"), false).is_empty());
+        assert_eq!(scoped(Some("notes"), false)[0].code, "COLLECTION_EMPTY");
+        assert_eq!(scoped(None, true).len(), 1);
+        assert_eq!(scoped(Some("missing"), false)[0].code, "COLLECTION_NOT_FOUND");
+    }
+
+    // ==================== File timestamp Tests ====================
+
+    #[test]
+    fn test_created_time_falls_back_to_modified_when_unsupported() {
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let unsupported = Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "creation time is not available on this platform",
+        ));
+
+        let created = created_or_modified(unsupported, modified);
+        assert_eq!(created, chrono::DateTime::<chrono::Utc>::from(modified));
+
+        let born = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+        assert_eq!(
+            created_or_modified(Ok(born), modified),
+            chrono::DateTime::<chrono::Utc>::from(born)
+        );
+    }
+
+    // ==================== Per-collection backend stamping Tests ====================
+
+    #[test]
+    fn test_stamp_collection_matches_sqlite_shape() {
+        let mut results = vec![make_result("guide.md", 1.0), make_result("notes/a.md", 0.5)];
+        stamp_collection("docs", &mut results);
+        assert_eq!(results[0].collection, "docs");
+        assert_eq!(results[0].path, "docs/guide.md");
+        assert_eq!(results[0].docid, "docs:guide.md");
+        assert_eq!(results[1].path, "docs/notes/a.md");
+
+        // Already-prefixed paths are left alone
+        stamp_collection("docs", &mut results);
+        assert_eq!(results[0].path, "docs/guide.md");
+        assert_eq!(results[0].docid, "docs:guide.md");
+    }
+
+    #[cfg(feature = "lancedb")]
+    #[test]
+    fn test_lance_multi_collection_search_tags_collection() {
+        use crate::config::BM25BackendConfig;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config {
+            bm25: BM25BackendConfig {
+                backend: BM25Backend::LanceDb,
+            },
+            collections: vec![
+                CollectionConfig {
+                    name: "alpha".to_string(),
+                    path: tmp.path().to_path_buf(),
+                    pattern: None,
+                    description: None,
+                    ..Default::default()
+                },
+                CollectionConfig {
+                    name: "beta".to_string(),
+                    path: tmp.path().to_path_buf(),
+                    pattern: None,
+                    description: None,
+                    ..Default::default()
+                },
+            ],
+            cache_path: tmp.path().to_path_buf(),
+            ..Config::default()
+        };
+        let dim = config.vector.dimension();
+        let store = Store::new(&config).unwrap();
+
+        for collection in ["alpha", "beta"] {
+            let conn = store.get_connection(collection).unwrap();
+            let hash = format!("hash_{}", collection);
+            conn.execute(
+                "INSERT INTO content (hash, doc, created_at) VALUES (?1, 'shared rust keyword', datetime('now'))",
+                [&hash],
+            ).unwrap();
+            conn.execute(
+                "INSERT INTO documents (collection, path, title, hash, created_at, modified_at, active)
+                 VALUES (?1, 'same.md', 'Same', ?2, datetime('now'), datetime('now'), 1)",
+                [collection, hash.as_str()],
+            ).unwrap();
+            fts::index(&conn, "d.hash = ?", [&hash]).unwrap();
+            drop(conn);
+            store.sync_to_lance(collection, |_| Ok(vec![0.1; dim])).unwrap();
+            store.ensure_lance_indexes(collection).unwrap();
+        }
+
+        let opts = SearchOptions {
+            limit: 10,
+            search_all: true,
+            ..Default::default()
+        };
+
+        let results = store.bm25_search("rust", opts.clone()).unwrap();
+        assert_eq!(results.len(), 2);
+        for r in &results {
+            assert_eq!(r.path, format!("{}/same.md", r.collection));
+            assert_eq!(r.docid, make_docid(&r.collection, "same.md"));
+        }
+        assert!(results.iter().any(|r| r.collection == "alpha"));
+        assert!(results.iter().any(|r| r.collection == "beta"));
+
+        let results = store.vector_search_lance(&vec![0.1; dim], opts).unwrap();
+        let mut collections: Vec<&str> = results.iter().map(|r| r.collection.as_str()).collect();
+        collections.sort();
+        assert_eq!(collections, vec!["alpha", "beta"]);
+        assert!(results.iter().all(|r| r.docid == make_docid(&r.collection, "same.md")));
+    }
+
+    #[cfg(feature = "lancedb")]
+    #[test]
+    fn test_lance_search_applies_min_score_to_relevance() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (mut config, _) = walrus_store(tmp.path());
+        config.bm25.backend = BM25Backend::LanceDb;
+        let store = Store::new(&config).unwrap();
+        let dim = config.vector.dimension();
+        // strong.md points the same way as the query, weak.md at right angles
+        let axis = |i: usize| (0..dim).map(|j| if i == j { 1.0 } else { 0.0 }).collect::<Vec<f32>>();
+        store
+            .sync_to_lance("docs", |text| Ok(axis(if text.contains("naps") { 0 } else { 1 })))
+            .unwrap();
+        store.ensure_lance_indexes("docs").unwrap();
+
+        let all = store.bm25_search("walrus", min_score_options(0.0)).unwrap();
+        let docids: Vec<&str> = all.iter().map(|r| r.docid.as_str()).collect();
+        assert_eq!(docids, ["docs:strong.md", "docs:weak.md"]);
+        // Reported on the same scale as the SQLite backend
+        assert!(all.iter().all(|r| r.score > 0.0 && r.score < 1.0), "{:?}", all);
+        let threshold = (all[0].score + all[1].score) / 2.0;
+        let kept = store.bm25_search("walrus", min_score_options(threshold)).unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].docid, "docs:strong.md");
+        assert_eq!(kept[0].score, all[0].score);
+
+        let all = store.vector_search_lance(&axis(0), min_score_options(0.0)).unwrap();
+        let scored: Vec<(&str, f32)> = all.iter().map(|r| (r.docid.as_str(), r.score)).collect();
+        assert_eq!(scored, [("docs:strong.md", 1.0), ("docs:weak.md", 0.0)]);
+        let kept = store.vector_search_lance(&axis(0), min_score_options(0.5)).unwrap();
+        let scored: Vec<(&str, f32)> = kept.iter().map(|r| (r.docid.as_str(), r.score)).collect();
+        assert_eq!(scored, [("docs:strong.md", 1.0)]);
+    }
+
     // ==================== make_docid Tests ====================
 
     #[test]
@@ -1671,6 +3859,19 @@ mod tests {
         assert_eq!(docid, "col:C:/Users/doc.md");
     }
 
+    // ==================== PathBoost Tests ====================
+
+    #[test]
+    fn test_path_boost_parse() {
+        let boost: PathBoost = "qmd://docs/official/:2.5".parse().unwrap();
+        assert_eq!(boost.prefix, "qmd://docs/official/");
+        assert_eq!(boost.weight, 2.5);
+
+        for invalid in ["docs/official", ":2", "docs/:0", "docs/:-1", "docs/:fast"] {
+            assert!(invalid.parse::<PathBoost>().is_err(), "{}", invalid);
+        }
+    }
+
     // ==================== Hash Extended Tests ====================
 
     #[test]
@@ -1798,6 +3999,11 @@ mod tests {
             title: "Doc".to_string(),
             hash: "h1".to_string(),
             query: Some("test query".to_string()),
+            size: None,
+            match_line: None,
+            duplicates: Vec::new(),
+            source: Vec::new(),
+            score_components: None,
         };
         let json = serde_json::to_string(&r).unwrap();
         assert!(json.contains("\"query\":\"test query\""));
@@ -1823,6 +4029,7 @@ mod tests {
                 path: tmp.path().to_path_buf(),
                 pattern: None,
                 description: None,
+                ..Default::default()
             }],
             cache_path: tmp.path().to_path_buf(),
             ..Config::default()
@@ -1834,7 +4041,12 @@ mod tests {
 
         let store = Store {
             config,
-            connections: HashMap::new(),
+            connections: Mutex::new(HashMap::new()),
+            pool_stats: Default::default(),
+            schemas: Default::default(),
+            candidate_cache: Default::default(),
+            warnings: Vec::new(),
+            vector_unavailable: None,
             #[cfg(feature = "lancedb")]
             lance_backend: None,
             #[cfg(feature = "qdrant")]
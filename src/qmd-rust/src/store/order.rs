@@ -0,0 +1,102 @@
+//! Display order of search results other than relevance.
+//!
+//! Retrieval always ranks by score, which decides which results are shown;
+//! `--sort-by` then reorders only those. Recency comes from the indexed
+//! `modified_at` of each document, newest first.
+
+use super::{SearchResult, Store};
+use anyhow::Result;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// What `--sort-by` orders results by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    /// Relevance, as retrieved
+    #[default]
+    Score,
+    /// `collection/path`, lexically
+    Path,
+    /// File modification time, newest first
+    Modified,
+}
+
+impl std::str::FromStr for SortBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "score" => Ok(Self::Score),
+            "path" => Ok(Self::Path),
+            "modified" => Ok(Self::Modified),
+            other => anyhow::bail!("Unknown sort key '{}'; valid options: score, path, modified", other),
+        }
+    }
+}
+
+impl Store {
+    /// Reorder `results` by `sort_by`, reversed with `reverse`
+    ///
+    /// The sort is stable, so ties keep their relevance order. Results whose
+    /// modification time is unknown sort last either way.
+    pub fn sort_results(&self, results: &mut [SearchResult], sort_by: SortBy, reverse: bool) -> Result<()> {
+        let directed = |ordering: Ordering| if reverse { ordering.reverse() } else { ordering };
+        match sort_by {
+            SortBy::Score => {
+                if reverse {
+                    results.reverse();
+                }
+            }
+            SortBy::Path => results.sort_by(|a, b| directed(a.path.cmp(&b.path))),
+            SortBy::Modified => {
+                let modified = self.modified_times(results)?;
+                results.sort_by(|a, b| match (modified.get(&a.docid), modified.get(&b.docid)) {
+                    (Some(a), Some(b)) => directed(b.cmp(a)),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Indexed modification time of each result's document, by docid
+    fn modified_times(&self, results: &[SearchResult]) -> Result<HashMap<String, chrono::DateTime<chrono::FixedOffset>>> {
+        let mut modified = HashMap::new();
+        for result in results {
+            let prefix = format!("{}/", result.collection);
+            let path = result.path.strip_prefix(&prefix).unwrap_or(&result.path);
+            let stored: Option<String> = self.with_connection(&result.collection, |conn| {
+                Ok(conn
+                    .query_row(
+                        "SELECT modified_at FROM documents WHERE collection = ? AND path = ?",
+                        [&result.collection, path],
+                        |row| row.get(0),
+                    )
+                    .optional()?)
+            })?;
+            if let Some(time) = stored.and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok()) {
+                modified.insert(result.docid.clone(), time);
+            }
+        }
+        Ok(modified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_by_from_str() {
+        assert_eq!("score".parse::<SortBy>().unwrap(), SortBy::Score);
+        assert_eq!("path".parse::<SortBy>().unwrap(), SortBy::Path);
+        assert_eq!("modified".parse::<SortBy>().unwrap(), SortBy::Modified);
+        let err = "date".parse::<SortBy>().unwrap_err();
+        assert!(err.to_string().contains("score, path, modified"), "{}", err);
+    }
+}
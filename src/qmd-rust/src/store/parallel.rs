@@ -0,0 +1,58 @@
+//! Per-collection search work run side by side.
+//!
+//! Each collection has its own database and pooled connection, so an `--all`
+//! search can query several collections at once instead of one after
+//! another. Results come back in input order, so merging them gives the same
+//! output as a serial run.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// `work` applied to each item on up to `parallelism` threads, in item order
+pub fn map_ordered<I: Sync, T: Send>(items: &[I], parallelism: usize, work: impl Fn(&I) -> T + Sync) -> Vec<T> {
+    let threads = parallelism.min(items.len());
+    if threads <= 1 {
+        return items.iter().map(work).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<T>>> = Mutex::new(items.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else { break };
+                let result = work(item);
+                results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|result| result.expect("every item is processed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_map_ordered_keeps_input_order() {
+        let items: Vec<u64> = (0..20).collect();
+        // Earlier items finish last
+        let slow_first = |n: &u64| {
+            std::thread::sleep(Duration::from_millis(20 - n));
+            n * 10
+        };
+        let expected: Vec<u64> = items.iter().map(|n| n * 10).collect();
+        assert_eq!(map_ordered(&items, 4, slow_first), expected);
+        assert_eq!(map_ordered(&items, 1, slow_first), expected);
+        assert_eq!(map_ordered(&items, 0, slow_first), expected);
+        assert!(map_ordered(&[] as &[u64], 4, slow_first).is_empty());
+    }
+}
@@ -7,6 +7,7 @@
 //! Or bare format: `collection-name/path/to/file.md`
 
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
 
 /// Represents a parsed virtual path
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -27,9 +28,9 @@ pub fn normalize_virtual_path(input: &str) -> String {
     let path = input.trim();
 
     // Handle qmd:// with extra slashes: qmd:////collection/path -> qmd://collection/path
-    if path.starts_with("qmd:") {
-        // Remove qmd: prefix and normalize slashes
-        let mut path = path[4..].to_string();
+    if let Some(rest) = path.strip_prefix("qmd:") {
+        // Remove qmd: prefix and normalize slashes; URIs never use backslashes
+        let mut path = rest.replace('\\', "/");
         // Remove leading slashes and re-add exactly two
         path = path.trim_start_matches('/').to_string();
         return format!("qmd://{path}");
@@ -104,10 +105,69 @@ pub fn is_virtual_path(path: &str) -> bool {
     false
 }
 
+/// Relative path as stored in the index, with `/` separators on every platform
+pub fn index_path(relative: &Path) -> String {
+    normalize_separators(&relative.to_string_lossy(), MAIN_SEPARATOR)
+}
+
+/// Replace the platform separator `sep` with `/`
+fn normalize_separators(path: &str, sep: char) -> String {
+    if sep == '/' {
+        path.to_string()
+    } else {
+        path.replace(sep, "/")
+    }
+}
+
+/// Filesystem location of an indexed relative path under `base`
+pub fn native_path(base: &Path, relative: &str) -> PathBuf {
+    let mut path = base.to_path_buf();
+    path.extend(relative.split('/').filter(|part| !part.is_empty()));
+    path
+}
+
+/// Collection pattern with `/` separators, matched against index paths
+pub fn collection_pattern(pattern: &str) -> String {
+    normalize_separators(pattern, MAIN_SEPARATOR)
+}
+
+/// User-supplied glob with `~` expanded and `/` separators
+pub fn portable_glob(pattern: &str) -> anyhow::Result<String> {
+    let expanded = crate::paths::expand(pattern)?;
+    Ok(normalize_separators(&expanded.to_string_lossy(), MAIN_SEPARATOR))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_normalize_separators_injected() {
+        assert_eq!(normalize_separators("docs\\api\\intro.md", '\\'), "docs/api/intro.md");
+        assert_eq!(normalize_separators("docs/api/intro.md", '\\'), "docs/api/intro.md");
+        // On Unix a backslash is an ordinary file name character
+        assert_eq!(normalize_separators("odd\\name.md", '/'), "odd\\name.md");
+    }
+
+    #[test]
+    fn test_collection_pattern_injected_separator() {
+        assert_eq!(normalize_separators("**\\*.md", '\\'), "**/*.md");
+        assert_eq!(collection_pattern("**/*.md"), "**/*.md");
+    }
+
+    #[test]
+    fn test_native_path_round_trips_index_path() {
+        let base = Path::new("/base");
+        let native = native_path(base, "a/b/c.md");
+        assert_eq!(native, base.join("a").join("b").join("c.md"));
+        assert_eq!(index_path(native.strip_prefix(base).unwrap()), "a/b/c.md");
+    }
+
+    #[test]
+    fn test_normalize_virtual_path_backslashes() {
+        assert_eq!(normalize_virtual_path("qmd://notes\\a\\b.md"), "qmd://notes/a/b.md");
+    }
+
     #[test]
     fn test_normalize_virtual_path() {
         // Test qmd:// with extra slashes
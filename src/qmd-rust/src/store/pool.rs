@@ -0,0 +1,284 @@
+//! Per-collection SQLite connections kept open between searches.
+//!
+//! Long-running servers search the same collections over and over, so the
+//! search paths reuse one connection per collection instead of reopening the
+//! database each time. A pooled connection goes stale when its database file
+//! is replaced underneath it (an import, a restore, a copied index): it keeps
+//! reading the old file or stale cached pages, or its queries start failing.
+//! The pool notices both: a connection whose file changed since it was opened
+//! is reopened before use, and a query failing with an error that means the
+//! database changed is retried once on a fresh connection before the error
+//! surfaces.
+//!
+//! Under concurrent writes a query can also find the database busy or locked
+//! once its busy timeout runs out. Pooled connections wait only briefly and
+//! the query is retried a few times with backoff; a `StorageError` surfaces
+//! only when the retries are exhausted.
+//!
+//! Connections opened outside the pool still share its bookkeeping: the
+//! schema of a database file is created or migrated once per store, not on
+//! every open.
+
+use super::Store;
+use crate::anel::{AnelError, AnelErrorCode};
+use anyhow::Result;
+use rusqlite::{Connection, ErrorCode};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long a pooled connection waits on a lock before its query fails busy
+const POOL_BUSY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Retries of a query that found the database busy or locked
+const BUSY_RETRIES: u32 = 5;
+
+/// Wait before the first busy retry, doubling for each further one
+const BUSY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// A collection's pooled connection, empty until first use
+pub(super) type ConnectionSlot = Arc<Mutex<Option<PooledConnection>>>;
+
+/// Pooled connection and the file it was opened on
+pub(super) struct PooledConnection {
+    conn: Connection,
+    file: Option<FileIdentity>,
+}
+
+/// Identity of a database file, changing when the file is replaced or written
+///
+/// Writes through other connections also change it, which only costs a reopen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileIdentity {
+    #[cfg(unix)]
+    dev: u64,
+    #[cfg(unix)]
+    ino: u64,
+    modified: Option<std::time::SystemTime>,
+    len: u64,
+}
+
+impl FileIdentity {
+    /// Whether both name the same file, however it was written since
+    #[cfg(unix)]
+    fn same_file(&self, other: &Self) -> bool {
+        self.dev == other.dev && self.ino == other.ino
+    }
+
+    /// Without inode numbers a replaced file cannot be told apart
+    #[cfg(not(unix))]
+    fn same_file(&self, _other: &Self) -> bool {
+        false
+    }
+
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        #[cfg(unix)]
+        use std::os::unix::fs::MetadataExt;
+        Some(Self {
+            #[cfg(unix)]
+            dev: metadata.dev(),
+            #[cfg(unix)]
+            ino: metadata.ino(),
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Database files whose schema this store created or migrated
+#[derive(Debug, Default)]
+pub(super) struct InitializedSchemas(Mutex<HashMap<PathBuf, FileIdentity>>);
+
+/// Busy retries and reopened connections, for the server's metrics
+#[derive(Debug, Default)]
+pub struct PoolStats {
+    retries: AtomicU64,
+    reopens: AtomicU64,
+    schema_inits: AtomicU64,
+}
+
+impl PoolStats {
+    /// Queries retried because the database was busy or locked
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Pooled connections reopened because their database changed or failed
+    pub fn reopens(&self) -> u64 {
+        self.reopens.load(Ordering::Relaxed)
+    }
+
+    /// Times a database schema was created or migrated
+    pub fn schema_inits(&self) -> u64 {
+        self.schema_inits.load(Ordering::Relaxed)
+    }
+}
+
+/// Whether a query error means another connection holds the database
+pub fn is_busy_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| match cause.downcast_ref::<rusqlite::Error>() {
+        Some(rusqlite::Error::SqliteFailure(failure, _)) => {
+            matches!(failure.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+        }
+        _ => false,
+    })
+}
+
+/// Whether a query error means the database changed under the connection
+pub fn is_stale_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| match cause.downcast_ref::<rusqlite::Error>() {
+        Some(rusqlite::Error::SqliteFailure(failure, message)) => {
+            matches!(
+                failure.code,
+                ErrorCode::DatabaseCorrupt
+                    | ErrorCode::NotADatabase
+                    | ErrorCode::SystemIoFailure
+                    | ErrorCode::SchemaChanged
+                    | ErrorCode::CannotOpen
+            ) || failure.extended_code == rusqlite::ffi::SQLITE_READONLY_DBMOVED
+                || message.as_deref().is_some_and(|m| m.starts_with("no such table"))
+        }
+        _ => false,
+    })
+}
+
+impl Store {
+    /// Run `query` on the collection's pooled connection
+    ///
+    /// Opens the connection on first use and reopens it when its database
+    /// file was replaced. A query failing because the database changed is
+    /// retried once on a freshly opened connection, and one finding the
+    /// database busy is retried with backoff; either fails as a
+    /// `StorageError` once retrying does not help. Each collection has its
+    /// own lock, so queries against different collections run concurrently.
+    pub fn with_connection<T>(&self, collection: &str, query: impl Fn(&Connection) -> Result<T>) -> Result<T> {
+        let db_path = self.config.db_path_for(collection);
+        let slot = {
+            let mut pool = self.connections.lock().unwrap_or_else(|e| e.into_inner());
+            Arc::clone(pool.entry(collection.to_string()).or_default())
+        };
+        let mut pooled = slot.lock().unwrap_or_else(|e| e.into_inner());
+
+        let current = FileIdentity::of(&db_path);
+        let reusable = pooled
+            .as_ref()
+            .is_some_and(|pooled| pooled.file.is_some() && pooled.file == current);
+        if !reusable {
+            if pooled.is_some() {
+                log::info!("Database for {} was replaced, reopening", collection);
+                self.pool_stats.reopens.fetch_add(1, Ordering::Relaxed);
+            }
+            *pooled = Some(self.open_pooled(collection, &db_path)?);
+        }
+
+        let mut busy_retries = 0;
+        let mut reopened = false;
+        loop {
+            let result = query(&pooled.as_ref().expect("connection opened above").conn);
+            match result {
+                Err(e) if is_busy_error(&e) && busy_retries < BUSY_RETRIES => {
+                    let delay = BUSY_BACKOFF * 2u32.pow(busy_retries);
+                    busy_retries += 1;
+                    log::debug!("Database for {} is busy ({}), retry {} in {:?}", collection, e, busy_retries, delay);
+                    self.pool_stats.retries.fetch_add(1, Ordering::Relaxed);
+                    std::thread::sleep(delay);
+                }
+                Err(e) if is_stale_error(&e) && !reopened => {
+                    log::warn!("Connection to {} went stale ({}), reopening", collection, e);
+                    reopened = true;
+                    self.pool_stats.reopens.fetch_add(1, Ordering::Relaxed);
+                    self.forget_schema(&db_path);
+                    *pooled = Some(self.open_pooled(collection, &db_path)?);
+                }
+                Err(e) if is_busy_error(&e) || is_stale_error(&e) => {
+                    let message = format!("Database for collection '{}' is unavailable: {}", collection, e);
+                    return Err(e.context(AnelError::new(
+                        AnelErrorCode::StorageError,
+                        "Storage Unavailable",
+                        message,
+                    )));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Counters of busy retries and reopened connections
+    pub fn pool_stats(&self) -> Arc<PoolStats> {
+        Arc::clone(&self.pool_stats)
+    }
+
+    /// Create or migrate the schema unless this store already did for the file
+    ///
+    /// A file replaced since, or deleted and recreated empty, is initialized
+    /// again. The lock is held while initializing, so concurrent opens of a
+    /// new database initialize it once.
+    pub(super) fn ensure_schema(&self, conn: &Connection, collection: &str, db_path: &Path) -> Result<()> {
+        let mut schemas = self.schemas.0.lock().unwrap_or_else(|e| e.into_inner());
+        let current = FileIdentity::of(db_path);
+        let initialized = match (schemas.get(db_path), current) {
+            (Some(known), Some(current)) => current.len > 0 && known.same_file(&current),
+            _ => false,
+        };
+        if initialized {
+            return Ok(());
+        }
+
+        Self::init_schema_with_tokenizer(conn, self.fts_tokenizer(collection), self.config.vector.dimension())?;
+        self.pool_stats.schema_inits.fetch_add(1, Ordering::Relaxed);
+        if let Some(identity) = FileIdentity::of(db_path) {
+            schemas.insert(db_path.to_path_buf(), identity);
+        }
+        Ok(())
+    }
+
+    /// Initialize the file's schema again on its next open
+    fn forget_schema(&self, db_path: &Path) {
+        self.schemas.0.lock().unwrap_or_else(|e| e.into_inner()).remove(db_path);
+    }
+
+    fn open_pooled(&self, collection: &str, db_path: &Path) -> Result<PooledConnection> {
+        let conn = self.get_connection(collection)?;
+        conn.busy_timeout(POOL_BUSY_TIMEOUT)?;
+        Ok(PooledConnection {
+            conn,
+            file: FileIdentity::of(db_path),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sqlite_error(code: std::os::raw::c_int, message: &str) -> anyhow::Error {
+        rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(code), Some(message.to_string())).into()
+    }
+
+    #[test]
+    fn test_is_stale_error_matches_changed_database() {
+        assert!(is_stale_error(&sqlite_error(rusqlite::ffi::SQLITE_NOTADB, "file is not a database")));
+        assert!(is_stale_error(&sqlite_error(rusqlite::ffi::SQLITE_CORRUPT, "database disk image is malformed")));
+        assert!(is_stale_error(&sqlite_error(rusqlite::ffi::SQLITE_READONLY_DBMOVED, "readonly database")));
+        assert!(is_stale_error(&sqlite_error(rusqlite::ffi::SQLITE_ERROR, "no such table: documents")));
+        assert!(is_stale_error(
+            &sqlite_error(rusqlite::ffi::SQLITE_NOTADB, "file is not a database").context("BM25 search")
+        ));
+
+        // Query mistakes are not fixed by reopening
+        assert!(!is_stale_error(&sqlite_error(rusqlite::ffi::SQLITE_ERROR, "fts5: syntax error near \"\"")));
+        assert!(!is_stale_error(&anyhow::anyhow!("no such table: documents")));
+    }
+
+    #[test]
+    fn test_is_busy_error_matches_busy_and_locked() {
+        assert!(is_busy_error(&sqlite_error(rusqlite::ffi::SQLITE_BUSY, "database is locked")));
+        assert!(is_busy_error(&sqlite_error(rusqlite::ffi::SQLITE_LOCKED, "database table is locked")));
+        assert!(is_busy_error(&sqlite_error(rusqlite::ffi::SQLITE_BUSY, "database is locked").context("BM25 search")));
+        assert!(!is_busy_error(&sqlite_error(rusqlite::ffi::SQLITE_NOTADB, "file is not a database")));
+        assert!(!is_busy_error(&anyhow::anyhow!("database is locked")));
+    }
+}
@@ -2,4 +2,4 @@
 
 pub mod qdrant_backend;
 
-pub use qdrant_backend::{DocumentInput, QdrantBackend};
+pub use qdrant_backend::{point_id, DocumentInput, QdrantBackend};
@@ -8,15 +8,21 @@
 //! - Cloud and local deployment support
 //! - RESTful API with gRPC transport
 //! - Automatic collection management
+//!
+//! `qmd embed` stores one point per chunk, with a payload naming the chunk's
+//! collection, path, title and content hash; searches map hits back to
+//! documents through that payload.
 
 use crate::store::SearchResult;
 use anyhow::{Context, Result};
 use qdrant_client::qdrant::{
-    CreateCollectionBuilder, Distance, PointStruct, SearchPointsBuilder,
-    Value, VectorParamsBuilder,
+    Condition, CreateCollectionBuilder, DeletePointsBuilder, Distance, Filter, PointStruct,
+    SearchPointsBuilder, Value, VectorParamsBuilder,
 };
 use qdrant_client::{Payload, Qdrant};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::sync::Mutex;
 
 /// Helper function to extract string from Value
 fn value_to_string(value: &Value) -> String {
@@ -35,7 +41,15 @@ pub struct QdrantBackend {
     /// Vector size/dimension
     vector_size: usize,
     /// Cache of collection existence
-    collections_cache: HashMap<String, bool>,
+    collections_cache: Mutex<HashMap<String, bool>>,
+}
+
+/// Point id of chunk `seq` of `path` in `collection`
+///
+/// Stable across runs, so re-embedding a document overwrites its points.
+pub fn point_id(collection: &str, path: &str, seq: usize) -> u64 {
+    let digest = Sha256::digest(format!("{}/{}#{}", collection, path, seq).as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"))
 }
 
 impl QdrantBackend {
@@ -68,14 +82,14 @@ impl QdrantBackend {
             client,
             collection: collection.to_string(),
             vector_size,
-            collections_cache: HashMap::new(),
+            collections_cache: Mutex::new(HashMap::new()),
         })
     }
 
     /// Ensure collection exists, create if not
-    pub async fn ensure_collection(&mut self) -> Result<()> {
+    pub async fn ensure_collection(&self) -> Result<()> {
         // Check cache first
-        if self.collections_cache.get(&self.collection) == Some(&true) {
+        if self.is_cached() {
             return Ok(());
         }
 
@@ -103,15 +117,23 @@ impl QdrantBackend {
         }
 
         // Cache the result
-        self.collections_cache.insert(self.collection.clone(), true);
+        self.collections_cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(self.collection.clone(), true);
         Ok(())
     }
 
+    fn is_cached(&self) -> bool {
+        let cache = self.collections_cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.get(&self.collection) == Some(&true)
+    }
+
     /// Upsert vectors into Qdrant collection
     ///
     /// # Arguments
     /// * `documents` - Vector of DocumentInput to upsert
-    pub async fn upsert_vectors(&mut self, documents: Vec<DocumentInput>) -> Result<()> {
+    pub async fn upsert_vectors(&self, documents: Vec<DocumentInput>) -> Result<()> {
         // Ensure collection exists
         self.ensure_collection().await?;
 
@@ -153,6 +175,28 @@ impl QdrantBackend {
         Ok(())
     }
 
+    /// Replace the points of every document in `collection` with content
+    /// `hash` by `documents`, so chunks a document no longer has go away
+    pub async fn replace_document(
+        &self,
+        collection: &str,
+        hash: &str,
+        documents: Vec<DocumentInput>,
+    ) -> Result<()> {
+        self.ensure_collection().await?;
+
+        let filter = Filter::must([
+            Condition::matches("collection", collection.to_string()),
+            Condition::matches("hash", hash.to_string()),
+        ]);
+        self.client
+            .delete_points(DeletePointsBuilder::new(&self.collection).points(filter).wait(true))
+            .await
+            .context("Failed to delete stale vectors from Qdrant")?;
+
+        self.upsert_vectors(documents).await
+    }
+
     /// Vector search using Qdrant
     ///
     /// # Arguments
@@ -218,6 +262,11 @@ impl QdrantBackend {
                 title,
                 hash,
                 query: None,
+                size: None,
+                match_line: None,
+                duplicates: Vec::new(),
+                source: vec![crate::store::SOURCE_VECTOR.to_string()],
+                score_components: None,
             });
         }
 
@@ -295,3 +344,15 @@ impl DocumentInput {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_id_is_stable_per_chunk() {
+        assert_eq!(point_id("docs", "docs/a.md", 0), point_id("docs", "docs/a.md", 0));
+        assert_ne!(point_id("docs", "docs/a.md", 0), point_id("docs", "docs/a.md", 1));
+        assert_ne!(point_id("docs", "docs/a.md", 0), point_id("notes", "docs/a.md", 0));
+    }
+}
@@ -0,0 +1,91 @@
+//! Rebuilding a collection's derived search tables from scratch.
+//!
+//! `update` only upserts and `verify --fix` repairs what it can detect. After
+//! a tokenizer or schema change, or an index too damaged to read, `reindex`
+//! drops the full-text table, creates it again and indexes every active
+//! document, then deletes the collection's vectors so the next
+//! `qmd embed` regenerates them. Documents and their content are untouched.
+//!
+//! A database shared by several collections has one full-text table, so
+//! reindexing one of them rebuilds the text index of all of them, once per
+//! call. Only the named collection's vectors are cleared, and not those of
+//! content another collection still has active.
+
+use super::trash::has_vec_table;
+use super::{fts, suggest, Store};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// What reindexing one collection rebuilt and cleared
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReindexReport {
+    pub collection: String,
+    /// Active documents in the new full-text index
+    pub documents_indexed: usize,
+    /// Vector chunks deleted, to be regenerated by `qmd embed`
+    pub vectors_cleared: usize,
+}
+
+impl Store {
+    /// Rebuild the full-text index of the named collection, or of every
+    /// collection, and clear its vectors
+    pub fn reindex(&self, collection: Option<&str>) -> Result<Vec<ReindexReport>> {
+        let collections: Vec<String> = match collection {
+            Some(name) => vec![self.resolve_collection(name)?.to_string()],
+            None => self.config.collections.iter().map(|c| c.name.clone()).collect(),
+        };
+
+        let mut reports = Vec::new();
+        // Databases whose full-text index was already rebuilt in this call
+        let mut rebuilt = HashSet::new();
+        for name in collections {
+            let mut conn = self.get_connection(&name)?;
+            let tx = conn.transaction()?;
+
+            let rebuild_fts = rebuilt.insert(self.config.db_path_for(&name));
+            if rebuild_fts {
+                // A damaged index cannot be trusted with 'delete' commands, so it
+                // is dropped rather than rebuilt in place
+                tx.execute("DROP TABLE IF EXISTS documents_fts", [])?;
+                Self::create_fts_index(&tx, self.fts_tokenizer(&name))?;
+                fts::rebuild(&tx)?;
+            }
+            let documents_indexed: i64 = tx.query_row(
+                "SELECT COUNT(*) FROM documents WHERE collection = ? AND active = 1",
+                [&name],
+                |row| row.get(0),
+            )?;
+
+            // Content shared with another collection's active documents keeps
+            // its vectors, which that collection still searches
+            let cleared = "SELECT hash FROM documents WHERE collection = ?1
+                           AND hash NOT IN (SELECT hash FROM documents WHERE collection != ?1 AND active = 1)";
+            if has_vec_table(&tx)? {
+                tx.execute(
+                    &format!(
+                        "DELETE FROM vectors_vec WHERE hash_seq IN (
+                             SELECT hash || '_' || seq FROM content_vectors WHERE hash IN ({}))",
+                        cleared
+                    ),
+                    [&name],
+                )?;
+            }
+            let vectors_cleared = tx.execute(
+                &format!("DELETE FROM content_vectors WHERE hash IN ({})", cleared),
+                [&name],
+            )?;
+            tx.commit()?;
+            if rebuild_fts {
+                suggest::rebuild_vocabulary(&mut conn)?;
+            }
+
+            reports.push(ReindexReport {
+                collection: name,
+                documents_indexed: documents_indexed as usize,
+                vectors_cleared,
+            });
+        }
+        Ok(reports)
+    }
+}
@@ -0,0 +1,82 @@
+//! Removing a single document from the index.
+//!
+//! A soft remove deactivates the document the way `update` does for a file
+//! gone from disk: it leaves the full-text index and the row waits for
+//! `qmd cleanup --purge`. A hard remove deletes the row, plus its stored text
+//! and vectors once no other document shares the content hash. Either way,
+//! the next `update` indexes the file again while it is still on disk.
+
+use super::trash::has_vec_table;
+use super::{fts, suggest, Store};
+use crate::anel::{AnelError, AnelErrorCode, RecoveryHint};
+use anyhow::Result;
+use rusqlite::OptionalExtension;
+
+fn not_found_error(collection: &str, path: &str) -> AnelError {
+    AnelError::new(
+        AnelErrorCode::NotFound,
+        "Document Not Found",
+        format!("No document {} in collection {}", path, collection),
+    )
+    .with_hint(RecoveryHint::new(
+        "LIST_FILES",
+        format!("Run `qmd ls {}` for the indexed paths", collection),
+    ))
+}
+
+impl Store {
+    /// Remove the document at `path` (relative to the collection root),
+    /// deleting it outright with `hard`
+    ///
+    /// Returns false when a soft remove finds the document already inactive.
+    /// An unknown path fails with NotFound.
+    pub fn remove_document(&self, collection: &str, path: &str, hard: bool) -> Result<bool> {
+        let collection = self.resolve_collection(collection)?.to_string();
+        let mut conn = self.get_connection(&collection)?;
+        let row: Option<(String, bool)> = conn
+            .query_row(
+                "SELECT hash, active FROM documents WHERE collection = ? AND path = ?",
+                [&collection, path],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((hash, active)) = row else {
+            return Err(not_found_error(&collection, path).into());
+        };
+        if !active && !hard {
+            return Ok(false);
+        }
+
+        let has_vec = has_vec_table(&conn)?;
+        let tx = conn.transaction()?;
+        fts::unindex(&tx, "d.collection = ? AND d.path = ?", [&collection, path])?;
+        if hard {
+            tx.execute("DELETE FROM documents WHERE collection = ? AND path = ?", [&collection, path])?;
+            // Copies at other paths keep the text and vectors they share
+            let shared: bool =
+                tx.query_row("SELECT EXISTS(SELECT 1 FROM documents WHERE hash = ?)", [&hash], |row| row.get(0))?;
+            if !shared {
+                if has_vec {
+                    tx.execute(
+                        "DELETE FROM vectors_vec WHERE hash_seq IN (
+                             SELECT hash || '_' || seq FROM content_vectors WHERE hash = ?)",
+                        [&hash],
+                    )?;
+                }
+                tx.execute("DELETE FROM content_vectors WHERE hash = ?", [&hash])?;
+                tx.execute("DELETE FROM content WHERE hash = ?", [&hash])?;
+            }
+        } else {
+            tx.execute(
+                "UPDATE documents SET active = 0 WHERE collection = ? AND path = ?",
+                [&collection, path],
+            )?;
+        }
+        tx.commit()?;
+        suggest::rebuild_vocabulary(&mut conn)?;
+
+        #[cfg(feature = "lancedb")]
+        self.deactivate_in_lance(&collection, &[path.to_string()])?;
+        Ok(true)
+    }
+}
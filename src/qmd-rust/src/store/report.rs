@@ -0,0 +1,180 @@
+//! Capacity-planning report for a single collection.
+//!
+//! Sizes come from the collection's index database: the recorded byte and
+//! token counts of active documents, chunk rows and stored embeddings, plus
+//! the database files on disk.
+
+use super::compress::CompressionStats;
+use super::{index_file_bytes, Store};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Largest documents kept in a full report
+pub const REPORT_LARGEST_DOCUMENTS: usize = 10;
+/// Extension reported for files without one
+const NO_EXTENSION: &str = "(none)";
+
+/// Documents sharing a file extension
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExtensionStats {
+    pub extension: String,
+    pub documents: usize,
+    pub bytes: u64,
+}
+
+/// Size of one document, for the largest-documents list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DocumentBytes {
+    pub path: String,
+    pub bytes: u64,
+    pub tokens: u64,
+}
+
+/// Size breakdown of one collection
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CollectionReport {
+    pub collection: String,
+    /// Database size on disk, including WAL/SHM files
+    pub index_bytes: u64,
+    pub document_count: usize,
+    /// Stored text across all documents
+    pub content_bytes: u64,
+    pub average_document_bytes: u64,
+    pub average_document_tokens: u64,
+    /// Most common extensions first
+    pub extensions: Vec<ExtensionStats>,
+    /// Biggest first
+    pub largest_documents: Vec<DocumentBytes>,
+    pub chunk_count: usize,
+    pub vector_count: usize,
+    /// Raw embedding storage: vectors x dimensions x 4 bytes
+    pub vector_bytes_estimate: u64,
+    /// How the document text is stored, see `compress_content`
+    pub compression: CompressionStats,
+}
+
+impl CollectionReport {
+    /// Copy keeping only the top `n` extensions and largest documents
+    pub fn trimmed(&self, n: usize) -> Self {
+        let mut report = self.clone();
+        report.extensions.truncate(n);
+        report.largest_documents.truncate(n);
+        report
+    }
+}
+
+impl Store {
+    /// Size report for the named collection
+    pub fn collection_report(&self, name: &str) -> Result<CollectionReport> {
+        let name = self.resolve_collection(name)?.to_string();
+        let compression = self.compression_stats(&name)?;
+        let conn = self.get_connection(&name)?;
+
+        let documents: Vec<DocumentBytes> = conn
+            .prepare(
+                "SELECT path, COALESCE(bytes, 0), COALESCE(tokens, 0) FROM documents
+                 WHERE collection = ? AND active = 1",
+            )?
+            .query_map([&name], |row| {
+                Ok(DocumentBytes {
+                    path: row.get(0)?,
+                    bytes: row.get::<_, i64>(1)? as u64,
+                    tokens: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let chunk_count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM content_vectors
+             WHERE hash IN (SELECT hash FROM documents WHERE collection = ? AND active = 1)",
+            [&name],
+            |row| row.get(0),
+        )?;
+        // vectors_vec only exists when sqlite-vec is loaded
+        let has_vec: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = 'vectors_vec'",
+            [],
+            |row| row.get(0),
+        )?;
+        let vector_count: i64 = if has_vec {
+            conn.query_row("SELECT COUNT(*) FROM vectors_vec", [], |row| row.get(0))?
+        } else {
+            0
+        };
+
+        let document_count = documents.len();
+        let content_bytes: u64 = documents.iter().map(|d| d.bytes).sum();
+        let total_tokens: u64 = documents.iter().map(|d| d.tokens).sum();
+        let average = |total: u64| total.checked_div(document_count as u64).unwrap_or(0);
+
+        Ok(CollectionReport {
+            index_bytes: index_file_bytes(&self.config.db_path_for(&name)),
+            document_count,
+            content_bytes,
+            average_document_bytes: average(content_bytes),
+            average_document_tokens: average(total_tokens),
+            extensions: extension_breakdown(&documents),
+            largest_documents: largest(documents, REPORT_LARGEST_DOCUMENTS),
+            chunk_count: chunk_count as usize,
+            vector_count: vector_count as usize,
+            vector_bytes_estimate: vector_count as u64 * self.config.vector.dimension() as u64 * 4,
+            compression,
+            collection: name,
+        })
+    }
+}
+
+/// Lowercased extension of a document path
+fn extension_of(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| NO_EXTENSION.to_string())
+}
+
+fn extension_breakdown(documents: &[DocumentBytes]) -> Vec<ExtensionStats> {
+    let mut by_extension: HashMap<String, ExtensionStats> = HashMap::new();
+    for doc in documents {
+        let extension = extension_of(&doc.path);
+        let entry = by_extension.entry(extension.clone()).or_insert(ExtensionStats {
+            extension,
+            documents: 0,
+            bytes: 0,
+        });
+        entry.documents += 1;
+        entry.bytes += doc.bytes;
+    }
+
+    let mut extensions: Vec<ExtensionStats> = by_extension.into_values().collect();
+    extensions.sort_by(|a, b| b.documents.cmp(&a.documents).then_with(|| a.extension.cmp(&b.extension)));
+    extensions
+}
+
+fn largest(mut documents: Vec<DocumentBytes>, n: usize) -> Vec<DocumentBytes> {
+    documents.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+    documents.truncate(n);
+    documents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(path: &str, bytes: u64) -> DocumentBytes {
+        DocumentBytes { path: path.to_string(), bytes, tokens: bytes / 4 }
+    }
+
+    #[test]
+    fn test_extension_breakdown_orders_by_count() {
+        let docs = vec![doc("a.md", 10), doc("b.MD", 20), doc("c.rs", 5), doc("Makefile", 1)];
+        let extensions = extension_breakdown(&docs);
+        let summary: Vec<(&str, usize, u64)> = extensions
+            .iter()
+            .map(|e| (e.extension.as_str(), e.documents, e.bytes))
+            .collect();
+        assert_eq!(summary, vec![("md", 2, 30), ("(none)", 1, 1), ("rs", 1, 5)]);
+    }
+}
@@ -0,0 +1,53 @@
+//! Waiting on async vector and FTS backends from the synchronous store API.
+//!
+//! Store methods are called from plain threads (the CLI, `parallel` workers)
+//! and from inside the server's, MCP's and CLI's tokio runtimes. Building and
+//! blocking on a second runtime inside a runtime panics, so a call made from
+//! a runtime worker blocks that worker in place and drives the future on the
+//! runtime it is already running on.
+
+use anyhow::Result;
+use std::future::Future;
+use tokio::runtime::{Handle, Runtime, RuntimeFlavor};
+
+/// Run a backend future to completion from synchronous store code
+///
+/// Fails on a current-thread runtime, whose only thread cannot block
+/// without stalling the backend's own I/O.
+pub fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            Ok(tokio::task::block_in_place(|| handle.block_on(future)))
+        }
+        Ok(_) => anyhow::bail!("Backend calls from a current-thread tokio runtime are not supported; use a multi-threaded runtime"),
+        Err(_) => Ok(Runtime::new()?.block_on(future)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_on_outside_a_runtime() {
+        assert_eq!(block_on(async { 7 }).unwrap(), 7);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_block_on_inside_a_multi_thread_runtime() {
+        let value = block_on(async {
+            tokio::task::yield_now().await;
+            7
+        });
+        assert_eq!(value.unwrap(), 7);
+
+        // Request handlers run on worker threads rather than in block_on
+        let spawned = tokio::spawn(async { block_on(async { 7 }).unwrap() });
+        assert_eq!(spawned.await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_block_on_inside_a_current_thread_runtime_is_an_error() {
+        assert!(block_on(async { 7 }).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Stopword removal for BM25 queries.
+//!
+//! porter+unicode61 keeps every word, so frequent words like "the" or "how"
+//! can dominate BM25 ranking. Collections with a stopword list get those words
+//! stripped from queries before they reach FTS5.
+
+use crate::anel::{AnelError, AnelErrorCode, RecoveryHint};
+use crate::config::Stopwords;
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// Default English list (the classic Lucene/Snowball short list)
+pub const ENGLISH: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in",
+    "into", "is", "it", "no", "not", "of", "on", "or", "such", "that", "the",
+    "their", "then", "there", "these", "they", "this", "to", "was", "will", "with",
+];
+
+/// FTS5 boolean operators, kept as written (they are case-sensitive)
+const OPERATORS: &[&str] = &["AND", "OR", "NOT", "NEAR"];
+
+/// Lowercased word set for a stopword config
+pub fn word_set(stopwords: &Stopwords) -> Result<HashSet<String>> {
+    match stopwords {
+        Stopwords::Builtin(name) => match name.as_str() {
+            "english" => Ok(ENGLISH.iter().map(|w| w.to_string()).collect()),
+            other => anyhow::bail!("Unknown stopword list '{}'; valid options: english", other),
+        },
+        Stopwords::Words(words) => Ok(words.iter().map(|w| w.to_lowercase()).collect()),
+    }
+}
+
+/// Remove stopwords from a query, leaving FTS syntax intact
+///
+/// Quoted phrases and tokens carrying FTS syntax (`*`, `:`, parentheses) are
+/// kept whole. Operators left without an operand on either side are dropped.
+/// Returns an empty string when nothing but stopwords remains.
+pub fn strip_stopwords(query: &str, stopwords: &HashSet<String>) -> String {
+    let mut kept: Vec<&str> = Vec::new();
+    let mut in_phrase = false;
+
+    for token in query.split_whitespace() {
+        let quotes = token.matches('"').count();
+        let keep = in_phrase
+            || quotes > 0
+            || OPERATORS.contains(&token)
+            || token.contains(['*', ':', '(', ')', '^'])
+            || !stopwords.contains(&token.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase());
+        if quotes % 2 == 1 {
+            in_phrase = !in_phrase;
+        }
+        if keep {
+            kept.push(token);
+        }
+    }
+
+    // Drop operators that lost an operand; of two adjacent operators the
+    // later one was bound to the removed word's right-hand side, so it wins
+    let mut cleaned: Vec<&str> = Vec::new();
+    for token in kept {
+        if OPERATORS.contains(&token) {
+            match cleaned.last() {
+                None => continue,
+                Some(last) if OPERATORS.contains(last) => {
+                    cleaned.pop();
+                }
+                Some(_) => {}
+            }
+        }
+        cleaned.push(token);
+    }
+    while cleaned.last().is_some_and(|last| OPERATORS.contains(last)) {
+        cleaned.pop();
+    }
+
+    cleaned.join(" ")
+}
+
+/// Error for a query made only of stopwords
+pub fn query_too_generic_error(query: &str) -> AnelError {
+    AnelError::new(
+        AnelErrorCode::InvalidInput,
+        "Query Too Generic",
+        format!("Query '{}' contains only stopwords; add a more specific term", query),
+    )
+    .with_hint(RecoveryHint::new(
+        "REFINE_QUERY",
+        "Include at least one distinctive word, or quote a phrase to search it verbatim",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn english() -> HashSet<String> {
+        word_set(&Stopwords::Builtin("english".to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_strip_stopwords_from_mixed_query() {
+        assert_eq!(strip_stopwords("how to install the server", &english()), "how install server");
+        assert_eq!(strip_stopwords("The Rust Book", &english()), "Rust Book");
+    }
+
+    #[test]
+    fn test_phrases_and_syntax_are_kept() {
+        assert_eq!(strip_stopwords("\"to be or not\" the", &english()), "\"to be or not\"");
+        assert_eq!(strip_stopwords("title:the config*", &english()), "title:the config*");
+    }
+
+    #[test]
+    fn test_dangling_operators_are_dropped() {
+        assert_eq!(strip_stopwords("rust AND the", &english()), "rust");
+        assert_eq!(strip_stopwords("the OR rust AND a NOT cargo", &english()), "rust NOT cargo");
+    }
+
+    #[test]
+    fn test_only_stopwords_is_empty() {
+        assert_eq!(strip_stopwords("the and of", &english()), "");
+        assert_eq!(strip_stopwords("", &english()), "");
+    }
+
+    #[test]
+    fn test_custom_words_and_unknown_list() {
+        let words = word_set(&Stopwords::Words(vec!["Foo".to_string()])).unwrap();
+        assert_eq!(strip_stopwords("foo bar", &words), "bar");
+
+        let err = word_set(&Stopwords::Builtin("klingon".to_string())).unwrap_err();
+        assert!(err.to_string().contains("valid options: english"));
+    }
+}
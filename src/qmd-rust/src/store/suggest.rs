@@ -0,0 +1,238 @@
+//! Query suggestions from the index vocabulary.
+//!
+//! Each collection keeps a `vocabulary` table of the words in its document
+//! titles and headings, with the number of active documents using them. It
+//! is rebuilt after every update and backs prefix completions (`qmd suggest`)
+//! and the "did you mean" hint searches print when nothing matched.
+
+use super::{SearchOptions, Store};
+use anyhow::Result;
+use rusqlite::{Connection, TransactionBehavior};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A vocabulary term and the documents using it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Suggestion {
+    pub term: String,
+    pub documents: usize,
+}
+
+/// Lowercased words of a document's title and heading lines
+///
+/// Words shorter than two characters and plain numbers are left out.
+pub fn heading_terms(title: &str, content: &str) -> BTreeSet<String> {
+    let headings = content
+        .lines()
+        .map(str::trim_start)
+        .filter(|line| line.starts_with('#'))
+        .map(|line| line.trim_start_matches('#'));
+    std::iter::once(title)
+        .chain(headings)
+        .flat_map(|text| text.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| word.chars().count() >= 2 && !word.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Terms of the collection's active documents and how many documents use each
+pub fn vocabulary_counts(conn: &Connection) -> Result<BTreeMap<String, usize>> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT d.title, qmd_doc(c.doc, c.codec, c.packed) FROM documents d JOIN content c ON c.hash = d.hash WHERE d.active = 1",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    for row in rows {
+        let (title, content) = row?;
+        for term in heading_terms(&title, &content) {
+            *counts.entry(term).or_default() += 1;
+        }
+    }
+    Ok(counts)
+}
+
+/// Replace the collection's vocabulary with the terms of its active documents
+pub fn rebuild_vocabulary(conn: &mut Connection) -> Result<()> {
+    let counts = vocabulary_counts(conn)?;
+
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    tx.execute("DELETE FROM vocabulary", [])?;
+    {
+        let mut insert = tx.prepare("INSERT INTO vocabulary (term, documents) VALUES (?, ?)")?;
+        for (term, documents) in &counts {
+            insert.execute(rusqlite::params![term, *documents as i64])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Edit distance between two words, counted in characters
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Largest edit distance a correction may have for a word of `len` characters
+fn max_distance(len: usize) -> usize {
+    if len <= 4 { 1 } else { 2 }
+}
+
+/// Closest vocabulary term to `word`: fewest edits, then most documents, then
+/// alphabetical
+fn closest<'a>(word: &str, vocabulary: &'a [Suggestion]) -> Option<&'a Suggestion> {
+    let limit = max_distance(word.chars().count());
+    vocabulary
+        .iter()
+        .map(|s| (levenshtein(word, &s.term), s))
+        .filter(|(distance, _)| *distance > 0 && *distance <= limit)
+        .min_by(|(da, a), (db, b)| da.cmp(db).then(b.documents.cmp(&a.documents)).then(a.term.cmp(&b.term)))
+        .map(|(_, s)| s)
+}
+
+/// Vocabulary terms sorted by documents, most first, then alphabetically
+fn ranked(counts: BTreeMap<String, usize>) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = counts
+        .into_iter()
+        .map(|(term, documents)| Suggestion { term, documents })
+        .collect();
+    suggestions.sort_by(|a, b| b.documents.cmp(&a.documents).then(a.term.cmp(&b.term)));
+    suggestions
+}
+
+impl Store {
+    /// Collections suggestions draw from: every one searchable by default
+    fn suggestion_collections(&self) -> Result<Vec<String>> {
+        let options = SearchOptions {
+            limit: 0,
+            search_all: true,
+            ..Default::default()
+        };
+        Ok(self.search_collections(&options)?.into_iter().map(str::to_string).collect())
+    }
+
+    /// Vocabulary terms starting with `prefix`, summed over collections
+    fn vocabulary_matching(&self, collections: &[String], prefix: &str) -> Result<BTreeMap<String, usize>> {
+        let mut counts = BTreeMap::new();
+        for collection in collections {
+            let rows = self.with_connection(collection, |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT term, documents FROM vocabulary WHERE substr(term, 1, length(?1)) = ?1",
+                )?;
+                let rows = stmt
+                    .query_map([prefix], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(rows)
+            })?;
+            for (term, documents) in rows {
+                *counts.entry(term).or_default() += documents as usize;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Completions of `prefix`, the terms used by the most documents first
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Result<Vec<Suggestion>> {
+        self.suggest_for(prefix, limit, None)
+    }
+
+    /// Completions drawn from the named collections only; `None` covers every
+    /// collection searchable by default
+    pub fn suggest_for(&self, prefix: &str, limit: usize, names: Option<&[String]>) -> Result<Vec<Suggestion>> {
+        let collections: Vec<String> = self
+            .suggestion_collections()?
+            .into_iter()
+            .filter(|c| names.is_none_or(|names| names.contains(c)))
+            .collect();
+        let prefix = prefix.trim().to_lowercase();
+        let mut suggestions = ranked(self.vocabulary_matching(&collections, &prefix)?);
+        suggestions.truncate(limit);
+        Ok(suggestions)
+    }
+
+    /// The query with each word that matches no document replaced by the
+    /// closest vocabulary term, or `None` when there is nothing to correct
+    pub fn did_you_mean(&self, query: &str) -> Result<Option<String>> {
+        let collections = self.suggestion_collections()?;
+        let vocabulary = ranked(self.vocabulary_matching(&collections, "")?);
+        let mut corrected = false;
+        let mut words = Vec::new();
+        for word in query.split_whitespace() {
+            let lower = word.to_lowercase();
+            let known = vocabulary.iter().any(|s| s.term == lower);
+            let replacement = if known || lower.chars().any(|c| !c.is_alphanumeric()) {
+                None
+            } else {
+                match closest(&lower, &vocabulary) {
+                    Some(s) if !self.matches_any(&collections, &lower)? => Some(s.term.clone()),
+                    _ => None,
+                }
+            };
+            corrected |= replacement.is_some();
+            words.push(replacement.unwrap_or_else(|| word.to_string()));
+        }
+        Ok(corrected.then(|| words.join(" ")))
+    }
+
+    /// Whether any of the collections' documents contain `word`
+    fn matches_any(&self, collections: &[String], word: &str) -> Result<bool> {
+        let phrase = format!("\"{}\"", word.replace('"', "\"\""));
+        for collection in collections {
+            let documents: i64 = self.with_connection(collection, |conn| {
+                Ok(conn.query_row(
+                    "SELECT COUNT(*) FROM documents_fts WHERE documents_fts MATCH ?",
+                    [&phrase],
+                    |row| row.get(0),
+                )?)
+            })?;
+            if documents > 0 {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(term: &str, documents: usize) -> Suggestion {
+        Suggestion { term: term.to_string(), documents }
+    }
+
+    #[test]
+    fn test_heading_terms_reads_title_and_headings_only() {
+        let terms = heading_terms("Walrus Care", "# Feeding\nbody text here\n  ## Ice-floes 2024\n#\n");
+        let terms: Vec<&str> = terms.iter().map(String::as_str).collect();
+        assert_eq!(terms, ["care", "feeding", "floes", "ice", "walrus"]);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("walrus", "walrus"), 0);
+        assert_eq!(levenshtein("walrsu", "walrus"), 2);
+        assert_eq!(levenshtein("walrs", "walrus"), 1);
+        assert_eq!(levenshtein("", "ice"), 3);
+        assert_eq!(levenshtein("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn test_closest_prefers_fewer_edits_then_more_documents() {
+        let vocabulary = [suggestion("walnut", 5), suggestion("walrus", 1)];
+        assert_eq!(closest("walrsu", &vocabulary).unwrap().term, "walrus");
+        let vocabulary = [suggestion("cart", 1), suggestion("card", 3)];
+        assert_eq!(closest("carx", &vocabulary).unwrap().term, "card");
+        // Short words allow only one edit
+        assert!(closest("cxrx", &vocabulary).is_none());
+    }
+}
@@ -0,0 +1,121 @@
+//! Per-stage wall-clock timings of the hybrid search pipeline.
+//!
+//! Every hybrid pipeline (CLI, MCP `query`, HTTP `/query`) times its stages
+//! unconditionally; a lap is one `Instant::now()`, so the cost is negligible.
+//! Callers decide where the numbers surface: JSON/NDJSON output, the
+//! `query --explain` view, the MCP audit record and the HTTP server's stage
+//! histograms.
+
+use serde::Serialize;
+use std::time::Instant;
+
+/// Milliseconds spent in each stage of one hybrid search
+///
+/// A stage that was skipped or not part of the pipeline reads 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct StageTimings {
+    /// Query expansion
+    pub expansion_ms: f64,
+    /// BM25 retrieval over the original query and its variants
+    pub bm25_ms: f64,
+    /// Query embedding and vector retrieval
+    pub vector_ms: f64,
+    /// RRF fusion of the BM25 and vector lists
+    pub fusion_ms: f64,
+    /// LLM reranking of the fused candidates
+    pub rerank_ms: f64,
+    /// Whole pipeline, from expansion to the final ordering
+    pub total_ms: f64,
+}
+
+impl StageTimings {
+    /// `(stage, ms)` pairs in pipeline order, without the total
+    pub fn stages(&self) -> [(&'static str, f64); 5] {
+        [
+            ("expansion", self.expansion_ms),
+            ("bm25", self.bm25_ms),
+            ("vector", self.vector_ms),
+            ("fusion", self.fusion_ms),
+            ("rerank", self.rerank_ms),
+        ]
+    }
+
+    /// One-line summary for the `--explain` view
+    pub fn render(&self) -> String {
+        let stages: Vec<String> = self
+            .stages()
+            .iter()
+            .map(|(stage, ms)| format!("{} {:.1}ms", stage, ms))
+            .collect();
+        format!("Timings: {} (total {:.1}ms)\n", stages.join(", "), self.total_ms)
+    }
+}
+
+/// Stopwatch handing out the time since the previous lap
+#[derive(Debug, Clone, Copy)]
+pub struct StageTimer {
+    start: Instant,
+    lap: Instant,
+}
+
+impl StageTimer {
+    /// Start timing the first stage now
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self { start: now, lap: now }
+    }
+
+    /// Milliseconds since the previous lap (or the start), starting the next
+    pub fn lap(&mut self) -> f64 {
+        let now = Instant::now();
+        let ms = millis(now - self.lap);
+        self.lap = now;
+        ms
+    }
+
+    /// Milliseconds since the start
+    pub fn total(&self) -> f64 {
+        millis(self.start.elapsed())
+    }
+}
+
+/// Duration in milliseconds, rounded to the microsecond
+fn millis(duration: std::time::Duration) -> f64 {
+    (duration.as_secs_f64() * 1_000_000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_timer_laps_add_up_to_total() {
+        let mut timer = StageTimer::start();
+        std::thread::sleep(Duration::from_millis(20));
+        let first = timer.lap();
+        let second = timer.lap();
+        let total = timer.total();
+        assert!(first >= 20.0, "{}", first);
+        assert!(second < first);
+        // Each figure is rounded to the microsecond
+        assert!(total >= first + second - 0.002, "{} < {} + {}", total, first, second);
+    }
+
+    #[test]
+    fn test_render_lists_stages_in_order() {
+        let timings = StageTimings {
+            expansion_ms: 0.31,
+            bm25_ms: 1.5,
+            vector_ms: 120.0,
+            fusion_ms: 0.04,
+            rerank_ms: 0.0,
+            total_ms: 121.9,
+        };
+        assert_eq!(
+            timings.render(),
+            "Timings: expansion 0.3ms, bm25 1.5ms, vector 120.0ms, fusion 0.0ms, rerank 0.0ms (total 121.9ms)\n"
+        );
+        assert_eq!(serde_json::to_value(timings).unwrap()["vector_ms"], 120.0);
+    }
+}
@@ -0,0 +1,473 @@
+//! Trash for purged documents.
+//!
+//! Purges copy what they delete into `<cache>/.trash/<id>.jsonl` first: a
+//! manifest line, then one line per document and per vector chunk.
+//! `qmd trash restore <id>` writes them back into the index. Operations older
+//! than `trash.retention_days` are deleted whenever the trash is written or
+//! listed.
+
+use super::{compress, fts, Store};
+use crate::anel::{AnelError, AnelErrorCode, RecoveryHint};
+use crate::config::{CollectionConfig, Config};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+/// One purge operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashManifest {
+    pub id: String,
+    /// Command that purged the documents, e.g. "cleanup --purge"
+    pub operation: String,
+    /// RFC 3339
+    pub created_at: String,
+    pub collections: Vec<String>,
+    pub documents: usize,
+    pub vectors: usize,
+    /// Config of a removed collection, added back on restore
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection_config: Option<CollectionConfig>,
+}
+
+/// A purged document with its stored text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrashedDocument {
+    collection: String,
+    path: String,
+    title: String,
+    hash: String,
+    created_at: String,
+    modified_at: String,
+    body: String,
+    bytes: Option<i64>,
+    words: Option<i64>,
+    tokens: Option<i64>,
+}
+
+/// A purged embedding chunk; `embedding` is absent without sqlite-vec
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrashedVector {
+    collection: String,
+    hash: String,
+    seq: i64,
+    pos: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    line: Option<i64>,
+    model: String,
+    embedded_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    embedding: Option<Vec<f32>>,
+}
+
+/// One line of a trash file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TrashRecord {
+    Manifest(TrashManifest),
+    Document(TrashedDocument),
+    Vector(TrashedVector),
+}
+
+/// Outcome of `Store::restore_trash`
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreSummary {
+    pub manifest: TrashManifest,
+    pub restored: usize,
+    /// Paths skipped because an active document already holds them
+    pub skipped: Vec<String>,
+}
+
+fn trash_file(config: &Config, id: &str) -> PathBuf {
+    config.trash_dir().join(format!("{}.jsonl", id))
+}
+
+fn not_found_error(id: &str) -> AnelError {
+    AnelError::new(
+        AnelErrorCode::NotFound,
+        "Trash Operation Not Found",
+        format!("No trash operation with id {}", id),
+    )
+    .with_hint(RecoveryHint::new(
+        "LIST_TRASH",
+        "Run `qmd trash list` for the operations that can still be restored",
+    ))
+}
+
+/// Manifest line of a trash file
+fn read_manifest(path: &std::path::Path) -> Result<TrashManifest> {
+    let mut line = String::new();
+    BufReader::new(std::fs::File::open(path)?).read_line(&mut line)?;
+    match serde_json::from_str(&line)? {
+        TrashRecord::Manifest(manifest) => Ok(manifest),
+        _ => anyhow::bail!("Trash file has no manifest: {}", path.display()),
+    }
+}
+
+/// Delete operations older than the retention period
+pub fn expire_trash(config: &Config) -> Result<usize> {
+    let dir = config.trash_dir();
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(config.trash.retention_days as i64);
+
+    let mut expired = 0;
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let Ok(manifest) = read_manifest(&path) else {
+            continue;
+        };
+        let created = chrono::DateTime::parse_from_rfc3339(&manifest.created_at)?;
+        if created < cutoff {
+            std::fs::remove_file(&path)?;
+            expired += 1;
+        }
+    }
+    Ok(expired)
+}
+
+/// Restorable operations, newest first
+pub fn list_trash(config: &Config) -> Result<Vec<TrashManifest>> {
+    expire_trash(config)?;
+    let dir = config.trash_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut manifests = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        if let Ok(manifest) = read_manifest(&entry?.path()) {
+            manifests.push(manifest);
+        }
+    }
+    manifests.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+    Ok(manifests)
+}
+
+/// Manifest of one operation
+pub fn trash_manifest(config: &Config, id: &str) -> Result<TrashManifest> {
+    let path = trash_file(config, id);
+    if !path.exists() {
+        return Err(not_found_error(id).into());
+    }
+    read_manifest(&path)
+}
+
+pub(super) fn has_vec_table(conn: &Connection) -> Result<bool> {
+    // vectors_vec only exists when sqlite-vec is loaded
+    Ok(conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = 'vectors_vec'",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+/// Documents of a collection matching `filter`, with their vector chunks
+fn collect(conn: &Connection, collection: &str, filter: &str) -> Result<(Vec<TrashedDocument>, Vec<TrashedVector>)> {
+    let documents: Vec<TrashedDocument> = conn
+        .prepare(&format!(
+            "SELECT d.path, d.title, d.hash, d.created_at, d.modified_at, qmd_doc(c.doc, c.codec, c.packed), d.bytes, d.words, d.tokens
+             FROM documents d JOIN content c ON c.hash = d.hash
+             WHERE d.collection = ? AND {}",
+            filter
+        ))?
+        .query_map([collection], |row| {
+            Ok(TrashedDocument {
+                collection: collection.to_string(),
+                path: row.get(0)?,
+                title: row.get(1)?,
+                hash: row.get(2)?,
+                created_at: row.get(3)?,
+                modified_at: row.get(4)?,
+                body: row.get(5)?,
+                bytes: row.get(6)?,
+                words: row.get(7)?,
+                tokens: row.get(8)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let has_vec = has_vec_table(conn)?;
+    let mut vectors = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for hash in documents.iter().map(|d| &d.hash) {
+        if !seen.insert(hash) {
+            continue;
+        }
+        let chunks: Vec<(i64, i64, Option<i64>, String, String)> = conn
+            .prepare("SELECT seq, pos, line, model, embedded_at FROM content_vectors WHERE hash = ? ORDER BY seq")?
+            .query_map([hash], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        for (seq, pos, line, model, embedded_at) in chunks {
+            let embedding = if has_vec {
+                conn.query_row(
+                    "SELECT embedding FROM vectors_vec WHERE hash_seq = ?",
+                    [format!("{}_{}", hash, seq)],
+                    |row| row.get::<_, Vec<u8>>(0),
+                )
+                .optional()?
+                .map(|blob| {
+                    blob.chunks_exact(4)
+                        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                        .collect()
+                })
+            } else {
+                None
+            };
+            vectors.push(TrashedVector {
+                collection: collection.to_string(),
+                hash: hash.clone(),
+                seq,
+                pos,
+                line,
+                model,
+                embedded_at,
+                embedding,
+            });
+        }
+    }
+    Ok((documents, vectors))
+}
+
+impl Store {
+    /// Write purged documents and vectors to a new trash file
+    fn write_trash(
+        &self,
+        operation: &str,
+        collections: Vec<String>,
+        documents: &[TrashedDocument],
+        vectors: &[TrashedVector],
+        collection_config: Option<CollectionConfig>,
+    ) -> Result<TrashManifest> {
+        expire_trash(&self.config)?;
+        let now = chrono::Utc::now();
+        let manifest = TrashManifest {
+            id: format!("{}-{}", now.format("%Y%m%d%H%M%S"), &uuid::Uuid::new_v4().simple().to_string()[..8]),
+            operation: operation.to_string(),
+            created_at: now.to_rfc3339(),
+            collections,
+            documents: documents.len(),
+            vectors: vectors.len(),
+            collection_config,
+        };
+
+        std::fs::create_dir_all(self.config.trash_dir())?;
+        let path = trash_file(&self.config, &manifest.id);
+        let mut out = std::io::BufWriter::new(std::fs::File::create(&path)?);
+        writeln!(out, "{}", serde_json::to_string(&TrashRecord::Manifest(manifest.clone()))?)?;
+        for document in documents {
+            writeln!(out, "{}", serde_json::to_string(&TrashRecord::Document(document.clone()))?)?;
+        }
+        for vector in vectors {
+            writeln!(out, "{}", serde_json::to_string(&TrashRecord::Vector(vector.clone()))?)?;
+        }
+        out.flush().with_context(|| format!("Failed to write trash file {}", path.display()))?;
+        Ok(manifest)
+    }
+
+    /// Permanently delete soft-deleted documents, keeping a copy in the trash
+    ///
+    /// Covers one collection or all of them. Stored text and vectors go too
+    /// once no remaining document shares their content hash. Returns `None`
+    /// when there was nothing to purge.
+    pub fn purge_inactive(&self, collection: Option<&str>) -> Result<Option<TrashManifest>> {
+        let collections: Vec<String> = match collection {
+            Some(name) => vec![self.resolve_collection(name)?.to_string()],
+            None => self.config.collections.iter().map(|c| c.name.clone()).collect(),
+        };
+
+        let mut documents = Vec::new();
+        let mut vectors = Vec::new();
+        let mut purged = Vec::new();
+        for name in &collections {
+            let conn = self.get_connection(name)?;
+            let (mut docs, mut vecs) = collect(&conn, name, "d.active = 0")?;
+            if !docs.is_empty() {
+                purged.push(name.clone());
+            }
+            documents.append(&mut docs);
+            vectors.append(&mut vecs);
+        }
+        if documents.is_empty() {
+            return Ok(None);
+        }
+
+        // The trash copy is written before anything is deleted
+        let manifest = self.write_trash("cleanup --purge", purged.clone(), &documents, &vectors, None)?;
+
+        for name in &purged {
+            let conn = self.get_connection(name)?;
+            let has_vec = has_vec_table(&conn)?;
+            let tx = conn.unchecked_transaction()?;
+            tx.execute("DELETE FROM documents WHERE collection = ? AND active = 0", [name])?;
+            for vector in vectors.iter().filter(|v| v.collection == *name) {
+                let shared: bool = tx.query_row(
+                    "SELECT COUNT(*) > 0 FROM documents WHERE hash = ?",
+                    [&vector.hash],
+                    |row| row.get(0),
+                )?;
+                if shared {
+                    continue;
+                }
+                tx.execute(
+                    "DELETE FROM content_vectors WHERE hash = ? AND seq = ?",
+                    params![vector.hash, vector.seq],
+                )?;
+                if has_vec {
+                    tx.execute(
+                        "DELETE FROM vectors_vec WHERE hash_seq = ?",
+                        [format!("{}_{}", vector.hash, vector.seq)],
+                    )?;
+                }
+            }
+            tx.execute("DELETE FROM content WHERE hash NOT IN (SELECT hash FROM documents)", [])?;
+            tx.commit()?;
+        }
+
+        Ok(Some(manifest))
+    }
+
+    /// Copy every active document of a collection into the trash before its
+    /// index is deleted
+    pub fn trash_collection(&self, name: &str, collection_config: Option<CollectionConfig>) -> Result<TrashManifest> {
+        let conn = self.get_connection(name)?;
+        let (documents, vectors) = collect(&conn, name, "d.active = 1")?;
+        self.write_trash(
+            "collection remove --purge",
+            vec![name.to_string()],
+            &documents,
+            &vectors,
+            collection_config,
+        )
+    }
+
+    /// Delete a collection's documents from a shared database, where there is
+    /// no per-collection index file to remove
+    ///
+    /// Stored text and vectors go too once no other collection's document
+    /// shares their content hash.
+    pub fn delete_collection_rows(&self, name: &str) -> Result<()> {
+        let conn = self.get_connection(name)?;
+        let has_vec = has_vec_table(&conn)?;
+        let tx = conn.unchecked_transaction()?;
+        fts::unindex(&tx, "d.collection = ?", [name])?;
+        tx.execute("DELETE FROM documents WHERE collection = ?", [name])?;
+        if has_vec {
+            tx.execute(
+                "DELETE FROM vectors_vec WHERE hash_seq IN (
+                     SELECT hash || '_' || seq FROM content_vectors
+                     WHERE hash NOT IN (SELECT hash FROM documents))",
+                [],
+            )?;
+        }
+        tx.execute("DELETE FROM content_vectors WHERE hash NOT IN (SELECT hash FROM documents)", [])?;
+        tx.execute("DELETE FROM content WHERE hash NOT IN (SELECT hash FROM documents)", [])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Put a trashed operation's documents, FTS rows and vectors back
+    ///
+    /// Documents come back active, so they are searchable again; the next
+    /// `update` deactivates any whose files are gone. A path that already has
+    /// an active document is left alone. The trash file is removed afterwards.
+    pub fn restore_trash(&self, id: &str) -> Result<RestoreSummary> {
+        let path = trash_file(&self.config, id);
+        if !path.exists() {
+            return Err(not_found_error(id).into());
+        }
+
+        let mut manifest = None;
+        let mut documents = Vec::new();
+        let mut vectors = Vec::new();
+        for line in BufReader::new(std::fs::File::open(&path)?).lines() {
+            match serde_json::from_str(&line?)? {
+                TrashRecord::Manifest(m) => manifest = Some(m),
+                TrashRecord::Document(d) => documents.push(d),
+                TrashRecord::Vector(v) => vectors.push(v),
+            }
+        }
+        let manifest = manifest.with_context(|| format!("Trash file has no manifest: {}", path.display()))?;
+
+        let mut restored = 0;
+        let mut skipped = Vec::new();
+        for name in &manifest.collections {
+            let conn = self.get_connection(name)?;
+            let has_vec = has_vec_table(&conn)?;
+            let tx = conn.unchecked_transaction()?;
+
+            for doc in documents.iter().filter(|d| d.collection == *name) {
+                let existing: Option<bool> = tx
+                    .query_row(
+                        "SELECT active = 1 FROM documents WHERE collection = ? AND path = ?",
+                        [name, &doc.path],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                match existing {
+                    Some(true) => {
+                        skipped.push(format!("{}/{}", name, doc.path));
+                        continue;
+                    }
+                    Some(false) => {
+                        tx.execute("DELETE FROM documents WHERE collection = ? AND path = ?", [name, &doc.path])?;
+                    }
+                    None => {}
+                }
+
+                compress::insert_content(&tx, &doc.hash, &doc.body, self.compresses_content(name), &doc.created_at)?;
+                tx.execute(
+                    "INSERT INTO documents (collection, path, title, hash, created_at, modified_at, active, bytes, words, tokens)
+                     VALUES (?, ?, ?, ?, ?, ?, 1, ?, ?, ?)",
+                    params![name, doc.path, doc.title, doc.hash, doc.created_at, doc.modified_at, doc.bytes, doc.words, doc.tokens],
+                )?;
+                fts::index(&tx, "d.id = ?", [tx.last_insert_rowid()])?;
+                restored += 1;
+            }
+
+            for vector in vectors.iter().filter(|v| v.collection == *name) {
+                tx.execute(
+                    "INSERT OR IGNORE INTO content_vectors (hash, seq, pos, line, model, embedded_at) VALUES (?, ?, ?, ?, ?, ?)",
+                    params![vector.hash, vector.seq, vector.pos, vector.line, vector.model, vector.embedded_at],
+                )?;
+                if let (true, Some(embedding)) = (has_vec, &vector.embedding) {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO vectors_vec (hash_seq, embedding) VALUES (?, ?)",
+                        [format!("{}_{}", vector.hash, vector.seq), serde_json::to_string(embedding)?],
+                    )?;
+                }
+            }
+            tx.commit()?;
+        }
+
+        std::fs::remove_file(&path)?;
+        Ok(RestoreSummary { manifest, restored, skipped })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trash_record_round_trip() {
+        let record = TrashRecord::Vector(TrashedVector {
+            collection: "docs".to_string(),
+            hash: "abc".to_string(),
+            seq: 1,
+            pos: 120,
+            line: Some(4),
+            model: "embed".to_string(),
+            embedded_at: "2026-01-01 00:00:00".to_string(),
+            embedding: Some(vec![0.5, -1.0]),
+        });
+        let line = serde_json::to_string(&record).unwrap();
+        assert!(line.starts_with("{\"type\":\"vector\""), "{}", line);
+        match serde_json::from_str(&line).unwrap() {
+            TrashRecord::Vector(v) => assert_eq!(v.embedding, Some(vec![0.5, -1.0])),
+            other => panic!("unexpected record {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Consistency checks between a collection's tables, behind `qmd verify`.
+//!
+//! The full-text index, vector chunks, vocabulary counts and document sizes
+//! are all derived from `documents` and `content`. An interrupted write or a
+//! database edited by hand can leave them out of step; `verify` reports where,
+//! and `verify --fix` re-derives them. Repairs never touch documents or content.
+
+use super::trash::has_vec_table;
+use super::{fts, suggest, DocumentSize, Store};
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// Discrepancies found in one collection, and the repairs applied to them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VerifyReport {
+    pub collection: String,
+    /// Active documents the full-text index does not cover
+    pub missing_fts: usize,
+    /// Full-text rows left behind by deleted or inactive documents
+    pub stale_fts: usize,
+    /// Indexed text no longer matching the documents, per FTS5's integrity check
+    pub fts_mismatch: bool,
+    /// Vector chunks whose content no document uses
+    pub orphan_vectors: usize,
+    /// Vocabulary terms whose document counts are wrong, missing or extra
+    pub vocabulary_drift: usize,
+    /// Documents whose recorded bytes, words or tokens differ from their content
+    pub wrong_sizes: usize,
+    /// What `--fix` changed, one line per repair
+    pub fixed: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Whether no discrepancy was found
+    pub fn is_consistent(&self) -> bool {
+        self.missing_fts == 0
+            && self.stale_fts == 0
+            && !self.fts_mismatch
+            && self.orphan_vectors == 0
+            && self.vocabulary_drift == 0
+            && self.wrong_sizes == 0
+    }
+
+    /// Whether discrepancies remain after the repairs, if any, were applied
+    pub fn needs_fix(&self) -> bool {
+        !self.is_consistent() && self.fixed.is_empty()
+    }
+}
+
+fn count(conn: &Connection, sql: &str) -> Result<usize> {
+    Ok(conn.query_row(sql, [], |row| row.get::<_, i64>(0))? as usize)
+}
+
+/// Documents whose recorded size is off, with the size measured from content
+fn wrong_sizes(conn: &Connection) -> Result<Vec<(i64, DocumentSize)>> {
+    let mut stmt = conn.prepare(
+        "SELECT d.id, d.bytes, d.words, d.tokens, qmd_doc(c.doc, c.codec, c.packed) FROM documents d JOIN content c ON c.hash = d.hash",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, DocumentSize::from_row(row, 1)?, row.get::<_, String>(4)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, recorded, doc)| {
+            let measured = DocumentSize::measure(&doc);
+            (recorded != Some(measured)).then_some((id, measured))
+        })
+        .collect())
+}
+
+/// Check one collection's database
+fn inspect(conn: &Connection, collection: &str) -> Result<VerifyReport> {
+    let missing_fts = count(
+        conn,
+        "SELECT COUNT(*) FROM documents d JOIN content c ON c.hash = d.hash
+         WHERE d.active = 1 AND d.id NOT IN (SELECT id FROM documents_fts_docsize)",
+    )?;
+    let stale_fts = count(
+        conn,
+        "SELECT COUNT(*) FROM documents_fts_docsize
+         WHERE id NOT IN (SELECT id FROM documents WHERE active = 1)",
+    )?;
+    // With rank 1 the check compares the index against the external content;
+    // it fails with SQLITE_CORRUPT_VTAB when they differ
+    let fts_mismatch = conn
+        .execute("INSERT INTO documents_fts(documents_fts, rank) VALUES('integrity-check', 1)", [])
+        .is_err();
+
+    // Soft-deleted documents keep their vectors for `trash restore`
+    let mut orphan_vectors = count(
+        conn,
+        "SELECT COUNT(*) FROM content_vectors WHERE hash NOT IN (SELECT hash FROM documents)",
+    )?;
+    if has_vec_table(conn)? {
+        orphan_vectors += count(
+            conn,
+            "SELECT COUNT(*) FROM vectors_vec
+             WHERE hash_seq NOT IN (SELECT hash || '_' || seq FROM content_vectors)",
+        )?;
+    }
+
+    let expected = suggest::vocabulary_counts(conn)?;
+    let mut stmt = conn.prepare("SELECT term, documents FROM vocabulary")?;
+    let stored = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let wrong = stored
+        .iter()
+        .filter(|(term, documents)| expected.get(term) != Some(documents))
+        .count();
+    let missing = expected
+        .keys()
+        .filter(|term| !stored.iter().any(|(stored, _)| stored == *term))
+        .count();
+
+    let wrong_sizes = wrong_sizes(conn)?.len();
+
+    Ok(VerifyReport {
+        collection: collection.to_string(),
+        missing_fts,
+        stale_fts,
+        fts_mismatch,
+        orphan_vectors,
+        vocabulary_drift: wrong + missing,
+        wrong_sizes,
+        fixed: Vec::new(),
+    })
+}
+
+/// Re-derive whatever `report` found out of step, noting each repair in it
+fn repair(conn: &mut Connection, report: &mut VerifyReport) -> Result<()> {
+    if report.missing_fts > 0 || report.stale_fts > 0 || report.fts_mismatch {
+        fts::rebuild(conn)?;
+        report.fixed.push(format!(
+            "rebuilt full-text index ({} missing, {} stale rows)",
+            report.missing_fts, report.stale_fts
+        ));
+    }
+
+    if report.orphan_vectors > 0 {
+        let tx = conn.transaction()?;
+        if has_vec_table(&tx)? {
+            tx.execute(
+                "DELETE FROM vectors_vec WHERE hash_seq IN (
+                     SELECT hash || '_' || seq FROM content_vectors
+                     WHERE hash NOT IN (SELECT hash FROM documents))",
+                [],
+            )?;
+            tx.execute(
+                "DELETE FROM vectors_vec WHERE hash_seq NOT IN (SELECT hash || '_' || seq FROM content_vectors)",
+                [],
+            )?;
+        }
+        tx.execute("DELETE FROM content_vectors WHERE hash NOT IN (SELECT hash FROM documents)", [])?;
+        tx.commit()?;
+        report.fixed.push(format!("deleted {} orphan vectors", report.orphan_vectors));
+    }
+
+    if report.vocabulary_drift > 0 {
+        suggest::rebuild_vocabulary(conn)?;
+        report.fixed.push(format!("rebuilt vocabulary ({} terms corrected)", report.vocabulary_drift));
+    }
+
+    if report.wrong_sizes > 0 {
+        for (id, size) in wrong_sizes(conn)? {
+            conn.execute(
+                "UPDATE documents SET bytes = ?, words = ?, tokens = ? WHERE id = ?",
+                rusqlite::params![size.bytes as i64, size.words as i64, size.tokens as i64, id],
+            )?;
+        }
+        report.fixed.push(format!("recounted sizes of {} documents", report.wrong_sizes));
+    }
+    Ok(())
+}
+
+impl Store {
+    /// Check the named collection, or every collection, for tables out of
+    /// step with its documents; with `fix`, repair what was found
+    pub fn verify(&self, collection: Option<&str>, fix: bool) -> Result<Vec<VerifyReport>> {
+        let collections: Vec<String> = match collection {
+            Some(name) => vec![self.resolve_collection(name)?.to_string()],
+            None => self.config.collections.iter().map(|c| c.name.clone()).collect(),
+        };
+
+        let mut reports = Vec::new();
+        for name in &collections {
+            let mut conn = self.get_connection(name)?;
+            let mut report = inspect(&conn, name)?;
+            if fix && !report.is_consistent() {
+                repair(&mut conn, &mut report)?;
+            }
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_fix_only_for_unrepaired_discrepancies() {
+        let mut report = VerifyReport::default();
+        assert!(report.is_consistent());
+        assert!(!report.needs_fix());
+
+        report.orphan_vectors = 2;
+        assert!(!report.is_consistent());
+        assert!(report.needs_fix());
+
+        report.fixed.push("deleted 2 orphan vectors".to_string());
+        assert!(!report.needs_fix());
+    }
+}
@@ -0,0 +1,238 @@
+//! Files of a collection, found by walking its directory.
+//!
+//! The walk matches each file's path under the collection against the
+//! collection pattern. Symbolic links are skipped unless `follow_symlinks`
+//! is set. When they are followed, a link resolves to its target: a target
+//! inside the collection is indexed under its own path, once, however many
+//! links reach it; a target outside is indexed under the link's path. A
+//! directory link whose target was already walked is skipped, so link
+//! cycles end instead of recursing forever.
+
+use super::path::{collection_pattern, index_path};
+use anyhow::{Context, Result};
+use log::debug;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// File found in a collection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectionFile {
+    /// Where to read the file
+    pub path: PathBuf,
+    /// Path stored in the index, relative to the collection with `/` separators
+    pub relative: String,
+}
+
+/// Entry the walk could not read, with its path relative to the collection
+#[derive(Debug)]
+pub struct WalkError {
+    pub relative: String,
+    pub error: std::io::Error,
+}
+
+/// Files under `base` matching `pattern`, sorted by path, and the entries
+/// that could not be read
+pub fn collection_files(
+    base: &Path,
+    pattern: &str,
+    follow_symlinks: bool,
+) -> Result<(Vec<CollectionFile>, Vec<WalkError>)> {
+    let root = base
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve collection path: {}", base.display()))?;
+    let relative = crate::paths::normalize_rel(base, pattern)
+        .with_context(|| format!("Invalid collection pattern: {}", pattern))?;
+    let pattern = glob::Pattern::new(&collection_pattern(&index_path(&relative)))
+        .with_context(|| format!("Invalid collection pattern: {}", pattern))?;
+
+    let mut walk = Walk {
+        root: root.clone(),
+        pattern,
+        follow_symlinks,
+        walked_dirs: HashSet::new(),
+        indexed_files: HashSet::new(),
+        links: VecDeque::new(),
+        files: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    // Real entries first, so files reached both directly and through a link
+    // keep their own path
+    walk.dir(&root, Path::new(""));
+    while let Some((link, relative)) = walk.links.pop_front() {
+        walk.link(&link, &relative);
+    }
+
+    walk.files.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok((walk.files, walk.errors))
+}
+
+struct Walk {
+    root: PathBuf,
+    pattern: glob::Pattern,
+    follow_symlinks: bool,
+    walked_dirs: HashSet<PathBuf>,
+    indexed_files: HashSet<PathBuf>,
+    /// Links found so far: where they are and their path under the collection
+    links: VecDeque<(PathBuf, PathBuf)>,
+    files: Vec<CollectionFile>,
+    errors: Vec<WalkError>,
+}
+
+impl Walk {
+    /// Walk the directory `dir`, which appears at `relative` in the collection
+    fn dir(&mut self, dir: &Path, relative: &Path) {
+        if let Ok(canonical) = dir.canonicalize() {
+            self.walked_dirs.insert(canonical);
+        }
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) => {
+                self.errors.push(WalkError { relative: index_path(relative), error });
+                return;
+            }
+        };
+
+        let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let entry_relative = relative.join(entry.file_name());
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(error) => {
+                    self.errors.push(WalkError { relative: index_path(&entry_relative), error });
+                    continue;
+                }
+            };
+
+            if file_type.is_symlink() {
+                if self.follow_symlinks {
+                    self.links.push_back((path, entry_relative));
+                } else {
+                    debug!("Skipping symlink {}", path.display());
+                }
+            } else if file_type.is_dir() {
+                self.dir(&path, &entry_relative);
+            } else if file_type.is_file() {
+                self.file(path, &entry_relative);
+            }
+        }
+    }
+
+    /// Follow the link at `link`, which appears at `relative` in the collection
+    fn link(&mut self, link: &Path, relative: &Path) {
+        let target = match link.canonicalize() {
+            Ok(target) => target,
+            Err(e) => {
+                // Dangling links and links resolving to themselves
+                debug!("Skipping unresolvable symlink {}: {}", link.display(), e);
+                return;
+            }
+        };
+
+        if target.is_dir() {
+            if self.walked_dirs.contains(&target) {
+                debug!("Skipping symlink {} to a directory already walked", link.display());
+                return;
+            }
+            self.dir(&target, relative);
+        } else if target.is_file() {
+            // A target inside the collection is indexed under its own path
+            let relative = target
+                .strip_prefix(&self.root)
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|_| relative.to_path_buf());
+            self.file(target, &relative);
+        }
+    }
+
+    /// Add the file at canonical `path`, appearing at `relative`, if it matches
+    fn file(&mut self, path: PathBuf, relative: &Path) {
+        let relative = index_path(relative);
+        if !self.pattern.matches_with(&relative, match_options()) {
+            return;
+        }
+        if self.indexed_files.insert(path.clone()) {
+            self.files.push(CollectionFile { path, relative });
+        }
+    }
+}
+
+/// `*` stays within one path component, as in a filesystem glob
+fn match_options() -> glob::MatchOptions {
+    glob::MatchOptions {
+        require_literal_separator: true,
+        ..glob::MatchOptions::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn relative_paths(files: &[CollectionFile]) -> Vec<&str> {
+        files.iter().map(|file| file.relative.as_str()).collect()
+    }
+
+    #[test]
+    fn test_collection_files_match_pattern() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("guides/deep")).unwrap();
+        fs::write(tmp.path().join("readme.md"), "").unwrap();
+        fs::write(tmp.path().join("notes.txt"), "").unwrap();
+        fs::write(tmp.path().join("guides/intro.md"), "").unwrap();
+        fs::write(tmp.path().join("guides/deep/more.md"), "").unwrap();
+
+        let (files, errors) = collection_files(tmp.path(), "**/*.md", false).unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(relative_paths(&files), ["guides/deep/more.md", "guides/intro.md", "readme.md"]);
+
+        let (files, _) = collection_files(tmp.path(), "guides/*.md", false).unwrap();
+        assert_eq!(relative_paths(&files), ["guides/intro.md"]);
+
+        let (files, _) = collection_files(tmp.path(), "**/*", false).unwrap();
+        assert_eq!(files.len(), 4);
+    }
+
+    #[test]
+    fn test_collection_files_normalize_pattern() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("guides")).unwrap();
+        fs::write(tmp.path().join("guides/intro.md"), "").unwrap();
+
+        let (files, _) = collection_files(tmp.path(), "./guides/../guides/*.md", false).unwrap();
+        assert_eq!(relative_paths(&files), ["guides/intro.md"]);
+
+        let err = collection_files(tmp.path(), "../**/*.md", false).unwrap_err();
+        assert!(format!("{:#}", err).contains("escapes"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_collection_files_base_with_glob_characters() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path().join("[old] notes");
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("a.md"), "").unwrap();
+
+        let (files, _) = collection_files(&base, "*.md", false).unwrap();
+        assert_eq!(relative_paths(&files), ["a.md"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collection_files_resolve_symlinks_inside_collection_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("real")).unwrap();
+        fs::write(tmp.path().join("real/doc.md"), "").unwrap();
+        std::os::unix::fs::symlink(tmp.path().join("real/doc.md"), tmp.path().join("alias.md")).unwrap();
+        std::os::unix::fs::symlink(tmp.path().join("real"), tmp.path().join("mirror")).unwrap();
+
+        let (files, _) = collection_files(tmp.path(), "**/*.md", true).unwrap();
+        assert_eq!(relative_paths(&files), ["real/doc.md"]);
+
+        let (files, _) = collection_files(tmp.path(), "**/*.md", false).unwrap();
+        assert_eq!(relative_paths(&files), ["real/doc.md"]);
+    }
+}
@@ -4,7 +4,6 @@
 //!         AnelSpec, NdjsonRecord, AnelResult, From<anyhow::Error>, constants.
 
 use qmd_rust::anel::*;
-use std::collections::HashMap;
 
 // ============================================================
 // ErrorCode
@@ -384,6 +383,13 @@ fn ndjson_record_with_complex_payload() {
     assert_eq!(parsed["payload"]["score"], 0.95);
 }
 
+#[test]
+fn ndjson_record_carries_anel_version() {
+    let record = NdjsonRecord::new("metadata", 0, serde_json::json!({}));
+    let parsed: serde_json::Value = serde_json::from_str(&record.to_ndjson()).unwrap();
+    assert_eq!(parsed["anel_version"], ANEL_VERSION);
+}
+
 // ============================================================
 // AnelResult
 // ============================================================
@@ -430,6 +436,36 @@ fn anel_result_error_to_ndjson() {
     assert!(parsed["error"].is_object());
 }
 
+#[test]
+fn anel_result_and_error_carry_anel_version() {
+    let success: serde_json::Value =
+        serde_json::from_str(&AnelResult::success(serde_json::json!({})).to_ndjson()).unwrap();
+    assert_eq!(success["anel_version"], ANEL_VERSION);
+
+    let err = AnelError::new(AnelErrorCode::NotFound, "Not Found", "missing");
+    let failure: serde_json::Value = serde_json::from_str(&AnelResult::error(err.clone()).to_ndjson()).unwrap();
+    assert_eq!(failure["anel_version"], ANEL_VERSION);
+    assert_eq!(failure["error"]["anel_version"], ANEL_VERSION);
+    let alone: serde_json::Value = serde_json::from_str(&err.to_ndjson()).unwrap();
+    assert_eq!(alone["anel_version"], ANEL_VERSION);
+}
+
+#[test]
+fn anel_error_without_version_deserializes_as_current() {
+    let err: AnelError = serde_json::from_value(serde_json::json!({
+        "error_code": "NOT_FOUND",
+        "status": 404,
+        "title": "Not Found",
+        "message": "missing",
+        "severity": "error",
+        "recovery_hints": [],
+        "trace_id": null
+    }))
+    .unwrap();
+    assert_eq!(err.anel_version, ANEL_VERSION);
+    assert!(err.metadata.is_empty());
+}
+
 // ============================================================
 // Constants / env vars
 // ============================================================
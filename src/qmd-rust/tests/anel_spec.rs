@@ -7,7 +7,7 @@ use qmd_rust::anel::*;
 
 const ALL_COMMANDS: &[&str] = &[
     "search", "vsearch", "query", "get", "multi_get", "collection",
-    "context", "embed", "update", "status", "cleanup", "agent", "mcp",
+    "context", "embed", "update", "sync", "status", "cleanup", "trash", "remove", "suggest", "verify", "reindex", "agent", "mcp",
 ];
 
 // ============================================================
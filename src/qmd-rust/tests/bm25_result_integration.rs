@@ -0,0 +1,44 @@
+//! BM25 hits carry the hash, line count and docid of the indexed document
+
+mod common;
+
+use common::{create_test_config, search_all_options};
+use qmd_rust::store::Store;
+use std::fs;
+use tempfile::tempdir;
+
+const WALRUS: &str = "# Walrus\nThe walrus naps on the ice.\nIt wakes at noon.\n";
+
+#[test]
+fn test_bm25_result_reads_indexed_document() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("walrus.md"), WALRUS).unwrap();
+    fs::write(content_dir.join("narwhal.md"), "# Narwhal\nThe narwhal has a tusk.").unwrap();
+
+    let store = Store::new(&create_test_config(&tmp.path().join("cache"), "docs", &content_dir)).unwrap();
+    store.update_index().unwrap();
+
+    let stored: String = store
+        .get_connection("docs")
+        .unwrap()
+        .query_row("SELECT hash FROM documents WHERE path = 'walrus.md'", [], |row| row.get(0))
+        .unwrap();
+
+    let results = store.bm25_search("walrus", search_all_options()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].hash, stored);
+    assert_eq!(results[0].hash, Store::calculate_hash(WALRUS));
+    assert_eq!(results[0].lines, 3);
+    assert_eq!(results[0].path, "docs/walrus.md");
+    assert_eq!(results[0].docid, "docs:walrus.md");
+
+    // Moving the file away without re-indexing leaves the hit as indexed
+    fs::rename(content_dir.join("walrus.md"), tmp.path().join("walrus-moved.md")).unwrap();
+    let results = store.bm25_search("walrus", search_all_options()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].hash, stored);
+    assert_eq!(results[0].lines, 3);
+    assert_eq!(results[0].docid, "docs:walrus.md");
+}
@@ -0,0 +1,81 @@
+//! Searches retry when another connection holds the database, and fail with
+//! a `StorageError` only once the retries are exhausted
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::anel::{AnelError, AnelErrorCode};
+use qmd_rust::store::{SearchOptions, Store};
+use rusqlite::Connection;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+fn options() -> SearchOptions {
+    SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    }
+}
+
+/// Index a walrus note as collection `docs`, returning the store and its database
+fn indexed(root: &Path) -> (Store, PathBuf) {
+    let content_dir = root.join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("walrus.md"), "# Walrus\nThe walrus naps on ice.").unwrap();
+    let config = create_test_config(&root.join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    (store, root.join("cache").join("docs").join("index.db"))
+}
+
+/// Connection holding an exclusive lock, which keeps readers out
+fn lock_exclusively(db: &Path) -> Connection {
+    let conn = Connection::open(db).unwrap();
+    conn.execute_batch("BEGIN EXCLUSIVE").unwrap();
+    conn
+}
+
+#[test]
+fn test_search_retries_until_lock_is_released() {
+    let tmp = tempdir().unwrap();
+    let (store, db) = indexed(tmp.path());
+    // Pools the collection's connection
+    assert_eq!(store.bm25_search("walrus", options()).unwrap().len(), 1);
+
+    let writer = lock_exclusively(&db);
+    let holder = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(1500));
+        writer.execute_batch("COMMIT").unwrap();
+    });
+
+    // Outlasts one busy timeout, so the query is retried
+    let start = Instant::now();
+    let results = store.bm25_search("walrus", options()).unwrap();
+    holder.join().unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(start.elapsed() >= Duration::from_millis(1000), "{:?}", start.elapsed());
+    assert!(store.pool_stats().retries() >= 1);
+    assert_eq!(store.pool_stats().reopens(), 0);
+}
+
+#[test]
+fn test_search_fails_with_storage_error_after_retries() {
+    let tmp = tempdir().unwrap();
+    let (store, db) = indexed(tmp.path());
+    assert_eq!(store.bm25_search("walrus", options()).unwrap().len(), 1);
+
+    let writer = lock_exclusively(&db);
+    let err = store.bm25_search("walrus", options()).unwrap_err();
+    writer.execute_batch("COMMIT").unwrap();
+
+    let anel = err.downcast_ref::<AnelError>().expect("a StorageError");
+    assert_eq!(anel.error_code, AnelErrorCode::StorageError);
+    assert!(anel.message.contains("'docs'"), "{}", anel.message);
+    assert_eq!(store.pool_stats().retries(), 5);
+
+    // The pooled connection works again once the lock is gone
+    assert_eq!(store.bm25_search("walrus", options()).unwrap().len(), 1);
+}
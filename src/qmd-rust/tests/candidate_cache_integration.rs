@@ -0,0 +1,179 @@
+//! Repeating a hybrid query with a longer page reuses the cached candidates
+//! and reranks only the windows the shorter page did not reach
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::config::Config;
+use qmd_rust::llm::{Rerank, RerankFuture, Router};
+use qmd_rust::store::candidates::CandidateCacheUse;
+use qmd_rust::store::deadline::{Deadline, SearchOutcome};
+use qmd_rust::store::lang::QueryLanguage;
+use qmd_rust::store::{SearchOptions, Store};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tempfile::tempdir;
+
+/// Scores documents by their text so the order differs from fusion, and
+/// counts how many it scored
+struct CountingReranker(Arc<AtomicUsize>);
+
+impl Rerank for CountingReranker {
+    fn model_name(&self) -> String {
+        "counting-reranker".to_string()
+    }
+
+    fn rerank<'a>(&'a self, _query: &'a str, docs: &'a [&'a str]) -> RerankFuture<'a> {
+        self.0.fetch_add(docs.len(), Ordering::SeqCst);
+        Box::pin(async move {
+            Ok(docs
+                .iter()
+                .map(|doc| doc.bytes().map(u32::from).sum::<u32>() % 97)
+                .map(|score| score as f32 / 97.0)
+                .collect())
+        })
+    }
+}
+
+fn setup(dir: &Path, window: Option<usize>) -> Config {
+    let content_dir = dir.join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    for i in 0..30 {
+        let body = format!("# Note {i}\n{}Other facts number {i}.\n", "The walrus naps. ".repeat(i % 7 + 1));
+        fs::write(content_dir.join(format!("note-{i:02}.md")), body).unwrap();
+    }
+    let mut config = create_test_config(&dir.join("cache"), "docs", &content_dir);
+    config.search.rerank_window = window;
+    Store::new(&config).unwrap().update_index().unwrap();
+    config
+}
+
+fn counting_router(config: &Config, calls: &Arc<AtomicUsize>) -> Router {
+    let mut router = Router::new(config).unwrap();
+    router.set_reranker(Arc::new(CountingReranker(calls.clone())));
+    router
+}
+
+async fn query(store: &Store, router: &Router, limit: usize) -> SearchOutcome {
+    let options = SearchOptions {
+        limit,
+        search_all: true,
+        ..Default::default()
+    };
+    store
+        .hybrid_search_within("walrus", options, router, QueryLanguage::English, &Deadline::unlimited())
+        .await
+        .unwrap()
+}
+
+fn docids(outcome: &SearchOutcome) -> Vec<String> {
+    outcome.results.iter().map(|r| r.docid.clone()).collect()
+}
+
+#[tokio::test]
+async fn test_longer_page_reranks_only_new_windows() {
+    let tmp = tempdir().unwrap();
+    let config = setup(tmp.path(), Some(10));
+    let calls = Arc::new(AtomicUsize::new(0));
+    let router = counting_router(&config, &calls);
+    let store = Store::new(&config).unwrap();
+
+    let first = query(&store, &router, 10).await;
+    assert_eq!(first.results.len(), 10);
+    assert_eq!(calls.load(Ordering::SeqCst), 10);
+    let cache = first.cache.as_ref().unwrap();
+    assert!(!cache.hit);
+    assert_eq!((cache.reused_scores, cache.reranked), (0, 10));
+
+    let second = query(&store, &router, 20).await;
+    assert_eq!(second.results.len(), 20);
+    // Only the second window went to the reranker
+    assert_eq!(calls.load(Ordering::SeqCst), 20);
+    assert_eq!(
+        second.cache,
+        Some(CandidateCacheUse { hit: true, reused_scores: 10, reranked: 10, hits: 1, misses: 1 })
+    );
+    assert_eq!(docids(&second)[..10], docids(&first)[..]);
+
+    // A cold run orders the longer page the same way
+    let cold_calls = Arc::new(AtomicUsize::new(0));
+    let cold = query(&Store::new(&config).unwrap(), &counting_router(&config, &cold_calls), 20).await;
+    assert_eq!(docids(&cold), docids(&second));
+    let scores = |outcome: &SearchOutcome| outcome.results.iter().map(|r| r.score).collect::<Vec<_>>();
+    assert_eq!(scores(&cold), scores(&second));
+    assert_eq!(cold_calls.load(Ordering::SeqCst), 20);
+
+    // Asking again reranks nothing
+    query(&store, &router, 20).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 20);
+}
+
+#[tokio::test]
+async fn test_single_window_reranks_all_candidates_once() {
+    let tmp = tempdir().unwrap();
+    let config = setup(tmp.path(), None);
+    let calls = Arc::new(AtomicUsize::new(0));
+    let router = counting_router(&config, &calls);
+    let store = Store::new(&config).unwrap();
+
+    let first = query(&store, &router, 5).await;
+    let candidates = calls.load(Ordering::SeqCst);
+    assert!(candidates > 5, "{}", candidates);
+
+    let second = query(&store, &router, 15).await;
+    assert_eq!(calls.load(Ordering::SeqCst), candidates);
+    assert_eq!(second.cache.as_ref().unwrap().reused_scores, candidates);
+    assert_eq!(docids(&second)[..5], docids(&first)[..]);
+}
+
+#[tokio::test]
+async fn test_index_change_invalidates_candidates() {
+    let tmp = tempdir().unwrap();
+    let config = setup(tmp.path(), Some(10));
+    let calls = Arc::new(AtomicUsize::new(0));
+    let router = counting_router(&config, &calls);
+    let store = Store::new(&config).unwrap();
+    query(&store, &router, 10).await;
+
+    fs::write(tmp.path().join("content").join("extra.md"), "# Extra\nThe walrus naps. The walrus naps.").unwrap();
+    store.update_index().unwrap();
+
+    let after = query(&store, &router, 10).await;
+    assert!(!after.cache.as_ref().unwrap().hit);
+    assert_eq!(calls.load(Ordering::SeqCst), 20);
+}
+
+#[tokio::test]
+async fn test_windowed_ranking_keeps_fused_order_between_windows() {
+    let tmp = tempdir().unwrap();
+    let windowed_config = setup(tmp.path(), Some(5));
+    let mut whole_config = windowed_config.clone();
+    whole_config.search.rerank_window = None;
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    // No reranker leaves the candidates in fused order
+    let fused = query(&Store::new(&whole_config).unwrap(), &Router::new(&whole_config).unwrap(), 100).await;
+    let whole = query(&Store::new(&whole_config).unwrap(), &counting_router(&whole_config, &calls), 100).await;
+    let windowed = query(&Store::new(&windowed_config).unwrap(), &counting_router(&windowed_config, &calls), 100).await;
+    assert!(fused.results.len() > 5, "{}", fused.results.len());
+    assert_eq!(whole.results.len(), fused.results.len());
+
+    // One window orders every candidate by its rerank score
+    let scores: HashMap<String, f32> = whole.results.iter().map(|r| (r.docid.clone(), r.score)).collect();
+    assert!(whole.results.windows(2).all(|pair| pair[0].score >= pair[1].score));
+
+    // Windows of 5 order the same scores within each run of 5 fused candidates
+    let expected: Vec<String> = docids(&fused)
+        .chunks(5)
+        .flat_map(|chunk| {
+            let mut chunk = chunk.to_vec();
+            chunk.sort_by(|a, b| scores[b].total_cmp(&scores[a]));
+            chunk
+        })
+        .collect();
+    assert_eq!(docids(&windowed), expected);
+    assert_ne!(docids(&windowed), docids(&whole));
+}
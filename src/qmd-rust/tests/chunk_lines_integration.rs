@@ -0,0 +1,95 @@
+//! Chunks record the line they start on, so results can point at `path:line`
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::cli::embed::embed_hashes_async;
+use qmd_rust::llm::{Embed, EmbedFuture, Router};
+use qmd_rust::store::bundle::{bundle_content, ContentMode};
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+struct MockEmbedder;
+
+impl Embed for MockEmbedder {
+    fn model_name(&self) -> String {
+        "line-mock".to_string()
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a> {
+        Box::pin(async move { Ok(texts.iter().map(|_| vec![0.1; 8]).collect()) })
+    }
+}
+
+/// Paragraphs of varying length separated by blank lines, with the query term near the end
+fn fixture() -> String {
+    let mut doc = String::from("# Guide\n\n");
+    for n in 1..=120 {
+        let sentences = "Each paragraph adds a few more words. ".repeat(n % 4 + 1);
+        doc.push_str(&format!("Paragraph {}. {}\n\n", n, sentences.trim_end()));
+    }
+    doc.push_str("The borrow checker enforces ownership rules.\n");
+    doc
+}
+
+#[test]
+fn test_embedded_chunk_lines_match_where_their_text_starts() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    let doc = fixture();
+    fs::write(content_dir.join("guide.md"), &doc).unwrap();
+    let config = create_test_config(&tmp.path().join("cache"), "notes", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let conn = store.get_connection("notes").unwrap();
+    let hash: String = conn.query_row("SELECT hash FROM documents", [], |row| row.get(0)).unwrap();
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(MockEmbedder));
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(embed_hashes_async(&store, "notes", &router, std::slice::from_ref(&hash))).unwrap();
+
+    let mut stmt = conn.prepare("SELECT pos, line FROM content_vectors WHERE hash = ? ORDER BY seq").unwrap();
+    let rows: Vec<(usize, usize)> = stmt
+        .query_map([&hash], |row| Ok((row.get::<_, i64>(0)? as usize, row.get::<_, i64>(1)? as usize)))
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+    assert!(rows.len() > 2, "fixture should span several chunks");
+
+    let lines: Vec<&str> = doc.lines().collect();
+    for (pos, line) in rows {
+        assert_eq!(line, doc[..pos].matches('\n').count() + 1, "chunk at {}", pos);
+        let first = doc[pos..].lines().next().unwrap();
+        assert!(lines[line - 1].ends_with(first), "chunk at {} does not start on line {}", pos, line);
+    }
+}
+
+#[test]
+fn test_bundled_chunks_carry_their_start_line() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    let doc = fixture();
+    fs::write(content_dir.join("guide.md"), &doc).unwrap();
+    let config = create_test_config(&tmp.path().join("cache"), "notes", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let options = SearchOptions {
+        limit: 5,
+        ..Default::default()
+    };
+    let results = store.bm25_search("borrow", options).unwrap();
+    let expected_line = doc.lines().position(|l| l.contains("borrow")).unwrap() + 1;
+    assert_eq!(results[0].match_line, Some(expected_line));
+
+    let bundled = bundle_content(&store, &results, "borrow", ContentMode::Chunks, usize::MAX).unwrap();
+    let piece = &bundled[0].pieces[0];
+    assert!(piece.text.contains("borrow"));
+    assert_eq!(piece.line, doc[..piece.pos].matches('\n').count() + 1);
+    assert!(piece.line <= expected_line);
+}
@@ -15,6 +15,28 @@ fn run_qmd_cmd(args: &[&str], config_path: &std::path::Path) -> Output {
     cmd.output().unwrap()
 }
 
+/// Helper to start a long-running qmd command and stop it after a short while
+///
+/// Returns the exit status if it exited on its own, None if it was still running.
+fn run_qmd_server(args: &[&str], config_path: &std::path::Path) -> Option<std::process::ExitStatus> {
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("qmd-rust"))
+        .env("QMD_CONFIG_PATH", config_path)
+        .args(args)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    for _ in 0..30 {
+        if let Some(status) = child.try_wait().unwrap() {
+            return Some(status);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+    None
+}
+
 /// Helper to create a test config file and content directory
 fn setup_test_env() -> (tempfile::TempDir, std::path::PathBuf) {
     let tmp = tempdir().unwrap();
@@ -449,6 +471,59 @@ fn test_collection_list() {
     assert!(output.status.code().is_some());
 }
 
+#[test]
+fn test_collection_list_with_stats_joins_config_and_counts() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("rust.md"), "# Rust\nOwnership and borrowing").unwrap();
+    fs::write(content_dir.join("go.md"), "# Go\nGoroutines and channels").unwrap();
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: docs\n    path: {}\n    pattern: \"**/*.md\"\n    description: Language notes\n\
+         models:\n  embed:\n    local: missing-model\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let output = Command::cargo_bin("qmd-rust").unwrap().env("HOME", tmp.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    qmd(&["update"]);
+
+    // Chunks count once they are embedded; the missing model falls back to local vectors
+    let listing: serde_json::Value =
+        serde_json::from_str(&qmd(&["collection", "--format", "json", "list", "--with-stats"])).unwrap();
+    assert_eq!(listing["collections"][0]["stats"]["documents"], 2);
+    assert_eq!(listing["collections"][0]["stats"]["chunks"], 0);
+    qmd(&["embed"]);
+
+    let json = qmd(&["collection", "--format", "json", "list", "--with-stats"]);
+    let listing: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let docs = &listing["collections"][0];
+    assert_eq!(docs["name"], "docs");
+    assert_eq!(docs["path"], content_dir.display().to_string());
+    assert_eq!(docs["pattern"], "**/*.md");
+    assert_eq!(docs["description"], "Language notes");
+    assert_eq!(docs["stats"]["documents"], 2, "{}", json);
+    assert_eq!(docs["stats"]["chunks"], 2, "{}", json);
+    assert!(docs["stats"]["index_bytes"].as_u64().unwrap() > 0, "{}", json);
+
+    // Without the flag the listing stays config-only
+    let plain: serde_json::Value = serde_json::from_str(&qmd(&["collection", "--format", "json", "list"])).unwrap();
+    assert_eq!(plain["collections"][0]["name"], "docs");
+    assert!(plain["collections"][0].get("stats").is_none());
+
+    let table = qmd(&["collection", "list", "--with-stats"]);
+    assert!(table.contains("Documents"), "{}", table);
+    assert!(table.contains("Language notes"), "{}", table);
+}
+
 #[test]
 fn test_collection_add() {
     let (tmp, config_path) = setup_test_env();
@@ -480,6 +555,42 @@ fn test_collection_remove() {
     assert!(output.status.code().is_some());
 }
 
+#[test]
+fn test_collection_remove_purge_deletes_index() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("notes");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("a.md"), "# A\nSome indexed text").unwrap();
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let cache = tmp.path().join("cache");
+    fs::write(config_dir.join("index.yaml"), format!("cache_path: {}\n", cache.display())).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("qmd-rust").unwrap();
+        cmd.env("HOME", tmp.path()).args(args);
+        cmd.output().unwrap()
+    };
+    let add = qmd(&["collection", "add", "-n", "notes", content_dir.to_str().unwrap()]);
+    assert!(add.status.success(), "{}", String::from_utf8_lossy(&add.stderr));
+    assert!(qmd(&["update"]).status.success());
+    let db = cache.join("notes").join("index.db");
+    assert!(db.exists());
+
+    // Without a terminal to confirm on, --purge needs --yes
+    let refused = qmd(&["collection", "remove", "notes", "--purge"]);
+    assert!(!refused.status.success());
+    assert!(db.exists());
+
+    let purged = qmd(&["collection", "remove", "notes", "--purge", "--yes"]);
+    assert!(purged.status.success(), "{}", String::from_utf8_lossy(&purged.stderr));
+    assert!(String::from_utf8_lossy(&purged.stdout).contains("Freed:"));
+    assert!(!db.exists());
+    assert!(!cache.join("notes").exists());
+    let config = fs::read_to_string(config_dir.join("index.yaml")).unwrap();
+    assert!(!config.contains("notes"), "{}", config);
+}
+
 #[test]
 fn test_collection_rename() {
     let (_tmp, config_path) = setup_test_env();
@@ -627,9 +738,9 @@ fn test_mcp_stdio_transport() {
 fn test_mcp_sse_transport() {
     let (_tmp, config_path) = setup_test_env();
     // SSE transport with short timeout
-    let output = run_qmd_cmd(&["mcp", "--transport", "sse", "--port", "18080"], &config_path);
+    let status = run_qmd_server(&["mcp", "--transport", "sse", "--port", "18080"], &config_path);
     // Server should at least attempt to start
-    assert!(output.status.code().is_some());
+    assert!(status.is_none_or(|s| s.code().is_some()));
 }
 
 // ============================================================================
@@ -684,16 +795,16 @@ fn test_plugin_info() {
 #[test]
 fn test_server_start() {
     let (_tmp, config_path) = setup_test_env();
-    let output = run_qmd_cmd(&["server", "--port", "18081"], &config_path);
+    let status = run_qmd_server(&["server", "--port", "18081"], &config_path);
     // Server may fail to bind but should not crash
-    assert!(output.status.code().is_some());
+    assert!(status.is_none_or(|s| s.code().is_some()));
 }
 
 #[test]
 fn test_server_with_workers() {
     let (_tmp, config_path) = setup_test_env();
-    let output = run_qmd_cmd(&["server", "--port", "18082", "--workers", "2"], &config_path);
-    assert!(output.status.code().is_some());
+    let status = run_qmd_server(&["server", "--port", "18082", "--workers", "2"], &config_path);
+    assert!(status.is_none_or(|s| s.code().is_some()));
 }
 
 // ============================================================================
@@ -776,3 +887,248 @@ fn test_get_help() {
         .success()
         .stdout(predicate::str::contains("Get"));
 }
+
+// ============================================================================
+// Profiling
+// ============================================================================
+
+#[test]
+fn test_search_profile_writes_chrome_trace() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("rust.md"), "# Rust\nOwnership and borrowing").unwrap();
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: docs\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("qmd-rust").unwrap();
+        cmd.env("HOME", tmp.path()).args(args);
+        cmd.output().unwrap()
+    };
+    qmd(&["update"]).assert().success();
+
+    let profile = tmp.path().join("profile.json");
+    qmd(&["search", "ownership", "--profile", profile.to_str().unwrap()])
+        .assert()
+        .success();
+
+    let trace: Vec<serde_json::Value> = serde_json::from_str(&fs::read_to_string(&profile).unwrap()).unwrap();
+    let spans: Vec<&str> = trace.iter().filter_map(|e| e["name"].as_str()).collect();
+    assert!(spans.contains(&"bm25_search"), "{:?}", spans);
+    assert!(spans.contains(&"bm25_collection"), "{:?}", spans);
+}
+
+#[test]
+fn test_search_json_schema_embeds_output_schema() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("rust.md"), "# Rust\nOwnership and borrowing").unwrap();
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: docs\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("qmd-rust").unwrap();
+        cmd.env("HOME", tmp.path()).args(args);
+        let output = cmd.output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        output
+    };
+    qmd(&["update"]);
+    let expected = qmd_rust::anel::AnelSpec::search().output_schema;
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&qmd(&["search", "ownership", "--format", "json", "--json-schema"]).stdout).unwrap();
+    assert_eq!(json["$schema"], expected);
+    assert_eq!(json["total"], 1);
+
+    let ndjson = qmd(&["search", "ownership", "--format", "ndjson", "--json-schema"]).stdout;
+    let metadata: serde_json::Value =
+        serde_json::from_str(String::from_utf8_lossy(&ndjson).lines().next().unwrap()).unwrap();
+    assert_eq!(metadata["payload"]["$schema"], expected);
+
+    // Opt-in only
+    let plain: serde_json::Value =
+        serde_json::from_slice(&qmd(&["search", "ownership", "--format", "json"]).stdout).unwrap();
+    assert!(plain.get("$schema").is_none());
+}
+
+#[test]
+fn test_search_ndjson_records_carry_anel_version() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("rust.md"), "# Rust\nOwnership and borrowing").unwrap();
+    fs::write(content_dir.join("go.md"), "# Go\nOwnership of goroutines").unwrap();
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: docs\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let output = Command::cargo_bin("qmd-rust").unwrap().env("HOME", tmp.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    qmd(&["update"]);
+
+    let ndjson = qmd(&["search", "ownership", "--format", "ndjson"]);
+    let records: Vec<serde_json::Value> = ndjson.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(records.len(), 3, "{}", ndjson);
+    for record in &records {
+        assert_eq!(record["anel_version"], qmd_rust::anel::ANEL_VERSION, "{}", record);
+    }
+}
+
+#[test]
+fn test_search_ndjson_emits_metadata_then_ordered_results() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("rust.md"), "# Rust\nOwnership ownership and borrowing").unwrap();
+    fs::write(content_dir.join("go.md"), "# Go\nOwnership of goroutines").unwrap();
+    fs::write(content_dir.join("zig.md"), "# Zig\nAllocators, and ownership by convention only").unwrap();
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: docs\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let output = Command::cargo_bin("qmd-rust").unwrap().env("HOME", tmp.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    qmd(&["update"]);
+
+    for format in ["ndjson", "jsonl"] {
+        let ndjson = qmd(&["search", "ownership", "--format", format]);
+        let records: Vec<serde_json::Value> = ndjson.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(records.len(), 4, "{}", ndjson);
+
+        // The metadata record leads, so a streaming reader learns the total first
+        assert_eq!(records[0]["type"], "metadata");
+        assert_eq!(records[0]["seq"], 0);
+        assert_eq!(records[0]["payload"]["total"], 3);
+        assert_eq!(records[0]["payload"]["query"], "ownership");
+
+        let seqs: Vec<u64> = records.iter().map(|r| r["seq"].as_u64().unwrap()).collect();
+        assert_eq!(seqs, [0, 1, 2, 3]);
+        for record in &records[1..] {
+            assert_eq!(record["type"], "result", "{}", record);
+            assert!(record["payload"]["path"].as_str().unwrap().starts_with("docs/"), "{}", record);
+        }
+    }
+}
+
+#[test]
+fn test_search_json_group_by_collection_adds_groups() {
+    let tmp = tempdir().unwrap();
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let mut config = format!("cache_path: {}\ncollections:\n", tmp.path().join("cache").display());
+    for name in ["docs", "notes"] {
+        let dir = tmp.path().join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.md"), format!("# {}\nOwnership in {}", name, name)).unwrap();
+        fs::write(dir.join("b.md"), "# More\nOwnership and borrowing rules").unwrap();
+        config.push_str(&format!("  - name: {}\n    path: {}\n    pattern: \"**/*.md\"\n", name, dir.display()));
+    }
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let output = Command::cargo_bin("qmd-rust").unwrap().env("HOME", tmp.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    qmd(&["update"]);
+
+    let json = qmd(&["search", "ownership", "--all", "--limit", "3", "--group-by", "collection", "--format", "json"]);
+    let output: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let results = output["results"].as_array().unwrap();
+    // Grouping applies after the limit
+    assert_eq!(results.len(), 3, "{}", json);
+    let groups = output["groups"].as_array().unwrap();
+    let mut indices: Vec<u64> = Vec::new();
+    for group in groups {
+        for index in group["indices"].as_array().unwrap() {
+            let index = index.as_u64().unwrap();
+            assert_eq!(results[index as usize]["collection"], group["name"], "{}", json);
+            indices.push(index);
+        }
+    }
+    indices.sort();
+    assert_eq!(indices, [0, 1, 2]);
+
+    let table = qmd(&["search", "ownership", "--all", "--group-by", "collection"]);
+    assert!(table.contains("\ndocs (2)\n") && table.contains("\nnotes (2)\n"), "{}", table);
+}
+
+#[test]
+fn test_get_json_if_hash_reports_unchanged() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("notes.md"), "# Notes\nFirst draft").unwrap();
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: docs\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("qmd-rust").unwrap();
+        cmd.env("HOME", tmp.path()).args(args);
+        let output = cmd.output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        output
+    };
+    let get_json = |extra: &[&str]| -> serde_json::Value {
+        let mut args = vec!["get", "--format", "json", "docs/notes.md"];
+        args.extend_from_slice(extra);
+        serde_json::from_slice(&qmd(&args).stdout).unwrap()
+    };
+    qmd(&["update"]);
+
+    let first = get_json(&[]);
+    assert_eq!(first["lines"][1], "First draft");
+    let hash = first["hash"].as_str().unwrap().to_string();
+
+    let again = get_json(&["--if-hash", &hash]);
+    assert_eq!(again, serde_json::json!({ "unchanged": true, "hash": hash }));
+
+    fs::write(content_dir.join("notes.md"), "# Notes\nSecond draft").unwrap();
+    qmd(&["update"]);
+
+    let changed = get_json(&["--if-hash", &hash]);
+    assert_eq!(changed["lines"][1], "Second draft");
+    assert_ne!(changed["hash"], first["hash"]);
+}
@@ -1,5 +1,11 @@
+mod common;
+
 use assert_cmd::Command;
+use clap::Parser;
 use predicates::prelude::*;
+use qmd_rust::cli::{Cli, Commands};
+use qmd_rust::config::BM25Backend;
+use qmd_rust::store::{SearchOptions, Store};
 
 #[test]
 fn test_cli_help() {
@@ -63,3 +69,170 @@ fn test_cli_update_runs() {
         "Update command should exit cleanly"
     );
 }
+
+#[test]
+#[cfg(not(feature = "lancedb"))]
+fn test_cli_fts_backend_without_feature_fails_at_parse() {
+    let mut cmd = Command::cargo_bin("qmd-rust").unwrap();
+    cmd.args(["search", "--fts-backend", "lancedb", "test"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Build with --features lancedb"));
+}
+
+#[test]
+fn test_cli_unknown_backend_lists_valid_options() {
+    let mut cmd = Command::cargo_bin("qmd-rust").unwrap();
+    cmd.args(["vsearch", "--vector-backend", "faiss", "test"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("valid options: qmd_builtin, lancedb, qdrant"));
+}
+
+#[test]
+#[cfg(not(feature = "lancedb"))]
+fn test_cli_backend_override_selects_search_path() {
+    let tmp = tempfile::tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    std::fs::create_dir_all(&content_dir).unwrap();
+    std::fs::write(content_dir.join("doc.md"), "override marker text").unwrap();
+
+    // The config file asks for a backend this build does not have
+    let mut config = common::create_test_config(tmp.path(), "docs", &content_dir);
+    config.bm25.backend = BM25Backend::LanceDb;
+    Store::new(&config).unwrap().update_index().unwrap();
+    let err = Store::new(&config)
+        .unwrap()
+        .bm25_search("marker", search_options())
+        .unwrap_err();
+    assert!(err.to_string().contains("LanceDB backend not enabled"));
+
+    // --fts-backend sqlite_fts5 routes the same search through FTS5
+    let cli = Cli::try_parse_from(["qmd", "search", "--fts-backend", "sqlite_fts5", "marker"]).unwrap();
+    let Commands::Search(args) = cli.command else {
+        panic!("expected search command");
+    };
+    let overridden = args.format.apply_backend_overrides(&config);
+    assert!(matches!(overridden.bm25.backend, BM25Backend::SqliteFts5));
+    assert!(matches!(config.bm25.backend, BM25Backend::LanceDb), "loaded config is not mutated");
+
+    let results = Store::new(&overridden)
+        .unwrap()
+        .bm25_search("marker", search_options())
+        .unwrap();
+    assert_eq!(results.len(), 1);
+}
+
+fn search_options() -> SearchOptions {
+    SearchOptions {
+        limit: 10,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_cli_include_content_modes_parse() {
+    use qmd_rust::store::bundle::ContentMode;
+
+    let parse = |args: &[&str]| {
+        let cli = Cli::try_parse_from(args).unwrap();
+        let Commands::Search(args) = cli.command else {
+            panic!("expected search command");
+        };
+        args.format
+    };
+
+    assert_eq!(parse(&["qmd", "search", "q"]).include_content, None);
+    assert_eq!(parse(&["qmd", "search", "--include-content", "q"]).include_content, Some(ContentMode::Full));
+    let opts = parse(&["qmd", "search", "--include-content=chunks", "--content-budget", "100", "q"]);
+    assert_eq!(opts.include_content, Some(ContentMode::Chunks));
+    assert_eq!(opts.content_budget, 100);
+
+    assert!(Cli::try_parse_from(["qmd", "search", "--include-content=all", "q"]).is_err());
+}
+
+#[test]
+fn test_cli_query_rerank_model_override() {
+    let cli = Cli::try_parse_from(["qmd", "query", "--rerank-model", "local:mock-reranker", "q"]).unwrap();
+    let Commands::Query(args) = cli.command else {
+        panic!("expected query command");
+    };
+    assert_eq!(args.rerank_model.as_deref(), Some("local:mock-reranker"));
+
+    let mut llm = qmd_rust::llm::Router::new(&qmd_rust::config::Config::default()).unwrap();
+    llm.override_reranker(args.rerank_model.as_deref().unwrap()).unwrap();
+    assert_eq!(llm.reranker_model().as_deref(), Some("mock-reranker"));
+
+    // Unset falls back to whatever config provides
+    let cli = Cli::try_parse_from(["qmd", "query", "q"]).unwrap();
+    let Commands::Query(args) = cli.command else {
+        panic!("expected query command");
+    };
+    assert_eq!(args.rerank_model, None);
+}
+
+#[test]
+fn test_cli_collection_add_stopwords_parse() {
+    use qmd_rust::cli::CollectionCommands;
+    use qmd_rust::config::Stopwords;
+
+    let stopwords = |args: &[&str]| {
+        let cli = Cli::try_parse_from(args).unwrap();
+        let Commands::Collection(cmd) = cli.command else {
+            panic!("expected collection command");
+        };
+        let CollectionCommands::Add(add) = cmd.command else {
+            panic!("expected collection add");
+        };
+        add.stopwords
+    };
+
+    assert_eq!(stopwords(&["qmd", "collection", "add", "."]), None);
+    assert_eq!(
+        stopwords(&["qmd", "collection", "add", ".", "--stopwords", "english"]),
+        Some(Stopwords::Builtin("english".to_string()))
+    );
+    assert_eq!(
+        stopwords(&["qmd", "collection", "add", ".", "--stopwords", "foo, bar"]),
+        Some(Stopwords::Words(vec!["foo".to_string(), "bar".to_string()]))
+    );
+    assert!(Cli::try_parse_from(["qmd", "collection", "add", ".", "--stopwords", ","]).is_err());
+}
+
+#[test]
+fn test_cli_preload_flag_parse() {
+    let cli = Cli::try_parse_from(["qmd", "server", "--preload"]).unwrap();
+    let Commands::Server(cmd) = cli.command else {
+        panic!("expected server command");
+    };
+    assert!(cmd.preload);
+
+    let cli = Cli::try_parse_from(["qmd", "mcp", "--transport", "sse"]).unwrap();
+    let Commands::Mcp(cmd) = cli.command else {
+        panic!("expected mcp command");
+    };
+    assert!(!cmd.preload);
+}
+
+#[test]
+fn test_cli_status_report_parse() {
+    let cli = Cli::try_parse_from(["qmd", "status", "--report", "-c", "docs", "--format", "json"]).unwrap();
+    let Commands::Status(cmd) = cli.command else {
+        panic!("expected status command");
+    };
+    assert!(cmd.report);
+    assert_eq!(cmd.collection.as_deref(), Some("docs"));
+    assert_eq!(cmd.format, "json");
+}
+
+#[test]
+fn test_cli_search_boost_parse() {
+    let cli = Cli::try_parse_from(["qmd", "search", "deploy", "--boost", "docs/official/:2", "--boost", "docs/community/:0.5"]).unwrap();
+    let Commands::Search(cmd) = cli.command else {
+        panic!("expected search command");
+    };
+    let boosts: Vec<(&str, f32)> = cmd.boost.iter().map(|b| (b.prefix.as_str(), b.weight)).collect();
+    assert_eq!(boosts, vec![("docs/official/", 2.0), ("docs/community/", 0.5)]);
+
+    assert!(Cli::try_parse_from(["qmd", "search", "deploy", "--boost", "docs/official/"]).is_err());
+}
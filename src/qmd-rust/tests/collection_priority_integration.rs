@@ -0,0 +1,147 @@
+//! `--all` searches default-searchable collections in priority order, concurrently
+
+mod common;
+
+use assert_cmd::Command;
+use common::create_multi_collection_config;
+use qmd_rust::config::Config;
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn all(really_all: bool) -> SearchOptions {
+    SearchOptions {
+        search_all: true,
+        really_all,
+        ..Default::default()
+    }
+}
+
+/// One directory per collection, each with documents about ownership
+fn write_collections(root: &Path, names: &[&str]) -> Vec<PathBuf> {
+    names
+        .iter()
+        .map(|name| {
+            let dir = root.join(name);
+            fs::create_dir_all(&dir).unwrap();
+            for i in 0..3 {
+                let body = "ownership ".repeat(i + 1);
+                fs::write(dir.join(format!("{}-{}.md", name, i)), format!("# {} {}\n{}", name, i, body)).unwrap();
+            }
+            dir
+        })
+        .collect()
+}
+
+fn config_for(root: &Path, names: &[&str]) -> Config {
+    let dirs = write_collections(root, names);
+    let collections: Vec<(&str, &Path)> = names.iter().copied().zip(dirs.iter().map(PathBuf::as_path)).collect();
+    create_multi_collection_config(&root.join("cache"), &collections)
+}
+
+fn collections_of(results: &[qmd_rust::store::SearchResult]) -> Vec<&str> {
+    let mut seen: Vec<&str> = Vec::new();
+    for result in results {
+        if !seen.contains(&result.collection.as_str()) {
+            seen.push(&result.collection);
+        }
+    }
+    seen
+}
+
+#[test]
+fn test_all_skips_collections_not_searchable_by_default() {
+    let tmp = tempdir().unwrap();
+    let mut config = config_for(tmp.path(), &["notes", "docs", "archive"]);
+    config.collections[0].priority = 2;
+    config.collections[2].searchable_by_default = false;
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    // Lower priority searches first; ties keep config order
+    assert_eq!(store.search_collections(&all(false)).unwrap(), ["docs", "notes"]);
+    assert_eq!(store.search_collections(&all(true)).unwrap(), ["docs", "archive", "notes"]);
+
+    let results = store.bm25_search("ownership", all(false)).unwrap();
+    assert_eq!(collections_of(&results), ["docs", "notes"]);
+
+    let results = store.bm25_search("ownership", all(true)).unwrap();
+    assert_eq!(collections_of(&results), ["docs", "archive", "notes"]);
+
+    // Naming the collection still searches it
+    let named = SearchOptions {
+        collection: Some("archive".to_string()),
+        search_all: false,
+        ..all(false)
+    };
+    let results = store.bm25_search("ownership", named).unwrap();
+    assert_eq!(collections_of(&results), ["archive"]);
+}
+
+#[test]
+fn test_concurrent_search_matches_serial_search() {
+    let tmp = tempdir().unwrap();
+    let names = ["a", "b", "c", "d", "e", "f", "g"];
+    let mut config = config_for(tmp.path(), &names);
+    for (i, collection) in config.collections.iter_mut().enumerate() {
+        collection.priority = (names.len() - i) as i32 % 3;
+    }
+    Store::new(&config).unwrap().update_index().unwrap();
+
+    let search = |parallelism: usize| {
+        let mut config = config.clone();
+        config.search.parallelism = parallelism;
+        let store = Store::new(&config).unwrap();
+        store
+            .bm25_search("ownership", all(true))
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.docid, r.collection, r.score))
+            .collect::<Vec<_>>()
+    };
+
+    let serial = search(1);
+    assert_eq!(serial.len(), names.len() * 3);
+    for parallelism in [2, 4, 16] {
+        assert_eq!(search(parallelism), serial, "parallelism {}", parallelism);
+    }
+}
+
+#[test]
+fn test_cli_all_and_really_all() {
+    let tmp = tempdir().unwrap();
+    let dirs = write_collections(tmp.path(), &["notes", "archive"]);
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n  \
+         - name: archive\n    path: {}\n    pattern: \"**/*.md\"\n    priority: -1\n    searchable_by_default: false\n",
+        tmp.path().join("cache").display(),
+        dirs[0].display(),
+        dirs[1].display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let output = Command::cargo_bin("qmd-rust").unwrap().env("HOME", tmp.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    qmd(&["update"]);
+
+    let collections = |args: &[&str]| {
+        let json: serde_json::Value = serde_json::from_str(&qmd(args)).unwrap();
+        let mut names: Vec<String> = json["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["collection"].as_str().unwrap().to_string())
+            .collect();
+        names.dedup();
+        names
+    };
+
+    assert_eq!(collections(&["search", "ownership", "--all", "--format", "json"]), ["notes"]);
+    assert_eq!(collections(&["search", "ownership", "--really-all", "--format", "json"]), ["archive", "notes"]);
+}
@@ -1,7 +1,20 @@
+// Each test binary uses only some of these helpers
+#![allow(dead_code)]
+
 use qmd_rust::config::{Config, CollectionConfig, BM25BackendConfig, VectorBackendConfig, ModelsConfig};
+use qmd_rust::store::{fts, SearchOptions};
 use rusqlite::Connection;
 use std::path::Path;
 
+/// Search options over every default collection, ten results at a time
+pub fn search_all_options() -> SearchOptions {
+    SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    }
+}
+
 /// Create a Config pointing at a temp directory with one collection.
 /// The collection's `path` is set to `content_dir` so update_index can scan it.
 pub fn create_test_config(cache_dir: &Path, collection_name: &str, content_dir: &Path) -> Config {
@@ -13,9 +26,11 @@ pub fn create_test_config(cache_dir: &Path, collection_name: &str, content_dir:
             path: content_dir.to_path_buf(),
             pattern: Some("**/*".to_string()),
             description: None,
+            ..Default::default()
         }],
         models: ModelsConfig::default(),
         cache_path: cache_dir.to_path_buf(),
+        ..Config::default()
     }
 }
 
@@ -34,10 +49,12 @@ pub fn create_multi_collection_config(
                 path: path.to_path_buf(),
                 pattern: Some("**/*".to_string()),
                 description: None,
+                ..Default::default()
             })
             .collect(),
         models: ModelsConfig::default(),
         cache_path: cache_dir.to_path_buf(),
+        ..Config::default()
     }
 }
 
@@ -98,6 +115,9 @@ fn init_schema(conn: &Connection) {
             created_at TEXT NOT NULL,
             modified_at TEXT NOT NULL,
             active INTEGER NOT NULL DEFAULT 1,
+            bytes INTEGER,
+            words INTEGER,
+            tokens INTEGER,
             FOREIGN KEY (hash) REFERENCES content(hash) ON DELETE CASCADE,
             UNIQUE(collection, path)
         );
@@ -114,45 +134,30 @@ fn init_schema(conn: &Connection) {
     )
     .unwrap();
 
+    // View the external-content FTS table reads bodies from
     conn.execute_batch(
         r#"
-        CREATE VIRTUAL TABLE IF NOT EXISTS documents_fts USING fts5(
-            filepath, title, body,
-            tokenize='porter unicode61'
-        );
+        CREATE VIEW IF NOT EXISTS documents_fts_view AS
+        SELECT
+            d.id AS id,
+            d.collection || '/' || d.path AS filepath,
+            d.title AS title,
+            c.doc AS body
+        FROM documents d
+        JOIN content c ON c.hash = d.hash
+        WHERE d.active = 1;
         "#,
     )
     .unwrap();
 
-    // FTS triggers - now references content table via documents.hash
     conn.execute_batch(
         r#"
-        CREATE TRIGGER IF NOT EXISTS documents_ai AFTER INSERT ON documents
-        WHEN new.active = 1
-        BEGIN
-            INSERT INTO documents_fts(rowid, filepath, title, body)
-            SELECT
-                new.id,
-                new.collection || '/' || new.path,
-                new.title,
-                (SELECT doc FROM content WHERE hash = new.hash)
-            WHERE new.active = 1;
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS documents_ad AFTER DELETE ON documents BEGIN
-            DELETE FROM documents_fts WHERE rowid = old.id;
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS documents_au AFTER UPDATE ON documents BEGIN
-            DELETE FROM documents_fts WHERE rowid = old.id AND new.active = 0;
-            INSERT OR REPLACE INTO documents_fts(rowid, filepath, title, body)
-            SELECT
-                new.id,
-                new.collection || '/' || new.path,
-                new.title,
-                (SELECT doc FROM content WHERE hash = new.hash)
-            WHERE new.active = 1;
-        END;
+        CREATE VIRTUAL TABLE IF NOT EXISTS documents_fts USING fts5(
+            filepath, title, body,
+            content='documents_fts_view',
+            content_rowid='id',
+            tokenize='porter unicode61'
+        );
         "#,
     )
     .unwrap();
@@ -205,7 +210,9 @@ pub fn insert_test_doc(conn: &Connection, collection: &str, path: &str, title: &
     )
     .unwrap();
 
-    // Then insert document reference
+    // Then upsert document reference, swapping its FTS row as the store does
+    let filter = "d.collection = ? AND d.path = ?";
+    fts::unindex(conn, filter, [collection, path]).unwrap();
     conn.execute(
         "INSERT INTO documents (collection, path, title, hash, created_at, modified_at, active)
          VALUES (?1, ?2, ?3, ?4, datetime('now'), datetime('now'), 1)
@@ -213,4 +220,5 @@ pub fn insert_test_doc(conn: &Connection, collection: &str, path: &str, title: &
         rusqlite::params![collection, path, title, hash],
     )
     .unwrap();
+    fts::index(conn, filter, [collection, path]).unwrap();
 }
@@ -37,6 +37,7 @@ fn test_config_serialize_deserialize_roundtrip() {
                 path: "/tmp/test/project".into(),
                 pattern: Some("**/*.rs".to_string()),
                 description: Some("Rust source files".to_string()),
+                ..Default::default()
             },
         ],
         models: ModelsConfig {
@@ -51,6 +52,7 @@ fn test_config_serialize_deserialize_roundtrip() {
             }),
         },
         cache_path: "/tmp/test/cache".into(),
+        ..Config::default()
     };
 
     // Serialize to YAML
@@ -84,6 +86,41 @@ cache_path: /tmp/cache
     assert!(config.collections.is_empty());
     assert!(matches!(config.bm25.backend, BM25Backend::SqliteFts5));
     assert!(matches!(config.vector.backend, VectorBackend::QmdBuiltin));
+    assert_eq!(config.search.max_expansions, 5);
+}
+
+#[test]
+fn test_config_search_max_expansions() {
+    let yaml = "cache_path: /tmp/cache\nsearch:\n  max_expansions: 2\n";
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(config.search.max_expansions, 2);
+
+    // Only non-default search settings are written back
+    assert!(serde_yaml::to_string(&config).unwrap().contains("max_expansions: 2"));
+    assert!(!serde_yaml::to_string(&Config::default()).unwrap().contains("search:"));
+}
+
+#[test]
+fn test_config_search_candidate_pools() {
+    let config = Config::default();
+    assert_eq!(config.search.fusion_pool_size, 100);
+    assert_eq!(config.search.rerank_candidates, 30);
+
+    let yaml = "cache_path: /tmp/cache\nsearch:\n  fusion_pool_size: 40\n  rerank_candidates: 10\n";
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(config.search.fusion_pool_size, 40);
+    assert_eq!(config.search.rerank_candidates, 10);
+    assert_eq!(config.search.max_expansions, 5);
+}
+
+#[test]
+fn test_config_server_mcp_audit() {
+    assert!(Config::default().server.mcp_audit());
+
+    let yaml = "cache_path: /tmp/cache\nserver:\n  mcp_audit: false\n";
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    assert!(!config.server.mcp_audit());
+    assert!(serde_yaml::to_string(&config).unwrap().contains("mcp_audit: false"));
 }
 
 // ==================== Path Generation ====================
@@ -155,6 +192,16 @@ model: test-model
     assert_eq!(config.model, "test-model");
 }
 
+#[test]
+fn test_vector_dimension_defaults_from_model() {
+    assert_eq!(VectorBackendConfig::default().dimension(), 768);
+    let config: VectorBackendConfig = serde_yaml::from_str("model: all-MiniLM-L6-v2").unwrap();
+    assert_eq!(config.dimension(), 384);
+    let config: VectorBackendConfig = serde_yaml::from_str("model: all-MiniLM-L6-v2\ndimension: 512").unwrap();
+    assert_eq!(config.dimension, Some(512));
+    assert_eq!(config.dimension(), 512);
+}
+
 // ==================== Config Save/Load (Phase 5) ====================
 
 #[test]
@@ -171,10 +218,12 @@ fn test_config_save_and_load_roundtrip() {
                 path: "/tmp/test/docs".into(),
                 pattern: Some("**/*.md".to_string()),
                 description: Some("Test collection".to_string()),
+                ..Default::default()
             },
         ],
         models: ModelsConfig::default(),
         cache_path: "/tmp/test/cache".into(),
+        ..Config::default()
     };
 
     // Serialize and write
@@ -211,6 +260,7 @@ fn test_config_add_collection_persists() {
         path: tmp.path().join("notes"),
         pattern: Some("**/*.md".to_string()),
         description: None,
+        ..Default::default()
     });
 
     // Serialize and write
@@ -237,12 +287,14 @@ fn test_config_remove_collection_persists() {
                 path: "/tmp/keep".into(),
                 pattern: None,
                 description: None,
+                ..Default::default()
             },
             CollectionConfig {
                 name: "remove_me".to_string(),
                 path: "/tmp/remove".into(),
                 pattern: None,
                 description: None,
+                ..Default::default()
             },
         ],
         cache_path: tmp.path().join("cache"),
@@ -277,6 +329,7 @@ fn test_config_rename_collection_persists() {
                 path: "/tmp/project".into(),
                 pattern: Some("**/*.rs".to_string()),
                 description: Some("My project".to_string()),
+                ..Default::default()
             },
         ],
         cache_path: tmp.path().join("cache"),
@@ -310,6 +363,7 @@ fn test_config_duplicate_collection_detection() {
                 path: "/tmp/a".into(),
                 pattern: None,
                 description: None,
+                ..Default::default()
             },
         ],
         ..Config::default()
@@ -322,3 +376,185 @@ fn test_config_duplicate_collection_detection() {
     let no_duplicate = config.collections.iter().any(|c| c.name == "new_one");
     assert!(!no_duplicate);
 }
+
+#[test]
+fn test_config_find_collection_prefers_exact_name() {
+    let collection = |name: &str| CollectionConfig {
+        name: name.to_string(),
+        path: format!("/tmp/{}", name).into(),
+        pattern: None,
+        description: None,
+        ..Default::default()
+    };
+    let config = Config {
+        collections: vec![collection("Docs"), collection("docs"), collection("Résumés")],
+        ..Config::default()
+    };
+
+    assert_eq!(config.find_collection("docs").unwrap().path, PathBuf::from("/tmp/docs"));
+    assert_eq!(config.find_collection("DOCS").unwrap().name, "Docs");
+    assert_eq!(config.find_collection("resumes").unwrap().name, "Résumés");
+    assert!(config.find_collection("notes").is_none());
+
+    assert_eq!(config.similar_collection_names("doc"), vec!["Docs", "docs"]);
+    assert!(config.similar_collection_names("zzz").is_empty());
+}
+
+// ==================== Atomic Updates ====================
+
+#[test]
+fn test_config_concurrent_updates_merge() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config_path = tmp.path().join("index.yaml");
+    Config {
+        cache_path: tmp.path().join("cache"),
+        ..Config::default()
+    }
+    .save_to(&config_path)
+    .unwrap();
+
+    let handles: Vec<_> = ["alpha", "beta"]
+        .into_iter()
+        .map(|name| {
+            let config_path = config_path.clone();
+            let collection_path = tmp.path().join(name);
+            std::thread::spawn(move || {
+                Config::update_at(&config_path, |config| {
+                    // Widen the race window between read and write
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                    config.collections.push(CollectionConfig {
+                        name: name.to_string(),
+                        path: collection_path,
+                        pattern: None,
+                        description: None,
+                        ..Default::default()
+                    });
+                    Ok(())
+                })
+                .unwrap();
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let content = std::fs::read_to_string(&config_path).unwrap();
+    let loaded: Config = serde_yaml::from_str(&content).unwrap();
+    let mut names: Vec<_> = loaded.collections.iter().map(|c| c.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["alpha", "beta"]);
+
+    // Lock and temp files are cleaned up
+    let leftovers: Vec<_> = std::fs::read_dir(tmp.path())
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|n| n.starts_with("index.yaml."))
+        .collect();
+    assert!(leftovers.is_empty(), "unexpected files: {:?}", leftovers);
+}
+
+#[test]
+fn test_config_update_error_leaves_file_untouched() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config_path = tmp.path().join("index.yaml");
+    Config::default().save_to(&config_path).unwrap();
+    let before = std::fs::read_to_string(&config_path).unwrap();
+
+    let result = Config::update_at(&config_path, |config| {
+        config.collections.clear();
+        anyhow::bail!("Collection not found: gone")
+    });
+
+    assert!(result.unwrap_err().to_string().contains("Collection not found"));
+    assert_eq!(std::fs::read_to_string(&config_path).unwrap(), before);
+    assert!(!tmp.path().join("index.yaml.lock").exists());
+}
+
+// ==================== TOML Files ====================
+
+#[test]
+fn test_config_toml_loads_like_yaml() {
+    let tmp = tempfile::tempdir().unwrap();
+    let yaml_path = tmp.path().join("index.yaml");
+    let toml_path = tmp.path().join("index.toml");
+    std::fs::write(
+        &yaml_path,
+        r#"
+cache_path: /tmp/qmd-cache
+collections:
+  - name: docs
+    path: /tmp/docs
+    pattern: "**/*.md"
+    priority: 2
+models:
+  embed:
+    local: nomic-embed
+search:
+  fusion_pool_size: 40
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        &toml_path,
+        r#"
+cache_path = "/tmp/qmd-cache"
+
+[[collections]]
+name = "docs"
+path = "/tmp/docs"
+pattern = "**/*.md"
+priority = 2
+
+[models.embed]
+local = "nomic-embed"
+
+[search]
+fusion_pool_size = 40
+"#,
+    )
+    .unwrap();
+
+    let from_yaml = Config::load_from(&yaml_path).unwrap();
+    let from_toml = Config::load_from(&toml_path).unwrap();
+    assert_eq!(from_toml.collections[0].name, "docs");
+    assert_eq!(from_toml.search.fusion_pool_size, 40);
+    assert_eq!(
+        serde_json::to_value(&from_toml).unwrap(),
+        serde_json::to_value(&from_yaml).unwrap()
+    );
+}
+
+#[test]
+fn test_config_toml_saved_as_toml() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config_path = tmp.path().join("index.toml");
+    Config {
+        cache_path: tmp.path().join("cache"),
+        ..Config::default()
+    }
+    .save_to(&config_path)
+    .unwrap();
+
+    let updated = Config::update_at(&config_path, |config| {
+        config.collections.push(CollectionConfig {
+            name: "docs".to_string(),
+            path: tmp.path().join("docs"),
+            pattern: Some("**/*.md".to_string()),
+            description: None,
+            ..Default::default()
+        });
+        Ok(())
+    })
+    .unwrap();
+
+    let content = std::fs::read_to_string(&config_path).unwrap();
+    assert!(content.contains("[[collections]]"), "{}", content);
+    let parsed: Config = toml::from_str(&content).unwrap();
+    assert_eq!(parsed.collections[0].name, "docs");
+    assert_eq!(
+        serde_json::to_value(Config::load_from(&config_path).unwrap()).unwrap(),
+        serde_json::to_value(&updated).unwrap()
+    );
+}
@@ -0,0 +1,130 @@
+//! compress_content stores document text zstd-compressed while search, reads
+//! and verification see the same text as an uncompressed collection
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::config::Config;
+use qmd_rust::llm::Router;
+use qmd_rust::store::{SearchOptions, SearchResult, Store};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+const PARAGRAPH: &str = "The walrus hauls out on the pack ice in late spring, where the herd \
+rests between long dives for clams along the shallow shelf. Keepers note the \
+weather, the state of the ice and how long each dive lasts.\n\n";
+
+fn write_fixture(content_dir: &Path) {
+    fs::create_dir_all(content_dir).unwrap();
+    for i in 0..40 {
+        let body = format!("# Field log {i}\n\n{}Entry {i} ends with the narwhal count.\n", PARAGRAPH.repeat(60));
+        fs::write(content_dir.join(format!("log-{i:02}.md")), body).unwrap();
+    }
+}
+
+fn setup(dir: &Path, compress: bool) -> (Config, Store) {
+    let content_dir = dir.join("content");
+    write_fixture(&content_dir);
+    let mut config = create_test_config(&dir.join("cache"), "docs", &content_dir);
+    config.collections[0].compress_content = compress;
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    (config, store)
+}
+
+fn options() -> SearchOptions {
+    SearchOptions {
+        limit: 50,
+        collection: Some("docs".to_string()),
+        ..Default::default()
+    }
+}
+
+fn search(store: &Store, query: &str) -> Vec<SearchResult> {
+    store.bm25_search(query, options()).unwrap()
+}
+
+#[test]
+fn test_compressed_collection_is_searchable_and_smaller() {
+    let plain_dir = tempdir().unwrap();
+    let (_, plain) = setup(plain_dir.path(), false);
+    let packed_dir = tempdir().unwrap();
+    let (_, packed) = setup(packed_dir.path(), true);
+
+    assert_eq!(search(&packed, "narwhal").len(), 40);
+    assert_eq!(search(&packed, "clams shelf").len(), 40);
+    // Reads return the same text as the uncompressed collection
+    let by_docid = |store: &Store| {
+        let mut texts: Vec<(String, String)> = search(store, "walrus")
+            .iter()
+            .map(|hit| (hit.docid.clone(), store.document_content(hit).unwrap().unwrap()))
+            .collect();
+        texts.sort();
+        texts
+    };
+    let texts = by_docid(&packed);
+    assert_eq!(texts.len(), 40);
+    assert!(texts[0].1.starts_with("# Field log 0\n"));
+    assert_eq!(texts, by_docid(&plain));
+
+    let plain_report = plain.collection_report("docs").unwrap();
+    let packed_report = packed.collection_report("docs").unwrap();
+    assert_eq!(packed_report.document_count, plain_report.document_count);
+    assert_eq!(packed_report.content_bytes, plain_report.content_bytes);
+    assert!(
+        packed_report.index_bytes * 10 < plain_report.index_bytes * 6,
+        "compressed {} vs plain {}",
+        packed_report.index_bytes,
+        plain_report.index_bytes
+    );
+
+    let compression = &packed_report.compression;
+    assert_eq!((compression.compressed_documents, compression.plain_documents), (40, 0));
+    assert_eq!(compression.original_bytes, packed_report.content_bytes);
+    assert!(compression.ratio > 10.0, "{}", compression.ratio);
+    assert_eq!(plain_report.compression.compressed_documents, 0);
+    assert_eq!(plain_report.compression.ratio, 1.0);
+
+    assert!(packed.verify(None, false).unwrap()[0].is_consistent());
+}
+
+#[tokio::test]
+async fn test_hybrid_search_reads_compressed_text() {
+    let tmp = tempdir().unwrap();
+    let (config, store) = setup(tmp.path(), true);
+    let router = Router::new(&config).unwrap();
+    let results = store.hybrid_search("narwhal count", options(), &router).await.unwrap();
+    assert!(!results.is_empty());
+}
+
+#[test]
+fn test_existing_plain_rows_keep_working_until_compressed() {
+    let tmp = tempdir().unwrap();
+    let (mut config, _) = setup(tmp.path(), false);
+
+    // Turning compression on leaves the rows already indexed alone
+    config.collections[0].compress_content = true;
+    fs::write(tmp.path().join("content").join("new.md"), format!("# New\n\n{}", PARAGRAPH.repeat(20))).unwrap();
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    let stats = store.compression_stats("docs").unwrap();
+    assert_eq!((stats.compressed_documents, stats.plain_documents), (1, 40));
+    assert_eq!(search(&store, "walrus").len(), 41);
+
+    assert_eq!(store.compress_existing_content("docs", true).unwrap(), 40);
+    assert_eq!(store.compression_stats("docs").unwrap().plain_documents, 40);
+    assert_eq!(store.compress_existing_content("docs", false).unwrap(), 40);
+    let stats = store.compression_stats("docs").unwrap();
+    assert_eq!((stats.compressed_documents, stats.plain_documents), (41, 0));
+
+    // The full-text index still matches the stored text
+    assert_eq!(search(&store, "narwhal").len(), 40);
+    let hit = &search(&store, "narwhal")[0];
+    assert!(store.document_content(hit).unwrap().unwrap().contains("narwhal count"));
+    assert!(store.verify(None, false).unwrap()[0].is_consistent());
+
+    // A collection without the setting is never rewritten
+    config.collections[0].compress_content = false;
+    assert_eq!(Store::new(&config).unwrap().compress_existing_content("docs", false).unwrap(), 0);
+}
@@ -0,0 +1,51 @@
+//! `ContextBuilder` assembles cited context from the hybrid search pipeline
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::context::{Budget, ContextBuilder, Granularity};
+use qmd_rust::llm::Router;
+use qmd_rust::store::Store;
+use std::fs;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_build_cites_indexed_documents_within_budget() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("borrow.md"), "# Borrowing\nBorrowing lends a reference without moving ownership.").unwrap();
+    fs::write(content_dir.join("copy.md"), "# Borrowing\nBorrowing lends a reference without moving ownership.").unwrap();
+    fs::write(content_dir.join("traits.md"), "# Traits\nTraits describe shared behaviour; borrowing is unrelated.").unwrap();
+
+    let config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    let router = Router::new(&config).unwrap();
+
+    let context = ContextBuilder::default()
+        .budget(Budget::Chars(4000))
+        .granularity(Granularity::Document)
+        .build(&store, &router, "borrowing")
+        .await
+        .unwrap();
+
+    // The two copies share a hash and are cited once
+    assert_eq!(context.sources.len(), 2, "{:#?}", context);
+    for (i, source) in context.sources.iter().enumerate() {
+        assert_eq!(source.citation, i + 1);
+        assert!(context.text.contains(&format!("[{}] {}\n", source.citation, source.path)), "{}", context.text);
+        let span = source.spans[0];
+        let stored = store.retrieve_document(&source.path).unwrap().content;
+        assert_eq!(&context.text[span.start..span.end], stored.trim_end());
+    }
+    assert!(context.sources[0].score >= context.sources[1].score);
+
+    let tight = ContextBuilder::default()
+        .budget(Budget::Tokens(10))
+        .build(&store, &router, "borrowing")
+        .await
+        .unwrap();
+    assert!(tight.tokens <= 10, "{:#?}", tight);
+    assert!(tight.truncated);
+}
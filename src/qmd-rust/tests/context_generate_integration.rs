@@ -0,0 +1,159 @@
+//! `context generate` describes directories with the LLM or a rule-based summary
+
+mod common;
+
+use assert_cmd::Command;
+use common::create_test_config;
+use qmd_rust::llm::{Generate, GenerateFuture, Router};
+use qmd_rust::store::context::{ContextSource, GenerateMethod};
+use qmd_rust::store::Store;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+
+/// Generator that records its prompts and answers with a fixed sentence
+#[derive(Default)]
+struct MockGenerator {
+    prompts: Mutex<Vec<String>>,
+}
+
+impl Generate for MockGenerator {
+    fn model_name(&self) -> String {
+        "mock-generator".to_string()
+    }
+
+    fn generate<'a>(&'a self, prompt: &'a str) -> GenerateFuture<'a> {
+        Box::pin(async move {
+            let mut prompts = self.prompts.lock().unwrap();
+            prompts.push(prompt.to_string());
+            Ok(format!("\"Mock description {}.\"\n", prompts.len()))
+        })
+    }
+}
+
+fn write_docs(dir: &Path) {
+    fs::create_dir_all(dir.join("rust/async")).unwrap();
+    fs::create_dir_all(dir.join("cooking")).unwrap();
+    fs::write(dir.join("rust/ownership-rules.md"), "# Ownership Rules\nEvery value has one owner.").unwrap();
+    fs::write(dir.join("rust/async/tokio-runtime.md"), "# Tokio Runtime\nSpawning tasks.").unwrap();
+    fs::write(dir.join("cooking/sourdough-bread.md"), "# Sourdough Bread\nFeed the starter.").unwrap();
+    fs::write(dir.join("readme.md"), "# Index\nTop level.").unwrap();
+}
+
+#[tokio::test]
+async fn test_generate_prompts_with_sampled_titles_and_persists() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_docs(&content_dir);
+    let config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let generator = Arc::new(MockGenerator::default());
+    let mut router = Router::new(&config).unwrap();
+    router.set_generator(generator.clone());
+
+    let generated = store.generate_path_contexts("docs", 2, false, &router).await.unwrap();
+    let paths: Vec<&str> = generated.iter().map(|c| c.path.as_str()).collect();
+    assert_eq!(paths, vec!["cooking", "rust", "rust/async"]);
+    assert!(generated.iter().all(|c| c.method == GenerateMethod::Llm));
+    assert_eq!(generated[1].documents, 2);
+
+    let prompts = generator.prompts.lock().unwrap().clone();
+    assert_eq!(prompts.len(), 3);
+    assert!(prompts[0].contains("sourdough-bread"), "{}", prompts[0]);
+    assert!(prompts[0].contains("Feed the starter."), "{}", prompts[0]);
+    assert!(prompts[1].contains("ownership-rules") && prompts[1].contains("tokio-runtime"), "{}", prompts[1]);
+    assert!(!prompts[1].contains("sourdough"), "{}", prompts[1]);
+
+    let (description, _) = store.get_path_context("docs", "rust").unwrap().unwrap();
+    assert_eq!(description, "Mock description 2.");
+    assert_eq!(store.path_context_source("docs", "rust").unwrap(), Some(ContextSource::Generated));
+
+    let coverage = store.context_coverage("docs", false).unwrap();
+    assert_eq!((coverage.covered, coverage.total), (3, 4));
+}
+
+#[tokio::test]
+async fn test_generate_keeps_manual_contexts_unless_overwrite() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_docs(&content_dir);
+    let config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    store.set_path_context("docs", "rust", "Hand-written Rust notes").unwrap();
+
+    let generator = Arc::new(MockGenerator::default());
+    let mut router = Router::new(&config).unwrap();
+    router.set_generator(generator.clone());
+
+    let generated = store.generate_path_contexts("docs", 1, false, &router).await.unwrap();
+    let rust = generated.iter().find(|c| c.path == "rust").unwrap();
+    assert_eq!(rust.method, GenerateMethod::Skipped);
+    assert_eq!(rust.description, "Hand-written Rust notes");
+    assert_eq!(generator.prompts.lock().unwrap().len(), 1);
+    assert_eq!(store.get_path_context("docs", "rust").unwrap().unwrap().0, "Hand-written Rust notes");
+
+    store.generate_path_contexts("docs", 1, true, &router).await.unwrap();
+    assert!(store.get_path_context("docs", "rust").unwrap().unwrap().0.starts_with("Mock description"));
+    assert_eq!(store.path_context_source("docs", "rust").unwrap(), Some(ContextSource::Generated));
+}
+
+#[tokio::test]
+async fn test_generate_without_llm_uses_rule_based_summary() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_docs(&content_dir);
+    let config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let router = Router::new(&config).unwrap();
+    assert!(!router.has_generator());
+    let generated = store.generate_path_contexts("docs", 1, false, &router).await.unwrap();
+    let cooking = generated.iter().find(|c| c.path == "cooking").unwrap();
+    assert_eq!(cooking.method, GenerateMethod::Rules);
+    assert_eq!(cooking.description, "1 document about sourdough, bread");
+    assert_eq!(store.get_path_context("docs", "cooking").unwrap().unwrap().0, cooking.description);
+}
+
+#[test]
+fn test_cli_generate_and_check_coverage() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("notes");
+    write_docs(&content_dir);
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let yaml = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), yaml).unwrap();
+    let qmd = |args: &[&str]| {
+        let output = Command::cargo_bin("qmd-rust")
+            .unwrap()
+            .env("HOME", tmp.path())
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    qmd(&["update"]);
+
+    let before = qmd(&["context", "check"]);
+    assert!(before.contains("notes: 0.0% (0/4 documents)"), "{}", before);
+
+    let json: serde_json::Value =
+        serde_json::from_str(&qmd(&["context", "--format", "json", "generate", "-c", "notes"])).unwrap();
+    let contexts = json["contexts"].as_array().unwrap();
+    assert_eq!(contexts.len(), 2);
+    assert_eq!(contexts[0]["path"], "cooking");
+    assert_eq!(contexts[0]["method"], "rules");
+
+    let after = qmd(&["context", "check"]);
+    assert!(after.contains("notes: 75.0% (3/4 documents)"), "{}", after);
+}
@@ -0,0 +1,102 @@
+//! `--dedupe-by-hash` folds copies of the same content into one result
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+const GUIDE: &str = "# Borrowing\nBorrowing rules for references, borrowing explained.";
+
+fn setup(home: &Path, defaults: &str) {
+    let content_dir = home.join("notes");
+    fs::create_dir_all(content_dir.join("vendor")).unwrap();
+    fs::write(content_dir.join("guide.md"), GUIDE).unwrap();
+    fs::write(content_dir.join("vendor").join("guide.md"), GUIDE).unwrap();
+    fs::write(content_dir.join("other.md"), "# Other\nA note that mentions borrowing once.").unwrap();
+
+    let config_dir = home.join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let yaml = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n{}",
+        home.join("cache").display(),
+        content_dir.display(),
+        defaults
+    );
+    fs::write(config_dir.join("index.yaml"), yaml).unwrap();
+}
+
+fn qmd(home: &Path, args: &[&str]) -> String {
+    let output = Command::cargo_bin("qmd-rust")
+        .unwrap()
+        .env("HOME", home)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn json(home: &Path, args: &[&str]) -> serde_json::Value {
+    serde_json::from_str(&qmd(home, args)).unwrap()
+}
+
+fn paths(results: &serde_json::Value) -> Vec<String> {
+    results["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["path"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_dedupe_by_hash_folds_copies_and_frees_slots() {
+    let tmp = tempdir().unwrap();
+    setup(tmp.path(), "");
+    qmd(tmp.path(), &["update"]);
+
+    // Without the flag both copies crowd the top two slots
+    let plain = json(tmp.path(), &["search", "borrowing", "--limit", "2", "--format", "json"]);
+    let plain_paths = paths(&plain);
+    assert!(plain_paths.iter().all(|p| p.ends_with("guide.md")), "{}", plain);
+
+    let deduped = json(
+        tmp.path(),
+        &["search", "borrowing", "--limit", "2", "--dedupe-by-hash", "--format", "json"],
+    );
+    let deduped_paths = paths(&deduped);
+    assert_eq!(deduped["total"], 2, "{}", deduped);
+    assert!(deduped_paths[0].ends_with("guide.md"), "{}", deduped);
+    assert_eq!(deduped_paths[1], "notes/other.md", "{}", deduped);
+
+    let kept = &deduped["results"][0];
+    let other_copy = if deduped_paths[0] == "notes/guide.md" {
+        "notes/vendor/guide.md"
+    } else {
+        "notes/guide.md"
+    };
+    assert_eq!(kept["duplicates"], serde_json::json!([other_copy]), "{}", deduped);
+    assert_eq!(kept["duplicate_count"], 1);
+    assert!(deduped["results"][1].get("duplicates").is_none());
+
+    // The explain view names the folded copies
+    let explained = qmd(tmp.path(), &["search", "borrowing", "--dedupe-by-hash", "--explain"]);
+    assert!(explained.contains(&format!("(1): {}", other_copy)), "{}", explained);
+}
+
+#[test]
+fn test_dedupe_by_hash_config_default_applies_to_query() {
+    let tmp = tempdir().unwrap();
+    setup(tmp.path(), "defaults:\n  dedupe_by_hash: true\n");
+    qmd(tmp.path(), &["update"]);
+
+    let results = json(tmp.path(), &["query", "borrowing", "--format", "json"]);
+    let guides: Vec<&serde_json::Value> = results["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|r| r["path"].as_str().unwrap().ends_with("guide.md"))
+        .collect();
+    assert_eq!(guides.len(), 1, "{}", results);
+    assert_eq!(guides[0]["duplicates"].as_array().unwrap().len(), 1, "{}", results);
+}
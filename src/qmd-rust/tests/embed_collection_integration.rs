@@ -0,0 +1,166 @@
+//! Store::embed_collection chunks pending documents and stores their vectors once
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::config::Config;
+use qmd_rust::llm::{Embed, EmbedFuture, Router};
+use qmd_rust::store::embed::EmbedStats;
+use qmd_rust::store::Store;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tempfile::tempdir;
+
+/// Counts the texts it is asked to embed
+struct CountingEmbedder(Arc<AtomicUsize>);
+
+impl Embed for CountingEmbedder {
+    fn model_name(&self) -> String {
+        "counting-mock".to_string()
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a> {
+        self.0.fetch_add(texts.len(), Ordering::SeqCst);
+        Box::pin(async move { Ok(texts.iter().map(|_| vec![0.1; 8]).collect()) })
+    }
+}
+
+fn setup(dir: &std::path::Path) -> (Config, Store) {
+    let content_dir = dir.join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("walrus.md"), "# Walrus\nThe walrus naps on the ice.").unwrap();
+    fs::write(content_dir.join("narwhal.md"), "# Narwhal\nThe narwhal has a tusk.").unwrap();
+    let mut config = create_test_config(&dir.join("cache"), "docs", &content_dir);
+    // The mock embedder's vectors are 8 wide
+    config.vector.dimension = Some(8);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    (config, store)
+}
+
+fn embed(store: &Store, config: &Config, calls: &Arc<AtomicUsize>, force: bool) -> EmbedStats {
+    let mut router = Router::new(config).unwrap();
+    router.set_embedder(Arc::new(CountingEmbedder(calls.clone())));
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(store.embed_collection("docs", &router, force)).unwrap()
+}
+
+/// `content_vectors` rows, and `vectors_vec` rows when sqlite-vec is loaded
+fn stored_vectors(store: &Store) -> (i64, Option<i64>) {
+    let conn = store.get_connection("docs").unwrap();
+    let chunks = conn.query_row("SELECT COUNT(*) FROM content_vectors", [], |row| row.get(0)).unwrap();
+    let vectors = conn.query_row("SELECT COUNT(*) FROM vectors_vec", [], |row| row.get(0)).ok();
+    (chunks, vectors)
+}
+
+#[test]
+fn test_embed_collection_stores_vectors_and_reports_stats() {
+    let tmp = tempdir().unwrap();
+    let (config, store) = setup(tmp.path());
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let stats = embed(&store, &config, &calls, false);
+    assert_eq!(stats.documents_embedded, 2);
+    assert_eq!(stats.chunks_embedded, 2);
+    assert_eq!(stats.model.as_deref(), Some("counting-mock"));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    let (chunks, vectors) = stored_vectors(&store);
+    assert_eq!(chunks, 2);
+    if let Some(vectors) = vectors {
+        assert_eq!(vectors, 2);
+    }
+}
+
+#[test]
+fn test_embed_collection_is_idempotent_without_force() {
+    let tmp = tempdir().unwrap();
+    let (config, store) = setup(tmp.path());
+    let calls = Arc::new(AtomicUsize::new(0));
+    embed(&store, &config, &calls, false);
+
+    let stats = embed(&store, &config, &calls, false);
+    assert_eq!(stats, EmbedStats::default());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert_eq!(stored_vectors(&store).0, 2);
+}
+
+#[test]
+fn test_embed_collection_force_re_embeds() {
+    let tmp = tempdir().unwrap();
+    let (config, store) = setup(tmp.path());
+    let calls = Arc::new(AtomicUsize::new(0));
+    let first = embed(&store, &config, &calls, false);
+
+    let forced = embed(&store, &config, &calls, true);
+    assert_eq!(forced, first);
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+    // Replaced, not duplicated
+    let (chunks, vectors) = stored_vectors(&store);
+    assert_eq!(chunks, 2);
+    if let Some(vectors) = vectors {
+        assert_eq!(vectors, 2);
+    }
+}
+
+#[test]
+fn test_embed_collection_only_embeds_new_documents() {
+    let tmp = tempdir().unwrap();
+    let (config, store) = setup(tmp.path());
+    let calls = Arc::new(AtomicUsize::new(0));
+    embed(&store, &config, &calls, false);
+    assert_eq!(store.get_stats().unwrap().pending_count, 0);
+
+    let dugong = "# Dugong\nThe dugong grazes on seagrass.";
+    fs::write(tmp.path().join("content").join("dugong.md"), dugong).unwrap();
+    store.update_index().unwrap();
+    assert_eq!(store.pending_embeddings("docs", Some("counting-mock")).unwrap(), vec![Store::calculate_hash(dugong)]);
+    assert_eq!(store.get_stats().unwrap().pending_count, 1);
+
+    let stats = embed(&store, &config, &calls, false);
+    assert_eq!(stats.documents_embedded, 1);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    assert!(store.pending_embeddings("docs", Some("counting-mock")).unwrap().is_empty());
+    assert_eq!(store.get_stats().unwrap().pending_count, 0);
+
+    // Vectors from another model do not count
+    assert_eq!(store.pending_embeddings("docs", Some("other-model")).unwrap().len(), 3);
+}
+
+#[test]
+fn test_identical_copies_share_one_embedding() {
+    let tmp = tempdir().unwrap();
+    let (config, store) = setup(tmp.path());
+    let copy_dir = tmp.path().join("content").join("copy");
+    fs::create_dir_all(&copy_dir).unwrap();
+    fs::copy(tmp.path().join("content").join("walrus.md"), copy_dir.join("walrus.md")).unwrap();
+    store.update_index().unwrap();
+    assert_eq!(store.get_stats().unwrap().document_count, 3);
+
+    // Vectors are keyed by content hash, so the copy is embedded once
+    let calls = Arc::new(AtomicUsize::new(0));
+    let stats = embed(&store, &config, &calls, false);
+    assert_eq!(stats.documents_embedded, 2);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert_eq!(stored_vectors(&store).0, 2);
+    assert_eq!(store.get_stats().unwrap().pending_count, 0);
+
+    // A vector query resolves the shared vectors back to every path
+    #[cfg(feature = "sqlite-vec")]
+    {
+        let options = qmd_rust::store::SearchOptions {
+            limit: 10,
+            collection: Some("docs".to_string()),
+            ..Default::default()
+        };
+        let docids: Vec<String> = store
+            .vector_search_with_embedding(&[0.1; 8], options)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.docid)
+            .collect();
+        assert!(docids.contains(&"docs:walrus.md".to_string()), "{:?}", docids);
+        assert!(docids.contains(&"docs:copy/walrus.md".to_string()), "{:?}", docids);
+    }
+}
@@ -0,0 +1,120 @@
+//! Embedding the same content twice, even concurrently, leaves one set of chunk rows
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::cli::embed::embed_hashes_async;
+use qmd_rust::config::Config;
+use qmd_rust::llm::{Embed, EmbedFuture, Router};
+use qmd_rust::store::chunker::{chunk_document, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP};
+use qmd_rust::store::Store;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+/// Yields between batches so concurrent runs interleave
+struct DelayedEmbedder;
+
+impl Embed for DelayedEmbedder {
+    fn model_name(&self) -> String {
+        "delayed-mock".to_string()
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a> {
+        Box::pin(async move {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            Ok(texts.iter().map(|_| vec![0.1; 8]).collect())
+        })
+    }
+}
+
+fn embed(store: &Store, config: &Config, hashes: &[String]) -> usize {
+    let mut router = Router::new(config).unwrap();
+    router.set_embedder(Arc::new(DelayedEmbedder));
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(embed_hashes_async(store, "notes", &router, hashes)).unwrap()
+}
+
+/// A document long enough for several embedding batches, and a short one
+fn write_docs(dir: &std::path::Path) -> (String, String) {
+    fs::create_dir_all(dir).unwrap();
+    let long: String = (0..1500).map(|i| format!("Line {} about ownership and borrowing.\n", i)).collect();
+    let short = "# Short\nOne chunk only".to_string();
+    fs::write(dir.join("long.md"), &long).unwrap();
+    fs::write(dir.join("short.md"), &short).unwrap();
+    (long, short)
+}
+
+fn hashes(store: &Store) -> Vec<String> {
+    let conn = store.get_connection("notes").unwrap();
+    let mut stmt = conn.prepare("SELECT hash FROM documents WHERE active = 1 ORDER BY path").unwrap();
+    stmt.query_map([], |row| row.get(0)).unwrap().map(|row| row.unwrap()).collect()
+}
+
+/// Stored chunk numbers of `hash`, in order
+fn chunk_seqs(store: &Store, hash: &str) -> Vec<i64> {
+    let conn = store.get_connection("notes").unwrap();
+    let mut stmt = conn.prepare("SELECT seq FROM content_vectors WHERE hash = ? ORDER BY seq").unwrap();
+    stmt.query_map([hash], |row| row.get(0)).unwrap().map(|row| row.unwrap()).collect()
+}
+
+fn expected_seqs(doc: &str) -> Vec<i64> {
+    (0..chunk_document(doc, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP).len() as i64).collect()
+}
+
+#[test]
+fn test_concurrent_embeds_of_the_same_documents_leave_one_set_of_chunks() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    let (long, short) = write_docs(&content_dir);
+    let config = create_test_config(&tmp.path().join("cache"), "notes", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    let hashes = hashes(&store);
+    assert!(expected_seqs(&long).len() > 10, "long document should span several batches");
+
+    let chunks = std::thread::scope(|scope| {
+        let runs: Vec<_> = (0..2).map(|_| scope.spawn(|| embed(&store, &config, &hashes))).collect();
+        runs.into_iter().map(|run| run.join().unwrap()).collect::<Vec<_>>()
+    });
+    let total = expected_seqs(&long).len() + expected_seqs(&short).len();
+    assert_eq!(chunks, [total, total]);
+
+    assert_eq!(chunk_seqs(&store, &hashes[0]), expected_seqs(&long));
+    assert_eq!(chunk_seqs(&store, &hashes[1]), expected_seqs(&short));
+}
+
+#[test]
+fn test_reembedding_replaces_chunks_and_drops_stale_ones() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    let (_, short) = write_docs(&content_dir);
+    let config = create_test_config(&tmp.path().join("cache"), "notes", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    let short_hash = hashes(&store)[1].clone();
+
+    embed(&store, &config, std::slice::from_ref(&short_hash));
+    // A chunk row left by a longer earlier version
+    store
+        .get_connection("notes")
+        .unwrap()
+        .execute(
+            "INSERT INTO content_vectors (hash, seq, pos, model, embedded_at) VALUES (?, 7, 0, 'old', datetime('now'))",
+            [&short_hash],
+        )
+        .unwrap();
+
+    // Re-running over embedded chunks succeeds and keeps one row per chunk
+    embed(&store, &config, std::slice::from_ref(&short_hash));
+    embed(&store, &config, std::slice::from_ref(&short_hash));
+    assert_eq!(chunk_seqs(&store, &short_hash), expected_seqs(&short));
+
+    let model: String = store
+        .get_connection("notes")
+        .unwrap()
+        .query_row("SELECT model FROM content_vectors WHERE hash = ?", [&short_hash], |row| row.get(0))
+        .unwrap();
+    assert_eq!(model, "delayed-mock");
+}
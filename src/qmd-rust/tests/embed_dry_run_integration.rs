@@ -0,0 +1,148 @@
+//! `embed --dry-run` estimates the same work a real embed run then performs
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_docs(dir: &Path) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("short.md"), "# Short\nA single small chunk.").unwrap();
+    // Long enough to be split into several overlapping chunks
+    let long: String = (0..400)
+        .map(|i| format!("Paragraph {} talks about embeddings and vector search.\n\n", i))
+        .collect();
+    fs::write(dir.join("long.md"), format!("# Long\n\n{}", long)).unwrap();
+}
+
+fn write_config(home: &Path, content_dir: &Path, extra: &str) {
+    let config_dir = home.join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let yaml = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n{}",
+        home.join("cache").display(),
+        content_dir.display(),
+        extra
+    );
+    fs::write(config_dir.join("index.yaml"), yaml).unwrap();
+}
+
+fn qmd(home: &Path, args: &[&str]) -> String {
+    let output = Command::cargo_bin("qmd-rust")
+        .unwrap()
+        .env("HOME", home)
+        .env("OPENAI_API_KEY", "test-key")
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn estimate(home: &Path, args: &[&str]) -> serde_json::Value {
+    let mut full = vec!["embed", "--dry-run", "--format", "json"];
+    full.extend_from_slice(args);
+    serde_json::from_str(&qmd(home, &full)).unwrap()
+}
+
+fn vector_rows(home: &Path) -> i64 {
+    let conn = rusqlite::Connection::open(home.join("cache").join("notes").join("index.db")).unwrap();
+    conn.query_row("SELECT COUNT(*) FROM content_vectors", [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn test_dry_run_chunk_counts_match_real_run() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("notes");
+    write_docs(&content_dir);
+    // The local model file does not exist, so embedding uses fallback vectors
+    write_config(
+        tmp.path(),
+        &content_dir,
+        "models:\n  embed:\n    local: missing-model\n    remote: text-embedding-3-small\n\
+         embed:\n  prices:\n    text-embedding-3-small: 0.02\n  chunks_per_second: 4.0\n",
+    );
+    qmd(tmp.path(), &["update"]);
+
+    let before = estimate(tmp.path(), &[]);
+    assert_eq!(before["dry_run"], true);
+    assert_eq!(before["model"], "text-embedding-3-small");
+    let notes = &before["collections"][0];
+    assert_eq!(notes["collection"], "notes");
+    assert_eq!(notes["documents"], 2);
+    let chunks = notes["chunks"].as_i64().unwrap();
+    assert!(chunks > 2, "{}", before);
+    let tokens = notes["tokens"].as_f64().unwrap();
+    assert!((notes["cost_usd"].as_f64().unwrap() - tokens * 0.02 / 1_000_000.0).abs() < 1e-12);
+    assert_eq!(notes["rate_source"], "config");
+    assert!((notes["seconds"].as_f64().unwrap() - chunks as f64 / 4.0).abs() < 1e-9);
+    assert_eq!(before["total"]["chunks"], chunks);
+
+    // Nothing is embedded by the dry run itself
+    assert_eq!(vector_rows(tmp.path()), 0);
+
+    qmd(tmp.path(), &["embed"]);
+    assert_eq!(vector_rows(tmp.path()), chunks);
+
+    let after = estimate(tmp.path(), &[]);
+    assert_eq!(after["collections"][0]["documents"], 0);
+    assert_eq!(after["collections"][0]["chunks"], 0);
+    let forced = estimate(tmp.path(), &["--force"]);
+    assert_eq!(forced["collections"][0]["chunks"], chunks);
+}
+
+#[test]
+fn test_dry_run_uses_last_run_rate_without_configured_throughput() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("notes");
+    write_docs(&content_dir);
+    write_config(tmp.path(), &content_dir, "models:\n  embed:\n    local: missing-model\n");
+    qmd(tmp.path(), &["update"]);
+
+    let first = estimate(tmp.path(), &[]);
+    let notes = &first["collections"][0];
+    assert!(notes["cost_usd"].is_null(), "{}", first);
+    assert!(notes["rate_source"].is_null(), "{}", first);
+    assert!(notes["seconds"].is_null(), "{}", first);
+
+    qmd(tmp.path(), &["embed"]);
+    let forced = estimate(tmp.path(), &["--force"]);
+    let notes = &forced["collections"][0];
+    assert_eq!(notes["rate_source"], "last_run");
+    assert!(notes["chunks_per_second"].as_f64().unwrap() > 0.0, "{}", forced);
+    assert!(notes["seconds"].as_f64().is_some(), "{}", forced);
+
+    let text = qmd(tmp.path(), &["embed", "--dry-run", "--force"]);
+    assert!(text.contains("[DRY-RUN] Embedding estimate"), "{}", text);
+    assert!(text.contains("notes: 2 documents"), "{}", text);
+    assert!(text.contains("(last run rate)"), "{}", text);
+}
+
+#[test]
+fn test_dry_run_chunk_count_matches_chunker_and_writes_nothing() {
+    use qmd_rust::store::chunker::{chunk_document, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP};
+
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("notes");
+    write_docs(&content_dir);
+    write_config(tmp.path(), &content_dir, "models:\n  embed:\n    local: missing-model\n");
+    qmd(tmp.path(), &["update"]);
+
+    let expected: usize = ["short.md", "long.md"]
+        .iter()
+        .map(|file| {
+            let doc = fs::read_to_string(content_dir.join(file)).unwrap();
+            chunk_document(&doc, DEFAULT_CHUNK_SIZE, DEFAULT_OVERLAP).len()
+        })
+        .sum();
+    assert!(expected > 2);
+
+    let report = estimate(tmp.path(), &["--collection", "notes"]);
+    assert_eq!(report["collections"][0]["chunks"], expected, "{}", report);
+    assert_eq!(report["total"]["chunks"], expected, "{}", report);
+
+    // Repeating the dry run still sees every document as pending
+    let again = estimate(tmp.path(), &["--collection", "notes"]);
+    assert_eq!(again["collections"][0]["documents"], 2);
+    assert_eq!(vector_rows(tmp.path()), 0);
+}
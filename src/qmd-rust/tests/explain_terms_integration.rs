@@ -0,0 +1,103 @@
+//! `search --explain-terms` reports how many documents contain each query term
+
+mod common;
+
+use assert_cmd::Command;
+use common::create_multi_collection_config;
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn all_collections() -> SearchOptions {
+    SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    }
+}
+
+/// Ten documents mentioning "rust", one of them also "lifetimes"
+fn write_docs(dir: &Path) {
+    fs::create_dir_all(dir).unwrap();
+    for i in 0..10 {
+        fs::write(dir.join(format!("doc{}.md", i)), format!("# Note {}\nRust ownership basics", i)).unwrap();
+    }
+    fs::write(dir.join("doc0.md"), "# Note 0\nRust lifetimes explained").unwrap();
+}
+
+#[test]
+fn test_term_frequencies_rare_and_common_terms() {
+    let tmp = tempdir().unwrap();
+    let notes = tmp.path().join("notes");
+    let code = tmp.path().join("code");
+    write_docs(&notes);
+    write_docs(&code);
+
+    let mut config = create_multi_collection_config(&tmp.path().join("cache"), &[("notes", &notes), ("code", &code)]);
+    config.collections[1].code = true;
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let explain = store.explain_query("rust lifetimes", Vec::new(), &all_collections(), None).unwrap();
+    let frequencies = store.term_frequencies(&explain.fts_queries).unwrap();
+    let documents = |collection: &str, term: &str| {
+        let found = frequencies
+            .iter()
+            .find(|f| f.collection == collection && f.term == term)
+            .unwrap_or_else(|| panic!("{} in {}: {:?}", term, collection, frequencies));
+        assert_eq!(found.total_documents, 10);
+        found.documents
+    };
+
+    // Porter-stemmed and trigram indexes agree on the counts
+    for collection in ["notes", "code"] {
+        assert_eq!(documents(collection, "rust"), 10);
+        assert_eq!(documents(collection, "lifetimes"), 1);
+    }
+
+    let missing = store.explain_query("borrowck", Vec::new(), &all_collections(), None).unwrap();
+    let frequencies = store.term_frequencies(&missing.fts_queries).unwrap();
+    assert!(frequencies.iter().all(|f| f.documents == 0), "{:?}", frequencies);
+}
+
+#[test]
+fn test_cli_search_explain_terms() {
+    let tmp = tempdir().unwrap();
+    let notes = tmp.path().join("notes");
+    write_docs(&notes);
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        notes.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let output = Command::cargo_bin("qmd-rust").unwrap().env("HOME", tmp.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    qmd(&["update"]);
+
+    let json: serde_json::Value =
+        serde_json::from_str(&qmd(&["search", "rust lifetimes", "--explain-terms", "--format", "json"])).unwrap();
+    let terms = json["explain"]["terms"].as_array().unwrap();
+    assert_eq!(terms.len(), 2, "{}", json);
+    assert_eq!(terms[0]["term"], "rust");
+    assert_eq!(terms[0]["documents"], 10);
+    assert_eq!(terms[1]["term"], "lifetimes");
+    assert_eq!(terms[1]["documents"], 1);
+    assert_eq!(terms[1]["total_documents"], 10);
+
+    let text = qmd(&["search", "rust lifetimes", "--explain-terms"]);
+    assert!(text.contains("term [notes] lifetimes: in 1 of 10 documents"), "{}", text);
+
+    // Plain --explain leaves the term counts out
+    let json: serde_json::Value =
+        serde_json::from_str(&qmd(&["search", "rust lifetimes", "--explain", "--format", "json"])).unwrap();
+    assert!(json["explain"].get("terms").is_none(), "{}", json);
+}
@@ -1,4 +1,4 @@
-use qmd_rust::formatter::Format;
+use qmd_rust::formatter::{group_results, render_markdown_grouped, Format, GroupBy};
 use qmd_rust::store::SearchResult;
 
 fn make_results() -> Vec<SearchResult> {
@@ -12,6 +12,11 @@ fn make_results() -> Vec<SearchResult> {
             title: "Main Entry".to_string(),
             hash: "abc123".to_string(),
             query: Some("test query".to_string()),
+            size: None,
+            match_line: None,
+            duplicates: Vec::new(),
+            source: Vec::new(),
+            score_components: None,
         },
         SearchResult {
             docid: "project:src/lib.rs".to_string(),
@@ -22,6 +27,11 @@ fn make_results() -> Vec<SearchResult> {
             title: "Library".to_string(),
             hash: "def456".to_string(),
             query: Some("test query".to_string()),
+            size: None,
+            match_line: None,
+            duplicates: Vec::new(),
+            source: Vec::new(),
+            score_components: None,
         },
     ]
 }
@@ -77,6 +87,12 @@ fn test_format_from_string_xml() {
     assert!(matches!(f, Format::Xml));
 }
 
+#[test]
+fn test_format_from_string_ndjson() {
+    assert!(matches!(Format::from_string("ndjson"), Format::Ndjson));
+    assert!(matches!(Format::from_string("JSONL"), Format::Ndjson));
+}
+
 // ==================== Format Output Tests ====================
 // These tests capture stdout to verify output content.
 
@@ -139,7 +155,7 @@ fn test_format_xml_no_panic() {
 #[test]
 fn test_format_empty_results() {
     let results: Vec<SearchResult> = vec![];
-    for fmt_str in &["cli", "json", "md", "csv", "files", "xml"] {
+    for fmt_str in &["cli", "json", "ndjson", "md", "csv", "files", "xml"] {
         let fmt = Format::from_string(fmt_str);
         fmt.format_search_results(&results, 10).unwrap();
     }
@@ -160,3 +176,82 @@ fn test_search_result_json_roundtrip() {
     let deserialized: Vec<SearchResult> = serde_json::from_str(&json).unwrap();
     assert_eq!(original, deserialized);
 }
+
+// ==================== Grouping Tests ====================
+
+fn result_in(collection: &str, path: &str, score: f32) -> SearchResult {
+    SearchResult {
+        docid: format!("#{}", path.len()),
+        path: format!("{}/{}", collection, path),
+        collection: collection.to_string(),
+        score,
+        lines: 1,
+        title: path.to_string(),
+        hash: path.to_string(),
+        query: None,
+        size: None,
+        match_line: None,
+        duplicates: Vec::new(),
+        source: Vec::new(),
+        score_components: None,
+    }
+}
+
+fn interleaved_results() -> Vec<SearchResult> {
+    vec![
+        result_in("notes", "guides/a.md", 0.9),
+        result_in("code", "src/main.rs", 0.8),
+        result_in("notes", "b.md", 0.7),
+        result_in("notes", "guides/c.md", 0.6),
+        result_in("code", "README.md", 0.5),
+    ]
+}
+
+#[test]
+fn test_group_results_by_collection_keeps_rank_order() {
+    let results = interleaved_results();
+    let groups = group_results(&results, GroupBy::Collection);
+
+    let shape: Vec<(&str, &[usize])> = groups.iter().map(|g| (g.name.as_str(), g.indices.as_slice())).collect();
+    assert_eq!(shape, [("notes", &[0, 2, 3][..]), ("code", &[1, 4][..])]);
+
+    let mut all: Vec<usize> = groups.iter().flat_map(|g| g.indices.clone()).collect();
+    all.sort();
+    assert_eq!(all, (0..results.len()).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_group_results_by_top_level_dir() {
+    let results = interleaved_results();
+    let groups = group_results(&results, GroupBy::Dir);
+
+    let shape: Vec<(&str, &[usize])> = groups.iter().map(|g| (g.name.as_str(), g.indices.as_slice())).collect();
+    assert_eq!(
+        shape,
+        [("notes/guides/", &[0, 3][..]), ("code/src/", &[1][..]), ("notes/", &[2][..]), ("code/", &[4][..])]
+    );
+    assert_eq!(groups.iter().map(|g| g.indices.len()).sum::<usize>(), results.len());
+}
+
+#[test]
+fn test_group_by_parses_known_values() {
+    assert_eq!("collection".parse::<GroupBy>().unwrap(), GroupBy::Collection);
+    assert_eq!("dir".parse::<GroupBy>().unwrap(), GroupBy::Dir);
+    assert!("extension".parse::<GroupBy>().is_err());
+}
+
+#[test]
+fn test_markdown_grouped_lists_each_result_once_under_its_header() {
+    let results = interleaved_results();
+    let md = render_markdown_grouped(&results, &[], Some(GroupBy::Collection));
+
+    let notes = md.find("## notes (3)").unwrap();
+    let code = md.find("## code (2)").unwrap();
+    assert!(notes < code, "{}", md);
+    // Overall rank numbers are kept
+    let second = md.find("### 2. code/src/main.rs").unwrap();
+    assert!(second > code, "{}", md);
+    for result in &results {
+        assert_eq!(md.matches(&format!(". {}\n", result.path)).count(), 1, "{}", md);
+    }
+}
@@ -0,0 +1,90 @@
+//! `get --around` centers the window on the match recorded by the last search
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn setup(home: &Path) {
+    let content_dir = home.join("notes");
+    fs::create_dir_all(&content_dir).unwrap();
+    let body: Vec<String> = (1..=200)
+        .map(|i| match i {
+            120 => "The zebra crossing is described here.".to_string(),
+            _ => format!("Filler line {} about nothing in particular.", i),
+        })
+        .collect();
+    fs::write(content_dir.join("long-notes.md"), body.join("\n")).unwrap();
+
+    let config_dir = home.join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let yaml = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n",
+        home.join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), yaml).unwrap();
+}
+
+fn qmd(home: &Path, args: &[&str]) -> std::process::Output {
+    Command::cargo_bin("qmd-rust")
+        .unwrap()
+        .env("HOME", home)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(home: &Path, args: &[&str]) -> String {
+    let output = qmd(home, args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn test_get_around_centers_on_search_match() {
+    let tmp = tempdir().unwrap();
+    setup(tmp.path());
+    stdout(tmp.path(), &["update"]);
+
+    let search: serde_json::Value =
+        serde_json::from_str(&stdout(tmp.path(), &["search", "zebra", "--format", "json"])).unwrap();
+    let hit = &search["results"][0];
+    assert_eq!(hit["match_line"], 120, "{}", search);
+    let docid = hit["docid"].as_str().unwrap();
+
+    let get: serde_json::Value = serde_json::from_str(&stdout(
+        tmp.path(),
+        &["get", docid, "--around", "--limit", "10", "--format", "json"],
+    ))
+    .unwrap();
+    assert_eq!(get["match_line"], 120);
+    assert_eq!(get["from"], 115);
+    let lines = get["lines"].as_array().unwrap();
+    assert_eq!(lines.len(), 10);
+    assert!(lines[5].as_str().unwrap().contains("zebra"), "{}", get);
+
+    let text = stdout(tmp.path(), &["get", docid, "--around", "--limit", "4"]);
+    assert!(text.contains("   120> The zebra crossing"), "{}", text);
+    assert!(text.contains("   118: ") && text.contains("   121: "), "{}", text);
+}
+
+#[test]
+fn test_get_around_explicit_line_and_missing_match() {
+    let tmp = tempdir().unwrap();
+    setup(tmp.path());
+    stdout(tmp.path(), &["update"]);
+
+    // No search has recorded a match for this document yet
+    let output = qmd(tmp.path(), &["get", "notes/long-notes.md", "--around"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No match recorded"));
+
+    let get: serde_json::Value = serde_json::from_str(&stdout(
+        tmp.path(),
+        &["get", "notes/long-notes.md:198", "--around", "--limit", "6", "--format", "json"],
+    ))
+    .unwrap();
+    assert_eq!(get["from"], 195);
+    assert_eq!(get["lines"].as_array().unwrap().len(), 6);
+}
@@ -23,6 +23,7 @@ async fn test_hybrid_search_bm25_only() {
             path: content_dir.clone(),
             pattern: Some("**/*".to_string()),
             description: None,
+            ..Default::default()
         }],
         cache_path: tmp.path().to_path_buf(),
         models: ModelsConfig {
@@ -47,17 +48,65 @@ async fn test_hybrid_search_bm25_only() {
 
     let opts = SearchOptions {
         limit: 10,
-        min_score: 0.0,
         collection: Some("test".to_string()),
-        search_all: false,
+        ..Default::default()
     };
 
-    // hybrid_search calls embed which will fail with "No embedder available",
-    // but the BM25 part should still execute. Since the current implementation
-    // doesn't gracefully handle embed failure in hybrid_search, we test the
-    // BM25 path directly as the core logic.
-    let bm25_results = store.bm25_search("Rust programming", opts).unwrap();
+    let bm25_results = store.bm25_search("Rust programming", opts.clone()).unwrap();
     assert!(!bm25_results.is_empty(), "BM25 should find results even without embedder");
+
+    // The failed embed degrades the vector leg instead of failing the query
+    let hybrid = store.hybrid_search("Rust programming", opts, &router).await.unwrap();
+    assert!(hybrid.iter().any(|r| r.path.ends_with("rust.md")), "{:?}", hybrid);
+}
+
+// ==================== Candidate pool sizes ====================
+
+#[tokio::test]
+async fn test_hybrid_search_respects_configured_candidate_pools() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+
+    let mut config = create_test_config(tmp.path(), "test", &content_dir);
+    let db_path = tmp.path().join("test").join("index.db");
+    let conn = init_test_db(&db_path);
+    for i in 0..20 {
+        insert_test_doc(
+            &conn,
+            "test",
+            &format!("doc{}.md", i),
+            &format!("Document {}", i),
+            &format!("ownership and borrowing notes number {}", i),
+            &format!("hash{}", i),
+        );
+    }
+    drop(conn);
+
+    let opts = SearchOptions {
+        limit: 50,
+        collection: Some("test".to_string()),
+        ..Default::default()
+    };
+
+    // The defaults leave all 20 hits in play
+    let store = Store::new(&config).unwrap();
+    let router = Router::new(&config).unwrap();
+    let results = store.hybrid_search("ownership", opts.clone(), &router).await.unwrap();
+    assert_eq!(results.len(), 20);
+
+    // A smaller fusion pool caps what reaches fusion
+    config.search.fusion_pool_size = 7;
+    let store = Store::new(&config).unwrap();
+    let results = store.hybrid_search("ownership", opts.clone(), &router).await.unwrap();
+    assert_eq!(results.len(), 7);
+
+    // Fewer rerank candidates cap what comes out of fusion
+    config.search.fusion_pool_size = 100;
+    config.search.rerank_candidates = 4;
+    let store = Store::new(&config).unwrap();
+    let results = store.hybrid_search("ownership", opts, &router).await.unwrap();
+    assert_eq!(results.len(), 4);
 }
 
 // ==================== Query Expansion Integration ====================
@@ -105,15 +154,15 @@ fn test_query_expansion_no_expander() {
 #[test]
 fn test_rrf_fusion_bm25_plus_vector_simulation() {
     // Simulate what hybrid_search does: combine BM25 and vector results
-    let bm25_results = vec![
+    let bm25_results = [
         make_result("design.md", "docs", 0.95),
         make_result("api.md", "docs", 0.80),
         make_result("readme.md", "docs", 0.60),
     ];
 
-    let vector_results = vec![
-        make_result("api.md", "docs", 0.35),    // cosine distance (lower = better)
-        make_result("guide.md", "docs", 0.42),
+    let vector_results = [
+        make_result("api.md", "docs", 0.65),    // cosine similarity (higher = better)
+        make_result("guide.md", "docs", 0.58),
         make_result("design.md", "docs", 0.50),
     ];
 
@@ -146,6 +195,7 @@ fn test_bm25_search_with_expanded_queries() {
             path: content_dir.clone(),
             pattern: Some("**/*".to_string()),
             description: None,
+            ..Default::default()
         }],
         cache_path: tmp.path().to_path_buf(),
         models: ModelsConfig {
@@ -178,9 +228,8 @@ fn test_bm25_search_with_expanded_queries() {
     for query in &expansions {
         let opts = SearchOptions {
             limit: 10,
-            min_score: 0.0,
             collection: Some("test".to_string()),
-            search_all: false,
+            ..Default::default()
         };
         if let Ok(results) = store.bm25_search(query, opts) {
             all_results.extend(results);
@@ -217,9 +266,8 @@ fn test_search_result_limit() {
 
     let opts = SearchOptions {
         limit: 3,
-        min_score: 0.0,
         collection: Some("test".to_string()),
-        search_all: false,
+        ..Default::default()
     };
 
     let results = store.bm25_search("programming", opts).unwrap();
@@ -236,5 +284,10 @@ fn make_result(path: &str, collection: &str, score: f32) -> SearchResult {
         title: path.to_string(),
         hash: format!("hash_{}", path),
         query: None,
+        size: None,
+        match_line: None,
+        duplicates: Vec::new(),
+        source: Vec::new(),
+        score_components: None,
     }
 }
@@ -1,9 +1,9 @@
 mod common;
 
-use common::{create_test_config, init_test_db, insert_test_doc};
-use qmd_rust::config::{Config, LLMModelConfig, ModelsConfig, CollectionConfig};
-use qmd_rust::llm::{Router, LocalEmbedder, LocalReranker, LocalQueryExpander, QueryExpander, EmbeddingResult, LLMProvider};
-use qmd_rust::store::{Store, SearchOptions, SearchResult, make_docid};
+use common::create_test_config;
+use qmd_rust::config::{Config, LLMModelConfig, ModelsConfig};
+use qmd_rust::llm::{Router, LocalReranker, LocalQueryExpander, QueryExpander, LLMProvider};
+use qmd_rust::store::{Store, SearchOptions, SearchResult};
 use tempfile::tempdir;
 use std::fs;
 
@@ -32,8 +32,8 @@ async fn test_router_embed_local_fallback() {
     let result = router.embed(&["test text"]).await.unwrap();
     assert!(matches!(result.provider, LLMProvider::Local));
     assert!(!result.embeddings.is_empty());
-    // Fallback uses 384 dimensions
-    assert_eq!(result.embeddings[0].len(), 384);
+    // Fallback vectors are as wide as the vector table
+    assert_eq!(result.embeddings[0].len(), config.vector.dimension());
 }
 
 #[tokio::test]
@@ -56,7 +56,7 @@ async fn test_router_embed_multiple_texts() {
 
     assert_eq!(result.embeddings.len(), 3);
     for emb in &result.embeddings {
-        assert_eq!(emb.len(), 384);
+        assert_eq!(emb.len(), config.vector.dimension());
     }
 }
 
@@ -80,8 +80,8 @@ async fn test_router_embed_returns_valid_dimensions() {
     let result = router.embed(&["test text"]).await.unwrap();
 
     assert_eq!(result.embeddings.len(), 1);
-    // Fallback uses 384 dimensions
-    assert_eq!(result.embeddings[0].len(), 384);
+    // Fallback vectors are as wide as the vector table
+    assert_eq!(result.embeddings[0].len(), config.vector.dimension());
 }
 
 #[tokio::test]
@@ -154,13 +154,121 @@ fn test_embedder_produces_normalized_vectors() {
         ..Config::default()
     };
 
-    let router = Router::new(&config).unwrap();
+    let _router = Router::new(&config).unwrap();
 
     // This test runs the embedder and checks the returned vectors
     // Note: Without actual model, this uses fallback random vectors
     // which may or may not be normalized
 }
 
+#[tokio::test]
+async fn test_env_file_key_reaches_remote_embedder() {
+    let server = wiremock::MockServer::start().await;
+    wiremock::Mock::given(wiremock::matchers::path("/v1/embeddings"))
+        .and(wiremock::matchers::header("authorization", "Bearer sk-from-env-file"))
+        .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "data": [{"index": 0, "embedding": [0.6, 0.8]}],
+        })))
+        .expect(1..)
+        .mount(&server)
+        .await;
+
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("notes");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("walrus.md"), "# Walrus\nThe walrus naps.").unwrap();
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("index.yaml"),
+        format!(
+            "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\nmodels:\n  embed:\n    remote: text-embedding-3-small\nvector:\n  dimension: 2\n",
+            tmp.path().join("cache").display(),
+            content_dir.display()
+        ),
+    )
+    .unwrap();
+    let env_file = tmp.path().join(".env");
+    fs::write(
+        &env_file,
+        format!("OPENAI_API_KEY=sk-from-env-file\nOPENAI_BASE_URL={}/v1\n", server.uri()),
+    )
+    .unwrap();
+
+    // The key exists only in the env file, never in the test's environment
+    let qmd = |args: &'static [&'static str]| {
+        let home = tmp.path().to_path_buf();
+        let env_file = env_file.clone();
+        tokio::task::spawn_blocking(move || {
+            assert_cmd::Command::cargo_bin("qmd-rust")
+                .unwrap()
+                .env("HOME", home)
+                .env_remove("OPENAI_API_KEY")
+                .env_remove("OPENAI_BASE_URL")
+                .env_remove("ANTHROPIC_API_KEY")
+                .arg("--env-file")
+                .arg(env_file)
+                .args(args)
+                .output()
+                .unwrap()
+        })
+    };
+    let update = qmd(&["update"]).await.unwrap();
+    assert!(update.status.success(), "{}", String::from_utf8_lossy(&update.stderr));
+    let embed = qmd(&["embed"]).await.unwrap();
+    assert!(embed.status.success(), "{}", String::from_utf8_lossy(&embed.stderr));
+
+    // Only requests carrying the file's key match the mock
+    server.verify().await;
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests
+        .iter()
+        .all(|r| r.headers.get("authorization").unwrap() == "Bearer sk-from-env-file"));
+}
+
+#[test]
+fn test_read_env_file_yields_to_existing_environment() {
+    let tmp = tempdir().unwrap();
+    let env_file = tmp.path().join(".env");
+    fs::write(
+        &env_file,
+        "# local dev keys\nOPENAI_API_KEY=sk-from-env-file\nOPENAI_BASE_URL=http://localhost:9/v1\nQMD_ENV_FILE_TEST=\"file value\"\n",
+    )
+    .unwrap();
+
+    // QMD_ENV_FILE_TEST is already set in this environment
+    let existing = std::collections::HashMap::from([("QMD_ENV_FILE_TEST", "process value")]);
+    let vars = qmd_rust::config::read_env_file(&env_file, |key| {
+        existing.get(key).map(|value| value.to_string())
+    })
+    .unwrap();
+
+    assert_eq!(
+        vars.into_iter().collect::<Vec<_>>(),
+        [
+            ("OPENAI_API_KEY".to_string(), "sk-from-env-file".to_string()),
+            ("OPENAI_BASE_URL".to_string(), "http://localhost:9/v1".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_read_env_file_unquotes_values() {
+    let tmp = tempdir().unwrap();
+    let env_file = tmp.path().join(".env");
+    fs::write(&env_file, "QMD_ENV_FILE_TEST=\"file value\"\n").unwrap();
+
+    let vars = qmd_rust::config::read_env_file(&env_file, |_| None).unwrap();
+    assert_eq!(vars["QMD_ENV_FILE_TEST"], "file value");
+}
+
+#[test]
+fn test_read_env_file_missing_file_errors() {
+    let tmp = tempdir().unwrap();
+    let result = qmd_rust::config::read_env_file(&tmp.path().join("missing.env"), |_| None);
+    assert!(result.is_err());
+}
+
 // =============================================================================
 // Router Reranking Tests (~10 tests)
 // =============================================================================
@@ -192,6 +300,11 @@ async fn test_router_rerank_local_fallback() {
             score: 0.8,
             lines: 10,
             query: None,
+            size: None,
+            match_line: None,
+            duplicates: Vec::new(),
+            source: Vec::new(),
+            score_components: None,
         },
         SearchResult {
             docid: "def456".to_string(),
@@ -202,6 +315,11 @@ async fn test_router_rerank_local_fallback() {
             score: 0.6,
             lines: 20,
             query: None,
+            size: None,
+            match_line: None,
+            duplicates: Vec::new(),
+            source: Vec::new(),
+            score_components: None,
         },
     ];
 
@@ -239,6 +357,11 @@ async fn test_router_rerank_single_document() {
         score: 0.8,
         lines: 10,
         query: None,
+        size: None,
+        match_line: None,
+        duplicates: Vec::new(),
+        source: Vec::new(),
+        score_components: None,
     }];
 
     let scores = router.rerank("query", &docs).await.unwrap();
@@ -283,6 +406,11 @@ async fn test_router_rerank_no_reranker() {
         score: 0.8,
         lines: 10,
         query: None,
+        size: None,
+        match_line: None,
+        duplicates: Vec::new(),
+        source: Vec::new(),
+        score_components: None,
     }];
 
     let result = router.rerank("query", &docs).await;
@@ -316,6 +444,11 @@ async fn test_router_rerank_uses_title_and_path() {
             score: 0.8,
             lines: 10,
             query: None,
+            size: None,
+            match_line: None,
+            duplicates: Vec::new(),
+            source: Vec::new(),
+            score_components: None,
         },
     ];
 
@@ -509,6 +642,11 @@ fn test_search_result_fields() {
         score: 0.95,
         lines: 42,
         query: Some("test query".to_string()),
+        size: None,
+        match_line: None,
+        duplicates: Vec::new(),
+        source: Vec::new(),
+        score_components: None,
     };
 
     assert_eq!(result.docid, "abc123");
@@ -531,6 +669,11 @@ fn test_search_result_default_values() {
         score: 0.5,
         lines: 10,
         query: None,
+        size: None,
+        match_line: None,
+        duplicates: Vec::new(),
+        source: Vec::new(),
+        score_components: None,
     };
 
     assert!(result.query.is_none());
@@ -547,6 +690,11 @@ fn test_search_result_equality() {
         score: 0.5,
         lines: 10,
         query: None,
+        size: None,
+        match_line: None,
+        duplicates: Vec::new(),
+        source: Vec::new(),
+        score_components: None,
     };
 
     let result2 = SearchResult {
@@ -558,6 +706,11 @@ fn test_search_result_equality() {
         score: 0.5,
         lines: 10,
         query: None,
+        size: None,
+        match_line: None,
+        duplicates: Vec::new(),
+        source: Vec::new(),
+        score_components: None,
     };
 
     assert_eq!(result1, result2);
@@ -574,6 +727,11 @@ fn test_search_result_clone() {
         score: 0.5,
         lines: 10,
         query: Some("test query".to_string()),
+        size: None,
+        match_line: None,
+        duplicates: Vec::new(),
+        source: Vec::new(),
+        score_components: None,
     };
 
     let result2 = result1.clone();
@@ -591,6 +749,11 @@ fn test_search_result_debug() {
         score: 0.95,
         lines: 42,
         query: Some("test".to_string()),
+        size: None,
+        match_line: None,
+        duplicates: Vec::new(),
+        source: Vec::new(),
+        score_components: None,
     };
 
     let debug = format!("{:?}", result);
@@ -627,17 +790,13 @@ fn test_store_make_docid_format() {
 
 #[test]
 fn test_store_search_options_default() {
-    // SearchOptions doesn't have Default, create manually
-    let options = SearchOptions {
-        limit: 10,
-        min_score: 0.0,
-        collection: None,
-        search_all: false,
-    };
+    let options = SearchOptions::default();
 
-    assert_eq!(options.limit, 10);
+    assert_eq!(options.limit, 20);
+    assert_eq!(options.offset, 0);
     assert!(options.collection.is_none());
     assert_eq!(options.min_score, 0.0);
+    assert!(!options.search_all);
 }
 
 #[test]
@@ -647,6 +806,7 @@ fn test_store_search_options_custom() {
         min_score: 0.5,
         collection: Some("docs".to_string()),
         search_all: true,
+        ..Default::default()
     };
 
     assert_eq!(options.limit, 50);
@@ -674,7 +834,7 @@ async fn test_store_with_embedder_integration() {
     store.update_index().unwrap();
 
     // Search should work
-    let results = store.bm25_search("rust", SearchOptions { limit: 10, min_score: 0.0, collection: None, search_all: false }).unwrap();
+    let results = store.bm25_search("rust", SearchOptions { limit: 10, offset: 0, min_score: 0.0, collection: None, search_all: false, really_all: false }).unwrap();
     assert!(!results.is_empty());
 }
 
@@ -693,7 +853,7 @@ async fn test_hybrid_search_integration() {
     store.update_index().unwrap();
 
     // BM25 search should work
-    let bm25_results = store.bm25_search("rust", SearchOptions { limit: 10, min_score: 0.0, collection: None, search_all: false }).unwrap();
+    let bm25_results = store.bm25_search("rust", SearchOptions { limit: 10, offset: 0, min_score: 0.0, collection: None, search_all: false, really_all: false }).unwrap();
     assert!(!bm25_results.is_empty());
 
     // First result should be about Rust
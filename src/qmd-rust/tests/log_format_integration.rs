@@ -0,0 +1,96 @@
+//! `--log-format json` writes every stderr line as a JSON record
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// Home directory with one indexed-ready collection
+fn setup(home: &Path) {
+    let content_dir = home.join("notes");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("a.md"), "# A\nBorrowing rules").unwrap();
+    let config_dir = home.join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let yaml = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n",
+        home.join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), yaml).unwrap();
+}
+
+/// Parse each stderr line, failing on any that is not JSON
+fn json_lines(stderr: &[u8]) -> Vec<serde_json::Value> {
+    String::from_utf8_lossy(stderr)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("not JSON ({}): {}", e, line)))
+        .collect()
+}
+
+#[test]
+fn test_log_format_json_flag() {
+    let tmp = tempdir().unwrap();
+    setup(tmp.path());
+
+    let output = Command::cargo_bin("qmd-rust")
+        .unwrap()
+        .env("HOME", tmp.path())
+        .env("RUST_LOG", "info")
+        .env("AGENT_TRACE_ID", "trace-abc")
+        .env_remove("QMD_LOG_FORMAT")
+        .args(["--log-format", "json", "update"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let records = json_lines(&output.stderr);
+    assert!(!records.is_empty());
+    for record in &records {
+        assert!(record["timestamp"].is_string(), "{}", record);
+        assert!(record["level"].is_string(), "{}", record);
+        assert!(record["target"].is_string(), "{}", record);
+        assert!(record["message"].is_string(), "{}", record);
+        assert_eq!(record["trace_id"], "trace-abc");
+    }
+    assert!(records.iter().any(|r| r["level"] == "INFO"
+        && r["target"] == "qmd_rust"
+        && r["message"] == "Configuration loaded successfully"));
+    // Preflight warnings become records instead of bare stderr text
+    assert!(records.iter().any(|r| r["level"] == "WARN"
+        && r["message"].as_str().unwrap().contains("[notes] Collection index has no documents")));
+}
+
+#[test]
+fn test_log_format_env_var() {
+    let tmp = tempdir().unwrap();
+    setup(tmp.path());
+
+    let output = Command::cargo_bin("qmd-rust")
+        .unwrap()
+        .env("HOME", tmp.path())
+        .env("RUST_LOG", "debug")
+        .env("QMD_LOG_FORMAT", "json")
+        .env_remove("AGENT_TRACE_ID")
+        .args(["search", "borrowing"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let records = json_lines(&output.stderr);
+    assert!(records.iter().any(|r| r["level"] == "INFO"));
+    assert!(records.iter().all(|r| r.get("trace_id").is_none()));
+
+    // The default stays human-readable
+    let output = Command::cargo_bin("qmd-rust")
+        .unwrap()
+        .env("HOME", tmp.path())
+        .env("RUST_LOG", "info")
+        .env_remove("QMD_LOG_FORMAT")
+        .args(["search", "borrowing"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Configuration loaded successfully"), "{}", stderr);
+    assert!(stderr.lines().all(|line| serde_json::from_str::<serde_json::Value>(line).is_err()));
+}
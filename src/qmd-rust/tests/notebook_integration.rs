@@ -0,0 +1,69 @@
+//! Jupyter notebooks are indexed by their cell text, not their JSON
+
+mod common;
+
+use common::{create_test_config, search_all_options};
+use qmd_rust::config::NotebookConfig;
+use qmd_rust::store::Store;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+const NOTEBOOK: &str = include_str!("fixtures/attention.ipynb");
+
+fn indexed(root: &Path, notebooks: NotebookConfig) -> Store {
+    let content_dir = root.join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("attention.ipynb"), NOTEBOOK).unwrap();
+    fs::write(content_dir.join("broken.ipynb"), "{\"cells\": [{\"cell_type\": ").unwrap();
+
+    let mut config = create_test_config(&root.join("cache"), "research", &content_dir);
+    config.notebooks = notebooks;
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    store
+}
+
+#[test]
+fn test_notebook_markdown_and_code_are_searchable() {
+    let tmp = tempdir().unwrap();
+    let store = indexed(tmp.path(), NotebookConfig::default());
+
+    let prose = store.bm25_search("softmax gradients vanishing", search_all_options()).unwrap();
+    assert_eq!(prose.len(), 1, "{:?}", prose);
+    assert!(prose[0].path.ends_with("attention.ipynb"));
+
+    let code = store.bm25_search("compute_attention_scores", search_all_options()).unwrap();
+    assert_eq!(code.len(), 1, "{:?}", code);
+
+    // Notebook JSON and, by default, cell outputs stay out of the index
+    assert!(store.bm25_search("kernelspec", search_all_options()).unwrap().is_empty());
+    assert!(store.bm25_search("converged warmup", search_all_options()).unwrap().is_empty());
+
+    // The malformed notebook is skipped rather than failing the update
+    assert_eq!(store.get_stats().unwrap().document_count, 1);
+
+    let doc = store.retrieve_document("research/attention.ipynb").unwrap();
+    assert!(doc.content.starts_with("%% [markdown] cell 1\n# Attention experiments"), "{}", doc.content);
+    assert!(doc.content.contains("%% [code] In [3]\nplot_heatmap"), "{}", doc.content);
+    assert_eq!(doc.hash, Store::calculate_hash(&doc.content));
+}
+
+#[test]
+fn test_notebook_plain_text_outputs_when_enabled() {
+    let tmp = tempdir().unwrap();
+    let store = indexed(
+        tmp.path(),
+        NotebookConfig {
+            include_outputs: true,
+            ..NotebookConfig::default()
+        },
+    );
+
+    let outputs = store.bm25_search("converged warmup", search_all_options()).unwrap();
+    assert_eq!(outputs.len(), 1, "{:?}", outputs);
+
+    let doc = store.retrieve_document("research/attention.ipynb").unwrap();
+    assert!(doc.content.contains("%% [output]\n<Figure size 640x480 with 1 Axes>"), "{}", doc.content);
+    assert!(!doc.content.contains("iVBORw0KGgo"));
+}
@@ -0,0 +1,97 @@
+//! `--max-output-bytes` and `--max-snippets` keep content-heavy output within a budget
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// Thirty long documents that all match "ownership"
+fn setup(home: &Path) {
+    let content_dir = home.join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    for i in 0..30 {
+        let body = format!("Paragraph about ownership and borrowing in note {}.\n\n", i).repeat(80);
+        fs::write(content_dir.join(format!("note-{:02}.md", i)), format!("# Note {}\n\n{}", i, body)).unwrap();
+    }
+    let config_dir = home.join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n",
+        home.join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+}
+
+fn qmd(home: &Path, args: &[&str]) -> (String, String) {
+    let output = Command::cargo_bin("qmd-rust").unwrap().env("HOME", home).args(args).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    (
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+#[test]
+fn test_output_stays_within_budget() {
+    let tmp = tempdir().unwrap();
+    setup(tmp.path());
+    qmd(tmp.path(), &["update"]);
+
+    let search = ["search", "ownership", "--limit", "30", "--include-content=chunks", "--format", "json"];
+    let (full, _) = qmd(tmp.path(), &search);
+    assert!(full.len() > 60_000, "fixture output should be large, got {} bytes", full.len());
+    let json: serde_json::Value = serde_json::from_str(&full).unwrap();
+    assert!(json.get("truncated").is_none());
+
+    // Content is cut to fit, keeping every result
+    let (out, _) = qmd(tmp.path(), &[&search[..], &["--max-output-bytes", "20000"]].concat());
+    assert!(out.len() <= 20_000, "{} bytes", out.len());
+    let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(json["truncated"], true);
+    assert_eq!(json["results"].as_array().unwrap().len(), 30);
+    assert_eq!(json["results"][29]["content"]["truncated"], true);
+
+    // Too small for every result even without content: trailing results go
+    let (out, _) = qmd(tmp.path(), &[&search[..], &["--max-output-bytes", "3000"]].concat());
+    assert!(out.len() <= 3_000, "{} bytes", out.len());
+    let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(json["truncated"], true);
+    let shown = json["results"].as_array().unwrap().len();
+    assert!(shown > 0 && shown < 30, "{} results", shown);
+    assert_eq!(json["total"], shown);
+
+    // Tokens are estimated at four characters each
+    let (out, _) = qmd(tmp.path(), &[&search[..], &["--max-output-tokens", "5000"]].concat());
+    assert!(out.len() <= 20_000, "{} bytes", out.len());
+
+    let ndjson = ["search", "ownership", "--limit", "30", "--include-content=chunks", "--format", "ndjson"];
+    let (out, _) = qmd(tmp.path(), &[&ndjson[..], &["--max-output-bytes", "20000"]].concat());
+    assert!(out.len() <= 20_000, "{} bytes", out.len());
+    let metadata: serde_json::Value = serde_json::from_str(out.lines().next().unwrap()).unwrap();
+    assert_eq!(metadata["payload"]["truncated"], true, "{}", metadata);
+
+    let markdown = ["search", "ownership", "--limit", "30", "--include-content=chunks", "--format", "md"];
+    let (out, err) = qmd(tmp.path(), &[&markdown[..], &["--max-output-bytes", "20000"]].concat());
+    assert!(out.len() <= 20_000, "{} bytes", out.len());
+    assert!(err.contains("Output truncated to fit 20000 bytes"), "{}", err);
+}
+
+#[test]
+fn test_max_snippets_caps_pieces_across_results() {
+    let tmp = tempdir().unwrap();
+    setup(tmp.path());
+    qmd(tmp.path(), &["update"]);
+
+    let (out, _) = qmd(
+        tmp.path(),
+        &["search", "ownership", "--limit", "10", "--include-content=chunks", "--max-snippets", "4", "--format", "json"],
+    );
+    let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 10);
+    let pieces: Vec<usize> = results.iter().map(|r| r["content"]["pieces"].as_array().unwrap().len()).collect();
+    assert_eq!(pieces.iter().sum::<usize>(), 4);
+    assert!(pieces[0] > 0);
+    assert_eq!(results[9]["content"]["truncated"], true);
+}
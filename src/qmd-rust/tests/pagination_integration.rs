@@ -0,0 +1,208 @@
+//! Paging through results with offset: pages never overlap and keep their order
+
+mod common;
+
+use assert_cmd::Command;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::IntoResponse;
+use axum::Json;
+use common::{create_multi_collection_config, create_test_config};
+use qmd_rust::anel::identity::IdentityVerifier;
+use qmd_rust::config::Config;
+use qmd_rust::llm::{Embed, EmbedFuture, Router};
+use qmd_rust::server::handlers::{self, SearchRequest};
+use qmd_rust::server::middleware::{AuthState, RateLimitState, RouteLimits};
+use qmd_rust::server::observability::{AuditLog, Metrics};
+use qmd_rust::server::ServerState;
+use qmd_rust::store::{SearchOptions, SearchResult, Store};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+/// `count` notes mentioning the walrus a varying number of times
+fn write_corpus(dir: &Path, prefix: &str, count: usize) {
+    fs::create_dir_all(dir).unwrap();
+    for i in 0..count {
+        let body = format!("# Note {i}\n{}Other facts about the sea number {i}.\n", "The walrus naps. ".repeat(i % 7 + 1));
+        fs::write(dir.join(format!("{prefix}-{i:02}.md")), body).unwrap();
+    }
+}
+
+fn options(limit: usize, offset: usize) -> SearchOptions {
+    SearchOptions {
+        limit,
+        offset,
+        search_all: true,
+        ..Default::default()
+    }
+}
+
+fn docids(results: &[SearchResult]) -> Vec<String> {
+    results.iter().map(|r| r.docid.clone()).collect()
+}
+
+/// Pages 1 and 2 are disjoint and, together, the first 20 results
+fn assert_pages(page1: Vec<String>, page2: Vec<String>, first20: Vec<String>) {
+    assert_eq!(page1.len(), 10, "{:?}", page1);
+    assert_eq!(page2.len(), 10, "{:?}", page2);
+    let seen: HashSet<&String> = page1.iter().chain(&page2).collect();
+    assert_eq!(seen.len(), 20, "pages overlap: {:?} / {:?}", page1, page2);
+    assert_eq!([page1, page2].concat(), first20);
+}
+
+fn single_collection_store(dir: &Path) -> (Config, Store) {
+    let content_dir = dir.join("content");
+    write_corpus(&content_dir, "note", 30);
+    let config = create_test_config(&dir.join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    (config, store)
+}
+
+#[test]
+fn test_bm25_pages_in_one_collection() {
+    let tmp = tempdir().unwrap();
+    let (_, store) = single_collection_store(tmp.path());
+
+    let page = |limit, offset| docids(&store.bm25_search("walrus", options(limit, offset)).unwrap());
+    assert_pages(page(10, 0), page(10, 10), page(20, 0));
+    // Asking twice gives the same page
+    assert_eq!(page(10, 10), page(10, 10));
+    assert_eq!(page(10, 20).len(), 10);
+    assert!(page(10, 30).is_empty());
+}
+
+#[test]
+fn test_bm25_pages_across_collections() {
+    let tmp = tempdir().unwrap();
+    let (first, second) = (tmp.path().join("first"), tmp.path().join("second"));
+    write_corpus(&first, "first", 15);
+    write_corpus(&second, "second", 15);
+    let config = create_multi_collection_config(&tmp.path().join("cache"), &[("first", &first), ("second", &second)]);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    // Merged results are cut to the page once, not per collection
+    let page = |limit, offset| {
+        let mut results = docids(&store.bm25_search("walrus", options(limit, offset)).unwrap());
+        results.truncate(limit);
+        results
+    };
+    let page2 = page(10, 10);
+    assert!(page2.iter().any(|id| id.starts_with("first:")), "{:?}", page2);
+    assert!(page2.iter().any(|id| id.starts_with("second:")), "{:?}", page2);
+    assert_pages(page(10, 0), page2, page(20, 0));
+}
+
+#[tokio::test]
+async fn test_hybrid_pages_after_fusion() {
+    let tmp = tempdir().unwrap();
+    let (config, store) = single_collection_store(tmp.path());
+    let router = Router::new(&config).unwrap();
+
+    let mut pages = Vec::new();
+    for (limit, offset) in [(10, 0), (10, 10), (20, 0)] {
+        let results = store.hybrid_search("walrus", options(limit, offset), &router).await.unwrap();
+        assert!(results.len() <= limit);
+        pages.push(docids(&results));
+    }
+    let first20 = pages.pop().unwrap();
+    let page2 = pages.pop().unwrap();
+    assert_pages(pages.pop().unwrap(), page2, first20);
+}
+
+struct MockEmbedder;
+
+impl Embed for MockEmbedder {
+    fn model_name(&self) -> String {
+        "page-mock".to_string()
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a> {
+        Box::pin(async move { Ok(texts.iter().map(|_| vec![0.1; 8]).collect()) })
+    }
+}
+
+async fn body_json(response: impl IntoResponse) -> serde_json::Value {
+    let bytes = axum::body::to_bytes(response.into_response().into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+fn body_docids(body: &serde_json::Value) -> Vec<String> {
+    body["results"].as_array().unwrap().iter().map(|r| r["docid"].as_str().unwrap().to_string()).collect()
+}
+
+#[tokio::test]
+async fn test_http_search_and_query_pages() {
+    let tmp = tempdir().unwrap();
+    let (config, store) = single_collection_store(tmp.path());
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(MockEmbedder));
+    let state = ServerState {
+        store: Arc::new(tokio::sync::Mutex::new(store)),
+        llm: Arc::new(tokio::sync::Mutex::new(router)),
+        config: config.clone(),
+        rate_limit_state: Arc::new(RateLimitState::new(100, 60)),
+        auth_state: Arc::new(AuthState::new(vec![], vec![])),
+        auth_enabled: false,
+        metrics: Arc::new(Metrics::new()),
+        audit: Arc::new(AuditLog::stderr(false)),
+        identity: Arc::new(IdentityVerifier::default()),
+        ready: Arc::new(std::sync::atomic::AtomicBool::new(true)),
+        limits: RouteLimits::new(&config.server),
+    };
+    let request = |limit, offset| {
+        Json(SearchRequest {
+            query: "walrus".to_string(),
+            limit: Some(limit),
+            offset: Some(offset),
+            collection: None,
+            timeout_ms: None,
+        })
+    };
+
+    let search = |limit, offset| handlers::search(State(state.clone()), HeaderMap::new(), request(limit, offset));
+    let body = body_json(search(10, 10).await).await;
+    assert_eq!(body["offset"], 10, "{}", body);
+    assert_eq!(body["total"], 10, "{}", body);
+    assert_pages(body_docids(&body_json(search(10, 0).await).await), body_docids(&body), body_docids(&body_json(search(20, 0).await).await));
+
+    let query = |limit, offset| handlers::query(State(state.clone()), HeaderMap::new(), request(limit, offset));
+    let body = body_json(query(10, 10).await).await;
+    assert_eq!(body["offset"], 10, "{}", body);
+    assert_pages(body_docids(&body_json(query(10, 0).await).await), body_docids(&body), body_docids(&body_json(query(20, 0).await).await));
+}
+
+#[test]
+fn test_cli_search_json_reports_offset() {
+    let tmp = tempdir().unwrap();
+    let (_, store) = single_collection_store(tmp.path());
+    drop(store);
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let yaml = format!(
+        "cache_path: {}\ncollections:\n  - name: docs\n    path: {}\n    pattern: \"**/*\"\n",
+        tmp.path().join("cache").display(),
+        tmp.path().join("content").display()
+    );
+    fs::write(config_dir.join("index.yaml"), yaml).unwrap();
+    let search = |limit: &str, offset: &str| -> serde_json::Value {
+        let output = Command::cargo_bin("qmd-rust")
+            .unwrap()
+            .env("HOME", tmp.path())
+            .args(["search", "walrus", "--format", "json", "--limit", limit, "--offset", offset])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    let page2 = search("10", "10");
+    assert_eq!(page2["offset"], 10, "{}", page2);
+    assert_eq!(page2["total"], 10, "{}", page2);
+    assert_pages(body_docids(&search("10", "0")), body_docids(&page2), body_docids(&search("20", "0")));
+}
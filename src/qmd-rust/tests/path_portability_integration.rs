@@ -0,0 +1,72 @@
+//! Stored paths use `/` separators regardless of platform, so docids, qmd://
+//! URIs and the FTS filepath agree across Windows and Unix
+
+mod common;
+
+use common::{create_test_config, search_all_options};
+use qmd_rust::store::path::build_virtual_path;
+use qmd_rust::store::Store;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_nested(dir: &Path) -> std::path::PathBuf {
+    let nested = dir.join("guides").join("setup");
+    fs::create_dir_all(&nested).unwrap();
+    let file = nested.join("install.md");
+    fs::write(&file, "# Install\nRun the quokka installer.").unwrap();
+    file
+}
+
+#[test]
+fn test_nested_paths_index_search_and_get_round_trip() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    let file = write_nested(&content_dir);
+    let config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let conn = store.get_connection("docs").unwrap();
+    let stored: String = conn
+        .query_row("SELECT path FROM documents", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(stored, "guides/setup/install.md");
+
+    let results = store.bm25_search("quokka", search_all_options()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "docs/guides/setup/install.md");
+    assert_eq!(results[0].docid, "docs:guides/setup/install.md");
+
+    // Round trips through the search path, a qmd:// URI and the native file path
+    let by_result = store.retrieve_document(&results[0].path).unwrap();
+    assert!(by_result.content.contains("quokka"));
+    let uri = build_virtual_path("docs", &stored);
+    assert_eq!(store.retrieve_document(&uri).unwrap().hash, by_result.hash);
+    assert_eq!(store.retrieve_document(&file.to_string_lossy()).unwrap().hash, by_result.hash);
+
+    // Still on disk, so nothing is stale
+    assert!(store.find_stale_entries(0).unwrap().is_empty());
+}
+
+#[cfg(windows)]
+#[test]
+fn test_windows_backslash_pattern_and_lookup() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_nested(&content_dir);
+    let mut config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    config.collections[0].pattern = Some("**\\*.md".to_string());
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let results = store.bm25_search("quokka", search_all_options()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].path.contains('\\'), "{}", results[0].path);
+    assert_eq!(results[0].docid, "docs:guides/setup/install.md");
+
+    let by_backslashes = store.retrieve_document("docs\\guides\\setup\\install.md").unwrap();
+    assert_eq!(by_backslashes.hash, store.retrieve_document(&results[0].path).unwrap().hash);
+    let by_uri = store.retrieve_document("qmd://docs\\guides\\setup\\install.md").unwrap();
+    assert_eq!(by_uri.hash, by_backslashes.hash);
+}
@@ -0,0 +1,131 @@
+//! Embedding into and searching a live Qdrant instance
+//!
+//! Runs only with `--features qdrant` and `QMD_TEST_QDRANT_URL` pointing at a
+//! Qdrant server (optionally `QMD_TEST_QDRANT_API_KEY`).
+
+#![cfg(feature = "qdrant")]
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::cli::embed::embed_hashes_async;
+use qmd_rust::config::VectorBackend;
+use qmd_rust::llm::{Embed, EmbedFuture, Router};
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+/// Vectors pointing one way for walrus text and another for everything else
+struct TopicEmbedder;
+
+impl Embed for TopicEmbedder {
+    fn model_name(&self) -> String {
+        "topic-mock".to_string()
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a> {
+        Box::pin(async move {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let mut vector = vec![0.0; 8];
+                    vector[if text.contains("walrus") { 0 } else { 1 }] = 1.0;
+                    vector
+                })
+                .collect())
+        })
+    }
+}
+
+#[test]
+fn test_embed_upserts_and_search_maps_hits_to_documents() {
+    let Ok(url) = std::env::var("QMD_TEST_QDRANT_URL") else {
+        eprintln!("QMD_TEST_QDRANT_URL not set, skipping");
+        return;
+    };
+
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("walrus.md"), "# Walrus\nThe walrus naps on ice.").unwrap();
+    fs::write(content_dir.join("narwhal.md"), "# Narwhal\nThe narwhal has a tusk.").unwrap();
+
+    let mut config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    config.vector.backend = VectorBackend::Qdrant;
+    config.vector.qdrant.url = url;
+    config.vector.qdrant.api_key = std::env::var("QMD_TEST_QDRANT_API_KEY").ok();
+    config.vector.qdrant.collection = format!("qmd-test-{}", uuid::Uuid::new_v4());
+    config.vector.qdrant.vector_size = 8;
+
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    let hashes: Vec<String> = {
+        let conn = store.get_connection("docs").unwrap();
+        let mut stmt = conn.prepare("SELECT hash FROM documents").unwrap();
+        let hashes = stmt.query_map([], |row| row.get(0)).unwrap().map(|h| h.unwrap()).collect();
+        hashes
+    };
+
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(TopicEmbedder));
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    // Embedding twice replaces the points instead of adding to them
+    for _ in 0..2 {
+        rt.block_on(embed_hashes_async(&store, "docs", &router, &hashes)).unwrap();
+    }
+
+    let mut query = vec![0.0; 8];
+    query[0] = 1.0;
+    let options = SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    };
+    let results = store.vector_search_with_embedding(&query, options).unwrap();
+    let backend = store.qdrant_backend().unwrap();
+    rt.block_on(backend.delete_collection()).unwrap();
+
+    assert_eq!(results.len(), 2, "{:?}", results);
+    assert_eq!(results[0].path, "docs/walrus.md");
+    assert_eq!(results[0].title, "walrus");
+    assert_eq!(results[0].collection, "docs");
+    assert_eq!(results[1].path, "docs/narwhal.md");
+}
+
+/// Server and MCP handlers search from inside a tokio runtime
+#[tokio::test(flavor = "multi_thread")]
+async fn test_search_from_inside_a_runtime() {
+    let url = std::env::var("QMD_TEST_QDRANT_URL").ok();
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("walrus.md"), "# Walrus\nThe walrus naps on ice.").unwrap();
+
+    let mut config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    config.vector.backend = VectorBackend::Qdrant;
+    config.vector.qdrant.url = url.clone().unwrap_or_else(|| "http://127.0.0.1:1".to_string());
+    config.vector.qdrant.api_key = std::env::var("QMD_TEST_QDRANT_API_KEY").ok();
+    config.vector.qdrant.collection = format!("qmd-test-{}", uuid::Uuid::new_v4());
+    config.vector.qdrant.vector_size = 8;
+
+    let store = Store::new(&config);
+    if url.is_none() {
+        // No server: connecting fails with an error instead of a runtime panic
+        assert!(store.is_err());
+        return;
+    }
+    let store = store.unwrap();
+    store.update_index().unwrap();
+
+    let options = SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    };
+    let results = store.vector_search_with_embedding(&[1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0], options);
+    let backend = store.qdrant_backend().unwrap();
+    backend.delete_collection().await.unwrap();
+
+    assert!(results.unwrap().is_empty(), "nothing was embedded yet");
+}
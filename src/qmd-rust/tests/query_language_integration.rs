@@ -0,0 +1,129 @@
+//! Query language detection picks the expansion rules and the n-gram fallback
+
+mod common;
+
+use assert_cmd::Command;
+use common::create_multi_collection_config;
+use qmd_rust::store::lang::{LanguageSource, QueryLanguage};
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn all_collections() -> SearchOptions {
+    SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    }
+}
+
+/// Run the CLI against `home` and parse its JSON output
+fn qmd_json(home: &Path, args: &[&str]) -> serde_json::Value {
+    let output = Command::cargo_bin("qmd-rust")
+        .unwrap()
+        .env("HOME", home)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_explain_plans_fallback_only_for_cjk_on_trigram_collections() {
+    let tmp = tempdir().unwrap();
+    let notes = tmp.path().join("notes");
+    let code = tmp.path().join("code");
+    fs::create_dir_all(&notes).unwrap();
+    fs::create_dir_all(&code).unwrap();
+
+    let mut config = create_multi_collection_config(&tmp.path().join("cache"), &[("notes", &notes), ("code", &code)]);
+    config.collections[1].code = true;
+    let store = Store::new(&config).unwrap();
+
+    let english = store.explain_query("borrow checker", Vec::new(), &all_collections(), None).unwrap();
+    assert_eq!(english.language, QueryLanguage::English);
+    assert_eq!(english.language_source, LanguageSource::Detected);
+    assert_eq!(english.expansion_rules, &["english"]);
+    assert!(english.fts_queries.iter().all(|q| !q.fallback));
+
+    let chinese = store.explain_query("所有权规则", Vec::new(), &all_collections(), None).unwrap();
+    assert_eq!(chinese.language, QueryLanguage::Chinese);
+    assert_eq!(chinese.expansion_rules, &["chinese"]);
+    let fallbacks: Vec<(&str, &str)> = chinese
+        .fts_queries
+        .iter()
+        .filter(|q| q.fallback)
+        .map(|q| (q.collection.as_str(), q.query.as_str()))
+        .collect();
+    assert_eq!(fallbacks, vec![("code", "\"所有权\" OR \"有权规\" OR \"权规则\"")]);
+
+    let mixed = store
+        .explain_query("rust 所有权", vec!["rust 所有权".to_string()], &all_collections(), None)
+        .unwrap();
+    assert_eq!(mixed.language, QueryLanguage::Mixed);
+    assert_eq!(mixed.expansion_rules, &["english", "chinese"]);
+    // The original query is not repeated as an expansion
+    assert!(mixed.expansions.is_empty());
+    assert!(mixed.fts_queries.iter().any(|q| q.fallback && q.collection == "code"));
+
+    let overridden = store
+        .explain_query("所有权规则", Vec::new(), &all_collections(), Some(QueryLanguage::English))
+        .unwrap();
+    assert_eq!(overridden.language, QueryLanguage::English);
+    assert_eq!(overridden.language_source, LanguageSource::Override);
+    assert!(overridden.fts_queries.iter().all(|q| !q.fallback));
+}
+
+#[test]
+fn test_cli_search_explain_and_ndjson_language() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("wiki");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("own.md"), "# 所有权\n所有权的规则决定了值何时被释放").unwrap();
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let yaml = format!(
+        "cache_path: {}\ncollections:\n  - name: wiki\n    path: {}\n    pattern: \"**/*.md\"\n    code: true\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), yaml).unwrap();
+    let home = tmp.path();
+    Command::cargo_bin("qmd-rust").unwrap().env("HOME", home).arg("update").assert().success();
+
+    // "所有权规则" is not a substring of the document; only the n-gram fallback finds it
+    let detected = qmd_json(home, &["search", "所有权规则", "--explain", "--format", "json"]);
+    assert_eq!(detected["language"], "zh");
+    assert_eq!(detected["explain"]["language_source"], "detected");
+    assert_eq!(detected["explain"]["expansion_rules"], serde_json::json!(["chinese"]));
+    let fallback = &detected["explain"]["fts_queries"][1];
+    assert_eq!(fallback["fallback"], true);
+    assert_eq!(fallback["collection"], "wiki");
+    assert_eq!(detected["total"], 1, "{}", detected);
+
+    let english = qmd_json(home, &["search", "所有权规则", "--lang", "en", "--explain", "--format", "json"]);
+    assert_eq!(english["language"], "en");
+    assert_eq!(english["explain"]["language_source"], "override");
+    assert_eq!(english["explain"]["fts_queries"].as_array().unwrap().len(), 1);
+    assert_eq!(english["total"], 0);
+
+    let mixed = qmd_json(home, &["search", "释放 memory", "--explain", "--format", "json"]);
+    assert_eq!(mixed["language"], "mixed");
+    assert_eq!(mixed["explain"]["expansion_rules"], serde_json::json!(["english", "chinese"]));
+
+    // NDJSON records the language in its metadata line even without --explain
+    let output = Command::cargo_bin("qmd-rust")
+        .unwrap()
+        .env("HOME", home)
+        .args(["search", "memory", "--format", "ndjson"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let metadata: serde_json::Value = serde_json::from_str(stdout.lines().next().unwrap()).unwrap();
+    assert_eq!(metadata["payload"]["language"], "en", "{}", metadata);
+    assert!(metadata["payload"].get("explain").is_none());
+}
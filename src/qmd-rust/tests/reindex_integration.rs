@@ -0,0 +1,162 @@
+//! `qmd reindex` rebuilding the full-text index from scratch and clearing vectors
+
+mod common;
+
+use assert_cmd::Command;
+use common::{create_multi_collection_config, create_test_config};
+use qmd_rust::anel::{AnelError, AnelErrorCode};
+use qmd_rust::llm::{Embed, EmbedFuture, Router};
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+struct FixedEmbedder;
+
+impl Embed for FixedEmbedder {
+    fn model_name(&self) -> String {
+        "fixed-mock".to_string()
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a> {
+        Box::pin(async move { Ok(texts.iter().map(|_| vec![0.1; 8]).collect()) })
+    }
+}
+
+fn write_notes(content_dir: &Path) {
+    fs::create_dir_all(content_dir).unwrap();
+    fs::write(content_dir.join("walrus.md"), "# Walrus\nThe walrus naps on the ice.").unwrap();
+    fs::write(content_dir.join("narwhal.md"), "# Narwhal\nThe narwhal has a tusk.").unwrap();
+}
+
+fn search(store: &Store, query: &str) -> anyhow::Result<Vec<String>> {
+    let options = SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    };
+    Ok(store.bm25_search(query, options)?.into_iter().map(|r| r.docid).collect())
+}
+
+/// Delete the index segments behind documents_fts, keeping only its
+/// averages (id 1) and structure (id 10) records, so lookups find no data
+fn corrupt_fts(store: &Store) {
+    let conn = store.get_connection("docs").unwrap();
+    conn.execute("DELETE FROM documents_fts_data WHERE id NOT IN (1, 10)", []).unwrap();
+}
+
+#[test]
+fn test_reindex_repairs_corrupted_fts_and_clears_vectors() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_notes(&content_dir);
+    let mut config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    config.vector.dimension = Some(8);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(FixedEmbedder));
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(store.embed_collection("docs", &router, false))
+        .unwrap();
+    assert_eq!(store.get_stats().unwrap().pending_count, 0);
+
+    corrupt_fts(&store);
+    assert!(search(&store, "tusk").map_or(true, |hits| hits.is_empty()));
+
+    let reports = store.reindex(None).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].collection, "docs");
+    assert_eq!(reports[0].documents_indexed, 2);
+    assert_eq!(reports[0].vectors_cleared, 2);
+
+    assert_eq!(search(&store, "tusk").unwrap(), vec!["docs:narwhal.md"]);
+    assert_eq!(search(&store, "walrus").unwrap(), vec!["docs:walrus.md"]);
+    // Writes after the rebuild still reach the index
+    fs::write(content_dir.join("dugong.md"), "# Dugong\nThe dugong grazes on seagrass.").unwrap();
+    store.update_index().unwrap();
+    assert_eq!(search(&store, "seagrass").unwrap(), vec!["docs:dugong.md"]);
+    assert!(store.verify(None, false).unwrap()[0].is_consistent());
+    // Every document waits for `qmd embed` again
+    assert_eq!(store.get_stats().unwrap().pending_count, 3);
+}
+
+#[test]
+fn test_reindex_shared_db_keeps_vectors_of_content_another_collection_uses() {
+    let tmp = tempdir().unwrap();
+    let (zoo, sea) = (tmp.path().join("zoo"), tmp.path().join("sea"));
+    write_notes(&zoo);
+    fs::create_dir_all(&sea).unwrap();
+    // Same file in both collections, so both share its content and vectors
+    fs::copy(zoo.join("walrus.md"), sea.join("walrus.md")).unwrap();
+    let mut config = create_multi_collection_config(&tmp.path().join("cache"), &[("zoo", &zoo), ("sea", &sea)]);
+    config.cache.shared_db = true;
+    config.vector.dimension = Some(8);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(FixedEmbedder));
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    for collection in ["zoo", "sea"] {
+        rt.block_on(store.embed_collection(collection, &router, false)).unwrap();
+    }
+
+    let reports = store.reindex(Some("zoo")).unwrap();
+    assert_eq!(reports[0].documents_indexed, 2);
+    // Only the narwhal is zoo's alone
+    assert_eq!(reports[0].vectors_cleared, 1);
+    assert!(store.pending_embeddings("sea", Some("fixed-mock")).unwrap().is_empty());
+    let narwhal = Store::calculate_hash(&fs::read_to_string(zoo.join("narwhal.md")).unwrap());
+    assert_eq!(store.pending_embeddings("zoo", Some("fixed-mock")).unwrap(), vec![narwhal]);
+
+    // Both collections share one full-text index, rebuilt once for the pair
+    let reports = store.reindex(None).unwrap();
+    assert_eq!(reports.iter().map(|r| r.documents_indexed).collect::<Vec<_>>(), [2, 1]);
+    let mut hits = search(&store, "walrus").unwrap();
+    hits.sort();
+    assert_eq!(hits, vec!["sea:walrus.md", "zoo:walrus.md"]);
+    assert_eq!(search(&store, "tusk").unwrap(), vec!["zoo:narwhal.md"]);
+}
+
+#[test]
+fn test_reindex_unknown_collection_is_not_found() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_notes(&content_dir);
+    let store = Store::new(&create_test_config(&tmp.path().join("cache"), "docs", &content_dir)).unwrap();
+
+    let err = store.reindex(Some("nope")).unwrap_err();
+    assert_eq!(err.downcast_ref::<AnelError>().unwrap().error_code, AnelErrorCode::CollectionNotFound);
+}
+
+#[test]
+fn test_reindex_command_json() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_notes(&content_dir);
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: docs\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| Command::cargo_bin("qmd-rust").unwrap().env("HOME", tmp.path()).args(args).output().unwrap();
+    assert!(qmd(&["update"]).status.success());
+    let store = Store::new(&create_test_config(&tmp.path().join("cache"), "docs", &content_dir)).unwrap();
+    corrupt_fts(&store);
+
+    let output = qmd(&["reindex", "--collection", "docs", "--format", "json"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["collections"][0]["collection"], "docs");
+    assert_eq!(json["collections"][0]["documents_indexed"], 2);
+
+    let output = qmd(&["search", "tusk", "--format", "json"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("narwhal.md"));
+}
@@ -0,0 +1,175 @@
+//! RemoteEmbedder against a mock OpenAI-compatible embeddings endpoint
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::cli::embed::EmbedJob;
+use qmd_rust::config::{HttpConfig, LLMModelConfig};
+use qmd_rust::llm::http::Client;
+use qmd_rust::llm::{RemoteEmbedder, Router};
+use qmd_rust::store::Store;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+use wiremock::matchers::{body_json, header, method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+fn embedder(server: &MockServer) -> RemoteEmbedder {
+    let http = Client::new(&HttpConfig::default()).unwrap();
+    RemoteEmbedder::with_endpoint("text-embedding-3-small", &format!("{}/v1", server.uri()), "sk-test", http)
+}
+
+#[tokio::test]
+async fn test_embed_posts_one_batch_and_orders_vectors_by_index() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .and(header("authorization", "Bearer sk-test"))
+        .and(body_json(serde_json::json!({
+            "model": "text-embedding-3-small",
+            "input": ["walrus", "narwhal"],
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "object": "list",
+            "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.0, 1.0, 0.5]},
+                {"object": "embedding", "index": 0, "embedding": [1.0, 0.0, -0.5]},
+            ],
+            "model": "text-embedding-3-small",
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let embeddings = embedder(&server).embed(&["walrus", "narwhal"]).await.unwrap();
+    assert_eq!(embeddings, [vec![1.0, 0.0, -0.5], vec![0.0, 1.0, 0.5]]);
+}
+
+#[tokio::test]
+async fn test_embed_fails_on_error_status() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(401).set_body_string(r#"{"error":{"message":"Incorrect API key"}}"#))
+        .mount(&server)
+        .await;
+
+    let err = embedder(&server).embed(&["walrus"]).await.unwrap_err();
+    let message = format!("{:#}", err);
+    assert!(message.contains("401"), "{}", message);
+    assert!(message.contains("Incorrect API key"), "{}", message);
+}
+
+#[tokio::test]
+async fn test_embed_fails_when_response_is_short() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "data": [{"index": 0, "embedding": [1.0]}],
+        })))
+        .mount(&server)
+        .await;
+
+    let err = embedder(&server).embed(&["walrus", "narwhal"]).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("expected 2 embeddings, got 1"), "{:#}", err);
+}
+
+#[tokio::test]
+async fn test_embed_retries_after_rate_limit() {
+    let server = MockServer::start().await;
+    Mock::given(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "data": [{"index": 0, "embedding": [1.0]}],
+        })))
+        .mount(&server)
+        .await;
+
+    let embeddings = embedder(&server).embed(&["walrus"]).await.unwrap();
+    assert_eq!(embeddings, [vec![1.0]]);
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
+
+/// How long the mock takes to answer each request
+const RESPONSE_DELAY: Duration = Duration::from_millis(200);
+
+/// Slow embeddings endpoint recording when each request arrived
+#[derive(Clone, Default)]
+struct SlowEmbeddings {
+    arrivals: Arc<Mutex<Vec<Instant>>>,
+}
+
+impl Respond for SlowEmbeddings {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        self.arrivals.lock().unwrap().push(Instant::now());
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        let data: Vec<_> = (0..body["input"].as_array().unwrap().len())
+            .map(|index| serde_json::json!({"index": index, "embedding": [0.6, 0.8]}))
+            .collect();
+        ResponseTemplate::new(200)
+            .set_body_json(serde_json::json!({"data": data}))
+            .set_delay(RESPONSE_DELAY)
+    }
+}
+
+impl SlowEmbeddings {
+    /// Most requests that arrived within one response delay of each other,
+    /// which bounds the requests in flight at once
+    fn max_in_flight(&self) -> usize {
+        let arrivals = self.arrivals.lock().unwrap();
+        arrivals
+            .iter()
+            .map(|start| arrivals.iter().filter(|t| **t >= *start && **t < *start + RESPONSE_DELAY).count())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[tokio::test]
+async fn test_bulk_embed_keeps_at_most_remote_concurrency_requests_in_flight() {
+    let endpoint = SlowEmbeddings::default();
+    let server = MockServer::start().await;
+    Mock::given(path("/v1/embeddings")).respond_with(endpoint.clone()).mount(&server).await;
+    std::env::set_var("OPENAI_API_KEY", "sk-test");
+    std::env::set_var("OPENAI_BASE_URL", format!("{}/v1", server.uri()));
+
+    // One chunk per note, ten chunks per request: six requests
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    for i in 0..60 {
+        fs::write(content_dir.join(format!("note-{}.md", i)), format!("# Note {}\nWalrus fact {}.", i, i)).unwrap();
+    }
+    let mut config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    config.models.embed = Some(LLMModelConfig {
+        local: None,
+        remote: Some("text-embedding-3-small".to_string()),
+    });
+    config.embed.remote_concurrency = Some(2);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    let router = Router::new(&config).unwrap();
+    std::env::remove_var("OPENAI_API_KEY");
+    std::env::remove_var("OPENAI_BASE_URL");
+
+    let started = Instant::now();
+    let chunks = EmbedJob::pending(&store, "docs", router.embedder_model().as_deref(), false).unwrap().run(&router, |_| {}).await.unwrap();
+    let elapsed = started.elapsed();
+
+    assert_eq!(chunks, 60);
+    assert_eq!(endpoint.arrivals.lock().unwrap().len(), 6);
+    assert_eq!(endpoint.max_in_flight(), 2);
+    // Pairs of requests overlap, so three response delays cover the run
+    assert!(elapsed >= RESPONSE_DELAY * 3, "{:?}", elapsed);
+    assert!(elapsed < RESPONSE_DELAY * 6, "{:?}", elapsed);
+
+    let conn = store.get_connection("docs").unwrap();
+    let stored: i64 = conn.query_row("SELECT COUNT(*) FROM content_vectors", [], |row| row.get(0)).unwrap();
+    assert_eq!(stored, 60);
+}
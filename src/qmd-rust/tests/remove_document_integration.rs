@@ -0,0 +1,114 @@
+//! Store::remove_document takes one document out of search, softly or for good
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::anel::{AnelError, AnelErrorCode};
+use qmd_rust::config::Config;
+use qmd_rust::llm::{Embed, EmbedFuture, Router};
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+struct FixedEmbedder;
+
+impl Embed for FixedEmbedder {
+    fn model_name(&self) -> String {
+        "fixed-mock".to_string()
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a> {
+        Box::pin(async move { Ok(texts.iter().map(|_| vec![0.1; 8]).collect()) })
+    }
+}
+
+fn setup(dir: &Path) -> (Config, Store) {
+    let content_dir = dir.join("content");
+    fs::create_dir_all(content_dir.join("copy")).unwrap();
+    let walrus = "# Walrus\nThe walrus naps on the ice.";
+    fs::write(content_dir.join("walrus.md"), walrus).unwrap();
+    fs::write(content_dir.join("copy").join("walrus.md"), walrus).unwrap();
+    fs::write(content_dir.join("narwhal.md"), "# Narwhal\nThe narwhal has a tusk.").unwrap();
+    let config = create_test_config(&dir.join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    (config, store)
+}
+
+fn docids(store: &Store, query: &str) -> Vec<String> {
+    let options = SearchOptions {
+        limit: 10,
+        collection: Some("docs".to_string()),
+        ..Default::default()
+    };
+    let mut ids: Vec<String> = store.bm25_search(query, options).unwrap().into_iter().map(|r| r.docid).collect();
+    ids.sort();
+    ids
+}
+
+fn count(store: &Store, sql: &str) -> i64 {
+    store.get_connection("docs").unwrap().query_row(sql, [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn test_soft_remove_hides_document_from_search() {
+    let tmp = tempdir().unwrap();
+    let (_, store) = setup(tmp.path());
+    assert_eq!(docids(&store, "walrus"), vec!["docs:copy/walrus.md", "docs:walrus.md"]);
+
+    assert!(store.remove_document("docs", "walrus.md", false).unwrap());
+    assert_eq!(docids(&store, "walrus"), vec!["docs:copy/walrus.md"]);
+    assert_eq!(store.get_stats().unwrap().inactive_count, 1);
+    // The row stays for cleanup --purge; removing it again changes nothing
+    assert_eq!(count(&store, "SELECT COUNT(*) FROM documents WHERE path = 'walrus.md' AND active = 0"), 1);
+    assert!(!store.remove_document("docs", "walrus.md", false).unwrap());
+
+    // A later hard remove still deletes the inactive row
+    assert!(store.remove_document("docs", "walrus.md", true).unwrap());
+    assert_eq!(count(&store, "SELECT COUNT(*) FROM documents WHERE path = 'walrus.md'"), 0);
+    assert!(store.verify(None, false).unwrap()[0].is_consistent());
+}
+
+#[test]
+fn test_hard_remove_keeps_vectors_shared_with_another_path() {
+    let tmp = tempdir().unwrap();
+    let (config, store) = setup(tmp.path());
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(FixedEmbedder));
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(store.embed_collection("docs", &router, false))
+        .unwrap();
+    assert_eq!(count(&store, "SELECT COUNT(*) FROM content_vectors"), 2);
+
+    assert!(store.remove_document("docs", "walrus.md", true).unwrap());
+    assert_eq!(docids(&store, "walrus"), vec!["docs:copy/walrus.md"]);
+    // The copy still has its text and vectors
+    assert_eq!(count(&store, "SELECT COUNT(*) FROM content_vectors"), 2);
+    assert_eq!(count(&store, "SELECT COUNT(*) FROM content"), 2);
+    assert_eq!(store.get_stats().unwrap().pending_count, 0);
+
+    assert!(store.remove_document("docs", "copy/walrus.md", true).unwrap());
+    assert!(docids(&store, "walrus").is_empty());
+    assert_eq!(count(&store, "SELECT COUNT(*) FROM content_vectors"), 1);
+    assert_eq!(count(&store, "SELECT COUNT(*) FROM content"), 1);
+    assert_eq!(docids(&store, "narwhal"), vec!["docs:narwhal.md"]);
+    assert!(store.verify(None, false).unwrap()[0].is_consistent());
+}
+
+#[test]
+fn test_remove_unknown_path_is_not_found() {
+    let tmp = tempdir().unwrap();
+    let (_, store) = setup(tmp.path());
+
+    for hard in [false, true] {
+        let err = store.remove_document("docs", "dugong.md", hard).unwrap_err();
+        let anel = err.downcast_ref::<AnelError>().unwrap();
+        assert_eq!(anel.error_code, AnelErrorCode::NotFound);
+    }
+    let err = store.remove_document("nope", "walrus.md", false).unwrap_err();
+    assert_eq!(err.downcast_ref::<AnelError>().unwrap().error_code, AnelErrorCode::CollectionNotFound);
+    assert_eq!(store.get_stats().unwrap().document_count, 3);
+}
@@ -0,0 +1,124 @@
+//! search.rerank_batch_size splits the rerank call without changing the scores
+//! each candidate gets or the order of the results
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::config::Config;
+use qmd_rust::llm::{Rerank, RerankFuture, Router};
+use qmd_rust::store::deadline::{Deadline, SearchOutcome};
+use qmd_rust::store::lang::QueryLanguage;
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+
+fn text_score(doc: &str) -> f32 {
+    (doc.bytes().map(u32::from).sum::<u32>() % 97) as f32 / 97.0
+}
+
+/// Scores documents by their text and records the size of every call
+struct BatchRecorder(Arc<Mutex<Vec<usize>>>);
+
+impl Rerank for BatchRecorder {
+    fn model_name(&self) -> String {
+        "batch-recorder".to_string()
+    }
+
+    fn rerank<'a>(&'a self, _query: &'a str, docs: &'a [&'a str]) -> RerankFuture<'a> {
+        self.0.lock().unwrap().push(docs.len());
+        Box::pin(async move { Ok(docs.iter().map(|doc| text_score(doc)).collect()) })
+    }
+}
+
+/// Drops the last score of every call
+struct ShortReranker;
+
+impl Rerank for ShortReranker {
+    fn model_name(&self) -> String {
+        "short".to_string()
+    }
+
+    fn rerank<'a>(&'a self, _query: &'a str, docs: &'a [&'a str]) -> RerankFuture<'a> {
+        Box::pin(async move { Ok(docs.iter().skip(1).map(|doc| text_score(doc)).collect()) })
+    }
+}
+
+fn setup(dir: &Path) -> Config {
+    let content_dir = dir.join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    for i in 0..30 {
+        let body = format!("# Note {i}\n{}Other facts number {i}.\n", "The walrus naps. ".repeat(i % 7 + 1));
+        fs::write(content_dir.join(format!("note-{i:02}.md")), body).unwrap();
+    }
+    let config = create_test_config(&dir.join("cache"), "docs", &content_dir);
+    Store::new(&config).unwrap().update_index().unwrap();
+    config
+}
+
+async fn query(config: &Config, batch_size: Option<usize>, calls: &Arc<Mutex<Vec<usize>>>) -> SearchOutcome {
+    let mut config = config.clone();
+    config.search.rerank_batch_size = batch_size;
+    let mut router = Router::new(&config).unwrap();
+    router.set_reranker(Arc::new(BatchRecorder(calls.clone())));
+    let options = SearchOptions {
+        limit: 30,
+        search_all: true,
+        ..Default::default()
+    };
+    Store::new(&config)
+        .unwrap()
+        .hybrid_search_within("walrus", options, &router, QueryLanguage::English, &Deadline::unlimited())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_batched_rerank_scores_every_candidate_in_order() {
+    let tmp = tempdir().unwrap();
+    let config = setup(tmp.path());
+
+    let single_calls = Arc::new(Mutex::new(Vec::new()));
+    let single = query(&config, None, &single_calls).await;
+    let candidates = single_calls.lock().unwrap()[0];
+    assert_eq!(single_calls.lock().unwrap().len(), 1);
+    assert!(candidates > 7, "{}", candidates);
+
+    let batched_calls = Arc::new(Mutex::new(Vec::new()));
+    let batched = query(&config, Some(7), &batched_calls).await;
+    let sizes = batched_calls.lock().unwrap().clone();
+    assert_eq!(sizes.len(), candidates.div_ceil(7), "{:?}", sizes);
+    assert!(sizes.iter().all(|&size| size <= 7), "{:?}", sizes);
+    assert_eq!(sizes.iter().sum::<usize>(), candidates);
+
+    // Every candidate carries the score of its own text
+    assert_eq!(batched.results.len(), candidates);
+    for result in &batched.results {
+        assert_eq!(result.score, text_score(&format!("{}\n{}", result.title, result.path)), "{}", result.docid);
+    }
+    assert!(batched.results.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    let ranked = |outcome: &SearchOutcome| {
+        outcome.results.iter().map(|r| (r.docid.clone(), r.score)).collect::<Vec<_>>()
+    };
+    assert_eq!(ranked(&batched), ranked(&single));
+}
+
+#[tokio::test]
+async fn test_router_rejects_misaligned_batch() {
+    let tmp = tempdir().unwrap();
+    let mut config = setup(tmp.path());
+    config.search.rerank_batch_size = Some(4);
+    let mut router = Router::new(&config).unwrap();
+    router.set_reranker(Arc::new(ShortReranker));
+
+    let store = Store::new(&config).unwrap();
+    let options = SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    };
+    let docs = store.bm25_search("walrus", options).unwrap();
+    let err = router.rerank("walrus", &docs).await.unwrap_err();
+    assert!(err.to_string().contains("3 scores for 4 documents"), "{}", err);
+}
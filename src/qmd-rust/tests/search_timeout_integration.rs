@@ -0,0 +1,214 @@
+//! Request timeouts and failing stages return the candidates gathered so far, flagged partial
+
+mod common;
+
+use assert_cmd::Command;
+use common::create_test_config;
+use qmd_rust::llm::{Embed, EmbedFuture, Router};
+use qmd_rust::store::deadline::{Deadline, SearchStage};
+use qmd_rust::store::lang::QueryLanguage;
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+/// Embedder that takes `delay` per call, like a cold model
+struct SlowEmbedder {
+    delay: Duration,
+}
+
+impl Embed for SlowEmbedder {
+    fn model_name(&self) -> String {
+        "slow-mock".to_string()
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a> {
+        Box::pin(async move {
+            tokio::time::sleep(self.delay).await;
+            Ok(texts.iter().map(|_| vec![0.1; 8]).collect())
+        })
+    }
+}
+
+/// Embedder whose every call fails, like an unreachable remote API
+struct FailingEmbedder;
+
+impl Embed for FailingEmbedder {
+    fn model_name(&self) -> String {
+        "failing-mock".to_string()
+    }
+
+    fn embed<'a>(&'a self, _texts: &'a [&'a str]) -> EmbedFuture<'a> {
+        Box::pin(async { Err(anyhow::anyhow!("connection refused")) })
+    }
+}
+
+fn options() -> SearchOptions {
+    SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_hybrid_search_returns_bm25_when_vector_leg_times_out() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("rust.md"), "# Rust\nOwnership and borrowing").unwrap();
+
+    let config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(SlowEmbedder { delay: Duration::from_secs(5) }));
+
+    let start = Instant::now();
+    let deadline = Deadline::new(Some(200));
+    let outcome = store
+        .hybrid_search_within("ownership", options(), &router, QueryLanguage::English, &deadline)
+        .await
+        .unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(2), "took {:?}", start.elapsed());
+    assert!(outcome.is_partial());
+    assert_eq!(outcome.skipped, vec![SearchStage::Vector]);
+    assert_eq!(outcome.results.len(), 1);
+    assert_eq!(outcome.results[0].path, "docs/rust.md");
+}
+
+#[cfg(feature = "sqlite-vec")]
+#[tokio::test]
+async fn test_hybrid_search_within_budget_is_complete() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("rust.md"), "# Rust\nOwnership and borrowing").unwrap();
+
+    let mut config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    // The mock embedder's vectors are 8 wide
+    config.vector.dimension = Some(8);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(SlowEmbedder { delay: Duration::from_millis(10) }));
+
+    let deadline = Deadline::new(Some(10_000));
+    let outcome = store
+        .hybrid_search_within("ownership", options(), &router, QueryLanguage::English, &deadline)
+        .await
+        .unwrap();
+
+    assert!(!outcome.is_partial(), "skipped {:?}", outcome.skipped);
+    assert_eq!(outcome.results.len(), 1);
+}
+
+#[tokio::test]
+async fn test_hybrid_search_degrades_to_bm25_when_embedder_fails() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("rust.md"), "# Rust\nOwnership and borrowing").unwrap();
+
+    let config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(FailingEmbedder));
+
+    let outcome = store
+        .hybrid_search_within("ownership", options(), &router, QueryLanguage::English, &Deadline::unlimited())
+        .await
+        .unwrap();
+
+    assert!(outcome.is_partial());
+    assert!(outcome.skipped.is_empty(), "{:?}", outcome.skipped);
+    assert_eq!(outcome.degraded.len(), 1);
+    assert_eq!(outcome.degraded[0].stage, SearchStage::Vector);
+    assert!(outcome.degraded[0].error.contains("connection refused"), "{:?}", outcome.degraded);
+    assert_eq!(outcome.results.len(), 1);
+    assert_eq!(outcome.results[0].path, "docs/rust.md");
+}
+
+#[test]
+fn test_cli_query_timeout_flags_partial_output() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("notes");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("a.md"), "# A\nBorrowing rules").unwrap();
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let yaml = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), yaml).unwrap();
+    let qmd = || {
+        let mut cmd = Command::cargo_bin("qmd-rust").unwrap();
+        cmd.env("HOME", tmp.path());
+        cmd
+    };
+    qmd().arg("update").assert().success();
+
+    // An exhausted budget skips every optional stage, even without an embedder
+    let output = qmd()
+        .args(["query", "borrowing", "--timeout", "0", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["partial"], true);
+    assert_eq!(json["skipped_stages"], serde_json::json!(["expansion", "vector"]));
+    assert_eq!(json["total"], 1, "{}", json);
+
+    let output = qmd()
+        .args(["vsearch", "borrowing", "--timeout", "0", "--format", "ndjson"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let metadata: serde_json::Value = serde_json::from_str(stdout.lines().next().unwrap()).unwrap();
+    assert_eq!(metadata["payload"]["partial"], true);
+    assert_eq!(metadata["payload"]["skipped_stages"], serde_json::json!(["vector"]));
+}
+
+#[test]
+fn test_cli_query_without_embedder_reports_degraded_vector_stage() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("notes");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("a.md"), "# A\nBorrowing rules").unwrap();
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let yaml = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), yaml).unwrap();
+    let qmd = || {
+        let mut cmd = Command::cargo_bin("qmd-rust").unwrap();
+        cmd.env("HOME", tmp.path());
+        cmd
+    };
+    qmd().arg("update").assert().success();
+
+    let output = qmd().args(["query", "borrowing", "--format", "json"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["partial"], true);
+    assert!(json.get("skipped_stages").is_none(), "{}", json);
+    assert_eq!(json["degraded_stages"][0]["stage"], "vector");
+    assert_eq!(json["degraded_stages"][0]["error"], "No embedder available");
+    assert_eq!(json["total"], 1, "{}", json);
+
+    let output = qmd().args(["query", "borrowing"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Partial results: vector stage failed"));
+}
@@ -0,0 +1,115 @@
+//! Hybrid search reports how long each pipeline stage took
+
+mod common;
+
+use assert_cmd::Command;
+use common::{create_test_config, search_all_options};
+use qmd_rust::llm::{Embed, EmbedFuture, Router};
+use qmd_rust::store::deadline::Deadline;
+use qmd_rust::store::lang::QueryLanguage;
+use qmd_rust::store::Store;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+/// Embedder that takes `delay` per call before answering
+struct DelayedEmbedder {
+    delay: Duration,
+}
+
+impl Embed for DelayedEmbedder {
+    fn model_name(&self) -> String {
+        "delayed-mock".to_string()
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a> {
+        Box::pin(async move {
+            tokio::time::sleep(self.delay).await;
+            Ok(texts.iter().map(|_| vec![0.1; 8]).collect())
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_stage_timings_reflect_embedder_delay() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("rust.md"), "# Rust\nOwnership and borrowing").unwrap();
+
+    let config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(DelayedEmbedder { delay: Duration::from_millis(150) }));
+
+    let outcome = store
+        .hybrid_search_within("ownership", search_all_options(), &router, QueryLanguage::English, &Deadline::unlimited())
+        .await
+        .unwrap();
+    let timings = outcome.timings.expect("hybrid search is timed");
+
+    // The embedder's delay lands in the vector stage and nowhere else
+    assert!((150.0..600.0).contains(&timings.vector_ms), "{:?}", timings);
+    for (stage, ms) in timings.stages() {
+        if stage != "vector" {
+            assert!(ms < 100.0, "{} took {}ms: {:?}", stage, ms, timings);
+        }
+    }
+    let staged: f64 = timings.stages().iter().map(|(_, ms)| ms).sum();
+    assert!(timings.total_ms >= staged - 0.01, "{:?}", timings);
+    assert!(timings.total_ms < staged + 50.0, "{:?}", timings);
+}
+
+fn setup(home: &Path) {
+    let content_dir = home.join("notes");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("a.md"), "# A\nBorrowing rules").unwrap();
+    let config_dir = home.join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let yaml = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n",
+        home.join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), yaml).unwrap();
+}
+
+fn qmd(home: &Path, args: &[&str]) -> String {
+    let output = Command::cargo_bin("qmd-rust")
+        .unwrap()
+        .env("HOME", home)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn test_cli_query_prints_timings_in_json_and_explain_only() {
+    let tmp = tempdir().unwrap();
+    setup(tmp.path());
+    qmd(tmp.path(), &["update"]);
+
+    let json: serde_json::Value =
+        serde_json::from_str(&qmd(tmp.path(), &["query", "borrowing", "--format", "json"])).unwrap();
+    let timings = &json["timings"];
+    for key in ["expansion_ms", "bm25_ms", "vector_ms", "fusion_ms", "rerank_ms", "total_ms"] {
+        assert!(timings[key].is_f64(), "{} missing: {}", key, json);
+    }
+
+    let ndjson = qmd(tmp.path(), &["query", "borrowing", "--format", "ndjson"]);
+    let metadata: serde_json::Value = serde_json::from_str(ndjson.lines().next().unwrap()).unwrap();
+    assert!(metadata["payload"]["timings"]["total_ms"].is_f64(), "{}", metadata);
+
+    // Plain CLI output shows them only with --explain
+    let plain = qmd(tmp.path(), &["query", "borrowing"]);
+    assert!(!plain.contains("Timings:"), "{}", plain);
+    let explained = qmd(tmp.path(), &["query", "borrowing", "--explain"]);
+    assert!(explained.contains("Timings: expansion "), "{}", explained);
+    assert!(explained.contains("(total "), "{}", explained);
+}
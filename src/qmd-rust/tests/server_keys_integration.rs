@@ -0,0 +1,102 @@
+//! `qmd server keys` manages hashed API keys in `server.api_keys_file`
+
+use assert_cmd::Command;
+use qmd_rust::server::keys::KeyFile;
+use qmd_rust::server::middleware::AuthState;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+/// Config pointing at a keys file under `home`
+fn setup(home: &Path) -> PathBuf {
+    let keys_file = home.join("secrets").join("api-keys.yaml");
+    let config_dir = home.join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\nserver:\n  api_keys_file: {}\n",
+        home.join("cache").display(),
+        keys_file.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+    keys_file
+}
+
+fn qmd(home: &Path, args: &[&str]) -> std::process::Output {
+    Command::cargo_bin("qmd-rust").unwrap().env("HOME", home).args(args).output().unwrap()
+}
+
+fn qmd_json(home: &Path, args: &[&str]) -> serde_json::Value {
+    let output = qmd(home, args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+/// Auth state as `qmd server` builds it from the keys file
+fn auth_from(keys_file: &Path) -> AuthState {
+    AuthState::with_keys(KeyFile::load(keys_file).unwrap().api_keys(), vec![])
+}
+
+#[tokio::test]
+async fn test_add_authenticate_and_revoke() {
+    let tmp = tempdir().unwrap();
+    let keys_file = setup(tmp.path());
+
+    let added = qmd_json(tmp.path(), &["server", "keys", "--format", "json", "add", "ci", "--collections", "docs"]);
+    let key = added["key"].as_str().unwrap().to_string();
+    qmd_json(tmp.path(), &["server", "keys", "--format", "json", "add", "ops"]);
+
+    // Only the digest is written
+    let stored = fs::read_to_string(&keys_file).unwrap();
+    assert!(!stored.contains(&key));
+    assert!(stored.contains(&qmd_rust::server::middleware::hash_api_key(&key)));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&keys_file).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    let listed = qmd_json(tmp.path(), &["server", "keys", "--format", "json", "list"]);
+    let names: Vec<&str> = listed["keys"].as_array().unwrap().iter().map(|k| k["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["ci", "ops"]);
+    assert_eq!(listed["keys"][0]["collections"], serde_json::json!(["docs"]));
+    assert!(!listed.to_string().contains(&key));
+
+    let auth = auth_from(&keys_file);
+    assert!(auth.is_allowed(Some(&key), "10.0.0.1").await);
+    assert!(!auth.is_allowed(Some("qmd_not-a-key"), "10.0.0.1").await);
+    assert!(!auth.is_allowed(None, "10.0.0.1").await);
+    assert_eq!(auth.key_name(Some(&key)).await.as_deref(), Some("ci"));
+    assert_eq!(auth.allowed_collections(Some(&key)).await, Some(vec!["docs".to_string()]));
+
+    qmd_json(tmp.path(), &["server", "keys", "--format", "json", "revoke", "ci"]);
+    let auth = auth_from(&keys_file);
+    assert!(!auth.is_allowed(Some(&key), "10.0.0.1").await);
+
+    let output = qmd(tmp.path(), &["server", "keys", "revoke", "ci"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No API key named 'ci'"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_refuses_keys_file_readable_by_others() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = tempdir().unwrap();
+    let keys_file = setup(tmp.path());
+    let output = qmd(tmp.path(), &["server", "keys", "add", "ci"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    fs::set_permissions(&keys_file, fs::Permissions::from_mode(0o644)).unwrap();
+    let err = KeyFile::load(&keys_file).unwrap_err();
+    assert!(err.to_string().contains("accessible by other users"), "{}", err);
+
+    for args in [&["server", "keys", "list"][..], &["server", "--auth", "--port", "0"][..]] {
+        let output = qmd(tmp.path(), args);
+        assert!(!output.status.success(), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("chmod 600"), "{:?}", args);
+    }
+
+    fs::set_permissions(&keys_file, fs::Permissions::from_mode(0o600)).unwrap();
+    assert_eq!(KeyFile::load(&keys_file).unwrap().keys.len(), 1);
+}
@@ -0,0 +1,102 @@
+//! cache.shared_db keeps every collection in one index.db without mixing them up
+
+mod common;
+
+use common::create_multi_collection_config;
+use qmd_rust::config::Config;
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn options(collection: Option<&str>) -> SearchOptions {
+    SearchOptions {
+        limit: 10,
+        collection: collection.map(str::to_string),
+        search_all: collection.is_none(),
+        ..Default::default()
+    }
+}
+
+fn setup(dir: &Path) -> (Config, Store) {
+    let (zoo, sea) = (dir.join("zoo"), dir.join("sea"));
+    fs::create_dir_all(&zoo).unwrap();
+    fs::create_dir_all(&sea).unwrap();
+    fs::write(zoo.join("walrus.md"), "# Walrus\nThe walrus naps in the zoo pool.").unwrap();
+    fs::write(zoo.join("lion.md"), "# Lion\nThe lion sleeps all day.").unwrap();
+    fs::write(sea.join("walrus.md"), "# Walrus\nThe walrus dives under the ice.").unwrap();
+
+    let mut config = create_multi_collection_config(&dir.join("cache"), &[("zoo", &zoo), ("sea", &sea)]);
+    config.cache.shared_db = true;
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    (config, store)
+}
+
+fn docids(store: &Store, query: &str, collection: Option<&str>) -> Vec<String> {
+    let mut ids: Vec<String> = store
+        .bm25_search(query, options(collection))
+        .unwrap()
+        .into_iter()
+        .map(|r| r.docid)
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn test_shared_db_searches_both_collections_from_one_file() {
+    let tmp = tempdir().unwrap();
+    let (config, store) = setup(tmp.path());
+
+    let cache = tmp.path().join("cache");
+    assert_eq!(config.db_path_for("zoo"), cache.join("index.db"));
+    assert_eq!(config.db_path_for("sea"), cache.join("index.db"));
+    assert!(cache.join("index.db").exists());
+    assert!(!cache.join("zoo").join("index.db").exists());
+    assert!(!cache.join("sea").join("index.db").exists());
+
+    // search_all finds each document once, under its own collection
+    assert_eq!(docids(&store, "walrus", None), vec!["sea:walrus.md", "zoo:walrus.md"]);
+    assert_eq!(docids(&store, "lion", None), vec!["zoo:lion.md"]);
+    // A single collection sees only its own rows
+    assert_eq!(docids(&store, "walrus", Some("sea")), vec!["sea:walrus.md"]);
+    assert!(docids(&store, "lion", Some("sea")).is_empty());
+}
+
+#[test]
+fn test_shared_db_keeps_collections_apart() {
+    let tmp = tempdir().unwrap();
+    let (_, store) = setup(tmp.path());
+
+    let stats = store.get_stats().unwrap();
+    assert_eq!(stats.document_count, 3);
+    assert_eq!(stats.collection_stats["zoo"], 2);
+    assert_eq!(stats.collection_stats["sea"], 1);
+    assert_eq!(stats.pending_count, 3);
+    assert_eq!(stats.index_bytes, stats.collection_index_bytes["zoo"]);
+
+    // Each collection's files are checked against its own directory
+    assert!(store.find_stale_entries(0).unwrap().is_empty());
+    store.update_index().unwrap();
+    assert_eq!(store.get_stats().unwrap().document_count, 3);
+    assert_eq!(store.pending_embeddings("sea", None).unwrap().len(), 1);
+
+    store.delete_collection_rows("zoo").unwrap();
+    assert_eq!(docids(&store, "walrus", None), vec!["sea:walrus.md"]);
+}
+
+#[test]
+fn test_default_keeps_one_db_per_collection() {
+    let tmp = tempdir().unwrap();
+    let (mut config, _) = setup(tmp.path());
+    config.cache.shared_db = false;
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let cache = tmp.path().join("cache");
+    assert_eq!(config.db_path_for("zoo"), cache.join("zoo").join("index.db"));
+    assert!(cache.join("zoo").join("index.db").exists());
+    assert!(cache.join("sea").join("index.db").exists());
+    assert_eq!(docids(&store, "walrus", None), vec!["sea:walrus.md", "zoo:walrus.md"]);
+}
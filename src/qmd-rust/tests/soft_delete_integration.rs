@@ -0,0 +1,194 @@
+//! Soft-deleted (inactive) documents must stay out of every read path
+
+mod common;
+
+use assert_cmd::Command;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use common::{create_test_config, search_all_options};
+use qmd_rust::anel::identity::IdentityVerifier;
+use qmd_rust::anel::AnelErrorCode;
+use qmd_rust::config::LLMModelConfig;
+use qmd_rust::llm::Router;
+use qmd_rust::server::handlers::{self, GetDocumentQuery, SearchRequest};
+use qmd_rust::server::middleware::{AuthState, RateLimitState, RouteLimits};
+use qmd_rust::server::observability::{AuditLog, Metrics};
+use qmd_rust::server::ServerState;
+use qmd_rust::store::{SearchResult, Store};
+use std::fs;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+fn paths(results: &[SearchResult]) -> Vec<String> {
+    results.iter().map(|r| r.path.clone()).collect()
+}
+
+async fn body_json(response: impl IntoResponse) -> (StatusCode, serde_json::Value) {
+    let response = response.into_response();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+#[tokio::test]
+async fn test_deactivated_document_absent_from_every_read_path() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("kept.md"), "# Kept\nOwnership rules for borrowing").unwrap();
+    fs::write(content_dir.join("gone.md"), "# Gone\nOwnership moves values").unwrap();
+
+    let mut config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    // The model file does not exist, so the local embedder returns fallback vectors
+    config.models.embed = Some(LLMModelConfig {
+        local: Some("missing-embed-model".to_string()),
+        remote: None,
+    });
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    assert_eq!(paths(&store.bm25_search("ownership", search_all_options()).unwrap()).len(), 2);
+
+    // Soft delete, leaving the file on disk
+    store.remove_stale_entries(&["gone.md".to_string()]).unwrap();
+    assert!(content_dir.join("gone.md").exists());
+
+    // BM25
+    let bm25 = paths(&store.bm25_search("ownership", search_all_options()).unwrap());
+    assert_eq!(bm25, vec!["docs/kept.md".to_string()]);
+
+    // Vector
+    #[cfg(feature = "sqlite-vec")]
+    {
+        let conn = store.get_connection("docs").unwrap();
+        let mut hashes = conn.prepare("SELECT hash FROM documents").unwrap();
+        let hashes: Vec<String> = hashes.query_map([], |row| row.get(0)).unwrap().map(|h| h.unwrap()).collect();
+        let mut embedding = vec![0.0f32; 768];
+        embedding[0] = 1.0;
+        for hash in &hashes {
+            conn.execute(
+                "INSERT INTO content_vectors (hash, seq, pos, model, embedded_at)
+                 VALUES (?, 0, 0, 'test-model', datetime('now'))",
+                [hash],
+            ).unwrap();
+            conn.execute(
+                "INSERT INTO vectors_vec (hash_seq, embedding) VALUES (?, ?)",
+                rusqlite::params![format!("{}_0", hash), serde_json::to_string(&embedding).unwrap()],
+            ).unwrap();
+        }
+        let vector = store.vector_search_with_embedding(&embedding, search_all_options()).unwrap();
+        assert!(vector.iter().all(|r| !r.path.ends_with("gone.md")), "{:?}", paths(&vector));
+        assert_eq!(store.get_stats().unwrap().chunk_count, 1);
+    }
+
+    // Hybrid
+    let router = Router::new(&config).unwrap();
+    let hybrid = store.hybrid_search("ownership", search_all_options(), &router).await.unwrap();
+    assert!(!hybrid.is_empty());
+    assert!(hybrid.iter().all(|r| !r.path.ends_with("gone.md")), "{:?}", paths(&hybrid));
+
+    // Get by index, even though the filesystem fallback could read the file
+    let err = store.retrieve_document("docs/gone.md").unwrap_err();
+    assert_eq!(err.error_code, AnelErrorCode::NotFound);
+    let err = store
+        .retrieve_document(&content_dir.join("gone.md").display().to_string())
+        .unwrap_err();
+    assert_eq!(err.error_code, AnelErrorCode::NotFound);
+    assert!(store.retrieve_document("docs/kept.md").is_ok());
+
+    // Stats
+    let stats = store.get_stats().unwrap();
+    assert_eq!(stats.document_count, 1);
+    assert_eq!(stats.collection_stats["docs"], 1);
+    assert_eq!(stats.inactive_count, 1);
+
+    // HTTP
+    let state = ServerState {
+        store: Arc::new(tokio::sync::Mutex::new(Store::new(&config).unwrap())),
+        llm: Arc::new(tokio::sync::Mutex::new(Router::new(&config).unwrap())),
+        config: config.clone(),
+        rate_limit_state: Arc::new(RateLimitState::new(100, 60)),
+        auth_state: Arc::new(AuthState::new(vec![], vec![])),
+        auth_enabled: false,
+        metrics: Arc::new(Metrics::new()),
+        audit: Arc::new(AuditLog::stderr(false)),
+        identity: Arc::new(IdentityVerifier::default()),
+        ready: Arc::new(std::sync::atomic::AtomicBool::new(true)),
+        limits: RouteLimits::new(&config.server),
+    };
+    let request = || {
+        Json(SearchRequest {
+            query: "ownership".to_string(),
+            limit: None,
+            offset: None,
+            collection: None,
+            timeout_ms: None,
+        })
+    };
+    let (_, body) = body_json(handlers::search(State(state.clone()), HeaderMap::new(), request()).await).await;
+    assert_eq!(body["total"], 1, "{}", body);
+    assert_eq!(body["results"][0]["path"], "docs/kept.md");
+
+    let (_, body) = body_json(handlers::query(State(state.clone()), HeaderMap::new(), request()).await).await;
+    let results = body["results"].as_array().unwrap();
+    assert!(results.iter().all(|r| !r["path"].as_str().unwrap().ends_with("gone.md")), "{}", body);
+
+    let no_range = || Query(GetDocumentQuery { from: None, limit: None, format: None });
+    let response = handlers::get_document(
+        State(state.clone()),
+        HeaderMap::new(),
+        Path("docs/gone.md".to_string()),
+        no_range(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let (_, body) = body_json(handlers::stats(State(state.clone()), HeaderMap::new()).await).await;
+    assert_eq!(body["documents"], 1);
+
+    // CLI ls and status, reading the same index
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let yaml = format!(
+        "cache_path: {}\ncollections:\n  - name: docs\n    path: {}\n    pattern: \"**/*\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), yaml).unwrap();
+    let qmd = |args: &[&str]| -> serde_json::Value {
+        let output = Command::cargo_bin("qmd-rust")
+            .unwrap()
+            .env("HOME", tmp.path())
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    let listed = qmd(&["ls", "docs", "--format", "json"]);
+    let listed: Vec<&str> = listed["files"].as_array().unwrap().iter().map(|f| f["path"].as_str().unwrap()).collect();
+    assert_eq!(listed, vec!["kept.md"]);
+
+    let collections = qmd(&["ls", "--format", "json"]);
+    assert_eq!(collections["collections"][0]["file_count"], 1);
+
+    let status = qmd(&["status", "--format", "json"]);
+    assert_eq!(status["documents"], 1);
+    assert!(status.get("inactive").is_none());
+
+    // The debugging escape hatch shows what was hidden
+    let everything = qmd(&["ls", "docs", "--format", "json", "--include-inactive"]);
+    let gone = everything["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["path"] == "gone.md")
+        .expect("inactive file listed");
+    assert_eq!(gone["inactive"], true);
+
+    let status = qmd(&["status", "--format", "json", "--include-inactive"]);
+    assert_eq!(status["documents"], 1);
+    assert_eq!(status["inactive"], 1);
+}
@@ -0,0 +1,113 @@
+//! `--sort-by` reorders the relevance-selected results by path or recency
+
+mod common;
+
+use assert_cmd::Command;
+use common::create_test_config;
+use qmd_rust::store::order::SortBy;
+use qmd_rust::store::{SearchOptions, SearchResult, Store};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// Three matching documents whose path order differs from their relevance
+/// order, and one that does not match
+fn write_docs(dir: &Path) {
+    fs::create_dir_all(dir.join("guides")).unwrap();
+    fs::write(dir.join("zebra.md"), "# Zebra\nownership ownership ownership ownership").unwrap();
+    fs::write(dir.join("guides/middle.md"), "# Middle\nownership ownership and more text here").unwrap();
+    fs::write(dir.join("alpha.md"), "# Alpha\nA long note that mentions ownership once among many other words").unwrap();
+    fs::write(dir.join("aardvark.md"), "# Aardvark\nNothing relevant").unwrap();
+}
+
+fn paths(results: &[SearchResult]) -> Vec<&str> {
+    results.iter().map(|r| r.path.as_str()).collect()
+}
+
+#[test]
+fn test_sort_results_by_path_and_modified() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_docs(&content_dir);
+    let config = create_test_config(&tmp.path().join("cache"), "notes", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let options = SearchOptions {
+        limit: 10,
+        ..Default::default()
+    };
+    let ranked = store.bm25_search("ownership", options).unwrap();
+    assert_eq!(paths(&ranked), ["notes/zebra.md", "notes/guides/middle.md", "notes/alpha.md"]);
+
+    let mut results = ranked.clone();
+    store.sort_results(&mut results, SortBy::Path, false).unwrap();
+    assert_eq!(paths(&results), ["notes/alpha.md", "notes/guides/middle.md", "notes/zebra.md"]);
+    store.sort_results(&mut results, SortBy::Path, true).unwrap();
+    assert_eq!(paths(&results), ["notes/zebra.md", "notes/guides/middle.md", "notes/alpha.md"]);
+
+    let conn = store.get_connection("notes").unwrap();
+    for (path, modified) in [
+        ("zebra.md", "2024-01-01T00:00:00+00:00"),
+        ("guides/middle.md", "2024-03-01T00:00:00+00:00"),
+        ("alpha.md", "2024-02-01T00:00:00.5+00:00"),
+    ] {
+        conn.execute("UPDATE documents SET modified_at = ? WHERE path = ?", [modified, path]).unwrap();
+    }
+    let mut results = ranked.clone();
+    store.sort_results(&mut results, SortBy::Modified, false).unwrap();
+    assert_eq!(paths(&results), ["notes/guides/middle.md", "notes/alpha.md", "notes/zebra.md"]);
+    store.sort_results(&mut results, SortBy::Modified, true).unwrap();
+    assert_eq!(paths(&results), ["notes/zebra.md", "notes/alpha.md", "notes/guides/middle.md"]);
+
+    let mut results = ranked.clone();
+    store.sort_results(&mut results, SortBy::Score, true).unwrap();
+    assert_eq!(paths(&results), ["notes/alpha.md", "notes/guides/middle.md", "notes/zebra.md"]);
+}
+
+#[test]
+fn test_cli_search_sort_by_path() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_docs(&content_dir);
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let output = Command::cargo_bin("qmd-rust").unwrap().env("HOME", tmp.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    qmd(&["update"]);
+
+    let result_paths = |args: &[&str]| -> Vec<String> {
+        let json: serde_json::Value = serde_json::from_str(&qmd(args)).unwrap();
+        json["results"].as_array().unwrap().iter().map(|r| r["path"].as_str().unwrap().to_string()).collect()
+    };
+
+    // Only matching documents, in lexical path order
+    let sorted = result_paths(&["search", "ownership", "--sort-by", "path", "--format", "json"]);
+    assert_eq!(sorted, ["notes/alpha.md", "notes/guides/middle.md", "notes/zebra.md"]);
+
+    // Relevance picks the top results before they are reordered
+    let top = result_paths(&["search", "ownership", "--sort-by", "path", "--limit", "2", "--format", "json"]);
+    assert_eq!(top, ["notes/guides/middle.md", "notes/zebra.md"]);
+
+    let reversed = result_paths(&["search", "ownership", "--sort-by", "path", "--reverse", "--format", "json"]);
+    assert_eq!(reversed, ["notes/zebra.md", "notes/guides/middle.md", "notes/alpha.md"]);
+
+    let output = Command::cargo_bin("qmd-rust")
+        .unwrap()
+        .env("HOME", tmp.path())
+        .args(["search", "ownership", "--sort-by", "size"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("score, path, modified"));
+}
@@ -0,0 +1,107 @@
+//! Searches reuse pooled connections and reopen them when the database file
+//! is replaced underneath; each database's schema is initialized once
+
+mod common;
+
+use common::{create_test_config, search_all_options};
+use qmd_rust::store::Store;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// Index `content` as collection `docs` under `root`, returning the store
+fn indexed(root: &Path, file: &str, content: &str) -> Store {
+    let content_dir = root.join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join(file), content).unwrap();
+    let config = create_test_config(&root.join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    store
+}
+
+#[test]
+fn test_search_reopens_connection_after_db_file_is_replaced() {
+    let tmp = tempdir().unwrap();
+    let store = indexed(&tmp.path().join("live"), "walrus.md", "# Walrus\nThe walrus naps on ice.");
+    let imported = indexed(&tmp.path().join("import"), "narwhal.md", "# Narwhal\nThe narwhal has a tusk.");
+    drop(imported);
+
+    // Pools the collection's connection
+    assert_eq!(store.bm25_search("walrus", search_all_options()).unwrap().len(), 1);
+
+    // An import swaps in another index file while the connection stays open
+    let live_db = tmp.path().join("live").join("cache").join("docs").join("index.db");
+    let import_db = tmp.path().join("import").join("cache").join("docs").join("index.db");
+    fs::rename(&import_db, &live_db).unwrap();
+
+    let results = store.bm25_search("narwhal", search_all_options()).unwrap();
+    assert_eq!(results.len(), 1, "{:?}", results);
+    assert!(results[0].path.ends_with("narwhal.md"));
+    assert!(store.bm25_search("walrus", search_all_options()).unwrap().is_empty());
+    assert_eq!(store.pool_stats().reopens(), 1);
+}
+
+#[test]
+fn test_search_recovers_when_db_file_is_overwritten_in_place() {
+    let tmp = tempdir().unwrap();
+    let store = indexed(&tmp.path().join("live"), "walrus.md", "# Walrus\nThe walrus naps on ice.");
+    let imported = indexed(&tmp.path().join("import"), "narwhal.md", "# Narwhal\nThe narwhal has a tusk.");
+    drop(imported);
+    assert_eq!(store.bm25_search("walrus", search_all_options()).unwrap().len(), 1);
+
+    // Same file, new contents: the open connection sees a changed database
+    let live_db = tmp.path().join("live").join("cache").join("docs").join("index.db");
+    let import_db = tmp.path().join("import").join("cache").join("docs").join("index.db");
+    fs::write(&live_db, fs::read(&import_db).unwrap()).unwrap();
+
+    let results = store.bm25_search("narwhal", search_all_options()).unwrap();
+    assert_eq!(results.len(), 1, "{:?}", results);
+}
+
+#[test]
+fn test_schema_is_initialized_once_per_database() {
+    let tmp = tempdir().unwrap();
+    let store = indexed(tmp.path(), "walrus.md", "# Walrus\nThe walrus naps on ice.");
+    assert_eq!(store.pool_stats().schema_inits(), 1);
+
+    for _ in 0..5 {
+        store.get_connection("docs").unwrap();
+        assert_eq!(store.bm25_search("walrus", search_all_options()).unwrap().len(), 1);
+    }
+    store.update_index().unwrap();
+    assert_eq!(store.pool_stats().schema_inits(), 1);
+
+    // Threads sharing the store neither block each other nor initialize again
+    let store = std::sync::Arc::new(store);
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let store = store.clone();
+            std::thread::spawn(move || {
+                for _ in 0..10 {
+                    store.get_connection("docs").unwrap();
+                    assert_eq!(store.bm25_search("walrus", search_all_options()).unwrap().len(), 1);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(store.pool_stats().schema_inits(), 1);
+}
+
+#[test]
+fn test_schema_is_initialized_again_for_a_new_database_file() {
+    let tmp = tempdir().unwrap();
+    let store = indexed(tmp.path(), "walrus.md", "# Walrus\nThe walrus naps on ice.");
+    assert_eq!(store.bm25_search("walrus", search_all_options()).unwrap().len(), 1);
+
+    // A deleted index is recreated with a schema on the next open
+    fs::remove_file(tmp.path().join("cache").join("docs").join("index.db")).unwrap();
+    assert!(store.bm25_search("walrus", search_all_options()).unwrap().is_empty());
+    assert_eq!(store.pool_stats().schema_inits(), 2);
+    store.update_index().unwrap();
+    assert_eq!(store.bm25_search("walrus", search_all_options()).unwrap().len(), 1);
+    assert_eq!(store.pool_stats().schema_inits(), 2);
+}
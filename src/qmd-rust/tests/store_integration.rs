@@ -1,10 +1,9 @@
 mod common;
 
 use common::{create_test_config, create_multi_collection_config, init_test_db, insert_test_doc};
-use qmd_rust::store::{Store, SearchOptions};
+use qmd_rust::store::{apply_path_boosts, apply_token_budget, DocumentSize, PathBoost, Store, SearchOptions};
 use qmd_rust::config::{Config, CollectionConfig};
 use std::fs;
-use std::collections::HashMap;
 use tempfile::tempdir;
 
 #[test]
@@ -122,9 +121,8 @@ fn test_bm25_search_multiple_collections() {
 
     let opts = SearchOptions {
         limit: 10,
-        min_score: 0.0,
-        collection: None,
         search_all: true,
+        ..Default::default()
     };
 
     let results = store.bm25_search("programming language", opts).unwrap();
@@ -165,9 +163,8 @@ fn test_bm25_search_single_collection_filter() {
     // Search only col_a
     let opts = SearchOptions {
         limit: 10,
-        min_score: 0.0,
         collection: Some("col_a".to_string()),
-        search_all: false,
+        ..Default::default()
     };
 
     let results = store.bm25_search("programming", opts).unwrap();
@@ -199,10 +196,61 @@ fn test_get_stats_with_documents() {
     assert_eq!(stats.collection_count, 1);
     assert_eq!(stats.document_count, 3);
     assert_eq!(stats.indexed_count, 3);
-    assert_eq!(stats.pending_count, 0);
+    // Nothing has been embedded yet
+    assert_eq!(stats.pending_count, 3);
     assert_eq!(*stats.collection_stats.get("docs").unwrap(), 3);
 }
 
+#[test]
+fn test_get_stats_aggregates_tokens() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("a.md"), "abcdefgh").unwrap();
+    fs::write(content_dir.join("b.md"), "one two three four five").unwrap();
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let stats = store.get_stats().unwrap();
+    // 8 chars -> 2 tokens, 23 chars -> 6 tokens
+    assert_eq!(stats.token_count, 8);
+    assert_eq!(*stats.collection_tokens.get("docs").unwrap(), 8);
+}
+
+#[test]
+fn test_get_stats_reports_index_bytes() {
+    let tmp = tempdir().unwrap();
+    let small = tmp.path().join("content").join("small");
+    let large = tmp.path().join("content").join("large");
+    fs::create_dir_all(&small).unwrap();
+    fs::create_dir_all(&large).unwrap();
+    fs::write(small.join("a.md"), "tiny note").unwrap();
+
+    // Distinct words so the FTS index grows with the content too
+    let mut content_bytes = 0;
+    for i in 0..20 {
+        let body: String = (0..2000).map(|w| format!("word{}x{} ", i, w)).collect();
+        content_bytes += body.len() as u64;
+        fs::write(large.join(format!("doc{}.md", i)), body).unwrap();
+    }
+
+    let cache = tmp.path().join("cache");
+    let config = create_multi_collection_config(&cache, &[("small", &small), ("large", &large)]);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let stats = store.get_stats().unwrap();
+    let small_bytes = stats.collection_index_bytes["small"];
+    let large_bytes = stats.collection_index_bytes["large"];
+    assert!(small_bytes > 0);
+    assert!(large_bytes >= content_bytes, "index holds the content: {} < {}", large_bytes, content_bytes);
+    assert!(large_bytes < content_bytes * 10, "index is not wildly larger than content");
+    assert!(large_bytes > small_bytes);
+    assert_eq!(stats.index_bytes, small_bytes + large_bytes);
+}
+
 #[test]
 fn test_get_stats_multiple_collections() {
     let tmp = tempdir().unwrap();
@@ -413,6 +461,133 @@ fn test_vector_search_aggregates_chunks() {
     assert!(hashes.contains(&"hash_sc"), "Should contain single-chunk document");
 }
 
+#[cfg(not(feature = "sqlite-vec"))]
+#[tokio::test]
+async fn test_vector_search_without_sqlite_vec_is_backend_unavailable() {
+    use qmd_rust::anel::{AnelError, AnelErrorCode};
+    use qmd_rust::llm::{Embed, EmbedFuture, Router};
+    use qmd_rust::store::deadline::{Deadline, SearchStage};
+    use qmd_rust::store::lang::QueryLanguage;
+    use std::sync::Arc;
+
+    struct FixedEmbedder;
+    impl Embed for FixedEmbedder {
+        fn model_name(&self) -> String {
+            "fixed-mock".to_string()
+        }
+
+        fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a> {
+            Box::pin(async move { Ok(texts.iter().map(|_| vec![0.1; 8]).collect()) })
+        }
+    }
+
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("walrus.md"), "# Walrus\nThe walrus naps on the ice.").unwrap();
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    assert!(store.warnings().iter().any(|w| w.code == "VECTOR_BACKEND_UNAVAILABLE"), "{:?}", store.warnings());
+    let options = SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    };
+
+    // An error with a way out, not an empty result set
+    let err = store.vector_search_with_embedding(&[0.1; 8], options.clone()).unwrap_err();
+    let anel = err.downcast_ref::<AnelError>().expect("should be an AnelError");
+    assert_eq!(anel.error_code, AnelErrorCode::BackendUnavailable);
+    assert!(anel.recovery_hints[0].message.contains("--features sqlite-vec"), "{:?}", anel.recovery_hints);
+
+    // Hybrid searches keep their BM25 results and report the vector stage
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(FixedEmbedder));
+    let outcome = store
+        .hybrid_search_within("walrus", options, &router, QueryLanguage::English, &Deadline::unlimited())
+        .await
+        .unwrap();
+    assert_eq!(outcome.results.len(), 1);
+    assert_eq!(outcome.degraded[0].stage, SearchStage::Vector);
+    assert!(outcome.degraded[0].error.contains("sqlite-vec is not available"), "{:?}", outcome.degraded);
+}
+
+#[cfg(feature = "sqlite-vec")]
+#[test]
+fn test_vector_dimension_sizes_table_and_rejects_other_widths() {
+    use qmd_rust::anel::{AnelError, AnelErrorCode};
+
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    let mut config = create_test_config(tmp.path(), "docs", &content_dir);
+    config.vector.dimension = Some(512);
+    let store = Store::new(&config).unwrap();
+
+    let sql: String = store
+        .get_connection("docs")
+        .unwrap()
+        .query_row("SELECT sql FROM sqlite_master WHERE name = 'vectors_vec'", [], |row| row.get(0))
+        .unwrap();
+    assert!(sql.contains("float[512]"), "{}", sql);
+
+    let options = SearchOptions {
+        limit: 10,
+        collection: Some("docs".to_string()),
+        ..Default::default()
+    };
+    assert!(store.vector_search_with_embedding(&[0.1; 512], options.clone()).unwrap().is_empty());
+    // A clear error instead of a SQLite failure
+    let err = store.vector_search_with_embedding(&[0.1; 384], options).unwrap_err();
+    let anel = err.downcast_ref::<AnelError>().expect("should be an AnelError");
+    assert_eq!(anel.error_code, AnelErrorCode::InvalidInput);
+    assert_eq!(anel.message, "Expected a 512-dimensional vector, got 384 dimensions");
+}
+
+#[cfg(feature = "sqlite-vec")]
+#[test]
+fn test_vsearch_path_rejects_embedder_of_other_width() {
+    use qmd_rust::anel::{AnelError, AnelErrorCode};
+    use qmd_rust::llm::{Embed, EmbedFuture, Router};
+    use std::sync::Arc;
+
+    /// Embeds every text as a 384-wide vector
+    struct NarrowEmbedder;
+
+    impl Embed for NarrowEmbedder {
+        fn model_name(&self) -> String {
+            "narrow-mock".to_string()
+        }
+
+        fn embed<'a>(&'a self, texts: &'a [&'a str]) -> EmbedFuture<'a> {
+            Box::pin(async move { Ok(texts.iter().map(|_| vec![0.1; 384]).collect()) })
+        }
+    }
+
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    let mut config = create_test_config(tmp.path(), "docs", &content_dir);
+    config.vector.dimension = Some(512);
+    let store = Store::new(&config).unwrap();
+    let mut router = Router::new(&config).unwrap();
+    router.set_embedder(Arc::new(NarrowEmbedder));
+
+    let options = SearchOptions {
+        limit: 10,
+        collection: Some("docs".to_string()),
+        ..Default::default()
+    };
+    // vsearch and agent embed the query through the router before searching
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let err = rt.block_on(store.vector_search_with_embedder_async("walrus", options, &router)).unwrap_err();
+    let anel = err.downcast_ref::<AnelError>().expect("should be an AnelError");
+    assert_eq!(anel.error_code, AnelErrorCode::InvalidInput);
+    assert_eq!(anel.message, "Expected a 512-dimensional vector, got 384 dimensions");
+}
+
 #[test]
 fn test_get_stats_includes_chunk_count() {
     let tmp = tempdir().unwrap();
@@ -532,7 +707,7 @@ fn test_schema_has_indexes() {
 }
 
 #[test]
-fn test_schema_fts_triggers_exist() {
+fn test_schema_has_no_fts_triggers() {
     let tmp = tempdir().unwrap();
     let content_dir = tmp.path().join("content");
     fs::create_dir_all(&content_dir).unwrap();
@@ -541,16 +716,58 @@ fn test_schema_fts_triggers_exist() {
     let store = Store::new(&config).unwrap();
     let conn = store.get_connection("test_col").unwrap();
 
-    for trigger in &["documents_ai", "documents_ad", "documents_au"] {
-        let count: i64 = conn
-            .query_row(
-                &format!("SELECT COUNT(*) FROM sqlite_master WHERE type='trigger' AND name='{}'", trigger),
-                [],
-                |row| row.get(0),
-            )
-            .unwrap();
-        assert_eq!(count, 1, "Trigger '{}' should exist", trigger);
-    }
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM sqlite_master WHERE type='trigger'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 0, "the store writes documents_fts itself");
+}
+
+#[test]
+fn test_plain_connection_writes_documents_and_verify_fixes_fts() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("walrus.md"), "# Walrus\nThe walrus hauls out on ice.").unwrap();
+
+    let config = create_test_config(tmp.path(), "test_col", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    let db_path = store.get_connection("test_col").unwrap().path().unwrap().to_string();
+
+    // A connection without qmd_doc(), like the sqlite3 shell, can still write documents
+    let plain = rusqlite::Connection::open(&db_path).unwrap();
+    plain.execute(
+        "INSERT INTO content (hash, doc, created_at) VALUES ('hash_manatee', 'The manatee grazes slowly.', datetime('now'))",
+        [],
+    ).unwrap();
+    plain.execute(
+        "INSERT INTO documents (collection, path, title, hash, created_at, modified_at, active)
+         VALUES ('test_col', 'manatee.md', 'Manatee', 'hash_manatee', datetime('now'), datetime('now'), 1)",
+        [],
+    ).unwrap();
+    plain.execute("UPDATE documents SET active = 0 WHERE path = 'walrus.md'", []).unwrap();
+    let err = plain
+        .query_row("SELECT body FROM documents_fts_view", [], |row| row.get::<_, String>(0))
+        .unwrap_err();
+    assert!(err.to_string().contains("qmd_doc"), "{}", err);
+    drop(plain);
+
+    let reports = store.verify(None, false).unwrap();
+    assert_eq!(reports[0].missing_fts, 1);
+    assert_eq!(reports[0].stale_fts, 1);
+    assert!(reports[0].needs_fix());
+
+    store.verify(None, true).unwrap();
+    assert!(store.verify(None, false).unwrap()[0].is_consistent());
+    let opts = SearchOptions {
+        limit: 10,
+        collection: Some("test_col".to_string()),
+        ..Default::default()
+    };
+    let hits = store.bm25_search("manatee", opts.clone()).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].path, "test_col/manatee.md");
+    assert!(store.bm25_search("walrus", opts).unwrap().is_empty());
 }
 
 #[test]
@@ -566,6 +783,7 @@ fn test_get_connection_creates_parent_dir() {
             path: content_dir,
             pattern: None,
             description: None,
+            ..Default::default()
         }],
         cache_path: nested_cache.clone(),
         ..Config::default()
@@ -578,6 +796,54 @@ fn test_get_connection_creates_parent_dir() {
     assert!(nested_cache.join("nested_col").exists());
 }
 
+// ==================== Collection Report Tests ====================
+
+#[test]
+fn test_collection_report_aggregates_fixture_sizes() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(content_dir.join("src")).unwrap();
+    fs::write(content_dir.join("small.md"), "a".repeat(100)).unwrap();
+    fs::write(content_dir.join("big.md"), "b".repeat(300)).unwrap();
+    fs::write(content_dir.join("src").join("lib.rs"), "c".repeat(50)).unwrap();
+    fs::write(content_dir.join("Makefile"), "d".repeat(10)).unwrap();
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let report = store.collection_report("DOCS").unwrap();
+    assert_eq!(report.collection, "docs");
+    assert_eq!(report.document_count, 4);
+    assert_eq!(report.content_bytes, 460);
+    assert_eq!(report.average_document_bytes, 115);
+    assert!(report.index_bytes > 0);
+    assert_eq!(report.chunk_count, 0);
+
+    let extensions: Vec<(&str, usize, u64)> = report
+        .extensions
+        .iter()
+        .map(|e| (e.extension.as_str(), e.documents, e.bytes))
+        .collect();
+    assert_eq!(extensions, vec![("md", 2, 400), ("(none)", 1, 10), ("rs", 1, 50)]);
+
+    let largest: Vec<(&str, u64)> = report
+        .largest_documents
+        .iter()
+        .map(|d| (d.path.as_str(), d.bytes))
+        .collect();
+    assert_eq!(largest, vec![("big.md", 300), ("small.md", 100), ("src/lib.rs", 50), ("Makefile", 10)]);
+
+    let trimmed = report.trimmed(1);
+    assert_eq!(trimmed.extensions.len(), 1);
+    assert_eq!(trimmed.largest_documents[0].path, "big.md");
+    assert_eq!(trimmed.content_bytes, 460);
+
+    let err = store.collection_report("nope").unwrap_err();
+    let err = err.downcast::<qmd_rust::anel::AnelError>().unwrap();
+    assert_eq!(err.error_code, qmd_rust::anel::AnelErrorCode::CollectionNotFound);
+}
+
 // ==================== LLM Cache Tests ====================
 
 #[test]
@@ -591,7 +857,7 @@ fn test_cache_set_and_get() {
 
     store.cache_set("docs", "key1", "gpt-4", "Hello world", None).unwrap();
 
-    let result = store.cache_get("docs", "key1").unwrap();
+    let result = store.cache_get("docs", "key1", "gpt-4").unwrap();
     assert!(result.is_some());
     assert_eq!(result.unwrap(), "Hello world");
 }
@@ -605,7 +871,7 @@ fn test_cache_get_not_found() {
     let config = create_test_config(tmp.path(), "docs", &content_dir);
     let store = Store::new(&config).unwrap();
 
-    let result = store.cache_get("docs", "nonexistent_key").unwrap();
+    let result = store.cache_get("docs", "nonexistent_key", "gpt-4").unwrap();
     assert!(result.is_none());
 }
 
@@ -621,7 +887,7 @@ fn test_cache_update_existing() {
     store.cache_set("docs", "key1", "gpt-4", "First response", None).unwrap();
     store.cache_set("docs", "key1", "gpt-4", "Updated response", None).unwrap();
 
-    let result = store.cache_get("docs", "key1").unwrap();
+    let result = store.cache_get("docs", "key1", "gpt-4").unwrap();
     assert_eq!(result.unwrap(), "Updated response");
 }
 
@@ -640,10 +906,34 @@ fn test_cache_clear_expired() {
     let count = store.cache_clear_expired("docs").unwrap();
     assert!(count >= 1);
 
-    let result = store.cache_get("docs", "expired_key").unwrap();
+    let result = store.cache_get("docs", "expired_key", "gpt-4").unwrap();
     assert!(result.is_none());
 }
 
+#[test]
+fn test_cache_default_ttl_expires_entries() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+
+    let mut config = create_test_config(tmp.path(), "docs", &content_dir);
+    config.cache.ttl_seconds = Some(1);
+    let store = Store::new(&config).unwrap();
+
+    store.cache_set("docs", "short_lived", "gpt-4", "Soon gone", None).unwrap();
+    store.cache_set("docs", "pinned", "gpt-4", "Kept", Some(3600)).unwrap();
+    assert_eq!(store.cache_get("docs", "short_lived", "gpt-4").unwrap().as_deref(), Some("Soon gone"));
+
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+
+    assert!(store.cache_get("docs", "short_lived", "gpt-4").unwrap().is_none());
+    assert_eq!(store.cache_get("docs", "pinned", "gpt-4").unwrap().as_deref(), Some("Kept"));
+
+    // Writing sweeps the expired entry, leaving nothing for an explicit clear
+    store.cache_set("docs", "fresh", "gpt-4", "New", None).unwrap();
+    assert_eq!(store.cache_clear_expired("docs").unwrap(), 0);
+}
+
 #[test]
 fn test_cache_clear_all() {
     let tmp = tempdir().unwrap();
@@ -659,8 +949,8 @@ fn test_cache_clear_all() {
     let count = store.cache_clear_all("docs").unwrap();
     assert_eq!(count, 2);
 
-    assert!(store.cache_get("docs", "key1").unwrap().is_none());
-    assert!(store.cache_get("docs", "key2").unwrap().is_none());
+    assert!(store.cache_get("docs", "key1", "gpt-4").unwrap().is_none());
+    assert!(store.cache_get("docs", "key2", "gpt-4").unwrap().is_none());
 }
 
 #[test]
@@ -678,13 +968,61 @@ fn test_cache_different_collections() {
     store.cache_set("col1", "key1", "gpt-4", "Col1 response", None).unwrap();
     store.cache_set("col2", "key1", "gpt-4", "Col2 response", None).unwrap();
 
-    let r1 = store.cache_get("col1", "key1").unwrap();
-    let r2 = store.cache_get("col2", "key1").unwrap();
+    let r1 = store.cache_get("col1", "key1", "gpt-4").unwrap();
+    let r2 = store.cache_get("col2", "key1", "gpt-4").unwrap();
 
     assert_eq!(r1.unwrap(), "Col1 response");
     assert_eq!(r2.unwrap(), "Col2 response");
 }
 
+#[test]
+fn test_cache_is_scoped_by_model() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+
+    store.cache_set("docs", "expand:rust", "model-a", "A's answer", None).unwrap();
+
+    // Switching models misses instead of serving A's response
+    assert!(store.cache_get("docs", "expand:rust", "model-b").unwrap().is_none());
+
+    store.cache_set("docs", "expand:rust", "model-b", "B's answer", None).unwrap();
+    assert_eq!(store.cache_get("docs", "expand:rust", "model-b").unwrap().as_deref(), Some("B's answer"));
+    assert_eq!(store.cache_get("docs", "expand:rust", "model-a").unwrap().as_deref(), Some("A's answer"));
+}
+
+#[test]
+fn test_unscoped_cache_table_is_recreated() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+
+    let db_path = tmp.path().join("docs").join("index.db");
+    fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+    let conn = rusqlite::Connection::open(&db_path).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE llm_cache (
+            cache_key TEXT PRIMARY KEY,
+            model TEXT NOT NULL,
+            response TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            expires_at TEXT
+        );
+        INSERT INTO llm_cache VALUES ('key1', 'model-a', 'stale', '2024-01-01T00:00:00Z', NULL);",
+    ).unwrap();
+    drop(conn);
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+
+    assert!(store.cache_get("docs", "key1", "model-a").unwrap().is_none());
+    store.cache_set("docs", "key1", "model-b", "fresh", None).unwrap();
+    assert_eq!(store.cache_get("docs", "key1", "model-b").unwrap().as_deref(), Some("fresh"));
+}
+
 // ==================== Stale Entry Tests ====================
 
 #[test]
@@ -693,7 +1031,6 @@ fn test_find_stale_entries_none() {
     let content_dir = tmp.path().join("content");
     fs::create_dir_all(&content_dir).unwrap();
 
-    // Create actual file with absolute path to match how paths are stored in DB
     let doc_path = content_dir.join("doc.md");
     fs::write(&doc_path, "Content").unwrap();
 
@@ -701,14 +1038,9 @@ fn test_find_stale_entries_none() {
     let store = Store::new(&config).unwrap();
     store.update_index().unwrap();
 
-    // find_stale_entries checks relative paths, but we indexed using relative paths
-    // The issue is that paths in DB are relative to the content_dir, not absolute
-    // For this test, we need to verify by checking the path exists relative to the collection path
+    // Stored paths are relative to the collection root, whatever the CWD is
     let stale = store.find_stale_entries(30).unwrap();
-    // Since the files exist relative to their indexed location, this should be empty
-    // Note: The function checks paths as stored (relative), so from CWD they may not exist
-    // This test verifies the basic functionality works
-    assert!(stale.len() <= 1, "Should have minimal stale entries: {:?}", stale);
+    assert!(stale.is_empty(), "Existing files are not stale: {:?}", stale);
 }
 
 #[test]
@@ -728,7 +1060,7 @@ fn test_find_stale_entries_with_deleted_file() {
     fs::remove_file(content_dir.join("doc.md")).unwrap();
 
     let stale = store.find_stale_entries(0).unwrap();
-    assert!(stale.len() >= 1, "Should find at least one stale entry");
+    assert!(!stale.is_empty(), "Should find at least one stale entry");
     assert!(stale[0].contains("doc.md"), "Stale entry should be doc.md");
 }
 
@@ -763,3 +1095,491 @@ fn test_remove_stale_entries_empty_list() {
     // Should not error on empty list
     store.remove_stale_entries(&[]).unwrap();
 }
+
+// ==================== Sync Tests ====================
+
+#[tokio::test]
+async fn test_sync_embeds_only_changed_documents() {
+    use qmd_rust::cli::sync::sync_async;
+    use qmd_rust::config::LLMModelConfig;
+    use qmd_rust::llm::Router;
+
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("a.md"), "Alpha document about rust").unwrap();
+    fs::write(content_dir.join("b.md"), "Beta document about sqlite").unwrap();
+
+    let mut config = create_test_config(tmp.path(), "docs", &content_dir);
+    // The model file does not exist, so the local embedder returns fallback vectors
+    config.models.embed = Some(LLMModelConfig {
+        local: Some("mock-embedder".to_string()),
+        remote: None,
+    });
+    let store = Store::new(&config).unwrap();
+    let llm = Router::new(&config).unwrap();
+
+    let first = sync_async(&store, &llm).await.unwrap();
+    assert_eq!(first.documents_changed, 2);
+    assert_eq!(first.documents_embedded, 2);
+
+    // Mark existing vectors so re-embedded chunks can be told apart
+    let conn = store.get_connection("docs").unwrap();
+    conn.execute("UPDATE content_vectors SET model = 'previous'", []).unwrap();
+
+    fs::write(content_dir.join("a.md"), "Alpha document about rust, revised").unwrap();
+    let second = sync_async(&store, &llm).await.unwrap();
+    assert_eq!(second.documents_changed, 1);
+    assert_eq!(second.documents_embedded, 1);
+    assert!(second.chunks_embedded > 0);
+
+    let hash_of = |path: &str| -> String {
+        conn.query_row(
+            "SELECT hash FROM documents WHERE path = ?",
+            [path],
+            |row| row.get(0),
+        ).unwrap()
+    };
+    let (hash_a, hash_b) = (hash_of("a.md"), hash_of("b.md"));
+
+    let fresh: Vec<String> = conn
+        .prepare("SELECT DISTINCT hash FROM content_vectors WHERE model != 'previous'")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(fresh, vec![hash_a.clone()]);
+
+    let fresh_chunks: usize = conn.query_row(
+        "SELECT COUNT(*) FROM content_vectors WHERE hash = ?",
+        [&hash_a],
+        |row| row.get(0),
+    ).unwrap();
+    assert_eq!(fresh_chunks, second.chunks_embedded);
+
+    let untouched_b: usize = conn.query_row(
+        "SELECT COUNT(*) FROM content_vectors WHERE hash = ? AND model = 'previous'",
+        [&hash_b],
+        |row| row.get(0),
+    ).unwrap();
+    assert!(untouched_b > 0, "unchanged document should keep its vectors");
+}
+
+// ==================== Document Size Tests ====================
+
+#[test]
+fn test_update_index_records_document_sizes() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("short.md"), "Rust sizes are tracked").unwrap();
+    fs::write(content_dir.join("unicode.md"), "héllo wörld rust").unwrap();
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let conn = store.get_connection("docs").unwrap();
+    let size_of = |path: &str| -> (i64, i64, i64) {
+        conn.query_row(
+            "SELECT bytes, words, tokens FROM documents WHERE path = ?",
+            [path],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap()
+    };
+    assert_eq!(size_of("short.md"), (22, 4, 6));
+    // Bytes count UTF-8 encoding, tokens count characters
+    assert_eq!(size_of("unicode.md"), (18, 3, 4));
+
+    let results = store.bm25_search("rust", SearchOptions {
+        limit: 10,
+        ..Default::default()
+    }).unwrap();
+    assert_eq!(results.len(), 2);
+    let short = results.iter().find(|r| r.path.ends_with("short.md")).unwrap();
+    assert_eq!(short.size, Some(DocumentSize { bytes: 22, words: 4, tokens: 6 }));
+
+    // Sizes are flattened into the JSON result
+    let json = serde_json::to_value(short).unwrap();
+    assert_eq!(json["tokens"], 6);
+    assert_eq!(json["words"], 4);
+    assert_eq!(json["bytes"], 22);
+}
+
+#[test]
+fn test_document_sizes_backfilled_for_existing_index() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+
+    // Rows inserted without sizes, as by an older build
+    let db_path = tmp.path().join("docs").join("index.db");
+    let conn = init_test_db(&db_path);
+    insert_test_doc(&conn, "docs", "a.md", "Doc A", "twelve chars", "hash1");
+    drop(conn);
+
+    let store = Store::new(&config).unwrap();
+    let conn = store.get_connection("docs").unwrap();
+    let tokens: i64 = conn
+        .query_row("SELECT tokens FROM documents WHERE path = 'a.md'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(tokens, 3);
+}
+
+#[test]
+fn test_max_tokens_filter_drops_oversized_documents() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("small.md"), "budget note").unwrap();
+    fs::write(content_dir.join("large.md"), format!("budget {}", "word ".repeat(200))).unwrap();
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let results = store.bm25_search("budget", SearchOptions {
+        limit: 10,
+        ..Default::default()
+    }).unwrap();
+    assert_eq!(results.len(), 2);
+
+    assert_eq!(apply_token_budget(results.clone(), None).len(), 2);
+
+    let within = apply_token_budget(results.clone(), Some(50));
+    assert_eq!(within.len(), 1);
+    assert!(within[0].path.ends_with("small.md"));
+
+    // Results without a recorded size are kept
+    let mut unknown = results;
+    for r in &mut unknown {
+        r.size = None;
+    }
+    assert_eq!(apply_token_budget(unknown, Some(1)).len(), 2);
+}
+
+// ==================== Code Mode Tests ====================
+
+fn code_search(store: &Store, query: &str) -> Vec<String> {
+    store
+        .bm25_search(query, SearchOptions {
+            limit: 10,
+            ..Default::default()
+        })
+        .unwrap()
+        .into_iter()
+        .map(|r| r.path)
+        .collect()
+}
+
+#[test]
+fn test_code_collection_matches_identifiers_and_substrings() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("src");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(
+        content_dir.join("store.rs"),
+        "pub fn get_connection(&self, collection: &str) -> Result<Connection> {}",
+    ).unwrap();
+    fs::write(content_dir.join("notes.md"), "Unrelated notes about indexing").unwrap();
+
+    let mut config = create_test_config(tmp.path(), "code", &content_dir);
+    config.collections[0].code = true;
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    // The identifier matches as a unit and as a substring
+    assert_eq!(code_search(&store, "get_connection"), vec!["code/store.rs"]);
+    assert_eq!(code_search(&store, "connection"), vec!["code/store.rs"]);
+    assert_eq!(code_search(&store, "\"_connection(\""), vec!["code/store.rs"]);
+
+    // No stemming: a word that only stems to the same root does not match
+    assert!(code_search(&store, "connections").is_empty());
+}
+
+#[test]
+fn test_switching_to_code_mode_rebuilds_fts_index() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("src");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("lib.rs"), "fn open_connection() {}").unwrap();
+
+    let mut config = create_test_config(tmp.path(), "code", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    // Prose tokenizer: porter stemming matches the plural, not the substring
+    assert_eq!(code_search(&store, "connections"), vec!["code/lib.rs"]);
+    assert!(code_search(&store, "pen_conn").is_empty());
+    drop(store);
+
+    config.collections[0].code = true;
+    let store = Store::new(&config).unwrap();
+    let conn = store.get_connection("code").unwrap();
+    let sql: String = conn
+        .query_row("SELECT sql FROM sqlite_master WHERE name = 'documents_fts'", [], |row| row.get(0))
+        .unwrap();
+    assert!(sql.contains("tokenize='trigram'"), "unexpected FTS schema: {}", sql);
+
+    // Existing documents were re-indexed without running update again
+    assert_eq!(code_search(&store, "pen_conn"), vec!["code/lib.rs"]);
+    assert!(code_search(&store, "connections").is_empty());
+}
+
+// ==================== Content Bundling Tests ====================
+
+#[test]
+fn test_bundle_full_content_respects_budget() {
+    use qmd_rust::store::bundle::{bundle_content, ContentMode};
+
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("a.md"), format!("bundle {}", "alpha ".repeat(20))).unwrap();
+    fs::write(content_dir.join("b.md"), format!("bundle {}", "beta ".repeat(20))).unwrap();
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let results = store.bm25_search("bundle", SearchOptions {
+        limit: 10,
+        ..Default::default()
+    }).unwrap();
+    assert_eq!(results.len(), 2);
+
+    // Unlimited budget returns both documents whole
+    let whole = bundle_content(&store, &results, "bundle", ContentMode::Full, usize::MAX).unwrap();
+    assert!(whole.iter().all(|c| !c.truncated));
+    let first_len = whole[0].pieces[0].text.len();
+
+    // A budget covering the first document and part of the second
+    let budget = first_len + 10;
+    let bundled = bundle_content(&store, &results, "bundle", ContentMode::Full, budget).unwrap();
+    let spent: usize = bundled.iter().flat_map(|c| &c.pieces).map(|p| p.text.len()).sum();
+    assert_eq!(spent, budget);
+    assert!(!bundled[0].truncated, "rank 1 fits entirely");
+    assert!(bundled[1].truncated, "rank 2 is cut to the remaining budget");
+    assert_eq!(bundled[1].pieces[0].text.len(), 10);
+}
+
+#[test]
+fn test_bundle_chunks_includes_matching_chunk() {
+    use qmd_rust::store::bundle::{bundle_content, ContentMode};
+
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+
+    // Long enough to split into several chunks; the term only appears late
+    let filler = "Plain filler sentence without the term. ".repeat(120);
+    let doc = format!("{filler}\n\nThe quetzal nests in cloud forests.\n\n{filler}");
+    fs::write(content_dir.join("birds.md"), &doc).unwrap();
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let results = store.bm25_search("quetzal", SearchOptions {
+        limit: 10,
+        ..Default::default()
+    }).unwrap();
+    assert_eq!(results.len(), 1);
+
+    let bundled = bundle_content(&store, &results, "quetzal", ContentMode::Chunks, usize::MAX).unwrap();
+    let best = &bundled[0].pieces[0];
+    assert!(best.text.contains("quetzal"));
+    assert!(best.seq.unwrap() > 0, "the matching chunk is not the first one");
+    assert_eq!(&doc[best.pos..best.pos + best.text.len()], best.text, "pos is the chunk's offset");
+    assert!(bundled[0].pieces.iter().all(|p| p.text.contains("quetzal")));
+}
+
+// ==================== Soft Delete Tests ====================
+
+#[test]
+fn test_deactivated_document_leaves_and_rejoins_search() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("gone.md"), "The axolotl regrows its limbs").unwrap();
+    fs::write(content_dir.join("kept.md"), "Salamanders are amphibians").unwrap();
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let search = |query: &str| {
+        store.bm25_search(query, SearchOptions {
+            limit: 10,
+            ..Default::default()
+        }).unwrap()
+    };
+    assert_eq!(search("axolotl").len(), 1);
+
+    store.remove_stale_entries(&["gone.md".to_string()]).unwrap();
+    assert!(search("axolotl").is_empty(), "soft-deleted document must not match");
+    assert_eq!(search("salamanders").len(), 1);
+
+    // Re-indexing with the file still present reactivates it
+    store.update_index().unwrap();
+    let results = search("axolotl");
+    assert_eq!(results.len(), 1);
+    assert!(results[0].path.ends_with("gone.md"));
+}
+
+// ==================== Stopword Tests ====================
+
+#[test]
+fn test_stopwords_stripped_and_generic_query_rejected() {
+    use qmd_rust::anel::{AnelError, AnelErrorCode};
+    use qmd_rust::config::Stopwords;
+
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("install.md"), "Install the server with cargo").unwrap();
+    fs::write(content_dir.join("other.md"), "The cat sat on the mat").unwrap();
+
+    let mut config = create_test_config(tmp.path(), "docs", &content_dir);
+    config.collections[0].stopwords = Some(Stopwords::Builtin("english".to_string()));
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let opts = SearchOptions {
+        limit: 10,
+        ..Default::default()
+    };
+
+    // FTS5 ANDs terms, so an unstripped "on" would exclude install.md
+    let results = store.bm25_search("the server on", opts.clone()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0].path.ends_with("install.md"));
+
+    let err = store.bm25_search("the with", opts.clone()).unwrap_err();
+    let anel = err.downcast_ref::<AnelError>().expect("should be an AnelError");
+    assert_eq!(anel.error_code, AnelErrorCode::InvalidInput);
+    assert!(anel.message.contains("only stopwords"), "{}", anel.message);
+
+    // Collections without a stopword list search the query as written
+    config.collections[0].stopwords = None;
+    let store = Store::new(&config).unwrap();
+    assert_eq!(store.bm25_search("the with", opts).unwrap().len(), 1);
+}
+
+// ==================== Collection Name Matching Tests ====================
+
+#[test]
+fn test_collection_name_resolves_case_and_accent_insensitively() {
+    use qmd_rust::anel::{AnelError, AnelErrorCode};
+
+    let tmp = tempdir().unwrap();
+    let docs_dir = tmp.path().join("docs");
+    let notes_dir = tmp.path().join("notes");
+    fs::create_dir_all(&docs_dir).unwrap();
+    fs::create_dir_all(&notes_dir).unwrap();
+    fs::write(docs_dir.join("guide.md"), "Rust ownership guide").unwrap();
+    fs::write(notes_dir.join("todo.md"), "Rust ownership notes").unwrap();
+
+    let config = create_multi_collection_config(
+        &tmp.path().join("cache"),
+        &[("docs", &docs_dir), ("café-notes", &notes_dir)],
+    );
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let search = |collection: &str| {
+        store.bm25_search("ownership", SearchOptions {
+            limit: 10,
+            collection: Some(collection.to_string()),
+            ..Default::default()
+        })
+    };
+
+    let results = search("DOCS").unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].collection, "docs");
+
+    let results = search("Cafe-Notes").unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].collection, "café-notes");
+
+    // A near miss suggests the closest configured name
+    let err = search("dcos").unwrap_err();
+    let anel = err.downcast_ref::<AnelError>().expect("should be an AnelError");
+    assert_eq!(anel.error_code, AnelErrorCode::CollectionNotFound);
+    assert_eq!(anel.recovery_hints[0].code, "DID_YOU_MEAN");
+    assert!(anel.recovery_hints[0].message.contains("docs"));
+}
+
+// ==================== Path Boost Tests ====================
+
+#[test]
+fn test_path_boost_lifts_lower_ranked_result() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(content_dir.join("community")).unwrap();
+    fs::create_dir_all(content_dir.join("official")).unwrap();
+    // The community page mentions the term more often, so it wins on BM25 alone
+    fs::write(content_dir.join("community/tips.md"), "deploy deploy deploy tips").unwrap();
+    fs::write(content_dir.join("official/guide.md"), "deploy guide with many other words about setup and release").unwrap();
+
+    let config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let opts = SearchOptions {
+        limit: 10,
+        collection: Some("docs".to_string()),
+        ..Default::default()
+    };
+    let results = store.bm25_search("deploy", opts).unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].path.ends_with("community/tips.md"));
+
+    // Collection-qualified and collection-relative prefixes both match
+    for prefix in ["docs/official/", "official/"] {
+        let boosts = vec![format!("{}:10", prefix).parse::<PathBoost>().unwrap()];
+        let boosted = apply_path_boosts(results.clone(), &boosts);
+        assert!(boosted[0].path.ends_with("official/guide.md"), "{}", prefix);
+        assert!((boosted[0].score / results[1].score - 10.0).abs() < 1e-4);
+    }
+
+    // Several boosts apply together; a boost that matches nothing changes nothing
+    let boosts: Vec<PathBoost> = ["official/:10", "community/:20", "archive/:100"]
+        .iter()
+        .map(|b| b.parse().unwrap())
+        .collect();
+    let boosted = apply_path_boosts(results.clone(), &boosts);
+    assert!(boosted[0].path.ends_with("community/tips.md"));
+}
+
+// ==================== Title Tests ====================
+
+#[test]
+fn test_update_index_never_stores_empty_title() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("  .md"), "\n## Borrowing Rules\nReferences must not outlive their data").unwrap();
+    fs::write(content_dir.join("..."), "   \n").unwrap();
+
+    let config = create_test_config(tmp.path(), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    let conn = store.get_connection("docs").unwrap();
+    let title_of = |path: &str| -> String {
+        conn.query_row("SELECT title FROM documents WHERE path = ?", [path], |row| row.get(0)).unwrap()
+    };
+    assert_eq!(title_of("  .md"), "Borrowing Rules");
+    assert_eq!(title_of("..."), "...");
+
+    let results = store.bm25_search("references", SearchOptions {
+        limit: 10,
+        ..Default::default()
+    }).unwrap();
+    assert_eq!(results[0].title, "Borrowing Rules");
+}
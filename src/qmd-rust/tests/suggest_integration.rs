@@ -0,0 +1,104 @@
+//! Query suggestions and did-you-mean hints from the vocabulary of titles
+//! and headings
+
+mod common;
+
+use assert_cmd::Command;
+use common::create_test_config;
+use qmd_rust::store::suggest::Suggestion;
+use qmd_rust::store::Store;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_notes(content_dir: &Path) {
+    fs::create_dir_all(content_dir).unwrap();
+    fs::write(content_dir.join("walrus-care.md"), "# Walrus Care\n## Feeding\nFish daily.").unwrap();
+    fs::write(content_dir.join("walrus-habitat.md"), "# Walrus Habitat\nIce floes.").unwrap();
+    fs::write(content_dir.join("warehouse.md"), "# Warehouse Layout\nWalnut crates are stored here.").unwrap();
+}
+
+fn suggestion(term: &str, documents: usize) -> Suggestion {
+    Suggestion { term: term.to_string(), documents }
+}
+
+#[test]
+fn test_suggest_ranks_completions_by_document_count() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_notes(&content_dir);
+    let store = Store::new(&create_test_config(&tmp.path().join("cache"), "docs", &content_dir)).unwrap();
+    store.update_index().unwrap();
+
+    assert_eq!(store.suggest("wa", 10).unwrap(), [suggestion("walrus", 2), suggestion("warehouse", 1)]);
+    assert_eq!(store.suggest("WAL", 10).unwrap(), [suggestion("walrus", 2)]);
+    assert_eq!(store.suggest("wa", 1).unwrap(), [suggestion("walrus", 2)]);
+    // Headings count, body text does not
+    assert_eq!(store.suggest("fe", 10).unwrap(), [suggestion("feeding", 1)]);
+    assert!(store.suggest("fish", 10).unwrap().is_empty());
+
+    // Removing a document updates the counts
+    fs::remove_file(content_dir.join("walrus-habitat.md")).unwrap();
+    let stale = store.find_stale_entries(0).unwrap();
+    store.remove_stale_entries(&stale).unwrap();
+    assert_eq!(store.suggest("wal", 10).unwrap(), [suggestion("walrus", 1)]);
+}
+
+#[test]
+fn test_did_you_mean_corrects_only_words_without_matches() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_notes(&content_dir);
+    let store = Store::new(&create_test_config(&tmp.path().join("cache"), "docs", &content_dir)).unwrap();
+    store.update_index().unwrap();
+
+    assert_eq!(store.did_you_mean("walrsu feeding").unwrap().as_deref(), Some("walrus feeding"));
+    assert_eq!(store.did_you_mean("Habitta").unwrap().as_deref(), Some("habitat"));
+    assert_eq!(store.did_you_mean("walrus").unwrap(), None);
+    // In a body, so not a typo even though it is close to "walrus"
+    assert_eq!(store.did_you_mean("walnut").unwrap(), None);
+    assert_eq!(store.did_you_mean("zebra").unwrap(), None);
+}
+
+#[test]
+fn test_suggest_command_and_search_hint() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_notes(&content_dir);
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: docs\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let output = Command::cargo_bin("qmd-rust").unwrap().env("HOME", tmp.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        output
+    };
+    qmd(&["update"]);
+
+    let output = qmd(&["suggest", "wa", "--format", "json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["prefix"], "wa");
+    assert_eq!(
+        json["suggestions"],
+        serde_json::json!([{"term": "walrus", "documents": 2}, {"term": "warehouse", "documents": 1}])
+    );
+
+    let output = qmd(&["suggest", "wa"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "walrus (2 documents)\nwarehouse (1 document)\n");
+
+    let output = qmd(&["search", "walrsu"]);
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Did you mean: walrus?"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let output = qmd(&["search", "walrus"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Did you mean"));
+}
@@ -0,0 +1,87 @@
+//! Symbolic links in a collection are skipped by default and resolved when followed
+#![cfg(unix)]
+
+mod common;
+
+use common::create_test_config;
+use qmd_rust::store::Store;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+/// A collection linking to a file and a directory outside it
+fn write_tree(root: &Path) -> std::path::PathBuf {
+    let content_dir = root.join("content");
+    let shared = root.join("shared");
+    fs::create_dir_all(content_dir.join("local")).unwrap();
+    fs::create_dir_all(shared.join("deep")).unwrap();
+    fs::write(content_dir.join("local/own.md"), "# Own\nWritten here").unwrap();
+    fs::write(shared.join("glossary.md"), "# Glossary\nTerms about ownership").unwrap();
+    fs::write(shared.join("deep/faq.md"), "# FAQ\nAnswers").unwrap();
+    symlink(shared.join("glossary.md"), content_dir.join("glossary.md")).unwrap();
+    symlink(&shared, content_dir.join("shared")).unwrap();
+    content_dir
+}
+
+fn indexed_paths(store: &Store, collection: &str) -> Vec<String> {
+    let conn = store.get_connection(collection).unwrap();
+    let mut stmt = conn.prepare("SELECT path FROM documents WHERE active = 1 ORDER BY path").unwrap();
+    stmt.query_map([], |row| row.get(0)).unwrap().map(|row| row.unwrap()).collect()
+}
+
+#[test]
+fn test_symlinks_are_skipped_by_default() {
+    let tmp = tempdir().unwrap();
+    let content_dir = write_tree(tmp.path());
+    let config = create_test_config(&tmp.path().join("cache"), "notes", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    assert_eq!(indexed_paths(&store, "notes"), ["local/own.md"]);
+}
+
+#[test]
+fn test_followed_symlinks_index_target_content() {
+    let tmp = tempdir().unwrap();
+    let content_dir = write_tree(tmp.path());
+    // A second link to a file already indexed is not indexed twice
+    symlink(content_dir.join("local/own.md"), content_dir.join("own-alias.md")).unwrap();
+
+    let mut config = create_test_config(&tmp.path().join("cache"), "notes", &content_dir);
+    config.follow_symlinks = true;
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    // Outside targets keep the link's path; the glossary, reachable through
+    // both links, is indexed once
+    assert_eq!(indexed_paths(&store, "notes"), ["glossary.md", "local/own.md", "shared/deep/faq.md"]);
+    let glossary = store.retrieve_document("notes/glossary.md").unwrap();
+    assert_eq!(glossary.content, "# Glossary\nTerms about ownership");
+}
+
+#[test]
+fn test_symlink_cycles_do_not_hang() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(content_dir.join("a/b")).unwrap();
+    fs::write(content_dir.join("a/b/doc.md"), "# Doc").unwrap();
+    symlink(content_dir.join("a"), content_dir.join("a/b/up")).unwrap();
+    symlink(&content_dir, content_dir.join("a/root")).unwrap();
+    symlink(content_dir.join("self.md"), content_dir.join("self.md")).unwrap();
+
+    let mut config = create_test_config(&tmp.path().join("cache"), "notes", &content_dir);
+    config.follow_symlinks = true;
+
+    let (done, finished) = mpsc::channel();
+    std::thread::spawn(move || {
+        let store = Store::new(&config).unwrap();
+        let report = store.update_index_report().unwrap();
+        done.send((report.indexed, report.errors.len(), indexed_paths(&store, "notes"))).unwrap();
+    });
+    let (indexed, errors, paths) = finished.recv_timeout(Duration::from_secs(30)).expect("update hung on a symlink cycle");
+    assert_eq!((indexed, errors), (1, 0));
+    assert_eq!(paths, ["a/b/doc.md"]);
+}
@@ -0,0 +1,168 @@
+//! Purged documents go to the trash and can be restored with their vectors
+
+mod common;
+
+use assert_cmd::Command;
+use common::{create_test_config, search_all_options};
+use qmd_rust::store::trash::list_trash;
+use qmd_rust::store::Store;
+use rusqlite::OptionalExtension;
+use std::fs;
+use tempfile::tempdir;
+
+fn has_vec_table(conn: &rusqlite::Connection) -> bool {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = 'vectors_vec'",
+        [],
+        |row| row.get(0),
+    )
+    .unwrap()
+}
+
+/// Stored embedding for a chunk, if sqlite-vec is loaded
+fn embedding(conn: &rusqlite::Connection, hash_seq: &str) -> Option<Vec<u8>> {
+    if !has_vec_table(conn) {
+        return None;
+    }
+    conn.query_row("SELECT embedding FROM vectors_vec WHERE hash_seq = ?", [hash_seq], |row| row.get(0))
+        .optional()
+        .unwrap()
+}
+
+#[test]
+fn test_purge_and_restore_document_with_vectors() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("kept.md"), "# Kept\nLifetimes and references").unwrap();
+    fs::write(content_dir.join("gone.md"), "# Gone\nOwnership moves values").unwrap();
+
+    let config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+
+    // Embed the document that will be purged
+    let conn = store.get_connection("docs").unwrap();
+    let hash: String = conn
+        .query_row("SELECT hash FROM documents WHERE path = 'gone.md'", [], |row| row.get(0))
+        .unwrap();
+    conn.execute(
+        "INSERT INTO content_vectors (hash, seq, pos, model, embedded_at)
+         VALUES (?, 0, 0, 'test-model', datetime('now'))",
+        [&hash],
+    )
+    .unwrap();
+    let hash_seq = format!("{}_0", hash);
+    if has_vec_table(&conn) {
+        let mut vector = vec![0.0f32; 768];
+        vector[3] = 0.25;
+        conn.execute(
+            "INSERT INTO vectors_vec (hash_seq, embedding) VALUES (?, ?)",
+            [&hash_seq, &serde_json::to_string(&vector).unwrap()],
+        )
+        .unwrap();
+    }
+    let original_embedding = embedding(&conn, &hash_seq);
+
+    // Soft delete, then purge
+    fs::remove_file(content_dir.join("gone.md")).unwrap();
+    let stale = store.find_stale_entries(0).unwrap();
+    assert_eq!(stale, vec!["gone.md".to_string()]);
+    store.remove_stale_entries(&stale).unwrap();
+    let manifest = store.purge_inactive(None).unwrap().expect("something purged");
+    assert_eq!(manifest.documents, 1);
+    assert_eq!(manifest.vectors, 1);
+    assert_eq!(manifest.operation, "cleanup --purge");
+
+    let rows: i64 = conn
+        .query_row("SELECT COUNT(*) FROM documents WHERE path = 'gone.md'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(rows, 0);
+    let vectors: i64 = conn
+        .query_row("SELECT COUNT(*) FROM content_vectors WHERE hash = ?", [&hash], |row| row.get(0))
+        .unwrap();
+    assert_eq!(vectors, 0);
+    assert!(embedding(&conn, &hash_seq).is_none());
+    assert!(store.bm25_search("ownership", search_all_options()).unwrap().is_empty());
+    let listed: Vec<String> = list_trash(&config).unwrap().into_iter().map(|m| m.id).collect();
+    assert_eq!(listed, vec![manifest.id.clone()]);
+
+    // Nothing left to purge
+    assert!(store.purge_inactive(None).unwrap().is_none());
+
+    let summary = store.restore_trash(&manifest.id).unwrap();
+    assert_eq!(summary.restored, 1);
+    assert!(summary.skipped.is_empty());
+
+    let results = store.bm25_search("ownership", search_all_options()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "docs/gone.md");
+    let vectors: i64 = conn
+        .query_row("SELECT COUNT(*) FROM content_vectors WHERE hash = ?", [&hash], |row| row.get(0))
+        .unwrap();
+    assert_eq!(vectors, 1);
+    assert_eq!(embedding(&conn, &hash_seq), original_embedding);
+
+    // Restoring removes the operation from the trash
+    assert!(list_trash(&config).unwrap().is_empty());
+    assert!(store.restore_trash(&manifest.id).is_err());
+}
+
+#[test]
+fn test_trash_expires_after_retention() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("a.md"), "# A\nAlpha").unwrap();
+
+    let mut config = create_test_config(&tmp.path().join("cache"), "docs", &content_dir);
+    let store = Store::new(&config).unwrap();
+    store.update_index().unwrap();
+    store.remove_stale_entries(&["a.md".to_string()]).unwrap();
+    store.purge_inactive(Some("docs")).unwrap().unwrap();
+    assert_eq!(list_trash(&config).unwrap().len(), 1);
+
+    config.trash.retention_days = 0;
+    assert!(list_trash(&config).unwrap().is_empty());
+    assert_eq!(fs::read_dir(config.trash_dir()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_cli_collection_purge_is_restorable() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("notes");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(content_dir.join("a.md"), "# A\nBorrowing rules").unwrap();
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let cache = tmp.path().join("cache");
+    fs::write(config_dir.join("index.yaml"), format!("cache_path: {}\n", cache.display())).unwrap();
+
+    let qmd = |args: &[&str]| {
+        let output = Command::cargo_bin("qmd-rust")
+            .unwrap()
+            .env("HOME", tmp.path())
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        output
+    };
+    qmd(&["collection", "add", "-n", "notes", content_dir.to_str().unwrap()]);
+    qmd(&["update"]);
+    qmd(&["collection", "remove", "notes", "--purge", "--yes"]);
+    assert!(!cache.join("notes").exists());
+
+    let listed: serde_json::Value = serde_json::from_slice(&qmd(&["trash", "--format", "json", "list"]).stdout).unwrap();
+    let op = &listed["operations"][0];
+    assert_eq!(op["operation"], "collection remove --purge");
+    assert_eq!(op["documents"], 1);
+    let id = op["id"].as_str().unwrap().to_string();
+
+    qmd(&["trash", "restore", &id]);
+    let config = fs::read_to_string(config_dir.join("index.yaml")).unwrap();
+    assert!(config.contains("name: notes"), "{}", config);
+    let found: serde_json::Value =
+        serde_json::from_slice(&qmd(&["search", "borrowing", "--format", "json"]).stdout).unwrap();
+    assert_eq!(found["total"], 1, "{}", found);
+}
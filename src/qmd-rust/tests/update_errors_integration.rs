@@ -0,0 +1,144 @@
+//! One unreadable file is reported by `update` instead of aborting the run
+
+mod common;
+
+use assert_cmd::Command;
+use common::create_test_config;
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// Two readable documents and one that is not valid UTF-8, unreadable
+/// with `strict_utf8`
+fn write_docs(dir: &Path) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("rust.md"), "# Rust\nOwnership and borrowing").unwrap();
+    fs::write(dir.join("go.md"), "# Go\nGoroutines and channels").unwrap();
+    fs::write(dir.join("binary.md"), [0xff, 0xfe, 0x00, 0x9f]).unwrap();
+}
+
+#[test]
+fn test_update_reports_unreadable_file_and_indexes_the_rest() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_docs(&content_dir);
+
+    let mut config = create_test_config(&tmp.path().join("cache"), "notes", &content_dir);
+    config.strict_utf8 = true;
+    let store = Store::new(&config).unwrap();
+    let report = store.update_index_report().unwrap();
+
+    assert_eq!(report.indexed, 2);
+    assert_eq!(report.changed.len(), 2);
+    assert_eq!(report.errors.len(), 1, "{:?}", report.errors);
+    let error = &report.errors[0];
+    assert_eq!(error.collection, "notes");
+    assert_eq!(error.path, "binary.md");
+    assert_eq!(error.kind, "invalid_data");
+    assert!(error.message.contains("binary.md"), "{}", error.message);
+    assert_eq!(store.get_stats().unwrap().document_count, 2);
+
+    // The failure is reported again on the next run; the rest is unchanged
+    let again = store.update_index_report().unwrap();
+    assert_eq!((again.indexed, again.unchanged, again.errors.len()), (0, 2, 1));
+}
+
+#[test]
+fn test_update_indexes_invalid_utf8_lossily_by_default() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    // Latin-1 "é" is an invalid byte in UTF-8
+    fs::write(content_dir.join("latin1.md"), b"# Caf\xe9\nOwnership notes from the caf\xe9").unwrap();
+
+    let config = create_test_config(&tmp.path().join("cache"), "notes", &content_dir);
+    let store = Store::new(&config).unwrap();
+    let report = store.update_index_report().unwrap();
+    assert_eq!((report.indexed, report.errors.len()), (1, 0), "{:?}", report.errors);
+
+    let doc = store.retrieve_document("notes/latin1.md").unwrap();
+    assert_eq!(doc.content, "# Caf\u{fffd}\nOwnership notes from the caf\u{fffd}");
+    let results = store.bm25_search("ownership", SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    }).unwrap();
+    assert_eq!(results.len(), 1);
+
+    // Unchanged bytes convert to the same text, so nothing is re-indexed
+    let again = store.update_index_report().unwrap();
+    assert_eq!((again.indexed, again.unchanged), (0, 1));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_update_reports_permission_denied() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_docs(&content_dir);
+    fs::remove_file(content_dir.join("binary.md")).unwrap();
+    let secret = content_dir.join("secret.md");
+    fs::write(&secret, "# Secret").unwrap();
+    fs::set_permissions(&secret, fs::Permissions::from_mode(0o000)).unwrap();
+    if fs::read(&secret).is_ok() {
+        // Running as root, permissions are not enforced
+        return;
+    }
+
+    let config = create_test_config(&tmp.path().join("cache"), "notes", &content_dir);
+    let report = Store::new(&config).unwrap().update_index_report().unwrap();
+    assert_eq!(report.indexed, 2);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].path, "secret.md");
+    assert_eq!(report.errors[0].kind, "permission_denied");
+}
+
+#[test]
+fn test_update_missing_collection_path_aborts() {
+    let tmp = tempdir().unwrap();
+    let config = create_test_config(&tmp.path().join("cache"), "notes", &tmp.path().join("missing"));
+    let error = Store::new(&config).unwrap().update_index_report().unwrap_err();
+    assert!(error.to_string().contains("does not exist"), "{}", error);
+}
+
+#[test]
+fn test_update_command_exit_codes_and_json_errors() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_docs(&content_dir);
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\nstrict_utf8: true\ncollections:\n  - name: notes\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| Command::cargo_bin("qmd-rust").unwrap().env("HOME", tmp.path()).args(args).output().unwrap();
+
+    let output = qmd(&["update", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(3), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["documents_indexed"], 2);
+    assert_eq!(report["errors"][0]["path"], "binary.md");
+    assert_eq!(report["errors"][0]["kind"], "invalid_data");
+
+    let output = qmd(&["update"]);
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Index updated with 1 errors"), "{}", stdout);
+    assert!(stdout.contains("notes/binary.md (invalid_data)"), "{}", stdout);
+
+    let output = qmd(&["update", "--strict"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--strict"));
+
+    fs::remove_file(content_dir.join("binary.md")).unwrap();
+    let output = qmd(&["update", "--strict"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
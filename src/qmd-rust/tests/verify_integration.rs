@@ -0,0 +1,136 @@
+//! `qmd verify` finding and repairing tables out of step with the documents
+
+mod common;
+
+use assert_cmd::Command;
+use common::create_test_config;
+use qmd_rust::store::{SearchOptions, Store};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_notes(content_dir: &Path) {
+    fs::create_dir_all(content_dir).unwrap();
+    fs::write(content_dir.join("walrus.md"), "# Walrus\nThe walrus naps on the ice.").unwrap();
+    fs::write(content_dir.join("narwhal.md"), "# Narwhal\nThe narwhal has a tusk.").unwrap();
+}
+
+fn options() -> SearchOptions {
+    SearchOptions {
+        limit: 10,
+        search_all: true,
+        ..Default::default()
+    }
+}
+
+/// Drop a document's full-text row without touching the document itself
+fn drop_fts_row(store: &Store, path: &str) {
+    let conn = store.get_connection("docs").unwrap();
+    conn.execute(
+        "INSERT INTO documents_fts(documents_fts, rowid, filepath, title, body)
+         SELECT 'delete', id, filepath, title, body FROM documents_fts_view WHERE filepath = ?",
+        [path],
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_verify_fix_repairs_index_and_search_finds_document_again() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_notes(&content_dir);
+    let store = Store::new(&create_test_config(&tmp.path().join("cache"), "docs", &content_dir)).unwrap();
+    store.update_index().unwrap();
+
+    let reports = store.verify(None, false).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].is_consistent(), "{:?}", reports[0]);
+
+    drop_fts_row(&store, "docs/walrus.md");
+    {
+        let conn = store.get_connection("docs").unwrap();
+        conn.execute(
+            "INSERT INTO content_vectors (hash, seq, pos, model, embedded_at) VALUES ('gone', 0, 0, 'mock', datetime('now'))",
+            [],
+        )
+        .unwrap();
+        conn.execute("UPDATE vocabulary SET documents = 7 WHERE term = 'walrus'", []).unwrap();
+        conn.execute("UPDATE documents SET words = 0 WHERE path = 'narwhal.md'", []).unwrap();
+    }
+    assert!(store.bm25_search("walrus", options()).unwrap().is_empty());
+
+    // Without --fix nothing changes
+    for _ in 0..2 {
+        let report = &store.verify(Some("docs"), false).unwrap()[0];
+        assert_eq!(report.missing_fts, 1);
+        assert_eq!(report.stale_fts, 0);
+        assert_eq!(report.orphan_vectors, 1);
+        assert_eq!(report.vocabulary_drift, 1);
+        assert_eq!(report.wrong_sizes, 1);
+        assert!(report.fixed.is_empty());
+        assert!(report.needs_fix());
+    }
+
+    let report = &store.verify(None, true).unwrap()[0];
+    assert_eq!(report.missing_fts, 1);
+    assert_eq!(
+        report.fixed,
+        [
+            "rebuilt full-text index (1 missing, 0 stale rows)",
+            "deleted 1 orphan vectors",
+            "rebuilt vocabulary (1 terms corrected)",
+            "recounted sizes of 1 documents",
+        ]
+    );
+    assert!(!report.needs_fix());
+
+    let report = &store.verify(None, false).unwrap()[0];
+    assert!(report.is_consistent(), "{:?}", report);
+    let results = store.bm25_search("walrus", options()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "docs/walrus.md");
+    assert_eq!(store.suggest("walrus", 1).unwrap()[0].documents, 1);
+}
+
+#[test]
+fn test_verify_command_exit_codes_and_json() {
+    let tmp = tempdir().unwrap();
+    let content_dir = tmp.path().join("content");
+    write_notes(&content_dir);
+
+    let config_dir = tmp.path().join(".config").join("qmd");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config = format!(
+        "cache_path: {}\ncollections:\n  - name: docs\n    path: {}\n    pattern: \"**/*.md\"\n",
+        tmp.path().join("cache").display(),
+        content_dir.display()
+    );
+    fs::write(config_dir.join("index.yaml"), config).unwrap();
+
+    let qmd = |args: &[&str]| Command::cargo_bin("qmd-rust").unwrap().env("HOME", tmp.path()).args(args).output().unwrap();
+    assert!(qmd(&["update"]).status.success());
+
+    let output = qmd(&["verify"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "docs: ok\n");
+
+    let store = Store::new(&create_test_config(&tmp.path().join("cache"), "docs", &content_dir)).unwrap();
+    drop_fts_row(&store, "docs/narwhal.md");
+
+    let output = qmd(&["verify"]);
+    assert_eq!(output.status.code(), Some(4));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1 documents missing from the full-text index"), "{}", stdout);
+    assert!(stdout.contains("qmd verify --fix"), "{}", stdout);
+
+    let output = qmd(&["verify", "--fix", "--format", "json"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["consistent"], false);
+    assert_eq!(json["collections"][0]["missing_fts"], 1);
+    assert_eq!(json["collections"][0]["fixed"][0], "rebuilt full-text index (1 missing, 0 stale rows)");
+
+    assert!(qmd(&["verify"]).status.success());
+    let output = qmd(&["search", "tusk", "--format", "json"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("narwhal.md"));
+}